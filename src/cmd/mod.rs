@@ -1,7 +0,0 @@
-pub mod login;
-pub mod new;
-pub mod push;
-pub mod pull;
-pub mod prototype;
-pub mod explain;
-
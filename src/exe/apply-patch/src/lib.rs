@@ -52,6 +52,13 @@ pub enum ApplyPatchError {
     /// Error that occurs while computing replacements when applying patch chunks
     #[error("{0}")]
     ComputeReplacements(String),
+    /// A chunk of an `Update File` hunk whose `@@` context or lines are not in the file
+    #[error("{message}")]
+    ChunkNotFound {
+        /// 1-based position of the chunk among the hunk's chunks
+        chunk: usize,
+        message: String,
+    },
     /// A raw patch body was provided without an explicit `apply_patch` invocation.
     #[error(
         "patch detected without explicit call to apply_patch. Rerun as [\"apply_patch\", \"<patch>\"]"
@@ -518,6 +525,19 @@ pub fn apply_hunks(
     hunks: &[Hunk],
     stdout: &mut impl std::io::Write,
     stderr: &mut impl std::io::Write,
+) -> Result<(), ApplyPatchError> {
+    apply_hunks_with_fuzz(hunks, 0, stdout, stderr)
+}
+
+/// Like [`apply_hunks`], but a chunk whose context no longer matches may still apply with up
+/// to `max_fuzz` of its leading and trailing context lines ignored. Every chunk that needed
+/// fuzz or whitespace-insensitive matching is listed on stdout, so the caller (and the model
+/// that wrote the patch) can check the result.
+pub fn apply_hunks_with_fuzz(
+    hunks: &[Hunk],
+    max_fuzz: usize,
+    stdout: &mut impl std::io::Write,
+    stderr: &mut impl std::io::Write,
 ) -> Result<(), ApplyPatchError> {
     // Optional unified diff for the entire invocation ("turn")
     let enable_turn_diff = std::env::var("QERNEL_TURN_DIFF").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false);
@@ -594,9 +614,10 @@ pub fn apply_hunks(
     }
 
     // Delegate to a helper that applies each hunk to the filesystem.
-    match apply_hunks_to_files(hunks) {
+    match apply_hunks_to_files(hunks, max_fuzz) {
         Ok(affected) => {
             print_summary(&affected, stdout).map_err(ApplyPatchError::from)?;
+            print_fuzz(&affected.fuzz, stdout).map_err(ApplyPatchError::from)?;
             if let Some(t) = tracker.as_mut() {
                 if let Ok(Some(_diff)) = t.get_unified_diff() {
                     // Diff will be shown via TurnDiff event, no direct output needed
@@ -627,6 +648,9 @@ pub fn apply_hunks(
             }
             if let Some(io) = err.downcast_ref::<std::io::Error>() {
                 Err(ApplyPatchError::from(io))
+            } else if err.is::<ApplyPatchError>() {
+                // Keep it structured, e.g. which chunk was not found
+                Err(err.downcast::<ApplyPatchError>().expect("checked above"))
             } else {
                 Err(ApplyPatchError::IoError(IoError {
                     context: msg,
@@ -644,11 +668,45 @@ pub struct AffectedPaths {
     pub added: Vec<PathBuf>,
     pub modified: Vec<PathBuf>,
     pub deleted: Vec<PathBuf>,
+    /// Chunks that only applied with relaxed matching
+    pub fuzz: Vec<Fuzz>,
+}
+
+/// How far an update chunk's context had to be relaxed before it applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fuzz {
+    pub path: PathBuf,
+    /// 1-based position of the chunk among the file's chunks
+    pub chunk: usize,
+    /// Context lines at the start of the chunk that were not required to match
+    pub leading: usize,
+    /// Context lines at the end of the chunk that were not required to match
+    pub trailing: usize,
+    /// Lines matched only once leading and trailing whitespace (or typographic
+    /// punctuation) was ignored
+    pub whitespace: bool,
+}
+
+impl std::fmt::Display for Fuzz {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: chunk {} applied", self.path.display(), self.chunk)?;
+        let mut relaxed = Vec::new();
+        if self.leading > 0 || self.trailing > 0 {
+            relaxed.push(format!(
+                "with {} leading and {} trailing context line(s) ignored",
+                self.leading, self.trailing
+            ));
+        }
+        if self.whitespace {
+            relaxed.push("ignoring whitespace differences".to_string());
+        }
+        write!(f, " {}", relaxed.join(" and "))
+    }
 }
 
 /// Apply the hunks to the filesystem, returning which files were added, modified, or deleted.
 /// Returns an error if the patch could not be applied.
-fn apply_hunks_to_files(hunks: &[Hunk]) -> anyhow::Result<AffectedPaths> {
+fn apply_hunks_to_files(hunks: &[Hunk], max_fuzz: usize) -> anyhow::Result<AffectedPaths> {
     if hunks.is_empty() {
         anyhow::bail!("No files were modified.");
     }
@@ -656,6 +714,7 @@ fn apply_hunks_to_files(hunks: &[Hunk]) -> anyhow::Result<AffectedPaths> {
     let mut added: Vec<PathBuf> = Vec::new();
     let mut modified: Vec<PathBuf> = Vec::new();
     let mut deleted: Vec<PathBuf> = Vec::new();
+    let mut fuzz: Vec<Fuzz> = Vec::new();
     for hunk in hunks {
         match hunk {
             Hunk::AddFile { path, contents } => {
@@ -680,8 +739,12 @@ fn apply_hunks_to_files(hunks: &[Hunk]) -> anyhow::Result<AffectedPaths> {
                 move_path,
                 chunks,
             } => {
-                let AppliedPatch { new_contents, .. } =
-                    derive_new_contents_from_chunks(path, chunks)?;
+                let AppliedPatch {
+                    new_contents,
+                    fuzz: chunk_fuzz,
+                    ..
+                } = derive_new_contents_from_chunks(path, chunks, max_fuzz)?;
+                fuzz.extend(chunk_fuzz);
                 if let Some(dest) = move_path {
                     if let Some(parent) = dest.parent() {
                         if !parent.as_os_str().is_empty() {
@@ -707,12 +770,14 @@ fn apply_hunks_to_files(hunks: &[Hunk]) -> anyhow::Result<AffectedPaths> {
         added,
         modified,
         deleted,
+        fuzz,
     })
 }
 
 struct AppliedPatch {
     original_contents: String,
     new_contents: String,
+    fuzz: Vec<Fuzz>,
 }
 
 /// Return *only* the new file contents (joined into a single `String`) after
@@ -720,6 +785,7 @@ struct AppliedPatch {
 fn derive_new_contents_from_chunks(
     path: &Path,
     chunks: &[UpdateFileChunk],
+    max_fuzz: usize,
 ) -> std::result::Result<AppliedPatch, ApplyPatchError> {
     let original_contents = match std::fs::read_to_string(path) {
         Ok(contents) => contents,
@@ -739,7 +805,7 @@ fn derive_new_contents_from_chunks(
         original_lines.pop();
     }
 
-    let replacements = compute_replacements(&original_lines, path, chunks)?;
+    let (replacements, fuzz) = compute_replacements(&original_lines, path, chunks, max_fuzz)?;
     let new_lines = apply_replacements(original_lines, &replacements);
     let mut new_lines = new_lines;
     if !new_lines.last().is_some_and(String::is_empty) {
@@ -749,21 +815,29 @@ fn derive_new_contents_from_chunks(
     Ok(AppliedPatch {
         original_contents,
         new_contents,
+        fuzz,
     })
 }
 
+/// `(start_index, old_len, new_lines)`: lines of the original file to swap for new ones
+type Replacement = (usize, usize, Vec<String>);
+
 /// Compute a list of replacements needed to transform `original_lines` into the
 /// new lines, given the patch `chunks`. Each replacement is returned as
-/// `(start_index, old_len, new_lines)`.
+/// `(start_index, old_len, new_lines)`, along with the chunks that needed relaxed
+/// matching. With `max_fuzz` > 0, a chunk whose lines are not found is retried with up to
+/// that many context lines dropped from each end, as `patch --fuzz` does.
 fn compute_replacements(
     original_lines: &[String],
     path: &Path,
     chunks: &[UpdateFileChunk],
-) -> std::result::Result<Vec<(usize, usize, Vec<String>)>, ApplyPatchError> {
-    let mut replacements: Vec<(usize, usize, Vec<String>)> = Vec::new();
+    max_fuzz: usize,
+) -> std::result::Result<(Vec<Replacement>, Vec<Fuzz>), ApplyPatchError> {
+    let mut replacements: Vec<Replacement> = Vec::new();
+    let mut fuzz: Vec<Fuzz> = Vec::new();
     let mut line_index: usize = 0;
 
-    for chunk in chunks {
+    for (chunk_idx, chunk) in chunks.iter().enumerate() {
         // If a chunk has a `change_context`, we use seek_sequence to find it, then
         // adjust our `line_index` to continue from there.
         if let Some(ctx_line) = &chunk.change_context {
@@ -775,11 +849,10 @@ fn compute_replacements(
             ) {
                 line_index = idx + 1;
             } else {
-                return Err(ApplyPatchError::ComputeReplacements(format!(
-                    "Failed to find context '{}' in {}",
-                    ctx_line,
-                    path.display()
-                )));
+                return Err(ApplyPatchError::ChunkNotFound {
+                    chunk: chunk_idx + 1,
+                    message: format!("Failed to find context '{}' in {}", ctx_line, path.display()),
+                });
             }
         }
 
@@ -807,8 +880,12 @@ fn compute_replacements(
         // located reliably.
 
         let mut pattern: &[String] = &chunk.old_lines;
-        let mut found =
-            seek_sequence::seek_sequence(original_lines, pattern, line_index, chunk.is_end_of_file);
+        let mut found = seek_sequence::seek_sequence_with_leniency(
+            original_lines,
+            pattern,
+            line_index,
+            chunk.is_end_of_file,
+        );
 
         let mut new_slice: &[String] = &chunk.new_lines;
 
@@ -820,7 +897,7 @@ fn compute_replacements(
                 new_slice = &new_slice[..new_slice.len() - 1];
             }
 
-            found = seek_sequence::seek_sequence(
+            found = seek_sequence::seek_sequence_with_leniency(
                 original_lines,
                 pattern,
                 line_index,
@@ -828,21 +905,73 @@ fn compute_replacements(
             );
         }
 
-        if let Some(start_idx) = found {
+        // Still not found: drop context lines from the ends of the chunk, one more on
+        // each side per fuzz level, while some of the chunk is left to anchor it.
+        let (mut leading, mut trailing) = (0, 0);
+        if found.is_none() && max_fuzz > 0 {
+            let context_before = pattern
+                .iter()
+                .zip(new_slice)
+                .take_while(|(old, new)| old == new)
+                .count();
+            let context_after = pattern
+                .iter()
+                .rev()
+                .zip(new_slice.iter().rev())
+                .take_while(|(old, new)| old == new)
+                .count()
+                .min(pattern.len().min(new_slice.len()) - context_before);
+            for level in 1..=max_fuzz {
+                let (lead, trail) = (level.min(context_before), level.min(context_after));
+                if lead + trail >= pattern.len() {
+                    break;
+                }
+                if let Some(hit) = seek_sequence::seek_sequence_with_leniency(
+                    original_lines,
+                    &pattern[lead..pattern.len() - trail],
+                    line_index,
+                    chunk.is_end_of_file && trail == 0,
+                ) {
+                    found = Some(hit);
+                    (leading, trailing) = (lead, trail);
+                    pattern = &pattern[lead..pattern.len() - trail];
+                    new_slice = &new_slice[lead..new_slice.len() - trail];
+                    break;
+                }
+                if lead == context_before && trail == context_after {
+                    break;
+                }
+            }
+        }
+
+        if let Some((start_idx, leniency)) = found {
+            let whitespace = leniency > seek_sequence::Leniency::TrailingWhitespace;
+            if leading > 0 || trailing > 0 || whitespace {
+                fuzz.push(Fuzz {
+                    path: path.to_path_buf(),
+                    chunk: chunk_idx + 1,
+                    leading,
+                    trailing,
+                    whitespace,
+                });
+            }
             replacements.push((start_idx, pattern.len(), new_slice.to_vec()));
             line_index = start_idx + pattern.len();
         } else {
-            return Err(ApplyPatchError::ComputeReplacements(format!(
-                "Failed to find expected lines in {}:\n{}",
-                path.display(),
-                chunk.old_lines.join("\n"),
-            )));
+            return Err(ApplyPatchError::ChunkNotFound {
+                chunk: chunk_idx + 1,
+                message: format!(
+                    "Failed to find expected lines in {}:\n{}",
+                    path.display(),
+                    chunk.old_lines.join("\n"),
+                ),
+            });
         }
     }
 
     replacements.sort_by(|(lhs_idx, _, _), (rhs_idx, _, _)| lhs_idx.cmp(rhs_idx));
 
-    Ok(replacements)
+    Ok((replacements, fuzz))
 }
 
 /// Apply the `(start_index, old_len, new_lines)` replacements to `original_lines`,
@@ -895,7 +1024,8 @@ pub fn unified_diff_from_chunks_with_context(
     let AppliedPatch {
         original_contents,
         new_contents,
-    } = derive_new_contents_from_chunks(path, chunks)?;
+        ..
+    } = derive_new_contents_from_chunks(path, chunks, 0)?;
     let text_diff = TextDiff::from_lines(&original_contents, &new_contents);
     let unified_diff = text_diff.unified_diff().context_radius(context).to_string();
     Ok(ApplyPatchFileUpdate {
@@ -914,6 +1044,18 @@ pub fn print_summary(
     Ok(())
 }
 
+/// List the chunks that needed relaxed matching, one per line.
+pub fn print_fuzz(fuzz: &[Fuzz], out: &mut impl std::io::Write) -> std::io::Result<()> {
+    if fuzz.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "Applied with fuzz; check that these changes landed where intended:")?;
+    for f in fuzz {
+        writeln!(out, "  {f}")?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1695,4 +1837,87 @@ g
         let result = apply_patch(&patch, &mut stdout, &mut stderr);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_fuzz_ignores_stale_context_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fuzz.py");
+        fs::write(&path, "import numpy as np\n\ntheta = 0.25\nprint(theta)\n").unwrap();
+        // The leading context line was written against an older version of the file.
+        let patch = wrap_patch(&format!(
+            r#"*** Update File: {}
+@@
+ import numpy
+-theta = 0.25
++theta = 0.3
+ print(theta)"#,
+            path.display()
+        ));
+        let hunks = parse_patch(&patch).unwrap().hunks;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        assert!(apply_hunks(&hunks, &mut stdout, &mut stderr).is_err());
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        apply_hunks_with_fuzz(&hunks, 2, &mut stdout, &mut stderr).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents, "import numpy as np\n\ntheta = 0.3\nprint(theta)\n");
+        let stdout_str = String::from_utf8(stdout).unwrap();
+        assert_eq!(
+            stdout_str,
+            format!(
+                "Applied with fuzz; check that these changes landed where intended:\n  {}: chunk 1 applied with 1 leading and 1 trailing context line(s) ignored\n",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_fuzz_keeps_changed_lines_as_anchor() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("anchor.txt");
+        fs::write(&path, "a\nb\nc\n").unwrap();
+        // Only context would be left to drop, and the removed line is not in the file.
+        let patch = wrap_patch(&format!(
+            r#"*** Update File: {}
+@@
+ a
+-x
++y
+ c"#,
+            path.display()
+        ));
+        let hunks = parse_patch(&patch).unwrap().hunks;
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        assert!(apply_hunks_with_fuzz(&hunks, 3, &mut stdout, &mut stderr).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test_fuzz_reports_whitespace_insensitive_match() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("indent.py");
+        fs::write(&path, "def f():\n    return 1\n").unwrap();
+        let patch = wrap_patch(&format!(
+            r#"*** Update File: {}
+@@
+ def f():
+-  return 1
++    return 2"#,
+            path.display()
+        ));
+        let hunks = parse_patch(&patch).unwrap().hunks;
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        apply_hunks_with_fuzz(&hunks, 0, &mut stdout, &mut stderr).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "def f():\n    return 2\n");
+        assert!(
+            String::from_utf8(stdout)
+                .unwrap()
+                .contains("chunk 1 applied ignoring whitespace differences")
+        );
+    }
 }
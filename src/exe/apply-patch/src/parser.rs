@@ -22,7 +22,7 @@
 //!
 //! The parser below is a little more lenient than the explicit spec and allows for
 //! leading/trailing whitespace around patch markers.
-use crate::ApplyPatchArgs;
+use super::ApplyPatchArgs;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -1,3 +1,13 @@
+/// How leniently lines had to be compared for a pattern to be found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Leniency {
+    Exact,
+    TrailingWhitespace,
+    Whitespace,
+    /// Whitespace, and typographic dashes, quotes and spaces taken as their ASCII equivalents
+    Punctuation,
+}
+
 /// Attempt to find the sequence of `pattern` lines within `lines` beginning at or after `start`.
 /// Returns the starting index of the match or `None` if not found. Matches are attempted with
 /// decreasing strictness: exact match, then ignoring trailing whitespace, then ignoring leading
@@ -15,8 +25,18 @@ pub(crate) fn seek_sequence(
     start: usize,
     eof: bool,
 ) -> Option<usize> {
+    seek_sequence_with_leniency(lines, pattern, start, eof).map(|(idx, _)| idx)
+}
+
+/// Like [`seek_sequence`], also returning how leniently the match was made.
+pub(crate) fn seek_sequence_with_leniency(
+    lines: &[String],
+    pattern: &[String],
+    start: usize,
+    eof: bool,
+) -> Option<(usize, Leniency)> {
     if pattern.is_empty() {
-        return Some(start);
+        return Some((start, Leniency::Exact));
     }
 
     // When the pattern is longer than the available input there is no possible
@@ -34,7 +54,7 @@ pub(crate) fn seek_sequence(
     // Exact match first.
     for i in search_start..=lines.len().saturating_sub(pattern.len()) {
         if lines[i..i + pattern.len()] == *pattern {
-            return Some(i);
+            return Some((i, Leniency::Exact));
         }
     }
     // Then rstrip match.
@@ -47,7 +67,7 @@ pub(crate) fn seek_sequence(
             }
         }
         if ok {
-            return Some(i);
+            return Some((i, Leniency::TrailingWhitespace));
         }
     }
     // Finally, trim both sides to allow more lenience.
@@ -60,7 +80,7 @@ pub(crate) fn seek_sequence(
             }
         }
         if ok {
-            return Some(i);
+            return Some((i, Leniency::Whitespace));
         }
     }
 
@@ -102,7 +122,7 @@ pub(crate) fn seek_sequence(
             }
         }
         if ok {
-            return Some(i);
+            return Some((i, Leniency::Punctuation));
         }
     }
 
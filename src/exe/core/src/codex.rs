@@ -2405,6 +2405,7 @@ async fn handle_function_call(
                 env: HashMap::new(),
                 with_escalated_permissions: None,
                 justification: None,
+                limits: Default::default(),
             };
             handle_container_exec_with_params(
                 exec_params,
@@ -2476,6 +2477,7 @@ async fn handle_custom_tool_call(
                 env: HashMap::new(),
                 with_escalated_permissions: None,
                 justification: None,
+                limits: Default::default(),
             };
 
             handle_container_exec_with_params(
@@ -2505,6 +2507,7 @@ fn to_exec_params(params: ShellToolCallParams, turn_context: &TurnContext) -> Ex
         env: create_env(&turn_context.shell_environment_policy),
         with_escalated_permissions: params.with_escalated_permissions,
         justification: params.justification,
+        limits: Default::default(),
     }
 }
 
@@ -2614,6 +2617,7 @@ async fn handle_container_exec_with_params(
                 env: HashMap::new(),
                 with_escalated_permissions: params.with_escalated_permissions,
                 justification: params.justification.clone(),
+                limits: Default::default(),
             };
             let safety = if *user_explicitly_approved_this_action {
                 SafetyCheck::AutoApprove {
@@ -3732,6 +3736,7 @@ mod tests {
             env: HashMap::new(),
             with_escalated_permissions: Some(true),
             justification: Some("test".to_string()),
+            limits: Default::default(),
         };
 
         let params2 = ExecParams {
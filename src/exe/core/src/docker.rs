@@ -0,0 +1,126 @@
+//! `SandboxType::Docker`: run the command in a throwaway container.
+//!
+//! Only the sandbox root is mounted (read-write, at the same path, so absolute
+//! paths inside it keep working). The container sees exactly the variables in
+//! `ExecParams::env`; the `docker` client itself runs with the host
+//! environment so it can find the daemon. Memory and CPU limits become
+//! container limits instead of rlimits on the client, and a GPU limit passes
+//! that many GPUs through.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+
+use crate::exec::ExecParams;
+use crate::limits::ResourceLimits;
+use crate::protocol::SandboxPolicy;
+
+/// `docker run` exits with this when the container could not be started
+/// (unknown image, daemon not running, bad flags).
+pub(crate) const DOCKER_RUN_FAILED_EXIT_CODE: i32 = 125;
+
+/// A container started for one exec call; see [`Container::cleanup`].
+pub(crate) struct Container {
+    name: String,
+}
+
+/// Rewrite `params` so its command runs in a new `image` container.
+pub(crate) fn containerize(
+    params: ExecParams,
+    image: &str,
+    sandbox_policy: &SandboxPolicy,
+    sandbox_root: &Path,
+) -> (ExecParams, Container) {
+    let name = format!("qernel-exec-{}", uuid::Uuid::new_v4());
+    let root = sandbox_root.to_string_lossy();
+    let mut argv: Vec<String> = vec![
+        "docker".into(),
+        "run".into(),
+        "--rm".into(),
+        // Reaps zombies and forwards signals inside the container
+        "--init".into(),
+        "--name".into(),
+        name.clone(),
+        "--volume".into(),
+        format!("{root}:{root}:rw"),
+        "--workdir".into(),
+        params.cwd.to_string_lossy().into_owned(),
+    ];
+    if params.use_pty {
+        argv.push("--tty".into());
+    }
+    if !sandbox_policy.has_full_network_access() {
+        argv.extend(["--network".into(), "none".into()]);
+    }
+    #[cfg(unix)]
+    {
+        // Files the command writes into the project stay owned by the user.
+        // SAFETY: getuid and getgid cannot fail and have no preconditions.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        argv.extend(["--user".into(), format!("{uid}:{gid}")]);
+    }
+    argv.extend(limit_flags(&params.limits));
+    let mut keys: Vec<&String> = params.env.keys().collect();
+    keys.sort();
+    for key in keys {
+        // Without a value, docker copies it from the client's environment,
+        // which keeps secrets off the command line.
+        argv.extend(["--env".into(), key.clone()]);
+    }
+    argv.push(image.to_string());
+    argv.extend(params.command.iter().cloned());
+
+    let mut env: HashMap<String, String> = std::env::vars().collect();
+    env.extend(params.env.clone());
+    let limits = ResourceLimits {
+        wall_clock: params.limits.wall_clock,
+        ..Default::default()
+    };
+    (
+        ExecParams {
+            command: argv,
+            env,
+            limits,
+            ..params
+        },
+        Container { name },
+    )
+}
+
+fn limit_flags(limits: &ResourceLimits) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(bytes) = limits.memory_bytes {
+        flags.extend(["--memory".into(), format!("{bytes}b")]);
+        // No swap on top of the cap, as with the cgroup limits
+        flags.extend(["--memory-swap".into(), format!("{bytes}b")]);
+    }
+    if let Some(secs) = limits.cpu_seconds {
+        flags.extend(["--ulimit".into(), format!("cpu={secs}:{}", secs.saturating_add(1))]);
+    }
+    // Containers see no GPUs unless some are passed through
+    if let Some(count) = limits.gpus.filter(|&n| n > 0) {
+        flags.extend(["--gpus".into(), count.to_string()]);
+    }
+    flags
+}
+
+impl Container {
+    /// Remove the container unless the client saw it exit on its own (then
+    /// `--rm` already did). Killing the `docker run` client on a timeout or
+    /// cancel does not stop the container.
+    pub(crate) async fn cleanup(self, exited_on_its_own: bool) {
+        if exited_on_its_own {
+            return;
+        }
+        let removed = tokio::process::Command::new("docker")
+            .args(["rm", "--force", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+        if let Err(e) = removed {
+            tracing::warn!("failed to remove container {}: {e}", self.name);
+        }
+    }
+}
@@ -33,6 +33,9 @@ pub enum SandboxErr {
     Timeout { output: Box<ExecToolCallOutput> },
     Denied { output: Box<ExecToolCallOutput> },
 }
+use crate::docker::DOCKER_RUN_FAILED_EXIT_CODE;
+use crate::docker::containerize;
+pub use crate::limits::ResourceLimits;
 use crate::protocol::Event;
 use crate::protocol::EventMsg;
 use crate::protocol::ExecCommandBeginEvent;
@@ -40,8 +43,12 @@ use crate::protocol::ExecCommandEndEvent;
 use crate::protocol::ExecCommandOutputDeltaEvent;
 use crate::protocol::ExecOutputStream;
 use crate::protocol::SandboxPolicy;
+use crate::spawn::PtyChild;
 use crate::spawn::StdioPolicy;
+use crate::spawn::kill_child_tree;
+use crate::spawn::kill_process_group;
 use crate::spawn::spawn_child_async;
+use crate::spawn::spawn_pty_child;
 
 const DEFAULT_TIMEOUT_MS: u64 = 10_000;
 
@@ -68,15 +75,23 @@ pub struct ExecParams {
     pub env: HashMap<String, String>,
     pub with_escalated_permissions: Option<bool>,
     pub justification: Option<String>,
+    pub limits: ResourceLimits,
+    /// Run the command under a pseudo-terminal. Its stdout and stderr arrive
+    /// merged, with ANSI escape sequences intact, as `stdout`.
+    pub use_pty: bool,
 }
 
 impl ExecParams {
     pub fn timeout_duration(&self) -> Duration {
-        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+        let timeout = Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+        match self.limits.wall_clock {
+            Some(wall_clock) => timeout.min(wall_clock),
+            None => timeout,
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum SandboxType {
     None,
 
@@ -85,6 +100,10 @@ pub enum SandboxType {
 
     /// Only available on Linux.
     LinuxSeccomp,
+
+    /// Run in a throwaway container of `image` with only the sandbox cwd
+    /// mounted. Needs a `docker` client on PATH.
+    Docker { image: String },
 }
 
 #[derive(Clone)]
@@ -106,8 +125,8 @@ pub async fn process_exec_tool_call(
 
     let _timeout_duration = params.timeout_duration();
 
-    // Sandbox support removed: always run without sandbox.
-    let _ = (sandbox_type, sandbox_cwd, codex_linux_sandbox_exe);
+    // Seatbelt and seccomp support removed: those run without a sandbox.
+    let _ = codex_linux_sandbox_exe;
     // Emit begin event if streaming enabled
     if let Some(stream) = &stdout_stream {
         let begin = ExecCommandBeginEvent {
@@ -122,32 +141,57 @@ pub async fn process_exec_tool_call(
             .await;
     }
 
+    let (exec_params, container, client_policy) = match &sandbox_type {
+        SandboxType::Docker { image } => {
+            let (wrapped, container) = containerize(params.clone(), image, sandbox_policy, sandbox_cwd);
+            // The container carries the network policy; the client needs to reach the daemon
+            (wrapped, Some(container), &SandboxPolicy::DANGER_FULL_ACCESS)
+        }
+        _ => (params.clone(), None, sandbox_policy),
+    };
     let raw_output_result: std::result::Result<RawExecToolCallOutput, CodexErr> =
-        exec(params.clone(), sandbox_policy, stdout_stream.clone()).await;
+        exec(exec_params, client_policy, stdout_stream.clone()).await;
+    if let Some(container) = container {
+        let exited = matches!(&raw_output_result, Ok(raw) if !raw.timed_out && raw.exit_status.code().is_some());
+        container.cleanup(exited).await;
+    }
     let duration = start.elapsed();
     match raw_output_result {
         Ok(raw_output) => {
             #[allow(unused_mut)]
             let mut timed_out = raw_output.timed_out;
+            #[allow(unused_mut)]
+            let mut exit_code = raw_output.exit_status.code().unwrap_or(-1);
+            // Set when a resource limit killed the command; reported on stderr
+            #[allow(unused_mut)]
+            let mut limit_note: Option<String> = None;
 
             #[cfg(target_family = "unix")]
             {
                 if let Some(signal) = raw_output.exit_status.signal() {
                     if signal == TIMEOUT_CODE {
                         timed_out = true;
+                    } else if let Some(note) = params.limits.describe_signal(signal, raw_output.oom_killed) {
+                        exit_code = EXIT_CODE_SIGNAL_BASE + signal;
+                        limit_note = Some(note);
                     } else {
                         return Err(CodexErr::Sandbox(SandboxErr::Signal(signal)));
                     }
                 }
             }
 
-            let mut exit_code = raw_output.exit_status.code().unwrap_or(-1);
             if timed_out {
                 exit_code = EXEC_TIMEOUT_EXIT_CODE;
             }
 
             let stdout = raw_output.stdout.from_utf8_lossy();
-            let stderr = raw_output.stderr.from_utf8_lossy();
+            let mut stderr = raw_output.stderr.from_utf8_lossy();
+            if let Some(note) = limit_note {
+                if !stderr.text.is_empty() && !stderr.text.ends_with('\n') {
+                    stderr.text.push('\n');
+                }
+                stderr.text.push_str(&format!("qernel: {note}\n"));
+            }
             let aggregated_output = raw_output.aggregated_output.from_utf8_lossy();
             let exec_output = ExecToolCallOutput {
                 exit_code,
@@ -164,7 +208,7 @@ pub async fn process_exec_tool_call(
                 }));
             }
 
-            if exit_code != 0 && is_likely_sandbox_denied(sandbox_type, exit_code) {
+            if exit_code != 0 && is_likely_sandbox_denied(&sandbox_type, exit_code) {
                 return Err(CodexErr::Sandbox(SandboxErr::Denied {
                     output: Box::new(exec_output),
                 }));
@@ -201,9 +245,12 @@ pub async fn process_exec_tool_call(
 /// error, but the command itself might fail or succeed for other reasons.
 /// For now, we conservatively check for 'command not found' (exit code 127),
 /// and can add additional cases as necessary.
-fn is_likely_sandbox_denied(sandbox_type: SandboxType, exit_code: i32) -> bool {
-    if sandbox_type == SandboxType::None {
-        return false;
+fn is_likely_sandbox_denied(sandbox_type: &SandboxType, exit_code: i32) -> bool {
+    match sandbox_type {
+        SandboxType::None => return false,
+        // Any other exit code is the command's own
+        SandboxType::Docker { .. } => return exit_code == DOCKER_RUN_FAILED_EXIT_CODE,
+        SandboxType::MacosSeatbelt | SandboxType::LinuxSeccomp => {}
     }
 
     // Quick rejects: well-known non-sandbox shell exit codes
@@ -228,6 +275,8 @@ struct RawExecToolCallOutput {
     pub stderr: StreamOutput<Vec<u8>>,
     pub aggregated_output: StreamOutput<Vec<u8>>,
     pub timed_out: bool,
+    /// The memory cgroup recorded an out-of-memory kill
+    pub oom_killed: bool,
 }
 
 impl StreamOutput<String> {
@@ -248,6 +297,18 @@ impl StreamOutput<Vec<u8>> {
     }
 }
 
+/// Longest prefix of `s` that is at most `max_bytes` long without splitting a
+/// UTF-8 sequence.
+pub fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
+    &s[..s.floor_char_boundary(max_bytes)]
+}
+
+/// Longest suffix of `s` that is at most `max_bytes` long without splitting a
+/// UTF-8 sequence.
+pub fn truncate_utf8_tail(s: &str, max_bytes: usize) -> &str {
+    &s[s.ceil_char_boundary(s.len().saturating_sub(max_bytes))..]
+}
+
 #[inline]
 fn append_all(dst: &mut Vec<u8>, src: &[u8]) {
     dst.extend_from_slice(src);
@@ -270,8 +331,9 @@ async fn exec(
 ) -> Result<RawExecToolCallOutput> {
     let timeout = params.timeout_duration();
     let ExecParams {
-        command, cwd, env, ..
+        command, cwd, mut env, limits, use_pty, ..
     } = params;
+    limits.limit_gpus(&mut env);
 
     let (program, args) = command.split_first().ok_or_else(|| {
         CodexErr::Io(io::Error::new(
@@ -279,8 +341,21 @@ async fn exec(
             "command args are empty",
         ))
     })?;
+    if use_pty {
+        let (child, limit_guard) = spawn_pty_child(
+            PathBuf::from(program),
+            args.into(),
+            cwd,
+            sandbox_policy,
+            env,
+            &limits,
+        )?;
+        let mut raw = consume_pty_output(child, timeout, stdout_stream).await?;
+        raw.oom_killed = limit_guard.oom_killed();
+        return Ok(raw);
+    }
     let arg0 = None;
-    let child = spawn_child_async(
+    let (child, limit_guard) = spawn_child_async(
         PathBuf::from(program),
         args.into(),
         arg0,
@@ -288,9 +363,12 @@ async fn exec(
         sandbox_policy,
         StdioPolicy::RedirectForShellTool,
         env,
+        &limits,
     )
     .await?;
-    consume_truncated_output(child, timeout, stdout_stream).await
+    let mut raw = consume_truncated_output(child, timeout, stdout_stream).await?;
+    raw.oom_killed = limit_guard.oom_killed();
+    Ok(raw)
 }
 
 /// Consumes the output of a child process, truncating it so it is suitable for
@@ -330,6 +408,10 @@ async fn consume_truncated_output(
         Some(agg_tx.clone()),
     ));
 
+    // The child leads its own process group; remember it so the group can
+    // still be killed once the child itself has been reaped.
+    let pgid = child.id();
+
     let (exit_status, timed_out) = tokio::select! {
         result = tokio::time::timeout(timeout, child.wait()) => {
             match result {
@@ -339,18 +421,26 @@ async fn consume_truncated_output(
                 }
                 Err(_) => {
                     // timeout
-                    child.start_kill()?;
-                    // Debatable whether `child.wait().await` should be called here.
+                    kill_child_tree(&mut child)?;
                     (synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + TIMEOUT_CODE), true)
                 }
             }
         }
         _ = tokio::signal::ctrl_c() => {
-            child.start_kill()?;
+            kill_child_tree(&mut child)?;
             (synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + SIGKILL_CODE), false)
         }
     };
 
+    // Background jobs the command left behind (`sleep 1000 &`) would otherwise
+    // outlive the call and hold stdout/stderr open, so the readers below would
+    // never see EOF. Take the rest of the group down and reap the child so it
+    // does not linger as a zombie after a timeout or cancel.
+    if let Some(pgid) = pgid {
+        kill_process_group(pgid);
+    }
+    let _ = child.wait().await;
+
     let stdout = stdout_handle.await.map_err(|e| std::io::Error::other(e))??;
     let stderr = stderr_handle.await.map_err(|e| std::io::Error::other(e))??;
 
@@ -371,6 +461,7 @@ async fn consume_truncated_output(
         stderr,
         aggregated_output,
         timed_out,
+        oom_killed: false,
     })
 }
 
@@ -394,22 +485,8 @@ async fn read_capped<R: AsyncRead + Unpin + Send + 'static>(
 
         if emitted_deltas < MAX_EXEC_OUTPUT_DELTAS_PER_CALL {
             if let Some(stream) = &stream {
-            let chunk = tmp[..n].to_vec();
-            let msg = EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent {
-                call_id: stream.call_id.clone(),
-                stream: if is_stderr {
-                    ExecOutputStream::Stderr
-                } else {
-                    ExecOutputStream::Stdout
-                },
-                chunk,
-            });
-            let event = Event {
-                id: stream.sub_id.clone(),
-                msg,
-            };
             #[allow(clippy::let_unit_value)]
-            let _ = stream.tx_event.send(event).await;
+            let _ = stream.tx_event.send(output_delta(stream, is_stderr, &tmp[..n])).await;
             emitted_deltas += 1;
             }
         }
@@ -428,6 +505,153 @@ async fn read_capped<R: AsyncRead + Unpin + Send + 'static>(
     })
 }
 
+fn output_delta(stream: &StdoutStream, is_stderr: bool, chunk: &[u8]) -> Event {
+    let msg = EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent {
+        call_id: stream.call_id.clone(),
+        stream: if is_stderr {
+            ExecOutputStream::Stderr
+        } else {
+            ExecOutputStream::Stdout
+        },
+        chunk: chunk.to_vec(),
+    });
+    Event {
+        id: stream.sub_id.clone(),
+        msg,
+    }
+}
+
+/// Counterpart of [`consume_truncated_output`] for a command running under a
+/// pseudo-terminal. Everything it printed is reported as stdout.
+async fn consume_pty_output(
+    child: PtyChild,
+    timeout: Duration,
+    stdout_stream: Option<StdoutStream>,
+) -> Result<RawExecToolCallOutput> {
+    let PtyChild { child, reader, _master: master } = child;
+    let pid = child.process_id().ok_or_else(|| {
+        CodexErr::Io(io::Error::other("PTY child has no process id"))
+    })?;
+    let mut killer = child.clone_killer();
+
+    let reader_handle = tokio::task::spawn_blocking(move || read_pty(reader, stdout_stream));
+    let mut wait_handle = tokio::task::spawn_blocking(move || wait_pty_child(child, pid));
+
+    let (exit_status, timed_out) = tokio::select! {
+        result = tokio::time::timeout(timeout, &mut wait_handle) => {
+            match result {
+                Ok(joined) => (joined.map_err(io::Error::other)??, false),
+                Err(_) => {
+                    // timeout
+                    kill_process_group(pid);
+                    let _ = killer.kill();
+                    (synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + TIMEOUT_CODE), true)
+                }
+            }
+        }
+        _ = tokio::signal::ctrl_c() => {
+            kill_process_group(pid);
+            let _ = killer.kill();
+            (synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + SIGKILL_CODE), false)
+        }
+    };
+
+    // As with pipes: background jobs would keep the terminal open, and the
+    // child has to be reaped after a timeout or cancel.
+    kill_process_group(pid);
+    if !wait_handle.is_finished() {
+        let _ = wait_handle.await;
+    }
+
+    let output = reader_handle.await.map_err(io::Error::other)??;
+    drop(master);
+
+    Ok(RawExecToolCallOutput {
+        exit_status,
+        stdout: StreamOutput {
+            text: output.clone(),
+            truncated_after_lines: None,
+        },
+        stderr: StreamOutput {
+            text: Vec::new(),
+            truncated_after_lines: None,
+        },
+        aggregated_output: StreamOutput {
+            text: output,
+            truncated_after_lines: None,
+        },
+        timed_out,
+        oom_killed: false,
+    })
+}
+
+/// Blocking counterpart of [`read_capped`] for the master end of a PTY.
+/// Returns once every process holding the terminal has exited, with the
+/// terminal's `\r\n` line endings turned back into `\n`.
+fn read_pty(
+    mut reader: Box<dyn std::io::Read + Send>,
+    stream: Option<StdoutStream>,
+) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(AGGREGATE_BUFFER_INITIAL_CAPACITY);
+    let mut tmp = [0u8; READ_CHUNK_SIZE];
+    let mut emitted_deltas: usize = 0;
+
+    loop {
+        let n = match reader.read(&mut tmp) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            // Linux reports EIO once the last process holding the slave end exits
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => break,
+            Err(e) => return Err(e),
+        };
+
+        if emitted_deltas < MAX_EXEC_OUTPUT_DELTAS_PER_CALL
+            && let Some(stream) = &stream
+        {
+            let _ = stream.tx_event.send_blocking(output_delta(stream, false, &tmp[..n]));
+            emitted_deltas += 1;
+        }
+
+        append_all(&mut buf, &tmp[..n]);
+    }
+
+    let mut text = Vec::with_capacity(buf.len());
+    let mut bytes = buf.iter().peekable();
+    while let Some(&b) = bytes.next() {
+        if b == b'\r' && bytes.peek() == Some(&&b'\n') {
+            continue;
+        }
+        text.push(b);
+    }
+    Ok(text)
+}
+
+#[cfg(unix)]
+fn wait_pty_child(child: Box<dyn portable_pty::Child + Send + Sync>, pid: u32) -> io::Result<ExitStatus> {
+    // portable-pty reports signals by name only; wait ourselves to keep the
+    // raw status, which resource limit reporting relies on.
+    let mut status = 0;
+    loop {
+        // SAFETY: `status` is a valid out-pointer and `pid` is our own child.
+        if unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) } != -1 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    drop(child);
+    Ok(ExitStatus::from_raw(status))
+}
+
+#[cfg(windows)]
+fn wait_pty_child(mut child: Box<dyn portable_pty::Child + Send + Sync>, _pid: u32) -> io::Result<ExitStatus> {
+    let status = child.wait()?;
+    Ok(synthetic_exit_status(status.exit_code() as i32))
+}
+
 #[cfg(unix)]
 fn synthetic_exit_status(code: i32) -> ExitStatus {
     use std::os::unix::process::ExitStatusExt;
@@ -440,3 +664,20 @@ fn synthetic_exit_status(code: i32) -> ExitStatus {
     #[expect(clippy::unwrap_used)]
     std::process::ExitStatus::from_raw(code.try_into().unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_utf8_keeps_char_boundaries() {
+        let s = "ab\u{2502}cd"; // the box-drawing char is 3 bytes
+        assert_eq!(truncate_utf8(s, 3), "ab");
+        assert_eq!(truncate_utf8(s, 5), "ab\u{2502}");
+        assert_eq!(truncate_utf8(s, 100), s);
+        assert_eq!(truncate_utf8_tail(s, 3), "cd");
+        assert_eq!(truncate_utf8_tail(s, 5), "\u{2502}cd");
+        assert_eq!(truncate_utf8_tail(s, 100), s);
+        assert_eq!(truncate_utf8_tail(s, 0), "");
+    }
+}
@@ -2,9 +2,28 @@
 
 #![deny(clippy::print_stdout, clippy::print_stderr)]
 
+/// Return early from a test that spawns processes when it runs inside the macOS seatbelt
+/// sandbox (upstream's `core_test_support::skip_if_sandbox!`)
+#[cfg(test)]
+macro_rules! skip_if_sandbox {
+    () => {{
+        if ::std::env::var("CODEX_SANDBOX").as_deref() == Ok("seatbelt") {
+            return;
+        }
+    }};
+    ($return_value:expr $(,)?) => {{
+        if ::std::env::var("CODEX_SANDBOX").as_deref() == Ok("seatbelt") {
+            return $return_value;
+        }
+    }};
+}
+
 pub mod bash;
+mod docker;
 pub mod exec;
+pub mod limits;
 pub mod parse_command;
+pub mod patch;
 pub mod shell;
 pub mod spawn;
 pub mod turn_diff_tracker;
@@ -60,7 +79,51 @@ pub mod openai_tools {
             r#type: String,
             description: Option<String> 
         },
+        Array {
+            r#type: String,
+            items: Box<JsonSchema>,
+            description: Option<String>,
+        },
+        /// Generated from a Rust type with [`JsonSchema::for_type`]
+        Generated(serde_json::Value),
+    }
+
+    impl JsonSchema {
+        /// Parameters schema of a tool's argument type, derived with `schemars` so the
+        /// definition sent to the model cannot drift from what is deserialized
+        pub fn for_type<T: schemars::JsonSchema>() -> Self {
+            let settings = schemars::r#gen::SchemaSettings::draft07().with(|s| {
+                s.inline_subschemas = true;
+                s.meta_schema = None;
+            });
+            let schema = settings.into_generator().into_root_schema_for::<T>();
+            let mut value = serde_json::to_value(schema).unwrap_or_default();
+            strip_titles(&mut value);
+            // The tool description already says what the arguments are for
+            if let Some(root) = value.as_object_mut() {
+                root.remove("description");
+                root.insert("additionalProperties".to_string(), serde_json::Value::Bool(false));
+            }
+            JsonSchema::Generated(value)
+        }
+    }
+
+    /// Type names are meaningless to the model; drop them from generated schemas
+    fn strip_titles(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if map.get("title").is_some_and(serde_json::Value::is_string) {
+                    map.remove("title");
+                }
+                map.values_mut().for_each(strip_titles);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip_titles),
+            _ => {}
+        }
     }
 }
 
+pub mod plan_tool;
+pub mod tool_add_dependency;
 pub mod tool_apply_patch;
+pub mod tool_shell;
@@ -0,0 +1,420 @@
+//! Resource limits for spawned commands.
+//!
+//! On Unix, memory and CPU time are capped with rlimits set in the child
+//! before exec. On Linux we first try to place the child in its own cgroup
+//! (v2) so the memory cap covers every process the command forks; this needs
+//! a delegated cgroup subtree and silently falls back to rlimits otherwise.
+//! On Windows the child is assigned to a job object that carries the limits
+//! and kills the whole process tree when the command finishes. GPUs are
+//! limited by narrowing the CUDA/ROCm visible-device variables.
+
+use std::time::Duration;
+
+use tokio::process::Child;
+use tokio::process::Command;
+
+/// Caps applied to a single exec call. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Maximum memory in bytes (address space per process with rlimits,
+    /// total for the process tree with cgroups and job objects).
+    pub memory_bytes: Option<u64>,
+    /// Maximum CPU time in seconds.
+    pub cpu_seconds: Option<u64>,
+    /// Maximum wall-clock time; takes precedence over a longer `timeout_ms`.
+    pub wall_clock: Option<Duration>,
+    /// Maximum number of GPUs the command can see; `Some(0)` hides them all.
+    pub gpus: Option<u32>,
+}
+
+/// Variables CUDA and ROCm runtimes read to pick the devices a process sees
+const GPU_VISIBILITY_VARS: &[&str] = &["CUDA_VISIBLE_DEVICES", "HIP_VISIBLE_DEVICES", "ROCR_VISIBLE_DEVICES"];
+
+impl ResourceLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.memory_bytes.is_none() && self.cpu_seconds.is_none() && self.wall_clock.is_none() && self.gpus.is_none()
+    }
+
+    /// Restrict `env` to the first `gpus` devices: of the ones it already lists
+    /// in a visibility variable, else devices `0..gpus`.
+    pub fn limit_gpus(&self, env: &mut std::collections::HashMap<String, String>) {
+        let Some(count) = self.gpus else { return };
+        for var in GPU_VISIBILITY_VARS {
+            let visible: Vec<String> = match env.get(*var).filter(|v| !v.trim().is_empty()) {
+                Some(listed) => listed.split(',').map(|d| d.trim().to_string()).take(count as usize).collect(),
+                None => (0..count).map(|d| d.to_string()).collect(),
+            };
+            env.insert(var.to_string(), visible.join(","));
+        }
+    }
+
+    /// Explain a terminating signal that was caused by one of these limits, so
+    /// callers can report it instead of a bare signal number. `oom_killed` says
+    /// whether the memory cgroup recorded an out-of-memory kill; other SIGKILLs
+    /// are not blamed on the memory limit.
+    pub fn describe_signal(&self, signal: i32, oom_killed: bool) -> Option<String> {
+        #[cfg(unix)]
+        {
+            if signal == libc::SIGXCPU
+                && let Some(secs) = self.cpu_seconds
+            {
+                return Some(format!("CPU time limit of {secs}s exceeded"));
+            }
+            if signal == libc::SIGKILL {
+                if oom_killed && let Some(bytes) = self.memory_bytes {
+                    return Some(format!("killed for exceeding the memory limit of {} MiB", bytes / (1024 * 1024)));
+                }
+                // RLIMIT_CPU sends SIGKILL one second after the SIGXCPU the command ignored
+                if let Some(secs) = self.cpu_seconds {
+                    return Some(format!("killed; the CPU time limit of {secs}s may have been exceeded"));
+                }
+            }
+        }
+        let _ = (signal, oom_killed);
+        None
+    }
+}
+
+/// Holds whatever OS object enforces the limits for the lifetime of the child.
+/// Dropping it tears the object down (and with it any leftover processes).
+#[derive(Default)]
+pub(crate) struct LimitGuard {
+    #[cfg(target_os = "linux")]
+    cgroup: Option<cgroup::Cgroup>,
+    #[cfg(windows)]
+    job: Option<job::Job>,
+}
+
+impl LimitGuard {
+    /// Whether the command's memory cgroup killed a process for running out of
+    /// memory. Always false without a cgroup, where a SIGKILL proves nothing.
+    pub(crate) fn oom_killed(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(cgroup) = &self.cgroup {
+            return cgroup.oom_kills() > 0;
+        }
+        false
+    }
+
+    /// Set up the limits before the child is spawned.
+    pub(crate) fn prepare(limits: &ResourceLimits, cmd: &mut Command) -> Self {
+        #[allow(unused_mut)]
+        let mut guard = Self::default();
+
+        #[cfg(target_os = "linux")]
+        if let Some(bytes) = limits.memory_bytes {
+            guard.cgroup = cgroup::Cgroup::create(bytes);
+        }
+
+        #[cfg(unix)]
+        {
+            let join_fd = {
+                #[cfg(target_os = "linux")]
+                {
+                    guard.cgroup.as_ref().map(|c| c.procs_fd())
+                }
+                #[cfg(not(target_os = "linux"))]
+                {
+                    None::<i32>
+                }
+            };
+            // The cgroup already caps the whole tree; only fall back to a
+            // per-process address space limit without one.
+            let memory = if join_fd.is_some() { None } else { limits.memory_bytes };
+            let cpu = limits.cpu_seconds;
+            if join_fd.is_some() || memory.is_some() || cpu.is_some() {
+                // SAFETY: the closure only calls async-signal-safe functions
+                // (write, setrlimit) and does not allocate.
+                unsafe {
+                    cmd.pre_exec(move || {
+                        if let Some(fd) = join_fd {
+                            // Writing "0" moves the calling process into the cgroup.
+                            if libc::write(fd, b"0".as_ptr().cast(), 1) == -1 {
+                                return Err(std::io::Error::last_os_error());
+                            }
+                        }
+                        if let Some(bytes) = memory {
+                            set_rlimit(libc::RLIMIT_AS, bytes, bytes)?;
+                        }
+                        if let Some(secs) = cpu {
+                            // SIGXCPU at the soft limit, SIGKILL one second later.
+                            set_rlimit(libc::RLIMIT_CPU, secs, secs.saturating_add(1))?;
+                        }
+                        Ok(())
+                    });
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = (limits, cmd);
+
+        guard
+    }
+
+    /// Attach limits that can only be applied to a running process.
+    pub(crate) fn attach(&mut self, limits: &ResourceLimits, child: &Child) {
+        #[cfg(windows)]
+        if limits.memory_bytes.is_some() || limits.cpu_seconds.is_some() {
+            if let Some(handle) = child.raw_handle() {
+                match job::Job::assign(handle, limits) {
+                    Ok(job) => self.job = Some(job),
+                    Err(e) => tracing::warn!("failed to apply job object limits: {e}"),
+                }
+            }
+        }
+
+        #[cfg(not(windows))]
+        let _ = (limits, child);
+    }
+}
+
+impl LimitGuard {
+    /// Apply the limits to a child that was not spawned from a `Command` we
+    /// could prepare, such as a PTY-backed command. Processes it forks before
+    /// this call are not covered by the memory and CPU caps.
+    pub(crate) fn attach_pid(limits: &ResourceLimits, pid: u32) -> Self {
+        #[allow(unused_mut)]
+        let mut guard = Self::default();
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(bytes) = limits.memory_bytes {
+                guard.cgroup = cgroup::Cgroup::create(bytes).filter(|c| match c.add(pid) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::debug!("failed to move {pid} into its cgroup, using rlimits: {e}");
+                        false
+                    }
+                });
+            }
+            let memory = if guard.cgroup.is_some() { None } else { limits.memory_bytes };
+            let applied = memory
+                .map_or(Ok(()), |bytes| set_prlimit(pid, libc::RLIMIT_AS, bytes, bytes))
+                .and_then(|_| {
+                    limits
+                        .cpu_seconds
+                        .map_or(Ok(()), |secs| set_prlimit(pid, libc::RLIMIT_CPU, secs, secs.saturating_add(1)))
+                });
+            if let Err(e) = applied {
+                tracing::warn!("failed to apply resource limits to {pid}: {e}");
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            if limits.memory_bytes.is_some() || limits.cpu_seconds.is_some() {
+                tracing::warn!("memory and CPU limits are not applied to PTY-backed commands on this platform");
+            }
+            let _ = pid;
+        }
+
+        guard
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_prlimit(pid: u32, resource: ResourceKind, soft: u64, hard: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call and the
+    // old limit is not requested.
+    if unsafe { libc::prlimit(pid as libc::pid_t, resource, &limit, std::ptr::null_mut()) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_rlimit(resource: ResourceKind, soft: u64, hard: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call.
+    if unsafe { libc::setrlimit(resource, &limit) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type ResourceKind = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type ResourceKind = libc::c_int;
+
+#[cfg(target_os = "linux")]
+mod cgroup {
+    use std::fs;
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering;
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    /// A child cgroup of our own, created per exec call.
+    pub(super) struct Cgroup {
+        path: PathBuf,
+        procs: fs::File,
+    }
+
+    impl Cgroup {
+        /// Returns `None` when cgroup v2 is unavailable or not delegated to us.
+        pub(super) fn create(memory_bytes: u64) -> Option<Self> {
+            let own = fs::read_to_string("/proc/self/cgroup").ok()?;
+            let rel = own.lines().find_map(|l| l.strip_prefix("0::"))?;
+            let parent = PathBuf::from("/sys/fs/cgroup").join(rel.trim_start_matches('/'));
+            let name = format!(
+                "qernel-exec-{}-{}",
+                std::process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = parent.join(name);
+            fs::create_dir(&path).ok()?;
+            // On hybrid v1 hosts /sys/fs/cgroup is a tmpfs and create_dir just made
+            // a plain directory; a real cgroup comes with its interface files.
+            if !path.join("cgroup.procs").exists() {
+                let _ = fs::remove_dir(&path);
+                return None;
+            }
+            // Never create files here: a missing interface file means the
+            // controller is not enabled for our subtree.
+            let write = |file: &str, value: &str| {
+                fs::OpenOptions::new().write(true).open(path.join(file))?.write_all(value.as_bytes())
+            };
+            let setup = write("memory.max", &memory_bytes.to_string()).and_then(|_| {
+                // Not every kernel has swap accounting; the memory cap still applies.
+                let _ = write("memory.swap.max", "0");
+                fs::OpenOptions::new().write(true).open(path.join("cgroup.procs"))
+            });
+            match setup {
+                Ok(procs) => Some(Self { path, procs }),
+                Err(e) => {
+                    tracing::debug!("cgroup limits unavailable, using rlimits: {e}");
+                    let _ = fs::remove_dir(&path);
+                    None
+                }
+            }
+        }
+
+        pub(super) fn procs_fd(&self) -> i32 {
+            self.procs.as_raw_fd()
+        }
+
+        /// Move a running process into the group.
+        pub(super) fn add(&self, pid: u32) -> std::io::Result<()> {
+            (&self.procs).write_all(pid.to_string().as_bytes())
+        }
+
+        /// Processes the kernel killed for exceeding `memory.max`, from
+        /// `memory.events`.
+        pub(super) fn oom_kills(&self) -> u64 {
+            let events = fs::read_to_string(self.path.join("memory.events")).unwrap_or_default();
+            events
+                .lines()
+                .find_map(|l| l.strip_prefix("oom_kill "))
+                .and_then(|n| n.trim().parse().ok())
+                .unwrap_or(0)
+        }
+    }
+
+    impl Drop for Cgroup {
+        fn drop(&mut self) {
+            // Kill anything the command left behind, then remove the group. The
+            // kernel may not have reaped the killed processes yet; then the group
+            // is removed in the background rather than blocking the caller.
+            if let Ok(mut kill) = fs::OpenOptions::new().write(true).open(self.path.join("cgroup.kill")) {
+                let _ = kill.write_all(b"1");
+            }
+            if fs::remove_dir(&self.path).is_ok() {
+                return;
+            }
+            let path = std::mem::take(&mut self.path);
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    if fs::remove_dir(&path).is_ok() {
+                        return;
+                    }
+                }
+                tracing::debug!("failed to remove cgroup {}", path.display());
+            });
+        }
+    }
+}
+
+#[cfg(windows)]
+mod job {
+    use std::os::windows::io::RawHandle;
+
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::System::JobObjects::AssignProcessToJobObject;
+    use windows_sys::Win32::System::JobObjects::CreateJobObjectW;
+    use windows_sys::Win32::System::JobObjects::JOB_OBJECT_LIMIT_JOB_MEMORY;
+    use windows_sys::Win32::System::JobObjects::JOB_OBJECT_LIMIT_JOB_TIME;
+    use windows_sys::Win32::System::JobObjects::JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+    use windows_sys::Win32::System::JobObjects::JOBOBJECT_EXTENDED_LIMIT_INFORMATION;
+    use windows_sys::Win32::System::JobObjects::JobObjectExtendedLimitInformation;
+    use windows_sys::Win32::System::JobObjects::SetInformationJobObject;
+
+    use super::ResourceLimits;
+
+    /// Job object that kills its processes when closed.
+    pub(super) struct Job(HANDLE);
+
+    // SAFETY: a job handle can be used and closed from any thread.
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        pub(super) fn assign(process: RawHandle, limits: &ResourceLimits) -> std::io::Result<Self> {
+            // SAFETY: plain Win32 calls on handles we own or were handed.
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle.is_null() {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let job = Job(handle);
+
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                if let Some(bytes) = limits.memory_bytes {
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                    info.JobMemoryLimit = bytes as usize;
+                }
+                if let Some(secs) = limits.cpu_seconds {
+                    // 100-nanosecond ticks
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_TIME;
+                    info.BasicLimitInformation.PerJobUserTimeLimit = (secs as i64).saturating_mul(10_000_000);
+                }
+                if SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    (&info as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION).cast(),
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                ) == 0
+                {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if AssignProcessToJobObject(job.0, process as HANDLE) == 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(job)
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: the handle came from CreateJobObjectW and is closed once.
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! `*** Begin Patch` bodies as [`FileChange`]s, without applying them. Validation, previews
+//! and audit records can read a patch through the same grammar `apply_patch` uses: the
+//! parser is compiled here from the apply-patch crate's source.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use similar::Algorithm;
+use similar::ChangeTag;
+
+use crate::protocol::FileChange;
+
+#[path = "../../apply-patch/src/parser.rs"]
+mod parser;
+
+pub use parser::Hunk;
+pub use parser::ParseError;
+pub use parser::UpdateFileChunk;
+pub use parser::parse_patch;
+
+/// A patch body and the hunks parsed from it
+#[derive(Debug, PartialEq)]
+pub struct ApplyPatchArgs {
+    pub patch: String,
+    pub hunks: Vec<Hunk>,
+    pub workdir: Option<String>,
+}
+
+/// The changes `patch` describes, keyed by path joined onto `cwd`. Nothing is read from or
+/// written to disk: an update's `unified_diff` has one `@@` section per chunk, headed by the
+/// chunk's context line rather than line numbers, since the chunk is not located in the file.
+pub fn parse_patch_changes(
+    patch: &str,
+    cwd: &Path,
+) -> Result<HashMap<PathBuf, FileChange>, ParseError> {
+    let hunks = parse_patch(patch)?.hunks;
+    let mut changes = HashMap::with_capacity(hunks.len());
+    for hunk in hunks {
+        let path = hunk.resolve_path(cwd);
+        let change = match hunk {
+            Hunk::AddFile { contents, .. } => FileChange::Add { content: contents },
+            Hunk::DeleteFile { .. } => FileChange::Delete,
+            Hunk::UpdateFile {
+                path: relative,
+                move_path,
+                chunks,
+            } => FileChange::Update {
+                unified_diff: chunks_diff(&relative, move_path.as_deref(), &chunks),
+                move_path: move_path.map(|dest| cwd.join(dest)),
+            },
+        };
+        changes.insert(path, change);
+    }
+    Ok(changes)
+}
+
+fn chunks_diff(path: &Path, move_path: Option<&Path>, chunks: &[UpdateFileChunk]) -> String {
+    let mut diff = format!(
+        "--- a/{}\n+++ b/{}\n",
+        path.display(),
+        move_path.unwrap_or(path).display()
+    );
+    for chunk in chunks {
+        match &chunk.change_context {
+            Some(context) => diff.push_str(&format!("@@ {context}\n")),
+            None => diff.push_str("@@\n"),
+        }
+        for (tag, lines) in
+            similar::utils::diff_slices(Algorithm::Myers, &chunk.old_lines, &chunk.new_lines)
+        {
+            let sign = match tag {
+                ChangeTag::Equal => ' ',
+                ChangeTag::Delete => '-',
+                ChangeTag::Insert => '+',
+            };
+            for line in lines {
+                diff.push(sign);
+                diff.push_str(line);
+                diff.push('\n');
+            }
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_each_kind_of_change_without_touching_disk() {
+        let cwd = Path::new("/project");
+        let patch = "*** Begin Patch
+*** Add File: src/new.py
++x = 1
+*** Delete File: src/old.py
+*** Update File: src/main.py
+*** Move to: src/app.py
+@@ def main():
+     a = 1
+-    b = 2
++    b = 3
+*** End Patch";
+        let changes = parse_patch_changes(patch, cwd).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes[&cwd.join("src/new.py")],
+            FileChange::Add {
+                content: "x = 1\n".to_string()
+            }
+        );
+        assert_eq!(changes[&cwd.join("src/old.py")], FileChange::Delete);
+        assert_eq!(
+            changes[&cwd.join("src/main.py")],
+            FileChange::Update {
+                unified_diff: "--- a/src/main.py\n+++ b/src/app.py\n@@ def main():\n     a = 1\n-    b = 2\n+    b = 3\n"
+                    .to_string(),
+                move_path: Some(cwd.join("src/app.py")),
+            }
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert!(parse_patch_changes("*** Add File: x\n+y", Path::new(".")).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub struct PlanItemArg {
+    pub step: String,
+    #[serde(default)]
+    pub status: StepStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub struct UpdatePlanArgs {
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// The list of steps
+    pub plan: Vec<PlanItemArg>,
+}
+
+/// Returns the `update_plan` function tool the model uses to record and update its plan.
+pub fn create_update_plan_tool() -> OpenAiTool {
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "update_plan".to_string(),
+        description: r#"Updates the task plan.
+Provide an optional explanation and a list of plan items, each with a step and status.
+At most one step can be in_progress at a time.
+"#
+        .to_string(),
+        strict: false,
+        parameters: JsonSchema::for_type::<UpdatePlanArgs>(),
+    })
+}
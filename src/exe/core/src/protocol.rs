@@ -92,12 +92,17 @@ pub enum EventMsg {
 pub struct Event { pub id: String, pub msg: EventMsg }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
-pub struct SandboxPolicy;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    network_access: bool,
+}
 
 impl SandboxPolicy {
-    pub const DANGER_FULL_ACCESS: SandboxPolicy = SandboxPolicy;
-    pub fn has_full_network_access(&self) -> bool { true }
+    pub const DANGER_FULL_ACCESS: SandboxPolicy = SandboxPolicy { network_access: true };
+    /// Full file access, but the command runs without a network: in a
+    /// network namespace of its own on Linux, `--network none` in a container.
+    pub const NO_NETWORK: SandboxPolicy = SandboxPolicy { network_access: false };
+    pub fn has_full_network_access(&self) -> bool { self.network_access }
 }
 
 
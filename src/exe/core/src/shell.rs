@@ -347,6 +347,8 @@ mod tests {
                     )]),
                     with_escalated_permissions: None,
                     justification: None,
+                    limits: Default::default(),
+                    use_pty: false,
                 },
                 SandboxType::None,
                 &SandboxPolicy::DANGER_FULL_ACCESS,
@@ -455,6 +457,8 @@ mod macos_tests {
                     )]),
                     with_escalated_permissions: None,
                     justification: None,
+                    limits: Default::default(),
+                    use_pty: false,
                 },
                 SandboxType::None,
                 &SandboxPolicy::DANGER_FULL_ACCESS,
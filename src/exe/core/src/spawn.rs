@@ -5,6 +5,8 @@ use tokio::process::Child;
 use tokio::process::Command;
 use tracing::trace;
 
+use crate::limits::LimitGuard;
+use crate::limits::ResourceLimits;
 use crate::protocol::SandboxPolicy;
 
 /// Experimental environment variable that will be set to some non-empty value
@@ -35,6 +37,9 @@ pub enum StdioPolicy {
 /// For now, we take `SandboxPolicy` as a parameter to spawn_child() because
 /// we need to determine whether to set the
 /// `CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR` environment variable.
+///
+/// The returned guard enforces `limits` and must outlive the child.
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn spawn_child_async(
     program: PathBuf,
     args: Vec<String>,
@@ -43,7 +48,8 @@ pub(crate) async fn spawn_child_async(
     sandbox_policy: &SandboxPolicy,
     stdio_policy: StdioPolicy,
     env: HashMap<String, String>,
-) -> std::io::Result<Child> {
+    limits: &ResourceLimits,
+) -> std::io::Result<(Child, LimitGuard)> {
     trace!(
         "spawn_child_async: {program:?} {args:?} {arg0:?} {cwd:?} {sandbox_policy:?} {stdio_policy:?} {env:?}"
     );
@@ -58,6 +64,7 @@ pub(crate) async fn spawn_child_async(
 
     if !sandbox_policy.has_full_network_access() {
         cmd.env(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR, "1");
+        isolate_network(cmd.as_std_mut())?;
     }
 
     // If this Codex process dies (including being killed via SIGKILL), we want
@@ -85,6 +92,8 @@ pub(crate) async fn spawn_child_async(
         });
     }
 
+    let mut guard = LimitGuard::prepare(limits, &mut cmd);
+
     match stdio_policy {
         StdioPolicy::RedirectForShellTool => {
             // Do not create a file descriptor for stdin because otherwise some
@@ -94,6 +103,11 @@ pub(crate) async fn spawn_child_async(
             cmd.stdin(Stdio::null());
 
             cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+            // Run in a process group of its own so cancelling the call can take
+            // down everything the command started, not just the direct child.
+            #[cfg(unix)]
+            cmd.process_group(0);
         }
         StdioPolicy::Inherit => {
             // Inherit stdin, stdout, and stderr from the parent process.
@@ -103,5 +117,174 @@ pub(crate) async fn spawn_child_async(
         }
     }
 
-    cmd.kill_on_drop(true).spawn()
+    let child = cmd.kill_on_drop(true).spawn()?;
+    guard.attach(limits, &child);
+    Ok((child, guard))
+}
+
+/// Run the child in a network namespace of its own, which has only a loopback
+/// device that is down. Unprivileged users get one through a user namespace
+/// that maps their own uid and gid, so files they write keep their owner.
+/// Fails rather than run the command with the network where this is not
+/// possible.
+#[cfg(target_os = "linux")]
+fn isolate_network(cmd: &mut std::process::Command) -> std::io::Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: getuid and getgid cannot fail and have no preconditions.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    // Formatted before the fork: the child must not allocate.
+    let uid_map = format!("{uid} {uid} 1");
+    let gid_map = format!("{gid} {gid} 1");
+    // SAFETY: the closure only calls async-signal-safe functions (unshare,
+    // open, write, close) and does not allocate.
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::unshare(libc::CLONE_NEWNET) == 0 {
+                return Ok(());
+            }
+            if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // gid_map may only be written once setgroups is denied
+            write_proc_file(c"/proc/self/setgroups", b"deny")?;
+            write_proc_file(c"/proc/self/uid_map", uid_map.as_bytes())?;
+            write_proc_file(c"/proc/self/gid_map", gid_map.as_bytes())
+        });
+    }
+    Ok(())
+}
+
+/// Whether [`SandboxPolicy::NO_NETWORK`] can be enforced here without a
+/// container: probed once by running `/bin/sh -c :` in a network namespace,
+/// since user namespaces may be disabled for unprivileged users.
+pub fn network_isolation_available() -> bool {
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let mut probe = std::process::Command::new("/bin/sh");
+        probe.args(["-c", ":"]).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+        isolate_network(&mut probe).is_ok() && probe.status().is_ok_and(|status| status.success())
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn write_proc_file(path: &std::ffi::CStr, contents: &[u8]) -> std::io::Result<()> {
+    // SAFETY: `path` is NUL-terminated and `contents` outlives the write.
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        libc::close(fd);
+        if written == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn isolate_network(_cmd: &mut std::process::Command) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "running a command without network access needs Linux or exec.backend: docker",
+    ))
+}
+
+/// Kill `child` and, on Unix, the process group it leads (see
+/// `StdioPolicy::RedirectForShellTool`).
+pub(crate) fn kill_child_tree(child: &mut Child) -> std::io::Result<()> {
+    if let Some(pid) = child.id() {
+        kill_process_group(pid);
+    }
+    child.start_kill()
+}
+
+/// Kill every process still in the group led by `pgid`. The leader itself may
+/// already have exited and been reaped: background jobs it left behind keep
+/// the group alive (and its pipes open) until they are killed here.
+pub(crate) fn kill_process_group(#[cfg_attr(not(unix), allow(unused_variables))] pgid: u32) {
+    #[cfg(unix)]
+    // SAFETY: killpg has no memory-safety preconditions. The group id is the
+    // leader's pid because it was spawned with `process_group(0)`, and a pid
+    // is not recycled while a process group with that id still exists.
+    unsafe {
+        libc::killpg(pgid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+/// A command running under a pseudo-terminal, see [`spawn_pty_child`].
+pub(crate) struct PtyChild {
+    pub(crate) child: Box<dyn portable_pty::Child + Send + Sync>,
+    /// The terminal's output: stdout and stderr merged, escape sequences intact.
+    pub(crate) reader: Box<dyn std::io::Read + Send>,
+    /// Keeps the terminal open until its output has been read.
+    pub(crate) _master: Box<dyn portable_pty::MasterPty + Send>,
+}
+
+/// Like [`spawn_child_async`] with `StdioPolicy::RedirectForShellTool`, but
+/// the command's stdin, stdout and stderr are the slave end of a new
+/// pseudo-terminal, for test harnesses and progress bars that behave
+/// differently without a TTY.
+///
+/// The child leads a session (and process group) of its own, so
+/// [`kill_process_group`] takes down everything it started. Limits are
+/// attached once the child is running; see `LimitGuard::attach_pid`.
+pub(crate) fn spawn_pty_child(
+    program: PathBuf,
+    args: Vec<String>,
+    cwd: PathBuf,
+    sandbox_policy: &SandboxPolicy,
+    env: HashMap<String, String>,
+    limits: &ResourceLimits,
+) -> std::io::Result<(PtyChild, LimitGuard)> {
+    trace!("spawn_pty_child: {program:?} {args:?} {cwd:?} {sandbox_policy:?} {env:?}");
+
+    let pair = portable_pty::native_pty_system()
+        .openpty(portable_pty::PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(std::io::Error::other)?;
+
+    let mut cmd = portable_pty::CommandBuilder::new(&program);
+    cmd.args(args);
+    cmd.cwd(cwd);
+    cmd.env_clear();
+    // Without TERM most tools assume a dumb terminal and drop their colors
+    // and progress bars, which is what running under a PTY is meant to keep.
+    if !env.contains_key("TERM") {
+        cmd.env("TERM", "xterm-256color");
+    }
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+    if !sandbox_policy.has_full_network_access() {
+        // portable_pty spawns without a pre_exec hook to enter a namespace from
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "commands without network access cannot run under a pseudo-terminal",
+        ));
+    }
+
+    let child = pair.slave.spawn_command(cmd).map_err(std::io::Error::other)?;
+    // Only the child may hold the slave end, or reads from the master never
+    // see the end of the output.
+    drop(pair.slave);
+    let guard = match child.process_id() {
+        Some(pid) => LimitGuard::attach_pid(limits, pid),
+        None => LimitGuard::default(),
+    };
+    let reader = pair.master.try_clone_reader().map_err(std::io::Error::other)?;
+    Ok((
+        PtyChild {
+            child,
+            reader,
+            _master: pair.master,
+        },
+        guard,
+    ))
 }
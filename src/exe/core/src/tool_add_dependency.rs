@@ -0,0 +1,29 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub struct AddDependencyArgs {
+    /// pip package name, e.g. `qiskit-aer`
+    pub package: String,
+    /// Exact version to pin, e.g. `0.15.1`; omit to use the version qernel recommends
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// Returns the `add_dependency` function tool the model uses to add a pinned package to
+/// the project's requirements.txt.
+pub fn create_add_dependency_tool() -> OpenAiTool {
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "add_dependency".to_string(),
+        description: r#"Adds a Python package, pinned to an exact version, to the project's requirements.txt and installs it into the project environment.
+Use it when the code imports a package that is not installed. Do not edit requirements.txt with a patch.
+"#
+        .to_string(),
+        strict: false,
+        parameters: JsonSchema::for_type::<AddDependencyArgs>(),
+    })
+}
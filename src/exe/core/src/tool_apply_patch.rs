@@ -1,6 +1,5 @@
 use serde::Deserialize;
 use serde::Serialize;
-use std::collections::BTreeMap;
 
 use crate::openai_tools::FreeformTool;
 use crate::openai_tools::FreeformToolFormat;
@@ -17,6 +16,13 @@ pub enum ApplyPatchToolType {
     Function,
 }
 
+/// Arguments of the JSON `apply_patch` tool
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub struct ApplyPatchArgs {
+    /// The entire contents of the apply_patch command
+    pub input: String,
+}
+
 /// Returns a custom tool that can be used to edit files. Well-suited for GPT-5 models
 /// https://platform.openai.com/docs/guides/function-calling#custom-tools
 pub fn create_apply_patch_freeform_tool() -> OpenAiTool {
@@ -31,17 +37,167 @@ pub fn create_apply_patch_freeform_tool() -> OpenAiTool {
     })
 }
 
+/// Where a freeform `apply_patch` input departs from [`APPLY_PATCH_LARK_GRAMMAR`];
+/// `line` and `column` are 1-based
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for GrammarError {}
+
+const FILE_OPS: &str = "`*** Add File: `, `*** Delete File: `, `*** Update File: `";
+
+/// Check a freeform `apply_patch` input against the grammar the tool declares, rule for rule
+/// as written in `tool_apply_patch.lark`. The patch is not parsed into hunks or applied;
+/// this only finds the first place a model's output stops following the syntax.
+pub fn validate_freeform_patch(input: &str) -> Result<(), GrammarError> {
+    let lines: Vec<&str> = input.split('\n').collect();
+    let error = |line: usize, column: usize, message: String| {
+        Err(GrammarError {
+            line: line + 1,
+            column,
+            message,
+        })
+    };
+    // Every rule but `end_patch` ends its line with LF, i.e. is not the last split piece
+    let terminated = |i: usize| i + 1 < lines.len();
+    let truncated = |i: usize| {
+        error(
+            i,
+            lines[i].chars().count() + 1,
+            "the patch ends here, before `*** End Patch`".to_string(),
+        )
+    };
+    let filename = |i: usize, marker: &str| -> Result<(), GrammarError> {
+        if lines[i].len() == marker.len() {
+            return error(i, marker.chars().count() + 1, format!("expected a file name after `{}`", marker.trim_end()));
+        }
+        if !terminated(i) {
+            return truncated(i);
+        }
+        Ok(())
+    };
+
+    // start: begin_patch hunk+ end_patch
+    if lines[0] != "*** Begin Patch" {
+        return error(0, 1, format!("expected `*** Begin Patch`, found `{}`", lines[0]));
+    }
+    if !terminated(0) {
+        return truncated(0);
+    }
+    let mut i = 1;
+    let mut hunks = 0;
+    loop {
+        let Some(&line) = lines.get(i) else {
+            return truncated(i - 1);
+        };
+        if line == "*** End Patch" {
+            if hunks == 0 {
+                return error(i, 1, format!("expected at least one file operation ({FILE_OPS}) before `*** End Patch`"));
+            }
+            // end_patch: "*** End Patch" LF?
+            let rest = &lines[i + 1..];
+            if rest.is_empty() || rest == [""] {
+                return Ok(());
+            }
+            return error(i + 1, 1, "unexpected text after `*** End Patch`".to_string());
+        }
+        hunks += 1;
+        if line.starts_with("*** Add File: ") {
+            // add_hunk: "*** Add File: " filename LF add_line+
+            filename(i, "*** Add File: ")?;
+            i += 1;
+            let first = i;
+            while lines.get(i).is_some_and(|l| l.starts_with('+')) {
+                if !terminated(i) {
+                    return truncated(i);
+                }
+                i += 1;
+            }
+            if i == first {
+                return match lines.get(i) {
+                    Some(l) if terminated(i) || !l.is_empty() => error(
+                        i,
+                        1,
+                        format!("expected a `+` line with the new file's contents, found `{l}`"),
+                    ),
+                    _ => truncated(i - 1),
+                };
+            }
+        } else if line.starts_with("*** Delete File: ") {
+            // delete_hunk: "*** Delete File: " filename LF
+            filename(i, "*** Delete File: ")?;
+            i += 1;
+        } else if line.starts_with("*** Update File: ") {
+            // update_hunk: "*** Update File: " filename LF change_move? change?
+            filename(i, "*** Update File: ")?;
+            i += 1;
+            if lines.get(i).is_some_and(|l| l.starts_with("*** Move to: ")) {
+                filename(i, "*** Move to: ")?;
+                i += 1;
+            }
+            // change: (change_context | change_line)+ eof_line?
+            let mut changes = 0;
+            while let Some(&l) = lines.get(i) {
+                if l == "@@" || l.starts_with(' ') || l.starts_with('+') || l.starts_with('-') {
+                    // change_context / change_line
+                } else if let Some(context) = l.strip_prefix("@@") {
+                    if context.len() <= 1 || !context.starts_with(' ') {
+                        return error(i, 3, "expected a line break or a space and a context line after `@@`".to_string());
+                    }
+                } else if l == "*** End of File" && changes > 0 {
+                    if !terminated(i) {
+                        return truncated(i);
+                    }
+                    i += 1;
+                    break;
+                } else {
+                    break;
+                }
+                if !terminated(i) {
+                    return truncated(i);
+                }
+                changes += 1;
+                i += 1;
+            }
+            if let Some(&l) = lines.get(i)
+                && !is_hunk_boundary(l)
+                && (terminated(i) || !l.is_empty())
+            {
+                let expected = if changes == 0 {
+                    "`*** Move to: `, `@@`, a line starting with ` `, `-` or `+`"
+                } else {
+                    "`@@`, a line starting with ` `, `-` or `+`, `*** End of File`"
+                };
+                return error(i, 1, format!("expected {expected}, {FILE_OPS} or `*** End Patch`, found `{l}`"));
+            }
+        } else if terminated(i) || !line.is_empty() {
+            return error(i, 1, format!("expected {FILE_OPS} or `*** End Patch`, found `{line}`"));
+        } else {
+            return truncated(i - 1);
+        }
+    }
+}
+
+/// A line that starts the next hunk or ends the patch
+fn is_hunk_boundary(line: &str) -> bool {
+    line == "*** End Patch"
+        || ["*** Add File: ", "*** Delete File: ", "*** Update File: "]
+            .iter()
+            .any(|marker| line.starts_with(marker))
+}
+
 /// Returns a json tool that can be used to edit files. Should only be used with gpt-oss models
 pub fn create_apply_patch_json_tool() -> OpenAiTool {
-    let mut properties = BTreeMap::new();
-    properties.insert(
-        "input".to_string(),
-        JsonSchema::String {
-            r#type: "string".to_string(),
-            description: Some(r#"The entire contents of the apply_patch command"#.to_string()),
-        },
-    );
-
     OpenAiTool::Function(ResponsesApiTool {
         name: "apply_patch".to_string(),
         description: r#"Use the `apply_patch` tool to edit files.
@@ -114,11 +270,54 @@ It is important to remember:
 "#
         .to_string(),
         strict: false,
-        parameters: JsonSchema::Object {
-            r#type: "object".to_string(),
-            properties,
-            required: Some(vec!["input".to_string()]),
-            additional_properties: Some(false),
-        },
+        parameters: JsonSchema::for_type::<ApplyPatchArgs>(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn error_at(input: &str) -> (usize, usize) {
+        let e = validate_freeform_patch(input).unwrap_err();
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn accepts_every_kind_of_hunk() {
+        let patch = "*** Begin Patch
+*** Add File: hello.txt
++Hello world
+*** Update File: src/app.py
+*** Move to: src/main.py
+@@ def greet():
+-print(\"Hi\")
++print(\"Hello, world!\")
+ 
+*** End of File
+*** Delete File: obsolete.txt
+*** End Patch
+";
+        assert_eq!(validate_freeform_patch(patch), Ok(()));
+        assert_eq!(validate_freeform_patch(patch.trim_end()), Ok(()));
+    }
+
+    #[test]
+    fn points_at_the_offending_line_and_column() {
+        assert_eq!(error_at("Begin Patch\n*** End Patch"), (1, 1));
+        assert_eq!(error_at("*** Begin Patch\n*** End Patch"), (2, 1));
+        assert_eq!(error_at("*** Begin Patch\n*** Add File: \n+x\n*** End Patch"), (2, 15));
+        assert_eq!(error_at("*** Begin Patch\n*** Add File: a\n*** End Patch"), (3, 1));
+        assert_eq!(error_at("*** Begin Patch\n*** Update File: a\n@@\nfoo\n*** End Patch"), (4, 1));
+        assert_eq!(error_at("*** Begin Patch\n*** Update File: a\n@@ \n-x\n*** End Patch"), (3, 3));
+        assert_eq!(error_at("*** Begin Patch\n*** Delete File: a\n*** End Patch\nmore"), (4, 1));
+    }
+
+    #[test]
+    fn reports_a_truncated_patch_at_its_end() {
+        let e = validate_freeform_patch("*** Begin Patch\n*** Update File: a\n@@\n-x").unwrap_err();
+        assert_eq!((e.line, e.column), (4, 3));
+        assert_eq!(e.message, "the patch ends here, before `*** End Patch`");
+    }
+}
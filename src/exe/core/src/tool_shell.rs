@@ -0,0 +1,26 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub struct ShellArgs {
+    /// The command line to run, e.g. `python -m pytest -x src/tests.py`
+    pub command: String,
+}
+
+/// Returns the `shell` function tool the model uses to run a command in the project
+/// directory and read its output.
+pub fn create_shell_tool() -> OpenAiTool {
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "shell".to_string(),
+        description: r#"Runs a command in the project directory and returns its exit code, stdout and stderr.
+Use it to inspect files, run the tests or try out code before and after patching.
+"#
+        .to_string(),
+        strict: false,
+        parameters: JsonSchema::for_type::<ShellArgs>(),
+    })
+}
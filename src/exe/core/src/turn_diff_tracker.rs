@@ -313,9 +313,12 @@ impl TurnDiffTracker {
             aggregated.push_str(&format!("new mode {current_mode}\n"));
         }
 
-        let left_text = left_bytes.and_then(|b| std::str::from_utf8(b).ok());
+        let left_text = left_bytes
+            .filter(|b| !is_binary(b))
+            .and_then(|b| std::str::from_utf8(b).ok());
         let right_text = right_bytes
             .as_deref()
+            .filter(|b| !is_binary(b))
             .and_then(|b| std::str::from_utf8(b).ok());
 
         let can_text_diff = matches!(
@@ -362,12 +365,29 @@ impl TurnDiffTracker {
             };
             aggregated.push_str(&format!("--- {old_header}\n"));
             aggregated.push_str(&format!("+++ {new_header}\n"));
-            aggregated.push_str("Binary files differ\n");
+            aggregated.push_str(&binary_summary(left_bytes, right_bytes.as_deref()));
         }
         aggregated
     }
 }
 
+/// Like git: content with a NUL byte near the start, or that is not UTF-8, is not diffed as text.
+fn is_binary(data: &[u8]) -> bool {
+    data[..data.len().min(8000)].contains(&0) || std::str::from_utf8(data).is_err()
+}
+
+/// The line shown instead of hunks for a binary file, with its size before and after.
+fn binary_summary(left: Option<&[u8]>, right: Option<&[u8]>) -> String {
+    match (left, right) {
+        (None, Some(r)) => format!("Binary file added ({} bytes)\n", r.len()),
+        (Some(l), None) => format!("Binary file deleted ({} bytes)\n", l.len()),
+        (Some(l), Some(r)) if l.len() != r.len() => {
+            format!("Binary file changed ({} -> {} bytes)\n", l.len(), r.len())
+        }
+        (_, r) => format!("Binary file changed ({} bytes)\n", r.map_or(0, <[u8]>::len)),
+    }
+}
+
 /// Compute the Git SHA-1 blob object ID for the given content (bytes).
 fn git_blob_sha1_hex_bytes(data: &[u8]) -> Output<sha1::Sha1> {
     // Git blob hash is sha1 of: "blob <len>\0<data>"
@@ -580,9 +600,7 @@ index {ZERO_OID}..{right_oid}
         let mut acc = TurnDiffTracker::new();
         let del_changes = HashMap::from([(
             file.clone(),
-            FileChange::Delete {
-                content: "x\n".to_string(),
-            },
+            FileChange::Delete,
         )]);
         acc.on_patch_begin(&del_changes);
 
@@ -748,9 +766,7 @@ index {left_oid}..{right_oid}
         // Next: introduce a brand-new path b.txt into baseline snapshots via a delete change.
         let del_b = HashMap::from([(
             b.clone(),
-            FileChange::Delete {
-                content: "z\n".to_string(),
-            },
+            FileChange::Delete,
         )]);
         acc.on_patch_begin(&del_b);
         // Simulate apply: delete b.txt.
@@ -819,13 +835,48 @@ index {left_oid_b}..{ZERO_OID}
 index {left_oid}..{right_oid}
 --- a/<TMP>/bin.dat
 +++ b/<TMP>/bin.dat
-Binary files differ
+Binary file changed (4 bytes)
 "#
             )
         };
         assert_eq!(diff, expected);
     }
 
+    #[test]
+    fn binary_file_with_nul_bytes_added() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("fixture.npz");
+        // Valid UTF-8, but the NUL bytes mark it as binary
+        let bytes: Vec<u8> = b"PK\x03\x04\x00\x00weights\x00".to_vec();
+
+        let mut acc = TurnDiffTracker::new();
+        let add_changes = HashMap::from([(
+            file.clone(),
+            FileChange::Add {
+                content: String::new(),
+            },
+        )]);
+        acc.on_patch_begin(&add_changes);
+        fs::write(&file, &bytes).unwrap();
+
+        let diff = acc.get_unified_diff().unwrap().unwrap();
+        let diff = normalize_diff_for_test(&diff, dir.path());
+        let expected = {
+            let mode = file_mode_for_path(&file).unwrap_or(FileMode::Regular);
+            let right_oid = format!("{:x}", git_blob_sha1_hex_bytes(&bytes));
+            format!(
+                r#"diff --git a/<TMP>/fixture.npz b/<TMP>/fixture.npz
+new file mode {mode}
+index {ZERO_OID}..{right_oid}
+--- {DEV_NULL}
++++ b/<TMP>/fixture.npz
+Binary file added (14 bytes)
+"#,
+            )
+        };
+        assert_eq!(diff, expected);
+    }
+
     #[test]
     fn filenames_with_spaces_add_and_update() {
         let mut acc = TurnDiffTracker::new();
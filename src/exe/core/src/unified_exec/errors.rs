@@ -9,6 +9,8 @@ pub enum UnifiedExecError {
     },
     #[error("Unknown session id {session_id}")]
     UnknownSessionId { session_id: i32 },
+    #[error("Too many unified exec sessions: {max} are already open; close one first")]
+    TooManySessions { max: usize },
     #[error("failed to write to stdin")]
     WriteToStdin,
     #[error("missing command line for unified exec request")]
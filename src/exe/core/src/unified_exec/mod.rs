@@ -10,6 +10,8 @@ use std::sync::Mutex as StdMutex;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI32;
 use std::sync::atomic::Ordering;
+use serde::Deserialize;
+use serde::Serialize;
 use tokio::sync::Mutex;
 use tokio::sync::Notify;
 use tokio::sync::mpsc;
@@ -18,38 +20,107 @@ use tokio::time::Duration;
 use tokio::time::Instant;
 
 // Minimal inline ExecCommandSession for this build
-use tokio::sync::broadcast;
-#[derive(Debug)]
+type Killer = Arc<StdMutex<Box<dyn portable_pty::ChildKiller + Send + Sync>>>;
+
 pub struct ExecCommandSession {
     writer_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    killer: Killer,
     exit_status: Arc<AtomicBool>,
 }
+impl std::fmt::Debug for ExecCommandSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecCommandSession")
+            .field("exit_status", &self.exit_status)
+            .finish_non_exhaustive()
+    }
+}
 impl ExecCommandSession {
     pub fn new(
         writer_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
-        output_tx: broadcast::Sender<Vec<u8>>,
-        _killer: Box<dyn portable_pty::ChildKiller + Send + Sync>,
+        output_rx: mpsc::Receiver<Vec<u8>>,
+        killer: Box<dyn portable_pty::ChildKiller + Send + Sync>,
         _reader_handle: tokio::task::JoinHandle<()>,
         _writer_handle: tokio::task::JoinHandle<()>,
         _wait_handle: tokio::task::JoinHandle<()>,
         exit_status: Arc<AtomicBool>,
-    ) -> (Self, broadcast::Receiver<Vec<u8>>) {
-        let initial_output_rx = output_tx.subscribe();
+    ) -> (Self, mpsc::Receiver<Vec<u8>>) {
         (
             Self {
                 writer_tx,
+                killer: Arc::new(StdMutex::new(killer)),
                 exit_status,
             },
-            initial_output_rx,
+            output_rx,
         )
     }
     pub fn writer_sender(&self) -> tokio::sync::mpsc::Sender<Vec<u8>> { self.writer_tx.clone() }
     pub fn has_exited(&self) -> bool { self.exit_status.load(Ordering::SeqCst) }
 }
 
-fn truncate_middle(input: &str, _max_bytes: usize) -> (String, Option<usize>) {
-    // Minimal fallback: do not actually truncate in this build.
-    (input.to_string(), None)
+/// Kill the session's child process; the wait task then marks the session exited
+fn kill(killer: &Killer) {
+    if let Ok(mut killer) = killer.lock() {
+        let _ = killer.kill();
+    }
+}
+
+/// A session that is dropped, whether closed, expired or left open when the manager goes
+/// away, takes its process with it
+impl Drop for ExecCommandSession {
+    fn drop(&mut self) {
+        if !self.has_exited() {
+            kill(&self.killer);
+        }
+    }
+}
+
+/// Output collected for one request, held to a byte cap by keeping its first and last
+/// halves and counting what falls between them
+#[derive(Debug)]
+struct CappedOutput {
+    max_bytes: usize,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    omitted: usize,
+}
+
+impl CappedOutput {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            head: Vec::with_capacity(max_bytes.min(4096)),
+            tail: VecDeque::new(),
+            omitted: 0,
+        }
+    }
+
+    fn push(&mut self, mut chunk: &[u8]) {
+        let head_room = (self.max_bytes / 2).saturating_sub(self.head.len());
+        if head_room > 0 {
+            let take = head_room.min(chunk.len());
+            self.head.extend_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+        }
+        let tail_max = self.max_bytes - self.max_bytes / 2;
+        self.tail.extend(chunk);
+        let excess = self.tail.len().saturating_sub(tail_max);
+        self.tail.drain(..excess);
+        self.omitted += excess;
+    }
+
+    /// The collected text, with a marker where bytes were dropped
+    fn into_string(mut self) -> String {
+        let head = String::from_utf8_lossy(&self.head);
+        let tail = String::from_utf8_lossy(self.tail.make_contiguous()).into_owned();
+        if self.omitted == 0 {
+            format!("{head}{tail}")
+        } else {
+            format!(
+                "{head}\n[... {} bytes of output omitted ...]\n{tail}",
+                self.omitted
+            )
+        }
+    }
 }
 
 mod errors;
@@ -58,13 +129,22 @@ pub use errors::UnifiedExecError;
 
 const DEFAULT_TIMEOUT_MS: u64 = 1_000;
 const MAX_TIMEOUT_MS: u64 = 60_000;
+const DEFAULT_OUTPUT_MAX_BYTES: usize = 16 * 1024; // 16 KiB
 const UNIFIED_EXEC_OUTPUT_MAX_BYTES: usize = 128 * 1024; // 128 KiB
+/// Chunks in flight from a session's pty reader; when they back up the reader stops
+/// reading and the command blocks on its writes
+const OUTPUT_CHANNEL_CAPACITY: usize = 32;
+const DEFAULT_IDLE_TTL: Duration = Duration::from_secs(30 * 60);
+const DEFAULT_MAX_SESSIONS: usize = 16;
 
 #[derive(Debug)]
 pub struct UnifiedExecRequest<'a> {
     pub session_id: Option<i32>,
     pub input_chunks: &'a [String],
     pub timeout_ms: Option<u64>,
+    /// Most bytes of output to return; the middle of longer output is replaced by a marker.
+    /// Defaults to 16 KiB and is clamped to 128 KiB.
+    pub max_output_bytes: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -73,19 +153,79 @@ pub struct UnifiedExecResult {
     pub output: String,
 }
 
+/// Bounds on the sessions a [`UnifiedExecSessionManager`] keeps open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnifiedExecLimits {
+    /// A session that receives no request for this long is killed and removed. Keep it
+    /// above the longest request timeout so a session is not reaped mid-request.
+    pub idle_ttl: Duration,
+    /// Requests that would open a session beyond this many fail with
+    /// [`UnifiedExecError::TooManySessions`]
+    pub max_sessions: usize,
+}
+
+impl Default for UnifiedExecLimits {
+    fn default() -> Self {
+        Self {
+            idle_ttl: DEFAULT_IDLE_TTL,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+        }
+    }
+}
+
+/// An open session, as returned by [`UnifiedExecSessionManager::list_sessions`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedExecSessionInfo {
+    pub session_id: i32,
+    /// The command line the session was opened with
+    pub command: Vec<String>,
+    /// Time since the session was opened
+    pub age: Duration,
+    /// Time since the session's last request
+    pub idle: Duration,
+}
+
+/// Session counts over a manager's lifetime
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnifiedExecMetrics {
+    /// Sessions still running after their first request, and so kept open
+    pub opened: u64,
+    /// Sessions ended with [`UnifiedExecSessionManager::close_session`]
+    pub closed: u64,
+    /// Sessions killed after `idle_ttl` without a request
+    pub expired: u64,
+    /// Sessions whose process exited on its own
+    pub exited: u64,
+    /// Requests refused because `max_sessions` were open
+    pub rejected: u64,
+    /// Most sessions open at once
+    pub peak_concurrent: usize,
+}
+
 #[derive(Debug, Default)]
 pub struct UnifiedExecSessionManager {
     next_session_id: AtomicI32,
     sessions: Mutex<HashMap<i32, ManagedUnifiedExecSession>>,
+    limits: UnifiedExecLimits,
+    metrics: StdMutex<UnifiedExecMetrics>,
 }
 
 #[derive(Debug)]
 struct ManagedUnifiedExecSession {
     session: ExecCommandSession,
+    command: Vec<String>,
+    opened_at: Instant,
+    last_used: Arc<StdMutex<Instant>>,
+    /// Set by `idle_task` when it kills the session for idling past the TTL
+    expired: Arc<AtomicBool>,
+    idle_task: JoinHandle<()>,
     output_buffer: OutputBuffer,
     /// Notifies waiters whenever new output has been appended to
     /// `output_buffer`, allowing clients to poll for fresh data.
     output_notify: Arc<Notify>,
+    /// Notified when a request drains `output_buffer`, waking an output task that stopped
+    /// forwarding because the buffer was full
+    drained_notify: Arc<Notify>,
     output_task: JoinHandle<()>,
 }
 
@@ -129,35 +269,96 @@ impl OutputBufferState {
 }
 
 type OutputBuffer = Arc<Mutex<OutputBufferState>>;
-type OutputHandles = (OutputBuffer, Arc<Notify>);
+type OutputHandles = (OutputBuffer, Arc<Notify>, Arc<Notify>);
 
 impl ManagedUnifiedExecSession {
     fn new(
         session: ExecCommandSession,
-        initial_output_rx: tokio::sync::broadcast::Receiver<Vec<u8>>,
+        output_rx: mpsc::Receiver<Vec<u8>>,
+        command: Vec<String>,
+        idle_ttl: Duration,
     ) -> Self {
         let output_buffer = Arc::new(Mutex::new(OutputBufferState::default()));
         let output_notify = Arc::new(Notify::new());
-        let mut receiver = initial_output_rx;
+        let drained_notify = Arc::new(Notify::new());
+        let mut receiver = output_rx;
         let buffer_clone = Arc::clone(&output_buffer);
         let notify_clone = Arc::clone(&output_notify);
+        let drained_clone = Arc::clone(&drained_notify);
         let output_task = tokio::spawn(async move {
-            while let Ok(chunk) = receiver.recv().await {
-                let mut guard = buffer_clone.lock().await;
-                guard.push_chunk(chunk);
-                drop(guard);
+            while let Some(chunk) = receiver.recv().await {
+                // Hold the chunk until a request drains a full buffer, so the reader backs up
+                // instead of the buffer dropping output nobody has read
+                loop {
+                    let mut guard = buffer_clone.lock().await;
+                    if guard.total_bytes < UNIFIED_EXEC_OUTPUT_MAX_BYTES {
+                        guard.push_chunk(chunk);
+                        break;
+                    }
+                    drop(guard);
+                    drained_clone.notified().await;
+                }
                 notify_clone.notify_waiters();
             }
         });
 
+        let opened_at = Instant::now();
+        let last_used = Arc::new(StdMutex::new(opened_at));
+        let expired = Arc::new(AtomicBool::new(false));
+        let idle_task = tokio::spawn({
+            let last_used = Arc::clone(&last_used);
+            let expired = Arc::clone(&expired);
+            let killer = Arc::clone(&session.killer);
+            async move {
+                loop {
+                    let Some(last) = last_used.lock().ok().map(|last| *last) else {
+                        return;
+                    };
+                    if last.elapsed() >= idle_ttl {
+                        expired.store(true, Ordering::SeqCst);
+                        kill(&killer);
+                        return;
+                    }
+                    tokio::time::sleep_until(last + idle_ttl).await;
+                }
+            }
+        });
+
         Self {
             session,
+            command,
+            opened_at,
+            last_used,
+            expired,
+            idle_task,
             output_buffer,
             output_notify,
+            drained_notify,
             output_task,
         }
     }
 
+    /// Restart the idle clock
+    fn touch(&self) {
+        if let Ok(mut last) = self.last_used.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    fn info(&self, session_id: i32) -> UnifiedExecSessionInfo {
+        let idle = self
+            .last_used
+            .lock()
+            .map(|last| last.elapsed())
+            .unwrap_or_default();
+        UnifiedExecSessionInfo {
+            session_id,
+            command: self.command.clone(),
+            age: self.opened_at.elapsed(),
+            idle,
+        }
+    }
+
     fn writer_sender(&self) -> mpsc::Sender<Vec<u8>> {
         self.session.writer_sender()
     }
@@ -166,6 +367,7 @@ impl ManagedUnifiedExecSession {
         (
             Arc::clone(&self.output_buffer),
             Arc::clone(&self.output_notify),
+            Arc::clone(&self.drained_notify),
         )
     }
 
@@ -177,6 +379,7 @@ impl ManagedUnifiedExecSession {
 impl Drop for ManagedUnifiedExecSession {
     fn drop(&mut self) {
         self.output_task.abort();
+        self.idle_task.abort();
     }
 }
 
@@ -195,28 +398,36 @@ impl UnifiedExecSessionManager {
             Some(requested) => (requested, None),
             None => (DEFAULT_TIMEOUT_MS, None),
         };
+        let (max_output_bytes, output_warning) = match request.max_output_bytes {
+            Some(requested) if requested > UNIFIED_EXEC_OUTPUT_MAX_BYTES => (
+                UNIFIED_EXEC_OUTPUT_MAX_BYTES,
+                Some(format!(
+                    "Warning: requested output cap of {requested} bytes exceeds maximum of {UNIFIED_EXEC_OUTPUT_MAX_BYTES} bytes; clamping to {UNIFIED_EXEC_OUTPUT_MAX_BYTES} bytes.\n"
+                )),
+            ),
+            Some(requested) => (requested, None),
+            None => (DEFAULT_OUTPUT_MAX_BYTES, None),
+        };
 
         let mut new_session: Option<ManagedUnifiedExecSession> = None;
         let session_id;
         let writer_tx;
         let output_buffer;
         let output_notify;
+        let drained_notify;
 
         if let Some(existing_id) = request.session_id {
             let mut sessions = self.sessions.lock().await;
+            self.reap(&mut sessions);
             match sessions.get(&existing_id) {
                 Some(session) => {
-                    if session.has_exited() {
-                        sessions.remove(&existing_id);
-                        return Err(UnifiedExecError::UnknownSessionId {
-                            session_id: existing_id,
-                        });
-                    }
-                    let (buffer, notify) = session.output_handles();
+                    session.touch();
+                    let (buffer, notify, drained) = session.output_handles();
                     session_id = existing_id;
                     writer_tx = session.writer_sender();
                     output_buffer = buffer;
                     output_notify = notify;
+                    drained_notify = drained;
                 }
                 None => {
                     return Err(UnifiedExecError::UnknownSessionId {
@@ -226,14 +437,29 @@ impl UnifiedExecSessionManager {
             }
             drop(sessions);
         } else {
+            let mut sessions = self.sessions.lock().await;
+            self.reap(&mut sessions);
+            if sessions.len() >= self.limits.max_sessions {
+                self.record(|metrics| metrics.rejected += 1);
+                return Err(UnifiedExecError::TooManySessions {
+                    max: self.limits.max_sessions,
+                });
+            }
+            drop(sessions);
             let command = request.input_chunks.to_vec();
             let new_id = self.next_session_id.fetch_add(1, Ordering::SeqCst);
-            let (session, initial_output_rx) = create_unified_exec_session(&command).await?;
-            let managed_session = ManagedUnifiedExecSession::new(session, initial_output_rx);
-            let (buffer, notify) = managed_session.output_handles();
+            let (session, output_rx) = create_unified_exec_session(&command).await?;
+            let managed_session = ManagedUnifiedExecSession::new(
+                session,
+                output_rx,
+                command,
+                self.limits.idle_ttl,
+            );
+            let (buffer, notify, drained) = managed_session.output_handles();
             writer_tx = managed_session.writer_sender();
             output_buffer = buffer;
             output_notify = notify;
+            drained_notify = drained;
             session_id = new_id;
             new_session = Some(managed_session);
         };
@@ -246,7 +472,7 @@ impl UnifiedExecSessionManager {
             }
         }
 
-        let mut collected: Vec<u8> = Vec::with_capacity(4096);
+        let mut collected = CappedOutput::new(max_output_bytes);
         let start = Instant::now();
         let deadline = start + Duration::from_millis(timeout_ms);
 
@@ -275,9 +501,10 @@ impl UnifiedExecSessionManager {
                 }
                 continue;
             }
+            drained_notify.notify_one();
 
             for chunk in drained_chunks {
-                collected.extend_from_slice(&chunk);
+                collected.push(&chunk);
             }
 
             if Instant::now() >= deadline {
@@ -285,29 +512,23 @@ impl UnifiedExecSessionManager {
             }
         }
 
-        let (output, _maybe_tokens) = truncate_middle(
-            &String::from_utf8_lossy(&collected),
-            UNIFIED_EXEC_OUTPUT_MAX_BYTES,
-        );
-        let output = if let Some(warning) = timeout_warning {
-            format!("{warning}{output}")
-        } else {
-            output
-        };
+        let warnings: String = [timeout_warning, output_warning]
+            .into_iter()
+            .flatten()
+            .collect();
+        let output = format!("{warnings}{}", collected.into_string());
 
         let should_store_session = if let Some(session) = new_session.as_ref() {
             !session.has_exited()
         } else if request.session_id.is_some() {
             let mut sessions = self.sessions.lock().await;
-            if let Some(existing) = sessions.get(&session_id) {
-                if existing.has_exited() {
-                    sessions.remove(&session_id);
-                    false
-                } else {
+            self.reap(&mut sessions);
+            match sessions.get(&session_id) {
+                Some(existing) => {
+                    existing.touch();
                     true
                 }
-            } else {
-                false
+                None => false,
             }
         } else {
             true
@@ -315,7 +536,13 @@ impl UnifiedExecSessionManager {
 
         if should_store_session {
             if let Some(session) = new_session {
-                self.sessions.lock().await.insert(session_id, session);
+                let mut sessions = self.sessions.lock().await;
+                sessions.insert(session_id, session);
+                let open = sessions.len();
+                self.record(|metrics| {
+                    metrics.opened += 1;
+                    metrics.peak_concurrent = metrics.peak_concurrent.max(open);
+                });
             }
             Ok(UnifiedExecResult {
                 session_id: Some(session_id),
@@ -328,6 +555,72 @@ impl UnifiedExecSessionManager {
             })
         }
     }
+
+    /// A manager that holds its sessions to `limits`
+    pub fn with_limits(limits: UnifiedExecLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// The open sessions, by id. Sessions that have exited or expired are removed first.
+    pub async fn list_sessions(&self) -> Vec<UnifiedExecSessionInfo> {
+        let mut sessions = self.sessions.lock().await;
+        self.reap(&mut sessions);
+        let mut infos: Vec<UnifiedExecSessionInfo> = sessions
+            .iter()
+            .map(|(id, session)| session.info(*id))
+            .collect();
+        infos.sort_by_key(|info| info.session_id);
+        infos
+    }
+
+    /// Kill a session's process and forget the session
+    pub async fn close_session(&self, session_id: i32) -> Result<(), UnifiedExecError> {
+        let mut sessions = self.sessions.lock().await;
+        self.reap(&mut sessions);
+        let session = sessions
+            .remove(&session_id)
+            .ok_or(UnifiedExecError::UnknownSessionId { session_id })?;
+        kill(&session.session.killer);
+        self.record(|metrics| metrics.closed += 1);
+        Ok(())
+    }
+
+    /// Session counts so far, for run summaries
+    pub async fn metrics(&self) -> UnifiedExecMetrics {
+        let mut sessions = self.sessions.lock().await;
+        self.reap(&mut sessions);
+        self.metrics
+            .lock()
+            .map(|metrics| *metrics)
+            .unwrap_or_default()
+    }
+
+    /// Drop sessions whose process has exited, counting those the idle TTL killed
+    fn reap(&self, sessions: &mut HashMap<i32, ManagedUnifiedExecSession>) {
+        sessions.retain(|_, session| {
+            if !session.has_exited() {
+                return true;
+            }
+            let expired = session.expired.load(Ordering::SeqCst);
+            self.record(|metrics| {
+                if expired {
+                    metrics.expired += 1;
+                } else {
+                    metrics.exited += 1;
+                }
+            });
+            false
+        });
+    }
+
+    fn record(&self, update: impl FnOnce(&mut UnifiedExecMetrics)) {
+        if let Ok(mut metrics) = self.metrics.lock() {
+            update(&mut metrics);
+        }
+    }
 }
 
 async fn create_unified_exec_session(
@@ -335,7 +628,7 @@ async fn create_unified_exec_session(
 ) -> Result<
     (
         ExecCommandSession,
-        tokio::sync::broadcast::Receiver<Vec<u8>>,
+        mpsc::Receiver<Vec<u8>>,
     ),
     UnifiedExecError,
 > {
@@ -367,20 +660,22 @@ async fn create_unified_exec_session(
     let killer = child.clone_killer();
 
     let (writer_tx, mut writer_rx) = mpsc::channel::<Vec<u8>>(128);
-    let (output_tx, _) = tokio::sync::broadcast::channel::<Vec<u8>>(256);
+    let (output_tx, output_rx) = mpsc::channel::<Vec<u8>>(OUTPUT_CHANNEL_CAPACITY);
 
     let mut reader = pair
         .master
         .try_clone_reader()
         .map_err(UnifiedExecError::create_session)?;
-    let output_tx_clone = output_tx.clone();
     let reader_handle = tokio::task::spawn_blocking(move || {
         let mut buf = [0u8; 8192];
         loop {
             match reader.read(&mut buf) {
                 Ok(0) => break,
+                // Blocks while the session's output is backed up
                 Ok(n) => {
-                    let _ = output_tx_clone.send(buf[..n].to_vec());
+                    if output_tx.blocking_send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
                 }
                 Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
                 Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
@@ -421,23 +716,21 @@ async fn create_unified_exec_session(
         wait_exit_status.store(true, Ordering::SeqCst);
     });
 
-    let (session, initial_output_rx) = ExecCommandSession::new(
+    let (session, output_rx) = ExecCommandSession::new(
         writer_tx,
-        output_tx,
+        output_rx,
         killer,
         reader_handle,
         writer_handle,
         wait_handle,
         exit_status,
     );
-    Ok((session, initial_output_rx))
+    Ok((session, output_rx))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[cfg(unix)]
-    use core_test_support::skip_if_sandbox;
 
     #[test]
     fn push_chunk_trims_only_excess_bytes() {
@@ -456,6 +749,61 @@ mod tests {
         assert_eq!(buffer.chunks.pop_back().unwrap(), vec![b'b']);
     }
 
+    #[test]
+    fn capped_output_keeps_head_and_tail_around_a_marker() {
+        let mut output = CappedOutput::new(8);
+        output.push(b"abcdef");
+        output.push(b"ghijkl");
+        assert_eq!(
+            output.into_string(),
+            "abcd\n[... 4 bytes of output omitted ...]\nijkl"
+        );
+
+        let mut output = CappedOutput::new(8);
+        output.push(b"abc");
+        output.push(b"de");
+        assert_eq!(output.into_string(), "abcde");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn runaway_output_is_capped_and_backed_up() -> Result<(), UnifiedExecError> {
+        skip_if_sandbox!(Ok(()));
+
+        let manager = UnifiedExecSessionManager::default();
+        let session_id = manager
+            .handle_request(UnifiedExecRequest {
+                session_id: None,
+                input_chunks: &["yes".to_string()],
+                timeout_ms: Some(200),
+                max_output_bytes: Some(1_000),
+            })
+            .await?
+            .session_id
+            .expect("expected session id");
+
+        // Nobody reads for a while: the buffer fills and the command blocks
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        {
+            let sessions = manager.sessions.lock().await;
+            let buffered = sessions[&session_id].output_buffer.lock().await.total_bytes;
+            assert!(buffered <= UNIFIED_EXEC_OUTPUT_MAX_BYTES);
+        }
+
+        let result = manager
+            .handle_request(UnifiedExecRequest {
+                session_id: Some(session_id),
+                input_chunks: &[],
+                timeout_ms: Some(200),
+                max_output_bytes: Some(1_000),
+            })
+            .await?;
+        assert!(result.output.contains("bytes of output omitted"));
+        assert!(result.output.len() < 1_100);
+
+        Ok(())
+    }
+
     #[cfg(unix)]
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn unified_exec_persists_across_requests_jif() -> Result<(), UnifiedExecError> {
@@ -468,6 +816,7 @@ mod tests {
                 session_id: None,
                 input_chunks: &["bash".to_string(), "-i".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         let session_id = open_shell.session_id.expect("expected session_id");
@@ -480,6 +829,7 @@ mod tests {
                     "CODEX_INTERACTIVE_SHELL_VAR=codex\n".to_string(),
                 ],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -488,6 +838,7 @@ mod tests {
                 session_id: Some(session_id),
                 input_chunks: &["echo $CODEX_INTERACTIVE_SHELL_VAR\n".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         assert!(out_2.output.contains("codex"));
@@ -507,6 +858,7 @@ mod tests {
                 session_id: None,
                 input_chunks: &["/bin/bash".to_string(), "-i".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         let session_a = shell_a.session_id.expect("expected session id");
@@ -516,6 +868,7 @@ mod tests {
                 session_id: Some(session_a),
                 input_chunks: &["export CODEX_INTERACTIVE_SHELL_VAR=codex\n".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -527,6 +880,7 @@ mod tests {
                     "$CODEX_INTERACTIVE_SHELL_VAR\n".to_string(),
                 ],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         assert!(!out_2.output.contains("codex"));
@@ -536,6 +890,7 @@ mod tests {
                 session_id: Some(session_a),
                 input_chunks: &["echo $CODEX_INTERACTIVE_SHELL_VAR\n".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         assert!(out_3.output.contains("codex"));
@@ -555,6 +910,7 @@ mod tests {
                 session_id: None,
                 input_chunks: &["bash".to_string(), "-i".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         let session_id = open_shell.session_id.expect("expected session id");
@@ -567,6 +923,7 @@ mod tests {
                     "CODEX_INTERACTIVE_SHELL_VAR=codex\n".to_string(),
                 ],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -575,6 +932,7 @@ mod tests {
                 session_id: Some(session_id),
                 input_chunks: &["sleep 5 && echo $CODEX_INTERACTIVE_SHELL_VAR\n".to_string()],
                 timeout_ms: Some(10),
+                max_output_bytes: None,
             })
             .await?;
         assert!(!out_2.output.contains("codex"));
@@ -587,6 +945,7 @@ mod tests {
                 session_id: Some(session_id),
                 input_chunks: &empty,
                 timeout_ms: Some(100),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -606,6 +965,7 @@ mod tests {
                 session_id: None,
                 input_chunks: &["echo".to_string(), "codex".to_string()],
                 timeout_ms: Some(120_000),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -627,6 +987,7 @@ mod tests {
                 session_id: None,
                 input_chunks: &["/bin/echo".to_string(), "codex".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -650,6 +1011,7 @@ mod tests {
                 session_id: None,
                 input_chunks: &["/bin/bash".to_string(), "-i".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
         let session_id = open_shell.session_id.expect("expected session id");
@@ -659,6 +1021,7 @@ mod tests {
                 session_id: Some(session_id),
                 input_chunks: &["exit\n".to_string()],
                 timeout_ms: Some(2_500),
+                max_output_bytes: None,
             })
             .await?;
 
@@ -669,6 +1032,7 @@ mod tests {
                 session_id: Some(session_id),
                 input_chunks: &[],
                 timeout_ms: Some(100),
+                max_output_bytes: None,
             })
             .await
             .expect_err("expected unknown session error");
@@ -684,4 +1048,102 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn sessions_can_be_listed_and_closed() -> Result<(), UnifiedExecError> {
+        skip_if_sandbox!(Ok(()));
+
+        let manager = UnifiedExecSessionManager::default();
+        let command = vec!["/bin/cat".to_string()];
+        let session_id = manager
+            .handle_request(UnifiedExecRequest {
+                session_id: None,
+                input_chunks: &command,
+                timeout_ms: Some(100),
+                max_output_bytes: None,
+            })
+            .await?
+            .session_id
+            .expect("expected session id");
+
+        let listed = manager.list_sessions().await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].session_id, session_id);
+        assert_eq!(listed[0].command, command);
+
+        manager.close_session(session_id).await?;
+        assert!(manager.list_sessions().await.is_empty());
+        assert!(matches!(
+            manager.close_session(session_id).await,
+            Err(UnifiedExecError::UnknownSessionId { .. })
+        ));
+
+        let metrics = manager.metrics().await;
+        assert_eq!((metrics.opened, metrics.closed), (1, 1));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn idle_sessions_expire() -> Result<(), UnifiedExecError> {
+        skip_if_sandbox!(Ok(()));
+
+        let manager = UnifiedExecSessionManager::with_limits(UnifiedExecLimits {
+            idle_ttl: Duration::from_millis(300),
+            ..UnifiedExecLimits::default()
+        });
+        let session_id = manager
+            .handle_request(UnifiedExecRequest {
+                session_id: None,
+                input_chunks: &["/bin/cat".to_string()],
+                timeout_ms: Some(100),
+                max_output_bytes: None,
+            })
+            .await?
+            .session_id
+            .expect("expected session id");
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert!(manager.list_sessions().await.is_empty());
+        assert_eq!(manager.metrics().await.expired, 1);
+        assert!(matches!(
+            manager.close_session(session_id).await,
+            Err(UnifiedExecError::UnknownSessionId { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn sessions_beyond_the_limit_are_rejected() -> Result<(), UnifiedExecError> {
+        skip_if_sandbox!(Ok(()));
+
+        let manager = UnifiedExecSessionManager::with_limits(UnifiedExecLimits {
+            max_sessions: 1,
+            ..UnifiedExecLimits::default()
+        });
+        let command = ["/bin/cat".to_string()];
+        let open = || {
+            manager.handle_request(UnifiedExecRequest {
+                session_id: None,
+                input_chunks: &command,
+                timeout_ms: Some(100),
+                max_output_bytes: None,
+            })
+        };
+        open().await?;
+
+        assert!(matches!(
+            open().await,
+            Err(UnifiedExecError::TooManySessions { max: 1 })
+        ));
+        let metrics = manager.metrics().await;
+        assert_eq!((metrics.rejected, metrics.peak_concurrent), (1, 1));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,103 @@
+#![cfg(unix)]
+
+use codex_core::exec::{
+    process_exec_tool_call, CodexErr, ExecParams, ExecToolCallOutput, SandboxErr, SandboxType,
+    StdoutStream,
+};
+use codex_core::protocol::SandboxPolicy;
+use std::collections::HashMap;
+use std::path::Path;
+
+const IMAGE: &str = "busybox";
+
+/// These tests need a running daemon and the busybox image; skip them otherwise
+fn docker_available() -> bool {
+    std::process::Command::new("docker")
+        .args(["image", "inspect", IMAGE])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn sh(script: &str, cwd: &Path, env: HashMap<String, String>) -> ExecParams {
+    ExecParams {
+        command: vec!["sh".into(), "-c".into(), script.into()],
+        cwd: cwd.to_path_buf(),
+        timeout_ms: Some(30_000),
+        env,
+        with_escalated_permissions: None,
+        justification: None,
+        limits: Default::default(),
+        use_pty: false,
+    }
+}
+
+async fn run_in(
+    params: ExecParams,
+    root: &Path,
+    image: &str,
+) -> Result<ExecToolCallOutput, CodexErr> {
+    process_exec_tool_call(
+        params,
+        SandboxType::Docker {
+            image: image.into(),
+        },
+        &SandboxPolicy::DANGER_FULL_ACCESS,
+        root,
+        &None,
+        None::<StdoutStream>,
+    )
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn container_writes_only_under_the_mounted_root() {
+    if !docker_available() {
+        return;
+    }
+    let root = tempfile::tempdir().unwrap();
+    let env = HashMap::from([("PROJECT_VAR".to_string(), "from-host".to_string())]);
+    let script = "echo \"$PROJECT_VAR\" > out.txt && pwd && test ! -e \"$HOME/.cargo\"";
+    let out = run_in(sh(script, root.path(), env), root.path(), IMAGE)
+        .await
+        .expect("exec failed");
+    assert_eq!(out.exit_code, 0, "{}", out.stderr.text);
+    assert_eq!(out.stdout.text.trim(), root.path().to_string_lossy());
+    assert_eq!(
+        std::fs::read_to_string(root.path().join("out.txt")).unwrap(),
+        "from-host\n"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn command_failures_are_not_sandbox_denials() {
+    if !docker_available() {
+        return;
+    }
+    let root = tempfile::tempdir().unwrap();
+    let out = run_in(
+        sh("exit 3", root.path(), HashMap::new()),
+        root.path(),
+        IMAGE,
+    )
+    .await
+    .expect("exec failed");
+    assert_eq!(out.exit_code, 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_container_that_cannot_start_is_denied() {
+    let root = tempfile::tempdir().unwrap();
+    let image = "qernel-test/no-such-image:missing";
+    let err = run_in(sh("true", root.path(), HashMap::new()), root.path(), image)
+        .await
+        .expect_err("container should not start");
+    assert!(
+        matches!(
+            err,
+            CodexErr::Sandbox(SandboxErr::Denied { .. }) | CodexErr::Io(_)
+        ),
+        "{err:?}"
+    );
+}
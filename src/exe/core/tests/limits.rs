@@ -0,0 +1,101 @@
+#![cfg(unix)]
+
+use codex_core::exec::{process_exec_tool_call, CodexErr, ExecParams, ExecToolCallOutput, ResourceLimits, SandboxErr, SandboxType, StdoutStream};
+use codex_core::protocol::SandboxPolicy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn sh(script: &str, limits: ResourceLimits) -> ExecParams {
+    ExecParams {
+        command: vec!["/bin/sh".into(), "-c".into(), script.into()],
+        cwd: std::env::current_dir().unwrap(),
+        timeout_ms: Some(20_000),
+        env: HashMap::new(),
+        with_escalated_permissions: None,
+        justification: None,
+        limits,
+        use_pty: false,
+    }
+}
+
+async fn run(params: ExecParams) -> Result<ExecToolCallOutput, CodexErr> {
+    process_exec_tool_call(params, SandboxType::None, &SandboxPolicy::DANGER_FULL_ACCESS, &PathBuf::from("/"), &None, None::<StdoutStream>).await
+}
+
+fn memory(mib: u64) -> ResourceLimits {
+    ResourceLimits { memory_bytes: Some(mib * 1024 * 1024), ..Default::default() }
+}
+
+#[test]
+fn only_a_recorded_oom_kill_is_blamed_on_memory() {
+    let limits = memory(64);
+    assert_eq!(limits.describe_signal(libc::SIGKILL, false), None);
+    let note = limits.describe_signal(libc::SIGKILL, true).unwrap();
+    assert!(note.contains("memory limit of 64 MiB"), "{note}");
+
+    let cpu = ResourceLimits { cpu_seconds: Some(5), ..Default::default() };
+    assert!(cpu.describe_signal(libc::SIGXCPU, false).unwrap().contains("CPU time limit of 5s"));
+    assert_eq!(cpu.describe_signal(libc::SIGTERM, false), None);
+}
+
+#[test]
+fn gpu_limit_narrows_the_visible_devices() {
+    let limits = ResourceLimits { gpus: Some(2), ..Default::default() };
+    let mut env = HashMap::new();
+    limits.limit_gpus(&mut env);
+    assert_eq!(env["CUDA_VISIBLE_DEVICES"], "0,1");
+    assert_eq!(env["HIP_VISIBLE_DEVICES"], "0,1");
+
+    let mut env = HashMap::from([("CUDA_VISIBLE_DEVICES".to_string(), "3, 5, 7".to_string())]);
+    limits.limit_gpus(&mut env);
+    assert_eq!(env["CUDA_VISIBLE_DEVICES"], "3,5");
+
+    let none = ResourceLimits { gpus: Some(0), ..Default::default() };
+    none.limit_gpus(&mut env);
+    assert_eq!(env["CUDA_VISIBLE_DEVICES"], "");
+    assert!(!none.is_unlimited());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn commands_see_only_the_allowed_gpus() {
+    let limits = ResourceLimits { gpus: Some(0), ..Default::default() };
+    let out = run(sh("echo \"[$CUDA_VISIBLE_DEVICES]\"", limits)).await.unwrap();
+    assert_eq!(out.stdout.text.trim(), "[]");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn cpu_limit_stops_a_busy_loop() {
+    let limits = ResourceLimits { cpu_seconds: Some(1), ..Default::default() };
+    let out = run(sh("while :; do :; done", limits)).await.unwrap();
+    assert_ne!(out.exit_code, 0);
+    assert!(out.stderr.text.contains("CPU time limit of 1s"), "{}", out.stderr.text);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_sigkill_from_elsewhere_is_not_reported_as_out_of_memory() {
+    let out = run(sh("kill -9 $$", memory(256))).await;
+    match out {
+        Err(CodexErr::Sandbox(SandboxErr::Signal(signal))) => assert_eq!(signal, libc::SIGKILL),
+        other => panic!("expected a plain SIGKILL, got {other:?}"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn memory_limit_stops_a_large_allocation() {
+    if std::process::Command::new("python3").arg("--version").output().is_err() {
+        return;
+    }
+    let out = run(sh("python3 -c 'x = bytearray(512 * 1024 * 1024); print(len(x))'", memory(128))).await;
+    match out {
+        // cgroup: the kernel OOM-kills it and memory.events records that
+        Ok(out) if out.stderr.text.contains("qernel:") => {
+            assert!(out.stderr.text.contains("memory limit of 128 MiB"), "{}", out.stderr.text)
+        }
+        // rlimit: the allocation fails
+        Ok(out) => {
+            assert_ne!(out.exit_code, 0);
+            assert!(out.stderr.text.contains("MemoryError"), "{}", out.stderr.text);
+        }
+        Err(e) => panic!("{e:?}"),
+    }
+}
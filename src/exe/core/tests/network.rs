@@ -0,0 +1,52 @@
+#![cfg(target_os = "linux")]
+
+use codex_core::exec::{process_exec_tool_call, ExecParams, ExecToolCallOutput, SandboxType, StdoutStream};
+use codex_core::protocol::SandboxPolicy;
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+fn sh(script: &str, cwd: PathBuf) -> ExecParams {
+    ExecParams {
+        command: vec!["/bin/sh".into(), "-c".into(), script.into()],
+        cwd,
+        timeout_ms: Some(10_000),
+        env: HashMap::new(),
+        with_escalated_permissions: None,
+        justification: None,
+        limits: Default::default(),
+        use_pty: false,
+    }
+}
+
+async fn run(params: ExecParams, policy: &SandboxPolicy) -> ExecToolCallOutput {
+    process_exec_tool_call(params, SandboxType::None, policy, &PathBuf::from("/"), &None, None::<StdoutStream>)
+        .await
+        .expect("command runs")
+}
+
+/// Interfaces of the network namespace the command ran in
+fn interfaces(out: &ExecToolCallOutput) -> Vec<String> {
+    out.stdout.text.lines().skip(2).filter_map(|l| l.split(':').next()).map(|i| i.trim().to_string()).collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn no_network_leaves_only_loopback() {
+    let cwd = std::env::current_dir().unwrap();
+    let out = run(sh("cat /proc/net/dev", cwd.clone()), &SandboxPolicy::NO_NETWORK).await;
+    assert_eq!(out.exit_code, 0, "{}", out.stderr.text);
+    assert_eq!(interfaces(&out), vec!["lo"]);
+
+    let out = run(sh("cat /proc/net/dev", cwd), &SandboxPolicy::DANGER_FULL_ACCESS).await;
+    assert_eq!(out.exit_code, 0, "{}", out.stderr.text);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn no_network_keeps_file_ownership() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = run(sh("echo hi > written", dir.path().to_path_buf()), &SandboxPolicy::NO_NETWORK).await;
+    assert_eq!(out.exit_code, 0, "{}", out.stderr.text);
+    let meta = std::fs::metadata(dir.path().join("written")).unwrap();
+    // SAFETY: getuid cannot fail and has no preconditions
+    assert_eq!(meta.uid(), unsafe { libc::getuid() });
+}
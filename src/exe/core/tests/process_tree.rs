@@ -0,0 +1,88 @@
+#![cfg(unix)]
+
+use codex_core::exec::{process_exec_tool_call, CodexErr, ExecParams, ExecToolCallOutput, SandboxErr, SandboxType, StdoutStream};
+use codex_core::protocol::SandboxPolicy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn bash(script: &str, timeout_ms: u64) -> ExecParams {
+    ExecParams {
+        command: vec!["/bin/bash".into(), "-c".into(), script.into()],
+        cwd: std::env::current_dir().unwrap(),
+        timeout_ms: Some(timeout_ms),
+        env: HashMap::new(),
+        with_escalated_permissions: None,
+        justification: None,
+        limits: Default::default(),
+        use_pty: false,
+    }
+}
+
+async fn run(params: ExecParams) -> Result<ExecToolCallOutput, CodexErr> {
+    process_exec_tool_call(
+        params,
+        SandboxType::None,
+        &SandboxPolicy::DANGER_FULL_ACCESS,
+        &PathBuf::from("/"),
+        &None,
+        None::<StdoutStream>,
+    )
+    .await
+}
+
+/// The pid the script printed on its first line of stdout
+fn first_pid(out: &ExecToolCallOutput) -> i32 {
+    out.stdout.text.lines().next().and_then(|l| l.trim().parse().ok()).expect("script prints a pid first")
+}
+
+/// Killed processes may linger as zombies until whoever adopted them reaps them
+fn is_gone(pid: i32) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    loop {
+        let state = std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .ok()
+            .and_then(|stat| stat.rsplit(')').next().and_then(|rest| rest.split_whitespace().next().map(str::to_string)));
+        // SAFETY: signal 0 only checks that the process exists
+        let exists = unsafe { libc::kill(pid, 0) } == 0;
+        if !exists || state.as_deref() == Some("Z") {
+            return true;
+        }
+        if Instant::now() > deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn orphaned_grandchild_does_not_hold_the_call_open() {
+    let started = Instant::now();
+    let out = run(bash("sleep 1000 & echo $!", 30_000)).await.expect("exec failed");
+    assert_eq!(out.exit_code, 0);
+    assert!(started.elapsed() < Duration::from_secs(10), "exec waited for the background job: {:?}", started.elapsed());
+    let pid = first_pid(&out);
+    assert!(is_gone(pid), "background job {pid} survived its command");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn timeout_kills_the_whole_process_tree() {
+    let started = Instant::now();
+    let err = run(bash("sleep 1000 & echo $!; sleep 1000", 500)).await.expect_err("command should time out");
+    assert!(started.elapsed() < Duration::from_secs(10), "timeout took {:?}", started.elapsed());
+    let CodexErr::Sandbox(SandboxErr::Timeout { output }) = err else {
+        panic!("expected a timeout, got {err:?}");
+    };
+    assert!(output.timed_out);
+    let pid = first_pid(&output);
+    assert!(is_gone(pid), "grandchild {pid} survived the timeout");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn output_before_the_timeout_is_kept() {
+    let err = run(bash("echo partial; sleep 1000", 500)).await.expect_err("command should time out");
+    let CodexErr::Sandbox(SandboxErr::Timeout { output }) = err else {
+        panic!("expected a timeout, got {err:?}");
+    };
+    assert!(output.stdout.text.contains("partial"));
+}
@@ -0,0 +1,56 @@
+#![cfg(unix)]
+
+use codex_core::exec::{process_exec_tool_call, CodexErr, ExecParams, ExecToolCallOutput, SandboxErr, SandboxType, StdoutStream};
+use codex_core::protocol::SandboxPolicy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn bash_pty(script: &str, timeout_ms: u64) -> ExecParams {
+    ExecParams {
+        command: vec!["/bin/bash".into(), "-c".into(), script.into()],
+        cwd: std::env::current_dir().unwrap(),
+        timeout_ms: Some(timeout_ms),
+        env: HashMap::new(),
+        with_escalated_permissions: None,
+        justification: None,
+        limits: Default::default(),
+        use_pty: true,
+    }
+}
+
+async fn run(params: ExecParams) -> Result<ExecToolCallOutput, CodexErr> {
+    process_exec_tool_call(
+        params,
+        SandboxType::None,
+        &SandboxPolicy::DANGER_FULL_ACCESS,
+        &PathBuf::from("/"),
+        &None,
+        None::<StdoutStream>,
+    )
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn command_sees_a_terminal() {
+    let out = run(bash_pty("[ -t 0 ] && [ -t 1 ] && [ -t 2 ] && echo tty", 10_000)).await.expect("exec failed");
+    assert_eq!(out.exit_code, 0);
+    assert_eq!(out.stdout.text, "tty\n");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn output_is_merged_with_ansi_intact() {
+    let out = run(bash_pty("printf '\\033[32mok\\033[0m\\n'; echo err >&2; exit 3", 10_000)).await.expect("exec failed");
+    assert_eq!(out.exit_code, 3);
+    assert_eq!(out.stdout.text, "\u{1b}[32mok\u{1b}[0m\nerr\n");
+    assert!(out.stderr.text.is_empty());
+    assert_eq!(out.aggregated_output.text, out.stdout.text);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn timeout_kills_the_terminal_session() {
+    let err = run(bash_pty("echo started; sleep 1000 & sleep 1000", 500)).await.expect_err("command should time out");
+    let CodexErr::Sandbox(SandboxErr::Timeout { output }) = err else {
+        panic!("expected a timeout, got {err:?}");
+    };
+    assert!(output.stdout.text.contains("started"));
+}
@@ -1,11 +1,10 @@
-use qernel_codex_core::exec::{process_exec_tool_call, ExecParams, SandboxType, StdoutStream};
-use qernel_codex_core::protocol::SandboxPolicy;
+use codex_core::exec::{process_exec_tool_call, ExecParams, SandboxType, StdoutStream};
+use codex_core::protocol::SandboxPolicy;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn smoke_exec_echo_and_curl() {
-    // 1) Echo "hello"
+async fn smoke_exec_echo() {
     let params = ExecParams {
         command: vec!["/bin/echo".into(), "hello".into()],
         cwd: std::env::current_dir().unwrap(),
@@ -13,6 +12,8 @@ async fn smoke_exec_echo_and_curl() {
         env: HashMap::new(),
         with_escalated_permissions: None,
         justification: None,
+        limits: Default::default(),
+        use_pty: false,
     };
     let out = process_exec_tool_call(
         params,
@@ -26,8 +27,12 @@ async fn smoke_exec_echo_and_curl() {
     .expect("echo failed");
     assert_eq!(out.exit_code, 0);
     assert!(out.stdout.text.contains("hello"));
+}
 
-    // 2) Fetch a small page (web fetch via shell path)
+/// Web fetch via the shell path; run with `cargo test -- --ignored` where example.com is reachable
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[ignore = "needs network access"]
+async fn smoke_exec_curl() {
     let curl = which::which("curl").expect("curl not found");
     let tmpdir = tempfile::tempdir().unwrap();
     let out_file = tmpdir.path().join("example.html");
@@ -44,6 +49,8 @@ async fn smoke_exec_echo_and_curl() {
         env: HashMap::new(),
         with_escalated_permissions: None,
         justification: None,
+        limits: Default::default(),
+        use_pty: false,
     };
     let out = process_exec_tool_call(
         params,
@@ -62,7 +69,7 @@ async fn smoke_exec_echo_and_curl() {
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn smoke_unified_exec_persists() {
-    use qernel_codex_core::unified_exec::{UnifiedExecRequest, UnifiedExecSessionManager};
+    use codex_core::unified_exec::{UnifiedExecRequest, UnifiedExecSessionManager};
 
     let manager = UnifiedExecSessionManager::default();
 
@@ -72,6 +79,7 @@ async fn smoke_unified_exec_persists() {
             session_id: None,
             input_chunks: &["/bin/bash".to_string(), "-i".to_string()],
             timeout_ms: Some(2_500),
+            max_output_bytes: None,
         })
         .await
         .expect("open bash session");
@@ -83,6 +91,7 @@ async fn smoke_unified_exec_persists() {
             session_id: Some(session_id),
             input_chunks: &["export CODEX_INTERACTIVE_SHELL_VAR=codex\n".to_string()],
             timeout_ms: Some(2_500),
+            max_output_bytes: None,
         })
         .await
         .expect("export variable");
@@ -93,6 +102,7 @@ async fn smoke_unified_exec_persists() {
             session_id: Some(session_id),
             input_chunks: &["echo $CODEX_INTERACTIVE_SHELL_VAR\n".to_string()],
             timeout_ms: Some(2_500),
+            max_output_bytes: None,
         })
         .await
         .expect("echo variable");
@@ -1,5 +0,0 @@
-pub mod cmd;
-pub mod config;
-pub mod util;
-
-
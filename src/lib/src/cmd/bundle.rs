@@ -0,0 +1,353 @@
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cmd::prototype::audit::{audit_path, sha256_hex};
+use crate::cmd::prototype::manifest::manifest_path;
+use crate::cmd::prototype::plan::{load_plan, plan_prompt_section};
+use crate::cmd::prototype::prompts::{build_system_prompt, build_user_prompt};
+use crate::cmd::prototype::snapshots::create_directory_snapshot;
+use crate::cmd::prototype::summary::last_run_path;
+use crate::cmd::prototype::turns::{apply_turn_diff, check_relative_path, list_turns, turn_diff_path};
+use crate::config::ConfigResolver;
+use crate::util::{sym_check, sym_cross};
+
+pub const BUNDLE_EXTENSION: &str = "qernelrun";
+const BUNDLE_FORMAT: u32 = 1;
+
+/// Directories that are never part of a project's source
+const SKIP_DIRS: &[&str] = &[".git", ".qernel", ".venv", "venv", "__pycache__", "node_modules", "target", "build", "dist", ".pytest_cache", ".mypy_cache", ".logs"];
+
+/// `bundle.json` at the root of a `.qernelrun` archive
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    format: u32,
+    qernel_version: String,
+    created_at: String,
+    project: String,
+    /// Iterations with a diff under `.qernel/turns/`, in replay order
+    turns: Vec<u32>,
+    /// SHA-256 of every file under `source/`
+    source: BTreeMap<String, String>,
+}
+
+/// Pack everything needed to inspect or replay the project's last run into one tarball:
+/// spec, config, papers manifest, plan, prompts, turn diffs, audit log, and the source
+/// before the first turn (`base/`) and at the end (`source/`)
+pub fn handle_bundle_create(cwd: String, output: Option<String>) -> Result<()> {
+    let cwd = Path::new(&cwd).canonicalize().with_context(|| format!("no project at {}", cwd))?;
+    let config_path = cwd.join(".qernel").join("qernel.yaml");
+    if !config_path.exists() {
+        anyhow::bail!("{} is not a qernel project (no .qernel/qernel.yaml)", cwd.display());
+    }
+    let project = cwd.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "project".to_string());
+
+    // The source before the run: the final source with every turn undone, newest first
+    let source = read_source_tree(&cwd)?;
+    let turns = list_turns(&cwd);
+    let mut base = text_files(&source);
+    for turn in turns.iter().rev() {
+        let diff = fs::read_to_string(turn_diff_path(&cwd, *turn))?;
+        apply_turn_diff(&mut base, &diff, true).with_context(|| {
+            format!("turn {} no longer applies to the source; were files edited after the run?", turn)
+        })?;
+    }
+
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+    let qernel_files = [
+        config_path.clone(),
+        cwd.join(".qernel").join("spec.md"),
+        cwd.join(".qernel").join("plan.md"),
+        manifest_path(&cwd),
+        audit_path(&cwd),
+        last_run_path(&cwd),
+    ];
+    for path in qernel_files.iter().filter(|p| p.is_file()) {
+        let rel = path.strip_prefix(&cwd).unwrap_or(path).to_string_lossy().to_string();
+        entries.push((rel, fs::read(path)?));
+    }
+    for turn in &turns {
+        entries.push((format!(".qernel/turns/{}.diff", turn), fs::read(turn_diff_path(&cwd, *turn))?));
+    }
+    for (name, prompt) in render_prompts(&cwd)? {
+        entries.push((format!("prompts/{}", name), prompt.into_bytes()));
+    }
+    // Turn diffs only cover text files, so binary files are the same before and after
+    let base_tree = source
+        .iter()
+        .filter(|(_, bytes)| std::str::from_utf8(bytes).is_err())
+        .map(|(rel, bytes)| (rel.clone(), bytes.clone()))
+        .chain(base.into_iter().map(|(rel, text)| (rel, text.into_bytes())));
+    for (rel, bytes) in base_tree {
+        entries.push((format!("base/{}", rel), bytes));
+    }
+    for (rel, bytes) in &source {
+        entries.push((format!("source/{}", rel), bytes.clone()));
+    }
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT,
+        qernel_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        project: project.clone(),
+        turns: turns.clone(),
+        source: source.iter().map(|(rel, bytes)| (rel.clone(), sha256_hex(bytes))).collect(),
+    };
+    entries.insert(0, ("bundle.json".to_string(), serde_json::to_vec_pretty(&manifest)?));
+
+    let output = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("{}-{}.{}", project, chrono::Local::now().format("%Y%m%d-%H%M%S"), BUNDLE_EXTENSION)));
+    write_archive(&output, &entries)?;
+    let ce = crate::util::color_enabled_stdout();
+    println!(
+        "{} Wrote {} ({} turns, {} source files)",
+        sym_check(ce),
+        output.display(),
+        turns.len(),
+        source.len()
+    );
+    Ok(())
+}
+
+/// Unpack a bundle's spec, config and pre-run source into `into`, then apply its turn diffs
+/// in order, committing each one, and check the result against the bundled final source
+pub fn handle_bundle_replay(bundle: String, into: Option<String>) -> Result<()> {
+    let entries = read_archive(Path::new(&bundle))?;
+    let manifest: BundleManifest = entries
+        .get("bundle.json")
+        .ok_or_else(|| anyhow::anyhow!("{} has no bundle.json; is it a .{} file?", bundle, BUNDLE_EXTENSION))
+        .and_then(|bytes| serde_json::from_slice(bytes).context("bundle.json is not valid"))?;
+    if manifest.format > BUNDLE_FORMAT {
+        anyhow::bail!("{} uses bundle format {}; this qernel reads up to {}", bundle, manifest.format, BUNDLE_FORMAT);
+    }
+    let dest = match into {
+        Some(into) => PathBuf::from(into),
+        None => {
+            check_relative_path(&manifest.project).context("bundle.json names an unsafe project")?;
+            PathBuf::from(format!("{}-replay", manifest.project))
+        }
+    };
+    if dest.exists() {
+        anyhow::bail!("Path already exists: {}", dest.display());
+    }
+
+    // The scaffold: the project's inputs and its source as it was before the first turn
+    let mut base: BTreeMap<String, String> = BTreeMap::new();
+    for (name, bytes) in &entries {
+        let rel = if let Some(rel) = name.strip_prefix("base/") {
+            if let Ok(text) = String::from_utf8(bytes.clone()) {
+                base.insert(rel.to_string(), text);
+            }
+            rel
+        } else if matches!(name.as_str(), ".qernel/qernel.yaml" | ".qernel/spec.md" | ".qernel/plan.md" | ".qernel/papers/manifest.json") {
+            name
+        } else {
+            continue;
+        };
+        write_file(&confined(&dest, rel)?, bytes)?;
+    }
+    git(&dest, &["init"]);
+    git(&dest, &["add", "."]);
+    git(&dest, &["commit", "-m", "chore: initial scaffold"]);
+
+    let mut files = base;
+    for turn in &manifest.turns {
+        let name = format!(".qernel/turns/{}.diff", turn);
+        let diff = entries
+            .get(&name)
+            .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+            .ok_or_else(|| anyhow::anyhow!("the bundle lists turn {} but has no {}", turn, name))?;
+        let before = files.clone();
+        apply_turn_diff(&mut files, &diff, false).with_context(|| format!("failed to replay turn {}", turn))?;
+        for rel in before.keys().filter(|rel| !files.contains_key(*rel)) {
+            fs::remove_file(confined(&dest, rel)?).ok();
+        }
+        for (rel, text) in files.iter().filter(|(rel, text)| before.get(*rel) != Some(*text)) {
+            write_file(&confined(&dest, rel)?, text.as_bytes())?;
+        }
+        write_file(&confined(&dest, &name)?, diff.as_bytes())?;
+        git(&dest, &["add", "-A"]);
+        git(&dest, &["commit", "-m", &format!("qernel: turn {}", turn)]);
+    }
+
+    // Deterministic replay reproduces the bundled source byte for byte
+    let replayed = read_source_tree(&dest)?;
+    let mut differs: Vec<&str> = manifest
+        .source
+        .iter()
+        .filter(|(rel, hash)| replayed.get(*rel).map(|bytes| sha256_hex(bytes)).as_ref() != Some(*hash))
+        .map(|(rel, _)| rel.as_str())
+        .collect();
+    differs.extend(replayed.keys().filter(|rel| !manifest.source.contains_key(*rel)).map(String::as_str));
+    let ce = crate::util::color_enabled_stdout();
+    if !differs.is_empty() {
+        println!("{} Replayed {} turns into {}, but these files differ from the bundle:", sym_cross(ce), manifest.turns.len(), dest.display());
+        for rel in &differs {
+            println!("  {}", rel);
+        }
+        anyhow::bail!("replay did not reproduce the bundled source");
+    }
+    println!(
+        "{} Replayed {} turns into {}; the source matches the bundle",
+        sym_check(ce),
+        manifest.turns.len(),
+        dest.display()
+    );
+    Ok(())
+}
+
+/// Every source file of the project, relative to it, skipping virtualenvs, caches and bundles
+fn read_source_tree(root: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    fn walk(dir: &Path, root: &Path, out: &mut BTreeMap<String, Vec<u8>>) -> Result<()> {
+        for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(kind) = entry.file_type() else { continue };
+            if kind.is_dir() {
+                if !SKIP_DIRS.contains(&name.as_str()) {
+                    walk(&path, root, out)?;
+                }
+            } else if kind.is_file() && !name.ends_with(".pyc") && !name.ends_with(&format!(".{}", BUNDLE_EXTENSION)) {
+                let rel = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+                out.insert(rel, fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?);
+            }
+        }
+        Ok(())
+    }
+    let mut out = BTreeMap::new();
+    walk(root, root, &mut out)?;
+    Ok(out)
+}
+
+fn text_files(source: &BTreeMap<String, Vec<u8>>) -> BTreeMap<String, String> {
+    source
+        .iter()
+        .filter_map(|(rel, bytes)| Some((rel.clone(), String::from_utf8(bytes.clone()).ok()?)))
+        .collect()
+}
+
+/// The system and first user prompt as `qernel prototype` builds them for this project
+fn render_prompts(cwd: &Path) -> Result<Vec<(&'static str, String)>> {
+    let Ok(goal) = fs::read_to_string(cwd.join(".qernel").join("spec.md")) else { return Ok(Vec::new()) };
+    let test_cmd = ConfigResolver::load(cwd)?.get("benchmarks.test_command")?.unwrap_or_default();
+    let snapshot = create_directory_snapshot(cwd).unwrap_or_default();
+    let plan = load_plan(cwd).as_deref().map(plan_prompt_section).unwrap_or_default();
+    Ok(vec![
+        ("system.md", build_system_prompt(&goal, &test_cmd, cwd, &snapshot)),
+        ("user.md", build_user_prompt(&goal, "", &plan)),
+    ])
+}
+
+fn write_archive(path: &Path, entries: &[(String, Vec<u8>)]) -> Result<()> {
+    let file = fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for (name, bytes) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        // A fixed mtime keeps bundles of the same run byte-identical apart from bundle.json
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, name, bytes.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn read_archive(path: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let file = fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut entries = BTreeMap::new();
+    for entry in archive.entries().with_context(|| format!("{} is not a gzipped tarball", path.display()))? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        // Never let an archive write outside the replay directory
+        check_relative_path(&name).with_context(|| format!("{} contains an unsafe path", path.display()))?;
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        entries.insert(name, bytes);
+    }
+    Ok(entries)
+}
+
+/// `rel` under `dest`, refusing any path that would land outside it, including through a
+/// symlink already inside the replay directory
+fn confined(dest: &Path, rel: &str) -> Result<PathBuf> {
+    check_relative_path(rel)?;
+    let mut path = dest.to_path_buf();
+    for part in Path::new(rel).components() {
+        path.push(part);
+        if fs::symlink_metadata(&path).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+            anyhow::bail!("unsafe path {:?}: {} is a symlink", rel, path.display());
+        }
+    }
+    Ok(path)
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Best effort: a replay without git still has the files
+fn git(cwd: &Path, args: &[&str]) {
+    Command::new("git").args(args).current_dir(cwd).output().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hostile_bundle(dir: &Path, diff: &str) -> PathBuf {
+        let manifest = BundleManifest {
+            format: BUNDLE_FORMAT,
+            qernel_version: "0".to_string(),
+            created_at: String::new(),
+            project: "evil".to_string(),
+            turns: vec![1],
+            source: BTreeMap::new(),
+        };
+        let entries = vec![
+            ("bundle.json".to_string(), serde_json::to_vec(&manifest).unwrap()),
+            ("base/x".to_string(), b"x\n".to_vec()),
+            (".qernel/turns/1.diff".to_string(), diff.as_bytes().to_vec()),
+        ];
+        let bundle = dir.join("evil.qernelrun");
+        write_archive(&bundle, &entries).unwrap();
+        bundle
+    }
+
+    #[test]
+    fn replay_refuses_diff_paths_outside_the_replay_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        for target in ["../escaped.txt", "/tmp/escaped.txt", "a/../../escaped.txt", "./escaped.txt"] {
+            let diff = format!("diff --git a/x b/{target}\n--- /dev/null\n+++ b/{target}\n@@ -0,0 +1 @@\n+pwned\n");
+            let bundle = hostile_bundle(tmp.path(), &diff);
+            let into = tmp.path().join("replay").join(target.len().to_string());
+            fs::create_dir_all(into.parent().unwrap()).unwrap();
+            let err = handle_bundle_replay(bundle.display().to_string(), Some(into.display().to_string())).unwrap_err();
+            assert!(format!("{:#}", err).contains("unsafe path"), "{target}: {err:#}");
+        }
+        assert!(!tmp.path().join("escaped.txt").exists());
+        assert!(!tmp.path().join("replay").join("escaped.txt").exists());
+    }
+
+    #[test]
+    fn replay_refuses_writes_through_a_symlink() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("replay");
+        fs::create_dir_all(&dest).unwrap();
+        std::os::unix::fs::symlink(tmp.path(), dest.join("link")).unwrap();
+        assert!(confined(&dest, "link/escaped.txt").is_err());
+        assert_eq!(confined(&dest, "src/a.py").unwrap(), dest.join("src").join("a.py"));
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use crate::cmd::common::cache::{CacheCategory, cache_root, disk_usage, last_modified};
+
+/// Print size and entry counts per cache category
+pub fn handle_cache_stats() -> Result<()> {
+    println!("Cache directory: {}", cache_root().display());
+    let mut total = (0, 0);
+    for category in CacheCategory::ALL {
+        let (bytes, files) = disk_usage(&category.dir());
+        total.0 += bytes;
+        total.1 += files;
+        println!("  {:<8} {:>10}  {} files", category.name(), format_size(bytes), files);
+    }
+    println!("  {:<8} {:>10}  {} files", "total", format_size(total.0), total.1);
+    Ok(())
+}
+
+/// Remove cache entries not used within `older_than` (e.g. "30d", "12h", "2w"); reading an
+/// entry refreshes its modification time
+pub fn handle_cache_prune(older_than: String, category: Option<String>) -> Result<()> {
+    let age = parse_age(&older_than)?;
+    let cutoff = SystemTime::now().checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+    let ce = crate::util::color_enabled_stdout();
+    let mut freed = 0;
+    let mut removed = 0;
+    for category in selected_categories(category.as_deref())? {
+        let Ok(entries) = fs::read_dir(category.dir()) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if last_modified(&path).is_some_and(|t| t < cutoff) {
+                let (bytes, _) = disk_usage(&path);
+                remove_path(&path)?;
+                freed += bytes;
+                removed += 1;
+            }
+        }
+    }
+    println!(
+        "{} Pruned {} entries older than {} ({} freed)",
+        crate::util::sym_check(ce),
+        removed,
+        older_than,
+        format_size(freed)
+    );
+    Ok(())
+}
+
+/// Delete every entry in the given category, or the whole cache
+pub fn handle_cache_clear(category: Option<String>) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let mut freed = 0;
+    for category in selected_categories(category.as_deref())? {
+        let dir = category.dir();
+        if dir.exists() {
+            freed += disk_usage(&dir).0;
+            fs::remove_dir_all(&dir).with_context(|| format!("failed to remove {}", dir.display()))?;
+        }
+    }
+    println!("{} Cleared cache ({} freed)", crate::util::sym_check(ce), format_size(freed));
+    Ok(())
+}
+
+fn selected_categories(category: Option<&str>) -> Result<Vec<CacheCategory>> {
+    match category {
+        None => Ok(CacheCategory::ALL.to_vec()),
+        Some(name) => {
+            let c = CacheCategory::from_name(name).ok_or_else(|| {
+                let names: Vec<&str> = CacheCategory::ALL.iter().map(|c| c.name()).collect();
+                anyhow::anyhow!("Unknown cache category '{}'. Expected one of: {}", name, names.join(", "))
+            })?;
+            Ok(vec![c])
+        }
+    }
+}
+
+fn remove_path(path: &std::path::Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("failed to remove {}", path.display()))
+}
+
+/// "90s", "45m", "12h", "30d", "2w" -> Duration
+fn parse_age(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().with_context(|| format!("invalid age '{}': expected e.g. 30d, 12h, 2w", s))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "" | "d" => 86_400,
+        "w" => 7 * 86_400,
+        other => anyhow::bail!("invalid age unit '{}': use s, m, h, d or w", other),
+    };
+    Ok(Duration::from_secs(n * secs))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
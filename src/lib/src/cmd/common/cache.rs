@@ -0,0 +1,314 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::{self, HeaderMap};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Kinds of data qernel caches across projects, each in its own subdirectory of `cache_root()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheCategory {
+    /// Model responses for `qernel explain` snippets
+    Explain,
+    /// Downloaded paper PDFs
+    Papers,
+    /// mineru output, keyed by PDF content
+    Parsed,
+    /// Base64-encoded images sent to vision models
+    Images,
+}
+
+impl CacheCategory {
+    pub const ALL: [CacheCategory; 4] = [
+        CacheCategory::Explain,
+        CacheCategory::Papers,
+        CacheCategory::Parsed,
+        CacheCategory::Images,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheCategory::Explain => "explain",
+            CacheCategory::Papers => "papers",
+            CacheCategory::Parsed => "parsed",
+            CacheCategory::Images => "images",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    pub fn dir(self) -> PathBuf {
+        cache_root().join(self.name())
+    }
+}
+
+/// `QERNEL_CACHE_DIR`, else `cache/` in the config home (`--config-dir`, `QERNEL_CONFIG_HOME`),
+/// else the platform cache directory (e.g. `~/.cache/qernel`)
+pub fn cache_root() -> PathBuf {
+    if let Ok(dir) = std::env::var("QERNEL_CACHE_DIR")
+        && !dir.trim().is_empty()
+    {
+        return PathBuf::from(dir);
+    }
+    if let Some(home) = crate::util::config_home() {
+        return home.join("cache");
+    }
+    directories::ProjectDirs::from("", "", "qernel")
+        .map(|d| d.cache_dir().to_path_buf())
+        .unwrap_or_else(|| std::env::temp_dir().join("qernel-cache"))
+}
+
+/// Stable cache key (hex SHA-1) over the given parts
+pub fn cache_key(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha1::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Path of a cache entry; the category directory is created on demand
+pub fn entry_path(category: CacheCategory, name: &str) -> PathBuf {
+    let dir = category.dir();
+    let _ = fs::create_dir_all(&dir);
+    dir.join(name)
+}
+
+/// Read a cached file entry, if present
+pub fn read_entry(category: CacheCategory, name: &str) -> Option<Vec<u8>> {
+    read_used(&category.dir().join(name))
+}
+
+fn read_used(path: &Path) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    touch(path);
+    Some(bytes)
+}
+
+/// Mark an entry as just used, so `qernel cache prune` (which goes by modification time)
+/// keeps entries that are read often. Best-effort.
+pub fn touch(path: &Path) {
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Store a file entry. Caching is best-effort, so failures are ignored.
+pub fn write_entry(category: CacheCategory, name: &str, bytes: &[u8]) {
+    store_file(&entry_path(category, name), bytes);
+}
+
+fn store_file(path: &Path, bytes: &[u8]) {
+    // Write then rename so concurrent readers never see a partial entry
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    if fs::write(&tmp, bytes).is_ok() && fs::rename(&tmp, path).is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+}
+
+/// Store a directory entry by copying `src`. Like file entries, the copy is renamed into
+/// place, so readers never see a partial tree. Best-effort.
+pub fn write_dir_entry(category: CacheCategory, name: &str, src: &Path) {
+    store_dir(&entry_path(category, name), src);
+}
+
+fn store_dir(path: &Path, src: &Path) {
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    // Renaming fails when another process stored the same entry first; theirs is as good
+    if copy_dir_all(src, &tmp).is_err() || fs::rename(&tmp, path).is_err() {
+        let _ = fs::remove_dir_all(&tmp);
+    }
+}
+
+/// How long a cached download is used without asking the server whether it changed
+pub const HTTP_FRESH_FOR: Duration = Duration::from_secs(24 * 3600);
+
+/// A cached HTTP response body with the validators to revalidate it, kept in `<name>.meta`
+pub struct CachedResponse {
+    pub bytes: Vec<u8>,
+    /// Fetched or revalidated within `HTTP_FRESH_FOR`
+    pub fresh: bool,
+    path: PathBuf,
+    meta: ResponseMeta,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ResponseMeta {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix time of the last fetch or revalidation
+    checked_at: u64,
+}
+
+impl CachedResponse {
+    /// Add `If-None-Match` / `If-Modified-Since` so an unchanged resource comes back as 304
+    pub fn conditional(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.meta.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(date) = &self.meta.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, date);
+        }
+        request
+    }
+
+    /// The server answered 304: the cached body is fresh again
+    pub fn revalidated(self) -> Vec<u8> {
+        store_meta(&self.path, &ResponseMeta { checked_at: unix_now(), ..self.meta });
+        self.bytes
+    }
+}
+
+/// Read a cached HTTP response, if present. A body without metadata counts as stale and
+/// is downloaded again in full.
+pub fn read_http_entry(category: CacheCategory, name: &str) -> Option<CachedResponse> {
+    read_http(&category.dir().join(name))
+}
+
+/// Store an HTTP response body with the validators from its headers
+pub fn write_http_entry(category: CacheCategory, name: &str, bytes: &[u8], headers: &HeaderMap) {
+    store_http(&entry_path(category, name), bytes, headers);
+}
+
+fn read_http(path: &Path) -> Option<CachedResponse> {
+    let bytes = read_used(path)?;
+    let meta: ResponseMeta = read_used(&meta_path(path)).and_then(|m| serde_json::from_slice(&m).ok()).unwrap_or_default();
+    let fresh = meta.checked_at > 0 && unix_now().saturating_sub(meta.checked_at) < HTTP_FRESH_FOR.as_secs();
+    Some(CachedResponse { bytes, fresh, path: path.to_path_buf(), meta })
+}
+
+fn store_http(path: &Path, bytes: &[u8], headers: &HeaderMap) {
+    let value = |name| headers.get(name).and_then(|v: &header::HeaderValue| v.to_str().ok()).map(str::to_string);
+    store_file(path, bytes);
+    store_meta(path, &ResponseMeta { etag: value(header::ETAG), last_modified: value(header::LAST_MODIFIED), checked_at: unix_now() });
+}
+
+fn store_meta(path: &Path, meta: &ResponseMeta) {
+    if let Ok(json) = serde_json::to_vec(meta) {
+        store_file(&meta_path(path), &json);
+    }
+}
+
+fn meta_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".meta");
+    PathBuf::from(name)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Recursively copy a directory tree
+pub fn copy_dir_all(src: &Path, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest).with_context(|| format!("failed to create {}", dest.display()))?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Total size in bytes and number of files under `path`
+pub fn disk_usage(path: &Path) -> (u64, u64) {
+    let Ok(meta) = fs::symlink_metadata(path) else { return (0, 0) };
+    if !meta.is_dir() {
+        return (meta.len(), 1);
+    }
+    let mut total = (0, 0);
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let (bytes, files) = disk_usage(&entry.path());
+            total.0 += bytes;
+            total.1 += files;
+        }
+    }
+    total
+}
+
+/// Most recent modification time of anything under `path`
+pub fn last_modified(path: &Path) -> Option<SystemTime> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    let own = meta.modified().ok();
+    if !meta.is_dir() {
+        return own;
+    }
+    fs::read_dir(path)
+        .ok()?
+        .flatten()
+        .filter_map(|e| last_modified(&e.path()))
+        .chain(own)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downloads_are_fresh_until_they_age_and_keep_their_validators() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("paper.pdf");
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, "\"v1\"".parse().unwrap());
+        store_http(&path, b"%PDF-1", &headers);
+
+        let cached = read_http(&path).unwrap();
+        assert!(cached.fresh);
+        assert_eq!(cached.bytes, b"%PDF-1");
+        let request = cached.conditional(reqwest::blocking::Client::new().get("http://localhost/")).build().unwrap();
+        assert_eq!(request.headers()[header::IF_NONE_MATCH], "\"v1\"");
+        assert!(request.headers().get(header::IF_MODIFIED_SINCE).is_none());
+
+        let old = ResponseMeta { etag: Some("\"v1\"".into()), last_modified: None, checked_at: unix_now() - HTTP_FRESH_FOR.as_secs() - 1 };
+        store_meta(&path, &old);
+        let stale = read_http(&path).unwrap();
+        assert!(!stale.fresh);
+        assert_eq!(stale.revalidated(), b"%PDF-1");
+        assert!(read_http(&path).unwrap().fresh);
+
+        // A body cached before validators were kept is stale
+        fs::remove_file(meta_path(&path)).unwrap();
+        assert!(!read_http(&path).unwrap().fresh);
+    }
+
+    #[test]
+    fn directory_entries_appear_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("out");
+        fs::create_dir_all(src.join("auto")).unwrap();
+        fs::write(src.join("auto").join("a_content_list.json"), "[]").unwrap();
+
+        let entry = dir.path().join("cache").join("abc");
+        fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        store_dir(&entry, &src);
+        assert_eq!(fs::read_to_string(entry.join("auto").join("a_content_list.json")).unwrap(), "[]");
+        // Storing it again leaves the first copy and no temporary tree behind
+        store_dir(&entry, &src);
+        let names: Vec<_> = fs::read_dir(entry.parent().unwrap()).unwrap().flatten().map(|e| e.file_name()).collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("abc")]);
+    }
+
+    #[test]
+    fn reading_an_entry_marks_it_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entry");
+        fs::write(&path, "x").unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(90 * 86_400);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(long_ago).unwrap();
+        read_used(&path).unwrap();
+        assert!(last_modified(&path).unwrap() > long_ago + Duration::from_secs(86_400));
+    }
+}
@@ -0,0 +1,158 @@
+//! Where requests for OpenAI models go: api.openai.com, or an Azure OpenAI resource after
+//! `qernel provider --set azure`. Azure serves the Responses API at `/openai/responses`,
+//! addresses models by deployment name (sent as `model`), takes an `api-version` query
+//! parameter and authenticates with an `api-key` header.
+
+use anyhow::Result;
+use reqwest::blocking::{Client, RequestBuilder};
+use std::collections::BTreeMap;
+
+use crate::error::QernelError;
+
+/// `api-version` sent to Azure when none is configured
+pub const DEFAULT_AZURE_API_VERSION: &str = "2025-04-01-preview";
+
+/// Backends `qernel provider --set` accepts
+pub const BACKENDS: &[&str] = &["openai", "azure"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    Openai,
+    Azure { base_url: String, api_version: String, deployments: BTreeMap<String, String> },
+}
+
+impl Backend {
+    /// The backend picked by `QERNEL_PROVIDER` (`openai` or `azure`), else the user config.
+    /// `AZURE_OPENAI_ENDPOINT` and `AZURE_OPENAI_API_VERSION` override the stored Azure settings.
+    pub fn active() -> Result<Self> {
+        let cfg = crate::util::load_config().unwrap_or_default();
+        let from_env = std::env::var("QERNEL_PROVIDER")
+            .ok()
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| BACKENDS.contains(&v.as_str()));
+        let name = from_env.or(cfg.provider).unwrap_or_else(|| "openai".to_string());
+        if name != "azure" {
+            return Ok(Backend::Openai);
+        }
+        let azure = cfg.azure.unwrap_or_default();
+        let env = |var: &str| std::env::var(var).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let Some(base_url) = env("AZURE_OPENAI_ENDPOINT").or(azure.base_url) else {
+            return Err(QernelError::Config(
+                "the azure provider has no endpoint; run `qernel provider --set azure --base-url https://<resource>.openai.azure.com`"
+                    .to_string(),
+            )
+            .into());
+        };
+        let api_version = env("AZURE_OPENAI_API_VERSION")
+            .or(azure.api_version)
+            .unwrap_or_else(|| DEFAULT_AZURE_API_VERSION.to_string());
+        Ok(Backend::Azure { base_url: base_url.trim_end_matches('/').to_string(), api_version, deployments: azure.deployments })
+    }
+
+    /// Provider name for API keys (`secrets.<name>`) and rate limits
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Openai => "openai",
+            Backend::Azure { .. } => "azure",
+        }
+    }
+
+    /// Name shown in errors
+    pub fn label(&self) -> &'static str {
+        match self {
+            Backend::Openai => "OpenAI",
+            Backend::Azure { .. } => "Azure OpenAI",
+        }
+    }
+
+    /// Azure deployment serving `model`; the model name itself when there is no mapping
+    pub fn deployment<'a>(&'a self, model: &'a str) -> &'a str {
+        match self {
+            Backend::Openai => model,
+            Backend::Azure { deployments, .. } => deployments.get(model).map(String::as_str).unwrap_or(model),
+        }
+    }
+
+    /// A request for the models this backend serves: `GET /models`
+    pub fn models(&self, client: &Client, api_key: &str) -> RequestBuilder {
+        match self {
+            Backend::Openai => client.get("https://api.openai.com/v1/models").bearer_auth(api_key),
+            Backend::Azure { base_url, api_version, .. } => client
+                .get(format!("{}/openai/models", base_url))
+                .query(&[("api-version", api_version)])
+                .header("api-key", api_key),
+        }
+    }
+
+    /// A Responses API request carrying `body`, whose `model` is swapped for the deployment on Azure
+    pub fn responses(&self, client: &Client, api_key: &str, body: &serde_json::Value) -> RequestBuilder {
+        match self {
+            Backend::Openai => client.post("https://api.openai.com/v1/responses").bearer_auth(api_key).json(body),
+            Backend::Azure { base_url, api_version, .. } => {
+                let model = body["model"].as_str().unwrap_or_default();
+                let deployment = self.deployment(model);
+                let mut body = body.clone();
+                body["model"] = serde_json::Value::String(deployment.to_string());
+                client
+                    .post(format!("{}/openai/responses", base_url))
+                    .query(&[("api-version", api_version)])
+                    .header("api-key", api_key)
+                    .json(&body)
+            }
+        }
+    }
+}
+
+/// Base URL of the local Ollama server: `OLLAMA_HOST`, default http://localhost:11434
+pub fn ollama_host() -> String {
+    let host = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://localhost:11434".to_string());
+    let host = if host.starts_with("http://") || host.starts_with("https://") { host } else { format!("http://{}", host) };
+    host.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn azure() -> Backend {
+        Backend::Azure {
+            base_url: "https://res.openai.azure.com".to_string(),
+            api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+            deployments: BTreeMap::from([("gpt-5".to_string(), "prod-gpt5".to_string())]),
+        }
+    }
+
+    fn body_of(request: &reqwest::blocking::Request) -> serde_json::Value {
+        serde_json::from_slice(request.body().and_then(|b| b.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn azure_responses_go_to_the_resource_with_the_deployment_as_model() {
+        let body = serde_json::json!({ "model": "gpt-5", "input": "hi" });
+        let request = azure().responses(&Client::new(), "key", &body).build().unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            format!("https://res.openai.azure.com/openai/responses?api-version={}", DEFAULT_AZURE_API_VERSION)
+        );
+        assert_eq!(request.headers()["api-key"], "key");
+        assert!(request.headers().get("authorization").is_none());
+        assert_eq!(body_of(&request)["model"], "prod-gpt5");
+        assert_eq!(body_of(&request)["input"], "hi");
+    }
+
+    #[test]
+    fn azure_models_without_a_deployment_keep_their_name() {
+        let body = serde_json::json!({ "model": "o3" });
+        let request = azure().responses(&Client::new(), "key", &body).build().unwrap();
+        assert_eq!(body_of(&request)["model"], "o3");
+    }
+
+    #[test]
+    fn openai_responses_use_bearer_auth() {
+        let body = serde_json::json!({ "model": "gpt-5" });
+        let request = Backend::Openai.responses(&Client::new(), "key", &body).build().unwrap();
+        assert_eq!(request.url().as_str(), "https://api.openai.com/v1/responses");
+        assert_eq!(request.headers()["authorization"], "Bearer key");
+        assert_eq!(body_of(&request)["model"], "gpt-5");
+    }
+}
@@ -0,0 +1,100 @@
+//! `QERNEL_MOCK=1`: deterministic canned model responses, so `prototype` and
+//! `explain` can run end to end (in tests and demos) without network or API keys.
+//!
+//! Fixtures live in `$QERNEL_MOCK_FIXTURES`, or `.qernel/mock/` under the current directory:
+//! - `steps.json`: array of agent steps (`{"action": "apply_patch", "patch": "..."}`, ...)
+//!   served to the prototype agent in order; the last one repeats
+//! - `summaries.json`: object mapping an explain snippet id (or `default`) to its summary
+//! - `reviews.json`: array of reviewer verdicts (`{"verdict": "reject", "critique": "..."}`)
+//!   served in order; the last one repeats. Without it every patch is approved
+//! - `report.md`: body of the review-mode report written after a successful run
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Whether `QERNEL_MOCK` is set to anything but empty, `0` or `false`. Kept apart from
+/// `QERNEL_PROVIDER`, which picks where real requests go.
+pub fn enabled() -> bool {
+    std::env::var("QERNEL_MOCK").is_ok_and(|v| !matches!(v.trim().to_lowercase().as_str(), "" | "0" | "false"))
+}
+
+pub fn fixtures_dir() -> PathBuf {
+    match std::env::var("QERNEL_MOCK_FIXTURES") {
+        Ok(dir) if !dir.trim().is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(".qernel").join("mock"),
+    }
+}
+
+static NEXT_STEP: AtomicUsize = AtomicUsize::new(0);
+static NEXT_REVIEW: AtomicUsize = AtomicUsize::new(0);
+
+/// The next agent step as JSON. Without a `steps.json` every step just re-runs the tests.
+pub fn next_step() -> Result<serde_json::Value> {
+    let path = fixtures_dir().join("steps.json");
+    if !path.exists() {
+        return Ok(serde_json::json!({
+            "action": "shell",
+            "rationale": "mock provider: no steps.json fixture, re-running the tests",
+        }));
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let steps: Vec<serde_json::Value> =
+        serde_json::from_str(&text).with_context(|| format!("{}: expected a JSON array of steps", path.display()))?;
+    let Some(last) = steps.len().checked_sub(1) else {
+        anyhow::bail!("{} has no steps", path.display());
+    };
+    let i = NEXT_STEP.fetch_add(1, Ordering::SeqCst).min(last);
+    Ok(steps[i].clone())
+}
+
+/// The next reviewer reply, as the JSON text the real reviewer model is asked for
+pub fn review_response() -> Result<String> {
+    let path = fixtures_dir().join("reviews.json");
+    if !path.exists() {
+        return Ok(serde_json::json!({ "verdict": "approve", "critique": "" }).to_string());
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let reviews: Vec<serde_json::Value> =
+        serde_json::from_str(&text).with_context(|| format!("{}: expected a JSON array of verdicts", path.display()))?;
+    let Some(last) = reviews.len().checked_sub(1) else {
+        anyhow::bail!("{} has no verdicts", path.display());
+    };
+    let i = NEXT_REVIEW.fetch_add(1, Ordering::SeqCst).min(last);
+    Ok(reviews[i].to_string())
+}
+
+/// Review-mode report body: `report.md` from the fixtures, else a placeholder
+pub fn report_response() -> Result<String> {
+    let path = fixtures_dir().join("report.md");
+    if path.exists() {
+        return std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()));
+    }
+    Ok("# Implementation report\n\nMock report: no report.md fixture.".to_string())
+}
+
+/// Reply to an explain prompt in the JSON shape the real model is asked for.
+/// Keyed by snippet id so concurrent requests stay deterministic.
+pub fn text_response(user: &str) -> Result<String> {
+    let meta = |key: &str| {
+        user.lines()
+            .find_map(|l| l.strip_prefix(key).map(|v| v.trim().to_string()))
+    };
+    let id = meta("id: ").unwrap_or_else(|| "snippet".to_string());
+
+    let path = fixtures_dir().join("summaries.json");
+    let fixtures: HashMap<String, String> = if path.exists() {
+        let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("{}: expected an object of id -> summary", path.display()))?
+    } else {
+        HashMap::new()
+    };
+    let summary = fixtures.get(&id).or_else(|| fixtures.get("default")).cloned().unwrap_or_else(|| {
+        let kind = meta("kind: ").unwrap_or_else(|| "snippet".to_string());
+        let name = meta("name: ").unwrap_or_else(|| id.clone());
+        let lines = meta("lines: ").unwrap_or_default();
+        format!("Mock summary of {} `{}` (lines {}).", kind, name, lines)
+    });
+    Ok(serde_json::json!({ "id": id, "summary": summary }).to_string())
+}
@@ -0,0 +1,10 @@
+pub mod cache;
+pub mod endpoint;
+pub mod mock;
+pub mod models;
+pub mod network;
+pub mod pager;
+pub mod theme;
+pub mod trace;
+pub mod usage;
+pub mod word_diff;
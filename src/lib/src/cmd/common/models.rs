@@ -0,0 +1,345 @@
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Who serves a model, which decides the endpoint a request goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelProvider {
+    Openai,
+    Anthropic,
+    Openrouter,
+    Ollama,
+}
+
+/// What a model supports, consulted when building requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelCapabilities {
+    pub provider: ModelProvider,
+    /// Grammar-based "custom" tools (freeform apply_patch) instead of JSON function tools
+    pub freeform_tools: bool,
+    /// Calls tools; models that cannot are asked for each step as structured JSON output
+    pub tool_calls: bool,
+    /// Accepts image inputs
+    pub vision: bool,
+    /// Context window in tokens
+    pub max_context: usize,
+    pub streaming: bool,
+    /// Reasoning model; these reject `temperature` and `top_p`
+    pub reasoning: bool,
+    /// List price, used to estimate what a run costs; `None` when unknown
+    pub pricing: Option<Pricing>,
+}
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl Pricing {
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input + output_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+impl ModelCapabilities {
+    /// Rough character budget for the context window (~4 characters per token)
+    pub fn max_context_chars(&self) -> usize {
+        self.max_context.saturating_mul(4)
+    }
+}
+
+/// One entry of a `models.yaml` override file; unset fields keep the built-in value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapabilityOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ModelProvider>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeform_tools: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<bool>,
+    /// USD per million input tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_usd_per_mtok: Option<f64>,
+    /// USD per million output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_usd_per_mtok: Option<f64>,
+}
+
+/// Built-in capabilities, matched by model-name prefix (longest prefix wins)
+const BUILTIN: &[(&str, ModelProvider, bool, bool, usize, bool)] = &[
+    // prefix, provider, freeform_tools, vision, max_context, streaming
+    ("gpt-5", ModelProvider::Openai, true, true, 400_000, true),
+    ("gpt-4.1", ModelProvider::Openai, false, true, 1_047_576, true),
+    ("gpt-4o", ModelProvider::Openai, false, true, 128_000, true),
+    ("codex-mini", ModelProvider::Openai, false, true, 200_000, true),
+    ("o3", ModelProvider::Openai, false, true, 200_000, true),
+    ("o4-mini", ModelProvider::Openai, false, true, 200_000, true),
+    ("claude-", ModelProvider::Anthropic, false, true, 200_000, true),
+    ("ollama/", ModelProvider::Ollama, false, false, 8_192, true),
+];
+
+/// Reasoning models by name prefix, matched like `BUILTIN`; `false` entries carve out
+/// chat variants of a reasoning family
+const REASONING: &[(&str, bool)] = &[
+    ("gpt-5", true),
+    ("gpt-5-chat", false),
+    ("codex-mini", true),
+    ("o1", true),
+    ("o3", true),
+    ("o4-mini", true),
+];
+
+/// Built-in list prices in USD per million tokens (input, output), matched like `BUILTIN`
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("codex-mini", 1.5, 6.0),
+    ("o3", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+];
+
+/// Built-in table plus overrides from the user's `models.yaml` (next to the qernel
+/// config file) and the project's `.qernel/models.yaml`, in increasing priority
+#[derive(Debug, Default)]
+pub struct ModelRegistry {
+    overrides: Vec<BTreeMap<String, CapabilityOverride>>,
+}
+
+impl ModelRegistry {
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let mut overrides = Vec::new();
+        let mut paths: Vec<PathBuf> = Vec::new();
+        if let Ok(config) = crate::util::config_file_path()
+            && let Some(dir) = config.parent()
+        {
+            paths.push(dir.join("models.yaml"));
+        }
+        paths.push(project_dir.join(".qernel").join("models.yaml"));
+        for path in paths {
+            if !path.exists() {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+            let entries: BTreeMap<String, CapabilityOverride> =
+                serde_yaml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+            overrides.push(entries);
+        }
+        Ok(Self { overrides })
+    }
+
+    pub fn capabilities(&self, model: &str) -> ModelCapabilities {
+        let mut caps = builtin_capabilities(model);
+        for entries in &self.overrides {
+            if let Some(o) = best_match(entries, model) {
+                caps.provider = o.provider.unwrap_or(caps.provider);
+                caps.freeform_tools = o.freeform_tools.unwrap_or(caps.freeform_tools);
+                caps.tool_calls = o.tool_calls.unwrap_or(caps.tool_calls);
+                caps.vision = o.vision.unwrap_or(caps.vision);
+                caps.max_context = o.max_context.unwrap_or(caps.max_context);
+                caps.streaming = o.streaming.unwrap_or(caps.streaming);
+                caps.reasoning = o.reasoning.unwrap_or(caps.reasoning);
+                if o.input_usd_per_mtok.is_some() || o.output_usd_per_mtok.is_some() {
+                    let base = caps.pricing.unwrap_or(Pricing { input: 0.0, output: 0.0 });
+                    caps.pricing = Some(Pricing {
+                        input: o.input_usd_per_mtok.unwrap_or(base.input),
+                        output: o.output_usd_per_mtok.unwrap_or(base.output),
+                    });
+                }
+            }
+        }
+        caps
+    }
+
+    /// Whether the built-in table or an override describes `model`, rather than the fallback guess
+    pub fn knows(&self, model: &str) -> bool {
+        let name = model.strip_prefix("openai/").unwrap_or(model);
+        let name = fine_tuned_base(name).unwrap_or(name);
+        BUILTIN.iter().any(|(prefix, ..)| name.starts_with(prefix))
+            || is_ollama_tag(name)
+            || self.overrides.iter().any(|entries| best_match(entries, model).is_some())
+    }
+}
+
+/// An exact key wins over `prefix*` keys; among those the longest prefix wins
+fn best_match<'a>(entries: &'a BTreeMap<String, CapabilityOverride>, model: &str) -> Option<&'a CapabilityOverride> {
+    if let Some(exact) = entries.get(model) {
+        return Some(exact);
+    }
+    entries
+        .iter()
+        .filter_map(|(key, o)| key.strip_suffix('*').filter(|p| model.starts_with(p)).map(|p| (p.len(), o)))
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, o)| o)
+}
+
+/// The base model of an OpenAI fine-tune, `ft:<base>:<org>:<suffix>:<id>`
+fn fine_tuned_base(name: &str) -> Option<&str> {
+    name.strip_prefix("ft:")?.split(':').next().filter(|base| !base.is_empty())
+}
+
+/// An Ollama `<model>:<tag>` name such as `llama3.1:8b` or `library/qwen2.5-coder:7b`
+fn is_ollama_tag(name: &str) -> bool {
+    let Some((model, tag)) = name.split_once(':') else { return false };
+    let model_chars = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-/".contains(c);
+    let tag_chars = |c: char| c.is_ascii_alphanumeric() || "._-".contains(c);
+    !model.is_empty() && !tag.is_empty() && model.chars().all(model_chars) && tag.chars().all(tag_chars)
+}
+
+fn builtin_capabilities(model: &str) -> ModelCapabilities {
+    let name = model.strip_prefix("openai/").unwrap_or(model);
+    // A fine-tune behaves like its base model but has its own (unknown) price
+    if let Some(base) = fine_tuned_base(name) {
+        return ModelCapabilities { provider: ModelProvider::Openai, pricing: None, ..builtin_capabilities(base) };
+    }
+    if let Some((_, provider, freeform_tools, vision, max_context, streaming)) = BUILTIN
+        .iter()
+        .filter(|(prefix, ..)| name.starts_with(prefix))
+        .max_by_key(|(prefix, ..)| prefix.len())
+    {
+        return ModelCapabilities {
+            provider: *provider,
+            freeform_tools: *freeform_tools,
+            tool_calls: true,
+            vision: *vision,
+            max_context: *max_context,
+            streaming: *streaming,
+            reasoning: builtin_reasoning(name),
+            pricing: builtin_pricing(name, *provider),
+        };
+    }
+    if is_ollama_tag(name) {
+        return ModelCapabilities {
+            provider: ModelProvider::Ollama,
+            freeform_tools: false,
+            tool_calls: true,
+            vision: false,
+            max_context: 8_192,
+            streaming: true,
+            reasoning: false,
+            pricing: builtin_pricing(name, ModelProvider::Ollama),
+        };
+    }
+    // Unknown models get the conservative request shape
+    ModelCapabilities {
+        provider: ModelProvider::Openai,
+        freeform_tools: false,
+        tool_calls: true,
+        vision: false,
+        max_context: 128_000,
+        streaming: false,
+        reasoning: false,
+        pricing: None,
+    }
+}
+
+fn builtin_reasoning(name: &str) -> bool {
+    REASONING
+        .iter()
+        .filter(|(prefix, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .is_some_and(|(_, reasoning)| *reasoning)
+}
+
+/// Local models are free; hosted ones are priced by the longest matching prefix
+fn builtin_pricing(name: &str, provider: ModelProvider) -> Option<Pricing> {
+    if provider == ModelProvider::Ollama {
+        return Some(Pricing { input: 0.0, output: 0.0 });
+    }
+    PRICES
+        .iter()
+        .filter(|(prefix, ..)| name.starts_with(prefix))
+        .max_by_key(|(prefix, ..)| prefix.len())
+        .map(|(_, input, output)| Pricing { input: *input, output: *output })
+}
+
+static REGISTRY: OnceCell<ModelRegistry> = OnceCell::new();
+
+/// The registry for the current directory, loaded once. A broken override file is
+/// reported once and then ignored.
+fn registry() -> &'static ModelRegistry {
+    REGISTRY.get_or_init(|| {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        ModelRegistry::load(&cwd).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring model overrides: {:#}", e);
+            ModelRegistry::default()
+        })
+    })
+}
+
+/// Capabilities of `model` using the registry for the current directory
+pub fn capabilities(model: &str) -> ModelCapabilities {
+    registry().capabilities(model)
+}
+
+/// Whether qernel has real capabilities for `model` rather than a conservative guess
+pub fn is_known(model: &str) -> bool {
+    registry().knows(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ollama_tags_are_local() {
+        let registry = ModelRegistry::default();
+        for model in ["llama3.1:8b", "qwen2.5-coder:7b-instruct-q4_K_M", "library/mistral:latest"] {
+            assert_eq!(registry.capabilities(model).provider, ModelProvider::Ollama, "{}", model);
+            assert!(registry.knows(model), "{}", model);
+        }
+        assert_eq!(registry.capabilities("ollama/llama3.1").provider, ModelProvider::Ollama);
+    }
+
+    #[test]
+    fn fine_tunes_are_openai_models_shaped_like_their_base() {
+        let registry = ModelRegistry::default();
+        let caps = registry.capabilities("ft:gpt-4o-mini:my-org::abc123");
+        assert_eq!(caps.provider, ModelProvider::Openai);
+        assert_eq!(caps.max_context, 128_000);
+        assert!(caps.vision);
+        assert_eq!(caps.pricing, None);
+        assert!(registry.knows("ft:gpt-4o-mini:my-org:custom:abc123"));
+        assert_eq!(registry.capabilities("ft:davinci-002:my-org::abc").provider, ModelProvider::Openai);
+        assert!(!registry.knows("ft:davinci-002:my-org::abc"));
+    }
+
+    #[test]
+    fn pricing_uses_the_longest_matching_prefix() {
+        let registry = ModelRegistry::default();
+        let price = |model: &str| registry.capabilities(model).pricing;
+        assert_eq!(price("o3"), Some(Pricing { input: 2.0, output: 8.0 }));
+        assert_eq!(price("o3-2025-04-16"), Some(Pricing { input: 2.0, output: 8.0 }));
+        assert_eq!(price("o3-mini"), Some(Pricing { input: 1.1, output: 4.4 }));
+        assert_eq!(price("o3-mini-2025-01-31"), Some(Pricing { input: 1.1, output: 4.4 }));
+        assert_eq!(price("gpt-5-mini"), Some(Pricing { input: 0.25, output: 2.0 }));
+        assert_eq!(price("llama3.1:8b"), Some(Pricing { input: 0.0, output: 0.0 }));
+    }
+
+    #[test]
+    fn colons_alone_do_not_make_a_model_local() {
+        let registry = ModelRegistry::default();
+        for model in ["custom:model:extra", "Vendor:Model", "a:"] {
+            assert_eq!(registry.capabilities(model).provider, ModelProvider::Openai, "{}", model);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use reqwest::blocking::{RequestBuilder, Response};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default requests/minute per provider; providers not listed are unlimited
+const DEFAULT_RATE_LIMITS: &[(&str, u32)] = &[("openai", 500)];
+
+/// How many times a 429 response is retried before giving up
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// Token bucket refilled continuously at `rate_per_sec`, holding at most `capacity` tokens
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    rate_per_sec: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rpm: u32) -> Self {
+        // Allow short bursts of ~10 seconds' worth of requests
+        let capacity = (rpm as f64 / 6.0).max(1.0);
+        Self { capacity, tokens: capacity, rate_per_sec: rpm as f64 / 60.0, last: Instant::now() }
+    }
+
+    /// Take a token, or return how long to wait before one is available
+    fn try_take(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate_per_sec).min(self.capacity);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate_per_sec))
+        }
+    }
+}
+
+/// Buckets shared by every worker thread in the process; `None` marks an unlimited provider
+static BUCKETS: Lazy<Mutex<HashMap<String, Option<Bucket>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Requests/minute for a provider. Checked in order: `QERNEL_RATE_LIMIT_<PROVIDER>`,
+/// `QERNEL_RATE_LIMIT`, `rate_limits.<provider>` in the user config, then the built-in default.
+/// A value of 0 disables limiting.
+pub fn requests_per_minute(provider: &str) -> Option<u32> {
+    let env_key = format!("QERNEL_RATE_LIMIT_{}", provider.to_uppercase());
+    let from_env = |k: &str| std::env::var(k).ok().and_then(|v| v.trim().parse::<u32>().ok());
+    let rpm = from_env(&env_key)
+        .or_else(|| from_env("QERNEL_RATE_LIMIT"))
+        .or_else(|| crate::util::load_config().ok().and_then(|c| c.rate_limits.get(provider).copied()))
+        .or_else(|| default_rate_limit(provider))?;
+    if rpm == 0 { None } else { Some(rpm) }
+}
+
+/// Built-in requests/minute for a provider, if it has one
+pub fn default_rate_limit(provider: &str) -> Option<u32> {
+    DEFAULT_RATE_LIMITS.iter().find(|(p, _)| *p == provider).map(|(_, r)| *r)
+}
+
+/// Block until the provider's rate limiter admits another request.
+/// Concurrent callers queue here instead of all hitting the provider at once.
+pub fn acquire(provider: &str) {
+    let _span = tracing::info_span!("rate_limit_wait", provider).entered();
+    loop {
+        let wait = {
+            let mut buckets = BUCKETS.lock().unwrap();
+            let bucket = buckets
+                .entry(provider.to_string())
+                .or_insert_with(|| requests_per_minute(provider).map(Bucket::new));
+            match bucket {
+                Some(b) => b.try_take(),
+                None => None,
+            }
+        };
+        match wait {
+            Some(d) => std::thread::sleep(d),
+            None => return,
+        }
+    }
+}
+
+/// Send a request through the provider's rate limiter, waiting and retrying on HTTP 429.
+/// `build` is called again for every attempt since a sent request can't be reused.
+pub fn send_rate_limited(provider: &str, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+    let _span = tracing::info_span!("model_request", provider).entered();
+    let mut retries = 0;
+    loop {
+        acquire(provider);
+        let resp = build().send().with_context(|| format!("send {} request", provider))?;
+        tracing::info!(status = resp.status().as_u16(), retries, "response");
+        if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || retries >= MAX_RATE_LIMIT_RETRIES {
+            return Ok(resp);
+        }
+        retries += 1;
+        // Honor Retry-After (seconds) when present, otherwise back off exponentially
+        let wait = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| Duration::from_secs(2u64.pow(retries)));
+        std::thread::sleep(wait);
+    }
+}
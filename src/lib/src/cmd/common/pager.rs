@@ -0,0 +1,120 @@
+//! Paging long terminal output. `$PAGER` wins when set; otherwise `less -R` where it is
+//! installed, and a small built-in pager everywhere else (e.g. Windows).
+
+use anyhow::{Context, Result};
+use console::{Key, Term, measure_text_width};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const ENTER_ALT_SCREEN: &str = "\x1b[?1049h";
+const LEAVE_ALT_SCREEN: &str = "\x1b[?1049l";
+const RESET: &str = "\x1b[0m";
+
+/// Show `text` in a pager. The caller checks that stdout is a terminal.
+pub fn page(text: &str) -> Result<()> {
+    if let Ok(pager) = std::env::var("PAGER") {
+        let pager = pager.trim();
+        if pager.is_empty() || pager == "builtin" {
+            return builtin(text);
+        }
+        let argv = shlex::split(pager).filter(|a| !a.is_empty()).ok_or_else(|| anyhow::anyhow!("invalid $PAGER: {}", pager))?;
+        match spawn(&argv, text) {
+            Ok(()) => return Ok(()),
+            Err(e) => eprintln!("[WARNING] $PAGER ({}) could not run: {:#}; using the built-in pager", pager, e),
+        }
+        return builtin(text);
+    }
+    if which::which("less").is_ok() {
+        return spawn(&["less".to_string(), "-R".to_string()], text);
+    }
+    builtin(text)
+}
+
+fn spawn(argv: &[String], text: &str) -> Result<()> {
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]).stdin(Stdio::piped());
+    // Like git: let less pass colors through unless the user configured it
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "R");
+    }
+    let mut child = command.spawn().with_context(|| format!("spawn {}", argv[0]))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A pager quit before reading everything closes the pipe; that is not an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait().with_context(|| format!("wait for {}", argv[0]))?;
+    Ok(())
+}
+
+/// Arrow keys, j/k and Enter scroll a line; Space, f, PageDown, b and PageUp a screen;
+/// g/Home and G/End jump to either end; q, Esc or Ctrl-C quit
+fn builtin(text: &str) -> Result<()> {
+    let term = Term::stdout();
+    let lines: Vec<&str> = text.lines().collect();
+    let (rows, cols) = term.size();
+    let height = (rows as usize).saturating_sub(1).max(1);
+    let width = (cols as usize).max(1);
+    // Rows a line occupies once the terminal wraps it
+    let row_count = |line: &str| measure_text_width(line).div_ceil(width).max(1);
+    if lines.iter().map(|l| row_count(l)).sum::<usize>() <= height {
+        term.write_line(text)?;
+        return Ok(());
+    }
+
+    // The last top line that still fills the screen
+    let mut last_top = lines.len();
+    let mut used = 0;
+    while last_top > 0 && used + row_count(lines[last_top - 1]) <= height {
+        last_top -= 1;
+        used += row_count(lines[last_top]);
+    }
+
+    let mut out = term.clone();
+    write!(out, "{}", ENTER_ALT_SCREEN)?;
+    term.hide_cursor()?;
+    let result = (|| -> Result<()> {
+        let mut top = 0usize;
+        loop {
+            term.clear_screen()?;
+            let mut used = 0;
+            let mut bottom = top;
+            // A line taller than the screen is still shown, cut off at the bottom
+            while bottom < lines.len() && (bottom == top || used + row_count(lines[bottom]) <= height) {
+                used += row_count(lines[bottom]);
+                term.write_line(&format!("{}{}", lines[bottom], RESET))?;
+                bottom += 1;
+            }
+            for _ in used..height {
+                term.write_line("~")?;
+            }
+            let percent = if lines.is_empty() { 100 } else { bottom * 100 / lines.len() };
+            write!(
+                out,
+                "\x1b[7m lines {}-{} of {} ({}%)  arrows/space to scroll, q to quit {}",
+                top + 1,
+                bottom,
+                lines.len(),
+                percent,
+                RESET
+            )?;
+            out.flush()?;
+
+            top = match term.read_key_raw()? {
+                Key::Char('q') | Key::Char('Q') | Key::Escape | Key::CtrlC => break,
+                Key::ArrowDown | Key::Enter | Key::Char('j') => top + 1,
+                Key::ArrowUp | Key::Char('k') => top.saturating_sub(1),
+                Key::Char(' ') | Key::Char('f') | Key::PageDown => top + (bottom - top).max(1),
+                Key::Char('b') | Key::PageUp => top.saturating_sub(height),
+                Key::Home | Key::Char('g') => 0,
+                Key::End | Key::Char('G') => last_top,
+                _ => top,
+            }
+            .min(last_top);
+        }
+        Ok(())
+    })();
+    term.show_cursor()?;
+    write!(out, "{}", LEAVE_ALT_SCREEN)?;
+    out.flush()?;
+    result
+}
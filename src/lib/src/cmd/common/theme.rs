@@ -0,0 +1,118 @@
+//! Terminal colors and syntax highlighting, from the `theme.*` user settings:
+//! `theme.name` is `dark`, `light`, a built-in syntect theme or a `.tmTheme` file, and
+//! `theme.accent|success|error|warning|info` override the message colors.
+
+use once_cell::sync::Lazy;
+use syntect::highlighting::{Theme as SyntaxTheme, ThemeSet};
+
+use crate::config::ConfigResolver;
+
+/// A theme name selecting the light or dark variant of the defaults
+const LIGHT: &str = "light";
+const DARK: &str = "dark";
+
+pub struct Theme {
+    /// Highlighting for code and Markdown; `None` keeps each view's built-in default
+    pub syntax: Option<SyntaxTheme>,
+    pub success: String,
+    pub error: String,
+    pub warning: String,
+    pub info: String,
+    /// Progress and highlights, e.g. `[THINKING]` and the in-progress plan step
+    pub accent: String,
+    /// Foreground and background of the explain snippet headers
+    pub header: String,
+}
+
+static THEME: Lazy<Theme> = Lazy::new(load);
+
+/// The theme for this invocation, read from the settings on first use
+pub fn theme() -> &'static Theme {
+    &THEME
+}
+
+fn load() -> Theme {
+    let resolver = ConfigResolver::load(std::path::Path::new(".")).ok();
+    let get = |key: &str| resolver.as_ref().and_then(|r| r.get(key).ok().flatten());
+
+    let name = get("theme.name");
+    let syntax = name.as_deref().and_then(|n| match syntax_theme(n) {
+        Ok(theme) => Some(theme),
+        Err(e) => {
+            eprintln!("[WARNING] {}; using the default theme", e);
+            None
+        }
+    });
+    let light = match (name.as_deref(), &syntax) {
+        (Some(LIGHT), _) => true,
+        (_, Some(theme)) => is_light(theme),
+        _ => false,
+    };
+    let color = |key: &str, default: &str| {
+        get(&format!("theme.{}", key)).and_then(|v| parse_color(&v)).unwrap_or_else(|| default.to_string())
+    };
+    Theme {
+        syntax,
+        success: color("success", "\x1b[32m"),
+        error: color("error", "\x1b[31m"),
+        warning: color("warning", "\x1b[33m"),
+        info: color("info", "\x1b[34m"),
+        accent: color("accent", "\x1b[36m"),
+        header: if light { "\x1b[30m\x1b[48;5;253m".to_string() } else { "\x1b[90m\x1b[48;5;240m".to_string() },
+    }
+}
+
+/// Check a `theme.name` value the way it will be loaded
+pub fn validate_theme_name(name: &str) -> anyhow::Result<()> {
+    syntax_theme(name).map(|_| ())
+}
+
+fn syntax_theme(name: &str) -> anyhow::Result<SyntaxTheme> {
+    let mut themes = ThemeSet::load_defaults();
+    let lookup = match name {
+        DARK => "base16-ocean.dark",
+        LIGHT => "InspiredGitHub",
+        other => other,
+    };
+    if let Some(theme) = themes.themes.remove(lookup) {
+        return Ok(theme);
+    }
+    let path = std::path::Path::new(name);
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tmTheme")) {
+        return ThemeSet::get_theme(path).map_err(|e| anyhow::anyhow!("cannot load theme file {}: {}", name, e));
+    }
+    let mut known: Vec<String> = vec![DARK.to_string(), LIGHT.to_string()];
+    known.extend(themes.themes.into_keys());
+    anyhow::bail!("unknown theme '{}'. Use one of: {}, or a path to a .tmTheme file", name, known.join(", "))
+}
+
+/// Whether the theme's background is light, so headers need dark text
+fn is_light(theme: &SyntaxTheme) -> bool {
+    theme.settings.background.is_some_and(|c| 0.299 * c.r as f64 + 0.587 * c.g as f64 + 0.114 * c.b as f64 > 128.0)
+}
+
+/// ANSI foreground sequence for a color name (`cyan`, `bright-blue`), `#rrggbb` or a
+/// 256-color index
+pub fn parse_color(value: &str) -> Option<String> {
+    const NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let v = value.trim().to_ascii_lowercase();
+    if let Some(hex) = v.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(format!("\x1b[38;2;{};{};{}m", channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = v.parse::<u8>() {
+        return Some(format!("\x1b[38;5;{}m", index));
+    }
+    if v == "gray" || v == "grey" {
+        return Some("\x1b[90m".to_string());
+    }
+    let (bright, base) = match v.strip_prefix("bright-") {
+        Some(base) => (true, base),
+        None => (false, v.as_str()),
+    };
+    let i = NAMES.iter().position(|n| *n == base)?;
+    Some(format!("\x1b[{}m", if bright { 90 + i } else { 30 + i }))
+}
@@ -0,0 +1,19 @@
+use anyhow::Result;
+use std::path::Path;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
+
+/// Record tracing spans (agent iterations, model requests, exec calls, patches) to `path`
+/// in Chrome trace format, viewable in chrome://tracing or https://ui.perfetto.dev.
+/// The trace is flushed when the returned guard is dropped.
+pub fn init_trace_file(path: &Path) -> Result<FlushGuard> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let (layer, guard) = ChromeLayerBuilder::new().file(path).include_args(true).build();
+    tracing_subscriber::registry()
+        .with(layer)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("failed to install trace subscriber: {}", e))?;
+    Ok(guard)
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::cmd::common::models;
+
+/// Tokens and estimated cost of every model request this process made
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated from list prices; requests to models without a known price add nothing
+    pub cost_usd: f64,
+    /// Requests whose model has no known price, so `cost_usd` is a lower bound
+    pub unpriced_requests: u64,
+}
+
+static USAGE: Mutex<Usage> = Mutex::new(Usage {
+    requests: 0,
+    input_tokens: 0,
+    output_tokens: 0,
+    cost_usd: 0.0,
+    unpriced_requests: 0,
+});
+
+/// Add the `usage` of a response body: Responses API (`input_tokens`/`output_tokens`),
+/// Chat Completions (`prompt_tokens`/`completion_tokens`) or Ollama (`prompt_eval_count`/`eval_count`)
+pub fn record(model: &str, body: &serde_json::Value) {
+    let count = |v: &serde_json::Value, keys: &[&str]| keys.iter().find_map(|k| v.get(*k).and_then(|n| n.as_u64())).unwrap_or(0);
+    let usage = body.get("usage").unwrap_or(body);
+    let input = count(usage, &["input_tokens", "prompt_tokens", "prompt_eval_count"]);
+    let output = count(usage, &["output_tokens", "completion_tokens", "eval_count"]);
+
+    let pricing = models::capabilities(model).pricing;
+    let mut totals = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    totals.requests += 1;
+    totals.input_tokens += input;
+    totals.output_tokens += output;
+    match pricing {
+        Some(p) => totals.cost_usd += p.cost(input, output),
+        None => totals.unpriced_requests += 1,
+    }
+    tracing::debug!(model, input, output, "model usage");
+}
+
+/// Count from zero again; each prototype run reports and budgets only its own requests
+pub fn reset() {
+    *USAGE.lock().unwrap_or_else(|e| e.into_inner()) = Usage::default();
+}
+
+/// Usage so far
+pub fn totals() -> Usage {
+    USAGE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+impl Usage {
+    /// e.g. `12 requests, 48.2k in / 6.1k out tokens, ~$0.14`
+    pub fn summary(&self) -> String {
+        let cost = if self.unpriced_requests > 0 {
+            format!("at least ${:.2} ({} request(s) to unpriced models)", self.cost_usd, self.unpriced_requests)
+        } else {
+            format!("~${:.2}", self.cost_usd)
+        };
+        format!(
+            "{} requests, {} in / {} out tokens, {}",
+            self.requests,
+            thousands(self.input_tokens),
+            thousands(self.output_tokens),
+            cost
+        )
+    }
+}
+
+fn thousands(n: u64) -> String {
+    if n >= 1000 { format!("{:.1}k", n as f64 / 1000.0) } else { n.to_string() }
+}
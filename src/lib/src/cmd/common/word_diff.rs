@@ -0,0 +1,73 @@
+//! Intra-line diffs: which words of a modified line changed, so a diff view can highlight
+//! them inside the removed and added lines instead of leaving the reader to spot a `0.25`
+//! that became `0.3`.
+
+use similar::{Algorithm, ChangeTag};
+
+/// Consecutive pieces of a line, each flagged when it is not in the paired line
+pub type Segments = Vec<(bool, String)>;
+
+/// Below this share of unchanged characters, a line pair is a rewrite and is shown whole
+const MIN_SHARED: f64 = 0.4;
+
+/// Split a removed line and the added line that replaces it into segments, marking the
+/// words that differ. `None` when the lines have too little in common for highlights to help.
+pub fn word_diff(old: &str, new: &str) -> Option<(Segments, Segments)> {
+    let (old_tokens, new_tokens) = (tokenize(old), tokenize(new));
+    let mut old_segments: Segments = Vec::new();
+    let mut new_segments: Segments = Vec::new();
+    let mut shared = 0;
+    for (tag, tokens) in similar::utils::diff_slices(Algorithm::Myers, &old_tokens, &new_tokens) {
+        let text: String = tokens.concat();
+        match tag {
+            ChangeTag::Equal => {
+                shared += text.chars().count();
+                push(&mut old_segments, false, &text);
+                push(&mut new_segments, false, &text);
+            }
+            ChangeTag::Delete => push(&mut old_segments, true, &text),
+            ChangeTag::Insert => push(&mut new_segments, true, &text),
+        }
+    }
+    let longest = old.chars().count().max(new.chars().count()).max(1);
+    (shared as f64 / longest as f64 >= MIN_SHARED).then_some((old_segments, new_segments))
+}
+
+/// Words (letters, digits, `_` and `.`, so `0.25` and `np.pi` stay whole), runs of
+/// whitespace, and single punctuation characters
+fn tokenize(line: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<u8> = None;
+    for (i, c) in line.char_indices() {
+        let k = class(c);
+        if let Some(p) = prev
+            && (p != k || k == 2)
+        {
+            tokens.push(&line[start..i]);
+            start = i;
+        }
+        prev = Some(k);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+/// Append `text`, merging it into the last segment when that has the same flag
+fn push(segments: &mut Segments, changed: bool, text: &str) {
+    match segments.last_mut() {
+        Some((flag, last)) if *flag == changed => last.push_str(text),
+        _ => segments.push((changed, text.to_string())),
+    }
+}
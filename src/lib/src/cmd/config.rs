@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::config::{
+    ConfigResolver, find_config_key, set_project_value, set_user_value, unset_project_value, unset_user_value,
+};
+
+/// Print the config layers, or with `effective` the merged value of every key and where it came from
+pub fn handle_config_show(cwd: String, effective: bool) -> Result<()> {
+    let resolver = ConfigResolver::load(Path::new(&cwd))?;
+    let project_path = resolver.project_path();
+    let user_path = crate::util::config_file_path()?;
+
+    if !effective {
+        println!("Precedence: flags > env > project > user > defaults");
+        println!(
+            "  project: {}{}",
+            project_path.display(),
+            if project_path.exists() { "" } else { " (not found)" }
+        );
+        println!(
+            "  user:    {}{}",
+            user_path.display(),
+            if user_path.exists() { "" } else { " (not found)" }
+        );
+        let stored = crate::util::load_stored_config().unwrap_or_default();
+        if let Some(profile) = crate::util::active_profile(&stored) {
+            println!("  profile: {}", profile);
+        }
+        println!("\nRun `qernel config show --effective` to see the merged values.");
+        return Ok(());
+    }
+
+    let values = resolver.effective();
+    let key_width = values.iter().map(|v| v.key.len()).max().unwrap_or(0);
+    let shown: Vec<String> = values.iter().map(|v| v.display_value()).collect();
+    let value_width = shown.iter().map(|v| v.chars().count()).max().unwrap_or(0).min(48);
+    for (v, display) in values.iter().zip(&shown) {
+        let source = match &v.source {
+            Some(s) => s.to_string(),
+            None => "-".to_string(),
+        };
+        println!("{:<kw$}  {:<vw$}  {}", v.key, display, source, kw = key_width, vw = value_width);
+    }
+    println!("\nproject: {}\nuser:    {}", project_path.display(), user_path.display());
+    Ok(())
+}
+
+/// Print the effective value of one key (secrets stay masked)
+pub fn handle_config_get(cwd: String, key: String) -> Result<()> {
+    let resolver = ConfigResolver::load(Path::new(&cwd))?;
+    let resolved = resolver.resolve(&key)?;
+    if resolved.value.is_none() {
+        anyhow::bail!("{} is not set", key);
+    }
+    println!("{}", resolved.display_value());
+    Ok(())
+}
+
+/// Set a key in the user config, or in the project's qernel.yaml with `project`.
+/// Secrets may omit the value to be prompted for it instead of leaving it in shell history.
+pub fn handle_config_set(cwd: String, key: String, value: Option<String>, project: bool) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let spec = find_config_key(&key)?;
+    let value = match value {
+        Some(v) => v,
+        None if spec.secret => {
+            println!("Enter value for {}:", key);
+            rpassword::read_password().unwrap_or_default()
+        }
+        None => anyhow::bail!("missing value for {}", key),
+    };
+    let scope = if project {
+        set_project_value(Path::new(&cwd), &spec, &value)?;
+        "project".to_string()
+    } else {
+        set_user_value(&spec, &value)?;
+        user_scope(&spec)
+    };
+    println!("{} Set {} in {} config", crate::util::sym_check(ce), key, scope);
+    warn_if_shadowed(&cwd, &key, project)?;
+    Ok(())
+}
+
+/// Remove a key from the user config, or from the project's qernel.yaml with `project`
+pub fn handle_config_unset(cwd: String, key: String, project: bool) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let spec = find_config_key(&key)?;
+    let scope = if project {
+        unset_project_value(Path::new(&cwd), &spec)?;
+        "project".to_string()
+    } else {
+        unset_user_value(&spec)?;
+        user_scope(&spec)
+    };
+    println!("{} Removed {} from {} config", crate::util::sym_check(ce), key, scope);
+    if !project && spec.user.first().and_then(|path| crate::util::profile_for_key(path)).is_some() {
+        let cfg = serde_json::to_value(crate::util::load_config()?)?;
+        if spec.user.iter().any(|path| path.split('.').try_fold(&cfg, |v, k| v.get(k)).is_some_and(|v| !v.is_null())) {
+            println!("  The top-level value of {} applies again while the profile is active", key);
+        }
+    }
+    Ok(())
+}
+
+/// Check the project's qernel.yaml against the schema, listing every problem found
+pub fn handle_config_validate(cwd: String) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let path = Path::new(&cwd).join(".qernel").join("qernel.yaml");
+    if !path.is_file() {
+        anyhow::bail!("No qernel.yaml at {}", path.display());
+    }
+    crate::migrations::upgrade(&path)?;
+    let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    crate::schema::check_config(&path, &text)?;
+    // Checks the schema cannot express, as `qernel prototype` makes them before a run
+    let config = crate::config::load_config(&path)?;
+    config.agent.sampling.validate()?;
+    crate::cmd::prototype::metrics::validate_metrics(&config.benchmarks.metrics)?;
+    crate::cmd::prototype::suites::test_suites(&config.benchmarks)?;
+    crate::cmd::prototype::artifacts::validate_artifact_globs(&config.benchmarks.artifacts)?;
+    println!("{} {} is valid", crate::util::sym_check(ce), path.display());
+    Ok(())
+}
+
+/// Print the JSON Schema of qernel.yaml, for editors and CI
+pub fn handle_config_schema() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&crate::schema::config_schema())?);
+    Ok(())
+}
+
+/// "user", or "user (profile NAME)" when the key is saved to the active profile
+fn user_scope(spec: &crate::config::ConfigKey) -> String {
+    match spec.user.first().and_then(|path| crate::util::profile_for_key(path)) {
+        Some(profile) => format!("user (profile {})", profile),
+        None => "user".to_string(),
+    }
+}
+
+/// Print where the user config, the caches and the project config are read from
+pub fn handle_config_path(cwd: String) -> Result<()> {
+    let origin = match crate::util::config_home_origin() {
+        Some((dir, from)) => format!("{} (from {})", dir.display(), from),
+        None => "platform default".to_string(),
+    };
+    let exists = |path: &Path| if path.exists() { "" } else { " (not found)" };
+    let user = crate::util::config_file_path()?;
+    let cache = crate::cmd::common::cache::cache_root();
+    let project = Path::new(&cwd).join(".qernel").join("qernel.yaml");
+    println!("config home: {}", origin);
+    println!("user:        {}{}", user.display(), exists(&user));
+    println!("cache:       {}{}", cache.display(), exists(&cache));
+    println!("project:     {}{}", project.display(), exists(&project));
+    Ok(())
+}
+
+/// Make `name` the profile used when `--profile` and `QERNEL_PROFILE` are not set, or with
+/// `None` go back to the top-level settings; lists the profiles afterwards
+pub fn handle_config_use_profile(name: Option<String>) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let mut cfg = crate::util::load_stored_config().unwrap_or_default();
+    match name.as_deref().map(str::trim) {
+        Some(name) => {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')) {
+                anyhow::bail!("invalid profile name '{}': use letters, digits, '-' and '_'", name);
+            }
+            let created = !cfg.profiles.contains_key(name);
+            cfg.profiles.entry(name.to_string()).or_default();
+            cfg.profile = Some(name.to_string());
+            crate::util::save_config(&cfg)?;
+            println!("{} Using profile {}", crate::util::sym_check(ce), name);
+            if created {
+                println!("  It is new and empty: settings saved now (`qernel auth`, `qernel config set`, `qernel provider`) go into it.");
+            }
+        }
+        None => {
+            cfg.profile = None;
+            crate::util::save_config(&cfg)?;
+            println!("{} Using the top-level settings (no profile)", crate::util::sym_check(ce));
+        }
+    }
+    if !cfg.profiles.is_empty() {
+        println!("Profiles: {}", cfg.profiles.keys().cloned().collect::<Vec<_>>().join(", "));
+    }
+    Ok(())
+}
+
+/// Point out when a higher-precedence layer still overrides the value just written
+fn warn_if_shadowed(cwd: &str, key: &str, project: bool) -> Result<()> {
+    use crate::config::ConfigSource;
+    let resolved = ConfigResolver::load(Path::new(cwd))?.resolve(key)?;
+    let shadowed = match &resolved.source {
+        Some(ConfigSource::Env(_)) => true,
+        Some(ConfigSource::Project) => !project,
+        _ => false,
+    };
+    if shadowed && let Some(source) = resolved.source {
+        let ce = crate::util::color_enabled_stdout();
+        println!("{} Note: {} is currently taken from {}", crate::util::sym_question(ce), key, source);
+    }
+    Ok(())
+}
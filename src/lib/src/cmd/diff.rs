@@ -0,0 +1,303 @@
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::Path;
+
+use crate::cmd::common::word_diff::{Segments, word_diff};
+use crate::cmd::explain::renderer::escape_html;
+use crate::cmd::prototype::turns::{list_turns, turn_diff_path};
+
+/// One line of the side-by-side view. Unpaired removals/additions leave the other side empty.
+enum Row {
+    Hunk(String),
+    Line {
+        left: Option<(usize, String)>,
+        right: Option<(usize, String)>,
+        changed: bool,
+    },
+}
+
+struct FileDiff {
+    path: String,
+    added: usize,
+    removed: usize,
+    rows: Vec<Row>,
+}
+
+/// Show the diff stored for an agent iteration, or list the recorded iterations
+pub fn handle_diff(cwd: String, turn: Option<u32>, html: bool) -> Result<()> {
+    let cwd = Path::new(&cwd);
+    let turns = list_turns(cwd);
+    let Some(turn) = turn else {
+        if turns.is_empty() {
+            println!("No turn diffs recorded in {}", cwd.join(".qernel").join("turns").display());
+        } else {
+            println!("Recorded turns:");
+            for t in &turns {
+                println!("  {}", t);
+            }
+            println!("\nShow one with `qernel diff --turn <n>`");
+        }
+        return Ok(());
+    };
+
+    let path = turn_diff_path(cwd, turn);
+    if !path.exists() {
+        let available = if turns.is_empty() {
+            "none".to_string()
+        } else {
+            turns.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ")
+        };
+        anyhow::bail!("no diff recorded for turn {} (available: {})", turn, available);
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let files = parse_unified_diff(&text);
+
+    if html {
+        let html_path = path.with_extension("html");
+        fs::write(&html_path, render_html(turn, &files))
+            .with_context(|| format!("failed to write {}", html_path.display()))?;
+        let ce = crate::util::color_enabled_stdout();
+        println!("{} Wrote side-by-side diff to {}", crate::util::sym_check(ce), html_path.display());
+    } else {
+        print!("{}", render_console(&files));
+    }
+    Ok(())
+}
+
+/// Split a multi-file unified diff into per-file side-by-side rows
+fn parse_unified_diff(text: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut in_hunk = false;
+    let (mut old_no, mut new_no) = (0, 0);
+    let mut removed: Vec<(usize, String)> = Vec::new();
+    let mut added: Vec<(usize, String)> = Vec::new();
+
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            if let Some(file) = files.last_mut() {
+                flush_changes(&mut file.rows, &mut removed, &mut added);
+            }
+            let path = rest.split_once(" b/").map(|(_, b)| b).unwrap_or(rest);
+            files.push(FileDiff { path: path.to_string(), added: 0, removed: 0, rows: Vec::new() });
+            in_hunk = false;
+            continue;
+        }
+        let Some(file) = files.last_mut() else { continue };
+        if line.starts_with("@@") {
+            flush_changes(&mut file.rows, &mut removed, &mut added);
+            (old_no, new_no) = parse_hunk_header(line);
+            file.rows.push(Row::Hunk(line.to_string()));
+            in_hunk = true;
+            continue;
+        }
+        if !in_hunk {
+            // ---/+++ file headers, or a binary file's size line in place of hunks
+            if line.starts_with("Binary file") {
+                file.rows.push(Row::Hunk(line.to_string()));
+            }
+            continue;
+        }
+        if let Some(s) = line.strip_prefix('-') {
+            removed.push((old_no, s.to_string()));
+            file.removed += 1;
+            old_no += 1;
+        } else if let Some(s) = line.strip_prefix('+') {
+            added.push((new_no, s.to_string()));
+            file.added += 1;
+            new_no += 1;
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+        } else {
+            flush_changes(&mut file.rows, &mut removed, &mut added);
+            let s = line.strip_prefix(' ').unwrap_or(line).to_string();
+            file.rows.push(Row::Line { left: Some((old_no, s.clone())), right: Some((new_no, s)), changed: false });
+            old_no += 1;
+            new_no += 1;
+        }
+    }
+    if let Some(file) = files.last_mut() {
+        flush_changes(&mut file.rows, &mut removed, &mut added);
+    }
+    files
+}
+
+/// Pair a run of removed lines with the additions that replace them
+fn flush_changes(rows: &mut Vec<Row>, removed: &mut Vec<(usize, String)>, added: &mut Vec<(usize, String)>) {
+    let n = removed.len().max(added.len());
+    let mut left = removed.drain(..);
+    let mut right = added.drain(..);
+    for _ in 0..n {
+        rows.push(Row::Line { left: left.next(), right: right.next(), changed: true });
+    }
+}
+
+/// Starting line numbers from `@@ -a,b +c,d @@`
+fn parse_hunk_header(line: &str) -> (usize, usize) {
+    let mut parts = line.split_whitespace().skip(1);
+    let start = |p: Option<&str>, sign: char| {
+        p.and_then(|s| s.strip_prefix(sign))
+            .and_then(|s| s.split(',').next())
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1)
+    };
+    let old = start(parts.next(), '-');
+    let new = start(parts.next(), '+');
+    (old, new)
+}
+
+fn render_console(files: &[FileDiff]) -> String {
+    let ce = crate::util::color_enabled_stdout();
+    let total_width = std::env::var("COLUMNS").ok().and_then(|c| c.parse::<usize>().ok()).unwrap_or(120);
+    // "NNNN " gutter on each side plus " │ " between the columns
+    let text_width = (total_width.saturating_sub(3) / 2).saturating_sub(5).max(10);
+    let mut out = String::new();
+    for file in files {
+        let title = format!("{} (+{} -{})", file.path, file.added, file.removed);
+        if ce {
+            out.push_str(&format!("{}\n", title.bold()));
+        } else {
+            out.push_str(&format!("{}\n", title));
+        }
+        for row in &file.rows {
+            match row {
+                Row::Hunk(header) => {
+                    if ce {
+                        out.push_str(&format!("{}\n", header.cyan()));
+                    } else {
+                        out.push_str(&format!("{}\n", header));
+                    }
+                }
+                Row::Line { left, right, changed } => {
+                    let l = console_cell(left.as_ref(), text_width);
+                    let r = console_cell(right.as_ref(), text_width);
+                    if ce && *changed {
+                        // Changed words inside a modified line stand out in reverse video
+                        let (l, r) = match row_words(row) {
+                            Some((old_words, new_words)) => (
+                                marked_console_cell(left.as_ref(), &old_words, text_width, |t, m| if m { t.red().reversed().to_string() } else { t.red().to_string() }),
+                                marked_console_cell(right.as_ref(), &new_words, text_width, |t, m| if m { t.green().reversed().to_string() } else { t.green().to_string() }),
+                            ),
+                            None => (
+                                if left.is_some() { l.red().to_string() } else { l },
+                                if right.is_some() { r.green().to_string() } else { r },
+                            ),
+                        };
+                        out.push_str(&format!("{} │ {}\n", l, r.trim_end()));
+                    } else {
+                        out.push_str(&format!("{} │ {}\n", l, r.trim_end()));
+                    }
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Fixed-width "line-number text" cell, truncated to fit the column
+fn console_cell(cell: Option<&(usize, String)>, width: usize) -> String {
+    match cell {
+        Some((no, text)) => {
+            let text: String = text.replace('\t', "    ").chars().take(width).collect();
+            let pad = width - text.chars().count();
+            format!("{:>4} {}{}", no, text, " ".repeat(pad))
+        }
+        None => " ".repeat(width + 5),
+    }
+}
+
+/// Like [`console_cell`], with each segment of the text styled by `style(text, changed)`
+fn marked_console_cell(cell: Option<&(usize, String)>, segments: &Segments, width: usize, style: impl Fn(&str, bool) -> String) -> String {
+    let Some((no, _)) = cell else {
+        return " ".repeat(width + 5);
+    };
+    let mut text = String::new();
+    let mut left = width;
+    for (changed, segment) in segments {
+        let part: String = segment.replace('\t', "    ").chars().take(left).collect();
+        left -= part.chars().count();
+        if !part.is_empty() {
+            text.push_str(&style(&part, *changed));
+        }
+    }
+    format!("{:>4} {}{}", no, text, " ".repeat(left))
+}
+
+/// Word diff of a modified line shown with its replacement on the same row
+fn row_words(row: &Row) -> Option<(Segments, Segments)> {
+    match row {
+        Row::Line { left: Some((_, old)), right: Some((_, new)), changed: true } => word_diff(old, new),
+        _ => None,
+    }
+}
+
+fn render_html(turn: u32, files: &[FileDiff]) -> String {
+    let mut html = String::new();
+    html.push_str("<!doctype html>\n<html><head><meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>Turn {} diff</title>\n", turn));
+    html.push_str(concat!(
+        "<style>\n",
+        "body{font-family:system-ui,sans-serif;margin:0;display:flex;color:#222}\n",
+        "nav{width:240px;flex-shrink:0;border-right:1px solid #ddd;padding:1em;height:100vh;overflow:auto;position:sticky;top:0;box-sizing:border-box}\n",
+        "nav ul{list-style:none;padding-left:0}\n",
+        "nav li{margin:.3em 0;word-break:break-all}\n",
+        "nav .stat{color:#888;font-size:.85em}\n",
+        "main{flex:1;padding:1em;overflow:auto}\n",
+        "table{border-collapse:collapse;width:100%;font-family:ui-monospace,monospace;font-size:13px;table-layout:fixed;margin-bottom:2em}\n",
+        "td{padding:0 .5em;white-space:pre-wrap;vertical-align:top}\n",
+        "td.no{width:3.5em;text-align:right;color:#999;user-select:none}\n",
+        "td.del{background:#fde8e8}\n",
+        "td.add{background:#e6f6e6}\n",
+        "td.del .chg{background:#f9b4b4}\n",
+        "td.add .chg{background:#a6e3a6}\n",
+        "tr.hunk td{background:#eef3fb;color:#557}\n",
+        "h2{font-size:1.05em;border-bottom:1px solid #ddd;padding-bottom:.3em}\n",
+    ));
+    html.push_str("</style></head><body>\n<nav>\n");
+    html.push_str(&format!("<h3>Turn {}</h3>\n<ul>\n", turn));
+    for (i, file) in files.iter().enumerate() {
+        html.push_str(&format!(
+            "<li><a href=\"#f{}\">{}</a> <span class=\"stat\">+{} -{}</span></li>\n",
+            i, escape_html(&file.path), file.added, file.removed
+        ));
+    }
+    html.push_str("</ul>\n</nav>\n<main>\n");
+    for (i, file) in files.iter().enumerate() {
+        html.push_str(&format!("<h2 id=\"f{}\">{}</h2>\n<table>\n", i, escape_html(&file.path)));
+        for row in &file.rows {
+            match row {
+                Row::Hunk(header) => {
+                    html.push_str(&format!("<tr class=\"hunk\"><td colspan=\"4\">{}</td></tr>\n", escape_html(header)));
+                }
+                Row::Line { left, right, changed } => {
+                    let (old_words, new_words) = row_words(row).unzip();
+                    html.push_str("<tr>");
+                    html.push_str(&html_cell(left.as_ref(), if *changed { "del" } else { "" }, old_words.as_ref()));
+                    html.push_str(&html_cell(right.as_ref(), if *changed { "add" } else { "" }, new_words.as_ref()));
+                    html.push_str("</tr>\n");
+                }
+            }
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</main>\n</body></html>\n");
+    html
+}
+
+/// A line-number and text cell; with `words`, changed words are wrapped in `<span class="chg">`
+fn html_cell(cell: Option<&(usize, String)>, class: &str, words: Option<&Segments>) -> String {
+    match cell {
+        Some((no, text)) => {
+            let body = match words {
+                Some(segments) => segments
+                    .iter()
+                    .map(|(changed, t)| if *changed { format!("<span class=\"chg\">{}</span>", escape_html(t)) } else { escape_html(t) })
+                    .collect(),
+                None => escape_html(text),
+            };
+            format!("<td class=\"no\">{}</td><td class=\"{}\">{}</td>", no, class, body)
+        }
+        None => "<td class=\"no\"></td><td></td>".to_string(),
+    }
+}
@@ -0,0 +1,298 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+use crate::cmd::prototype::environment::{conda_exe, PythonEnv};
+use crate::config::load_config;
+
+/// Create (or refresh) the project environment for its `python.env_manager`, install its
+/// dependencies and verify the interpreter
+pub fn handle_env_setup(cwd: String, recreate: bool) -> Result<()> {
+    let cwd_path = Path::new(&cwd);
+    let project = cwd_path.canonicalize().unwrap_or_else(|_| cwd_path.to_path_buf());
+    let qernel_dir = project.join(".qernel");
+    if !qernel_dir.is_dir() {
+        anyhow::bail!("No .qernel directory in {}. Create a project with 'qernel new --template' first.", project.display());
+    }
+    let config = load_config(&qernel_dir.join("qernel.yaml"))?;
+    let python_env = PythonEnv::detect(&project)?;
+
+    let version = setup_python_env(&project, &python_env, config.python_version.as_deref(), recreate)?;
+    let ce = crate::util::color_enabled_stdout();
+    println!("{} Environment ready: Python {} in {}", crate::util::sym_check(ce), version, python_env.describe());
+    Ok(())
+}
+
+/// Create or update `python_env` (deleting it first with `recreate`) and check its interpreter
+/// against `python_version`; returns the interpreter version
+pub fn setup_python_env(project: &Path, python_env: &PythonEnv, python_version: Option<&str>, recreate: bool) -> Result<String> {
+    let ce = crate::util::color_enabled_stdout();
+    match python_env {
+        PythonEnv::Venv(venv_dir) => {
+            if recreate && venv_dir.exists() {
+                fs::remove_dir_all(venv_dir).with_context(|| format!("failed to remove {}", venv_dir.display()))?;
+            }
+            let python = bootstrap_venv(project, python_version)?;
+            return Ok(interpreter_version(&python.to_string_lossy(), &[]).unwrap_or_else(|| "unknown".to_string()));
+        }
+        PythonEnv::Conda { name, file } => {
+            let conda = conda_exe();
+            let exists = conda_env_exists(&conda, name)?;
+            if recreate && exists {
+                println!("{} Removing conda environment '{}'", crate::util::sym_check(ce), name);
+                run_tool(Command::new(&conda).args(["env", "remove", "-y", "-n", name]))?;
+            }
+            if exists && !recreate {
+                println!("{} Updating conda environment '{}' from {}", crate::util::sym_check(ce), name, file.display());
+                run_tool(Command::new(&conda).args(["env", "update", "--prune", "-n", name, "-f"]).arg(file))?;
+            } else {
+                println!("{} Creating conda environment '{}' from {}", crate::util::sym_check(ce), name, file.display());
+                run_tool(Command::new(&conda).args(["env", "create", "-n", name, "-f"]).arg(file))?;
+            }
+        }
+        PythonEnv::Uv(root) => {
+            let venv_dir = root.join(".venv");
+            if recreate && venv_dir.exists() {
+                fs::remove_dir_all(&venv_dir).with_context(|| format!("failed to remove {}", venv_dir.display()))?;
+            }
+            println!("{} Syncing uv project at {}", crate::util::sym_check(ce), root.display());
+            let mut sync = Command::new("uv");
+            sync.arg("sync").current_dir(root);
+            if let Some(spec) = python_version {
+                sync.args(["--python", spec]);
+            }
+            run_tool(&mut sync)?;
+        }
+    }
+
+    let runner = python_env.runner();
+    let mut pre: Vec<&str> = runner[1..].iter().map(String::as_str).collect();
+    pre.push("python");
+    let found = interpreter_version(&runner[0], &pre)
+        .ok_or_else(|| anyhow::anyhow!("Could not run python in the {}", python_env.describe()))?;
+    if let Some(spec) = python_version
+        && !version_satisfies(&found, spec)
+    {
+        anyhow::bail!(
+            "The {} uses Python {} but qernel.yaml requires python_version '{}'. Pin a matching python in the environment and run `qernel env setup --recreate`.",
+            python_env.describe(),
+            found,
+            spec
+        );
+    }
+    Ok(found)
+}
+
+/// Run conda or uv with inherited output, failing on a non-zero exit
+fn run_tool(cmd: &mut Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let status = cmd.status().with_context(|| format!("failed to run {}; is it installed?", program))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// Whether `conda env list` has an environment called `name`
+fn conda_env_exists(conda: &str, name: &str) -> Result<bool> {
+    let out = Command::new(conda)
+        .args(["env", "list", "--json"])
+        .output()
+        .with_context(|| format!("failed to run {}; is conda installed?", conda))?;
+    if !out.status.success() {
+        anyhow::bail!("`{} env list` failed: {}", conda, String::from_utf8_lossy(&out.stderr).trim());
+    }
+    let list: serde_json::Value = serde_json::from_slice(&out.stdout).context("unexpected `conda env list --json` output")?;
+    let envs = list.get("envs").and_then(|e| e.as_array()).cloned().unwrap_or_default();
+    Ok(envs.iter().filter_map(|e| e.as_str()).any(|path| Path::new(path).file_name().is_some_and(|f| f == name)))
+}
+
+/// Create `.qernel/.venv` if missing, install `.qernel/requirements.txt`, and check the
+/// interpreter against the project's `python_version` requirement (e.g. ">=3.10" or "3.11").
+pub fn bootstrap_venv(project_path: &Path, python_version: Option<&str>) -> Result<PathBuf> {
+    // Always use an absolute project path to avoid nested <proj>/<proj>/.qernel/.venv
+    let project_abs = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+
+    let venv_dir = project_abs.join(".qernel").join(".venv");
+    fs::create_dir_all(venv_dir.parent().unwrap())
+        .with_context(|| format!("failed to create {}", venv_dir.parent().unwrap().display()))?;
+
+    let ce = crate::util::color_enabled_stdout();
+    let vpy = venv_python(&venv_dir);
+    if vpy.exists() {
+        println!("{} Using existing project venv at: {}", crate::util::sym_check(ce), venv_dir.display());
+    } else {
+        println!("{} Creating project venv at: {}", crate::util::sym_check(ce), venv_dir.display());
+        create_venv(&venv_dir, python_version)?;
+    }
+
+    if let Some(spec) = python_version {
+        let found = interpreter_version(&vpy.to_string_lossy(), &[])
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the Python version of {}", vpy.display()))?;
+        if !version_satisfies(&found, spec) {
+            anyhow::bail!(
+                "Project venv uses Python {} but qernel.yaml requires python_version '{}'. Install a matching interpreter and run `qernel env setup --recreate`.",
+                found,
+                spec
+            );
+        }
+    }
+
+    // Upgrading the packaging toolchain is best-effort
+    let _ = Command::new(&vpy)
+        .args(["-m", "pip", "install", "-U", "pip", "setuptools", "wheel"])
+        .status();
+
+    let req = project_abs.join(".qernel").join("requirements.txt");
+    if req.exists() {
+        let status = Command::new(&vpy)
+            .args(["-m", "pip", "install", "-r"])
+            .arg(&req)
+            .status()
+            .context("failed to run pip")?;
+        if !status.success() {
+            anyhow::bail!("pip install -r {} failed", req.display());
+        }
+    }
+
+    Ok(vpy)
+}
+
+fn create_venv(venv_dir: &Path, python_version: Option<&str>) -> Result<()> {
+    // Use absolute target for venv creation
+    let venv_arg = venv_dir.to_string_lossy().to_string();
+
+    // Prefer an interpreter matching the requested version, then python3, python, and Windows py -3
+    let mut candidates: Vec<(String, Vec<String>)> = Vec::new();
+    if let Some(minor) = python_version.and_then(requested_minor) {
+        candidates.push((format!("python{}", minor), vec![]));
+        candidates.push(("py".to_string(), vec![format!("-{}", minor)]));
+    }
+    candidates.push(("python3".to_string(), vec![]));
+    candidates.push(("python".to_string(), vec![]));
+    candidates.push(("py".to_string(), vec!["-3".to_string()]));
+
+    for (prog, pre) in candidates.iter() {
+        if let Some(spec) = python_version {
+            let pre_refs: Vec<&str> = pre.iter().map(String::as_str).collect();
+            match interpreter_version(prog, &pre_refs) {
+                Some(v) if version_satisfies(&v, spec) => {}
+                _ => continue,
+            }
+        }
+        let status = Command::new(prog)
+            .args(pre)
+            .args(["-m", "venv", &venv_arg])
+            .status();
+
+        if matches!(status, Ok(s) if s.success()) {
+            return Ok(());
+        }
+    }
+    match python_version {
+        Some(spec) => anyhow::bail!("Failed to create virtual environment in {}: no Python interpreter matching '{}' found", venv_dir.display(), spec),
+        None => anyhow::bail!("Failed to create virtual environment in {}", venv_dir.display()),
+    }
+}
+
+pub fn venv_python(venv_dir: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv_dir.join("Scripts").join("python.exe")
+    } else {
+        venv_dir.join("bin").join("python")
+    }
+}
+
+/// Return "major.minor.patch" for an interpreter, or None if it can't be run
+pub fn interpreter_version(program: &str, pre: &[&str]) -> Option<String> {
+    let out = Command::new(program)
+        .args(pre)
+        .args(["-c", "import sys; print('%d.%d.%d' % sys.version_info[:3])"])
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let v = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    if v.is_empty() { None } else { Some(v) }
+}
+
+/// "3.11" or "==3.11" or ">=3.11" -> Some("3.11"), used to look for `python3.11` on PATH
+fn requested_minor(spec: &str) -> Option<String> {
+    let v = spec.trim().trim_start_matches(['>', '<', '=', '~', '^']).trim();
+    let parts: Vec<&str> = v.split('.').take(2).collect();
+    if parts.len() == 2 && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()) && !p.is_empty()) {
+        Some(parts.join("."))
+    } else {
+        None
+    }
+}
+
+fn parse_version(v: &str) -> Vec<u64> {
+    v.trim()
+        .trim_end_matches(".*")
+        .split('.')
+        .map(|p| p.parse::<u64>().unwrap_or(0))
+        .collect()
+}
+
+/// Check a "major.minor.patch" version against a requirement like ">=3.10", "<3.13",
+/// "==3.11.4", "~=3.10" or a bare prefix such as "3.11". Comma-separated requirements must
+/// all hold.
+pub fn version_satisfies(version: &str, spec: &str) -> bool {
+    let have = parse_version(version);
+    spec.split(',').map(str::trim).filter(|s| !s.is_empty()).all(|req| {
+        let (op, want) = ["==", ">=", "<=", "!=", "~=", ">", "<", "="]
+            .iter()
+            .find_map(|op| req.strip_prefix(op).map(|rest| (*op, rest)))
+            .unwrap_or(("", req));
+        let want_parts = parse_version(want);
+        // Compare only as many components as the requirement specifies
+        let have_cut: Vec<u64> = (0..want_parts.len()).map(|i| have.get(i).copied().unwrap_or(0)).collect();
+        match op {
+            ">=" => have_cut >= want_parts,
+            "<=" => have_cut <= want_parts,
+            ">" => have_cut > want_parts,
+            "<" => have_cut < want_parts,
+            "!=" => have_cut != want_parts,
+            // Compatible release: "~=3.10" is ">=3.10, ==3.*", "~=3.10.2" is ">=3.10.2, ==3.10.*"
+            "~=" => {
+                let fixed = (want_parts.len() - 1).max(1);
+                have_cut >= want_parts && have_cut[..fixed] == want_parts[..fixed]
+            }
+            _ => have_cut == want_parts,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_requirements() {
+        assert!(version_satisfies("3.11.4", ">=3.10"));
+        assert!(!version_satisfies("3.9.18", ">=3.10"));
+        assert!(version_satisfies("3.12.1", ">=3.10, <3.13"));
+        assert!(!version_satisfies("3.13.0", ">=3.10, <3.13"));
+        assert!(version_satisfies("3.11.4", "3.11"));
+        assert!(version_satisfies("3.11.4", "==3.11.*"));
+        assert!(!version_satisfies("3.12.0", "3.11"));
+    }
+
+    #[test]
+    fn compatible_release_fixes_all_but_the_last_component() {
+        assert!(version_satisfies("3.10.0", "~=3.10"));
+        assert!(version_satisfies("3.12.3", "~=3.10"));
+        assert!(!version_satisfies("3.9.18", "~=3.10"));
+        assert!(!version_satisfies("4.0.0", "~=3.10"));
+        assert!(version_satisfies("3.10.5", "~=3.10.2"));
+        assert!(!version_satisfies("3.10.1", "~=3.10.2"));
+        assert!(!version_satisfies("3.11.0", "~=3.10.2"));
+        assert_eq!(requested_minor("~=3.11"), Some("3.11".to_string()));
+    }
+}
@@ -1,8 +1,18 @@
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use std::path::Path;
-use tree_sitter::{Node, Parser};
+use tree_sitter::{Node, Parser, Query, QueryCursor};
 use tree_sitter_python as tspy;
 
+/// Called names: `f(...)` and `obj.method(...)` capture `f` and `method`
+static CALL_QUERY: Lazy<Query> = Lazy::new(|| {
+    Query::new(
+        &tspy::language(),
+        "(call function: [(identifier) @callee (attribute attribute: (identifier) @callee)])",
+    )
+    .expect("valid python call query")
+});
+
 #[derive(Clone, Copy, Debug)]
 pub enum ChunkGranularity { Function, Class, Block }
 
@@ -14,6 +24,21 @@ pub struct PythonChunk {
     pub start_line: usize,
     pub end_line: usize,
     pub code: String,
+    /// Definition line(s) up to the body, e.g. `def energy(h, shots=1000):`
+    pub signature: String,
+    /// Names of the functions and methods this snippet calls, in order of first call
+    pub calls: Vec<String>,
+}
+
+impl PythonChunk {
+    /// Other snippets that call this one, and the snippets it calls. Calls are matched by
+    /// name within the file, so a method call matches any definition with that name.
+    pub fn callers_and_callees<'a>(&self, all: &'a [PythonChunk]) -> (Vec<&'a PythonChunk>, Vec<&'a PythonChunk>) {
+        let others = || all.iter().filter(move |c| c.id != self.id && c.kind != "block");
+        let callers = others().filter(|c| c.calls.contains(&self.name)).collect();
+        let callees = others().filter(|c| self.calls.contains(&c.name)).collect();
+        (callers, callees)
+    }
 }
 
 pub fn chunk_python_or_fallback(content: &str, path: &Path, granularity: ChunkGranularity) -> Result<Vec<PythonChunk>> {
@@ -70,7 +95,8 @@ pub fn chunk_python_or_fallback(content: &str, path: &Path, granularity: ChunkGr
         let kind = if is_class { "class" } else { "function" }.to_string();
         idx += 1;
         let id = format!("{}::{}:{}", filename, kind, idx);
-        chunks.push(PythonChunk { id, name, kind, start_line: start, end_line: end, code });
+        let signature = trimmed.to_string();
+        chunks.push(PythonChunk { id, name, kind, start_line: start, end_line: end, code, signature, calls: Vec::new() });
     }
 
     if chunks.is_empty() {
@@ -83,6 +109,8 @@ pub fn chunk_python_or_fallback(content: &str, path: &Path, granularity: ChunkGr
             start_line: 1,
             end_line: total,
             code: content.to_string(),
+            signature: String::new(),
+            calls: Vec::new(),
         });
     }
 
@@ -126,7 +154,8 @@ fn collect_defs(content: &str, filename: &str, node: Node, granularity: ChunkGra
                 let end = range.end_point.row + 1;
                 let code = slice_lines(content, start, end);
                 let id = format!("{}::function:{}", filename, *idx_fn);
-                chunks.push(PythonChunk { id, name, kind: "function".to_string(), start_line: start, end_line: end, code });
+                let (signature, calls) = (signature(content, node), calls(content, node));
+                chunks.push(PythonChunk { id, name, kind: "function".to_string(), start_line: start, end_line: end, code, signature, calls });
             }
         }
         "class_definition" => {
@@ -141,7 +170,8 @@ fn collect_defs(content: &str, filename: &str, node: Node, granularity: ChunkGra
                 let end = range.end_point.row + 1;
                 let code = slice_lines(content, start, end);
                 let id = format!("{}::class:{}", filename, *idx_cls);
-                chunks.push(PythonChunk { id, name, kind: "class".to_string(), start_line: start, end_line: end, code });
+                let (signature, calls) = (signature(content, node), calls(content, node));
+                chunks.push(PythonChunk { id, name, kind: "class".to_string(), start_line: start, end_line: end, code, signature, calls });
             }
         }
         _ => {}
@@ -154,6 +184,28 @@ fn collect_defs(content: &str, filename: &str, node: Node, granularity: ChunkGra
     }
 }
 
+/// Source of a definition from `def`/`class` up to the start of its body
+fn signature(content: &str, node: Node) -> String {
+    let end = node.child_by_field_name("body").map(|b| b.start_byte()).unwrap_or(node.end_byte());
+    let text = content.get(node.start_byte()..end).unwrap_or("");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn calls(content: &str, node: Node) -> Vec<String> {
+    let mut cursor = QueryCursor::new();
+    let mut calls: Vec<String> = Vec::new();
+    for (m, _) in cursor.captures(&CALL_QUERY, node, content.as_bytes()) {
+        for capture in m.captures {
+            if let Ok(name) = capture.node.utf8_text(content.as_bytes())
+                && !calls.iter().any(|c| c == name)
+            {
+                calls.push(name.to_string());
+            }
+        }
+    }
+    calls
+}
+
 fn slice_lines(content: &str, start: usize, end: usize) -> String {
     content.lines().skip(start - 1).take(end - start + 1).collect::<Vec<_>>().join("\n")
 }
@@ -2,7 +2,9 @@ mod run;
 pub mod chunk;
 pub mod prompts;
 pub mod renderer;
-mod network;
+pub mod network;
+pub mod since;
+pub mod tree;
 
 pub use run::handle_explain;
 
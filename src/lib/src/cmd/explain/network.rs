@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use crate::cmd::common::endpoint::{Backend, ollama_host};
+use crate::cmd::common::models::{capabilities, ModelProvider};
+use crate::cmd::common::network::send_rate_limited;
+use crate::cmd::common::usage;
+use crate::cmd::prototype::audit;
+use crate::config::Sampling;
+use crate::error::QernelError;
+
+/// Models served by a local Ollama instance: `ollama/<name>`, Ollama `name:tag` names like
+/// `llama3.1:8b` (but not OpenAI `ft:` fine-tunes), or anything a models.yaml override
+/// assigns to `provider: ollama`
+pub fn is_local_model(model: &str) -> bool {
+    capabilities(model).provider == ModelProvider::Ollama
+}
+
+pub fn call_text_model(api_key: &str, model: &str, system: &str, user: &str, sampling: &Sampling) -> Result<String> {
+    use reqwest::blocking::Client;
+    if is_local_model(model) {
+        return call_ollama_model(model.trim_start_matches("ollama/"), system, user, sampling);
+    }
+    let backend = Backend::active()?;
+    if api_key.is_empty() { anyhow::bail!("{} API key is empty", backend.label()); }
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .context("create http client")?;
+
+    // Use Responses API for consistency with existing code
+    let input = vec![
+        json!({"role":"system","content":system}),
+        json!({"role":"user","content":user}),
+    ];
+
+    let mut request = json!({
+        "model": model,
+        "input": input,
+        "parallel_tool_calls": false
+    });
+    sampling.apply_to_responses(&mut request, &capabilities(model));
+    let resp = send_rate_limited(backend.name(), || backend.responses(&client, api_key, &request))?;
+
+    let status = resp.status();
+    let text = resp.text().unwrap_or_default();
+    if !status.is_success() {
+        return Err(QernelError::Provider { provider: backend.label().to_string(), status: status.as_u16(), body: text }.into());
+    }
+    let body: serde_json::Value = serde_json::from_str(&text).context("parse openai json")?;
+    usage::record(model, &body);
+    audit::record_model_request(model, Some(&request), &body);
+
+    // Prefer output_text, else join message content
+    if let Some(s) = body.get("output_text").and_then(|v| v.as_str()) {
+        return Ok(s.to_string());
+    }
+    if let Some(arr) = body.get("output").and_then(|v| v.as_array()) {
+        // Try to concatenate text parts
+        let mut buf = String::new();
+        for item in arr {
+            if item.get("type").and_then(|v| v.as_str()) == Some("message") {
+                if let Some(parts) = item.get("content").and_then(|v| v.as_array()) {
+                    for p in parts {
+                        if let Some(t) = p.get("text").and_then(|t| t.as_str()) { buf.push_str(t); }
+                    }
+                }
+            }
+        }
+        if !buf.is_empty() { return Ok(buf); }
+    }
+    anyhow::bail!("No text in OpenAI response")
+}
+
+/// Chat completion against the local Ollama server at `ollama_host()`
+fn call_ollama_model(model: &str, system: &str, user: &str, sampling: &Sampling) -> Result<String> {
+    use reqwest::blocking::Client;
+    let host = ollama_host();
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .context("create http client")?;
+
+    let url = format!("{}/api/chat", host);
+    let request = json!({
+        "model": model,
+        "stream": false,
+        "options": sampling.ollama_options(),
+        "messages": [
+            {"role":"system","content":system},
+            {"role":"user","content":user},
+        ]
+    });
+    let resp = send_rate_limited("ollama", || client.post(&url).json(&request))
+        .with_context(|| format!("send ollama request to {}", host))?;
+
+    let status = resp.status();
+    let text = resp.text().unwrap_or_default();
+    if !status.is_success() {
+        return Err(QernelError::Provider { provider: "Ollama".to_string(), status: status.as_u16(), body: text }.into());
+    }
+    let body: serde_json::Value = serde_json::from_str(&text).context("parse ollama json")?;
+    usage::record(&format!("ollama/{}", model), &body);
+    audit::record_model_request(&format!("ollama/{}", model), Some(&request), &body);
+    body.get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow::anyhow!("No text in Ollama response"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparison_models_go_to_the_right_provider() {
+        assert!(is_local_model("llama3.1:8b"));
+        assert!(is_local_model("ollama/mistral"));
+        assert!(!is_local_model("gpt-5-codex"));
+        assert!(!is_local_model("ft:gpt-4o-mini:my-org::abc123"));
+    }
+}
@@ -0,0 +1,146 @@
+use super::chunk::PythonChunk;
+
+/// Most callers or callees listed in a snippet prompt
+const MAX_CALL_CONTEXT: usize = 10;
+
+/// What a snippet prompt includes besides the file itself
+#[derive(Default)]
+pub struct SnippetContext<'a> {
+    /// Summaries of the modules the file imports (directory mode)
+    pub imported: &'a str,
+    /// Diff hunks touching the snippet (`--since`)
+    pub diff: &'a str,
+}
+
+pub fn build_snippet_prompt(
+    filename: &str,
+    full_content: &str,
+    snip: &PythonChunk,
+    all: &[PythonChunk],
+    max_chars: Option<usize>,
+    large_file: bool,
+    context: &SnippetContext,
+) -> (String, String) {
+    let limit = if let Some(m) = max_chars { format!(" Limit your summary to at most {} characters.", m) } else { String::new() };
+    let system = format!(
+        "You are a precise code explainer. Explain ONLY the requested snippet. Consider Python semantics and the snippet's position within the entire file, including what calls it and what it calls. Do not propose changes or add code. Output MUST be strict JSON with exactly these keys: id, summary. No markdown, no code, no extra keys.{}",
+        limit
+    );
+
+    // Summaries of the modules this file imports, explained earlier in directory mode
+    let imported = if context.imported.is_empty() { String::new() } else { format!("[IMPORTED MODULES]\n{}\n\n", context.imported) };
+    let calls = call_context(snip, all);
+    let calls = if context.diff.is_empty() {
+        calls
+    } else {
+        format!("{}[CHANGES TO THIS SNIPPET]\n{}\nExplain what the snippet does now, then what these changes did to it.\n\n", calls, context.diff)
+    };
+
+    // Truncate full file for very large files; always include exact snippet.
+    let user = if large_file {
+        let lines: Vec<&str> = full_content.lines().collect();
+        let total = lines.len();
+        let head = 400.min(total);
+        let tail = 400.min(total.saturating_sub(head));
+        let mut truncated = String::new();
+        truncated.push_str(&lines[..head].join("\n"));
+        truncated.push_str("\n...\n[TRUNCATED]\n...\n");
+        if tail > 0 { truncated.push_str(&lines[total - tail..].join("\n")); }
+
+        let mut neighborhood = String::new();
+        let win = 120usize;
+        let start = snip.start_line.saturating_sub(1).saturating_sub(win);
+        let end = (snip.end_line + win).min(total);
+        neighborhood.push_str(&lines[start..end].join("\n"));
+
+        format!(
+            "Filename: {filename}\n\n[FILE CONTENT TRUNCATED]\n{truncated}\n\n[SNIPPET NEIGHBORHOOD]\n{neighborhood}\n\n{imported}{calls}[SNIPPET META]\nid: {id}\nname: {name}\nkind: {kind}\nlines: {lstart}-{lend}\n\n[SNIPPET CODE]\n{code}\n\n[RESPONSE FORMAT]\nReturn exactly this JSON on one line: {{\"id\":\"{id}\",\"summary\":\"<plain text summary only>\"}}",
+            filename=filename,
+            truncated=truncated,
+            neighborhood=neighborhood,
+            imported=imported,
+            calls=calls,
+            id=snip.id,
+            name=snip.name,
+            kind=snip.kind,
+            lstart=snip.start_line,
+            lend=snip.end_line,
+            code=snip.code
+        )
+    } else {
+        format!(
+            "Filename: {filename}\n\n[FILE CONTENT]\n{full}\n\n{imported}{calls}[SNIPPET META]\nid: {id}\nname: {name}\nkind: {kind}\nlines: {lstart}-{lend}\n\n[SNIPPET CODE]\n{code}\n\n[RESPONSE FORMAT]\nReturn exactly this JSON on one line: {{\"id\":\"{id}\",\"summary\":\"<plain text summary only>\"}}",
+            filename=filename,
+            full=full_content,
+            imported=imported,
+            calls=calls,
+            id=snip.id,
+            name=snip.name,
+            kind=snip.kind,
+            lstart=snip.start_line,
+            lend=snip.end_line,
+            code=snip.code
+        )
+    };
+
+    (system, user)
+}
+
+/// Signatures of the snippet's direct callers and callees in the file, so the summary can
+/// describe its role in the program
+fn call_context(snip: &PythonChunk, all: &[PythonChunk]) -> String {
+    let (callers, callees) = snip.callers_and_callees(all);
+    if callers.is_empty() && callees.is_empty() {
+        return String::new();
+    }
+    let list = |chunks: &[&PythonChunk]| {
+        let mut out: String = chunks
+            .iter()
+            .take(MAX_CALL_CONTEXT)
+            .map(|c| format!("- {} (lines {}-{})\n", c.signature, c.start_line, c.end_line))
+            .collect();
+        if chunks.len() > MAX_CALL_CONTEXT {
+            out.push_str(&format!("- ... and {} more\n", chunks.len() - MAX_CALL_CONTEXT));
+        }
+        if out.is_empty() { "- none\n".to_string() } else { out }
+    };
+    format!("[CALL CONTEXT]\nCalled by:\n{}Calls:\n{}\n", list(&callers), list(&callees))
+}
+
+/// Prompt for the architecture overview of a directory, from its per-file summaries.
+/// `modules` is (file, files it imports, summary) in dependency order.
+pub fn build_overview_prompt(root: &str, modules: &[(String, Vec<String>, String)]) -> (String, String) {
+    let system = "You are a precise code explainer. From per-module summaries of a Python code base, write an architecture overview: what the code does, its main components and how they depend on each other, and where to start reading. Do not propose changes. Output MUST be strict JSON with exactly these keys: id, summary. The summary may use plain paragraphs and '-' bullet lists. No code, no extra keys.".to_string();
+    let mut body = String::new();
+    for (file, imports, summary) in modules {
+        body.push_str(&format!("## {}\n", file));
+        if !imports.is_empty() {
+            body.push_str(&format!("imports: {}\n", imports.join(", ")));
+        }
+        body.push_str(summary.trim());
+        body.push_str("\n\n");
+    }
+    let user = format!(
+        "Directory: {root}\n\n[MODULES IN DEPENDENCY ORDER]\n{body}[SNIPPET META]\nid: overview\nname: {root}\nkind: directory\nlines: -\n\n[RESPONSE FORMAT]\nReturn exactly this JSON on one line: {{\"id\":\"overview\",\"summary\":\"<architecture overview>\"}}",
+        root = root,
+        body = body
+    );
+    (system, user)
+}
+
+/// Prompt for the combined summary of `--since`: what changed and why it matters, from the
+/// diff and the summaries of the snippets it touched
+pub fn build_changes_prompt(since: &str, snippets: &[(String, String)], diff: &str) -> (String, String) {
+    let system = "You are a precise code reviewer. From a diff and summaries of the functions and classes it touched, explain what changed and why it matters: the behavior that differs, what depends on it, and anything a reviewer should check. Do not propose new code. Output MUST be strict JSON with exactly these keys: id, summary. The summary may use plain paragraphs and '-' bullet lists. No code, no extra keys.".to_string();
+    let mut body = String::new();
+    for (header, summary) in snippets {
+        body.push_str(&format!("## {}\n{}\n\n", header, summary.trim()));
+    }
+    let user = format!(
+        "Changes since: {since}\n\n[DIFF]\n{diff}\n\n[CHANGED SNIPPETS]\n{body}[SNIPPET META]\nid: changes\nname: {since}\nkind: diff\nlines: -\n\n[RESPONSE FORMAT]\nReturn exactly this JSON on one line: {{\"id\":\"changes\",\"summary\":\"<what changed and why it matters>\"}}",
+        since = since,
+        diff = diff,
+        body = body
+    );
+    (system, user)
+}
@@ -0,0 +1,297 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::io::IsTerminal;
+
+use super::chunk::PythonChunk;
+use crate::cmd::common::theme::theme;
+use crate::cmd::prototype::console::ConsoleStreamer;
+use syntect::parsing::SyntaxSet;
+use syntect::highlighting::ThemeSet;
+use syntect::easy::HighlightLines;
+use syntect::util::as_24_bit_terminal_escaped;
+use once_cell::sync::Lazy;
+
+pub struct RenderOptions { pub pager: bool }
+
+static PS: Lazy<SyntaxSet> = Lazy::new(|| SyntaxSet::load_defaults_newlines());
+static TS: Lazy<ThemeSet> = Lazy::new(|| ThemeSet::load_defaults());
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+
+pub fn render_console(_file: &str, snip: &PythonChunk, explanation: &str) -> Result<String> {
+    let mut out = console_header(snip);
+    // Summary in default terminal color (explicit reset, no ANSI styling)
+    out.push_str(RESET);
+    out.push_str(explanation.trim());
+    out.push_str(RESET);
+    out.push('\n');
+    out.push('\n');
+    out.push_str(&console_code(snip));
+    Ok(out)
+}
+
+/// Render one snippet with each model's summary in its own column
+pub fn render_console_comparison(_file: &str, snip: &PythonChunk, summaries: &[(String, String)]) -> Result<String> {
+    let mut out = console_header(snip);
+    let total_width = std::env::var("COLUMNS").ok().and_then(|c| c.parse::<usize>().ok()).unwrap_or(120);
+    let gap = 3;
+    let n = summaries.len().max(1);
+    let col_width = (total_width.saturating_sub(gap * (n - 1)) / n).max(20);
+
+    let columns: Vec<Vec<String>> = summaries
+        .iter()
+        .map(|(model, summary)| {
+            let mut lines = vec![truncate_chars(model, col_width), "-".repeat(col_width.min(model.chars().count().max(3)))];
+            lines.extend(wrap_text(summary.trim(), col_width));
+            lines
+        })
+        .collect();
+    let rows = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+    for row in 0..rows {
+        let mut line = String::new();
+        for (ci, col) in columns.iter().enumerate() {
+            let cell = col.get(row).map(String::as_str).unwrap_or("");
+            let pad = col_width.saturating_sub(cell.chars().count());
+            if row == 0 {
+                line.push_str(&format!("{}{}{}", BOLD, cell, RESET));
+            } else {
+                line.push_str(cell);
+            }
+            if ci + 1 < columns.len() {
+                line.push_str(&" ".repeat(pad + gap));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out.push('\n');
+    out.push_str(&console_code(snip));
+    Ok(out)
+}
+
+/// The architecture overview of a directory, then its modules in the order they were explained
+pub fn render_console_overview(dir: &str, overview: &str, modules: &[(String, Vec<String>, String)]) -> String {
+    let mut out = format!("{}Architecture overview: {}{}\n\n", BOLD, dir, RESET);
+    out.push_str(overview.trim());
+    out.push_str("\n\n");
+    out.push_str(&format!("{}Modules in dependency order{}\n", BOLD, RESET));
+    for (file, imports, _) in modules {
+        if imports.is_empty() {
+            out.push_str(&format!("  {}\n", file));
+        } else {
+            out.push_str(&format!("  {}  (imports {})\n", file, imports.join(", ")));
+        }
+    }
+    out
+}
+
+/// Write `<dir>.architecture.md`: the overview, then each module with a link to its report
+pub fn render_markdown_overview(out_dir: &Path, dir: &str, overview: &str, modules: &[(String, Vec<String>, String)]) -> Result<()> {
+    let base = Path::new(dir).canonicalize().ok().and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string())).unwrap_or_else(|| "project".to_string());
+    let md_path = out_dir.join(format!("{}.architecture.md", base));
+    let mut md = format!("# Architecture overview: {}\n\n{}\n\n## Modules\n\n", dir, overview.trim());
+    for (file, imports, _) in modules {
+        md.push_str(&format!("- [{}]({}.md)", file, report_base(file)));
+        if !imports.is_empty() {
+            md.push_str(&format!(" imports {}", imports.join(", ")));
+        }
+        md.push('\n');
+    }
+    std::fs::write(&md_path, md).with_context(|| format!("write {}", md_path.display()))?;
+    Ok(())
+}
+
+/// `--since` summary: what changed and why it matters, then the snippets it touched
+pub fn render_console_changes(since: &str, summary: &str, snippets: &[(String, String)]) -> String {
+    let mut out = format!("{}Changes since {}{}\n\n", BOLD, since, RESET);
+    out.push_str(summary.trim());
+    out.push_str(&format!("\n\n{}Changed snippets{}\n", BOLD, RESET));
+    for (header, _) in snippets {
+        out.push_str(&format!("  {}\n", header));
+    }
+    out
+}
+
+/// Write `changes.md` with the `--since` summary and every changed snippet's summary
+pub fn render_markdown_changes(out_dir: &Path, since: &str, summary: &str, snippets: &[(String, String)]) -> Result<()> {
+    let md_path = out_dir.join("changes.md");
+    let mut md = format!("# Changes since {}\n\n{}\n\n## Changed snippets\n", since, summary.trim());
+    for (header, snippet_summary) in snippets {
+        md.push_str(&format!("\n### {}\n\n{}\n", header, snippet_summary.trim()));
+    }
+    std::fs::write(&md_path, md).with_context(|| format!("write {}", md_path.display()))?;
+    Ok(())
+}
+
+/// Greedy word wrap by character count
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for para in text.lines() {
+        let mut current = String::new();
+        for word in para.split_whitespace() {
+            let word = truncate_chars(word, width);
+            if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&word);
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+fn truncate_chars(s: &str, width: usize) -> String {
+    s.chars().take(width).collect()
+}
+
+fn console_header(snip: &PythonChunk) -> String {
+    let mut out = String::new();
+    // Padded header with a subtle background from the theme
+    let header = format!("[{} -> {}]  {} {}  (id={})", snip.start_line, snip.end_line, snip.kind, snip.name, snip.id);
+    out.push_str(&theme().header);
+    out.push(' ');
+    out.push_str(&header);
+    out.push(' ');
+    out.push_str(RESET);
+    out.push('\n');
+    out.push('\n');
+    out
+}
+
+fn console_code(snip: &PythonChunk) -> String {
+    let mut out = String::new();
+    // Syntax highlighted code with line numbers
+    // Force Python syntax highlighting per docs
+    let syntax = PS.find_syntax_by_token("Python").or_else(|| PS.find_syntax_by_extension("py")).unwrap_or(PS.find_syntax_plain_text());
+    let theme = theme().syntax.as_ref().or_else(|| TS.themes.get("InspiredGitHub")).or_else(|| TS.themes.get("base16-ocean.dark")).unwrap_or_else(|| TS.themes.values().next().expect("theme"));
+    let mut h = HighlightLines::new(syntax, theme);
+    for (i, line) in snip.code.lines().enumerate() {
+        let n = snip.start_line + i;
+        let ranges = h.highlight_line(line, &PS).unwrap_or_default();
+        let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
+        out.push_str(&format!("{:>6} | {}\n", n, escaped));
+    }
+    // Reset color after code block and add spacing
+    out.push_str("\x1b[0m\n\n");
+    out
+}
+
+/// Markdown with terminal syntax colors, for documents like `.qernel/report.md`
+pub fn highlight_markdown(text: &str) -> String {
+    let syntax = PS.find_syntax_by_extension("md").unwrap_or(PS.find_syntax_plain_text());
+    let theme = theme().syntax.as_ref().or_else(|| TS.themes.get("base16-ocean.dark")).unwrap_or_else(|| TS.themes.values().next().expect("theme"));
+    let mut h = HighlightLines::new(syntax, theme);
+    let mut out = String::new();
+    for line in syntect::util::LinesWithEndings::from(text) {
+        let ranges = h.highlight_line(line, &PS).unwrap_or_default();
+        out.push_str(&as_24_bit_terminal_escaped(&ranges[..], false));
+    }
+    out.push_str(RESET);
+    out
+}
+
+pub fn print_blocks(assembled: String, opts: &RenderOptions) -> Result<()> {
+    if opts.pager && std::io::stdout().is_terminal() {
+        return crate::cmd::common::pager::page(&crate::util::colorize_stdout(&assembled));
+    }
+    let console = ConsoleStreamer::new();
+    console.println(&assembled)?;
+    Ok(())
+}
+
+pub fn render_markdown_report(dir: &Path, file: &str, snip: &PythonChunk, explanation: &str) -> Result<()> {
+    let md_path = dir.join(format!("{}.md", report_base(file)));
+    let mut md = String::new();
+    md.push_str(&format!("\n### {}:{}-{} {} {}\n\n", file, snip.start_line, snip.end_line, snip.kind, snip.name));
+    md.push_str(&format!("_id: {}_\n\n", snip.id));
+    md.push_str(explanation.trim());
+    md.push_str("\n\n");
+    md.push_str("```python\n");
+    md.push_str(&format!("# lines {}-{}\n", snip.start_line, snip.end_line));
+    md.push_str(&snip.code);
+    md.push_str("\n```\n");
+
+    // Append per file
+    use std::fs::{OpenOptions};
+    let mut f = OpenOptions::new().create(true).append(true).open(&md_path).with_context(|| format!("open {}", md_path.display()))?;
+    use std::io::Write;
+    f.write_all(md.as_bytes())?;
+    Ok(())
+}
+
+/// Markdown report section with one subsection per model
+pub fn render_markdown_comparison(dir: &Path, file: &str, snip: &PythonChunk, summaries: &[(String, String)]) -> Result<()> {
+    let explanation = summaries
+        .iter()
+        .map(|(model, summary)| format!("#### {}\n\n{}", model, summary.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    render_markdown_report(dir, file, snip, &explanation)
+}
+
+/// Write `<stem>.compare.html` with one tab per model for every snippet
+pub fn render_html_comparison(dir: &Path, file: &str, models: &[String], blocks: &[(PythonChunk, Vec<(String, String)>)]) -> Result<()> {
+    let html_path = dir.join(format!("{}.compare.html", report_base(file)));
+    let mut html = String::new();
+    html.push_str("<!doctype html>\n<html><head><meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{} - model comparison</title>\n", escape_html(file)));
+    html.push_str(concat!(
+        "<style>\n",
+        "body{font-family:system-ui,sans-serif;max-width:960px;margin:2em auto;color:#222}\n",
+        ".snippet{border:1px solid #ddd;border-radius:6px;margin:1.5em 0;padding:0 1em 1em}\n",
+        ".tabs input{display:none}\n",
+        ".tabs label{display:inline-block;padding:.4em .9em;border:1px solid #ccc;border-bottom:none;border-radius:4px 4px 0 0;cursor:pointer;background:#f4f4f4}\n",
+        ".tabs input:checked+label{background:#fff;font-weight:bold}\n",
+        ".panel{display:none;border:1px solid #ccc;padding:.8em;white-space:pre-wrap}\n",
+        "pre{background:#f7f7f7;padding:.8em;overflow:auto}\n",
+    ));
+    // One `:checked ~ .panel-N` rule per model position
+    for i in 0..models.len() {
+        html.push_str(&format!(".tabs input.t{i}:checked~.p{i}{{display:block}}\n", i = i));
+    }
+    html.push_str("</style></head><body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n<p>Models: {}</p>\n", escape_html(file), escape_html(&models.join(", "))));
+
+    for (bi, (snip, summaries)) in blocks.iter().enumerate() {
+        html.push_str("<div class=\"snippet\">\n");
+        html.push_str(&format!(
+            "<h3>{}:{}-{} {} {}</h3>\n<div class=\"tabs\">\n",
+            escape_html(file), snip.start_line, snip.end_line, escape_html(&snip.kind), escape_html(&snip.name)
+        ));
+        for (mi, (model, _)) in summaries.iter().enumerate() {
+            let id = format!("s{}m{}", bi, mi);
+            html.push_str(&format!(
+                "<input type=\"radio\" class=\"t{mi}\" name=\"s{bi}\" id=\"{id}\"{checked}><label for=\"{id}\">{model}</label>\n",
+                mi = mi, bi = bi, id = id, checked = if mi == 0 { " checked" } else { "" }, model = escape_html(model)
+            ));
+        }
+        for (mi, (_, summary)) in summaries.iter().enumerate() {
+            html.push_str(&format!("<div class=\"panel p{}\">{}</div>\n", mi, escape_html(summary.trim())));
+        }
+        html.push_str("</div>\n");
+        html.push_str(&format!("<pre><code>{}</code></pre>\n</div>\n", escape_html(&snip.code)));
+    }
+    html.push_str("</body></html>\n");
+    std::fs::write(&html_path, html).with_context(|| format!("write {}", html_path.display()))?;
+    Ok(())
+}
+
+/// Report file name for a source file: its stem, or the package name for `__init__.py`
+fn report_base(file: &str) -> String {
+    let path = Path::new(file);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("report");
+    if stem == "__init__"
+        && let Some(package) = path.canonicalize().ok().and_then(|p| p.parent()?.file_name().map(|n| n.to_string_lossy().to_string()))
+    {
+        return package;
+    }
+    stem.to_string()
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
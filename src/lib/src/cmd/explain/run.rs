@@ -0,0 +1,331 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::chunk::{ChunkGranularity, PythonChunk, chunk_python_or_fallback};
+use super::prompts::{build_changes_prompt, build_overview_prompt, build_snippet_prompt, SnippetContext};
+use super::since::Changes;
+use super::tree::SourceTree;
+use super::network::{call_text_model, is_local_model};
+use crate::cmd::common::cache::{CacheCategory, cache_key, read_entry, write_entry};
+use crate::cmd::common::mock;
+use crate::cmd::common::endpoint::Backend;
+use crate::config::Sampling;
+use crate::util::get_api_key;
+use super::renderer::{render_console, render_console_changes, render_console_comparison, render_console_overview, render_html_comparison, render_markdown_changes, render_markdown_comparison, render_markdown_overview, render_markdown_report, RenderOptions};
+use serde::Deserialize;
+use indicatif::ProgressStyle;
+
+#[derive(Deserialize)]
+struct SnippetSummary { id: String, summary: String }
+
+/// Longest module summary passed on to the modules importing it
+const MAX_MODULE_SUMMARY_CHARS: usize = 2000;
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_explain(
+    files: Vec<String>,
+    per: String,
+    model: Option<String>,
+    models: Vec<String>,
+    markdown: bool,
+    output: Option<String>,
+    pager: bool,
+    max_chars: Option<usize>,
+    api_key_env: Option<String>,
+    since: Option<String>,
+) -> Result<()> {
+    let changes = since.as_deref().map(Changes::load).transpose()?;
+    // With --since and no paths, every changed Python file is explained
+    let files = match &changes {
+        Some(changes) if files.is_empty() => changes.paths.clone(),
+        _ => files,
+    };
+    if files.is_empty() {
+        match &changes {
+            Some(changes) => {
+                println!("No Python changes since {}.", changes.since);
+                return Ok(());
+            }
+            None => anyhow::bail!("no files provided"),
+        }
+    }
+
+    let granularity = match per.as_str() {
+        "function" => ChunkGranularity::Function,
+        "class" => ChunkGranularity::Class,
+        "block" => ChunkGranularity::Block,
+        other => anyhow::bail!("unsupported --per value: {}", other),
+    };
+
+    // Flags > env > the project's explain: section > user config > defaults
+    let resolver = crate::config::ConfigResolver::load(Path::new("."))?
+        .with_flag("explain.model", model.clone())
+        .with_flag("explain.max_chars", max_chars.map(|n| n.to_string()));
+    let max_chars = resolver.get_usize("explain.max_chars")?;
+    let max_workers = resolver.get_usize("explain.workers")?.unwrap_or(crate::config::DEFAULT_EXPLAIN_WORKERS).max(1);
+
+    // --models compares several models on every snippet; --model alone explains with one.
+    // Without either flag, explain.models compares and explain.model explains.
+    let models: Vec<String> = if !models.is_empty() {
+        models
+    } else if model.is_none() {
+        crate::config::load_config(&PathBuf::from(".qernel").join("qernel.yaml"))?.explain.models
+    } else {
+        Vec::new()
+    };
+    let models: Vec<String> = if models.is_empty() {
+        vec![resolver.get("explain.model")?.unwrap_or_default()]
+    } else {
+        models.into_iter().map(|m| m.trim().to_string()).filter(|m| !m.is_empty()).collect()
+    };
+    if models.is_empty() {
+        anyhow::bail!("--models needs at least one model name");
+    }
+    let comparing = models.len() > 1;
+
+    // Output dir for markdown
+    let output_dir = if markdown {
+        if let Some(o) = output.as_ref() {
+            Some(PathBuf::from(o))
+        } else {
+            Some(PathBuf::from(".qernel/explain"))
+        }
+    } else { None };
+
+    if let Some(dir) = output_dir.as_ref() { std::fs::create_dir_all(dir).ok(); }
+
+    // Directories expand to their Python files, each after the modules it imports
+    let mut targets: Vec<String> = Vec::new();
+    let mut imports: HashMap<String, Vec<String>> = HashMap::new();
+    let mut directories: Vec<String> = Vec::new();
+    for arg in files {
+        if !Path::new(&arg).is_dir() {
+            targets.push(arg);
+            continue;
+        }
+        let tree = SourceTree::scan(Path::new(&arg))?;
+        if tree.files.is_empty() {
+            anyhow::bail!("no Python files under {}", arg);
+        }
+        for file in &tree.files {
+            let deps = tree.imports.get(file).map(|d| d.iter().map(|p| p.display().to_string()).collect()).unwrap_or_default();
+            imports.insert(file.display().to_string(), deps);
+            targets.push(file.display().to_string());
+        }
+        directories.push(arg);
+    }
+    if let Some(changes) = &changes {
+        targets.retain(|f| changes.get(f).is_some());
+        if targets.is_empty() {
+            println!("No Python changes since {} in the given paths.", changes.since);
+            return Ok(());
+        }
+    }
+
+    let api_key = get_api_key(Backend::active()?.name(), api_key_env.as_deref()).unwrap_or_default();
+    // Per-file summaries from the first model, for importing modules and the overview
+    let mut module_summaries: HashMap<String, String> = HashMap::new();
+    // (header, first model's summary) of every changed snippet, for the --since summary
+    let mut changed_snippets: Vec<(String, String)> = Vec::new();
+
+    // For now, sequential per file; we can parallelize later with a concurrency cap.
+    for file in targets.clone() {
+        let path = PathBuf::from(&file);
+        let content = std::fs::read_to_string(&path).with_context(|| format!("read file {}", file))?;
+
+        // Large-file rule: warn if >1000 lines
+        let total_lines = content.lines().count();
+        let large_file = total_lines > 1000;
+        if large_file {
+            eprintln!("[WARNING] File {} exceeds 1000 lines; using truncated full-file context plus local window per snippet.", file);
+        }
+
+        let mut snippets: Vec<PythonChunk> = chunk_python_or_fallback(&content, &path, granularity)?;
+        let file_changes = changes.as_ref().and_then(|c| c.get(&file));
+        let all_snippets = snippets.clone();
+        if let Some(file_changes) = file_changes {
+            let filename = path.file_name().and_then(|s| s.to_str()).unwrap_or("<file>");
+            snippets = file_changes.select(snippets, &content, filename);
+        }
+        let imported = imports
+            .get(&file)
+            .map(|deps| {
+                deps.iter()
+                    .filter_map(|d| module_summaries.get(d).map(|s| format!("{}:\n{}", d, s)))
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            })
+            .unwrap_or_default();
+
+        // Concurrent per-snippet calls, bounded by explain.workers
+        let mut handles: Vec<std::thread::JoinHandle<(usize, usize, String)>> = Vec::new();
+        let mut results: Vec<Vec<Option<String>>> = vec![vec![None; models.len()]; snippets.len()];
+
+        // Progress bar for snippet processing (one request per snippet per model)
+        let pb = crate::util::progress_bar((snippets.len() * models.len()) as u64);
+        let unit = if comparing { "requests" } else { "snippets" };
+        pb.set_style(ProgressStyle::with_template(&format!("{{spinner:.green}} [{{elapsed_precise}}<{{eta_precise}}] {{bar:40.cyan/blue}} {{pos}}/{{len}} {}", unit))
+            .unwrap()
+            .progress_chars("=>-"));
+        // Keep spinner animating even when waiting on network calls
+        pb.enable_steady_tick(std::time::Duration::from_millis(120));
+
+        for (idx, snip) in snippets.iter().cloned().enumerate() {
+            let diff = file_changes.map(|c| c.diff_for(snip.start_line, snip.end_line)).unwrap_or_default();
+            let context = SnippetContext { imported: &imported, diff: &diff };
+            let (system, user) = build_snippet_prompt(&file, &content, &snip, &all_snippets, max_chars, large_file, &context);
+
+            for (m_idx, model) in models.iter().enumerate() {
+                if handles.len() >= max_workers
+                    && let Some(h) = handles.pop()
+                {
+                    let (i_done, m_done, txt) = h.join().unwrap_or((idx, m_idx, String::from("(error: join failed)")));
+                    results[i_done][m_done] = Some(txt);
+                    pb.inc(1);
+                }
+
+                let model_cl = model.clone();
+                let api_key_cl = api_key.clone();
+                let system = system.clone();
+                let user = user.clone();
+                let handle = std::thread::spawn(move || {
+                    let _span = tracing::info_span!("explain_snippet", model = %model_cl, snippet = idx).entered();
+                    let text = explain_request(&api_key_cl, &model_cl, &system, &user);
+                    (idx, m_idx, text)
+                });
+                handles.insert(0, handle);
+            }
+        }
+
+        for h in handles {
+            let (i_done, m_done, txt) = h.join().unwrap_or((0, 0, String::from("(error: join failed)")));
+            results[i_done][m_done] = Some(txt);
+            pb.inc(1);
+        }
+        pb.finish_and_clear();
+
+        // Assemble outputs in original order
+        let mut rendered_blocks: Vec<String> = Vec::with_capacity(snippets.len());
+        let mut comparison_blocks: Vec<(PythonChunk, Vec<(String, String)>)> = Vec::new();
+        for (i, snip) in snippets.iter().enumerate() {
+            let summaries: Vec<(String, String)> = models
+                .iter()
+                .zip(results[i].iter())
+                .map(|(m, r)| (m.clone(), summary_text(r.as_deref().unwrap_or("(no explanation)"))))
+                .collect();
+            if comparing {
+                rendered_blocks.push(render_console_comparison(&file, snip, &summaries)?);
+                if let Some(dir) = output_dir.as_ref() {
+                    render_markdown_comparison(dir, &file, snip, &summaries)?;
+                }
+                comparison_blocks.push((snip.clone(), summaries));
+            } else {
+                let summary = &summaries[0].1;
+                rendered_blocks.push(render_console(&file, snip, summary)?);
+                if let Some(dir) = output_dir.as_ref() {
+                    render_markdown_report(dir, &file, snip, summary)?;
+                }
+            }
+        }
+        if comparing && let Some(dir) = output_dir.as_ref() {
+            render_html_comparison(dir, &file, &models, &comparison_blocks)?;
+        }
+        let first: Vec<String> = results.iter().map(|r| summary_text(r[0].as_deref().unwrap_or(""))).collect();
+        if imports.contains_key(&file) {
+            module_summaries.insert(file.clone(), module_summary(&snippets, &first));
+        }
+        if changes.is_some() {
+            for (snip, summary) in snippets.iter().zip(&first) {
+                changed_snippets.push((format!("{}:{}-{} {} {}", file, snip.start_line, snip.end_line, snip.kind, snip.name), summary.clone()));
+            }
+        }
+
+        let options = RenderOptions { pager };
+        super::renderer::print_blocks(rendered_blocks.join("\n"), &options)?;
+    }
+
+    if let Some(changes) = &changes {
+        let (system, user) = build_changes_prompt(&changes.since, &changed_snippets, &changes.diff);
+        let spinner = crate::util::spinner();
+        spinner.set_message(format!("Summarizing the changes since {}", changes.since));
+        spinner.enable_steady_tick(std::time::Duration::from_millis(120));
+        let summary = summary_text(&explain_request(&api_key, &models[0], &system, &user));
+        spinner.finish_and_clear();
+
+        if let Some(out) = output_dir.as_ref() {
+            render_markdown_changes(out, &changes.since, &summary, &changed_snippets)?;
+        }
+        let options = RenderOptions { pager };
+        super::renderer::print_blocks(render_console_changes(&changes.since, &summary, &changed_snippets), &options)?;
+        // The architecture of only the changed modules says little; the change summary replaces it
+        return Ok(());
+    }
+
+    for dir in &directories {
+        let root = Path::new(dir);
+        let modules: Vec<(String, Vec<String>, String)> = targets
+            .iter()
+            .filter(|f| Path::new(f).starts_with(root))
+            .map(|f| (f.clone(), imports.get(f).cloned().unwrap_or_default(), module_summaries.get(f).cloned().unwrap_or_default()))
+            .collect();
+        let (system, user) = build_overview_prompt(dir, &modules);
+        let spinner = crate::util::spinner();
+        spinner.set_message(format!("Writing the architecture overview of {}", dir));
+        spinner.enable_steady_tick(std::time::Duration::from_millis(120));
+        let overview = summary_text(&explain_request(&api_key, &models[0], &system, &user));
+        spinner.finish_and_clear();
+
+        if let Some(out) = output_dir.as_ref() {
+            render_markdown_overview(out, dir, &overview, &modules)?;
+        }
+        let options = RenderOptions { pager };
+        super::renderer::print_blocks(render_console_overview(dir, &overview, &modules), &options)?;
+    }
+
+    Ok(())
+}
+
+/// One model request, served from the cache when the same model and prompt were seen before
+fn explain_request(api_key: &str, model: &str, system: &str, user: &str) -> String {
+    // Responses are cached by model and prompt, so unchanged snippets are free to re-explain
+    let entry = format!("{}.txt", cache_key(&[model.as_bytes(), system.as_bytes(), user.as_bytes()]));
+    // Canned replies (QERNEL_MOCK=1, or no key for a hosted model) never touch the cache
+    let mocked = mock::enabled() || (api_key.is_empty() && !is_local_model(model));
+    let cached = if mocked { None } else { read_entry(CacheCategory::Explain, &entry).and_then(|b| String::from_utf8(b).ok()) };
+    if let Some(hit) = cached {
+        hit
+    } else if mocked {
+        mock::text_response(user).unwrap_or_else(|e| format!("(error: {})", e))
+    } else {
+        match call_text_model(api_key, model, system, user, &Sampling::default()) {
+            Ok(text) => {
+                write_entry(CacheCategory::Explain, &entry, text.as_bytes());
+                text
+            }
+            Err(e) => format!("(error: {})", e),
+        }
+    }
+}
+
+/// A file's snippet summaries as one block, shortened for the prompts of files importing it
+fn module_summary(snippets: &[PythonChunk], summaries: &[String]) -> String {
+    let mut out = String::new();
+    for (snip, summary) in snippets.iter().zip(summaries) {
+        let line = format!("- {} {}: {}\n", snip.kind, snip.name, summary.trim());
+        if out.chars().count() + line.chars().count() > MAX_MODULE_SUMMARY_CHARS {
+            out.push_str("- ...\n");
+            break;
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Summary text from a model response: the structured JSON `summary` if present, else the raw text
+fn summary_text(raw: &str) -> String {
+    let parsed: Option<SnippetSummary> = serde_json::from_str(raw).ok();
+    // Touch id so the field isn't considered dead code
+    let _parsed_id_used = parsed.as_ref().map(|p| p.id.as_str()).unwrap_or("");
+    parsed.map(|p| p.summary).unwrap_or_else(|| raw.trim().to_string())
+}
@@ -0,0 +1,157 @@
+//! `qernel explain --since <ref>`: the Python changes since a git revision, so only the
+//! functions and classes they touch are explained.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::chunk::PythonChunk;
+
+/// Longest diff included in the combined "what changed" prompt
+const MAX_DIFF_CHARS: usize = 20_000;
+
+/// Lines changed in one file, on the new side of the diff
+#[derive(Debug, Default)]
+pub struct FileChanges {
+    /// The file is new (untracked or added since the revision)
+    pub added: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug)]
+pub struct Hunk {
+    pub start: usize,
+    pub end: usize,
+    /// The hunk as printed by `git diff -U0`, header included
+    pub text: String,
+}
+
+/// Changed Python files under the current directory, keyed by canonical path
+pub struct Changes {
+    pub since: String,
+    pub files: HashMap<PathBuf, FileChanges>,
+    /// Paths as git prints them (relative to the current directory), in git's order
+    pub paths: Vec<String>,
+    /// `git diff <ref>` of those files, for the combined summary
+    pub diff: String,
+}
+
+impl Changes {
+    pub fn load(since: &str) -> Result<Changes> {
+        git(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", since)])
+            .with_context(|| format!("'{}' is not a git revision in this repository", since))?;
+
+        let mut files = HashMap::new();
+        let mut paths = Vec::new();
+        let names = git(&["-c", "core.quotepath=off", "diff", "--relative", "--name-only", "--diff-filter=AMR", since, "--", "*.py"])?;
+        for path in names.lines().filter(|l| !l.is_empty()) {
+            let diff = git(&["diff", "--relative", "-U0", "--no-color", since, "--", path])?;
+            let changes = FileChanges { added: diff.contains("\nnew file mode"), hunks: parse_hunks(&diff) };
+            files.insert(canonical(path), changes);
+            paths.push(path.to_string());
+        }
+        // Files the agent created and nobody has committed yet
+        let untracked = git(&["-c", "core.quotepath=off", "ls-files", "--others", "--exclude-standard", "--", "*.py"])?;
+        for path in untracked.lines().filter(|l| !l.is_empty()) {
+            files.insert(canonical(path), FileChanges { added: true, hunks: Vec::new() });
+            paths.push(path.to_string());
+        }
+
+        let mut diff = git(&["diff", "--relative", "--no-color", since, "--", "*.py"])?;
+        if diff.len() > MAX_DIFF_CHARS {
+            let cut = (0..=MAX_DIFF_CHARS).rev().find(|&i| diff.is_char_boundary(i)).unwrap_or(0);
+            diff.truncate(cut);
+            diff.push_str("\n[DIFF TRUNCATED]\n");
+        }
+        Ok(Changes { since: since.to_string(), files, paths, diff })
+    }
+
+    pub fn get(&self, file: &str) -> Option<&FileChanges> {
+        self.files.get(&canonical(file))
+    }
+}
+
+impl FileChanges {
+    /// The snippets the changes touch. Changed lines outside every snippet (module-level
+    /// code) become blocks of their own so no change goes unexplained.
+    pub fn select(&self, snippets: Vec<PythonChunk>, content: &str, filename: &str) -> Vec<PythonChunk> {
+        if self.added {
+            return snippets;
+        }
+        let mut selected: Vec<PythonChunk> = snippets
+            .iter()
+            .filter(|s| self.hunks.iter().any(|h| h.start <= s.end_line && s.start_line <= h.end))
+            .cloned()
+            .collect();
+        let lines: Vec<&str> = content.lines().collect();
+        for (i, hunk) in self.hunks.iter().enumerate() {
+            if snippets.iter().any(|s| s.start_line <= hunk.start && hunk.end <= s.end_line) {
+                continue;
+            }
+            let end = hunk.end.min(lines.len());
+            let start = hunk.start.min(end).max(1);
+            if lines.is_empty() {
+                continue;
+            }
+            selected.push(PythonChunk {
+                id: format!("{}::change:{}", filename, i + 1),
+                name: format!("{} (module level)", filename),
+                kind: "block".to_string(),
+                start_line: start,
+                end_line: end,
+                code: lines[start - 1..end].join("\n"),
+                signature: String::new(),
+                calls: Vec::new(),
+            });
+        }
+        selected.sort_by_key(|s| s.start_line);
+        selected
+    }
+
+    /// The hunks overlapping lines `start..=end`, for the snippet's prompt
+    pub fn diff_for(&self, start: usize, end: usize) -> String {
+        if self.added {
+            return "(new file)".to_string();
+        }
+        self.hunks
+            .iter()
+            .filter(|h| h.start <= end && start <= h.end)
+            .map(|h| h.text.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Hunks of a `-U0` diff with their line range in the new file. A pure deletion covers the
+/// line before and after it.
+fn parse_hunks(diff: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("@@ ") {
+            let new = header.split_whitespace().find_map(|p| p.strip_prefix('+')).unwrap_or("0");
+            let (start, count) = match new.split_once(',') {
+                Some((s, c)) => (s.parse().unwrap_or(0), c.parse().unwrap_or(0)),
+                None => (new.parse().unwrap_or(0), 1),
+            };
+            let (start, end) = if count == 0 { (start.max(1), start + 1) } else { (start, start + count - 1) };
+            hunks.push(Hunk { start, end, text: format!("{}\n", line) });
+        } else if let Some(hunk) = hunks.last_mut() {
+            hunk.text.push_str(line);
+            hunk.text.push('\n');
+        }
+    }
+    hunks
+}
+
+fn canonical(path: &str) -> PathBuf {
+    Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path))
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let out = Command::new("git").args(args).output().context("run git (is it installed?)")?;
+    if !out.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
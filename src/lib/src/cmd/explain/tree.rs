@@ -0,0 +1,168 @@
+//! Directory mode for `qernel explain`: the Python files under a directory and the import
+//! graph between them, ordered so every module is explained after the modules it imports.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Directories never walked into: caches, virtualenvs and vendored packages
+const SKIP_DIRS: &[&str] = &["__pycache__", "venv", "env", "node_modules", "site-packages", "build", "dist"];
+
+/// Python files under a directory in dependency order, with the files each one imports
+pub struct SourceTree {
+    /// Imported modules come before the modules importing them; files in an import cycle
+    /// keep path order
+    pub files: Vec<PathBuf>,
+    /// Files each file imports, limited to the tree
+    pub imports: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl SourceTree {
+    pub fn scan(dir: &Path) -> Result<SourceTree> {
+        let mut files = Vec::new();
+        collect_python_files(dir, &mut files)?;
+        files.sort();
+
+        // Module names relative to `dir`; a `dir` that is itself a package is also reachable by its own name
+        let prefix = dir
+            .join("__init__.py")
+            .is_file()
+            .then(|| dir.canonicalize().ok()?.file_name()?.to_str().map(str::to_string))
+            .flatten();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        for (i, file) in files.iter().enumerate() {
+            let name = module_name(dir, file);
+            if let Some(prefix) = &prefix {
+                let full = if name.is_empty() { prefix.clone() } else { format!("{}.{}", prefix, name) };
+                by_name.insert(full, i);
+            }
+            if !name.is_empty() {
+                by_name.insert(name, i);
+            }
+        }
+
+        let mut deps: Vec<Vec<usize>> = Vec::with_capacity(files.len());
+        for (i, file) in files.iter().enumerate() {
+            let source = std::fs::read_to_string(file).with_context(|| format!("read file {}", file.display()))?;
+            let is_package = file.file_stem().is_some_and(|s| s == "__init__");
+            let mut found: Vec<usize> = Vec::new();
+            for target in imported_names(&source) {
+                let resolved = resolve(&module_name(dir, file), is_package, &target);
+                if let Some(&j) = longest_known(&resolved, &by_name)
+                    && j != i
+                    && !found.contains(&j)
+                {
+                    found.push(j);
+                }
+            }
+            deps.push(found);
+        }
+
+        let order = dependency_order(&deps);
+        let imports = files
+            .iter()
+            .zip(&deps)
+            .map(|(f, d)| (f.clone(), d.iter().map(|&j| files[j].clone()).collect()))
+            .collect();
+        Ok(SourceTree { files: order.into_iter().map(|i| files[i].clone()).collect(), imports })
+    }
+}
+
+fn collect_python_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("read directory {}", dir.display()))? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIP_DIRS.contains(&name) {
+                collect_python_files(&path, out)?;
+            }
+        } else if path.extension().and_then(|e| e.to_str()) == Some("py") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `pkg/sub/mod.py` -> `pkg.sub.mod`, `pkg/__init__.py` -> `pkg`
+fn module_name(root: &Path, file: &Path) -> String {
+    let rel = file.strip_prefix(root).unwrap_or(file).with_extension("");
+    let mut parts: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    if parts.last().is_some_and(|p| p == "__init__") {
+        parts.pop();
+    }
+    parts.join(".")
+}
+
+/// Every module an import statement may refer to. `from a import b, c` yields `a.b`,
+/// `a.c` and `a`, since `b` and `c` may be submodules or names defined in `a`.
+fn imported_names(source: &str) -> Vec<String> {
+    let from_re = Regex::new(r"(?m)^\s*from\s+([.\w]+)\s+import\s+(\([^)]*\)|[^\n]*)").expect("from regex");
+    let import_re = Regex::new(r"(?m)^\s*import\s+([^\n#;]+)").expect("import regex");
+    let mut names = Vec::new();
+    for caps in from_re.captures_iter(source) {
+        let module = &caps[1];
+        let sep = if module.ends_with('.') { "" } else { "." };
+        for item in caps[2].trim_matches(|c| c == '(' || c == ')').split(',') {
+            let item = item.split('#').next().unwrap_or("").split_whitespace().next().unwrap_or("");
+            if !item.is_empty() && item != "*" {
+                names.push(format!("{}{}{}", module, sep, item));
+            }
+        }
+        names.push(module.to_string());
+    }
+    for caps in import_re.captures_iter(source) {
+        for item in caps[1].split(',') {
+            if let Some(module) = item.split_whitespace().next() {
+                names.push(module.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Make a relative import (`.x`, `..y.z`) absolute from the importing module
+fn resolve(from_module: &str, is_package: bool, target: &str) -> String {
+    let dots = target.chars().take_while(|&c| c == '.').count();
+    if dots == 0 {
+        return target.to_string();
+    }
+    let mut package: Vec<&str> = from_module.split('.').filter(|p| !p.is_empty()).collect();
+    if !is_package {
+        package.pop();
+    }
+    for _ in 1..dots {
+        package.pop();
+    }
+    let rest = &target[dots..];
+    if !rest.is_empty() {
+        package.push(rest);
+    }
+    package.join(".")
+}
+
+/// The file for the longest known prefix of a dotted name (`a.b.func` -> `a/b.py`)
+fn longest_known<'a>(name: &str, by_name: &'a HashMap<String, usize>) -> Option<&'a usize> {
+    let mut candidate = name;
+    loop {
+        if let Some(i) = by_name.get(candidate) {
+            return Some(i);
+        }
+        candidate = &candidate[..candidate.rfind('.')?];
+    }
+}
+
+/// Topological order of `deps` (indices sorted by path); a cycle is broken at its first file
+fn dependency_order(deps: &[Vec<usize>]) -> Vec<usize> {
+    let mut placed: HashSet<usize> = HashSet::new();
+    let mut order = Vec::with_capacity(deps.len());
+    while order.len() < deps.len() {
+        let next = (0..deps.len())
+            .find(|i| !placed.contains(i) && deps[*i].iter().all(|d| placed.contains(d)))
+            .or_else(|| (0..deps.len()).find(|i| !placed.contains(i)))
+            .expect("unplaced file");
+        placed.insert(next);
+        order.push(next);
+    }
+    order
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+
+use crate::cmd::explain::renderer::{RenderOptions, highlight_markdown, print_blocks};
+
+/// Long-form guides shown by `qernel help <topic>`: name, one-line summary, Markdown
+pub const TOPICS: &[(&str, &str, &str)] = &[
+    ("prototype-workflow", "How `qernel prototype` goes from spec to passing tests", include_str!("topics/prototype-workflow.md")),
+    ("patch-format", "The patch format the agent edits files with", include_str!("topics/patch-format.md")),
+    ("providers", "Model providers, API keys and rate limits", include_str!("topics/providers.md")),
+    ("benchmarks", "Test commands, metrics and limits in qernel.yaml", include_str!("topics/benchmarks.md")),
+];
+
+/// Show a topic, paged when stdout is a terminal, or list the topics when none is given
+pub fn handle_help(topic: Option<String>, pager: bool) -> Result<()> {
+    let Some(topic) = topic else {
+        println!("Help topics (qernel help <topic>):\n");
+        for (name, summary, _) in TOPICS {
+            println!("  {:<20} {}", name, summary);
+        }
+        println!("\nRun `qernel <command> --help` for a command's options.");
+        return Ok(());
+    };
+    let Some((_, _, text)) = TOPICS.iter().find(|(name, _, _)| *name == topic) else {
+        let names: Vec<&str> = TOPICS.iter().map(|(name, _, _)| *name).collect();
+        anyhow::bail!("no help topic '{}'. Topics: {}", topic, names.join(", "));
+    };
+    let rendered = if crate::util::color_enabled_stdout() { highlight_markdown(text) } else { text.to_string() };
+    print_blocks(rendered, &RenderOptions { pager })
+}
@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::cmd::prototype::mineru::{process_content_files, process_papers};
+use crate::config::{QernelConfig, load_config};
+use crate::error::QernelError;
+
+/// Prepare `.qernel/spec.md` and the parsed paper assets without running the agent.
+/// Without `--papers` or `--content` both are ingested.
+pub fn handle_ingest(cwd: String, papers: bool, content: bool, force: bool) -> Result<()> {
+    let cwd_path = Path::new(&cwd);
+    let cwd_abs = cwd_path.canonicalize().unwrap_or_else(|_| cwd_path.to_path_buf());
+    let config_path = cwd_abs.join(".qernel").join("qernel.yaml");
+    if !config_path.exists() {
+        anyhow::bail!("{} not found. Run this inside a qernel project or pass --cwd.", config_path.display());
+    }
+    let config = load_config(&config_path)?;
+
+    let (papers, content) = if !papers && !content { (true, true) } else { (papers, content) };
+    ingest(&config, &cwd_abs, papers, content, force)?;
+    println!("Ingestion done. Run `qernel prototype --spec-only` to start the agent without ingesting again.");
+    Ok(())
+}
+
+/// Ingest the configured papers and/or content files into the project.
+///
+/// Papers unchanged since their last parse (per `.qernel/papers/manifest.json`) are
+/// skipped unless `force`, which downloads and parses them again.
+pub fn ingest(config: &QernelConfig, cwd: &Path, papers: bool, content: bool, force: bool) -> Result<()> {
+    let _span = tracing::info_span!("ingest", papers, content, force).entered();
+    if papers {
+        process_papers(&config.papers, cwd, force).context(QernelError::Ingest("ingesting the papers failed".to_string()))?;
+    }
+    if content
+        && let Some(content_files) = &config.content_files
+    {
+        process_content_files(content_files, cwd).context(QernelError::Ingest("ingesting the content files failed".to_string()))?;
+    }
+    Ok(())
+}
@@ -1,9 +1,9 @@
 use anyhow::{bail, Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
 use std::env;
 use std::io::{self, Read};
 
-use crate::util::{load_config, save_config, get_openai_api_key_from_env_or_config, set_openai_api_key_in_config, unset_openai_api_key_in_config};
+use crate::util::{load_config, ensure_known_provider, get_openai_api_key_from_env_or_config, provider_env_var, set_api_key_in_config, stored_api_key_providers, unset_api_key_in_config};
 use owo_colors::OwoColorize;
 use reqwest::blocking::Client;
 use serde::Deserialize;
@@ -16,26 +16,36 @@ struct WhoAmIResponse {
     screen_name: Option<String>,
 }
 
-pub fn handle_auth_with_flags(set_openai_key: bool, unset_openai_key: bool) -> Result<()> {
+/// The hosted Zoo's token check
+fn whoami_url() -> String {
+    format!("{}/_api/whoami", crate::cmd::zoo::DEFAULT_SERVER.trim_end_matches('/'))
+}
+
+pub fn handle_auth_with_flags(set_openai_key: bool, unset_openai_key: bool, set_key: Option<String>, unset_key: Option<String>) -> Result<()> {
     let ce = crate::util::color_enabled_stdout();
-    // Handle OpenAI key management flags first
-    if set_openai_key {
-        println!("Enter your OpenAI API key (or set OPENAI_API_KEY):");
+    // Handle provider key management flags first (--set-openai-key is --set-key openai)
+    let set_key = set_key.or_else(|| set_openai_key.then(|| "openai".to_string()));
+    let unset_key = unset_key.or_else(|| unset_openai_key.then(|| "openai".to_string()));
+    if let Some(provider) = set_key {
+        ensure_known_provider(&provider)?;
+        println!("{} `qernel auth --set-key` is deprecated; use `qernel config set secrets.{}`", crate::util::sym_question(ce), provider);
+        let var = provider_env_var(&provider).unwrap_or("");
+        println!("Enter your {} API key (or set {}):", provider, var);
         let key = match rpassword::read_password() {
-            Ok(k) => if k.trim().is_empty() { std::env::var("OPENAI_API_KEY").unwrap_or_default() } else { k },
-            Err(_) => std::env::var("OPENAI_API_KEY").unwrap_or_default(),
+            Ok(k) => if k.trim().is_empty() { std::env::var(var).unwrap_or_default() } else { k },
+            Err(_) => std::env::var(var).unwrap_or_default(),
         };
         if key.trim().is_empty() {
-            anyhow::bail!("OpenAI API key cannot be empty");
+            anyhow::bail!("{} API key cannot be empty", provider);
         }
-        set_openai_api_key_in_config(&key)?;
-        println!("{} OpenAI API key saved to local config.", crate::util::sym_check(ce));
-        println!("Note: We're migrating to Ollama/open-source models soon.");
+        set_api_key_in_config(&provider, &key)?;
+        println!("{} {} API key saved to local config.", crate::util::sym_check(ce), provider);
         return Ok(());
     }
-    if unset_openai_key {
-        unset_openai_api_key_in_config()?;
-        println!("{} Removed stored OpenAI API key.", crate::util::sym_check(ce));
+    if let Some(provider) = unset_key {
+        println!("{} `qernel auth --unset-key` is deprecated; use `qernel config unset secrets.{}`", crate::util::sym_question(ce), provider);
+        unset_api_key_in_config(&provider)?;
+        println!("{} Removed stored {} API key.", crate::util::sym_check(ce), provider);
         return Ok(());
     }
     // If we already have a token, show masked and attempt to fetch identity
@@ -49,12 +59,16 @@ pub fn handle_auth_with_flags(set_openai_key: bool, unset_openai_key: bool) -> R
                 println!("{} OpenAI API key detected. Note: prototyping uses OpenAI today; we're migrating to Ollama/open-source models soon.", crate::util::sym_check(ce));
             } else {
                 println!("{} Warning: No OpenAI API key detected. Prototyping features won't be available until a key is set.", crate::util::sym_question(ce));
-                println!("   You can set one with: qernel auth --set-openai-key");
+                println!("   You can set one with: qernel config set secrets.openai");
+            }
+            let stored = stored_api_key_providers();
+            if !stored.is_empty() {
+                println!("{} Stored API keys: {}", crate::util::sym_check(ce), stored.join(", "));
             }
 
             if let Ok(client) = Client::builder().timeout(std::time::Duration::from_secs(10)).build() {
                 if let Ok(r) = client
-                    .get("https://dojoservice.onrender.com/_api/whoami")
+                    .get(whoami_url())
                     .bearer_auth(token)
                     .send() {
                     if r.status().is_success() {
@@ -87,13 +101,14 @@ pub fn handle_auth_with_flags(set_openai_key: bool, unset_openai_key: bool) -> R
         bail!("Token cannot be empty");
     }
 
-    let pb = ProgressBar::new_spinner();
+    let pb = crate::util::spinner();
     pb.set_style(ProgressStyle::with_template("{spinner} Saving token...").unwrap());
     pb.enable_steady_tick(std::time::Duration::from_millis(80));
 
-    let mut cfg = load_config().unwrap_or_default();
-    cfg.token = Some(token.trim().to_string());
-    save_config(&cfg)?;
+    crate::util::edit_config(|cfg| {
+        cfg.token = Some(token.trim().to_string());
+        Ok(())
+    })?;
 
     pb.finish_with_message("Token Saved");
     let ce = crate::util::color_enabled_stdout();
@@ -101,7 +116,7 @@ pub fn handle_auth_with_flags(set_openai_key: bool, unset_openai_key: bool) -> R
 
     if let Ok(client) = Client::builder().timeout(std::time::Duration::from_secs(10)).build() {
         if let Ok(r) = client
-            .get("https://dojoservice.onrender.com/_api/whoami")
+            .get(whoami_url())
             .bearer_auth(token.trim())
             .send() {
             if r.status().is_success() {
@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cmd::prototype::logging::{LogEvent, logs_dir};
+
+/// List recorded run logs, oldest first
+pub fn handle_logs_list(cwd: String) -> Result<()> {
+    let runs = list_runs(Path::new(&cwd));
+    if runs.is_empty() {
+        println!("No run logs in {}. Run `qernel prototype --debug` to record one.", logs_dir(Path::new(&cwd)).display());
+        return Ok(());
+    }
+    for run in runs {
+        let events = std::fs::read_to_string(&run).map(|s| s.lines().count()).unwrap_or(0);
+        println!("{}  {} events", run_name(&run), events);
+    }
+    Ok(())
+}
+
+/// Pretty-print every event of a run (the latest one by default)
+pub fn handle_logs_show(cwd: String, run: Option<String>, category: Option<String>) -> Result<()> {
+    let path = resolve_run(Path::new(&cwd), run.as_deref())?;
+    let file = std::fs::File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
+    for line in BufReader::new(file).lines() {
+        print_line(&line?, category.as_deref());
+    }
+    Ok(())
+}
+
+/// Print the last `lines` events of a run, then keep printing new ones with `follow`
+pub fn handle_logs_tail(cwd: String, run: Option<String>, lines: usize, follow: bool, category: Option<String>) -> Result<()> {
+    let path = resolve_run(Path::new(&cwd), run.as_deref())?;
+    let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let all: Vec<&str> = content
+        .lines()
+        .filter(|l| category.is_none() || parse_event(l).is_some_and(|e| Some(e.category.as_str()) == category.as_deref()))
+        .collect();
+    for line in &all[all.len().saturating_sub(lines)..] {
+        print_line(line, None);
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    // Poll for appended lines; the logger only ever appends whole lines
+    let mut offset = content.len() as u64;
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let mut file = std::fs::File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
+        let len = file.metadata().map(|m| m.len()).unwrap_or(offset);
+        if len <= offset {
+            continue;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if !line.ends_with('\n') {
+                // Partially written line; re-read it on the next poll
+                break;
+            }
+            offset += line.len() as u64;
+            print_line(line.trim_end(), category.as_deref());
+            line.clear();
+        }
+    }
+}
+
+fn list_runs(cwd: &Path) -> Vec<PathBuf> {
+    let mut runs: Vec<PathBuf> = std::fs::read_dir(logs_dir(cwd))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+                .collect()
+        })
+        .unwrap_or_default();
+    // run-<YYYYmmdd-HHMMSS> names sort chronologically
+    runs.sort();
+    runs
+}
+
+fn run_name(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Accepts a run name (`run-20250101-120000`), a file path, or nothing for the latest run
+fn resolve_run(cwd: &Path, run: Option<&str>) -> Result<PathBuf> {
+    let runs = list_runs(cwd);
+    match run {
+        None => runs
+            .last()
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no run logs in {}", logs_dir(cwd).display())),
+        Some(r) => {
+            let direct = PathBuf::from(r);
+            if direct.is_file() {
+                return Ok(direct);
+            }
+            runs.iter()
+                .find(|p| run_name(p) == r || run_name(p) == format!("run-{}", r))
+                .cloned()
+                .ok_or_else(|| {
+                    let names: Vec<String> = runs.iter().map(|p| run_name(p)).collect();
+                    anyhow::anyhow!("run '{}' not found. Available: {}", r, if names.is_empty() { "none".to_string() } else { names.join(", ") })
+                })
+        }
+    }
+}
+
+fn parse_event(line: &str) -> Option<LogEvent> {
+    serde_json::from_str(line).ok()
+}
+
+fn print_line(line: &str, category: Option<&str>) {
+    let Some(event) = parse_event(line) else {
+        if category.is_none() && !line.trim().is_empty() {
+            println!("{}", line);
+        }
+        return;
+    };
+    if category.is_some_and(|c| c != event.category) {
+        return;
+    }
+    print!("{}", format_event(&event, crate::util::color_enabled_stdout()));
+}
+
+/// `HH:MM:SS.mmm [category] message`, with object payloads shown as indented fields
+fn format_event(event: &LogEvent, ce: bool) -> String {
+    // RFC 3339: keep the time of day
+    let time = event.ts.get(11..23).unwrap_or(&event.ts);
+    let tag = format!("[{}]", event.category);
+    let tag = if ce { tag.cyan().to_string() } else { tag };
+    let time = if ce { time.dimmed().to_string() } else { time.to_string() };
+    let mut out = format!("{} {}", time, tag);
+    match &event.payload {
+        serde_json::Value::Object(map) if map.len() == 1 && map.contains_key("message") => {
+            let msg = map["message"].as_str().unwrap_or_default();
+            push_text(&mut out, msg);
+        }
+        serde_json::Value::Object(map) => {
+            out.push('\n');
+            for (key, value) in map {
+                let text = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                if text.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("    {}:", key));
+                push_text(&mut out, &text);
+            }
+            return out;
+        }
+        other => push_text(&mut out, &other.to_string()),
+    }
+    out
+}
+
+/// Single-line text stays inline; multi-line text is indented beneath
+fn push_text(out: &mut String, text: &str) {
+    let text = text.trim_end();
+    if text.contains('\n') {
+        out.push('\n');
+        for line in text.lines() {
+            out.push_str("      ");
+            out.push_str(line);
+            out.push('\n');
+        }
+    } else {
+        out.push(' ');
+        out.push_str(text);
+        out.push('\n');
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::migrations::{self, CURRENT_VERSION};
+
+/// Upgrade the project's `.qernel` layout to this CLI's version, or with `dry_run` only list
+/// what would change
+pub fn handle_migrate(cwd: String, dry_run: bool) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let config_path = Path::new(&cwd).join(".qernel").join("qernel.yaml");
+    if !config_path.is_file() {
+        anyhow::bail!("No qernel project at {} (.qernel/qernel.yaml not found)", cwd);
+    }
+    let plan = migrations::plan(&config_path)?;
+    if plan.is_empty() {
+        println!("{} Project is up to date (version {})", crate::util::sym_check(ce), CURRENT_VERSION);
+        return Ok(());
+    }
+
+    println!("Version {} -> {}:", plan.from_version, CURRENT_VERSION);
+    for change in &plan.changes {
+        println!("  {}", change);
+    }
+    println!("  set version: {} in qernel.yaml", CURRENT_VERSION);
+    if plan.changes.iter().any(|c| matches!(c, migrations::Change::RenameKey { .. })) {
+        println!("  (qernel.yaml is rewritten, so comments in it are not kept)");
+    }
+    if dry_run {
+        println!("{} Dry run: nothing was changed", crate::util::sym_question(ce));
+        return Ok(());
+    }
+    migrations::apply(&plan)?;
+    println!("{} Migrated to version {}", crate::util::sym_check(ce), CURRENT_VERSION);
+    Ok(())
+}
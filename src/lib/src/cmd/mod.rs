@@ -0,0 +1,27 @@
+pub mod audit;
+pub mod bundle;
+pub mod cache;
+pub mod common;
+pub mod config;
+pub mod diff;
+pub mod env;
+pub mod ingest;
+pub mod login;
+pub mod logs;
+pub mod migrate;
+pub mod new;
+pub mod papers;
+pub mod publish;
+pub mod push;
+pub mod pull;
+pub mod prototype;
+pub mod provider;
+pub mod secrets;
+pub mod see;
+pub mod status;
+pub mod sweep;
+pub mod workspace;
+pub mod explain;
+pub mod help;
+pub mod zoo;
+
@@ -0,0 +1,115 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::cmd::prototype::environment::build_exec_env;
+
+use super::templates::substitute_variables;
+
+/// Post-generate commands may install packages, so allow them longer than agent tool calls
+const HOOK_TIMEOUT_MS: u64 = 600_000;
+
+/// Show a remote template's `post_generate` commands and ask before running them, since they
+/// run with full access to the machine. Without a terminal to ask on, they are skipped.
+pub fn confirm_post_generate(source: &str, commands: &[String], vars: &[(String, String)]) -> Result<bool> {
+    println!("The template {} wants to run these commands in the new project:", source);
+    for raw in commands {
+        println!("  {}", substitute_variables(raw, vars));
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!("warning: skipping the template's post_generate commands; review them and pass --trust to run them");
+        return Ok(false);
+    }
+    print!("Run them? [y/N]: ");
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Run a template's `post_generate` commands in the new project, stopping at the first failure.
+/// Commands go through the platform shell with the project venv (if any) on PATH.
+pub fn run_post_generate(project_path: &Path, commands: &[String], vars: &[(String, String)]) -> Result<()> {
+    let project_abs = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+    let ce = crate::util::color_enabled_stdout();
+    for raw in commands {
+        let command = substitute_variables(raw, vars);
+        println!("{} Running post-generate hook: {}", crate::util::sym_check(ce), command);
+        let exit_code = run_hook(&project_abs, &command)?;
+        if exit_code != 0 {
+            anyhow::bail!("post-generate hook `{}` exited with code {}", command, exit_code);
+        }
+    }
+    Ok(())
+}
+
+fn run_hook(cwd: &Path, command: &str) -> Result<i32> {
+    use async_channel::unbounded as async_unbounded;
+    use codex_core::exec::{process_exec_tool_call, ExecParams, SandboxType, StdoutStream};
+    use codex_core::protocol::{Event, EventMsg, ExecOutputStream, SandboxPolicy};
+
+    let argv = if cfg!(windows) {
+        vec!["cmd".to_string(), "/C".to_string(), command.to_string()]
+    } else {
+        vec!["sh".to_string(), "-c".to_string(), command.to_string()]
+    };
+    let _span = tracing::info_span!("post_generate_hook", command).entered();
+    let params = ExecParams {
+        command: argv,
+        cwd: cwd.to_path_buf(),
+        timeout_ms: Some(HOOK_TIMEOUT_MS),
+        env: build_exec_env(cwd)?,
+        with_escalated_permissions: None,
+        justification: None,
+        limits: Default::default(),
+        use_pty: false,
+    };
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to create tokio runtime")?;
+
+    // Stream hook output to the terminal as it arrives
+    let (tx_event, rx_event) = async_unbounded::<Event>();
+    let printer = std::thread::spawn(move || {
+        while let Ok(ev) = rx_event.recv_blocking() {
+            if let EventMsg::ExecCommandOutputDelta(delta) = ev.msg {
+                match delta.stream {
+                    ExecOutputStream::Stdout => {
+                        let mut out = std::io::stdout();
+                        let _ = out.write_all(&delta.chunk);
+                        let _ = out.flush();
+                    }
+                    ExecOutputStream::Stderr => {
+                        let _ = std::io::stderr().write_all(&delta.chunk);
+                    }
+                }
+            }
+        }
+    });
+
+    let stream = StdoutStream {
+        sub_id: "new".into(),
+        call_id: "post_generate".into(),
+        tx_event,
+    };
+
+    let out = rt
+        .block_on(process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DANGER_FULL_ACCESS,
+            &std::path::PathBuf::from("/"),
+            &None,
+            Some(stream),
+        ))
+        .map_err(|e| anyhow::anyhow!("exec error: {:?}", e))?;
+    // Shutting down the runtime drops any remaining event senders so the printer can finish
+    drop(rt);
+    let _ = printer.join();
+    Ok(out.exit_code)
+}
@@ -0,0 +1,296 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use indicatif::ProgressStyle;
+
+use crate::config::{PaperConfig, TemplateConfig, load_config, save_config};
+
+pub mod hooks;
+pub mod remote;
+pub mod templates;
+
+use remote::RemoteTemplate;
+use templates::{DEFAULT_TEMPLATE, TEMPLATES, Template, TemplateManifest, find_template, substitute_variables};
+
+/// Template chosen with `--template`: a built-in name or a cloned repository
+enum SelectedTemplate {
+    Builtin(&'static Template),
+    Remote(RemoteTemplate),
+}
+
+/// Print the built-in project templates
+pub fn list_templates() -> Result<()> {
+    println!("Available templates:");
+    let width = TEMPLATES.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for t in TEMPLATES {
+        println!("  {:<width$}  {}", t.name, t.description, width = width);
+    }
+    Ok(())
+}
+
+pub fn handle_new(path: String, template: Option<String>, no_venv: bool, vars: Vec<(String, String)>, no_hooks: bool, trust: bool) -> Result<()> {
+    let project_path = Path::new(&path);
+    if project_path.exists() {
+        anyhow::bail!("Path already exists: {}", project_path.display());
+    }
+    if template.is_none() && !vars.is_empty() {
+        anyhow::bail!("--var requires --template");
+    }
+
+    let template = match template.as_deref() {
+        Some(spec) if remote::is_remote_spec(spec) => {
+            println!("Fetching template {}...", spec);
+            Some(SelectedTemplate::Remote(remote::fetch_template(spec)?))
+        }
+        Some(name) => Some(SelectedTemplate::Builtin(find_template(name).ok_or_else(|| {
+            anyhow::anyhow!("Unknown template '{}'. Run `qernel new --list-templates` to see available templates.", name)
+        })?)),
+        None => None,
+    };
+
+    // Resolve template variables up front so missing values fail before anything is written
+    let project_name = project_path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("qernel-project")
+        .to_string();
+    let resolved = match template.as_ref() {
+        Some(t) => {
+            let manifest = template_manifest(t)?;
+            let values = manifest.resolve_variables(&template_variables(&project_name), &vars)?;
+            Some((manifest, values))
+        }
+        None => None,
+    };
+
+    let pb = crate::util::spinner();
+    pb.set_style(ProgressStyle::with_template("{spinner} Scaffolding project...").unwrap());
+    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+
+    fs::create_dir_all(&project_path).with_context(|| "failed to create project dir")?;
+
+
+    let src_dir = project_path.join("src");
+    fs::create_dir_all(&src_dir)?;
+    fs::write(project_path.join("README.md"), "# New Qernel Project\n")?;
+    fs::write(
+        project_path.join(".gitignore"),
+        concat!(
+            ".DS_Store\n",
+            "/target\n",
+            ".env\n",
+            ".qernel/.venv/\n",
+            ".qernel/parsed/\n",
+            ".qernel/papers/\n",
+            ".qernel/STOP\n",
+            ".qernel/checkpoint.json\n",
+            ".qernel/turns/\n",
+            "__pycache__/\n",
+            "*.py[cod]\n",
+            "*$py.class\n",
+            ".logs/\n",
+            ".qernel/logs/\n",
+            "*.log\n",
+        ),
+    )?;
+
+    // Optional template placeholders
+    if let (Some(template), Some((manifest, values))) = (template.as_ref(), resolved.as_ref()) {
+        // Suspend spinner while running long-running steps (venv + pip) to avoid flicker
+        let res: Result<()> = pb.suspend(|| {
+            create_prototype_template(project_path, template, values, !no_venv)?;
+            // Built-in hooks are ours; a remote template's run only once the user has seen them
+            let run_hooks = !no_hooks
+                && !manifest.post_generate.is_empty()
+                && match template {
+                    SelectedTemplate::Builtin(_) => true,
+                    SelectedTemplate::Remote(remote) => trust || hooks::confirm_post_generate(&remote.source, &manifest.post_generate, values)?,
+                };
+            if run_hooks
+                && let Err(e) = hooks::run_post_generate(project_path, &manifest.post_generate, values)
+            {
+                eprintln!("warning: {e}");
+                eprintln!("         fix the problem and re-run the remaining template.yaml post_generate commands by hand.");
+            }
+            Ok(())
+        });
+        res?;
+    }
+
+    // Initialize git repository
+    Command::new("git").arg("init").current_dir(&project_path).output().context("git init failed")?;
+    Command::new("git").args(["add", "."]).current_dir(&project_path).output().ok();
+    Command::new("git").args(["commit", "-m", "chore: initial scaffold"]).current_dir(&project_path).output().ok();
+
+    pb.finish_with_message("done");
+    let ce = crate::util::color_enabled_stdout();
+    println!("{} Created project at {}", crate::util::sym_check(ce), project_path.display());
+
+    Ok(())
+}
+
+fn create_prototype_template(project_path: &Path, template: &SelectedTemplate, vars: &[(String, String)], bootstrap: bool) -> Result<()> {
+    // Create src directory
+    let src_dir = project_path.join("src");
+    fs::create_dir_all(&src_dir)?;
+
+    // Create .qernel directory with README
+    let qernel_dir = project_path.join(".qernel");
+    fs::create_dir_all(&qernel_dir)?;
+    let qernel_readme = r#"# Qernel Project Files
+
+This directory contains all qernel-specific files and is automatically ignored by git.
+
+## Directory Structure
+
+- `papers/` - Your PDF files and downloaded papers
+- `parsed/` - Processed paper content (generated by mineru)
+- `environments/` - Virtual environments and dependencies
+- `cache/` - Temporary files and caches
+
+## Adding Papers
+
+To add your own papers to this project:
+
+1. Place PDF files in this directory (or in `papers/` subdirectory)
+2. Update `qernel.yaml` to reference the local PDF:
+
+```yaml
+papers:
+  - url: ".qernel/your-paper.pdf"
+```
+
+3. Run `qernel prototype` to process the paper and implement the concepts
+
+## Supported Formats
+
+- PDF files (will be processed with mineru)
+- Direct arXiv URLs (will be downloaded and processed)
+
+## Note
+
+This entire directory is ignored by git, so you can store personal files, API keys, and other sensitive data here without worrying about accidentally committing them.
+"#;
+    fs::write(qernel_dir.join("README.md"), qernel_readme)?;
+    
+    // Create .qernel/benchmark.md
+    let benchmark_content = r#"# Benchmarking Criteria
+
+## Functional Tests
+- [ ] Core algorithms work correctly
+- [ ] Examples run without errors
+- [ ] Edge cases are handled properly
+- [ ] Performance is acceptable
+
+## Code Quality
+- [ ] All functions have comprehensive docstrings
+- [ ] Code follows style guidelines
+- [ ] No critical bugs or runtime errors
+- [ ] Modular design with clear interfaces
+
+## Documentation
+- [ ] README explains how to use the code
+- [ ] Examples are clear and runnable
+- [ ] API documentation is complete
+"#;
+    fs::write(qernel_dir.join("benchmark.md"), benchmark_content)?;
+    
+    let project_name = project_path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("qernel-project")
+        .to_string();
+
+    // Write the template's spec, config, requirements and starting sources.
+    // Remote templates are layered over the default template so they only need
+    // to ship the files they customize.
+    let base = match template {
+        SelectedTemplate::Builtin(t) => *t,
+        SelectedTemplate::Remote(_) => find_template(DEFAULT_TEMPLATE).expect("default template is registered"),
+    };
+    for (rel, contents) in base.files {
+        let dest = project_path.join(rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest, substitute_variables(contents, vars))
+            .with_context(|| format!("failed to write {}", dest.display()))?;
+    }
+    fs::write(src_dir.join("__init__.py"), "")?;
+    if let SelectedTemplate::Remote(remote) = template {
+        remote::copy_template_tree(&remote.dir, project_path, vars)?;
+    }
+
+    // Name the project after its directory and record where it came from
+    let config_path = qernel_dir.join("qernel.yaml");
+    let mut config = load_config(&config_path)?;
+    config.project.name = project_name;
+    // Well-known variables also feed the project config
+    if let Some(url) = lookup_variable(vars, "paper_url")
+        && !config.papers.iter().any(|p| p.url == url)
+    {
+        config.papers.push(PaperConfig::from_url(url));
+    }
+    if let Some(version) = lookup_variable(vars, "python_version") {
+        config.python_version = Some(version.to_string());
+    }
+    config.template = Some(match template {
+        SelectedTemplate::Builtin(t) => TemplateConfig { source: t.name.to_string(), revision: None },
+        SelectedTemplate::Remote(r) => TemplateConfig { source: r.source.clone(), revision: r.revision.clone() },
+    });
+    save_config(&config, &config_path)?;
+
+    // Create the project environment (a venv unless the template brings conda or uv) and
+    // install deps (best-effort).
+    if bootstrap {
+        let setup = crate::cmd::prototype::environment::PythonEnv::detect(project_path).and_then(|python_env| {
+            crate::cmd::env::setup_python_env(project_path, &python_env, config.python_version.as_deref(), false)
+        });
+        if let Err(e) = setup {
+            eprintln!("warning: could not create or bootstrap the project environment: {e}");
+            eprintln!("         make sure Python 3 (or conda/uv) is installed and run `qernel env setup` in the project.");
+        }
+    }
+    
+    Ok(())
+}
+
+/// The template's `template.yaml`; remote templates layer theirs over the default template's
+fn template_manifest(template: &SelectedTemplate) -> Result<TemplateManifest> {
+    match template {
+        SelectedTemplate::Builtin(t) => TemplateManifest::parse(t.manifest),
+        SelectedTemplate::Remote(r) => {
+            let base = find_template(DEFAULT_TEMPLATE).expect("default template is registered");
+            let mut manifest = TemplateManifest::parse(base.manifest)?;
+            if let Some(own) = remote::read_manifest(&r.dir)? {
+                manifest.merge(own);
+            }
+            Ok(manifest)
+        }
+    }
+}
+
+/// Non-empty value of a resolved variable
+fn lookup_variable<'a>(vars: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    vars.iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+/// Built-in variables available to every template as `{{name}}` placeholders
+fn template_variables(project_name: &str) -> Vec<(String, String)> {
+    let author = Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_default();
+    vec![
+        ("project_name".to_string(), project_name.to_string()),
+        ("author".to_string(), author),
+    ]
+}
@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::templates::{MANIFEST_FILE, TemplateManifest, substitute_variables};
+
+/// A template repository cloned into a temporary directory (removed on drop).
+pub struct RemoteTemplate {
+    pub source: String,
+    pub revision: Option<String>,
+    pub dir: PathBuf,
+}
+
+impl Drop for RemoteTemplate {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Whether a `--template` value points at a git repository rather than a built-in template
+pub fn is_remote_spec(spec: &str) -> bool {
+    spec.starts_with("gh:")
+        || spec.starts_with("https://")
+        || spec.starts_with("http://")
+        || spec.starts_with("git@")
+        || spec.starts_with("file://")
+}
+
+/// Resolve `gh:user/repo[#ref]` or `<git url>[#ref]` into a clone URL and optional ref
+fn resolve_spec(spec: &str) -> Result<(String, Option<String>)> {
+    let (base, git_ref) = match spec.rsplit_once('#') {
+        Some((b, r)) if !r.is_empty() => (b, Some(r.to_string())),
+        _ => (spec, None),
+    };
+    if let Some(repo) = base.strip_prefix("gh:") {
+        let repo = repo.trim_matches('/').trim_end_matches(".git");
+        if repo.split('/').count() != 2 || repo.split('/').any(|p| p.is_empty()) {
+            anyhow::bail!("Invalid GitHub template '{}'. Expected gh:<user>/<repo>", spec);
+        }
+        return Ok((format!("https://github.com/{}.git", repo), git_ref));
+    }
+    Ok((base.to_string(), git_ref))
+}
+
+/// Shallow-clone a template repository into a temporary directory
+pub fn fetch_template(spec: &str) -> Result<RemoteTemplate> {
+    let (url, git_ref) = resolve_spec(spec)?;
+    let dir = std::env::temp_dir().join(format!(
+        "qernel-template-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis()
+    ));
+
+    let mut cmd = Command::new("git");
+    cmd.args(["clone", "--depth", "1", "--quiet"]);
+    if let Some(r) = git_ref.as_ref() {
+        cmd.args(["--branch", r]);
+    }
+    cmd.arg(&url).arg(&dir);
+    let output = cmd.output().context("failed to run git clone for template")?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&dir);
+        anyhow::bail!("Failed to fetch template {}: {}", url, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let revision = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&dir)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+
+    Ok(RemoteTemplate { source: spec.to_string(), revision, dir })
+}
+
+/// Read the template's `template.yaml`, if it ships one
+pub fn read_manifest(dir: &Path) -> Result<Option<TemplateManifest>> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    TemplateManifest::parse(&text).map(Some)
+}
+
+/// Copy a template tree into the project, substituting variables in text files.
+/// Files from the template overwrite the scaffolded defaults; `.git`, the
+/// top-level `template.yaml` and symlinks (which could point outside the
+/// template) are skipped.
+pub fn copy_template_tree(src: &Path, dest: &Path, vars: &[(String, String)]) -> Result<()> {
+    copy_dir(src, src, dest, vars)
+}
+
+fn copy_dir(root: &Path, src: &Path, dest: &Path, vars: &[(String, String)]) -> Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        if name == ".git" || (src == root && name == MANIFEST_FILE) {
+            continue;
+        }
+        let target = dest.join(&name);
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            eprintln!("warning: skipping symlink {} in the template", path.strip_prefix(root).unwrap_or(&path).display());
+            continue;
+        }
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir(root, &path, &target, vars)?;
+        } else {
+            let bytes = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+            match String::from_utf8(bytes) {
+                Ok(text) => fs::write(&target, substitute_variables(&text, vars))?,
+                // Binary files are copied verbatim
+                Err(e) => fs::write(&target, e.into_bytes())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_in_templates_are_not_followed() {
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret"), "key").unwrap();
+        let template = tempfile::tempdir().unwrap();
+        fs::create_dir_all(template.path().join("src")).unwrap();
+        fs::write(template.path().join("src").join("main.py"), "print('{{project_name}}')").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret"), template.path().join("src").join("leak.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), template.path().join("home")).unwrap();
+
+        let project = tempfile::tempdir().unwrap();
+        let vars = vec![("project_name".to_string(), "demo".to_string())];
+        copy_template_tree(template.path(), project.path(), &vars).unwrap();
+        assert_eq!(fs::read_to_string(project.path().join("src").join("main.py")).unwrap(), "print('demo')");
+        assert!(!project.path().join("src").join("leak.txt").exists());
+        assert!(!project.path().join("home").exists());
+    }
+}
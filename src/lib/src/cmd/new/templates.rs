@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A built-in project template embedded in the binary.
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Project-relative path and contents of every file the template ships.
+    pub files: &'static [(&'static str, &'static str)],
+    /// Contents of the template's `template.yaml` manifest.
+    pub manifest: &'static str,
+}
+
+/// Name of the manifest file at the root of a template (never copied into the project).
+pub const MANIFEST_FILE: &str = "template.yaml";
+
+/// `template.yaml`: variables a template accepts and commands to run after scaffolding.
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateManifest {
+    #[serde(default)]
+    pub variables: BTreeMap<String, TemplateVariable>,
+    #[serde(default)]
+    pub post_generate: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateVariable {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Default value; may itself reference other variables such as `{{project_name}}`.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Fail unless the variable is given with `--var`.
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl TemplateManifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_yaml::from_str(text).context("failed to parse template.yaml")
+    }
+
+    /// Layer another manifest on top: its variables replace ours and its hooks run after ours.
+    pub fn merge(&mut self, other: TemplateManifest) {
+        self.variables.extend(other.variables);
+        self.post_generate.extend(other.post_generate);
+    }
+
+    /// Resolve variable values. Later sources win: manifest defaults, then the
+    /// built-in values (project name, author), then `--var` overrides.
+    pub fn resolve_variables(
+        &self,
+        builtins: &[(String, String)],
+        overrides: &[(String, String)],
+    ) -> Result<Vec<(String, String)>> {
+        let mut values: BTreeMap<String, String> = BTreeMap::new();
+        for (name, value) in builtins.iter().chain(overrides) {
+            values.insert(name.clone(), value.clone());
+        }
+
+        let known: Vec<(String, String)> = values.clone().into_iter().collect();
+        let mut missing = Vec::new();
+        for (name, var) in &self.variables {
+            if values.contains_key(name) {
+                continue;
+            }
+            match var.default.as_ref() {
+                Some(default) if !var.required => {
+                    values.insert(name.clone(), substitute_variables(default, &known));
+                }
+                _ => missing.push(match var.description.as_deref() {
+                    Some(desc) => format!("{} ({})", name, desc),
+                    None => name.clone(),
+                }),
+            }
+        }
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Template requires values for: {}. Pass them with --var NAME=VALUE.",
+                missing.join(", ")
+            );
+        }
+        Ok(values.into_iter().collect())
+    }
+}
+
+/// Template used when `--template` is passed without a name.
+pub const DEFAULT_TEMPLATE: &str = "prototype";
+
+macro_rules! template_files {
+    ($dir:literal) => {
+        &[
+            (".qernel/spec.md", include_str!(concat!("templates/", $dir, "/.qernel/spec.md"))),
+            (".qernel/qernel.yaml", include_str!(concat!("templates/", $dir, "/.qernel/qernel.yaml"))),
+            (".qernel/requirements.txt", include_str!(concat!("templates/", $dir, "/.qernel/requirements.txt"))),
+            ("src/main.py", include_str!(concat!("templates/", $dir, "/src/main.py"))),
+            ("src/tests.py", include_str!(concat!("templates/", $dir, "/src/tests.py"))),
+        ]
+    };
+}
+
+macro_rules! template_manifest {
+    ($dir:literal) => {
+        include_str!(concat!("templates/", $dir, "/template.yaml"))
+    };
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "prototype",
+        description: "Blank prototype to fill in from a paper or spec.md",
+        files: template_files!("prototype"),
+        manifest: template_manifest!("prototype"),
+    },
+    Template {
+        name: "vqe",
+        description: "Variational Quantum Eigensolver for small qubit Hamiltonians",
+        files: template_files!("vqe"),
+        manifest: template_manifest!("vqe"),
+    },
+    Template {
+        name: "qaoa",
+        description: "QAOA for MaxCut on small graphs",
+        files: template_files!("qaoa"),
+        manifest: template_manifest!("qaoa"),
+    },
+    Template {
+        name: "grover",
+        description: "Grover search over an unstructured database",
+        files: template_files!("grover"),
+        manifest: template_manifest!("grover"),
+    },
+    Template {
+        name: "tensor-network",
+        description: "Matrix product state simulation of qubit circuits",
+        files: template_files!("tensor-network"),
+        manifest: template_manifest!("tensor-network"),
+    },
+    Template {
+        name: "qiskit-bench",
+        description: "Qiskit circuit benchmark on the Aer simulator",
+        files: template_files!("qiskit-bench"),
+        manifest: template_manifest!("qiskit-bench"),
+    },
+];
+
+/// Look up a built-in template by name.
+pub fn find_template(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|t| t.name == name)
+}
+
+/// Replace `{{name}}` (or `{{ name }}`) placeholders with their values.
+pub fn substitute_variables(text: &str, vars: &[(String, String)]) -> String {
+    let mut out = text.to_string();
+    for (name, value) in vars {
+        out = out
+            .replace(&format!("{{{{{}}}}}", name), value)
+            .replace(&format!("{{{{ {} }}}}", name), value);
+    }
+    out
+}
@@ -0,0 +1,23 @@
+"""Grover's search on a NumPy statevector simulator."""
+
+import numpy as np
+
+
+def phase_oracle(n_qubits, marked):
+    """Return the diagonal unitary that flips the phase of every marked basis state."""
+    raise NotImplementedError
+
+
+def diffusion_operator(n_qubits):
+    """Return the Grover diffusion operator 2|s><s| - I."""
+    raise NotImplementedError
+
+
+def optimal_iterations(n_qubits, n_marked=1):
+    """Return the number of Grover iterations that maximizes success probability."""
+    raise NotImplementedError
+
+
+def grover_search(n_qubits, marked, iterations=None):
+    """Run Grover's algorithm and return the probability of each basis state."""
+    raise NotImplementedError
@@ -0,0 +1,29 @@
+import numpy as np
+import pytest
+
+from src.main import diffusion_operator, grover_search, optimal_iterations, phase_oracle
+
+
+def test_oracle_flips_marked_phase():
+    oracle = phase_oracle(3, [5])
+    assert oracle.shape == (8, 8)
+    assert np.isclose(oracle[5, 5], -1)
+    assert np.isclose(oracle[0, 0], 1)
+
+
+def test_diffusion_is_unitary():
+    d = diffusion_operator(3)
+    assert np.allclose(d @ d.conj().T, np.eye(8))
+
+
+def test_optimal_iterations():
+    assert optimal_iterations(2, 1) == 1
+    assert optimal_iterations(5, 1) == 4
+
+
+def test_grover_finds_marked_item():
+    probs = grover_search(5, [19])
+    assert probs.shape == (32,)
+    assert np.isclose(probs.sum(), 1.0)
+    assert int(np.argmax(probs)) == 19
+    assert probs[19] > 0.9
@@ -0,0 +1 @@
+# Main implementation file
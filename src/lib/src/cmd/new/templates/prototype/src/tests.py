@@ -0,0 +1,5 @@
+# Test file
+import pytest
+
+def test_basic():
+    assert True
@@ -0,0 +1,23 @@
+"""QAOA for MaxCut on a NumPy statevector simulator."""
+
+import numpy as np
+
+
+def cut_value(bitstring, edges):
+    """Return the total weight of edges cut by the partition in `bitstring`."""
+    raise NotImplementedError
+
+
+def maxcut_cost_diagonal(n_nodes, edges):
+    """Return the cut value of every computational basis state as a 1-D array."""
+    raise NotImplementedError
+
+
+def qaoa_state(n_nodes, edges, gammas, betas):
+    """Return the depth-p QAOA statevector for the given angles."""
+    raise NotImplementedError
+
+
+def run_qaoa(n_nodes, edges, p=1, seed=0):
+    """Optimize QAOA angles and return (best_bitstring, best_cut, approximation_ratio)."""
+    raise NotImplementedError
@@ -0,0 +1,32 @@
+import numpy as np
+import pytest
+
+from src.main import cut_value, maxcut_cost_diagonal, qaoa_state, run_qaoa
+
+RING = [(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (3, 0, 1.0)]
+
+
+def test_cut_value():
+    assert cut_value("0101", RING) == 4
+    assert cut_value("0000", RING) == 0
+    assert cut_value("0011", RING) == 2
+
+
+def test_cost_diagonal_matches_cut_value():
+    diag = maxcut_cost_diagonal(4, RING)
+    assert diag.shape == (16,)
+    assert diag.max() == 4
+    assert diag[int("0101", 2)] == 4
+
+
+def test_qaoa_state_is_normalized():
+    state = qaoa_state(4, RING, [0.4, 0.8], [0.6, 0.3])
+    assert state.shape == (16,)
+    assert np.isclose(np.vdot(state, state).real, 1.0)
+
+
+def test_run_qaoa_finds_good_cut():
+    bitstring, cut, ratio = run_qaoa(4, RING, p=2, seed=3)
+    assert len(bitstring) == 4
+    assert cut == cut_value(bitstring, RING)
+    assert ratio >= 0.9
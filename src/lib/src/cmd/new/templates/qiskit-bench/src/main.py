@@ -0,0 +1,26 @@
+"""Qiskit circuit benchmark on the Aer simulator."""
+
+import time
+
+from qiskit import QuantumCircuit, transpile
+from qiskit_aer import AerSimulator
+
+
+def build_circuit(n_qubits):
+    """Return the benchmark circuit (GHZ state with measurements) on n_qubits."""
+    raise NotImplementedError
+
+
+def transpile_for_backend(circuit, backend=None):
+    """Transpile the circuit for the given backend (AerSimulator by default)."""
+    raise NotImplementedError
+
+
+def run_counts(circuit, shots=1024, seed=0):
+    """Execute the circuit and return a dict of measurement counts."""
+    raise NotImplementedError
+
+
+def benchmark(n_qubits, shots=1024, seed=0):
+    """Return depth, gate counts, measurement counts, and runtime in seconds."""
+    raise NotImplementedError
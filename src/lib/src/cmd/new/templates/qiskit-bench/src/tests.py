@@ -0,0 +1,21 @@
+import pytest
+
+from src.main import benchmark, build_circuit, run_counts, transpile_for_backend
+
+
+def test_circuit_width():
+    circuit = build_circuit(5)
+    assert circuit.num_qubits == 5
+
+
+def test_counts_are_deterministic():
+    circuit = transpile_for_backend(build_circuit(3))
+    assert run_counts(circuit, shots=256, seed=11) == run_counts(circuit, shots=256, seed=11)
+
+
+def test_ghz_outcomes():
+    result = benchmark(5, shots=512, seed=5)
+    assert set(result["counts"]) <= {"00000", "11111"}
+    assert sum(result["counts"].values()) == 512
+    assert result["depth"] > 0
+    assert result["seconds"] >= 0
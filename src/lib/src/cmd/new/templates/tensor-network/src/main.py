@@ -0,0 +1,23 @@
+"""Matrix product state (MPS) simulation of qubit circuits."""
+
+import numpy as np
+
+
+def mps_from_bitstring(bitstring):
+    """Return a list of site tensors with shape (1, 2, 1) for a basis state."""
+    raise NotImplementedError
+
+
+def apply_single(mps, gate, site):
+    """Apply a 2x2 gate to one site and return the updated MPS."""
+    raise NotImplementedError
+
+
+def apply_two(mps, gate, site, max_bond=None):
+    """Apply a 4x4 gate to sites (site, site + 1), truncating the bond to max_bond."""
+    raise NotImplementedError
+
+
+def to_statevector(mps):
+    """Contract the MPS into a dense statevector."""
+    raise NotImplementedError
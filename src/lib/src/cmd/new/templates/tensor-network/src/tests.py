@@ -0,0 +1,38 @@
+import numpy as np
+import pytest
+
+from src.main import apply_single, apply_two, mps_from_bitstring, to_statevector
+
+H = np.array([[1, 1], [1, -1]]) / np.sqrt(2)
+X = np.array([[0, 1], [1, 0]])
+CNOT = np.array([[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 0, 1], [0, 0, 1, 0]])
+
+
+def test_basis_state_roundtrip():
+    state = to_statevector(mps_from_bitstring("101"))
+    expected = np.zeros(8)
+    expected[int("101", 2)] = 1
+    assert np.allclose(state, expected)
+
+
+def test_single_qubit_gate():
+    mps = apply_single(mps_from_bitstring("00"), X, 1)
+    state = to_statevector(mps)
+    assert np.isclose(abs(state[int("01", 2)]), 1)
+
+
+def test_ghz_state():
+    n = 6
+    mps = apply_single(mps_from_bitstring("0" * n), H, 0)
+    for site in range(n - 1):
+        mps = apply_two(mps, CNOT, site, max_bond=4)
+    state = to_statevector(mps)
+    expected = np.zeros(2**n)
+    expected[0] = expected[-1] = 1 / np.sqrt(2)
+    assert np.allclose(state, expected)
+
+
+def test_bond_dimension_is_truncated():
+    mps = apply_single(mps_from_bitstring("00"), H, 0)
+    mps = apply_two(mps, CNOT, 0, max_bond=1)
+    assert mps[0].shape[2] == 1
@@ -0,0 +1,23 @@
+"""Variational Quantum Eigensolver (VQE) on a NumPy statevector simulator."""
+
+import numpy as np
+
+
+def pauli_hamiltonian(terms):
+    """Return the dense matrix for a list of (coefficient, pauli_string) terms."""
+    raise NotImplementedError
+
+
+def ansatz_state(params, n_qubits, layers=1):
+    """Return the statevector prepared by the RY/CNOT hardware-efficient ansatz."""
+    raise NotImplementedError
+
+
+def expectation(hamiltonian, state):
+    """Return the real expectation value <state|H|state>."""
+    raise NotImplementedError
+
+
+def run_vqe(terms, n_qubits, layers=1, seed=0):
+    """Minimize the ansatz energy and return (energy, params)."""
+    raise NotImplementedError
@@ -0,0 +1,45 @@
+import numpy as np
+import pytest
+
+from src.main import ansatz_state, expectation, pauli_hamiltonian, run_vqe
+
+# Two-qubit reduced H2 Hamiltonian at bond length 0.735 A (STO-3G)
+H2_TERMS = [
+    (-1.052373245772859, "II"),
+    (0.39793742484318045, "IZ"),
+    (-0.39793742484318045, "ZI"),
+    (-0.01128010425623538, "ZZ"),
+    (0.18093119978423156, "XX"),
+]
+
+
+def test_pauli_hamiltonian_is_hermitian():
+    h = pauli_hamiltonian(H2_TERMS)
+    assert h.shape == (4, 4)
+    assert np.allclose(h, h.conj().T)
+
+
+def test_single_qubit_z():
+    h = pauli_hamiltonian([(1.0, "Z")])
+    assert np.allclose(h, np.diag([1.0, -1.0]))
+
+
+def test_ansatz_state_is_normalized():
+    params = np.linspace(0.1, 0.4, 4)
+    state = ansatz_state(params, 2, layers=1)
+    assert state.shape == (4,)
+    assert np.isclose(np.vdot(state, state).real, 1.0)
+
+
+def test_expectation_of_zero_state():
+    h = pauli_hamiltonian([(1.0, "ZZ")])
+    state = np.zeros(4)
+    state[0] = 1.0
+    assert np.isclose(expectation(h, state), 1.0)
+
+
+def test_vqe_matches_exact_ground_state():
+    exact = np.linalg.eigvalsh(pauli_hamiltonian(H2_TERMS))[0]
+    energy, params = run_vqe(H2_TERMS, 2, layers=2, seed=7)
+    assert len(params) == 2 * 3
+    assert abs(energy - exact) < 1e-3
@@ -0,0 +1,139 @@
+pub mod resolver;
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::cmd::prototype::manifest::PaperManifest;
+use crate::cmd::prototype::mineru::{is_remote, paper_parsed_dir, paper_pdf_path, process_papers};
+use resolver::{parse_doi, recorded};
+use crate::config::{PaperConfig, QernelConfig, load_config, save_config};
+
+fn project_config(cwd: &Path) -> Result<(PathBuf, QernelConfig)> {
+    let path = cwd.join(".qernel").join("qernel.yaml");
+    if !path.exists() {
+        anyhow::bail!("{} not found. Run this inside a qernel project or pass --cwd.", path.display());
+    }
+    let config = load_config(&path)?;
+    Ok((path, config))
+}
+
+/// Find a paper by its 1-based position in `qernel papers list` or by its exact source
+fn find_paper(papers: &[PaperConfig], which: &str) -> Result<usize> {
+    if let Some(i) = papers.iter().position(|p| p.source() == which || p.doi.as_deref() == Some(which) || p.title.as_deref() == Some(which)) {
+        return Ok(i);
+    }
+    match which.parse::<usize>() {
+        Ok(n) if (1..=papers.len()).contains(&n) => Ok(n - 1),
+        _ => anyhow::bail!("no paper '{}' in qernel.yaml; see `qernel papers list`", which),
+    }
+}
+
+/// Add a paper URL, DOI, title (with `title`) or local PDF path to `papers:` and
+/// ingest it unless `no_parse`
+pub fn handle_papers_add(cwd: String, source: String, title: bool, no_parse: bool) -> Result<()> {
+    let cwd = Path::new(&cwd);
+    let (config_path, mut config) = project_config(cwd)?;
+    let source = source.trim().to_string();
+    let paper = if title {
+        PaperConfig { title: Some(source), ..PaperConfig::default() }
+    } else if let Some(doi) = parse_doi(&source) {
+        PaperConfig { doi: Some(doi), ..PaperConfig::default() }
+    } else {
+        let paper = PaperConfig::from_url(source.clone());
+        if !is_remote(&paper) && !cwd.join(&source).is_file() {
+            anyhow::bail!(
+                "{} is not a URL, DOI or PDF file under {}; pass --title to look it up by title",
+                source,
+                cwd.display()
+            );
+        }
+        paper
+    };
+    let source = paper.source();
+    if config.papers.iter().any(|p| p.source() == source) {
+        anyhow::bail!("{} is already listed in qernel.yaml; use `qernel papers reparse` to ingest it again", source);
+    }
+    config.papers.push(paper.clone());
+    save_config(&config, &config_path)?;
+    println!("Added {} to {}", source, config_path.display());
+
+    if no_parse {
+        println!("Run `qernel papers reparse` to ingest it later.");
+        return Ok(());
+    }
+    process_papers(std::slice::from_ref(&paper), cwd, false)
+}
+
+/// List configured papers with whether each one has been downloaded and parsed
+pub fn handle_papers_list(cwd: String) -> Result<()> {
+    let cwd = Path::new(&cwd);
+    let (_, config) = project_config(cwd)?;
+    if config.papers.is_empty() {
+        println!("No papers in qernel.yaml. Add one with `qernel papers add <url|path>`.");
+        return Ok(());
+    }
+    let manifest = PaperManifest::load(cwd);
+    for (i, paper) in config.papers.iter().enumerate() {
+        println!("{:>3}. {}  ({})", i + 1, paper.source(), paper_state(paper, &manifest, cwd));
+    }
+    Ok(())
+}
+
+/// e.g. `downloaded, parsed` or `not resolved yet`, as shown by `qernel papers list`
+pub fn paper_state(paper: &PaperConfig, manifest: &PaperManifest, cwd: &Path) -> String {
+    let Some(downloaded) = recorded(paper, manifest) else {
+        return "not resolved yet".to_string();
+    };
+    let pdf = if paper_pdf_path(&downloaded, cwd).is_file() {
+        if is_remote(&downloaded) { "downloaded" } else { "local" }
+    } else if is_remote(&downloaded) {
+        "not downloaded"
+    } else {
+        "missing"
+    };
+    let parsed = if paper_parsed_dir(&downloaded, cwd).is_dir() { "parsed" } else { "not parsed" };
+    format!("{}, {}", pdf, parsed)
+}
+
+/// Remove a paper from `papers:` along with its download and parsed output.
+/// Local PDFs are left in place.
+pub fn handle_papers_remove(cwd: String, which: String) -> Result<()> {
+    let cwd = Path::new(&cwd);
+    let (config_path, mut config) = project_config(cwd)?;
+    let paper = config.papers.remove(find_paper(&config.papers, &which)?);
+    let mut manifest = PaperManifest::load(cwd);
+
+    if let Some(downloaded) = recorded(&paper, &manifest) {
+        let parsed = paper_parsed_dir(&downloaded, cwd);
+        if parsed.is_dir() {
+            std::fs::remove_dir_all(&parsed).with_context(|| format!("failed to remove {}", parsed.display()))?;
+        }
+        let pdf = paper_pdf_path(&downloaded, cwd);
+        if is_remote(&downloaded) && pdf.is_file() {
+            std::fs::remove_file(&pdf).with_context(|| format!("failed to remove {}", pdf.display()))?;
+        }
+    }
+    if manifest.papers.remove(&paper.source()).is_some() {
+        manifest.save(cwd)?;
+    }
+    save_config(&config, &config_path)?;
+    println!("Removed {}", paper.source());
+    println!("Its text stays in .qernel/spec.md under \"## Paper Content\" until another paper is ingested or you edit it.");
+    Ok(())
+}
+
+/// Throw away parsed output (including the shared cache copy) and ingest again,
+/// for one paper or all of them
+pub fn handle_papers_reparse(cwd: String, which: Option<String>) -> Result<()> {
+    let cwd = Path::new(&cwd);
+    let (_, config) = project_config(cwd)?;
+    let papers: Vec<PaperConfig> = match which {
+        Some(w) => vec![config.papers[find_paper(&config.papers, &w)?].clone()],
+        None => config.papers.clone(),
+    };
+    if papers.is_empty() {
+        println!("No papers in qernel.yaml. Add one with `qernel papers add <url|path>`.");
+        return Ok(());
+    }
+    process_papers(&papers, cwd, true)
+}
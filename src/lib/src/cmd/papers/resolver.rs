@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::cmd::prototype::manifest::PaperManifest;
+use crate::config::PaperConfig;
+
+const SEMANTIC_SCHOLAR_API: &str = "https://api.semanticscholar.org/graph/v1/paper";
+const CROSSREF_API: &str = "https://api.crossref.org/works";
+const S2_FIELDS: &str = "title,externalIds,openAccessPdf";
+
+/// DOIs registered by arXiv itself, e.g. `10.48550/arXiv.2101.00001`
+const ARXIV_DOI_PREFIX: &str = "10.48550/arxiv.";
+
+/// What a paper needs looked up before it can be downloaded
+enum Lookup {
+    Doi(String),
+    Title(String),
+}
+
+fn lookup(paper: &PaperConfig) -> Option<Lookup> {
+    if !paper.url.is_empty() {
+        // A doi.org link points at the publisher's landing page, not a PDF
+        return parse_doi(&paper.url).map(Lookup::Doi);
+    }
+    if let Some(doi) = &paper.doi {
+        return Some(Lookup::Doi(parse_doi(doi).unwrap_or_else(|| doi.trim().to_string())));
+    }
+    paper.title.as_ref().map(|t| Lookup::Title(t.trim().to_string()))
+}
+
+/// Whether a paper is given by DOI or title and must be resolved to a URL first
+pub fn needs_resolution(paper: &PaperConfig) -> bool {
+    lookup(paper).is_some()
+}
+
+/// A bare DOI from `10.x/y`, `doi:10.x/y` or a `doi.org` URL
+pub fn parse_doi(s: &str) -> Option<String> {
+    let s = s.trim();
+    let rest = ["https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "http://dx.doi.org/", "doi:"]
+        .iter()
+        .find_map(|prefix| s.strip_prefix(prefix))
+        .unwrap_or(s);
+    (rest.starts_with("10.") && rest.contains('/') && !rest.contains(char::is_whitespace)).then(|| rest.to_string())
+}
+
+/// The paper as it should be downloaded: DOI and title papers become the URL recorded
+/// in the manifest at their last ingest, if there is one
+pub fn recorded(paper: &PaperConfig, manifest: &PaperManifest) -> Option<PaperConfig> {
+    if !needs_resolution(paper) {
+        return Some(paper.clone());
+    }
+    let entry = manifest.papers.get(&paper.source())?;
+    entry.resolved_url.as_ref().map(|url| PaperConfig::from_url(url.clone()))
+}
+
+/// Resolve a DOI or title to an arXiv abstract URL or an open-access PDF URL.
+///
+/// arXiv is preferred since its LaTeX source ingests best. Semantic Scholar is asked
+/// first; Crossref is the fallback when Semantic Scholar has no match or rate-limits.
+/// Set `SEMANTIC_SCHOLAR_API_KEY` to use a Semantic Scholar API key.
+pub fn resolve(paper: &PaperConfig) -> Result<String> {
+    let Some(lookup) = lookup(paper) else {
+        return Ok(paper.url.clone());
+    };
+    let _span = tracing::info_span!("resolve_paper", source = %paper.source()).entered();
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .user_agent(concat!("qernel/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    let found = match &lookup {
+        Lookup::Doi(doi) => {
+            if let Some(id) = doi.to_lowercase().strip_prefix(ARXIV_DOI_PREFIX) {
+                return Ok(format!("https://arxiv.org/abs/{}", id));
+            }
+            let s2 = semantic_scholar(&client, &format!("{}/DOI:{}", SEMANTIC_SCHOLAR_API, doi), &[]);
+            match s2.as_ref().and_then(download_url) {
+                Some(url) => Some(url),
+                None => crossref_get(&client, &format!("{}/{}", CROSSREF_API, doi)).and_then(|m| crossref_pdf(&m)),
+            }
+        }
+        Lookup::Title(title) => {
+            let s2 = semantic_scholar(&client, &format!("{}/search/match", SEMANTIC_SCHOLAR_API), &[("query", title)])
+                .and_then(|v| v["data"].get(0).cloned())
+                .filter(|p| similar_titles(title, p["title"].as_str().unwrap_or("")));
+            match s2.as_ref().and_then(download_url) {
+                Some(url) => Some(url),
+                None => crossref_search(&client, title).and_then(|m| crossref_pdf(&m)),
+            }
+        }
+    };
+    found.with_context(|| match lookup {
+        Lookup::Doi(doi) => format!("no arXiv version or open-access PDF found for DOI {}", doi),
+        Lookup::Title(title) => format!("no arXiv version or open-access PDF found for \"{}\"", title),
+    })
+}
+
+/// GET a Semantic Scholar endpoint, waiting and retrying once if rate-limited
+fn semantic_scholar(client: &Client, url: &str, query: &[(&str, &str)]) -> Option<Value> {
+    let api_key = std::env::var("SEMANTIC_SCHOLAR_API_KEY").ok().filter(|k| !k.is_empty());
+    for attempt in 0..2 {
+        let mut request = client.get(url).query(query).query(&[("fields", S2_FIELDS)]);
+        if let Some(key) = &api_key {
+            request = request.header("x-api-key", key);
+        }
+        let response = match request.send() {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::debug!("Semantic Scholar request failed: {e}");
+                return None;
+            }
+        };
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt == 0 {
+            std::thread::sleep(std::time::Duration::from_secs(3));
+            continue;
+        }
+        if !response.status().is_success() {
+            tracing::debug!("Semantic Scholar returned {}", response.status());
+            return None;
+        }
+        return response.json().ok();
+    }
+    None
+}
+
+/// arXiv abstract URL if Semantic Scholar knows an arXiv ID, else its open-access PDF
+fn download_url(paper: &Value) -> Option<String> {
+    if let Some(id) = paper["externalIds"]["ArXiv"].as_str() {
+        return Some(format!("https://arxiv.org/abs/{}", id));
+    }
+    paper["openAccessPdf"]["url"].as_str().filter(|u| !u.is_empty()).map(str::to_string)
+}
+
+fn crossref_get(client: &Client, url: &str) -> Option<Value> {
+    let response = client.get(url).send().ok()?;
+    if !response.status().is_success() {
+        tracing::debug!("Crossref returned {}", response.status());
+        return None;
+    }
+    response.json::<Value>().ok().map(|v| v["message"].clone())
+}
+
+/// Best Crossref match for a title, if its title is close enough to be the same paper
+fn crossref_search(client: &Client, title: &str) -> Option<Value> {
+    let url = url::Url::parse_with_params(CROSSREF_API, &[("query.bibliographic", title), ("rows", "1")]).ok()?;
+    let item = crossref_get(client, url.as_str())?["items"].get(0)?.clone();
+    let found = item["title"].get(0).and_then(Value::as_str).unwrap_or("");
+    similar_titles(title, found).then_some(item)
+}
+
+/// A preprint on arXiv linked from the Crossref record, else a PDF full-text link
+fn crossref_pdf(work: &Value) -> Option<String> {
+    let preprints = work["relation"]["has-preprint"].as_array().into_iter().flatten();
+    for preprint in preprints {
+        if let Some(id) = preprint["id"].as_str().and_then(|d| d.to_lowercase().strip_prefix(ARXIV_DOI_PREFIX).map(str::to_string)) {
+            return Some(format!("https://arxiv.org/abs/{}", id));
+        }
+    }
+    work["link"]
+        .as_array()?
+        .iter()
+        .find(|l| l["content-type"].as_str() == Some("application/pdf"))
+        .and_then(|l| l["URL"].as_str())
+        .map(str::to_string)
+}
+
+/// Search APIs always return something; only accept a result whose title shares
+/// most of its words with the requested one
+fn similar_titles(a: &str, b: &str) -> bool {
+    fn words(s: &str) -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect()
+    }
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    let shared = a.intersection(&b).count() as f64;
+    shared / a.union(&b).count() as f64 >= 0.8
+}
@@ -0,0 +1,1525 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use codex_core::exec::{truncate_utf8, truncate_utf8_tail, ResourceLimits};
+use codex_core::tool_apply_patch::validate_freeform_patch;
+
+use crate::cmd::common::endpoint::Backend;
+use crate::cmd::common::mock;
+use crate::cmd::common::usage;
+use crate::cmd::common::models::{self, ModelCapabilities};
+use crate::cmd::explain::network::call_text_model;
+use crate::config::{ConstraintsConfig, CoverageConfig, ImageConfig, MetricConfig, PolicyAction, Sampling, SlurmConfig};
+use crate::error::QernelError;
+use crate::util::OutputLevel;
+use crate::cmd::prototype::{
+    approval::{Action, Approvals},
+    artifacts::{artifact_paths, artifacts_prompt_section, collect_artifacts},
+    audit,
+    budget::{format_elapsed, Budget},
+    charts::write_metrics_chart,
+    checkpoint::{checkpoint_path, clear_checkpoint, clear_stop_request, load_checkpoint, save_checkpoint, stop_requested, Checkpoint},
+    command_policy,
+    console::ConsoleStreamer,
+    coverage::{coverage_line, coverage_path, coverage_prompt_section, default_coverage_command, read_coverage, runs_pytest, CoverageReport, PYTEST_COVERAGE_ARGS},
+    constraints::{self, Violation},
+    environment::{prepare_command, resolve_absolute_path, PreparedCommand},
+    events::{ProgressEvent, ProgressSink, UsageTracker},
+    exec_events::{self, ExecEcho, ExecEventRenderer},
+    figures::select_relevant_figures,
+    interrupt::{self, interrupted, run_interruptible},
+    logging::{debug_log, init_debug_logging, RunLogger},
+    metrics::{extract_metrics, metrics_prompt_section, record_iteration, render_trajectory, targets_met, test_counts, validate_metrics},
+    network::{make_openai_request, make_openai_request_with_images, send_tool_output, AiAction, AiStep, ToolCall},
+    patch_stats::PatchStats,
+    plan::{load_plan, mark_all_completed, plan_prompt_section, save_plan},
+    prompts::{build_planning_prompt, build_review_prompt, build_review_system_prompt, build_system_prompt, build_user_prompt},
+    report::{write_report, TestRun},
+    sdk::{analyze_project, install_command, pin_dependency, record_dependency, sdk_prompt_section, Dependency},
+    slurm,
+    snapshots::create_directory_snapshot,
+    suites::{describe_suites, score, SuiteResult, SuiteRuns, TestSuite},
+    summary::RunSummary,
+    transaction::{apply_transactional, python_syntax_error, PatchOutcome},
+    turns::{list_turns, record_turn_diff, TurnSnapshot},
+    validation::validate_patch_paths,
+};
+
+/// A participant in the agent loop backed by its own model: the coder proposes the
+/// next action, and an optional reviewer vets each patch before it is applied.
+/// Roles are cloned onto a worker thread so Ctrl-C can abandon a request.
+pub trait AgentRole: Clone + Send + 'static {
+    type Input: Send + 'static;
+    type Output: Send + 'static;
+
+    fn name(&self) -> &'static str;
+    fn model(&self) -> &str;
+    fn run(&self, input: &Self::Input) -> Result<Self::Output>;
+}
+
+/// Run a role on a worker thread; `None` when Ctrl-C abandoned the request
+fn run_role<R: AgentRole>(role: &R, input: R::Input) -> Result<Option<R::Output>> {
+    let role = role.clone();
+    run_interruptible(move || {
+        let _span = tracing::info_span!("role", name = role.name(), model = role.model()).entered();
+        role.run(&input)
+    })
+}
+
+/// Proposes the next step (patch, shell command or plan) for a user prompt
+#[derive(Clone)]
+struct Coder {
+    api_key: String,
+    model: String,
+    goal: String,
+    test_cmd: String,
+    cwd: PathBuf,
+    figures: Vec<String>,
+    images: ImageConfig,
+    sampling: Sampling,
+    debug_file: Option<RunLogger>,
+}
+
+/// What the coder responds to: an iteration's prompt, or the result of its last tool call
+pub enum CoderInput {
+    Prompt(String),
+    ToolOutput { response_id: String, call: ToolCall, output: String },
+}
+
+impl AgentRole for Coder {
+    type Input = CoderInput;
+    type Output = AiStep;
+
+    fn name(&self) -> &'static str {
+        "coder"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn run(&self, input: &CoderInput) -> Result<AiStep> {
+        match input {
+            CoderInput::Prompt(user) => request_ai_step(&self.api_key, &self.model, &self.goal, &self.test_cmd, &self.cwd, &self.figures, &self.images, &self.sampling, &self.debug_file, user),
+            CoderInput::ToolOutput { response_id, call, output } => {
+                let tools = create_tools(&models::capabilities(&self.model));
+                send_tool_output(&self.api_key, &self.model, tools, response_id, call, output, &self.sampling, &self.debug_file)
+            }
+        }
+    }
+}
+
+/// Critiques a proposed patch against the spec (`agent.reviewer_model`)
+#[derive(Clone)]
+struct Reviewer {
+    api_key: String,
+    model: String,
+    goal: String,
+    cwd: PathBuf,
+    sampling: Sampling,
+    debug_file: Option<RunLogger>,
+}
+
+/// The reviewer's decision on one patch
+#[derive(Debug, Clone)]
+pub struct Review {
+    pub approved: bool,
+    pub critique: String,
+}
+
+impl AgentRole for Reviewer {
+    type Input = String;
+    type Output = Review;
+
+    fn name(&self) -> &'static str {
+        "reviewer"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn run(&self, patch: &String) -> Result<Review> {
+        let context = project_context(&self.cwd, &self.model);
+        let reply = if mock::enabled() {
+            mock::review_response()?
+        } else {
+            call_text_model(&self.api_key, &self.model, &build_review_system_prompt(&self.goal), &build_review_prompt(patch, &context), &self.sampling)?
+        };
+        debug_log(&self.debug_file, &format!("[review] {}", reply), self.debug_file.is_some());
+        parse_review(&reply)
+    }
+}
+
+/// The reviewer answers `{"verdict": "approve" | "reject", "critique": "..."}`,
+/// possibly wrapped in prose or a code fence
+fn parse_review(reply: &str) -> Result<Review> {
+    #[derive(serde::Deserialize)]
+    struct Verdict {
+        verdict: String,
+        #[serde(default)]
+        critique: String,
+    }
+    let start = reply.find('{').context("reviewer reply has no JSON object")?;
+    let end = reply.rfind('}').filter(|&e| e > start).context("reviewer reply has no JSON object")?;
+    let v: Verdict = serde_json::from_str(&reply[start..=end]).context("reviewer reply is not a verdict")?;
+    let approved = match v.verdict.trim().to_lowercase().as_str() {
+        "approve" => true,
+        "reject" => false,
+        other => anyhow::bail!("reviewer verdict {:?} is neither approve nor reject", other),
+    };
+    Ok(Review { approved, critique: v.critique.trim().to_string() })
+}
+
+/// Main agent loop - coordinates the AI agent execution
+#[allow(clippy::too_many_arguments)]
+pub fn run_agent_loop(
+    cwd: String, 
+    goal: String, 
+    suites: Vec<TestSuite>,
+    metrics: Vec<MetricConfig>,
+    coverage: Option<CoverageConfig>,
+    artifact_globs: Vec<String>,
+    limits: ResourceLimits,
+    use_pty: bool,
+    slurm: Option<SlurmConfig>,
+    images: ImageConfig,
+    max_images: usize,
+    model: String, 
+    max_iters: u32, 
+    max_tool_calls: u32,
+    max_patch_fuzz: usize,
+    budget: Budget,
+    non_interactive: bool,
+    debug: bool,
+    reviewer_model: Option<String>,
+    post_patch_hooks: Vec<String>,
+    constraints: ConstraintsConfig,
+    approvals: Approvals,
+    sampling: Sampling,
+    stream_tests: bool,
+    test_output_chars: usize,
+    suite_workers: usize,
+    plan_first: bool,
+    report: bool,
+    resume: bool,
+    api_key_env: Option<String>,
+    quiet: bool,
+    sink: &mut dyn ProgressSink,
+    summary: &mut RunSummary,
+) -> Result<()> {
+    let cwd_abs = resolve_absolute_path(&cwd)?;
+    std::fs::create_dir_all(&cwd_abs).context("create cwd")?;
+    // Ensure all FS mutations happen under the project root.
+    std::env::set_current_dir(&cwd_abs).context("chdir to project root")?;
+
+    // Initialize debug logging if enabled
+    let debug_file = init_debug_logging(&cwd_abs, debug)?;
+    let _run_span = tracing::info_span!("agent_loop", model = %model, max_iters).entered();
+    // Ctrl-C now stops the run at the next safe point instead of killing it mid-patch
+    interrupt::install();
+    let run_started = Instant::now();
+
+    // Note: streaming diffs removed as they're handled directly in console.rs
+
+    // Initialize console streamer
+    let console = ConsoleStreamer::new().non_interactive(non_interactive).quiet(quiet);
+    // --debug shows on the console what -v does, besides writing the run log
+    let verbose = debug || console.level() == OutputLevel::Verbose;
+    // The model's shell commands and patches, shown as they run
+    let exec_echo = if quiet || console.level() == OutputLevel::Quiet {
+        ExecEcho::Silent
+    } else if verbose {
+        ExecEcho::Full
+    } else {
+        ExecEcho::Summary
+    };
+    exec_events::watch_patch_events();
+    let test_cmd = describe_suites(&suites);
+    let mut usage_tracker = UsageTracker::new();
+    match audit::open(&cwd_abs) {
+        Ok(Some(reason)) => console.warning(&format!("Started a new audit log: {}", reason))?,
+        Ok(None) => {}
+        Err(e) => console.warning(&format!("No audit log for this run: {:#}", e))?,
+    }
+    audit::record("run_started", serde_json::json!({ "model": model, "test_command": test_cmd, "max_iterations": max_iters, "resume": resume, "sampling": sampling }));
+    
+    // Present the goal in a more elegant way
+    console.section("AI Agent Objective")?;
+    if verbose {
+        // Show full content in verbose mode
+        console.println(&goal)?;
+    } else {
+        // Show just a summary in normal mode
+        console.typewriter("User intent loaded", 15)?;
+    }
+    console.println("")?;
+    // An empty shell command from the model runs the first suite
+    let argv: Vec<String> = suites.first().map(|s| s.argv.clone()).context("no test suites")?;
+    // benchmarks.coverage without a command of its own is measured by the first pytest suite,
+    // so the tests run once per iteration (without pytest-cov, measuring reports why it can't)
+    let mut suites = suites;
+    let coverage_suite = match &coverage {
+        Some(config) if config.command.is_none() => suites.iter().position(|s| runs_pytest(&s.argv)).filter(|_| pytest_cov_installed(&cwd_abs, &limits)),
+        _ => None,
+    };
+    if let Some(i) = coverage_suite {
+        suites[i].argv.extend(PYTEST_COVERAGE_ARGS.map(String::from));
+    }
+    validate_metrics(&metrics)?;
+    let mut metric_history = Vec::new();
+    let analysis = analyze_project(&cwd_abs);
+    if !analysis.sdks.is_empty() {
+        let names: Vec<&str> = analysis.sdks.iter().map(|s| s.name()).collect();
+        console.info(&format!("Detected quantum SDK: {}", names.join(", ")))?;
+    }
+    if !analysis.missing.is_empty() {
+        console.warning(&format!("Imported but not in requirements.txt: {}", analysis.missing.join(", ")))?;
+    }
+
+    // Figures don't change during a run: pick the ones whose captions match the spec once
+    let figures: Vec<String> = select_relevant_figures(&cwd_abs, &goal, max_images)
+        .into_iter()
+        .map(|f| {
+            debug_log(&debug_file, &format!("[ai] selected figure {} ({})", f.path.display(), f.caption), debug);
+            f.path.to_string_lossy().to_string()
+        })
+        .collect();
+
+    // Minimal AI loop using OpenAI Chat Completions
+    // Resolve API key from --api-key-env, env or stored config without mutating process env
+    let api_key = crate::util::require_api_key(Backend::active()?.name(), api_key_env.as_deref())?;
+    let coder = Coder {
+        api_key: api_key.clone(),
+        model: model.clone(),
+        goal: goal.clone(),
+        test_cmd: test_cmd.clone(),
+        cwd: cwd_abs.clone(),
+        figures,
+        images,
+        sampling,
+        debug_file: debug_file.clone(),
+    };
+    let reviewer = reviewer_model.map(|model| Reviewer {
+        api_key: api_key.clone(),
+        model,
+        goal: goal.clone(),
+        cwd: cwd_abs.clone(),
+        sampling,
+        debug_file: debug_file.clone(),
+    });
+    if let Some(reviewer) = &reviewer {
+        console.info(&format!("Patches are reviewed by {} before they are applied", reviewer.model))?;
+    }
+    let mut iteration: u32 = 0;
+    let mut failure_context = String::new();
+    let mut last_exit_code: Option<i32> = None;
+    if resume {
+        let checkpoint = load_checkpoint(&cwd_abs)?.ok_or_else(|| {
+            anyhow::anyhow!("nothing to resume: {} does not exist", checkpoint_path(&cwd_abs).display())
+        })?;
+        if checkpoint.iteration >= max_iters {
+            anyhow::bail!(
+                "the checkpoint is at iteration {} of {}; pass a higher --max-iters to continue",
+                checkpoint.iteration, max_iters
+            );
+        }
+        console.info(&format!("Resuming after iteration {} ({})", checkpoint.iteration, checkpoint.reason))?;
+        iteration = checkpoint.iteration;
+        failure_context = checkpoint.failure_context;
+        last_exit_code = checkpoint.last_exit_code;
+    }
+    summary.max_iterations = max_iters;
+    summary.iterations = iteration;
+    summary.final_exit_code = last_exit_code;
+
+    // Planning phase: reuse an existing .qernel/plan.md, otherwise ask the model for one
+    let mut plan = load_plan(&cwd_abs);
+    if stop_requested(&cwd_abs) {
+        return stop_for_request(&console, &cwd_abs, iteration, &failure_context, last_exit_code);
+    }
+    if plan.is_none() && plan_first {
+        let _planning_span = tracing::info_span!("planning").entered();
+        let spinner = console.start_spinner_with_timer("AI is drafting an implementation plan...", 600);
+        let step = run_role(&coder, CoderInput::Prompt(build_planning_prompt(&goal)));
+        console.stop_spinner(&spinner);
+        usage_tracker.report(sink, &coder.model);
+        match step {
+            Ok(None) => {
+                return stop_for_interrupt(&console, &cwd_abs, iteration, &failure_context, last_exit_code, "while drafting the plan");
+            }
+            Ok(Some(AiStep { action: AiAction::Plan { steps }, .. })) if !steps.is_empty() => {
+                save_plan(&cwd_abs, &steps)?;
+                plan = Some(steps);
+            }
+            Ok(Some(step)) => console.warning(&format!("Model did not return a plan (action {:?}); continuing without one", step.action.name()))?,
+            Err(e) => console.warning(&format!("Planning failed: {}; continuing without a plan", e))?,
+        }
+    }
+    if let Some(items) = &plan {
+        console.section("Implementation Plan")?;
+        console.plan(items)?;
+    }
+    
+    loop {
+        // Checked before every model request so a run can be stopped from another terminal
+        if stop_requested(&cwd_abs) {
+            return stop_for_request(&console, &cwd_abs, iteration, &failure_context, last_exit_code);
+        }
+        if interrupted() {
+            return stop_for_interrupt(&console, &cwd_abs, iteration, &failure_context, last_exit_code, "between iterations");
+        }
+        iteration += 1;
+        let _iteration_span = tracing::info_span!("iteration", n = iteration).entered();
+        let mut review_feedback = String::new();
+        sink.on_event(&ProgressEvent::IterationStarted { iteration, max_iterations: max_iters });
+
+        // Show context size warning if needed
+        let system_prompt = build_system_prompt(&goal, &test_cmd, &cwd_abs, &create_directory_snapshot(&cwd_abs).unwrap_or_default());
+        let plan_section = plan.as_deref().map(plan_prompt_section).unwrap_or_default();
+        let user_prompt = build_user_prompt(&goal, &failure_context, &plan_section);
+        let total_context_size = system_prompt.len() + user_prompt.len();
+        console.context_size_warning(total_context_size)?;
+        
+        // Start thinking spinner with timer (10 minute timeout)
+        let spinner = console.start_spinner_with_timer("AI is thinking...", 600);
+        
+        // Ask model for next action
+        let suggestion = run_role(&coder, CoderInput::Prompt(user_prompt))?;
+        usage_tracker.report(sink, &coder.model);
+        let Some(mut suggestion) = suggestion else {
+            console.stop_spinner(&spinner);
+            return stop_for_interrupt(&console, &cwd_abs, iteration - 1, &failure_context, last_exit_code, "while waiting for the model");
+        };
+        
+        // Stop thinking spinner (already stopped in streaming callback, but ensure it's stopped)
+        console.stop_spinner(&spinner);
+        
+        // Add a thoughtful pause
+        std::thread::sleep(Duration::from_millis(800));
+
+        // An iteration is one turn of tool calls: each call's result goes back to the model
+        // until it answers without a tool call or the turn's budget is used up
+        let mut turn_snapshot = TurnSnapshot::default();
+        let mut tool_calls: u32 = 0;
+        loop {
+            tool_calls += 1;
+            audit::record("tool_call", serde_json::json!({
+                "iteration": iteration,
+                "n": tool_calls,
+                "action": suggestion.action.name(),
+                "call_id": suggestion.call.as_ref().map(|c| c.id.clone()),
+            }));
+            let result = match &suggestion.action {
+                AiAction::ApplyPatch { patch } => {
+                    unsafe { std::env::set_var("QERNEL_TURN_DIFF", "1") };
+                    let patch_body = patch.clone();
+                    let stats = PatchStats::of(&patch_body, &cwd_abs);
+
+                    // Show patch preview
+                    console.patch_preview(&patch_body, &stats)?;
+
+                    // More thoughtful apply message
+                    console.typewriter("Analyzing code changes...", 20)?;
+                    std::thread::sleep(Duration::from_millis(500));
+                    console.typewriter("Applying modifications...", 20)?;
+
+                    // Check for empty or invalid patches
+                    let mut applied = false;
+                    let outcome = if patch_body.trim() == "*** Begin Patch\n*** End Patch" ||
+                       patch_body.trim() == "*** End Patch" ||
+                       patch_body.trim().is_empty() {
+                        console.error("Rejected empty patch - no changes detected")?;
+                        "Rejected: the patch is empty.".to_string()
+                    } else if suggestion.call.as_ref().is_some_and(|c| c.custom)
+                        && let Err(e) = validate_freeform_patch(&patch_body)
+                    {
+                        // Freeform calls promise the tool's grammar; say where this one broke it
+                        console.error(&format!("Rejected patch: not valid apply_patch syntax at {}", e))?;
+                        format!("Rejected: the patch does not follow the apply_patch grammar at {}. Nothing was applied; send the corrected patch.", e)
+                    } else if let Err(e) = validate_patch_paths(&patch_body, &cwd_abs) {
+                        console.error(&format!("Rejected patch: {}", e))?;
+                        format!("Rejected: {}", e)
+                    } else if let Err(violation) = constraints::check_patch(&constraints, &patch_body, &stats) {
+                        reject_for_constraint(&console, &debug_file, iteration, &violation)?
+                    } else if let Some(reviewer) = &reviewer
+                        && let Some(review) = review_patch(reviewer, &console, &patch_body)
+                            .inspect(|_| usage_tracker.report(sink, &reviewer.model))?
+                        && !review.approved
+                    {
+                        console.warning(&format!("Reviewer rejected the patch: {}", review.critique))?;
+                        if let Some(logger) = &debug_file {
+                            logger.event("review", serde_json::json!({ "iteration": iteration, "approved": false, "critique": review.critique }));
+                        }
+                        review_feedback = review.critique.clone();
+                        format!("Not applied: the reviewer rejected the patch.\n{}", review.critique)
+                    } else if interrupted() {
+                        // Ctrl-C during the review: leave the patch unapplied, the run stops below
+                        "Not applied: interrupted.".to_string()
+                    } else if let Some(feedback) = confirm_action(&console, &debug_file, iteration, &approvals, Action::Patch, "apply the patch above")? {
+                        feedback
+                    } else {
+                        review_feedback.clear();
+                        let (report, files) =
+                            apply_agent_patch(&console, &cwd_abs, iteration, &patch_body, max_patch_fuzz, exec_echo, &mut turn_snapshot, &post_patch_hooks, &limits, test_output_chars, &debug_file)?;
+                        if let Some(files) = files {
+                            applied = true;
+                            summary.record_patch(&stats, &files);
+                            sink.on_event(&ProgressEvent::PatchApplied { iteration, files });
+                        }
+                        report
+                    };
+                    audit::record("patch", serde_json::json!({
+                        "iteration": iteration,
+                        "sha256": audit::sha256_hex(patch_body.as_bytes()),
+                        "applied": applied,
+                    }));
+                    outcome
+                }
+                AiAction::Shell { command } => {
+                    let cmd_s = command.clone();
+                    let cmd = if cmd_s.is_empty() { argv.clone() } else { shlex::split(&cmd_s).unwrap_or(argv.clone()) };
+                    // An empty command re-runs the configured tests, which are always allowed
+                    let refusal = if cmd_s.is_empty() {
+                        None
+                    } else if let Err(violation) = constraints::check_command(&constraints, &cmd) {
+                        Some(reject_for_constraint(&console, &debug_file, iteration, &violation)?)
+                    } else {
+                        screen_command(&console, &debug_file, iteration, &approvals, &cmd)?
+                    };
+                    audit::record("shell", serde_json::json!({ "iteration": iteration, "command": cmd, "ran": refusal.is_none() }));
+                    if let Some(feedback) = refusal {
+                        feedback
+                    } else {
+                        let offline = constraints.no_network || command_policy::runs_offline(&approvals.command_policy, command_policy::classify(&cmd).0);
+                        let result = run_cmd_streaming(&cmd, &cwd_abs, &limits, exec_echo, false, offline);
+                        if interrupted() {
+                            return stop_for_interrupt(&console, &cwd_abs, iteration - 1, &failure_context, last_exit_code, "while running a shell command");
+                        }
+                        let out = result?;
+                        format!(
+                            "Exit code: {}\nstdout:\n{}\nstderr:\n{}",
+                            out.exit_code,
+                            summarize_test_output(&out.stdout.text, test_output_chars),
+                            summarize_test_output(&out.stderr.text, test_output_chars)
+                        )
+                    }
+                }
+                AiAction::AddDependency { package, version } => {
+                    let what = format!("add the dependency {}{}", package, version.as_deref().map(|v| format!("=={}", v)).unwrap_or_default());
+                    if let Err(violation) = constraints::check_dependency(&constraints, package) {
+                        reject_for_constraint(&console, &debug_file, iteration, &violation)?
+                    } else if let Some(feedback) = confirm_action(&console, &debug_file, iteration, &approvals, Action::Dependency, &what)? {
+                        feedback
+                    } else {
+                        console.typewriter(&format!("Adding dependency {}", package), 15)?;
+                        let result = match pin_dependency(&cwd_abs, package, version.as_deref()) {
+                            Ok(dependency) => install_dependency(&cwd_abs, &dependency, &limits, exec_echo, constraints.no_network)?,
+                            Err(message) => message,
+                        };
+                        audit::record("dependency", serde_json::json!({ "iteration": iteration, "package": package, "version": version }));
+                        console.info(&result)?;
+                        result
+                    }
+                }
+                AiAction::Plan { steps } => {
+                    if steps.is_empty() {
+                        console.warning("Rejected empty plan update")?;
+                        "Rejected: the plan is empty.".to_string()
+                    } else {
+                        save_plan(&cwd_abs, steps)?;
+                        console.section("Implementation Plan")?;
+                        console.plan(steps)?;
+                        if let Some(note) = suggestion.rationale.as_deref() {
+                            console.info(note)?;
+                        }
+                        plan = Some(steps.clone());
+                        "Plan saved.".to_string()
+                    }
+                }
+                AiAction::Done { summary } => {
+                    let text = if summary.trim().is_empty() { suggestion.rationale.as_deref().unwrap_or("") } else { summary };
+                    if !text.trim().is_empty() {
+                        console.info(text.trim())?;
+                    }
+                    break;
+                }
+            };
+
+            // Steps that did not come from a tool call end the turn
+            let Some(call) = suggestion.call.clone() else { break };
+            if interrupted() {
+                break;
+            }
+            if tool_calls >= max_tool_calls {
+                console.info(&format!("Used all {} tool calls of this iteration", max_tool_calls))?;
+                break;
+            }
+            if let Some(logger) = &debug_file {
+                logger.event("tool_call", serde_json::json!({ "iteration": iteration, "n": tool_calls, "action": suggestion.action.name(), "result": truncate_utf8(&result, 2_000) }));
+            }
+            let spinner = console.start_spinner_with_timer("AI is thinking...", 600);
+            let next = run_role(&coder, CoderInput::ToolOutput {
+                response_id: suggestion.response_id.clone().unwrap_or_default(),
+                call,
+                output: result,
+            })?;
+            console.stop_spinner(&spinner);
+            usage_tracker.report(sink, &coder.model);
+            let Some(next) = next else {
+                return stop_for_interrupt(&console, &cwd_abs, iteration - 1, &failure_context, last_exit_code, "while waiting for the model");
+            };
+            suggestion = next;
+        }
+
+        if interrupted() {
+            return stop_for_interrupt(&console, &cwd_abs, iteration - 1, &failure_context, last_exit_code, "before running the tests");
+        }
+
+        // Syntax gate: files that do not compile would only fail the (slower) tests
+        let changed_py: Vec<String> = turn_snapshot.changed_files(&cwd_abs).into_iter().filter(|f| f.ends_with(".py")).collect();
+        if let Some(errors) = compile_errors(&changed_py, &cwd_abs, &limits) {
+            console.error("✗ Changed files do not compile; skipping the tests")?;
+            console.println(&errors)?;
+            if let Some(logger) = &debug_file {
+                logger.event("syntax_check", serde_json::json!({ "iteration": iteration, "files": changed_py, "errors": errors }));
+            }
+            failure_context.clear();
+            failure_context.push_str(&format!(
+                "Previous iteration {} left files that do not compile, so the tests were not run.\nCompiler output:\n{}\n",
+                iteration,
+                summarize_test_output(&errors, test_output_chars)
+            ));
+            if let Some(reason) = budget.exceeded(run_started, &usage::totals()) {
+                return stop_for_budget(&console, &cwd_abs, iteration, &failure_context, last_exit_code, run_started, &reason);
+            }
+            if !continue_after_failure(&console, iteration, max_iters, "Changed files do not compile")? {
+                break Ok(());
+            }
+            continue;
+        }
+
+        // Add a thoughtful pause before testing
+        console.typewriter("Running tests to verify implementation...", 20)?;
+        std::thread::sleep(Duration::from_millis(600));
+        
+        // Test, `prototype.workers` suites at a time (one at a time when streaming, so the output
+        // stays readable); Ctrl-C kills the test process groups and lands here
+        // A report left by an earlier iteration must not pass for this one
+        if coverage.is_some() {
+            std::fs::remove_file(coverage_path(&cwd_abs)).ok();
+        }
+        let run_suite = |suite: &TestSuite| match &slurm {
+            Some(config) => slurm::run_job(config, &suite.argv, &cwd_abs, &limits, stream_tests),
+            None => run_cmd_streaming(&suite.argv, &cwd_abs, &limits, if stream_tests { ExecEcho::Full } else { ExecEcho::Silent }, use_pty, constraints.no_network),
+        };
+        let workers = if stream_tests { 1 } else { suite_workers.max(1) };
+        let mut runs = SuiteRuns(Vec::new());
+        for batch in suites.chunks(workers) {
+            if stream_tests && suites.len() > 1 {
+                console.info(&format!("Suite {}: {}", batch[0].name, batch[0].command))?;
+            }
+            let outputs: Vec<Result<codex_core::exec::ExecToolCallOutput>> = if let [suite] = batch {
+                vec![run_suite(suite)]
+            } else {
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = batch.iter().map(|suite| scope.spawn(|| run_suite(suite))).collect();
+                    handles
+                        .into_iter()
+                        .map(|h| h.join().unwrap_or_else(|_| Err(anyhow::anyhow!("test suite thread panicked"))))
+                        .collect()
+                })
+            };
+            if interrupted() {
+                return stop_for_interrupt(&console, &cwd_abs, iteration - 1, &failure_context, last_exit_code, "while the tests were running");
+            }
+            for (suite, out) in batch.iter().zip(outputs) {
+                let out = out?;
+                tracing::info!(suite = %suite.name, exit_code = out.exit_code, timed_out = out.timed_out, "suite finished");
+                let result = SuiteResult {
+                    name: suite.name.clone(),
+                    command: suite.command.clone(),
+                    required: suite.required,
+                    weight: suite.weight,
+                    exit_code: out.exit_code,
+                    passed: is_success(&out, None),
+                };
+                runs.0.push((result, out));
+            }
+        }
+        let out = TestOutput {
+            exit_code: runs.exit_code(),
+            stdout: runs.stdout(),
+            stderr: runs.stderr(),
+            timed_out: runs.timed_out(),
+        };
+
+        // benchmarks.coverage; with min_percent, success needs it too
+        let min_coverage = coverage.as_ref().and_then(|c| c.min_percent);
+        let covered = match &coverage {
+            Some(config) => measure_coverage(&console, config, &cwd_abs, &limits, coverage_suite.is_some()),
+            None => None,
+        };
+        if interrupted() {
+            return stop_for_interrupt(&console, &cwd_abs, iteration - 1, &failure_context, last_exit_code, "while measuring coverage");
+        }
+        let coverage_met = covered.as_ref().map_or(min_coverage.is_none(), |r| r.meets(min_coverage));
+        last_exit_code = Some(out.exit_code);
+        summary.iterations = iteration;
+        summary.final_exit_code = last_exit_code;
+        
+        // Numeric objectives from benchmarks.metrics; success needs every target met
+        let readings = extract_metrics(&metrics, &out.stdout);
+        let passed = runs.required_passed() && targets_met(&readings) && coverage_met;
+        summary.record_metrics(&readings);
+        summary.coverage = covered.as_ref().map(|r| r.percent);
+        summary.suites = if suites.len() > 1 { runs.results() } else { Vec::new() };
+        summary.passed = passed;
+        sink.on_event(&ProgressEvent::TestsFinished {
+            iteration,
+            command: test_cmd.trim().to_string(),
+            exit_code: out.exit_code,
+            passed,
+            stdout: out.stdout.clone(),
+            stderr: out.stderr.clone(),
+            metrics: summary.metrics.clone(),
+            suites: summary.suites.clone(),
+        });
+        if !out.timed_out.is_empty() {
+            console.warning(&format!("Timed out: {} (partial output above)", out.timed_out.join(", ")))?;
+        }
+        if !summary.suites.is_empty() {
+            let failed: Vec<&str> = summary.suites.iter().filter(|r| !r.passed).map(|r| r.name.as_str()).collect();
+            if let Some(logger) = &debug_file {
+                logger.event("suites", serde_json::json!({ "iteration": iteration, "results": summary.suites, "score": score(&summary.suites) }));
+            }
+            audit::record("suites", serde_json::json!({ "iteration": iteration, "failed": failed, "score": score(&summary.suites) }));
+        }
+        if let Err(e) = record_iteration(&cwd_abs, &mut metric_history, iteration, out.exit_code, test_counts(&out.stdout), &readings) {
+            console.warning(&format!("Could not save metrics: {}", e))?;
+        }
+        match write_metrics_chart(&cwd_abs, &metrics, &metric_history) {
+            Ok(chart) => summary.artifacts.metrics_chart = chart,
+            Err(e) => console.warning(&format!("Could not draw the metrics chart: {:#}", e))?,
+        }
+        if !metrics.is_empty() {
+            console.section("Metrics")?;
+            for line in render_trajectory(&readings, &metric_history) {
+                console.println(&format!("  {}", line))?;
+            }
+            if let Some(logger) = &debug_file {
+                let values: serde_json::Map<String, serde_json::Value> =
+                    readings.iter().map(|r| (r.name.clone(), serde_json::json!(r.value))).collect();
+                logger.event("metrics", serde_json::json!({ "iteration": iteration, "values": values }));
+            }
+        }
+
+        // benchmarks.artifacts: keep what this run wrote before the next iteration overwrites it
+        let artifacts = if artifact_globs.is_empty() {
+            Vec::new()
+        } else {
+            match collect_artifacts(&cwd_abs, &artifact_globs, iteration) {
+                Ok(files) => files,
+                Err(e) => {
+                    console.warning(&format!("Could not collect artifacts: {:#}", e))?;
+                    Vec::new()
+                }
+            }
+        };
+        if !artifacts.is_empty() {
+            console.info(&format!("Collected {} artifact(s) in .qernel/artifacts/iter-{}", artifacts.len(), iteration))?;
+            if let Some(logger) = &debug_file {
+                logger.event("artifacts", serde_json::json!({ "iteration": iteration, "files": artifacts }));
+            }
+        }
+        summary.artifacts.test_artifacts = artifact_paths(&cwd_abs, iteration, &artifacts);
+
+        if let Some(report) = &covered {
+            console.info(&coverage_line(report, min_coverage))?;
+            if let Some(logger) = &debug_file {
+                logger.event("coverage", serde_json::json!({ "iteration": iteration, "percent": report.percent, "min_percent": min_coverage }));
+            }
+        }
+
+        // Collect failure context for next iteration
+        if !passed {
+            failure_context.clear();
+            if !out.timed_out.is_empty() {
+                let killed = if suites.len() > 1 { format!("suite {}", out.timed_out.join(", ")) } else { "the test command".to_string() };
+                failure_context.push_str(&format!(
+                    "Previous iteration {} timed out: {} was killed after the wall-clock limit of {}s. \
+                    The output below is what it printed before then; look for an infinite loop or work that is too slow.\n",
+                    iteration,
+                    killed,
+                    limits.wall_clock.map_or(120, |d| d.as_secs())
+                ));
+            } else if out.exit_code == 0 && !coverage_met {
+                failure_context.push_str(&format!("Previous iteration {} passed the tests but did not reach the coverage target.\n", iteration));
+            } else if out.exit_code == 0 {
+                failure_context.push_str(&format!("Previous iteration {} passed the tests but missed metric targets.\n", iteration));
+            } else {
+                failure_context.push_str(&format!("Previous iteration {} failed with exit code {}.\n", iteration, out.exit_code));
+            }
+            if !summary.suites.is_empty() {
+                let failed: Vec<String> = summary
+                    .suites
+                    .iter()
+                    .filter(|r| !r.passed)
+                    .map(|r| format!("{} ({}, exit code {})", r.name, if r.required { "required" } else { "optional" }, r.exit_code))
+                    .collect();
+                failure_context.push_str(&format!("Failing test suites: {}\n", failed.join(", ")));
+            }
+            
+            if !out.stdout.is_empty() {
+                failure_context.push_str("Test output:\n");
+                failure_context.push_str(&summarize_test_output(&out.stdout, test_output_chars));
+                failure_context.push_str("\n");
+            }
+            
+            if !out.stderr.is_empty() {
+                failure_context.push_str("Error output:\n");
+                failure_context.push_str(&summarize_test_output(&out.stderr, test_output_chars));
+                failure_context.push_str("\n");
+            }
+
+            if !metrics.is_empty() {
+                failure_context.push_str(&metrics_prompt_section(&readings));
+            }
+
+            if let Some(report) = &covered {
+                failure_context.push_str(&coverage_prompt_section(report, min_coverage));
+            }
+
+            failure_context.push_str(&artifacts_prompt_section(&cwd_abs, iteration, &artifacts));
+
+            if !review_feedback.is_empty() {
+                failure_context.push_str(&format!(
+                    "Your patch was NOT applied: the reviewer rejected it with this critique:\n{}\nRevise the patch to address it.\n",
+                    review_feedback
+                ));
+            }
+        }
+        
+        // Always log debug info to the run log
+        if let Some(logger) = &debug_file {
+            logger.event("exec", serde_json::json!({
+                "command": argv.join(" "),
+                "exit_code": out.exit_code,
+                "stdout": out.stdout,
+                "stderr": out.stderr,
+            }));
+        }
+        
+        if passed {
+            if let Some(items) = plan.as_mut() {
+                mark_all_completed(items);
+                save_plan(&cwd_abs, items)?;
+            }
+            clear_checkpoint(&cwd_abs);
+            console.println("")?;
+            console.success("🎉 Implementation completed successfully!")?;
+            if report {
+                // Review mode: the run already succeeded, so a failed report is only a warning
+                let spinner = console.start_spinner_with_timer("AI is writing the implementation report...", 600);
+                let tests = TestRun {
+                    command: &test_cmd,
+                    iteration,
+                    stdout: &out.stdout,
+                    stderr: &out.stderr,
+                    readings: &readings,
+                    artifacts: &artifacts,
+                };
+                let written = write_report(&api_key, &model, &cwd_abs, &goal, &tests, &sampling);
+                console.stop_spinner(&spinner);
+                usage_tracker.report(sink, &model);
+                match written {
+                    Ok(path) => console.info(&format!("Report written to {} (view with `qernel see`)", path.display()))?,
+                    Err(e) => console.warning(&format!("Could not write the report: {:#}", e))?,
+                }
+            }
+            break Ok(());
+        }
+
+        if let Some(reason) = budget.exceeded(run_started, &usage::totals()) {
+            return stop_for_budget(&console, &cwd_abs, iteration, &failure_context, last_exit_code, run_started, &reason);
+        }
+        let status = if out.exit_code != 0 {
+            "Tests are still failing"
+        } else if !coverage_met {
+            "Coverage is below the target"
+        } else {
+            "Metric targets are not met yet"
+        };
+        if !continue_after_failure(&console, iteration, max_iters, status)? {
+            break Ok(());
+        }
+    }
+}
+
+/// How a run ended without success, for callers (and exit codes) to tell apart
+#[derive(Debug, thiserror::Error)]
+pub enum RunFailure {
+    #[error("max iters ({0}) reached without success")]
+    MaxIterations(u32),
+    #[error("budget exceeded: {0}")]
+    BudgetExceeded(String),
+}
+
+impl RunFailure {
+    /// 2 for max iterations, 3 for an exceeded budget; other errors exit with 1
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunFailure::MaxIterations(_) => 2,
+            RunFailure::BudgetExceeded(_) => 3,
+        }
+    }
+}
+
+/// End of an unsuccessful iteration: fail at the iteration limit, otherwise ask the user
+/// whether to go on. `false` means the user chose to stop.
+fn continue_after_failure(console: &ConsoleStreamer, iteration: u32, max_iters: u32, status: &str) -> Result<bool> {
+    if iteration >= max_iters {
+        console.println("")?;
+        console.error("⚠️  Maximum iterations reached without success")?;
+        return Err(RunFailure::MaxIterations(max_iters).into());
+    }
+    console.println("")?;
+    let should_continue = console.ask_continue(&format!(
+        "Iteration {} completed. {}. Would you like the AI agent to continue with iteration {}?",
+        iteration, status, iteration + 1
+    ))?;
+    if !should_continue {
+        console.info("User chose to stop. Exiting...")?;
+    }
+    Ok(should_continue)
+}
+
+/// Read the coverage report the tests just wrote (`measured`), or run the `benchmarks.coverage`
+/// command for one. Problems are warnings: without a report the iteration simply has no
+/// coverage (and misses a `min_percent` target).
+fn measure_coverage(console: &ConsoleStreamer, config: &CoverageConfig, cwd: &Path, limits: &ResourceLimits, measured: bool) -> Option<CoverageReport> {
+    if !measured {
+        let argv = match &config.command {
+            Some(command) => match shlex::split(command).filter(|a| !a.is_empty()) {
+                Some(argv) => argv,
+                None => {
+                    let _ = console.warning(&format!("Invalid benchmarks.coverage command: {}", command));
+                    return None;
+                }
+            },
+            None => default_coverage_command(cwd),
+        };
+        if let Err(e) = run_cmd_with_events(&argv, cwd, limits) {
+            let _ = console.warning(&format!("Could not measure coverage: {:#}", e));
+            return None;
+        }
+    }
+    match read_coverage(&coverage_path(cwd)) {
+        Ok(report) => Some(report),
+        Err(e) => {
+            // pytest-cov missing is the usual cause
+            let _ = console.warning(&format!("Could not measure coverage: {:#} (is pytest-cov installed?)", e));
+            None
+        }
+    }
+}
+
+/// Whether the project's Python can load pytest-cov, whose options would otherwise make
+/// pytest fail before running any test
+fn pytest_cov_installed(cwd: &Path, limits: &ResourceLimits) -> bool {
+    let argv = ["python", "-c", "import pytest_cov"].map(String::from);
+    run_cmd_with_events(&argv, cwd, limits).is_ok_and(|out| out.exit_code == 0)
+}
+
+/// Compiler output for changed Python files that do not compile, from `python -m py_compile`.
+/// Falls back to tree-sitter when Python cannot be run.
+fn compile_errors(files: &[String], cwd: &Path, limits: &ResourceLimits) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+    let mut argv = vec!["python".to_string(), "-m".to_string(), "py_compile".to_string()];
+    argv.extend(files.iter().cloned());
+    match run_cmd_with_events(&argv, cwd, limits) {
+        Ok(out) if out.exit_code == 0 => None,
+        Ok(out) if out.exit_code != 127 && !out.stderr.text.contains("No module named") => {
+            Some(format!("{}{}", out.stdout.text, out.stderr.text).trim().to_string())
+        }
+        _ => {
+            let errors: Vec<String> = files
+                .iter()
+                .filter_map(|f| {
+                    let source = std::fs::read_to_string(cwd.join(f)).ok()?;
+                    python_syntax_error(&source).map(|e| format!("{}:{}:{}: {}", f, e.line, e.column, e.message))
+                })
+                .collect();
+            (!errors.is_empty()).then(|| errors.join("\n"))
+        }
+    }
+}
+
+
+/// Honor `.qernel/STOP`: save a checkpoint, consume the stop file and end the run cleanly
+fn stop_for_request(console: &ConsoleStreamer, cwd: &Path, iteration: u32, failure_context: &str, last_exit_code: Option<i32>) -> Result<()> {
+    save_checkpoint(cwd, &Checkpoint {
+        iteration,
+        reason: "stop file".to_string(),
+        failure_context: failure_context.to_string(),
+        last_exit_code,
+        saved_at: chrono::Utc::now().to_rfc3339(),
+    })?;
+    clear_stop_request(cwd);
+    console.println("")?;
+    console.warning(&format!(
+        "Stop requested via .qernel/STOP after {} iteration(s). Checkpoint saved to .qernel/checkpoint.json; continue with `qernel prototype --resume`",
+        iteration
+    ))?;
+    Ok(())
+}
+
+/// A budget limit was reached: save a checkpoint, summarize the run and fail
+#[allow(clippy::too_many_arguments)]
+fn stop_for_budget(console: &ConsoleStreamer, cwd: &Path, completed: u32, failure_context: &str, last_exit_code: Option<i32>, started: Instant, reason: &str) -> Result<()> {
+    save_checkpoint(cwd, &Checkpoint {
+        iteration: completed,
+        reason: format!("budget exceeded: {}", reason),
+        failure_context: failure_context.to_string(),
+        last_exit_code,
+        saved_at: chrono::Utc::now().to_rfc3339(),
+    })?;
+    console.println("")?;
+    console.section("Budget exceeded")?;
+    console.println(&format!("  {}", reason))?;
+    console.println(&format!("  Completed iterations: {}", completed))?;
+    console.println(&format!("  Elapsed: {}", format_elapsed(started.elapsed())))?;
+    console.println(&format!("  Model usage: {}", usage::totals().summary()))?;
+    if let Some(code) = last_exit_code {
+        console.println(&format!("  Last test run: exit code {}", code))?;
+    }
+    let turns = list_turns(cwd).len();
+    if turns > 0 {
+        console.println(&format!("  Recorded turn diffs: {} (view with `qernel diff`)", turns))?;
+    }
+    console.println(&format!("  Checkpoint: {}", checkpoint_path(cwd).display()))?;
+    console.info("Raise the limit and continue with `qernel prototype --resume`")?;
+    Err(RunFailure::BudgetExceeded(reason.to_string()).into())
+}
+
+/// Ctrl-C: save what the next run needs for `--resume` and summarize the run so far.
+/// `completed` is the last iteration whose tests finished.
+fn stop_for_interrupt(console: &ConsoleStreamer, cwd: &Path, completed: u32, failure_context: &str, last_exit_code: Option<i32>, during: &str) -> Result<()> {
+    save_checkpoint(cwd, &Checkpoint {
+        iteration: completed,
+        reason: format!("interrupted {}", during),
+        failure_context: failure_context.to_string(),
+        last_exit_code,
+        saved_at: chrono::Utc::now().to_rfc3339(),
+    })?;
+    console.println("")?;
+    console.section("Run interrupted")?;
+    console.println(&format!("  Stopped {}", during))?;
+    console.println(&format!("  Completed iterations: {}", completed))?;
+    match last_exit_code {
+        Some(code) => console.println(&format!("  Last test run: exit code {}", code))?,
+        None => console.println("  Last test run: none")?,
+    }
+    let turns = list_turns(cwd).len();
+    if turns > 0 {
+        console.println(&format!("  Recorded turn diffs: {} (view with `qernel diff`)", turns))?;
+    }
+    console.println(&format!("  Checkpoint: {}", checkpoint_path(cwd).display()))?;
+    console.info("Continue with `qernel prototype --resume`")?;
+    Ok(())
+}
+
+/// Request AI step with focused context and clear instructions
+#[allow(clippy::too_many_arguments)]
+fn request_ai_step(api_key: &str, model: &str, goal: &str, test_cmd: &str, cwd: &Path, figures: &[String], image_config: &ImageConfig, sampling: &Sampling, debug_file: &Option<RunLogger>, user: &str) -> Result<AiStep> {
+    let _span = tracing::info_span!("ai_step", model).entered();
+    let caps = models::capabilities(model);
+    debug_log(debug_file, &format!("[ai] model capabilities: {:?}", caps), debug_file.is_some());
+    let project_directory_content = project_context(cwd, model);
+    
+    // Debug: Show what context the agent is receiving
+    debug_log(debug_file, &format!("[ai] project directory content length: {} chars", project_directory_content.len()), debug_file.is_some());
+    debug_log(debug_file, &format!("[ai] project directory preview: {}", truncate_utf8(&project_directory_content, 500)), debug_file.is_some());
+    debug_log(debug_file, &format!("[ai] model: {}", model), debug_file.is_some());
+    
+    // Show the complete project context that the model sees
+    debug_log(debug_file, "[ai] ===== COMPLETE PROJECT CONTEXT =====", false);
+    debug_log(debug_file, &project_directory_content, false);
+    debug_log(debug_file, "[ai] ===== END PROJECT CONTEXT =====", false);
+
+    let mut system = build_system_prompt(goal, test_cmd, cwd, &project_directory_content);
+    system.push_str(&sdk_prompt_section(&analyze_project(cwd)));
+    
+    // Debug: Show prompt lengths
+    debug_log(debug_file, &format!("[ai] system prompt length: {} chars", system.len()), debug_file.is_some());
+    debug_log(debug_file, &format!("[ai] user prompt length: {} chars", user.len()), debug_file.is_some());
+    
+    // Show the complete system prompt that the model sees
+    debug_log(debug_file, "[ai] ===== COMPLETE SYSTEM PROMPT =====", false);
+    debug_log(debug_file, &system, false);
+    debug_log(debug_file, "[ai] ===== END SYSTEM PROMPT =====", false);
+    
+    // Show the complete user prompt that the model sees
+    debug_log(debug_file, "[ai] ===== COMPLETE USER PROMPT =====", false);
+    debug_log(debug_file, user, false);
+    debug_log(debug_file, "[ai] ===== END USER PROMPT =====", false);
+
+    // Create tools for the request
+    let tools = create_tools(&caps);
+    
+    // Attach the selected paper figures; text-only models never get them
+    if !caps.vision {
+        debug_log(debug_file, "[ai] model has no vision support, using text-only request", debug_file.is_some());
+        make_openai_request(api_key, model, &system, user, tools, sampling, debug_file)
+    } else if figures.is_empty() {
+        debug_log(debug_file, "[ai] no figures selected from parsed content", debug_file.is_some());
+        make_openai_request(api_key, model, &system, user, tools, sampling, debug_file)
+    } else {
+        debug_log(debug_file, &format!("[ai] including {} figures from parsed PDFs in model request", figures.len()), debug_file.is_some());
+        make_openai_request_with_images(api_key, model, &system, user, tools, sampling, debug_file, Some(figures.to_vec()), image_config)
+    }
+}
+
+/// Apply an approved patch and run the post-patch hooks on the files it wrote, keeping the
+/// iteration's cumulative diff for `qernel diff --turn <n>`; returns the result reported
+/// back to the model, and the files changed when the patch landed
+#[allow(clippy::too_many_arguments)]
+fn apply_agent_patch(console: &ConsoleStreamer, cwd: &Path, iteration: u32, patch: &str, max_fuzz: usize, echo: ExecEcho, snapshot: &mut TurnSnapshot, hooks: &[String], limits: &ResourceLimits, max_output: usize, debug_file: &Option<RunLogger>) -> Result<(String, Option<Vec<PathBuf>>)> {
+    // Debug: Log the patch content for troubleshooting
+    debug_log(debug_file, &format!("[patch] Applying patch:\n{}", patch), debug_file.is_some());
+    snapshot.add_patch_files(patch, cwd);
+    let outcome = {
+        let _patch_span = tracing::info_span!("apply_patch", bytes = patch.len()).entered();
+        apply_transactional(patch, cwd, max_fuzz)?
+    };
+    exec_events::render_patch_events(&mut ExecEventRenderer::new(echo));
+    let (stdout, files) = match outcome {
+        PatchOutcome::Applied { stdout, files } => (stdout, files),
+        PatchOutcome::RolledBack { reason } => {
+            console.error(&format!("Patch rolled back: {}", reason))?;
+            debug_log(debug_file, &format!("[patch] Rolled back: {}", reason), debug_file.is_some());
+            return Ok((format!("Not applied, no files were changed: {}", reason), None));
+        }
+    };
+    console.print(&stdout)?;
+    console.typewriter("Code changes applied successfully", 15)?;
+    let hook_report = run_post_patch_hooks(console, cwd, hooks, &files, limits, max_output, debug_file)?;
+    // Recorded after the hooks so their changes are part of the turn diff; a failed write should not stop the run
+    match record_turn_diff(cwd, iteration, snapshot) {
+        Ok(Some(path)) => debug_log(debug_file, &format!("[patch] Turn diff saved to {}", path.display()), debug_file.is_some()),
+        Ok(None) => {}
+        Err(e) => console.warning(&format!("Could not save turn diff: {}", e))?,
+    }
+    Ok((format!("Patch applied.\n{}{}", stdout, hook_report).trim_end().to_string(), Some(files)))
+}
+
+/// Run `agent.post_patch_hooks` in the exec sandbox with the patch's files appended.
+/// Returns what the model should hear: files a hook changed and hooks that failed.
+fn run_post_patch_hooks(console: &ConsoleStreamer, cwd: &Path, hooks: &[String], files: &[PathBuf], limits: &ResourceLimits, max_output: usize, debug_file: &Option<RunLogger>) -> Result<String> {
+    let rel: Vec<String> = files.iter().map(|f| f.strip_prefix(cwd).unwrap_or(f).to_string_lossy().to_string()).collect();
+    let mut report = String::new();
+    if rel.is_empty() {
+        return Ok(report);
+    }
+    for hook in hooks {
+        let Some(mut argv) = shlex::split(hook).filter(|a| !a.is_empty()) else {
+            console.warning(&format!("Skipping post-patch hook that could not be parsed: {}", hook))?;
+            continue;
+        };
+        argv.extend(rel.iter().cloned());
+        let before: Vec<Option<String>> = files.iter().map(|f| std::fs::read_to_string(f).ok()).collect();
+        console.info(&format!("Running post-patch hook: {}", hook))?;
+        let out = match run_cmd_with_events(&argv, cwd, limits) {
+            Ok(out) => out,
+            Err(e) => {
+                console.warning(&format!("Post-patch hook `{}` could not run: {:#}", hook, e))?;
+                report.push_str(&format!("`{}` could not run: {:#}\n", hook, e));
+                continue;
+            }
+        };
+        let changed: Vec<&str> = rel
+            .iter()
+            .zip(files.iter().zip(before))
+            .filter(|(_, (f, before))| std::fs::read_to_string(f).ok() != *before)
+            .map(|(r, _)| r.as_str())
+            .collect();
+        debug_log(debug_file, &format!("[hook] {} exited {}; changed {:?}", hook, out.exit_code, changed), debug_file.is_some());
+        if !changed.is_empty() {
+            report.push_str(&format!("`{}` changed: {}\n", hook, changed.join(", ")));
+        }
+        if out.exit_code != 0 {
+            console.warning(&format!("Post-patch hook `{}` failed with exit code {}", hook, out.exit_code))?;
+            report.push_str(&format!(
+                "`{}` failed with exit code {}:\n{}\n",
+                hook,
+                out.exit_code,
+                summarize_test_output(&format!("{}{}", out.stdout.text, out.stderr.text), max_output)
+            ));
+        }
+    }
+    Ok(report)
+}
+
+/// Classify a shell command, log it, and apply the approval policy: `None` when it may
+/// run, else the model's feedback
+fn screen_command(console: &ConsoleStreamer, debug_file: &Option<RunLogger>, iteration: u32, approvals: &Approvals, argv: &[String]) -> Result<Option<String>> {
+    let (class, parsed) = command_policy::classify(argv);
+    let command = argv.join(" ");
+    let (decision, approved) = approve(console, approvals, Action::Shell(class), &format!("run a {} command:\n  {}", class.label(), command))?;
+    if let Some(logger) = debug_file {
+        logger.event("command", serde_json::json!({ "iteration": iteration, "command": argv, "class": class, "parsed": parsed, "decision": decision, "approved": approved }));
+    }
+    if approved {
+        return Ok(None);
+    }
+    Ok(Some(refusal(console, approvals, Action::Shell(class), decision, &format!("Not running `{}`", command))?))
+}
+
+/// Install an approved dependency the way shell commands run (exec backend, resource limits,
+/// timeout) and add it to requirements.txt only once pip succeeded
+fn install_dependency(cwd: &Path, dependency: &Dependency, limits: &ResourceLimits, echo: ExecEcho, offline: bool) -> Result<String> {
+    let Some(argv) = install_command(cwd, dependency) else {
+        record_dependency(cwd, dependency)?;
+        return Ok(format!("Added {} to requirements.txt. There is no project venv yet, so it was not installed.", dependency.line));
+    };
+    let out = match run_cmd_streaming(&argv, cwd, limits, echo, false, offline) {
+        Ok(out) => out,
+        Err(e) => return Ok(format!("Did not add {}: pip could not run: {:#}", dependency.line, e)),
+    };
+    if out.exit_code != 0 {
+        return Ok(format!(
+            "Did not add {}: pip install failed with exit code {}:\n{}",
+            dependency.line,
+            out.exit_code,
+            out.stderr.text.trim()
+        ));
+    }
+    record_dependency(cwd, dependency)?;
+    Ok(format!("Added {} to requirements.txt and installed it.", dependency.line))
+}
+
+/// Ask about a patch or dependency when the approval policy says to: `None` when it may go
+/// ahead, else the model's feedback
+fn confirm_action(console: &ConsoleStreamer, debug_file: &Option<RunLogger>, iteration: u32, approvals: &Approvals, action: Action, what: &str) -> Result<Option<String>> {
+    let (decision, approved) = approve(console, approvals, action, what)?;
+    if decision == PolicyAction::Allow {
+        return Ok(None);
+    }
+    if let Some(logger) = debug_file {
+        logger.event("approval", serde_json::json!({ "iteration": iteration, "action": action.label(), "approved": approved }));
+    }
+    if approved {
+        return Ok(None);
+    }
+    Ok(Some(refusal(console, approvals, action, decision, &format!("Not going to {}", what))?))
+}
+
+/// The policy's decision for `action`, and whether it may go ahead (asking the user if need be;
+/// nobody to ask means no)
+fn approve(console: &ConsoleStreamer, approvals: &Approvals, action: Action, what: &str) -> Result<(PolicyAction, bool)> {
+    let decision = approvals.decide(action);
+    let approved = match decision {
+        PolicyAction::Allow => true,
+        PolicyAction::Deny => false,
+        PolicyAction::Ask => console.confirm(&format!("The agent wants to {}", what))?.unwrap_or(false),
+    };
+    Ok((decision, approved))
+}
+
+/// Warn that an action was refused and build the model's feedback
+fn refusal(console: &ConsoleStreamer, approvals: &Approvals, action: Action, decision: PolicyAction, headline: &str) -> Result<String> {
+    let reason = match decision {
+        PolicyAction::Deny if approvals.policy(action) == PolicyAction::Deny => {
+            format!("{0} commands are denied by agent.command_policy.{0}", action.label())
+        }
+        PolicyAction::Deny => format!("{0} commands are refused by --approval never, since agent.command_policy.{0} asks before running them", action.label()),
+        _ => format!("{} actions need the user's approval, and it was not given", action.label()),
+    };
+    console.warning(&format!("{}: {}", headline, reason))?;
+    Ok(serde_json::json!({ "status": "rejected", "action": action.label(), "reason": reason, "hint": "this action was not carried out; try another approach" }).to_string())
+}
+
+/// Tell the user an action broke `agent.constraints`, log it, and return the model's feedback
+fn reject_for_constraint(console: &ConsoleStreamer, debug_file: &Option<RunLogger>, iteration: u32, violation: &Violation) -> Result<String> {
+    console.warning(&format!("Refused by agent.constraints.{}: {}", violation.constraint, violation.reason))?;
+    if let Some(logger) = debug_file {
+        logger.event("constraint", serde_json::json!({ "iteration": iteration, "violation": violation }));
+    }
+    Ok(violation.feedback())
+}
+
+/// Ask the reviewer about a patch. A review that fails rejects the patch; `None` only means
+/// Ctrl-C abandoned the review.
+fn review_patch(reviewer: &Reviewer, console: &ConsoleStreamer, patch: &str) -> Result<Option<Review>> {
+    let spinner = console.start_spinner_with_timer(&format!("Reviewer ({}) is checking the patch...", reviewer.model), 600);
+    let review = run_role(reviewer, patch.to_string());
+    console.stop_spinner(&spinner);
+    match review {
+        Ok(Some(review)) => {
+            if review.approved {
+                console.success("Reviewer approved the patch")?;
+            }
+            Ok(Some(review))
+        }
+        Ok(None) => Ok(None),
+        // Fails closed: an unreviewed patch is not applied
+        Err(e) => Ok(Some(Review {
+            approved: false,
+            critique: format!("The reviewer could not check the patch ({:#}), so it was not applied; send it again to retry the review.", e),
+        })),
+    }
+}
+
+/// Focused directory snapshot, capped to keep requests reasonable and within half of a small model's window
+fn project_context(cwd: &Path, model: &str) -> String {
+    let snapshot = create_directory_snapshot(cwd).unwrap_or_else(|_| "Failed to read project directory".to_string());
+    let max_ctx = 120_000.min(models::capabilities(model).max_context_chars() / 2);
+    if snapshot.len() > max_ctx {
+        let head = truncate_utf8(&snapshot, max_ctx / 2);
+        let tail = truncate_utf8_tail(&snapshot, max_ctx / 2);
+        format!("{head}\n...\n[TRUNCATED]\n...\n{tail}")
+    } else {
+        snapshot
+    }
+}
+
+fn create_tools(caps: &ModelCapabilities) -> serde_json::Value {
+    use codex_core::plan_tool::create_update_plan_tool;
+    use codex_core::tool_add_dependency::create_add_dependency_tool;
+    use codex_core::tool_shell::create_shell_tool;
+    use codex_core::tool_apply_patch::{
+        create_apply_patch_freeform_tool,  // "custom" (free-form / grammar)
+        create_apply_patch_json_tool,      // "function" (JSON schema)
+    };
+    
+    if !caps.tool_calls {
+        // No tools: each step is requested as structured JSON output instead
+        serde_json::json!([])
+    } else if caps.freeform_tools {
+        // e.g. GPT-5 models use custom freeform tools
+        serde_json::to_value(vec![create_apply_patch_freeform_tool(), create_shell_tool(), create_add_dependency_tool(), create_update_plan_tool()]).expect("tools json")
+    } else {
+        // codex-mini-latest and other models use JSON function tools
+        serde_json::to_value(vec![create_apply_patch_json_tool(), create_shell_tool(), create_add_dependency_tool(), create_update_plan_tool()]).expect("tools json")
+    }
+}
+
+/// Run a command in the project venv under `limits`, the way the agent runs the tests,
+/// without echoing it
+pub fn run_cmd_with_events(argv: &[String], cwd: &Path, limits: &ResourceLimits) -> Result<codex_core::exec::ExecToolCallOutput> {
+    run_cmd_streaming(argv, cwd, limits, ExecEcho::Silent, false, false)
+}
+
+/// Like [`run_cmd_with_events`], with the command's events rendered on the console as they
+/// arrive at `echo`; with `use_pty` it runs under a pseudo-terminal (`benchmarks.use_pty`),
+/// and with `offline` without a network (`agent.constraints.no_network`). An offline
+/// command never gets a pseudo-terminal, which cannot be spawned into a network namespace.
+pub fn run_cmd_streaming(argv: &[String], cwd: &Path, limits: &ResourceLimits, echo: ExecEcho, use_pty: bool, offline: bool) -> Result<codex_core::exec::ExecToolCallOutput> {
+    use async_channel::unbounded as async_unbounded;
+    use codex_core::exec::{process_exec_tool_call, CodexErr, ExecParams, SandboxErr, StdoutStream};
+    use codex_core::protocol::{Event, SandboxPolicy};
+
+    // exec.backend: locally in the project's Python environment or in a container
+    let PreparedCommand { argv: cmd, env, sandbox } = prepare_command(argv, cwd)?;
+    let _span = tracing::info_span!("exec", command = %cmd.join(" ")).entered();
+    let params = ExecParams {
+        command: cmd,
+        cwd: cwd.to_path_buf(),
+        // Tests can reasonable take longer; benchmarks.limits.wall_clock_secs overrides this
+        timeout_ms: Some(limits.wall_clock.map_or(120_000, |d| d.as_millis() as u64)),
+        env,
+        with_escalated_permissions: None,
+        justification: None,
+        limits: *limits,
+        use_pty: use_pty && !offline,
+    };
+    let sandbox_policy = if offline { SandboxPolicy::NO_NETWORK } else { SandboxPolicy::DANGER_FULL_ACCESS };
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to create tokio runtime")?;
+
+    let (tx_event, rx_event) = async_unbounded::<Event>();
+    let printer = std::thread::spawn(move || {
+        let mut renderer = ExecEventRenderer::new(echo);
+        while let Ok(ev) = rx_event.recv_blocking() {
+            renderer.render(&ev);
+        }
+        renderer.flush();
+    });
+
+    let stream = StdoutStream {
+        sub_id: "s1".into(),
+        call_id: "c1".into(),
+        tx_event: tx_event.clone(),
+    };
+
+    let timeout = params.timeout_duration();
+    let result = rt.block_on(process_exec_tool_call(
+        params,
+        sandbox,
+        &sandbox_policy,
+        cwd,
+        &None,
+        Some(stream),
+    ));
+    // Every sender is gone once the command has finished, so the printer drains and exits
+    drop(tx_event);
+    let _ = printer.join();
+    let out = match result {
+        Ok(out) => Ok(out),
+        // A timed-out run is a failed run the model can act on, not a fatal error
+        Err(CodexErr::Sandbox(SandboxErr::Timeout { output })) => {
+            let mut out = *output;
+            out.stderr.text.push_str(&format!("\nqernel: timed out; command killed after the wall-clock limit of {}s\n", timeout.as_secs()));
+            Ok(out)
+        }
+        // docker could not start the container (unknown image, daemon down)
+        Err(CodexErr::Sandbox(SandboxErr::Denied { output })) => {
+            Err(QernelError::Exec(format!("could not run `{}` in a container: {}", argv.join(" "), output.stderr.text.trim())))
+        }
+        Err(e) => Err(QernelError::Exec(format!("could not run `{}`: {:?}", argv.join(" "), e))),
+    }?;
+    audit::record("exec", serde_json::json!({
+        "command": argv,
+        "exit_code": out.exit_code,
+        "duration_ms": out.duration.as_millis() as u64,
+        "stdout_sha256": audit::sha256_hex(out.stdout.text.as_bytes()),
+        "stderr_sha256": audit::sha256_hex(out.stderr.text.as_bytes()),
+    }));
+    Ok(out)
+}
+
+/// Lines kept from the end of each failing test's traceback
+const TRACEBACK_TAIL_LINES: usize = 25;
+
+/// Shrink test output for the next prompt. Pytest output keeps the failure summary
+/// and the end of each failing test's traceback; anything else keeps its first and
+/// last lines. Omitted lines are counted so the model knows something was cut.
+/// `max_len` (`prototype.max_chars`) bounds the result in bytes.
+fn summarize_test_output(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    let summary = summarize_pytest(&lines).unwrap_or_else(|| head_tail_lines(&lines, 40, 120));
+    if summary.len() <= max_len {
+        return summary;
+    }
+    // Still too long (e.g. a few enormous lines): keep mostly the end, where the verdict is
+    let head = truncate_utf8(&summary, max_len / 4);
+    let tail = truncate_utf8_tail(&summary, max_len * 3 / 4);
+    format!("{}\n[... {} bytes omitted ...]\n{}", head, summary.len() - head.len() - tail.len(), tail)
+}
+
+/// `None` when the output has no pytest FAILURES/ERRORS section
+fn summarize_pytest(lines: &[&str]) -> Option<String> {
+    let is_report = |l: &str| is_pytest_banner(l) && (l.contains(" FAILURES ") || l.contains(" ERRORS "));
+    let start = lines.iter().position(|l| is_report(l))?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| is_pytest_banner(l) && !is_report(l))
+        .map_or(lines.len(), |i| start + 1 + i);
+
+    let mut out: Vec<String> = Vec::new();
+    if start > 0 {
+        out.push(format!("[{} lines before the failures omitted]", start));
+    }
+    let mut block: Vec<&str> = Vec::new();
+    for line in &lines[start..end] {
+        if is_pytest_banner(line) || is_pytest_test_header(line) {
+            push_traceback_tail(&mut out, &block);
+            block.clear();
+            out.push(line.to_string());
+        } else {
+            block.push(line);
+        }
+    }
+    push_traceback_tail(&mut out, &block);
+
+    // Warnings and the short test summary, ending with the "N failed, M passed" line
+    out.push(head_tail_lines(&lines[end..], 10, 60));
+    Some(out.join("\n"))
+}
+
+fn push_traceback_tail(out: &mut Vec<String>, block: &[&str]) {
+    let omitted = block.len().saturating_sub(TRACEBACK_TAIL_LINES);
+    if omitted > 0 {
+        out.push(format!("[{} traceback lines omitted]", omitted));
+    }
+    out.extend(block[omitted..].iter().map(|l| l.to_string()));
+}
+
+/// `===== FAILURES =====`, `===== short test summary info =====`, ...
+fn is_pytest_banner(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 6 && line.starts_with("===") && line.ends_with("===")
+}
+
+/// `_____ test_name _____` starts one failing test's report
+fn is_pytest_test_header(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 6 && line.starts_with("___") && line.ends_with("___") && !line.trim_matches('_').trim().is_empty()
+}
+
+fn head_tail_lines(lines: &[&str], head: usize, tail: usize) -> String {
+    if lines.len() <= head + tail {
+        return lines.join("\n");
+    }
+    format!(
+        "{}\n[{} lines omitted]\n{}",
+        lines[..head].join("\n"),
+        lines.len() - head - tail,
+        lines[lines.len() - tail..].join("\n")
+    )
+}
+
+/// An iteration's test outcome over all its suites
+struct TestOutput {
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+    /// Suites killed at the wall-clock limit
+    timed_out: Vec<String>,
+}
+
+fn is_success(out: &codex_core::exec::ExecToolCallOutput, must_contain: Option<&str>) -> bool {
+    let code_ok = out.exit_code == 0;
+    if !code_ok { return false; }
+    match must_contain {
+        Some(s) => out.stdout.text.to_lowercase().contains(&s.to_lowercase()),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_review_reads_a_verdict_wrapped_in_prose() {
+        let review = parse_review("Looks fine.\n```json\n{\"verdict\": \"Approve\", \"critique\": \" ok \"}\n```").unwrap();
+        assert!(review.approved);
+        assert_eq!(review.critique, "ok");
+
+        let review = parse_review("{\"verdict\": \"reject\", \"critique\": \"misses the ansatz\"}").unwrap();
+        assert!(!review.approved);
+        assert_eq!(review.critique, "misses the ansatz");
+
+        let review = parse_review("{\"verdict\": \"reject\"}").unwrap();
+        assert!(!review.approved && review.critique.is_empty());
+    }
+
+    #[test]
+    fn parse_review_refuses_anything_but_a_clear_verdict() {
+        assert!(parse_review("I approve").is_err());
+        assert!(parse_review("} {").is_err());
+        assert!(parse_review("{\"critique\": \"no verdict\"}").is_err());
+        assert!(parse_review("{\"verdict\": \"maybe\"}").is_err());
+        assert!(parse_review("{\"verdict\": \"\"}").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Which agent actions need the user's go-ahead. [`Approvals::decide`] only answers the
+//! question; each front end asks the user its own way (the CLI on the console).
+
+use serde::Serialize;
+
+use crate::cmd::prototype::command_policy::{self, CommandClass};
+use crate::config::{CommandPolicy, PolicyAction};
+
+/// `--approval`: when to stop and ask before an action runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApprovalMode {
+    /// Never ask; actions `agent.command_policy` says to ask about are refused
+    Never,
+    /// Ask before patches, new dependencies and shell commands that are not read-only
+    OnWrite,
+    /// Ask before every shell command and new dependency; patches apply without asking
+    OnShell,
+    /// Ask before every action
+    Always,
+}
+
+/// An action the agent wants to take
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Patch,
+    Shell(CommandClass),
+    Dependency,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Patch => "patch",
+            Action::Shell(class) => class.label(),
+            Action::Dependency => "dependency",
+        }
+    }
+}
+
+/// `--approval` on top of `agent.command_policy`. Without a mode the command policy alone
+/// decides; a command it denies is refused whatever the mode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Approvals {
+    pub mode: Option<ApprovalMode>,
+    pub command_policy: CommandPolicy,
+}
+
+impl Approvals {
+    pub fn decide(&self, action: Action) -> PolicyAction {
+        let base = self.policy(action);
+        if base == PolicyAction::Deny {
+            return base;
+        }
+        let Some(mode) = self.mode else { return base };
+        let ask = match (mode, action) {
+            // Nobody will be asked, so what the policy would ask about does not run
+            (ApprovalMode::Never, _) => return if base == PolicyAction::Ask { PolicyAction::Deny } else { base },
+            (ApprovalMode::Always, _) => true,
+            (ApprovalMode::OnWrite, Action::Shell(class)) => class != CommandClass::ReadOnly,
+            (ApprovalMode::OnWrite, Action::Patch | Action::Dependency) => true,
+            (ApprovalMode::OnShell, Action::Shell(_) | Action::Dependency) => true,
+            (ApprovalMode::OnShell, Action::Patch) => false,
+        };
+        if ask { PolicyAction::Ask } else { PolicyAction::Allow }
+    }
+
+    /// What `agent.command_policy` alone says about `action`
+    pub fn policy(&self, action: Action) -> PolicyAction {
+        match action {
+            Action::Shell(class) => command_policy::decide(&self.command_policy, class),
+            Action::Patch | Action::Dependency => PolicyAction::Allow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_refuses_what_the_policy_would_ask_about() {
+        let approvals = Approvals { mode: Some(ApprovalMode::Never), command_policy: CommandPolicy::default() };
+        assert_eq!(approvals.decide(Action::Shell(CommandClass::Network)), PolicyAction::Deny);
+        assert_eq!(approvals.decide(Action::Shell(CommandClass::Mutating)), PolicyAction::Allow);
+        assert_eq!(approvals.decide(Action::Patch), PolicyAction::Allow);
+    }
+
+    #[test]
+    fn policy_denials_hold_in_every_mode() {
+        let policy = CommandPolicy { mutating: PolicyAction::Deny, network: PolicyAction::Ask };
+        for mode in [ApprovalMode::Never, ApprovalMode::OnWrite, ApprovalMode::OnShell, ApprovalMode::Always] {
+            let approvals = Approvals { mode: Some(mode), command_policy: policy };
+            assert_eq!(approvals.decide(Action::Shell(CommandClass::Mutating)), PolicyAction::Deny);
+            assert_ne!(approvals.decide(Action::Shell(CommandClass::ReadOnly)), PolicyAction::Deny);
+        }
+    }
+}
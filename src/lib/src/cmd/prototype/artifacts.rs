@@ -0,0 +1,98 @@
+//! `benchmarks.artifacts`: after each test run, copy the files the tests wrote (result
+//! tables, plots) to `.qernel/artifacts/iter-<n>/`, and show the model the small JSON ones.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::QernelError;
+
+/// JSON artifacts up to this size are included in the model's context
+const MAX_INLINE_BYTES: u64 = 4 * 1024;
+/// Budget for all inlined artifacts together
+const MAX_INLINE_TOTAL: usize = 16 * 1024;
+
+pub fn artifacts_dir(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("artifacts")
+}
+
+pub fn iteration_dir(cwd: &Path, iteration: u32) -> PathBuf {
+    artifacts_dir(cwd).join(format!("iter-{}", iteration))
+}
+
+/// Reject globs that would reach outside the project
+pub fn validate_artifact_globs(patterns: &[String]) -> Result<()> {
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(QernelError::Config(format!("benchmarks.artifacts: '{}' must be relative to the project root", pattern)).into());
+        }
+        glob::Pattern::new(pattern).map_err(|e| QernelError::Config(format!("benchmarks.artifacts: invalid glob '{}': {}", pattern, e)))?;
+    }
+    Ok(())
+}
+
+/// Copy the files matching `patterns` to [`iteration_dir`], keeping their paths relative to
+/// the project, and return those paths. Files under `.qernel/` are never collected.
+pub fn collect_artifacts(cwd: &Path, patterns: &[String], iteration: u32) -> Result<Vec<String>> {
+    let mut found = BTreeSet::new();
+    for pattern in patterns {
+        // The project path itself may contain glob metacharacters
+        let full = format!("{}/{}", glob::Pattern::escape(&cwd.to_string_lossy()), pattern);
+        let paths = glob::glob(&full).map_err(|e| QernelError::Config(format!("benchmarks.artifacts: invalid glob '{}': {}", pattern, e)))?;
+        for path in paths.flatten() {
+            let Ok(rel) = path.strip_prefix(cwd) else { continue };
+            if path.is_file() && !rel.starts_with(".qernel") {
+                found.insert(rel.to_path_buf());
+            }
+        }
+    }
+
+    // A resumed run can repeat an iteration number
+    let dir = iteration_dir(cwd, iteration);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).with_context(|| format!("failed to clear {}", dir.display()))?;
+    }
+    let mut copied = Vec::new();
+    for rel in found {
+        let dest = dir.join(&rel);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(cwd.join(&rel), &dest).with_context(|| format!("failed to copy {} to {}", rel.display(), dest.display()))?;
+        copied.push(rel.to_string_lossy().replace('\\', "/"));
+    }
+    Ok(copied)
+}
+
+/// Prompt section listing the collected files, with the content of small JSON ones
+pub fn artifacts_prompt_section(cwd: &Path, iteration: u32, files: &[String]) -> String {
+    if files.is_empty() {
+        return String::new();
+    }
+    let dir = iteration_dir(cwd, iteration);
+    let mut out = String::from("Artifacts the test run wrote:\n");
+    let mut inlined = String::new();
+    for rel in files {
+        let path = dir.join(rel);
+        let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+        out.push_str(&format!("- {} ({} bytes)\n", rel, size));
+        if !rel.ends_with(".json") || size > MAX_INLINE_BYTES {
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(&path) else { continue };
+        // Only well-formed JSON; a half-written file would mislead more than help
+        if serde_json::from_str::<serde_json::Value>(&text).is_err() || inlined.len() + text.len() > MAX_INLINE_TOTAL {
+            continue;
+        }
+        inlined.push_str(&format!("--- {} ---\n{}\n", rel, text.trim_end()));
+    }
+    out.push_str(&inlined);
+    out
+}
+
+/// Absolute paths of the collected copies, for the run summary
+pub fn artifact_paths(cwd: &Path, iteration: u32, files: &[String]) -> Vec<PathBuf> {
+    let dir = iteration_dir(cwd, iteration);
+    files.iter().map(|rel| dir.join(rel)).collect()
+}
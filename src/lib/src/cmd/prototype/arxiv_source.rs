@@ -0,0 +1,893 @@
+//! Structured ingestion for arXiv papers.
+//!
+//! The LaTeX source (`arxiv.org/src/<id>`) or the ar5iv HTML rendering usually
+//! extracts far cleaner than the PDF, with exact math instead of OCR. Both are
+//! converted into mineru's `content_list.json` schema so the rest of the pipeline
+//! (spec rendering, figure selection) treats them like a mineru parse.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::cmd::common::cache::{CacheCategory, cache_key, read_http_entry, write_http_entry};
+use crate::cmd::prototype::manifest::PaperFormat;
+
+/// Result of a structured ingest: the written content list and the hash of what was downloaded
+pub struct StructuredPaper {
+    pub format: PaperFormat,
+    pub content_list: PathBuf,
+    pub sha256: String,
+}
+
+/// arXiv identifier (with version, if given) from an abs/pdf/html URL or `arxiv:<id>`
+pub fn arxiv_id(source: &str) -> Option<String> {
+    let s = source.trim();
+    let rest = if let Some(rest) = s.strip_prefix("arxiv:") {
+        rest
+    } else {
+        let idx = s.find("arxiv.org/")?;
+        let path = &s[idx + "arxiv.org/".len()..];
+        let (kind, rest) = path.split_once('/')?;
+        if !matches!(kind, "abs" | "pdf" | "html") {
+            return None;
+        }
+        rest
+    };
+    let id = rest.split(['?', '#']).next()?.trim_end_matches('/').trim_end_matches(".pdf");
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '/' | '-')) {
+        return None;
+    }
+    Some(id.to_string())
+}
+
+/// Try the LaTeX source, then ar5iv HTML, writing `<out_dir>/auto/<stem>_content_list.json`
+/// (plus figure images). `Ok(None)` means neither is available and the PDF should be used.
+pub fn ingest_structured(id: &str, out_dir: &Path, use_cache: bool) -> Result<Option<StructuredPaper>> {
+    let _span = tracing::info_span!("arxiv_structured", id).entered();
+    let auto_dir = out_dir.join("auto");
+    let stem = out_dir.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "paper".to_string());
+
+    if let Some(bytes) = fetch(&format!("https://arxiv.org/src/{}", id), use_cache)?
+        && let Some(files) = unpack_source(&bytes, MAX_SOURCE_BYTES)
+        && let Some(blocks) = latex_to_blocks(&files, &auto_dir)?
+    {
+        let content_list = write_blocks(&auto_dir, &stem, &blocks)?;
+        return Ok(Some(StructuredPaper { format: PaperFormat::Latex, content_list, sha256: sha256(&bytes) }));
+    }
+
+    if let Some(bytes) = fetch(&format!("https://ar5iv.labs.arxiv.org/html/{}", id), use_cache)?
+        && let Some(blocks) = html_to_blocks(&String::from_utf8_lossy(&bytes))
+    {
+        let content_list = write_blocks(&auto_dir, &stem, &blocks)?;
+        return Ok(Some(StructuredPaper { format: PaperFormat::Html, content_list, sha256: sha256(&bytes) }));
+    }
+
+    Ok(None)
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn write_blocks(auto_dir: &Path, stem: &str, blocks: &[Value]) -> Result<PathBuf> {
+    fs::create_dir_all(auto_dir)?;
+    let path = auto_dir.join(format!("{}_content_list.json", stem));
+    fs::write(&path, serde_json::to_string_pretty(blocks)?).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Largest download, and largest decompressed source archive, worth reading
+const MAX_SOURCE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// GET a URL through the shared papers cache, revalidating copies older than a day.
+/// Missing pages (and ar5iv redirecting back to arxiv.org when it has no rendering) are `None`.
+fn fetch(url: &str, use_cache: bool) -> Result<Option<Vec<u8>>> {
+    let entry = format!("{}.src", cache_key(&[b"arxiv-structured", url.as_bytes()]));
+    let cached = if use_cache { read_http_entry(CacheCategory::Papers, &entry) } else { None };
+    if let Some(cached) = cached.as_ref().filter(|c| c.fresh) {
+        return Ok(Some(cached.bytes.clone()));
+    }
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(120))
+        .user_agent(concat!("qernel/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let request = client.get(url);
+    let request = match &cached {
+        Some(cached) => cached.conditional(request),
+        None => request,
+    };
+    let response = match (request.send(), cached) {
+        (Ok(response), Some(cached)) if response.status() == reqwest::StatusCode::NOT_MODIFIED => {
+            return Ok(Some(cached.revalidated()));
+        }
+        (Ok(response), _) => response,
+        (Err(e), cached) => {
+            tracing::debug!("fetching {url} failed: {e}");
+            return Ok(cached.map(|c| c.bytes));
+        }
+    };
+    let final_host = response.url().host_str().unwrap_or("").to_string();
+    if !response.status().is_success() || (url.contains("ar5iv") && !final_host.contains("ar5iv")) {
+        return Ok(None);
+    }
+    let headers = response.headers().clone();
+    let mut bytes = Vec::new();
+    response.take(MAX_SOURCE_BYTES + 1).read_to_end(&mut bytes).context("Failed to read response body")?;
+    if bytes.len() as u64 > MAX_SOURCE_BYTES {
+        tracing::debug!("{url} is larger than {MAX_SOURCE_BYTES} bytes; not using it");
+        return Ok(None);
+    }
+    write_http_entry(CacheCategory::Papers, &entry, &bytes, &headers);
+    Ok(Some(bytes))
+}
+
+/// arXiv serves sources as a gzipped tarball, a single gzipped .tex, or (for
+/// PDF-only submissions) the PDF itself. Returns the files by relative path;
+/// `None` also for sources that decompress to more than `limit` bytes.
+fn unpack_source(bytes: &[u8], limit: u64) -> Option<BTreeMap<String, Vec<u8>>> {
+    if bytes.starts_with(b"%PDF") {
+        return None;
+    }
+    let mut raw = Vec::new();
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        flate2::read::GzDecoder::new(bytes).take(limit + 1).read_to_end(&mut raw).ok()?;
+        if raw.len() as u64 > limit {
+            return None;
+        }
+    } else {
+        raw = bytes.to_vec();
+    }
+    let mut files = BTreeMap::new();
+    if raw.len() > 262 && &raw[257..262] == b"ustar" {
+        let mut archive = tar::Archive::new(raw.as_slice());
+        for entry in archive.entries().ok()? {
+            let Ok(mut entry) = entry else { continue };
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let Ok(path) = entry.path().map(|p| p.to_string_lossy().trim_start_matches("./").to_string()) else {
+                continue;
+            };
+            let mut data = Vec::new();
+            if entry.read_to_end(&mut data).is_ok() {
+                files.insert(path, data);
+            }
+        }
+    } else if raw.starts_with(b"%PDF") {
+        return None;
+    } else {
+        files.insert("main.tex".to_string(), raw);
+    }
+    Some(files)
+}
+
+// ---------------------------------------------------------------------------
+// LaTeX
+
+const RASTER_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+fn latex_to_blocks(files: &BTreeMap<String, Vec<u8>>, auto_dir: &Path) -> Result<Option<Vec<Value>>> {
+    let tex: BTreeMap<&str, String> = files
+        .iter()
+        .filter(|(p, _)| p.ends_with(".tex"))
+        .map(|(p, b)| (p.as_str(), String::from_utf8_lossy(b).to_string()))
+        .collect();
+    // The main file is the one that starts a document; prefer conventional names
+    let Some((main_path, main)) = tex
+        .iter()
+        .filter(|(_, t)| t.contains("\\begin{document}"))
+        .max_by_key(|(p, t)| (p.ends_with("main.tex") || p.ends_with("ms.tex"), t.len()))
+    else {
+        return Ok(None);
+    };
+    let base = Path::new(main_path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+
+    let source = expand_inputs(&strip_comments(main), &tex, &base, 0);
+    let title = find_command_arg(&source, "\\title").map(|t| clean_inline(&t));
+    let Some(start) = source.find("\\begin{document}") else { return Ok(None) };
+    let mut body = &source[start + "\\begin{document}".len()..];
+    for end_marker in ["\\end{document}", "\\begin{thebibliography}", "\\bibliography{", "\\printbibliography"] {
+        if let Some(i) = body.find(end_marker) {
+            body = &body[..i];
+        }
+    }
+
+    let mut blocks = Vec::new();
+    if let Some(t) = title.filter(|t| !t.is_empty()) {
+        blocks.push(json!({"type": "text", "text": t, "text_level": 1}));
+    }
+    let mut converter = LatexConverter { files, base: &base, auto_dir, blocks, paragraph: String::new(), images: 0 };
+    converter.convert(body)?;
+    converter.flush_paragraph();
+    let blocks = converter.blocks;
+    // A source that yields almost nothing (e.g. a PDF-only wrapper) is not worth using
+    let text_len: usize = blocks.iter().filter_map(|b| b["text"].as_str()).map(str::len).sum();
+    if text_len < 500 {
+        return Ok(None);
+    }
+    Ok(Some(blocks))
+}
+
+/// Drop `%` comments (but not `\%`)
+fn strip_comments(tex: &str) -> String {
+    tex.lines()
+        .map(|line| {
+            let bytes = line.as_bytes();
+            let mut i = 0;
+            while i < bytes.len() {
+                if bytes[i] == b'\\' {
+                    i += 2;
+                    continue;
+                }
+                if bytes[i] == b'%' {
+                    return &line[..i];
+                }
+                i += 1;
+            }
+            line
+        })
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+static INPUT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\(?:input|include)\s*\{([^}]+)\}").expect("valid regex"));
+
+/// Inline `\input{..}` and `\include{..}` files from the archive
+fn expand_inputs(tex: &str, files: &BTreeMap<&str, String>, base: &str, depth: usize) -> String {
+    if depth > 8 {
+        return tex.to_string();
+    }
+    INPUT.replace_all(tex, |caps: &regex::Captures| {
+        let name = caps[1].trim();
+        let candidates = [name.to_string(), format!("{}.tex", name)];
+        candidates
+            .iter()
+            .flat_map(|c| [join_rel(base, c), c.clone()])
+            .find_map(|p| files.get(p.as_str()))
+            .map(|t| expand_inputs(&strip_comments(t), files, base, depth + 1))
+            .unwrap_or_default()
+    })
+    .to_string()
+}
+
+fn join_rel(base: &str, name: &str) -> String {
+    if base.is_empty() { name.to_string() } else { format!("{}/{}", base, name) }
+}
+
+/// Content of the balanced `{..}` group starting at `s[open]`, and the index after it
+fn brace_group(s: &str, open: usize) -> Option<(&str, usize)> {
+    if s.as_bytes().get(open) != Some(&b'{') {
+        return None;
+    }
+    let mut depth = 0usize;
+    let bytes = s.as_bytes();
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[open + 1..i], i + 1));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Skip whitespace and an optional `[..]` argument, returning the index of the next `{`
+fn skip_optional(s: &str, mut i: usize) -> usize {
+    let bytes = s.as_bytes();
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    if bytes.get(i) == Some(&b'[')
+        && let Some(close) = s[i..].find(']')
+    {
+        i += close + 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+    }
+    i
+}
+
+fn find_command_arg(s: &str, command: &str) -> Option<String> {
+    let at = s.find(&format!("{}{{", command)).or_else(|| s.find(&format!("{}[", command)))?;
+    let open = skip_optional(s, at + command.len());
+    brace_group(s, open).map(|(arg, _)| arg.to_string())
+}
+
+const DISPLAY_MATH_ENVS: &[&str] = &[
+    "equation", "equation*", "align", "align*", "gather", "gather*", "multline", "multline*", "eqnarray",
+    "eqnarray*", "displaymath", "flalign", "flalign*",
+];
+
+static EQUATION_LABEL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\label\{[^}]*\}|\\nonumber|\\notag").expect("valid regex"));
+
+struct LatexConverter<'a> {
+    files: &'a BTreeMap<String, Vec<u8>>,
+    base: &'a str,
+    auto_dir: &'a Path,
+    blocks: Vec<Value>,
+    paragraph: String,
+    images: usize,
+}
+
+impl LatexConverter<'_> {
+    fn convert(&mut self, body: &str) -> Result<()> {
+        let mut i = 0;
+        let bytes = body.as_bytes();
+        while i < body.len() {
+            let rest = &body[i..];
+            // Blank line ends a paragraph
+            if rest.starts_with("\n\n") || rest.starts_with("\n \n") {
+                self.flush_paragraph();
+                i += 2;
+                continue;
+            }
+            if rest.starts_with("\\[")
+                && let Some(end) = rest.find("\\]")
+            {
+                self.push_equation(&rest[2..end], "");
+                i += end + 2;
+                continue;
+            }
+            if let Some(display) = rest.strip_prefix("$$")
+                && let Some(end) = display.find("$$")
+            {
+                self.push_equation(&display[..end], "");
+                i += end + 4;
+                continue;
+            }
+            if let Some((level, name_len)) = section_command(rest) {
+                let open = skip_optional(body, i + name_len);
+                if let Some((title, after)) = brace_group(body, open) {
+                    self.flush_paragraph();
+                    self.blocks.push(json!({"type": "text", "text": clean_inline(title), "text_level": level}));
+                    i = after;
+                    continue;
+                }
+            }
+            if rest.starts_with("\\begin{")
+                && let Some((env, _)) = brace_group(body, i + "\\begin".len())
+            {
+                let env = env.to_string();
+                let end_tag = format!("\\end{{{}}}", env);
+                let content_start = i + "\\begin{".len() + env.len() + 1;
+                if let Some(end) = find_env_end(&body[content_start..], &env) {
+                    let content = &body[content_start..content_start + end];
+                    self.environment(&env, content)?;
+                    i = content_start + end + end_tag.len();
+                    continue;
+                }
+            }
+            // Inline math is copied verbatim so cleanup never touches it
+            if bytes[i] == b'$'
+                && let Some(end) = rest[1..].find('$')
+            {
+                self.paragraph.push_str(&rest[..end + 2]);
+                i += end + 2;
+                continue;
+            }
+            if rest.starts_with("\\(")
+                && let Some(end) = rest.find("\\)")
+            {
+                self.paragraph.push('$');
+                self.paragraph.push_str(rest[2..end].trim());
+                self.paragraph.push('$');
+                i += end + 2;
+                continue;
+            }
+            let ch = rest.chars().next().unwrap_or(' ');
+            self.paragraph.push(ch);
+            i += ch.len_utf8();
+        }
+        Ok(())
+    }
+
+    fn environment(&mut self, env: &str, content: &str) -> Result<()> {
+        let name = env.trim_end_matches('*');
+        if DISPLAY_MATH_ENVS.contains(&env) {
+            let wrapper = match name {
+                "align" | "eqnarray" | "flalign" => "aligned",
+                "gather" => "gathered",
+                _ => "",
+            };
+            self.push_equation(content, wrapper);
+            return Ok(());
+        }
+        match name {
+            "abstract" => {
+                self.flush_paragraph();
+                self.blocks.push(json!({"type": "text", "text": "Abstract", "text_level": 1}));
+                self.convert(content)?;
+                self.flush_paragraph();
+            }
+            "figure" | "wrapfigure" => {
+                self.flush_paragraph();
+                let caption = find_command_arg(content, "\\caption").map(|c| clean_inline(&c));
+                let img_path = find_command_arg(content, "\\includegraphics").and_then(|g| self.copy_image(g.trim()));
+                let mut block = json!({"type": "image"});
+                if let Some(c) = caption {
+                    block["img_caption"] = json!([c]);
+                }
+                if let Some(p) = img_path {
+                    block["img_path"] = json!(p);
+                }
+                self.blocks.push(block);
+            }
+            "table" => {
+                self.flush_paragraph();
+                if let Some(c) = find_command_arg(content, "\\caption") {
+                    self.blocks.push(json!({"type": "table", "table_caption": [clean_inline(&c)]}));
+                }
+            }
+            "itemize" | "enumerate" | "description" => {
+                self.flush_paragraph();
+                let items: Vec<String> = split_items(content)
+                    .into_iter()
+                    .map(|item| clean_inline(skip_item_label(item)))
+                    .filter(|item| !item.is_empty())
+                    .collect();
+                if !items.is_empty() {
+                    self.blocks.push(json!({"type": "list", "list_items": items}));
+                }
+            }
+            "algorithm" | "algorithmic" | "lstlisting" | "verbatim" | "minted" => {
+                self.flush_paragraph();
+                let caption = find_command_arg(content, "\\caption").map(|c| vec![clean_inline(&c)]);
+                let mut block = json!({"type": "code", "code_body": content.trim()});
+                if let Some(c) = caption {
+                    block["code_caption"] = json!(c);
+                }
+                self.blocks.push(block);
+            }
+            "thebibliography" | "comment" | "tikzpicture" => {}
+            // Theorems, proofs, center, minipage, ...: keep their text
+            _ => self.convert(content)?,
+        }
+        Ok(())
+    }
+
+    fn push_equation(&mut self, content: &str, wrapper: &str) {
+        self.flush_paragraph();
+        let math = EQUATION_LABEL.replace_all(content, "");
+        let math = math.trim();
+        if math.is_empty() {
+            return;
+        }
+        let text = if wrapper.is_empty() {
+            format!("$$\n{}\n$$", math)
+        } else {
+            format!("$$\n\\begin{{{w}}}\n{}\n\\end{{{w}}}\n$$", math, w = wrapper)
+        };
+        self.blocks.push(json!({"type": "equation", "text": text}));
+    }
+
+    fn flush_paragraph(&mut self) {
+        let text = clean_inline(&self.paragraph);
+        self.paragraph.clear();
+        if !text.is_empty() {
+            self.blocks.push(json!({"type": "text", "text": text}));
+        }
+    }
+
+    /// Copy a raster figure from the source into `auto/images`, returning its relative path
+    fn copy_image(&mut self, name: &str) -> Option<String> {
+        let mut candidates = vec![name.to_string()];
+        for ext in RASTER_EXTENSIONS {
+            candidates.push(format!("{}.{}", name, ext));
+        }
+        let (found, data) = candidates
+            .iter()
+            .flat_map(|c| [join_rel(self.base, c), c.clone()])
+            .find_map(|p| self.files.get(&p).map(|d| (p, d)))?;
+        let ext = Path::new(&found).extension()?.to_str()?.to_lowercase();
+        if !RASTER_EXTENSIONS.contains(&ext.as_str()) {
+            return None;
+        }
+        self.images += 1;
+        let file = format!("figure_{:03}.{}", self.images, ext);
+        let images_dir = self.auto_dir.join("images");
+        fs::create_dir_all(&images_dir).ok()?;
+        fs::write(images_dir.join(&file), data).ok()?;
+        Some(format!("images/{}", file))
+    }
+}
+
+/// Offset of the `\end{env}` matching an already opened `\begin{env}`, allowing nesting
+fn find_env_end(s: &str, env: &str) -> Option<usize> {
+    let begin = format!("\\begin{{{}}}", env);
+    let end = format!("\\end{{{}}}", env);
+    let mut depth = 1usize;
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with(&begin) {
+            depth += 1;
+            i += begin.len();
+        } else if rest.starts_with(&end) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+            i += end.len();
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// `\section{`-like commands: (text_level, length of the command including `*`)
+fn section_command(s: &str) -> Option<(u64, usize)> {
+    for (cmd, level) in [("\\section", 1), ("\\subsection", 2), ("\\subsubsection", 3), ("\\paragraph", 4)] {
+        if let Some(rest) = s.strip_prefix(cmd) {
+            let star = rest.starts_with('*') as usize;
+            let next = rest[star..].trim_start().chars().next();
+            if matches!(next, Some('{') | Some('[')) {
+                return Some((level, cmd.len() + star));
+            }
+        }
+    }
+    None
+}
+
+/// Split a list body at its own `\item`s; nested lists stay inside their item
+fn split_items(content: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start: Option<usize> = None;
+    let mut i = 0;
+    while i < content.len() {
+        let rest = &content[i..];
+        if rest.starts_with("\\begin{") {
+            depth += 1;
+        } else if rest.starts_with("\\end{") {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && rest.starts_with("\\item") && !rest[5..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            if let Some(s) = start {
+                items.push(&content[s..i]);
+            }
+            start = Some(i + 5);
+            i += 5;
+            continue;
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    if let Some(s) = start {
+        items.push(&content[s..]);
+    }
+    items
+}
+
+fn skip_item_label(item: &str) -> &str {
+    let trimmed = item.trim_start();
+    if trimmed.starts_with('[')
+        && let Some(close) = trimmed.find(']')
+    {
+        return &trimmed[close + 1..];
+    }
+    trimmed
+}
+
+/// Turn inline LaTeX markup into Markdown, leaving `$..$` math untouched
+fn clean_inline(tex: &str) -> String {
+    // Escaped dollars are text, not math delimiters
+    let tex = tex.replace("\\$", "\u{3}");
+    let mut out = String::new();
+    let mut rest = tex.as_str();
+    while let Some(start) = rest.find('$') {
+        out.push_str(&clean_text(&rest[..start]));
+        let after = &rest[start + 1..];
+        match after.find('$') {
+            Some(end) => {
+                out.push('$');
+                out.push_str(after[..end].trim());
+                out.push('$');
+                rest = &after[end + 1..];
+            }
+            None => {
+                rest = after;
+            }
+        }
+    }
+    out.push_str(&clean_text(rest));
+    out.replace('\u{3}', "\\$").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Inline markup rewritten to Markdown, in order
+static CLEANUP: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+    [
+        (r"\\(?:textbf|mathbf)\{([^{}]*)\}", "**$1**"),
+        (r"\\(?:emph|textit)\{([^{}]*)\}", "*$1*"),
+        (r"\\texttt\{([^{}]*)\}", "`$1`"),
+        (r"\\href\{([^{}]*)\}\{([^{}]*)\}", "[$2]($1)"),
+        (r"\\url\{([^{}]*)\}", "$1"),
+        (r"\\footnote\{([^{}]*)\}", " ($1)"),
+        (r"\\(?:cite[pt]?|citep|citet|citealp)\*?(?:\[[^\]]*\])*\{([^{}]*)\}", "[$1]"),
+        (r"\\(?:eq)?ref\{([^{}]*)\}", "($1)"),
+        (r"\\(?:label|vspace|hspace|vskip)\*?\{[^{}]*\}", ""),
+        // Nested lists are flattened into their parent item
+        (r"\\(?:begin|end)\{(?:itemize|enumerate|description)\}", ""),
+        (r"\\item\b", ";"),
+        (r"\\(?:noindent|centering|maketitle|medskip|smallskip|bigskip|newpage|clearpage|hfill|appendix|small|footnotesize|large|Large)\b", ""),
+        (r"\\\\", " "),
+        (r"~", " "),
+        (r"``|''", "\""),
+        (r"\\([%&_#])", "$1"),
+        // Anything left: drop the command and keep its argument text
+        (r"\\[a-zA-Z]+\*?", ""),
+        (r"[{}]", ""),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid regex"), replacement))
+    .collect()
+});
+
+fn clean_text(tex: &str) -> String {
+    // Escaped braces survive the brace stripping below
+    let mut s = tex.replace("\\{", "\u{1}").replace("\\}", "\u{2}");
+    for (re, replacement) in CLEANUP.iter() {
+        s = re.replace_all(&s, *replacement).to_string();
+    }
+    s.replace('\u{1}', "{").replace('\u{2}', "}")
+}
+
+// ---------------------------------------------------------------------------
+// ar5iv HTML
+
+fn html_to_blocks(html: &str) -> Option<Vec<Value>> {
+    use scraper::{Html, Selector};
+
+    let doc = Html::parse_document(html);
+    let root_selector = Selector::parse("article.ltx_document, div.ltx_page_content").ok()?;
+    let root = doc.select(&root_selector).next()?;
+    let mut blocks = Vec::new();
+    walk_html(root, &mut blocks);
+    let text_len: usize = blocks.iter().filter_map(|b| b["text"].as_str()).map(str::len).sum();
+    if text_len < 500 {
+        return None;
+    }
+    Some(blocks)
+}
+
+fn has_class(el: &scraper::ElementRef, class: &str) -> bool {
+    el.value().classes().any(|c| c == class)
+}
+
+fn walk_html(el: scraper::ElementRef, blocks: &mut Vec<Value>) {
+    for child in el.children() {
+        let Some(child) = scraper::ElementRef::wrap(child) else { continue };
+        let tag = child.value().name();
+        if has_class(&child, "ltx_bibliography") || matches!(tag, "nav" | "header" | "footer" | "script" | "style") {
+            continue;
+        }
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = match tag {
+                    "h1" | "h2" => 1,
+                    "h3" => 2,
+                    _ => 3,
+                };
+                let text = inline_text(child);
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text, "text_level": level}));
+                }
+            }
+            "p" => {
+                let text = inline_text(child);
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+            }
+            "table" if has_class(&child, "ltx_equation") || has_class(&child, "ltx_equationgroup") => {
+                let math: Vec<String> = child
+                    .descendants()
+                    .filter_map(scraper::ElementRef::wrap)
+                    .filter(|e| e.value().name() == "math")
+                    .filter_map(|e| e.value().attr("alttext").map(|a| a.trim().to_string()))
+                    .filter(|a| !a.is_empty())
+                    .collect();
+                if !math.is_empty() {
+                    blocks.push(json!({"type": "equation", "text": format!("$$\n{}\n$$", math.join(" \\\\\n"))}));
+                }
+            }
+            "figure" => {
+                let caption = child
+                    .children()
+                    .filter_map(scraper::ElementRef::wrap)
+                    .find(|e| e.value().name() == "figcaption")
+                    .map(inline_text)
+                    .unwrap_or_default();
+                if has_class(&child, "ltx_table") {
+                    let body = child
+                        .descendants()
+                        .filter_map(scraper::ElementRef::wrap)
+                        .find(|e| e.value().name() == "table")
+                        .map(|t| t.html());
+                    let mut block = json!({"type": "table", "table_caption": [caption]});
+                    if let Some(body) = body {
+                        block["table_body"] = json!(body);
+                    }
+                    blocks.push(block);
+                } else if !caption.is_empty() {
+                    blocks.push(json!({"type": "image", "img_caption": [caption]}));
+                }
+            }
+            "ul" | "ol" => {
+                let items: Vec<String> = child
+                    .children()
+                    .filter_map(scraper::ElementRef::wrap)
+                    .filter(|e| e.value().name() == "li")
+                    .map(inline_text)
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                if !items.is_empty() {
+                    blocks.push(json!({"type": "list", "list_items": items}));
+                }
+            }
+            _ => walk_html(child, blocks),
+        }
+    }
+}
+
+/// Text of an element with `<math>` replaced by its LaTeX `alttext`
+fn inline_text(el: scraper::ElementRef) -> String {
+    fn collect(el: scraper::ElementRef, out: &mut String) {
+        for child in el.children() {
+            if let Some(text) = child.value().as_text() {
+                out.push_str(text);
+            } else if let Some(e) = scraper::ElementRef::wrap(child) {
+                if e.value().name() == "math" {
+                    let alt = e.value().attr("alttext").unwrap_or("").trim();
+                    if e.value().attr("display") == Some("block") {
+                        out.push_str(&format!(" $${}$$ ", alt));
+                    } else {
+                        out.push_str(&format!("${}$", alt));
+                    }
+                } else if !has_class(&e, "ltx_note_outer") && !has_class(&e, "ltx_tag") {
+                    collect(e, out);
+                }
+            }
+        }
+    }
+    let mut out = String::new();
+    collect(el, &mut out);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        gzip(&builder.into_inner().unwrap())
+    }
+
+    fn paragraph() -> String {
+        "Quantum error correction protects logical qubits from noise. ".repeat(12)
+    }
+
+    #[test]
+    fn ids_come_from_urls_and_prefixes() {
+        assert_eq!(arxiv_id("https://arxiv.org/abs/2401.01234v2").as_deref(), Some("2401.01234v2"));
+        assert_eq!(arxiv_id("https://arxiv.org/pdf/2401.01234.pdf?download=1").as_deref(), Some("2401.01234"));
+        assert_eq!(arxiv_id("arxiv:quant-ph/0101001").as_deref(), Some("quant-ph/0101001"));
+        assert_eq!(arxiv_id("https://arxiv.org/list/quant-ph/new"), None);
+        assert_eq!(arxiv_id("https://example.com/paper.pdf"), None);
+    }
+
+    #[test]
+    fn sources_unpack_from_tarballs_single_files_and_not_pdfs() {
+        let files = unpack_source(&tarball(&[("./main.tex", b"\\begin{document}"), ("fig.png", b"png")]), MAX_SOURCE_BYTES).unwrap();
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["fig.png", "main.tex"]);
+
+        let single = unpack_source(&gzip(b"\\documentclass{article}"), MAX_SOURCE_BYTES).unwrap();
+        assert_eq!(single["main.tex"], b"\\documentclass{article}");
+
+        assert!(unpack_source(b"%PDF-1.5", MAX_SOURCE_BYTES).is_none());
+        assert!(unpack_source(&gzip(b"%PDF-1.5"), MAX_SOURCE_BYTES).is_none());
+    }
+
+    #[test]
+    fn oversized_archives_are_not_unpacked() {
+        let source = gzip(&[b' '; 4096]);
+        assert!(unpack_source(&source, 4096).is_some());
+        assert!(unpack_source(&source, 4095).is_none());
+    }
+
+    #[test]
+    fn latex_becomes_content_blocks() {
+        let main = format!(
+            "\\documentclass{{article}}\n\\title{{Surface \\emph{{codes}}}}\n\\begin{{document}}\n\\input{{intro}}\n\\section{{Method}}\n{p} % a comment\n\\begin{{equation}}\\label{{eq:h}}H = \\sum_i Z_i\\end{{equation}}\n\\begin{{figure}}\\includegraphics{{plot}}\\caption{{Threshold}}\\end{{figure}}\n\\begin{{itemize}}\\item One \\item Two\\end{{itemize}}\n\\begin{{thebibliography}}\\end{{thebibliography}}\n\\end{{document}}",
+            p = paragraph()
+        );
+        let files = BTreeMap::from([
+            ("main.tex".to_string(), main.into_bytes()),
+            ("intro.tex".to_string(), b"\\section*{Introduction} We use $d=3$ and \\textbf{bold} text.".to_vec()),
+            ("plot.png".to_string(), b"png".to_vec()),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let blocks = latex_to_blocks(&files, dir.path()).unwrap().unwrap();
+
+        assert_eq!(blocks[0], json!({"type": "text", "text": "Surface *codes*", "text_level": 1}));
+        assert_eq!(blocks[1], json!({"type": "text", "text": "Introduction", "text_level": 1}));
+        assert_eq!(blocks[2]["text"], "We use $d=3$ and **bold** text.");
+        assert_eq!(blocks[3]["text"], "Method");
+        assert!(!blocks[4]["text"].as_str().unwrap().contains("comment"));
+        assert_eq!(blocks[5], json!({"type": "equation", "text": "$$\nH = \\sum_i Z_i\n$$"}));
+        assert_eq!(blocks[6], json!({"type": "image", "img_caption": ["Threshold"], "img_path": "images/figure_001.png"}));
+        assert!(dir.path().join("images").join("figure_001.png").is_file());
+        assert_eq!(blocks[7], json!({"type": "list", "list_items": ["One", "Two"]}));
+        assert_eq!(blocks.len(), 8);
+    }
+
+    #[test]
+    fn sources_with_little_text_fall_back() {
+        let files = BTreeMap::from([("main.tex".to_string(), b"\\begin{document}\\includegraphics{paper.pdf}\\end{document}".to_vec())]);
+        let dir = tempfile::tempdir().unwrap();
+        assert!(latex_to_blocks(&files, dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn inline_markup_is_cleaned_but_math_is_kept() {
+        assert_eq!(clean_inline("See~\\cite[Thm.~1]{knill} and \\ref{fig:a}."), "See [knill] and (fig:a).");
+        assert_eq!(clean_inline("Costs \\$5 for $\\{x_i\\}$ \\{set\\}"), "Costs \\$5 for $\\{x_i\\}$ {set}");
+        assert_eq!(clean_inline("\\href{https://x.org}{site}, ``quoted''"), "[site](https://x.org), \"quoted\"");
+        assert_eq!(strip_comments("a % b\n100\\% sure"), "a\n100\\% sure");
+    }
+
+    #[test]
+    fn ar5iv_html_becomes_content_blocks() {
+        let html = format!(
+            r#"<html><body><nav>menu</nav><article class="ltx_document">
+            <h2>Results</h2>
+            <p>{p} with <math alttext="\alpha" display="inline"></math></p>
+            <table class="ltx_equation"><tr><td><math alttext="E = mc^2"></math></td></tr></table>
+            <figure class="ltx_figure"><img src="x.png"><figcaption>Noise model</figcaption></figure>
+            <ul><li>first</li><li>second</li></ul>
+            <section class="ltx_bibliography"><p>References</p></section>
+            </article></body></html>"#,
+            p = paragraph()
+        );
+        let blocks = html_to_blocks(&html).unwrap();
+        assert_eq!(blocks[0], json!({"type": "text", "text": "Results", "text_level": 1}));
+        assert!(blocks[1]["text"].as_str().unwrap().ends_with("with $\\alpha$"));
+        assert_eq!(blocks[2], json!({"type": "equation", "text": "$$\nE = mc^2\n$$"}));
+        assert_eq!(blocks[3], json!({"type": "image", "img_caption": ["Noise model"]}));
+        assert_eq!(blocks[4], json!({"type": "list", "list_items": ["first", "second"]}));
+        assert_eq!(blocks.len(), 5);
+        assert!(html_to_blocks("<html><body><p>No article</p></body></html>").is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! `.qernel/audit.jsonl`: an append-only record of everything the agent did, kept for every
+//! run (unlike the `--debug` run log). Entries are hash-chained: each one carries the hash of
+//! the one before it, so an entry edited, removed or reordered in the middle breaks the chain.
+//! The chain is not keyed, so it cannot show that a log was cut short or rewritten and
+//! rehashed as a whole. For that, the last entry written is also recorded outside the project
+//! (the "head", in the user config directory), and the log must still end there.
+//! Large payloads (requests, patches, output) are stored as SHA-256 digests.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// `prev` of the first entry
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub ts: String,
+    pub kind: String,
+    pub data: serde_json::Value,
+    /// `hash` of the previous entry
+    pub prev: String,
+    /// SHA-256 over `seq`, `ts`, `kind`, `data` and `prev`
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// The hash this entry should have
+    pub fn compute_hash(&self) -> String {
+        let body = serde_json::json!([self.seq, self.ts, self.kind, self.data, self.prev]);
+        sha256_hex(body.to_string().as_bytes())
+    }
+}
+
+/// The last entry written to a project's log, kept where the project's files cannot change it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Head {
+    pub seq: u64,
+    pub hash: String,
+}
+
+/// An open audit log, appending to `path` and keeping its head in `head_path`
+pub struct AuditLog {
+    file: File,
+    seq: u64,
+    prev: String,
+    head_path: Option<PathBuf>,
+    /// Why the log was started over when it was opened
+    restarted: Option<String>,
+}
+
+impl AuditLog {
+    /// Continue the chain in `path`. A log that cannot be continued (unreadable, broken, or
+    /// not ending at the head recorded in `head_path`) is moved aside and a new one is started
+    /// with a `log_restarted` entry saying why, so auditing never stops over it.
+    pub fn open(path: &Path, head_path: Option<PathBuf>) -> Result<Self> {
+        let recorded = head_path.as_deref().and_then(read_head);
+        let checked = read_entries(path).and_then(|entries| {
+            verify(&entries).and_then(|()| recorded.as_ref().map_or(Ok(()), |head| check_head(&entries, head))).map_err(anyhow::Error::msg)?;
+            Ok(entries)
+        });
+        let (seq, prev, restarted) = match checked {
+            Ok(entries) => match entries.last() {
+                Some(last) => (last.seq + 1, last.hash.clone(), None),
+                None => (0, GENESIS.to_string(), None),
+            },
+            Err(problem) => {
+                let previous_log = set_aside(path)?;
+                let reason = match &previous_log {
+                    Some(name) => format!("{:#}; the old log was moved to {}", problem, name),
+                    None => format!("{:#}", problem),
+                };
+                let data = serde_json::json!({
+                    "reason": format!("{:#}", problem),
+                    "previous_log": previous_log,
+                    "previous_head": recorded,
+                });
+                (0, GENESIS.to_string(), Some((reason, data)))
+            }
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let mut log = AuditLog { file, seq, prev, head_path, restarted: None };
+        if let Some((reason, data)) = restarted {
+            log.append("log_restarted", data);
+            log.restarted = Some(reason);
+        }
+        Ok(log)
+    }
+
+    /// Append an entry and move the head to it; write failures are ignored
+    pub fn append(&mut self, kind: &str, data: serde_json::Value) {
+        let mut entry = AuditEntry {
+            seq: self.seq,
+            ts: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            kind: kind.to_string(),
+            data,
+            prev: self.prev.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        let Ok(mut line) = serde_json::to_string(&entry) else { return };
+        line.push('\n');
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            if let Some(head_path) = &self.head_path {
+                let _ = write_head(head_path, &Head { seq: entry.seq, hash: entry.hash.clone() });
+            }
+            self.seq += 1;
+            self.prev = entry.hash;
+        }
+    }
+}
+
+static AUDIT: Mutex<Option<AuditLog>> = Mutex::new(None);
+
+pub fn audit_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("audit.jsonl")
+}
+
+/// Where the head of `cwd`'s audit log is kept: `audit/<digest of the project path>.json`
+/// next to the user config file. `None` when there is no user config directory.
+pub fn head_path(cwd: &Path) -> Option<PathBuf> {
+    let project = std::fs::canonicalize(cwd).unwrap_or_else(|_| cwd.to_path_buf());
+    let digest = sha256_hex(project.to_string_lossy().as_bytes());
+    let config = crate::util::config_file_path().ok()?;
+    Some(config.parent()?.join("audit").join(format!("{}.json", &digest[..16])))
+}
+
+/// The recorded head, if any
+pub fn read_head(path: &Path) -> Option<Head> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// Replace the recorded head (write then rename, so a crash never leaves half a file)
+fn write_head(path: &Path, head: &Head) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(head)?).with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Move a log that cannot be continued to `audit-<time>.broken.jsonl` beside it; the new
+/// name, or `None` when there was no log
+fn set_aside(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let name = format!("audit-{}.broken.jsonl", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+    let aside = path.with_file_name(&name);
+    std::fs::rename(path, &aside).with_context(|| format!("failed to move {} aside", path.display()))?;
+    Ok(Some(name))
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Start recording to the project's audit log, continuing the chain of earlier runs.
+/// Returns why the log was started over, if it was.
+pub fn open(cwd: &Path) -> Result<Option<String>> {
+    let mut log = AuditLog::open(&audit_path(cwd), head_path(cwd))?;
+    let restarted = log.restarted.take();
+    *AUDIT.lock().unwrap_or_else(|e| e.into_inner()) = Some(log);
+    Ok(restarted)
+}
+
+/// Stop recording; later entries are dropped until the next `open`
+pub fn close() {
+    *AUDIT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Append an entry; does nothing before `open`, and write failures never interrupt a run
+pub fn record(kind: &str, data: serde_json::Value) {
+    if let Some(log) = AUDIT.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        log.append(kind, data);
+    }
+}
+
+/// A model request and its response, as digests; canned mock steps have no request
+pub fn record_model_request(model: &str, request: Option<&serde_json::Value>, response: &serde_json::Value) {
+    record(
+        "model_request",
+        serde_json::json!({
+            "model": model,
+            "request_sha256": request.map(|r| sha256_hex(r.to_string().as_bytes())),
+            "response_sha256": sha256_hex(response.to_string().as_bytes()),
+        }),
+    );
+}
+
+/// Every entry in `path`, oldest first; a missing file has none
+pub fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("{} line {} is not an audit entry", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// The first break in the chain: a wrong hash, a `prev` that does not match, or a gap in `seq`
+pub fn verify(entries: &[AuditEntry]) -> std::result::Result<(), String> {
+    let mut prev = GENESIS.to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.seq != i as u64 {
+            return Err(format!("entry {} has seq {}; entries were removed or reordered", i, entry.seq));
+        }
+        if entry.prev != prev {
+            return Err(format!("entry {} does not follow entry {}; entries were removed or reordered", entry.seq, i as i64 - 1));
+        }
+        if entry.compute_hash() != entry.hash {
+            return Err(format!("entry {} ({}) was modified after it was written", entry.seq, entry.kind));
+        }
+        prev = entry.hash.clone();
+    }
+    Ok(())
+}
+
+/// Whether a log whose chain [`verify`] accepted still ends at the recorded head
+pub fn check_head(entries: &[AuditEntry], head: &Head) -> std::result::Result<(), String> {
+    match entries.get(head.seq as usize) {
+        None => Err(format!("the log ends before entry {}, the last one written; entries were removed", head.seq)),
+        Some(entry) if entry.hash != head.hash => {
+            Err(format!("entry {} is not the one written; the log was rewritten", head.seq))
+        }
+        Some(_) if entries.len() as u64 > head.seq + 1 => {
+            Err(format!("entries after {} were added outside qernel", head.seq))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_in(dir: &Path) -> AuditLog {
+        AuditLog::open(&dir.join("audit.jsonl"), Some(dir.join("head.json"))).unwrap()
+    }
+
+    fn write_lines(path: &Path, entries: &[AuditEntry]) {
+        let text: String = entries.iter().map(|e| serde_json::to_string(e).unwrap() + "\n").collect();
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn runs_continue_the_chain_and_move_the_head() {
+        let dir = tempfile::tempdir().unwrap();
+        log_in(dir.path()).append("run_started", serde_json::json!({"n": 1}));
+        log_in(dir.path()).append("run_started", serde_json::json!({"n": 2}));
+        let entries = read_entries(&dir.path().join("audit.jsonl")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(verify(&entries), Ok(()));
+        let head = read_head(&dir.path().join("head.json")).unwrap();
+        assert_eq!(head, Head { seq: 1, hash: entries[1].hash.clone() });
+        assert_eq!(check_head(&entries, &head), Ok(()));
+    }
+
+    #[test]
+    fn edits_and_removals_break_the_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = log_in(dir.path());
+        for n in 0..3 {
+            log.append("shell", serde_json::json!({"n": n}));
+        }
+        let entries = read_entries(&dir.path().join("audit.jsonl")).unwrap();
+        let mut edited = entries.clone();
+        edited[1].data = serde_json::json!({"n": 9});
+        assert!(verify(&edited).unwrap_err().contains("modified"));
+        let removed = [entries[0].clone(), entries[2].clone()];
+        assert!(verify(&removed).is_err());
+    }
+
+    #[test]
+    fn truncated_or_rehashed_logs_do_not_end_at_the_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = log_in(dir.path());
+        for n in 0..3 {
+            log.append("shell", serde_json::json!({"n": n}));
+        }
+        let entries = read_entries(&dir.path().join("audit.jsonl")).unwrap();
+        let head = read_head(&dir.path().join("head.json")).unwrap();
+
+        // Cutting entries off the end leaves a valid chain
+        assert_eq!(verify(&entries[..2]), Ok(()));
+        assert!(check_head(&entries[..2], &head).unwrap_err().contains("removed"));
+
+        // So does rewriting an entry and rehashing everything after it
+        let mut rewritten = entries.clone();
+        rewritten[1].data = serde_json::json!({"n": 9});
+        for i in 1..rewritten.len() {
+            rewritten[i].prev = rewritten[i - 1].hash.clone();
+            rewritten[i].hash = rewritten[i].compute_hash();
+        }
+        assert_eq!(verify(&rewritten), Ok(()));
+        assert!(check_head(&rewritten, &head).unwrap_err().contains("rewritten"));
+    }
+
+    #[test]
+    fn a_log_that_cannot_be_continued_is_set_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut log = log_in(dir.path());
+        log.append("shell", serde_json::json!({}));
+        log.append("shell", serde_json::json!({}));
+        drop(log);
+        let entries = read_entries(&path).unwrap();
+        write_lines(&path, &entries[..1]);
+
+        log_in(dir.path()).append("run_started", serde_json::json!({}));
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(verify(&entries), Ok(()));
+        assert_eq!(entries[0].kind, "log_restarted");
+        assert!(entries[0].data["reason"].as_str().unwrap().contains("removed"));
+        let aside = entries[0].data["previous_log"].as_str().unwrap();
+        assert_eq!(read_entries(&dir.path().join(aside)).unwrap().len(), 1);
+        assert_eq!(entries[1].kind, "run_started");
+    }
+
+    #[test]
+    fn a_corrupt_line_does_not_stop_auditing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        std::fs::write(&path, "not json\n").unwrap();
+        log_in(dir.path()).append("run_started", serde_json::json!({}));
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].data["reason"].as_str().unwrap().contains("line 1 is not an audit entry"));
+        assert_eq!(verify(&entries), Ok(()));
+    }
+}
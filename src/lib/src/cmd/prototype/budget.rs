@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+use crate::cmd::common::usage::Usage;
+
+/// `agent.max_wall_clock_minutes` and `agent.max_cost_usd`, checked after every iteration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Budget {
+    pub wall_clock: Option<Duration>,
+    pub max_cost_usd: Option<f64>,
+}
+
+impl Budget {
+    pub fn new(max_wall_clock_minutes: Option<u32>, max_cost_usd: Option<f64>) -> Self {
+        Budget {
+            wall_clock: max_wall_clock_minutes.map(|m| Duration::from_secs(u64::from(m) * 60)),
+            max_cost_usd,
+        }
+    }
+
+    /// Why the run must stop, if a limit has been reached
+    pub fn exceeded(&self, started: Instant, usage: &Usage) -> Option<String> {
+        let elapsed = started.elapsed();
+        if let Some(limit) = self.wall_clock
+            && elapsed >= limit
+        {
+            return Some(format!(
+                "ran for {} of the {} allowed by agent.max_wall_clock_minutes",
+                format_elapsed(elapsed),
+                format_elapsed(limit)
+            ));
+        }
+        if let Some(limit) = self.max_cost_usd
+            && usage.cost_usd >= limit
+        {
+            return Some(format!("estimated cost ${:.2} reached agent.max_cost_usd (${:.2})", usage.cost_usd, limit));
+        }
+        None
+    }
+}
+
+/// e.g. `1h 05m`, `12m 30s`
+pub fn format_elapsed(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,106 @@
+//! `.qernel/artifacts/metrics.svg`: each benchmark metric and the test pass counts plotted
+//! across the iterations of a run, redrawn after every test run.
+
+use anyhow::Result;
+use plotters::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::cmd::prototype::artifacts::artifacts_dir;
+use crate::cmd::prototype::metrics::IterationMetrics;
+use crate::config::MetricConfig;
+
+const WIDTH: u32 = 800;
+const PANEL_HEIGHT: u32 = 240;
+
+pub fn metrics_chart_path(cwd: &Path) -> PathBuf {
+    artifacts_dir(cwd).join("metrics.svg")
+}
+
+/// One panel per metric (with its target as a dashed line) and one for the tests passed and
+/// failed, when the output had a pytest or unittest summary. Returns `None` when there is
+/// nothing to plot yet.
+pub fn write_metrics_chart(cwd: &Path, metrics: &[MetricConfig], history: &[IterationMetrics]) -> Result<Option<PathBuf>> {
+    let has_counts = history.iter().any(|h| h.tests.is_some());
+    let panels = metrics.len() + usize::from(has_counts);
+    if history.is_empty() || panels == 0 {
+        return Ok(None);
+    }
+    let path = metrics_chart_path(cwd);
+    std::fs::create_dir_all(artifacts_dir(cwd))?;
+    let root = SVGBackend::new(&path, (WIDTH, PANEL_HEIGHT * panels as u32)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let areas = root.split_evenly((panels, 1));
+
+    let first = history.first().map_or(1, |h| h.iteration) as f64;
+    let last = history.last().map_or(1, |h| h.iteration) as f64;
+    let x_range = (first - 0.5)..(last + 0.5);
+    let x_labels = history.len().min(10);
+
+    for (metric, area) in metrics.iter().zip(&areas) {
+        let points: Vec<(f64, f64)> = history
+            .iter()
+            .filter_map(|h| h.values.get(&metric.name).copied().flatten().map(|v| (h.iteration as f64, v)))
+            .collect();
+        let y_range = value_range(points.iter().map(|p| p.1).chain(metric.target));
+        let mut chart = ChartBuilder::on(area)
+            .caption(&metric.name, ("sans-serif", 18))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_range.clone(), y_range)?;
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(x_labels)
+            .x_label_formatter(&|x| format!("{}", x.round()))
+            .x_desc("iteration")
+            .draw()?;
+        if let Some(target) = metric.target {
+            let dashed = DashedLineSeries::new([(x_range.start, target), (x_range.end, target)], 6, 4, RED.stroke_width(1));
+            chart.draw_series(dashed)?;
+        }
+        chart.draw_series(LineSeries::new(points.iter().copied(), BLUE.stroke_width(2)))?;
+        chart.draw_series(points.iter().map(|&p| Circle::new(p, 3, BLUE.filled())))?;
+    }
+
+    if has_counts {
+        let area = &areas[panels - 1];
+        let passed: Vec<(f64, f64)> = history.iter().filter_map(|h| h.tests.map(|t| (h.iteration as f64, t.passed as f64))).collect();
+        let failed: Vec<(f64, f64)> = history.iter().filter_map(|h| h.tests.map(|t| (h.iteration as f64, t.failed as f64))).collect();
+        let top = passed.iter().chain(&failed).map(|p| p.1).fold(1.0, f64::max);
+        let mut chart = ChartBuilder::on(area)
+            .caption("tests", ("sans-serif", 18))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_range, 0.0..top * 1.1)?;
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(x_labels)
+            .x_label_formatter(&|x| format!("{}", x.round()))
+            .y_label_formatter(&|y| format!("{}", y.round()))
+            .x_desc("iteration")
+            .draw()?;
+        for (label, points, color) in [("passed", passed, GREEN), ("failed", failed, RED)] {
+            chart
+                .draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(2)))?
+                .label(label)
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2)));
+            chart.draw_series(points.iter().map(|&p| Circle::new(p, 3, color.filled())))?;
+        }
+        chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    }
+    root.present()?;
+    Ok(Some(metrics_chart_path(cwd)))
+}
+
+/// Y range covering `values` with some headroom; a flat or empty series gets a unit range
+fn value_range(values: impl Iterator<Item = f64>) -> std::ops::Range<f64> {
+    let (lo, hi) = values.filter(|v| v.is_finite()).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if !lo.is_finite() {
+        return 0.0..1.0;
+    }
+    let pad = if hi > lo { (hi - lo) * 0.1 } else { lo.abs().max(1.0) * 0.1 };
+    (lo - pad)..(hi + pad)
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Sentinel file that asks a running agent to stop at the next safe point
+pub fn stop_file_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("STOP")
+}
+
+/// Whether `.qernel/STOP` exists. Checked between iterations and before model requests.
+pub fn stop_requested(cwd: &Path) -> bool {
+    stop_file_path(cwd).exists()
+}
+
+/// Remove the stop file once it has been honored so the next run starts normally
+pub fn clear_stop_request(cwd: &Path) {
+    let _ = fs::remove_file(stop_file_path(cwd));
+}
+
+/// State saved when a run stops early, written to `.qernel/checkpoint.json`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Last iteration that completed before the stop
+    pub iteration: u32,
+    pub reason: String,
+    /// Test failure details carried into the next iteration's prompt
+    pub failure_context: String,
+    /// Exit code of the last test run, if one finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_exit_code: Option<i32>,
+    pub saved_at: String,
+}
+
+pub fn checkpoint_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("checkpoint.json")
+}
+
+pub fn save_checkpoint(cwd: &Path, checkpoint: &Checkpoint) -> Result<()> {
+    let path = checkpoint_path(cwd);
+    let json = serde_json::to_string_pretty(checkpoint)?;
+    fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// The saved checkpoint, if any, for `qernel prototype --resume`
+pub fn load_checkpoint(cwd: &Path) -> Result<Option<Checkpoint>> {
+    let path = checkpoint_path(cwd);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let checkpoint = serde_json::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(Some(checkpoint))
+}
+
+/// Drop the checkpoint once a run has finished successfully
+pub fn clear_checkpoint(cwd: &Path) {
+    let _ = fs::remove_file(checkpoint_path(cwd));
+}
@@ -0,0 +1,179 @@
+//! Classifies the agent's shell commands so `agent.command_policy` can let them run, ask
+//! the user first, or refuse them. Only commands built entirely from a short list of
+//! read-only programs count as read-only; anything that cannot be shown to be one is not.
+//! Whether a command may reach the network is enforced where it runs, see
+//! [`runs_offline`].
+
+use codex_core::parse_command::{ParsedCommand, parse_command};
+use serde::Serialize;
+
+use crate::config::{CommandPolicy, PolicyAction};
+
+/// Programs that only read, list or search, with the arguments that would make them
+/// write files or run other programs
+const READ_ONLY_PROGRAMS: &[(&str, &[&str])] = &[
+    ("cat", &[]),
+    ("head", &[]),
+    ("tail", &[]),
+    ("ls", &[]),
+    ("pwd", &[]),
+    ("echo", &[]),
+    ("wc", &[]),
+    ("nl", &[]),
+    ("cut", &[]),
+    ("tr", &[]),
+    ("diff", &[]),
+    ("cmp", &[]),
+    ("stat", &[]),
+    ("du", &[]),
+    ("which", &[]),
+    ("basename", &[]),
+    ("dirname", &[]),
+    ("realpath", &[]),
+    ("grep", &[]),
+    ("egrep", &[]),
+    ("fgrep", &[]),
+    ("rg", &["--pre"]),
+    ("tree", &["-o"]),
+    ("sort", &["-o", "--output", "--compress-program"]),
+    ("find", &["-delete", "-exec", "-execdir", "-ok", "-okdir", "-fprint", "-fprint0", "-fprintf", "-fls"]),
+];
+
+/// Read-only `git` subcommands; `--output` writes a file for all of them, `-O` opens a pager
+const READ_ONLY_GIT: &[&str] = &["status", "diff", "log", "show", "blame", "ls-files", "rev-parse", "grep"];
+
+/// Shells whose `-c` script is split into the commands it runs
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandClass {
+    /// Only reads, lists or searches files
+    ReadOnly,
+    /// May change files or run project code
+    Mutating,
+    /// Names a URL, so it is meant to reach the network
+    Network,
+}
+
+impl CommandClass {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CommandClass::ReadOnly => "read-only",
+            CommandClass::Mutating => "mutating",
+            CommandClass::Network => "network",
+        }
+    }
+}
+
+/// The command's class, with `parse_command`'s summary of it for the run log
+pub fn classify(argv: &[String]) -> (CommandClass, Vec<ParsedCommand>) {
+    let class = if argv.iter().any(|a| a.contains("://")) {
+        CommandClass::Network
+    } else if shell_segments(argv).is_some_and(|segments| !segments.is_empty() && segments.iter().all(|s| is_read_only(s))) {
+        CommandClass::ReadOnly
+    } else {
+        CommandClass::Mutating
+    };
+    (class, parse_command(argv))
+}
+
+/// What `policy` says to do with a command of `class`
+pub fn decide(policy: &CommandPolicy, class: CommandClass) -> PolicyAction {
+    match class {
+        CommandClass::ReadOnly => PolicyAction::Allow,
+        CommandClass::Mutating => policy.mutating,
+        CommandClass::Network => policy.network,
+    }
+}
+
+/// Whether a command the policy let through runs without a network. Unless
+/// `agent.command_policy.network` is `allow`, only a network command the user approved
+/// gets one; where the network cannot be taken away (see
+/// `codex_core::spawn::network_isolation_available`) commands keep it.
+pub fn runs_offline(policy: &CommandPolicy, class: CommandClass) -> bool {
+    policy.network != PolicyAction::Allow && class != CommandClass::Network && codex_core::spawn::network_isolation_available()
+}
+
+/// The plain commands `argv` runs: itself, or each command of a `sh -c` / `bash -lc` script
+/// (recursively). `None` when a script uses anything beyond words joined by `&&`, `||`, `;`
+/// and `|` (redirects, substitutions, subshells, control flow), so what it runs is unknown.
+pub fn shell_segments(argv: &[String]) -> Option<Vec<Vec<String>>> {
+    let program = argv.first().map(|p| p.rsplit('/').next().unwrap_or(p))?;
+    let script = match argv {
+        [_, flag, script] if SHELLS.contains(&program) && matches!(flag.as_str(), "-c" | "-lc") => script,
+        _ if SHELLS.contains(&program) && argv.len() > 1 => return None,
+        _ => return Some(vec![argv.to_vec()]),
+    };
+    let tree = codex_core::bash::try_parse_bash(script)?;
+    let mut segments = Vec::new();
+    for command in codex_core::bash::try_parse_word_only_commands_sequence(&tree, script)? {
+        segments.extend(shell_segments(&command)?);
+    }
+    Some(segments)
+}
+
+/// Whether one plain command is a read-only program without a writing argument
+fn is_read_only(segment: &[String]) -> bool {
+    let Some((program, args)) = segment.split_first() else { return false };
+    if program.contains('/') {
+        return false;
+    }
+    // `--output=x`, and a short option inside a cluster such as `sort -uo x`
+    let writes = |forbidden: &[&str]| {
+        args.iter().any(|a| {
+            forbidden.iter().any(|f| {
+                a == f
+                    || a.strip_prefix(f).is_some_and(|rest| rest.starts_with('='))
+                    || (f.len() == 2 && !a.starts_with("--") && a.starts_with('-') && a[1..].contains(&f[1..]))
+            })
+        })
+    };
+    if program == "git" {
+        let sub = args.iter().find(|a| !a.starts_with('-'));
+        return sub.is_some_and(|sub| READ_ONLY_GIT.contains(&sub.as_str())) && !writes(&["--output", "-c", "-O", "--open-files-in-pager", "--ext-diff"]);
+    }
+    READ_ONLY_PROGRAMS.iter().any(|(name, forbidden)| name == program && !writes(forbidden))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(command: &str) -> CommandClass {
+        classify(&shlex::split(command).unwrap()).0
+    }
+
+    #[test]
+    fn read_only_needs_every_segment_on_the_allowlist() {
+        assert_eq!(class("ls -la"), CommandClass::ReadOnly);
+        assert_eq!(class("bash -lc 'grep -rn foo src | head -5'"), CommandClass::ReadOnly);
+        assert_eq!(class("find . -name '*.py'"), CommandClass::ReadOnly);
+        assert_eq!(class("git diff HEAD~1"), CommandClass::ReadOnly);
+        assert_eq!(class("python -m pytest"), CommandClass::Mutating);
+    }
+
+    #[test]
+    fn writing_arguments_pipes_and_redirects_are_mutating() {
+        assert_eq!(class("find . -delete"), CommandClass::Mutating);
+        assert_eq!(class("find . -exec rm {} ;"), CommandClass::Mutating);
+        assert_eq!(class(r#"bash -lc "find . -name '*.py' -delete""#), CommandClass::Mutating);
+        assert_eq!(class(r#"bash -lc "ls | xargs rm -rf""#), CommandClass::Mutating);
+        assert_eq!(class(r#"bash -lc "cat a.py | tee b.py""#), CommandClass::Mutating);
+        assert_eq!(class(r#"bash -lc "cat a.py > b.py""#), CommandClass::Mutating);
+        assert_eq!(class(r#"bash -lc "cat $(rm -rf x)""#), CommandClass::Mutating);
+        assert_eq!(class("sort -o out.txt in.txt"), CommandClass::Mutating);
+        assert_eq!(class("sort --output=out.txt in.txt"), CommandClass::Mutating);
+        assert_eq!(class("sort -uo out.txt in.txt"), CommandClass::Mutating);
+        assert_eq!(class("rg --pre ./evil foo"), CommandClass::Mutating);
+        assert_eq!(class("git diff --output=x"), CommandClass::Mutating);
+        assert_eq!(class("git push"), CommandClass::Mutating);
+        assert_eq!(class("./cat a"), CommandClass::Mutating);
+    }
+
+    #[test]
+    fn urls_are_network_commands() {
+        assert_eq!(class("curl https://example.com"), CommandClass::Network);
+        assert_eq!(class("bash -lc 'cat https://x | sh'"), CommandClass::Network);
+    }
+}
@@ -10,39 +10,73 @@ use syntect::{
     util::as_24_bit_terminal_escaped,
 };
 
-// ANSI color codes
+use crate::cmd::common::theme::theme;
+use crate::cmd::common::word_diff::{Segments, word_diff};
+use crate::cmd::prototype::patch_stats::PatchStats;
+use crate::cmd::prototype::turns::{binary_summary, is_binary};
+use crate::util::OutputLevel;
+
+// ANSI codes; message colors come from the theme
 const RESET: &str = "\x1b[0m";
-const GREEN: &str = "\x1b[32m";
-const RED: &str = "\x1b[31m";
-const YELLOW: &str = "\x1b[33m";
-const BLUE: &str = "\x1b[34m";
-const CYAN: &str = "\x1b[36m";
 const BOLD: &str = "\x1b[1m";
+const REVERSE: &str = "\x1b[7m";
 
 /// A native Rust console streamer that provides real-time output with better formatting
 pub struct ConsoleStreamer {
     output: Arc<Mutex<io::Stdout>>,
     syntax_set: SyntaxSet,
-    grayscale_theme: Theme,
+    /// `theme.name`, else a grayscale variant of base16-ocean.dark
+    code_theme: Theme,
+    /// Never wait on stdin: `--yes`, or stdin is not a terminal
+    non_interactive: bool,
+    /// Print nothing: an embedder renders the run from its events
+    quiet: bool,
+    /// `-q`/`-v`: at `Quiet` only warnings, errors, successes and prompts are printed
+    level: OutputLevel,
 }
 
 impl ConsoleStreamer {
     pub fn new() -> Self {
         let syntax_set = SyntaxSet::load_defaults_newlines();
-        let grayscale_theme = Self::create_grayscale_theme();
+        let code_theme = theme().syntax.clone().unwrap_or_else(Self::create_grayscale_theme);
         // On Windows, enable VT processing so ANSI escape sequences render.
         #[cfg(windows)]
-        if io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        if crate::util::color_enabled_stdout() {
             let _ = Self::enable_vt_mode();
         }
 
         Self {
             output: Arc::new(Mutex::new(io::stdout())),
             syntax_set,
-            grayscale_theme,
+            code_theme,
+            non_interactive: false,
+            quiet: false,
+            level: crate::util::output_level(),
         }
     }
 
+    /// Answer every prompt with its default instead of reading stdin
+    pub fn non_interactive(mut self, yes: bool) -> Self {
+        self.non_interactive = yes;
+        self
+    }
+
+    /// Drop all output, including spinners and typewriter pauses; implies non-interactive
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self.non_interactive |= quiet;
+        self
+    }
+
+    pub fn level(&self) -> OutputLevel {
+        self.level
+    }
+
+    /// Progress, sections and animations are left out
+    fn hushed(&self) -> bool {
+        self.quiet || self.level == OutputLevel::Quiet
+    }
+
     #[cfg(windows)]
     fn enable_vt_mode() -> Result<()> {
         use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
@@ -115,16 +149,27 @@ impl ConsoleStreamer {
 
     /// Print a message with proper formatting and immediate flush
     pub fn print(&self, message: &str) -> Result<()> {
-        let mut output = self.output.lock().unwrap();
-        write!(output, "{}", message)?;
-        output.flush()?;
-        Ok(())
+        if self.hushed() {
+            return Ok(());
+        }
+        self.write(message)
     }
 
     /// Print a message with newline and flush
     pub fn println(&self, message: &str) -> Result<()> {
+        if self.hushed() {
+            return Ok(());
+        }
+        self.write(&format!("{}\n", message))
+    }
+
+    /// Print even at `OutputLevel::Quiet`, for outcomes and prompts
+    fn write(&self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
         let mut output = self.output.lock().unwrap();
-        writeln!(output, "{}", message)?;
+        write!(output, "{}", crate::util::colorize_stdout(message))?;
         output.flush()?;
         Ok(())
     }
@@ -139,25 +184,39 @@ impl ConsoleStreamer {
 
     /// Print success message with green indicator
     pub fn success(&self, message: &str) -> Result<()> {
-        self.println(&format!("{}[SUCCESS]{} {}", GREEN, RESET, message))?;
+        self.write(&format!("{}[SUCCESS]{} {}\n", theme().success, RESET, message))?;
         Ok(())
     }
 
     /// Print error message with red indicator
     pub fn error(&self, message: &str) -> Result<()> {
-        self.println(&format!("{}[ERROR]{} {}", RED, RESET, message))?;
+        self.write(&format!("{}[ERROR]{} {}\n", theme().error, RESET, message))?;
         Ok(())
     }
 
     /// Print warning message with yellow indicator
     pub fn warning(&self, message: &str) -> Result<()> {
-        self.println(&format!("{}[WARNING]{} {}", YELLOW, RESET, message))?;
+        self.write(&format!("{}[WARNING]{} {}\n", theme().warning, RESET, message))?;
         Ok(())
     }
 
     /// Print info message with blue indicator
     pub fn info(&self, message: &str) -> Result<()> {
-        self.println(&format!("{}[INFO]{} {}", BLUE, RESET, message))?;
+        self.println(&format!("{}[INFO]{} {}", theme().info, RESET, message))?;
+        Ok(())
+    }
+
+    /// Print the implementation plan as a numbered checklist
+    pub fn plan(&self, items: &[codex_core::plan_tool::PlanItemArg]) -> Result<()> {
+        use codex_core::plan_tool::StepStatus;
+        for (i, item) in items.iter().enumerate() {
+            let (marker, color) = match item.status {
+                StepStatus::Completed => ("✓", theme().success.as_str()),
+                StepStatus::InProgress => ("→", theme().accent.as_str()),
+                StepStatus::Pending => ("·", RESET),
+            };
+            self.println(&format!("  {}{}{} {}. {}", color, marker, RESET, i + 1, item.step.trim()))?;
+        }
         Ok(())
     }
 
@@ -180,6 +239,9 @@ impl ConsoleStreamer {
     /// Start an animated spinner with timer for long-running operations
     pub fn start_spinner_with_timer(&self, message: &str, total_timeout_secs: u64) -> Arc<Mutex<bool>> {
         let running = Arc::new(Mutex::new(true));
+        if self.hushed() {
+            return running;
+        }
         let running_clone = Arc::clone(&running);
         let output_clone = Arc::clone(&self.output);
         let message = message.to_string();
@@ -198,19 +260,21 @@ impl ConsoleStreamer {
                 let mut output = output_clone.lock().unwrap();
                 
                 // Start showing timer after 1 minute
+                let mut line = String::new();
                 if elapsed_secs >= 60 && !timer_started {
                     timer_started = true;
-                    write!(output, "\n{}[INFO]{} Timer started - showing remaining time\n", BLUE, RESET).unwrap();
+                    line.push_str(&format!("\n{}[INFO]{} Timer started - showing remaining time\n", theme().info, RESET));
                 }
                 
                 if timer_started {
                     let minutes = remaining_secs / 60;
                     let seconds = remaining_secs % 60;
-                    write!(output, "\r{}[THINKING]{} {} {} {}[TIMER]{} {}m {}s remaining", 
-                           CYAN, RESET, message, spinner_chars[i], YELLOW, RESET, minutes, seconds).unwrap();
+                    line.push_str(&format!("\r{}[THINKING]{} {} {} {}[TIMER]{} {}m {}s remaining", 
+                           theme().accent, RESET, message, spinner_chars[i], theme().warning, RESET, minutes, seconds));
                 } else {
-                    write!(output, "\r{}[THINKING]{} {} {}", CYAN, RESET, message, spinner_chars[i]).unwrap();
+                    line.push_str(&format!("\r{}[THINKING]{} {} {}", theme().accent, RESET, message, spinner_chars[i]));
                 }
+                write!(output, "{}", crate::util::colorize_stdout(&line)).unwrap();
                 
                 output.flush().unwrap();
                 drop(output);
@@ -231,12 +295,17 @@ impl ConsoleStreamer {
     /// Stop the spinner
     pub fn stop_spinner(&self, running: &Arc<Mutex<bool>>) {
         *running.lock().unwrap() = false;
-        thread::sleep(Duration::from_millis(150));
+        if !self.hushed() {
+            thread::sleep(Duration::from_millis(150));
+        }
     }
 
 
     /// Typewriter effect for text
     pub fn typewriter(&self, text: &str, delay_ms: u64) -> Result<()> {
+        if self.hushed() {
+            return Ok(());
+        }
         for ch in text.chars() {
             self.print(&ch.to_string())?;
             thread::sleep(Duration::from_millis(delay_ms));
@@ -247,6 +316,9 @@ impl ConsoleStreamer {
 
     /// Fade-in effect for text with progressive reveal
     pub fn fade_in(&self, text: &str, steps: u32) -> Result<()> {
+        if self.hushed() {
+            return Ok(());
+        }
         let chars: Vec<char> = text.chars().collect();
         let step_size = chars.len() as f32 / steps as f32;
         
@@ -264,13 +336,15 @@ impl ConsoleStreamer {
 
 
     /// Enhanced patch preview with grayscale syntax highlighting
-    pub fn patch_preview(&self, patch: &str) -> Result<()> {
+    pub fn patch_preview(&self, patch: &str, stats: &PatchStats) -> Result<()> {
         self.section("Code Changes")?;
         
         // Parse the patch to extract file changes
         let mut in_changes = false;
         let mut current_file = String::new();
         let mut changes = Vec::new();
+        // `*** Add/Update/Delete File:` headers, for telling binary files apart below
+        let mut operations: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
         
         for line in patch.lines() {
             if line.starts_with("*** Begin Patch") {
@@ -281,7 +355,17 @@ impl ConsoleStreamer {
                 break;
             }
             if in_changes {
-                if line.starts_with("diff --git") {
+                let header = ["Add", "Update", "Delete"]
+                    .into_iter()
+                    .find_map(|op| line.strip_prefix(&format!("*** {} File: ", op)).map(|path| (op, path.trim())));
+                if let Some((op, path)) = header {
+                    current_file = path.to_string();
+                    operations.insert(current_file.clone(), op);
+                    if op == "Delete" && binary_on_disk(path).is_some() {
+                        // Deletions carry no lines, but a binary one is still worth a mention
+                        changes.push((current_file.clone(), String::new()));
+                    }
+                } else if line.starts_with("diff --git") {
                     // Extract filename from diff header
                     if let Some(start) = line.find("b/") {
                         if let Some(end) = line[start + 2..].find(' ') {
@@ -305,7 +389,7 @@ impl ConsoleStreamer {
         }
         
         // Show a clean summary of changes
-        self.println(&format!("{}[CHANGES]{} The AI is making these changes:", CYAN, RESET))?;
+        self.println(&format!("{}[CHANGES]{} The AI is making these changes ({}):", theme().accent, RESET, stats))?;
         self.println("")?;
         
         // Group changes by file
@@ -317,7 +401,21 @@ impl ConsoleStreamer {
         // Process each file with syntax highlighting
         for (file, file_lines) in file_changes {
             if !file.is_empty() {
-                self.println(&format!("{}File: {}:{}", BLUE, file, RESET))?;
+                self.println(&format!("{}File: {}:{}", theme().info, file, RESET))?;
+            }
+
+            // Binary content would print as garbage; show its size instead
+            let on_disk = binary_on_disk(&file);
+            if on_disk.is_some() || file_lines.iter().any(|l| l.contains('\0') || l.contains('\u{FFFD}')) {
+                let added: usize = file_lines.iter().filter_map(|l| l.strip_prefix('+')).map(|l| l.len() + 1).sum();
+                let summary = match operations.get(&file).copied() {
+                    Some("Add") => binary_summary(None, Some(added)),
+                    Some("Delete") => binary_summary(on_disk, None),
+                    _ => binary_summary(on_disk, on_disk),
+                };
+                self.println(&format!("  {}{}{}", theme().warning, summary, RESET))?;
+                self.println("")?;
+                continue;
             }
             
             // Show the diff with syntax highlighting
@@ -343,21 +441,33 @@ impl ConsoleStreamer {
             ))
             .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
         
-        // Create highlighter with grayscale theme
-        let mut highlighter = HighlightLines::new(syntax, &self.grayscale_theme);
-        
+        // Create highlighter with the code theme
+        let mut highlighter = HighlightLines::new(syntax, &self.code_theme);
+
+        // Removed lines paired with the added lines that replace them get their changed words marked
+        let words = paired_word_diffs(file_lines);
+
         // Process each line with diff markers and syntax highlighting
-        for line in file_lines {
-            let (marker, content) = if line.starts_with("+") {
-                (format!("  {}[+]{} ", GREEN, RESET), &line[1..])
-            } else if line.starts_with("-") {
-                (format!("  {}[-]{} ", RED, RESET), &line[1..])
+        for (i, line) in file_lines.iter().enumerate() {
+            let (marker, content) = if let Some(added) = line.strip_prefix('+') {
+                (format!("  {}[+]{} ", theme().success, RESET), added)
+            } else if let Some(removed) = line.strip_prefix('-') {
+                (format!("  {}[-]{} ", theme().error, RESET), removed)
             } else {
-                (format!("    "), line.as_str())
+                ("    ".to_string(), line.as_str())
             };
             
             // Apply syntax highlighting to the content
-            if !content.trim().is_empty() {
+            if let Some(segments) = &words[i] {
+                // Still highlighted, so the highlighter keeps track of strings and comments
+                highlighter.highlight_line(content, &self.syntax_set)?;
+                let color = if line.starts_with('+') { &theme().success } else { &theme().error };
+                let marked: String = segments
+                    .iter()
+                    .map(|(changed, text)| if *changed { format!("{}{}{}{}", color, REVERSE, text, RESET) } else { text.clone() })
+                    .collect();
+                self.println(&format!("{}{}", marker, marked))?;
+            } else if !content.trim().is_empty() {
                 let ranges: Vec<(Style, &str)> = highlighter.highlight_line(content, &self.syntax_set)?;
                 let highlighted_content = as_24_bit_terminal_escaped(&ranges[..], false);
                 self.println(&format!("{}{}", marker, highlighted_content))?;
@@ -371,10 +481,13 @@ impl ConsoleStreamer {
 
     /// Ask user for confirmation before continuing
     pub fn ask_continue(&self, message: &str) -> Result<bool> {
+        if self.non_interactive {
+            self.info("Continuing (non-interactive)")?;
+            return Ok(true);
+        }
         self.println("")?;
         self.section("User Confirmation Required")?;
-        self.typewriter(&format!("{}", message), 10)?;
-        self.typewriter("Press Enter to continue, or 'q' to quit: ", 5)?;
+        self.prompt(message, "Press Enter to continue, or 'q' to quit: ")?;
         
         let mut input = String::new();
         stdin().read_line(&mut input)?;
@@ -383,6 +496,29 @@ impl ConsoleStreamer {
         Ok(response != "q" && response != "quit" && response != "exit")
     }
 
+    /// Ask a yes/no question; `None` when nobody can answer (non-interactive)
+    pub fn confirm(&self, message: &str) -> Result<Option<bool>> {
+        if self.non_interactive {
+            return Ok(None);
+        }
+        self.println("")?;
+        self.section("User Confirmation Required")?;
+        self.prompt(message, "Allow? [y/N]: ")?;
+
+        let mut input = String::new();
+        stdin().read_line(&mut input)?;
+        Ok(Some(matches!(input.trim().to_lowercase().as_str(), "y" | "yes")))
+    }
+
+    /// A question and its answer line; shown at every output level since the run waits on it
+    fn prompt(&self, message: &str, answer: &str) -> Result<()> {
+        if self.level == OutputLevel::Quiet {
+            return self.write(&format!("{}\n{}\n", message, answer));
+        }
+        self.typewriter(message, 10)?;
+        self.typewriter(answer, 5)
+    }
+
     /// Enhanced iteration header with animation
     pub fn animated_iteration_header(&self, iteration: u32, max_iterations: u32) -> Result<()> {
         self.println("")?;
@@ -403,23 +539,23 @@ impl ConsoleStreamer {
         self.section("Debug Execution Result")?;
         
         let status = if exit_code == 0 { 
-            format!("{}[SUCCESS]{} Succeeded", GREEN, RESET) 
+            format!("{}[SUCCESS]{} Succeeded", theme().success, RESET) 
         } else { 
-            format!("{}[FAILED]{} Failed", RED, RESET) 
+            format!("{}[FAILED]{} Failed", theme().error, RESET) 
         };
         
-        self.typewriter(&format!("{}[COMMAND]{} {}", CYAN, RESET, command), 5)?;
-        self.typewriter(&format!("{}[STATUS]{} {} (exit code: {})", BLUE, RESET, status, exit_code), 5)?;
+        self.typewriter(&format!("{}[COMMAND]{} {}", theme().accent, RESET, command), 5)?;
+        self.typewriter(&format!("{}[STATUS]{} {} (exit code: {})", theme().info, RESET, status, exit_code), 5)?;
         
         if !stdout.is_empty() {
-            self.println(&format!("{}[OUTPUT]{}", YELLOW, RESET))?;
+            self.println(&format!("{}[OUTPUT]{}", theme().warning, RESET))?;
             for line in stdout.lines() {
                 self.println(&format!("  {}", line))?;
             }
         }
         
         if !stderr.is_empty() {
-            self.println(&format!("{}[ERRORS]{}", RED, RESET))?;
+            self.println(&format!("{}[ERRORS]{}", theme().error, RESET))?;
             for line in stderr.lines() {
                 self.println(&format!("  {}", line))?;
             }
@@ -435,3 +571,30 @@ impl Default for ConsoleStreamer {
         Self::new()
     }
 }
+
+/// Size of the file at `path` (relative to the project, the working directory during a run)
+/// if it exists and holds binary content
+fn binary_on_disk(path: &str) -> Option<usize> {
+    let bytes = std::fs::read(path).ok()?;
+    is_binary(&bytes).then_some(bytes.len())
+}
+
+/// Word diffs for the diff lines of one file: each run of removed lines is paired, in order,
+/// with the run of added lines right after it
+fn paired_word_diffs(lines: &[String]) -> Vec<Option<Segments>> {
+    let mut words: Vec<Option<Segments>> = vec![None; lines.len()];
+    let mut i = 0;
+    while i < lines.len() {
+        let removed = lines[i..].iter().take_while(|l| l.starts_with('-')).count();
+        let added = lines[i + removed..].iter().take_while(|l| l.starts_with('+')).count();
+        for k in 0..removed.min(added) {
+            let (old, new) = (i + k, i + removed + k);
+            if let Some((old_words, new_words)) = word_diff(&lines[old][1..], &lines[new][1..]) {
+                words[old] = Some(old_words);
+                words[new] = Some(new_words);
+            }
+        }
+        i += (removed + added).max(1);
+    }
+    words
+}
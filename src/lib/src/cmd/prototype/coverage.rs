@@ -0,0 +1,262 @@
+//! `benchmarks.coverage`: after each test run, measure which code in src/ the tests execute
+//! (a coverage.py JSON report) and give the model a compact list of what they never reach.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Functions and line ranges listed per file before the rest is summarized as a count
+const MAX_ITEMS: usize = 10;
+
+pub fn coverage_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("coverage.json")
+}
+
+/// pytest-cov options that write the report [`read_coverage`] expects
+pub const PYTEST_COVERAGE_ARGS: [&str; 3] = ["--cov=src", "--cov-branch", "--cov-report=json:.qernel/coverage.json"];
+
+/// Whether `argv` runs pytest (`pytest ...` or `<python> -m pytest ...`), so a run of it
+/// can measure coverage too
+pub fn runs_pytest(argv: &[String]) -> bool {
+    let program = |arg: &String| Path::new(arg).file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+    match argv {
+        [first, ..] if program(first) == "pytest" => true,
+        [first, flag, module, ..] => program(first).starts_with("python") && flag == "-m" && module == "pytest",
+        _ => false,
+    }
+}
+
+/// The command for `benchmarks.coverage` without a `command` of its own when no test suite
+/// runs pytest: pytest under the project venv's interpreter
+pub fn default_coverage_command(cwd: &Path) -> Vec<String> {
+    let venv_python = crate::cmd::env::venv_python(&cwd.join(".qernel").join(".venv"));
+    let python = if venv_python.exists() { venv_python.to_string_lossy().to_string() } else { "python".to_string() };
+    let mut argv = vec![python, "-m".to_string(), "pytest".to_string()];
+    argv.extend(PYTEST_COVERAGE_ARGS.map(String::from));
+    argv.push("-q".to_string());
+    argv
+}
+
+/// Coverage of one file under src/
+#[derive(Debug, Clone)]
+pub struct FileCoverage {
+    pub path: String,
+    pub percent: f64,
+    /// Functions no test calls, as `Class.method` for methods
+    pub unrun_functions: Vec<String>,
+    /// Functions with some lines never run, with those lines
+    pub partial_functions: Vec<(String, Vec<u64>)>,
+    /// Lines never run; only used when the report has no per-function data
+    pub missing_lines: Vec<u64>,
+    /// Source lines of branches that never went one of their ways
+    pub missing_branches: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    /// Total over the files under src/
+    pub percent: f64,
+    pub files: Vec<FileCoverage>,
+}
+
+impl CoverageReport {
+    pub fn meets(&self, min_percent: Option<f64>) -> bool {
+        min_percent.is_none_or(|min| self.percent >= min)
+    }
+}
+
+/// Read a coverage.py JSON report, keeping only files under src/
+pub fn read_coverage(path: &Path) -> Result<CoverageReport> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("no coverage report at {}", path.display()))?;
+    let json: Value = serde_json::from_str(&text).with_context(|| format!("{} is not a coverage.py JSON report", path.display()))?;
+    let files = json.get("files").and_then(Value::as_object).context("coverage report has no `files`")?;
+
+    let (mut covered, mut statements) = (0u64, 0u64);
+    let mut out = Vec::new();
+    for (file, data) in files {
+        let rel = file.replace('\\', "/");
+        if !(rel.starts_with("src/") || rel.contains("/src/")) {
+            continue;
+        }
+        let summary = &data["summary"];
+        covered += summary["covered_lines"].as_u64().unwrap_or(0);
+        statements += summary["num_statements"].as_u64().unwrap_or(0);
+
+        let mut unrun_functions = Vec::new();
+        let mut partial_functions = Vec::new();
+        let functions = data.get("functions").and_then(Value::as_object);
+        for (name, function) in functions.into_iter().flatten() {
+            // "" is the module-level code
+            if name.is_empty() {
+                continue;
+            }
+            let missing = line_numbers(&function["missing_lines"]);
+            if missing.is_empty() {
+                continue;
+            }
+            if line_numbers(&function["executed_lines"]).is_empty() {
+                unrun_functions.push(name.clone());
+            } else {
+                partial_functions.push((name.clone(), missing));
+            }
+        }
+        let missing_lines = if functions.is_some() { Vec::new() } else { line_numbers(&data["missing_lines"]) };
+        let mut missing_branches: Vec<u64> = data["missing_branches"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|arc| arc.get(0).and_then(Value::as_i64))
+            .filter(|&line| line > 0)
+            .map(|line| line as u64)
+            .collect();
+        missing_branches.sort_unstable();
+        missing_branches.dedup();
+        out.push(FileCoverage {
+            path: rel,
+            percent: summary["percent_covered"].as_f64().unwrap_or(0.0),
+            unrun_functions,
+            partial_functions,
+            missing_lines,
+            missing_branches,
+        });
+    }
+    let percent = if statements == 0 { 100.0 } else { covered as f64 * 100.0 / statements as f64 };
+    Ok(CoverageReport { percent, files: out })
+}
+
+fn line_numbers(value: &Value) -> Vec<u64> {
+    value.as_array().into_iter().flatten().filter_map(Value::as_u64).collect()
+}
+
+/// `3-5, 9, 12-14`, at most `MAX_ITEMS` ranges
+fn line_ranges(lines: &[u64]) -> String {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if line == *end + 1 => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    let mut parts: Vec<String> = ranges
+        .iter()
+        .take(MAX_ITEMS)
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect();
+    if ranges.len() > MAX_ITEMS {
+        parts.push(format!("and {} more", ranges.len() - MAX_ITEMS));
+    }
+    parts.join(", ")
+}
+
+fn capped(names: &[String]) -> String {
+    let mut shown: Vec<String> = names.iter().take(MAX_ITEMS).cloned().collect();
+    if names.len() > MAX_ITEMS {
+        shown.push(format!("and {} more", names.len() - MAX_ITEMS));
+    }
+    shown.join(", ")
+}
+
+/// One line for the console: `Coverage of src/: 72.4% (target 90%)`
+pub fn coverage_line(report: &CoverageReport, min_percent: Option<f64>) -> String {
+    match min_percent {
+        Some(min) => format!("Coverage of src/: {:.1}% (target {}%)", report.percent, min),
+        None => format!("Coverage of src/: {:.1}%", report.percent),
+    }
+}
+
+/// The untested code, file by file, for the next iteration's prompt
+pub fn coverage_prompt_section(report: &CoverageReport, min_percent: Option<f64>) -> String {
+    let mut section = format!("{}.\n", coverage_line(report, min_percent));
+    for file in report.files.iter().filter(|f| f.percent < 100.0) {
+        section.push_str(&format!("{} ({:.1}%):\n", file.path, file.percent));
+        if !file.unrun_functions.is_empty() {
+            section.push_str(&format!("  never called by the tests: {}\n", capped(&file.unrun_functions)));
+        }
+        for (name, lines) in file.partial_functions.iter().take(MAX_ITEMS) {
+            section.push_str(&format!("  {}: lines {} never run\n", name, line_ranges(lines)));
+        }
+        if !file.missing_lines.is_empty() {
+            section.push_str(&format!("  lines never run: {}\n", line_ranges(&file.missing_lines)));
+        }
+        if !file.missing_branches.is_empty() {
+            section.push_str(&format!("  branches never taken at lines: {}\n", line_ranges(&file.missing_branches)));
+        }
+    }
+    if min_percent.is_some() && !report.meets(min_percent) {
+        section.push_str("Coverage is below the target: make sure the tests reach these paths, or remove code the goal does not need.\n");
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn report(files: Value) -> CoverageReport {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coverage.json");
+        std::fs::write(&path, json!({ "files": files }).to_string()).unwrap();
+        read_coverage(&path).unwrap()
+    }
+
+    #[test]
+    fn reports_keep_src_files_and_split_functions_by_what_ran() {
+        let report = report(json!({
+            "src/circuit.py": {
+                "summary": { "covered_lines": 6, "num_statements": 10, "percent_covered": 60.0 },
+                "missing_branches": [[12, 14], [12, -1], [3, 5]],
+                "functions": {
+                    "": { "executed_lines": [1, 2], "missing_lines": [] },
+                    "build": { "executed_lines": [3, 4], "missing_lines": [7, 8] },
+                    "Circuit.draw": { "executed_lines": [], "missing_lines": [20, 21] },
+                    "measure": { "executed_lines": [30], "missing_lines": [] }
+                }
+            },
+            "src/noise.py": {
+                "summary": { "covered_lines": 4, "num_statements": 10, "percent_covered": 40.0 },
+                "missing_lines": [3, 4, 5, 9]
+            },
+            "tests/test_circuit.py": {
+                "summary": { "covered_lines": 0, "num_statements": 100, "percent_covered": 0.0 }
+            }
+        }));
+        assert_eq!(report.percent, 50.0);
+        assert_eq!(report.files.len(), 2);
+        let circuit = &report.files[0];
+        assert_eq!(circuit.unrun_functions, ["Circuit.draw"]);
+        assert_eq!(circuit.partial_functions, [("build".to_string(), vec![7, 8])]);
+        assert!(circuit.missing_lines.is_empty());
+        assert_eq!(circuit.missing_branches, [3, 12]);
+        assert_eq!(report.files[1].missing_lines, [3, 4, 5, 9]);
+        assert!(report.meets(Some(50.0)) && !report.meets(Some(50.1)) && report.meets(None));
+    }
+
+    #[test]
+    fn reports_without_src_files_count_as_covered() {
+        assert_eq!(report(json!({})).percent, 100.0);
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_coverage(&dir.path().join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn line_ranges_merge_runs_and_cap_the_list() {
+        assert_eq!(line_ranges(&[3, 4, 5, 9, 12, 13, 14]), "3-5, 9, 12-14");
+        assert_eq!(line_ranges(&[]), "");
+        let scattered: Vec<u64> = (0..12).map(|i| i * 10).collect();
+        assert_eq!(line_ranges(&scattered), "0, 10, 20, 30, 40, 50, 60, 70, 80, 90, and 2 more");
+    }
+
+    #[test]
+    fn pytest_commands_are_recognized() {
+        let argv = |s: &str| shlex::split(s).unwrap();
+        assert!(runs_pytest(&argv("pytest -q tests")));
+        assert!(runs_pytest(&argv("python -m pytest -x")));
+        assert!(runs_pytest(&argv(".qernel/.venv/bin/python3 -m pytest")));
+        assert!(!runs_pytest(&argv("python run_tests.py")));
+        assert!(!runs_pytest(&argv("make test")));
+        let default = default_coverage_command(Path::new("/nonexistent"));
+        assert!(runs_pytest(&default));
+        assert!(default.iter().any(|a| a == "--cov-report=json:.qernel/coverage.json"));
+    }
+}
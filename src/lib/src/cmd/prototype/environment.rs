@@ -0,0 +1,290 @@
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use codex_core::exec::SandboxType;
+
+use crate::config::{load_config, ConfigResolver, EnvManager, ExecBackend, QernelConfig, DEFAULT_EXEC_IMAGE};
+use crate::error::QernelError;
+
+/// The Python environment the project's commands run in, from `python.env_manager`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PythonEnv {
+    /// `.qernel/.venv`, put first on PATH
+    Venv(PathBuf),
+    /// A conda environment, run through `conda run -n <name>`
+    Conda { name: String, file: PathBuf },
+    /// A uv-managed project, run through `uv run`
+    Uv(PathBuf),
+}
+
+impl PythonEnv {
+    /// Resolve `python.env_manager` for the project; `auto` picks conda when there is an
+    /// `environment.yml`, uv when there is a `uv.lock` or a `[tool.uv]` table, venv otherwise
+    pub fn detect(project_root: &Path) -> Result<Self> {
+        let name = ConfigResolver::load(project_root)?.get("python.env_manager")?.unwrap_or_default();
+        let manager = EnvManager::parse(&name).ok_or_else(|| {
+            QernelError::Config(format!("python.env_manager must be one of {}, got '{}'", EnvManager::NAMES.join(", "), name))
+        })?;
+        let conda_file = ["environment.yml", "environment.yaml"].iter().map(|f| project_root.join(f)).find(|p| p.is_file());
+        let manager = match manager {
+            EnvManager::Auto if conda_file.is_some() => EnvManager::Conda,
+            EnvManager::Auto if is_uv_project(project_root) => EnvManager::Uv,
+            EnvManager::Auto => EnvManager::Venv,
+            other => other,
+        };
+        Ok(match manager {
+            EnvManager::Conda => {
+                let file = conda_file.ok_or_else(|| {
+                    QernelError::Config(format!("python.env_manager is conda but {} has no environment.yml", project_root.display()))
+                })?;
+                let name = conda_env_name(&file)?;
+                PythonEnv::Conda { name, file }
+            }
+            EnvManager::Uv => PythonEnv::Uv(project_root.to_path_buf()),
+            _ => PythonEnv::Venv(project_root.join(".qernel").join(".venv")),
+        })
+    }
+
+    /// What prefixes every command, e.g. `conda run --no-capture-output -n qsim`; empty for a venv
+    pub fn runner(&self) -> Vec<String> {
+        match self {
+            PythonEnv::Venv(_) => Vec::new(),
+            PythonEnv::Conda { name, .. } => {
+                vec![conda_exe(), "run".into(), "--no-capture-output".into(), "-n".into(), name.clone()]
+            }
+            PythonEnv::Uv(root) => vec!["uv".into(), "run".into(), "--project".into(), root.display().to_string()],
+        }
+    }
+
+    /// For messages, e.g. "conda environment 'qsim'"
+    pub fn describe(&self) -> String {
+        match self {
+            PythonEnv::Venv(dir) => format!("venv at {}", dir.display()),
+            PythonEnv::Conda { name, .. } => format!("conda environment '{}'", name),
+            PythonEnv::Uv(root) => format!("uv project at {}", root.display()),
+        }
+    }
+}
+
+fn is_uv_project(root: &Path) -> bool {
+    root.join("uv.lock").is_file()
+        || std::fs::read_to_string(root.join("pyproject.toml")).is_ok_and(|t| t.lines().any(|l| l.trim_start().starts_with("[tool.uv")))
+}
+
+/// The `name:` of a conda environment file
+fn conda_env_name(file: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let doc: serde_yaml::Value = serde_yaml::from_str(&content)
+        .with_context(|| QernelError::Config(format!("Failed to parse {}", file.display())))?;
+    doc.get("name")
+        .and_then(|n| n.as_str())
+        .filter(|n| !n.trim().is_empty())
+        .map(|n| n.trim().to_string())
+        .ok_or_else(|| QernelError::Config(format!("{} has no `name:` for the conda environment", file.display())).into())
+}
+
+/// `conda`, or the executable an activated conda (or mamba) shell points at
+pub fn conda_exe() -> String {
+    std::env::var("CONDA_EXE").ok().filter(|c| !c.trim().is_empty()).unwrap_or_else(|| "conda".to_string())
+}
+
+/// A project command ready for `process_exec_tool_call`, with the project root as sandbox cwd
+pub struct PreparedCommand {
+    pub argv: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub sandbox: SandboxType,
+}
+
+/// How `argv` runs for the project, per `exec.backend`: locally in its Python environment
+/// ([`normalize_command`], [`build_exec_env`]), or unchanged in an `exec.image` container that
+/// sees only the project's `env_files` and `env:` variables
+pub fn prepare_command(argv: &[String], project_root: &Path) -> Result<PreparedCommand> {
+    let resolver = ConfigResolver::load(project_root)?;
+    let name = resolver.get("exec.backend")?.unwrap_or_default();
+    let backend = ExecBackend::parse(&name).ok_or_else(|| {
+        QernelError::Config(format!("exec.backend must be one of {}, got '{}'", ExecBackend::NAMES.join(", "), name))
+    })?;
+    match backend {
+        ExecBackend::Local => Ok(PreparedCommand {
+            argv: normalize_command(argv, &PythonEnv::detect(project_root)?),
+            env: build_exec_env(project_root)?,
+            sandbox: SandboxType::None,
+        }),
+        ExecBackend::Docker => {
+            let config = load_config(&project_root.join(".qernel").join("qernel.yaml"))?;
+            let image = resolver.get("exec.image")?.unwrap_or_else(|| DEFAULT_EXEC_IMAGE.to_string());
+            Ok(PreparedCommand {
+                argv: argv.to_vec(),
+                env: project_env(&config, project_root)?.into_iter().collect(),
+                sandbox: SandboxType::Docker { image },
+            })
+        }
+    }
+}
+
+/// Build execution environment with virtual environment support and the project's
+/// `env_files` and `env:` variables
+pub fn build_exec_env(project_root: &Path) -> Result<HashMap<String, String>> {
+    let mut env: HashMap<String, String> = std::env::vars().collect();
+    let config = load_config(&project_root.join(".qernel").join("qernel.yaml"))?;
+    env.extend(project_env(&config, project_root)?);
+    // conda and uv activate their environment through the command runner instead
+    let PythonEnv::Venv(venv) = PythonEnv::detect(project_root)? else {
+        return Ok(env);
+    };
+    let bin = if cfg!(windows) { venv.join("Scripts") } else { venv.join("bin") };
+
+    if bin.is_dir() {
+        let sep = if cfg!(windows) { ';' } else { ':' };
+        let old = env.get("PATH").cloned().unwrap_or_default();
+        env.insert("PATH".into(),
+            if old.is_empty() { bin.display().to_string() } else { format!("{}{}{}", bin.display(), sep, old) }
+        );
+        env.insert("VIRTUAL_ENV".into(), venv.display().to_string());
+        env.insert("PIP_DISABLE_PIP_VERSION_CHECK".into(), "1".into());
+    }
+    Ok(env)
+}
+
+/// Variables the project adds to every command: its `env_files` in order, then `env:`.
+/// `${VAR}` is expanded from the user's environment, except in single-quoted dotenv values.
+pub fn project_env(config: &QernelConfig, project_root: &Path) -> Result<IndexMap<String, String>> {
+    let mut vars = IndexMap::new();
+    for file in &config.env_files {
+        let path = project_root.join(file);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| QernelError::Config(format!("env_files: cannot read {}", path.display())))?;
+        for (i, line) in content.lines().enumerate() {
+            let Some((key, value, expand)) = parse_dotenv_line(line)
+                .map_err(|e| QernelError::Config(format!("{}:{}: {}", file, i + 1, e)))?
+            else {
+                continue;
+            };
+            let value = if expand { expand_vars(&value, &format!("{} in {}", key, file))? } else { value };
+            vars.insert(key, value);
+        }
+    }
+    for (key, value) in &config.env {
+        if !is_env_name(key) {
+            return Err(QernelError::Config(format!("env: '{}' is not a valid variable name", key)).into());
+        }
+        vars.insert(key.clone(), expand_vars(value, &format!("env.{}", key))?);
+    }
+    Ok(vars)
+}
+
+/// `KEY=VALUE` with an optional `export ` prefix; blank lines and `#` comments yield `None`.
+/// The flag says whether the value is subject to `${VAR}` expansion.
+fn parse_dotenv_line(line: &str) -> std::result::Result<Option<(String, String, bool)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+    let (key, value) = line.split_once('=').ok_or("expected KEY=VALUE")?;
+    let key = key.trim();
+    if !is_env_name(key) {
+        return Err(format!("'{}' is not a valid variable name", key));
+    }
+    let value = value.trim();
+    for quote in ['\'', '"'] {
+        if let Some(rest) = value.strip_prefix(quote) {
+            let end = rest.find(quote).ok_or_else(|| format!("unterminated {} quote", quote))?;
+            return Ok(Some((key.to_string(), rest[..end].to_string(), quote == '"')));
+        }
+    }
+    // Unquoted values end at an inline comment
+    let value = match value.find(" #") {
+        Some(i) => value[..i].trim_end(),
+        None => value,
+    };
+    Ok(Some((key.to_string(), value.to_string(), true)))
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace every `${VAR}` in `value` with the user's `VAR`; `what` names the setting in errors
+fn expand_vars(value: &str, what: &str) -> Result<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| QernelError::Config(format!("{}: unterminated ${{ in '{}'", what, value)))?;
+        let name = &rest[start + 2..start + end];
+        let resolved = std::env::var(name).map_err(|_| {
+            QernelError::Config(format!("{} references ${{{}}}, which is not set in your environment", what, name))
+        })?;
+        out.push_str(&resolved);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Run `argv` in the project's Python environment: under `conda run` or `uv run`, or for a
+/// venv with `python` replaced by the venv's interpreter (falling back to system python3).
+pub fn normalize_command(argv: &[String], python_env: &PythonEnv) -> Vec<String> {
+    if argv.is_empty() { return vec![]; }
+    let mut out = argv.to_vec();
+    let PythonEnv::Venv(venv) = python_env else {
+        let mut wrapped = python_env.runner();
+        wrapped.extend(out);
+        return wrapped;
+    };
+
+    // If the command is 'python', try to use the virtual environment Python first
+    if out[0] == "python" {
+        let venv_python = crate::cmd::env::venv_python(venv);
+        if venv_python.exists() {
+            out[0] = venv_python.to_string_lossy().to_string();
+            return out;
+        }
+        
+        // Fallback to system python3 if python is not found
+        if which_in_path("python").is_none() && which_in_path("python3").is_some() {
+            println!("[exec] 'python' not found, using 'python3'");
+            out[0] = "python3".to_string();
+        }
+    }
+    out
+}
+
+pub fn which_in_path(cmd: &str) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    let path: OsString = std::env::var_os("PATH")?;
+    for dir in std::env::split_paths(&path) {
+        let candidate = dir.join(cmd);
+        if candidate.is_file() {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                if std::fs::metadata(&candidate).ok()?.permissions().mode() & 0o111 != 0 {
+                    return Some(candidate);
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+pub fn resolve_absolute_path(p: &str) -> Result<PathBuf> {
+    let path = Path::new(p);
+    let abs = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(abs.canonicalize().unwrap_or(abs))
+}
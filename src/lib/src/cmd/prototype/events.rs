@@ -0,0 +1,158 @@
+//! Typed progress of a prototype run. The agent loop reports to a [`ProgressSink`] so GUIs
+//! and the Zoo web backend can render a run themselves; the CLI is [`ConsoleSink`].
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::cmd::common::usage::{self, Usage};
+use crate::cmd::prototype::console::ConsoleStreamer;
+use crate::cmd::prototype::suites::{SuiteResult, score};
+use crate::cmd::prototype::summary::MetricResult;
+use crate::util::OutputLevel;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// Before the model is asked for the iteration's changes
+    IterationStarted { iteration: u32, max_iterations: u32 },
+    /// A patch landed; `files` are absolute
+    PatchApplied { iteration: u32, files: Vec<PathBuf> },
+    /// The tests finished; `passed` needs every required suite to pass and every metric
+    /// target met. `suites` is empty unless `benchmarks.suites` is configured.
+    TestsFinished {
+        iteration: u32,
+        command: String,
+        exit_code: i32,
+        passed: bool,
+        stdout: String,
+        stderr: String,
+        metrics: Vec<MetricResult>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        suites: Vec<SuiteResult>,
+    },
+    /// A model answered: `usage` is what it cost, `total` the run so far
+    ModelUsage { model: String, usage: Usage, total: Usage },
+}
+
+/// Receives the events of a run, in order, on the thread that started it
+pub trait ProgressSink {
+    fn on_event(&mut self, event: &ProgressEvent);
+}
+
+impl<F: FnMut(&ProgressEvent)> ProgressSink for F {
+    fn on_event(&mut self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+/// The CLI's sink: iteration headers and test results on stdout
+pub struct ConsoleSink {
+    console: ConsoleStreamer,
+    /// Show the full command, exit code and output of each test run (`--debug`, `-v`)
+    debug: bool,
+    /// The output was already printed live (`--stream-tests`)
+    streamed: bool,
+}
+
+impl ConsoleSink {
+    pub fn new(debug: bool) -> Self {
+        let console = ConsoleStreamer::new();
+        let debug = debug || console.level() == OutputLevel::Verbose;
+        ConsoleSink { console, debug, streamed: false }
+    }
+
+    pub fn streamed(mut self, streamed: bool) -> Self {
+        self.streamed = streamed;
+        self
+    }
+
+    fn render(&self, event: &ProgressEvent) -> anyhow::Result<()> {
+        match event {
+            ProgressEvent::IterationStarted { iteration, max_iterations } => {
+                self.console.animated_iteration_header(*iteration, *max_iterations)
+            }
+            ProgressEvent::TestsFinished { command, exit_code, stdout, stderr, .. } if self.debug => {
+                self.console.debug_execution_result(command, *exit_code, stdout, stderr)
+            }
+            ProgressEvent::TestsFinished { exit_code, passed, stdout, stderr, suites, .. } => {
+                if !stdout.is_empty() && !self.streamed {
+                    self.console.println(stdout)?;
+                }
+                if !stderr.is_empty() && !self.streamed {
+                    self.console.println(stderr)?;
+                }
+                if !suites.is_empty() {
+                    return self.render_suites(suites);
+                }
+                if *passed {
+                    self.console.success("✓ Tests passed!")
+                } else if *exit_code == 0 {
+                    self.console.error("✗ Tests ran, but a metric target was not met")
+                } else {
+                    self.console.error("✗ Tests failed")
+                }
+            }
+            // The agent loop prints the patch as it applies it; the CLI does not show per-request usage
+            ProgressEvent::PatchApplied { .. } | ProgressEvent::ModelUsage { .. } => Ok(()),
+        }
+    }
+}
+
+impl ConsoleSink {
+    /// One line per suite, then the weighted score
+    fn render_suites(&self, suites: &[SuiteResult]) -> anyhow::Result<()> {
+        for suite in suites {
+            let kind = if suite.required { "" } else { " (optional)" };
+            if suite.passed {
+                self.console.success(&format!("✓ {}{} passed", suite.name, kind))?;
+            } else if suite.required {
+                self.console.error(&format!("✗ {} failed (exit code {})", suite.name, suite.exit_code))?;
+            } else {
+                self.console.warning(&format!("✗ {}{} failed (exit code {})", suite.name, kind, suite.exit_code))?;
+            }
+        }
+        let passed = suites.iter().filter(|s| s.passed).count();
+        self.console.info(&format!("{}/{} suites passed, score {:.2}", passed, suites.len(), score(suites)))
+    }
+}
+
+impl ProgressSink for ConsoleSink {
+    fn on_event(&mut self, event: &ProgressEvent) {
+        // Output errors (e.g. a closed pipe) must not end the run
+        let _ = self.render(event);
+    }
+}
+
+/// Turns the process-wide usage counters into `ModelUsage` events
+pub struct UsageTracker {
+    seen: Usage,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        UsageTracker { seen: usage::totals() }
+    }
+
+    /// Report what `model` used since the last report; nothing when it made no request
+    pub fn report(&mut self, sink: &mut dyn ProgressSink, model: &str) {
+        let total = usage::totals();
+        if total.requests == self.seen.requests {
+            return;
+        }
+        let usage = Usage {
+            requests: total.requests - self.seen.requests,
+            input_tokens: total.input_tokens - self.seen.input_tokens,
+            output_tokens: total.output_tokens - self.seen.output_tokens,
+            cost_usd: total.cost_usd - self.seen.cost_usd,
+            unpriced_requests: total.unpriced_requests - self.seen.unpriced_requests,
+        };
+        self.seen = total.clone();
+        sink.on_event(&ProgressEvent::ModelUsage { model: model.to_string(), usage, total });
+    }
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,161 @@
+//! Live console view of the exec layer's events: the commands a run executes with their
+//! output as it arrives, and the files the apply_patch layer writes. The exec layer reports
+//! these as [`Event`]s; without a renderer the user sees a command only once it has finished.
+
+use std::io::Write;
+
+use codex_core::protocol::{Event, EventMsg, ExecOutputStream, FileChange};
+use once_cell::sync::OnceCell;
+
+use crate::cmd::common::theme::theme;
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+
+/// Output lines shown per command at [`ExecEcho::Summary`] before the rest is only counted
+const SUMMARY_LINES: usize = 10;
+
+/// How much of a command's run the console shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecEcho {
+    /// Nothing: `-q`, an embedder, or callers that report the result themselves
+    #[default]
+    Silent,
+    /// The command, its first output lines and its exit code
+    Summary,
+    /// Every output line as it arrives, and each file a patch writes (`-v`, `--debug`, `--stream-tests`)
+    Full,
+}
+
+/// Renders exec events on stdout (a command's stderr goes to stderr) as they arrive
+#[derive(Default)]
+pub struct ExecEventRenderer {
+    echo: ExecEcho,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    /// Output lines of the current command shown and held back at `Summary`
+    shown: usize,
+    hidden: usize,
+}
+
+impl ExecEventRenderer {
+    pub fn new(echo: ExecEcho) -> Self {
+        ExecEventRenderer { echo, ..Default::default() }
+    }
+
+    pub fn render(&mut self, event: &Event) {
+        if self.echo == ExecEcho::Silent {
+            return;
+        }
+        match &event.msg {
+            EventMsg::ExecCommandBegin(begin) => {
+                self.shown = 0;
+                self.hidden = 0;
+                print_line(&format!("{}[RUN]{} {}", theme().accent, RESET, begin.command));
+            }
+            EventMsg::ExecCommandOutputDelta(delta) => {
+                self.push(matches!(delta.stream, ExecOutputStream::Stderr), &delta.chunk)
+            }
+            EventMsg::ExecCommandEnd(end) => {
+                self.flush();
+                if self.hidden > 0 {
+                    print_line(&format!("  {}[... {} more lines]{}", DIM, self.hidden, RESET));
+                }
+                let color = if end.exit_code == 0 { &theme().success } else { &theme().error };
+                print_line(&format!("{}[EXIT]{} {} in {:.1}s", color, RESET, end.exit_code, end.duration.as_secs_f64()));
+            }
+            EventMsg::PatchApplyBegin(begin) if self.echo == ExecEcho::Full => {
+                let mut changes: Vec<_> = begin.changes.iter().collect();
+                changes.sort_by(|a, b| a.0.cmp(b.0));
+                for (path, change) in changes {
+                    let verb = match change {
+                        FileChange::Add { .. } => "add",
+                        FileChange::Delete => "delete",
+                        FileChange::Update { move_path: Some(_), .. } => "move",
+                        FileChange::Update { .. } => "update",
+                    };
+                    print_line(&format!("{}[PATCH]{} {} {}", theme().accent, RESET, verb, path.display()));
+                }
+            }
+            EventMsg::PatchApplyEnd(end) if self.echo == ExecEcho::Full && !end.success => {
+                print_line(&format!("{}[PATCH]{} failed: {}", theme().error, RESET, end.stderr.trim()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Print what is left of a last line without a newline
+    pub fn flush(&mut self) {
+        for is_stderr in [false, true] {
+            let buf = if is_stderr { &mut self.stderr } else { &mut self.stdout };
+            if !buf.is_empty() {
+                buf.push(b'\n');
+                let rest = std::mem::take(buf);
+                self.print_output(&rest, is_stderr);
+            }
+        }
+    }
+
+    /// Complete lines only, stderr apart from stdout so the two never interleave mid-line
+    fn push(&mut self, is_stderr: bool, chunk: &[u8]) {
+        let buf = if is_stderr { &mut self.stderr } else { &mut self.stdout };
+        buf.extend_from_slice(chunk);
+        if let Some(end) = buf.iter().rposition(|&b| b == b'\n') {
+            let complete: Vec<u8> = buf.drain(..=end).collect();
+            self.print_output(&complete, is_stderr);
+        }
+    }
+
+    fn print_output(&mut self, bytes: &[u8], is_stderr: bool) {
+        let text = String::from_utf8_lossy(bytes);
+        if self.echo == ExecEcho::Full {
+            return print_raw(&text, is_stderr);
+        }
+        let mut shown = String::new();
+        for line in text.lines() {
+            if self.shown < SUMMARY_LINES {
+                self.shown += 1;
+                shown.push_str(&format!("  {}{}{}\n", DIM, line, RESET));
+            } else {
+                self.hidden += 1;
+            }
+        }
+        print_raw(&crate::util::colorize_stdout(&shown), is_stderr);
+    }
+}
+
+fn print_line(line: &str) {
+    print_raw(&format!("{}\n", crate::util::colorize_stdout(line)), false);
+}
+
+fn print_raw(text: &str, is_stderr: bool) {
+    if is_stderr {
+        let mut err = std::io::stderr().lock();
+        let _ = err.write_all(text.as_bytes());
+        let _ = err.flush();
+    } else {
+        let mut out = std::io::stdout().lock();
+        let _ = out.write_all(text.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+static PATCH_EVENTS: OnceCell<crossbeam_channel::Receiver<Event>> = OnceCell::new();
+
+/// Queue the apply_patch layer's events for [`render_patch_events`] from now on
+pub fn watch_patch_events() {
+    PATCH_EVENTS.get_or_init(|| {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let _ = codex_apply_patch::standalone_executable::EVENT_SENDER.set(sender);
+        receiver
+    });
+}
+
+/// Render the patch events queued since the last call
+pub fn render_patch_events(renderer: &mut ExecEventRenderer) {
+    if let Some(receiver) = PATCH_EVENTS.get() {
+        for event in receiver.try_iter() {
+            renderer.render(&event);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::cmd::prototype::mineru::figure_captions;
+
+/// A figure from a parsed paper, with its caption when mineru found one
+#[derive(Debug, Clone)]
+pub struct Figure {
+    pub path: PathBuf,
+    pub caption: String,
+}
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "webp"];
+
+/// Words too common in specs and captions to say anything about relevance
+const STOPWORDS: &[&str] = &[
+    "about", "above", "after", "also", "and", "are", "based", "been", "below", "between", "both", "can", "each",
+    "figure", "fig", "for", "from", "have", "into", "its", "more", "not", "other", "our", "over", "shown", "shows",
+    "such", "than", "that", "the", "their", "them", "then", "there", "these", "this", "those", "through", "under",
+    "using", "used", "via", "was", "were", "when", "where", "which", "while", "with", "within", "would", "your",
+];
+
+/// Pick at most `max` figures from `.qernel/parsed` that are most relevant to the spec.
+///
+/// Captions come from mineru's `content_list.json`. Each figure is scored by how many
+/// distinct keywords of the spec (excluding its pasted `## Paper Content`) its caption
+/// mentions; ties keep document order, and the selection is returned in document order.
+pub fn select_relevant_figures(cwd: &Path, spec: &str, max: usize) -> Vec<Figure> {
+    if max == 0 {
+        return Vec::new();
+    }
+    let figures = collect_figures(&cwd.join(".qernel").join("parsed"));
+    if figures.len() <= max {
+        return figures;
+    }
+
+    let own_text = spec.split("## Paper Content").next().unwrap_or(spec);
+    let terms = keywords(own_text);
+    let mut scored: Vec<(usize, usize)> = figures
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (keywords(&f.caption).intersection(&terms).count(), i))
+        .collect();
+    // Highest score first; a captioned figure beats an uncaptioned one at equal score
+    scored.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), figures[i].caption.is_empty(), i));
+    let mut keep: Vec<usize> = scored.into_iter().take(max).map(|(_, i)| i).collect();
+    keep.sort_unstable();
+    keep.into_iter().map(|i| figures[i].clone()).collect()
+}
+
+/// All figures under each parsed paper directory, captioned ones first in document
+/// order, followed by any image files the content list does not mention.
+fn collect_figures(parsed_dir: &Path) -> Vec<Figure> {
+    let Ok(entries) = std::fs::read_dir(parsed_dir) else {
+        return Vec::new();
+    };
+    let mut paper_dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    paper_dirs.sort();
+
+    let mut figures = Vec::new();
+    for dir in paper_dirs {
+        let mut seen = HashSet::new();
+        for content_list in find_content_lists(&dir) {
+            let base = content_list.parent().unwrap_or(&dir).to_path_buf();
+            let Some(data) = std::fs::read_to_string(&content_list)
+                .ok()
+                .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+            else {
+                continue;
+            };
+            for (img_path, caption) in figure_captions(&data) {
+                let path = base.join(img_path);
+                if path.is_file() && seen.insert(path.clone()) {
+                    figures.push(Figure { path, caption });
+                }
+            }
+        }
+        for images_dir in [dir.join("images"), dir.join("auto").join("images")] {
+            let Ok(entries) = std::fs::read_dir(&images_dir) else { continue };
+            let mut files: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| is_image(p)).collect();
+            files.sort();
+            for path in files {
+                if !seen.contains(&path) {
+                    seen.insert(path.clone());
+                    figures.push(Figure { path, caption: String::new() });
+                }
+            }
+        }
+    }
+    figures
+}
+
+fn find_content_lists(dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&d) else { continue };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.is_dir() {
+                stack.push(path);
+            } else if path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.ends_with("content_list.json")) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+fn is_image(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '-')
+        .map(|w| w.trim_matches('-').to_lowercase())
+        .filter(|w| w.len() >= 3 && !w.chars().all(|c| c.is_ascii_digit()) && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
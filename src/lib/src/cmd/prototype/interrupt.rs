@@ -0,0 +1,62 @@
+use anyhow::Result;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static INSTALL: Once = Once::new();
+
+/// Catch Ctrl-C for the rest of the process. The first press asks the agent to stop
+/// at the next safe point; a second press quits immediately.
+///
+/// Listens through tokio's signal handling so it coexists with the exec layer, which
+/// kills the running command's process group on the same signal.
+pub fn install() {
+    INSTALL.call_once(|| {
+        std::thread::spawn(|| {
+            let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+                return;
+            };
+            rt.block_on(async {
+                while tokio::signal::ctrl_c().await.is_ok() {
+                    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                        eprintln!("\nInterrupted twice, quitting without saving state");
+                        std::process::exit(130);
+                    }
+                    eprintln!("\nInterrupted: stopping at the next safe point (press Ctrl-C again to quit now)");
+                }
+            });
+        });
+    });
+}
+
+/// Whether Ctrl-C was pressed since `install`
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Run a blocking call (e.g. a model request) on a worker thread and give up on it
+/// as soon as Ctrl-C is pressed. Returns `None` when interrupted; the abandoned
+/// request ends with the process.
+pub fn run_interruptible<T, F>(f: F) -> Result<Option<T>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let span = tracing::Span::current();
+    std::thread::spawn(move || {
+        let _ = tx.send(span.in_scope(f));
+    });
+    loop {
+        if interrupted() {
+            return Ok(None);
+        }
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(result) => return result.map(Some),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("model request thread panicked"),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// One line of a run log
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub ts: String,
+    pub category: String,
+    pub payload: serde_json::Value,
+}
+
+/// Appends JSONL events to `.qernel/logs/run-<ts>.jsonl`. Cheap to clone; clones share the file.
+#[derive(Clone)]
+pub struct RunLogger {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl RunLogger {
+    /// Start a new run log under `<cwd>/.qernel/logs`
+    pub fn create(cwd: &Path) -> Result<Self> {
+        let dir = logs_dir(cwd);
+        std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let path = dir.join(format!("run-{}.jsonl", chrono::Utc::now().format("%Y%m%d-%H%M%S")));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        Ok(Self { path, file: Arc::new(Mutex::new(file)) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one event; logging failures never interrupt a run
+    pub fn event(&self, category: &str, payload: serde_json::Value) {
+        let event = LogEvent {
+            ts: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            category: category.to_string(),
+            payload,
+        };
+        if let Ok(mut line) = serde_json::to_string(&event) {
+            line.push('\n');
+            if let Ok(mut file) = self.file.lock() {
+                let _ = file.write_all(line.as_bytes());
+            }
+        }
+    }
+
+    /// Append a free-text event as `{"message": ...}`
+    pub fn message(&self, category: &str, message: &str) {
+        self.event(category, serde_json::json!({ "message": message }));
+    }
+}
+
+/// Directory holding one JSONL file per prototype run
+pub fn logs_dir(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("logs")
+}
+
+/// The current run's logger, created on first use so the prototype handler and the agent
+/// loop write to the same run file
+static RUN_LOGGER: Mutex<Option<RunLogger>> = Mutex::new(None);
+
+/// Initialize debug logging if enabled
+pub fn init_debug_logging(cwd: &Path, debug: bool) -> Result<Option<RunLogger>> {
+    if !debug {
+        return Ok(None);
+    }
+    let mut current = RUN_LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(logger) = current.as_ref() {
+        return Ok(Some(logger.clone()));
+    }
+    let logger = RunLogger::create(cwd)?;
+    logger.message("run", "Qernel debug log started");
+    *current = Some(logger.clone());
+    Ok(Some(logger))
+}
+
+/// Finish the current run's log; the next run starts a new file
+pub fn end_debug_logging() {
+    *RUN_LOGGER.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Write a debug message to the run log and optionally print it to the console.
+/// A leading `[category]` tag (e.g. `[ai]`, `[patch]`) becomes the event category.
+pub fn debug_log(logger: &Option<RunLogger>, message: &str, print_to_console: bool) {
+    if print_to_console {
+        println!("{}", message);
+    }
+
+    if let Some(logger) = logger {
+        let (category, text) = split_category(message);
+        logger.message(category, text);
+    }
+}
+
+fn split_category(message: &str) -> (&str, &str) {
+    if let Some(rest) = message.strip_prefix('[')
+        && let Some((tag, text)) = rest.split_once(']')
+        && !tag.is_empty()
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return (tag, text.strip_prefix(' ').unwrap_or(text));
+    }
+    ("log", message)
+}
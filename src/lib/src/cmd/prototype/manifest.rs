@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// `.qernel/papers/manifest.json`: what was downloaded and parsed for each paper source
+/// (keyed by [`PaperConfig::source`](crate::config::PaperConfig::source)),
+/// so unchanged papers are not downloaded or parsed again on every run
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PaperManifest {
+    #[serde(default)]
+    pub papers: BTreeMap<String, PaperEntry>,
+}
+
+/// What a paper was ingested from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaperFormat {
+    /// PDF parsed with mineru
+    #[default]
+    Pdf,
+    /// arXiv LaTeX source
+    Latex,
+    /// ar5iv HTML
+    Html,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperEntry {
+    #[serde(default)]
+    pub format: PaperFormat,
+    /// PDF the source resolved to, relative to the project (empty for LaTeX/HTML)
+    #[serde(default)]
+    pub pdf: String,
+    /// Of the PDF, or of the downloaded LaTeX/HTML source
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parsed_at: Option<DateTime<Utc>>,
+    /// `mineru --version` at parse time; a different version parses again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mineru_version: Option<String>,
+    /// URL a DOI or title resolved to, reused instead of asking the lookup APIs again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_url: Option<String>,
+}
+
+pub fn manifest_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("papers").join("manifest.json")
+}
+
+impl PaperManifest {
+    /// A missing or unreadable manifest is treated as empty: everything is fetched again
+    pub fn load(cwd: &Path) -> Self {
+        std::fs::read_to_string(manifest_path(cwd))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, cwd: &Path) -> Result<()> {
+        let path = manifest_path(cwd);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Hex SHA-256 of a file's contents
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
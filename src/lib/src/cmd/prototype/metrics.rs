@@ -0,0 +1,214 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::{MetricConfig, MetricDirection};
+
+/// One metric's value after a test run
+#[derive(Debug, Clone)]
+pub struct MetricReading {
+    pub name: String,
+    pub value: Option<f64>,
+    pub target: Option<f64>,
+    pub direction: MetricDirection,
+}
+
+impl MetricReading {
+    /// Whether the target (if any) is reached; a missing value never meets a target
+    pub fn met(&self) -> bool {
+        match (self.target, self.value) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(t), Some(v)) => match self.direction {
+                MetricDirection::Maximize => v >= t,
+                MetricDirection::Minimize => v <= t,
+            },
+        }
+    }
+
+    fn target_label(&self) -> Option<String> {
+        let op = match self.direction {
+            MetricDirection::Maximize => ">=",
+            MetricDirection::Minimize => "<=",
+        };
+        self.target.map(|t| format!("{} {}", op, t))
+    }
+}
+
+/// Metric values recorded for one iteration, persisted to `.qernel/metrics.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationMetrics {
+    pub iteration: u32,
+    pub exit_code: i32,
+    pub values: BTreeMap<String, Option<f64>>,
+    /// Tests passed and failed, when the output has a pytest or unittest summary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<TestCounts>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestCounts {
+    pub passed: u32,
+    pub failed: u32,
+}
+
+pub fn metrics_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("metrics.json")
+}
+
+/// Fail early on metrics that can't be extracted (bad regex, no extraction rule)
+pub fn validate_metrics(metrics: &[MetricConfig]) -> Result<()> {
+    for m in metrics {
+        match (&m.regex, &m.json_key) {
+            (Some(re), None) => {
+                Regex::new(re).with_context(|| format!("benchmarks.metrics '{}': invalid regex", m.name))?;
+            }
+            (None, Some(_)) => {}
+            _ => anyhow::bail!("benchmarks.metrics '{}': set exactly one of regex or json_key", m.name),
+        }
+    }
+    Ok(())
+}
+
+/// Read every configured metric from the test stdout. When a metric appears several
+/// times the last occurrence wins, so progress output doesn't shadow the final value.
+pub fn extract_metrics(metrics: &[MetricConfig], stdout: &str) -> Vec<MetricReading> {
+    metrics
+        .iter()
+        .map(|m| {
+            let value = if let Some(re) = &m.regex {
+                extract_regex(re, stdout)
+            } else if let Some(key) = &m.json_key {
+                extract_json(key, stdout)
+            } else {
+                None
+            };
+            MetricReading { name: m.name.clone(), value, target: m.target, direction: m.direction }
+        })
+        .collect()
+}
+
+fn extract_regex(pattern: &str, stdout: &str) -> Option<f64> {
+    let re = Regex::new(pattern).ok()?;
+    let caps = re.captures_iter(stdout).last()?;
+    let text = caps.get(1).or_else(|| caps.get(0))?.as_str();
+    text.trim().parse().ok()
+}
+
+fn extract_json(key: &str, stdout: &str) -> Option<f64> {
+    stdout.lines().rev().find_map(|line| {
+        let value: serde_json::Value = serde_json::from_str(line.trim()).ok()?;
+        let mut node = &value;
+        for part in key.split('.') {
+            node = node.get(part)?;
+        }
+        node.as_f64().or_else(|| node.as_str().and_then(|s| s.trim().parse().ok()))
+    })
+}
+
+pub fn targets_met(readings: &[MetricReading]) -> bool {
+    readings.iter().all(MetricReading::met)
+}
+
+/// Passed and failed test counts summed over every pytest (`=== 2 failed, 5 passed in 0.3s ===`)
+/// or unittest (`Ran 7 tests` ... `FAILED (failures=2)`) summary in `stdout`
+pub fn test_counts(stdout: &str) -> Option<TestCounts> {
+    static PYTEST: Lazy<Regex> = Lazy::new(|| Regex::new(r"^=+ (.*) in [\d.]+s.* =+$").expect("pytest summary regex"));
+    static PYTEST_COUNT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) (passed|failed|errors?)\b").expect("pytest count regex"));
+    static UNITTEST_RAN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^Ran (\d+) tests? in ").expect("unittest regex"));
+    static UNITTEST_FAILED: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(?:failures|errors)=(\d+)").expect("unittest count regex"));
+
+    let mut counts: Option<TestCounts> = None;
+    let mut ran: Option<u32> = None;
+    for line in stdout.lines().map(str::trim) {
+        if let Some(caps) = PYTEST.captures(line) {
+            let total = counts.get_or_insert_with(TestCounts::default);
+            for c in PYTEST_COUNT.captures_iter(&caps[1]) {
+                let n: u32 = c[1].parse().unwrap_or(0);
+                if &c[2] == "passed" {
+                    total.passed += n;
+                } else {
+                    total.failed += n;
+                }
+            }
+        } else if let Some(caps) = UNITTEST_RAN.captures(line) {
+            ran = caps[1].parse().ok();
+        } else if let Some(n) = ran.filter(|_| line.starts_with("OK") || line.starts_with("FAILED (")) {
+            let failed: u32 = UNITTEST_FAILED.captures_iter(line).filter_map(|c| c[1].parse::<u32>().ok()).sum();
+            let total = counts.get_or_insert_with(TestCounts::default);
+            total.passed += n.saturating_sub(failed);
+            total.failed += failed;
+            ran = None;
+        }
+    }
+    counts
+}
+
+/// Append this iteration to the run's history and, when metrics are configured, write it
+/// to `.qernel/metrics.json`
+pub fn record_iteration(
+    cwd: &Path,
+    history: &mut Vec<IterationMetrics>,
+    iteration: u32,
+    exit_code: i32,
+    tests: Option<TestCounts>,
+    readings: &[MetricReading],
+) -> Result<()> {
+    history.push(IterationMetrics {
+        iteration,
+        exit_code,
+        values: readings.iter().map(|r| (r.name.clone(), r.value)).collect(),
+        tests,
+    });
+    if readings.is_empty() {
+        return Ok(());
+    }
+    let path = metrics_path(cwd);
+    std::fs::write(&path, serde_json::to_string_pretty(history)?).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// One line per metric: current value, target status and the values of earlier iterations
+pub fn render_trajectory(readings: &[MetricReading], history: &[IterationMetrics]) -> Vec<String> {
+    readings
+        .iter()
+        .map(|r| {
+            let trajectory: Vec<String> = history
+                .iter()
+                .map(|h| h.values.get(&r.name).copied().flatten().map(format_value).unwrap_or_else(|| "-".to_string()))
+                .collect();
+            let status = match r.target_label() {
+                Some(label) if r.met() => format!("target {} ✓", label),
+                Some(label) => format!("target {} ✗", label),
+                None => "no target".to_string(),
+            };
+            format!("{}: {}  ({})", r.name, trajectory.join(" → "), status)
+        })
+        .collect()
+}
+
+/// Metric section for the next iteration's prompt
+pub fn metrics_prompt_section(readings: &[MetricReading]) -> String {
+    let mut out = String::from("Benchmark metrics from the last test run:\n");
+    for r in readings {
+        let value = r.value.map(format_value).unwrap_or_else(|| "not found in output".to_string());
+        match r.target_label() {
+            Some(label) => out.push_str(&format!(
+                "- {} = {} (target {}, {})\n",
+                r.name,
+                value,
+                label,
+                if r.met() { "met" } else { "not met" }
+            )),
+            None => out.push_str(&format!("- {} = {}\n", r.name, value)),
+        }
+    }
+    out.push_str("Tests passing is not enough: keep improving the implementation until every target is met.\n");
+    out
+}
+
+fn format_value(v: f64) -> String {
+    format!("{}", (v * 1e6).round() / 1e6)
+}
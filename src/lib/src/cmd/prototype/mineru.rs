@@ -0,0 +1,718 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::fs;
+
+use crate::cmd::common::cache::{CacheCategory, cache_key, copy_dir_all, read_http_entry, touch, write_dir_entry, write_http_entry};
+use crate::cmd::papers::resolver::{needs_resolution, resolve};
+use crate::cmd::prototype::arxiv_source::{arxiv_id, ingest_structured};
+use crate::cmd::prototype::manifest::{PaperEntry, PaperFormat, PaperManifest, sha256_file};
+use crate::config::PaperConfig;
+
+/// Process all papers from configuration.
+///
+/// Papers given by DOI or title are first resolved to an arXiv or open-access PDF URL.
+/// A paper whose PDF hash and mineru version match `.qernel/papers/manifest.json`,
+/// and whose parsed output is still there, is neither downloaded nor parsed again.
+/// `force` redoes both for every paper, bypassing the shared cache.
+pub fn process_papers(papers: &[PaperConfig], cwd: &Path, force: bool) -> Result<()> {
+    let _span = tracing::info_span!("process_papers", count = papers.len(), force).entered();
+    if papers.is_empty() {
+        return Ok(());
+    }
+    let mut manifest = PaperManifest::load(cwd);
+    let mineru = mineru_version(cwd);
+    for paper in papers {
+        let key = paper.source();
+        let previous = if force { None } else { manifest.papers.get(&key).cloned() };
+
+        // DOIs and titles are looked up once; the URL they resolve to is kept in the manifest
+        let resolved_url = if needs_resolution(paper) {
+            match previous.as_ref().and_then(|e| e.resolved_url.clone()) {
+                Some(url) => Some(url),
+                None => match resolve(paper) {
+                    Ok(url) => {
+                        println!("🔎 Resolved {} to {}", key, url);
+                        Some(url)
+                    }
+                    Err(e) => {
+                        println!("⚠️  {:#}. Add a PDF of it to the project and list that path instead.", e);
+                        continue;
+                    }
+                },
+            }
+        } else {
+            None
+        };
+        let resolved;
+        let paper = match &resolved_url {
+            Some(url) => {
+                resolved = PaperConfig::from_url(url.clone());
+                &resolved
+            }
+            None => paper,
+        };
+
+        // arXiv LaTeX source or ar5iv HTML extracts more cleanly than the PDF; try those first
+        // (unless an earlier run already had to fall back to the PDF)
+        if let Some(id) = arxiv_id(&paper.url)
+            && previous.as_ref().is_none_or(|e| e.format != PaperFormat::Pdf)
+        {
+            if previous.is_some() && paper_parsed_dir(paper, cwd).is_dir() {
+                println!("📄 {}: unchanged since the last ingest, skipping", paper.url);
+                continue;
+            }
+            println!("📄 Fetching arXiv source for {}", id);
+            discard_parse(paper, cwd)?;
+            match ingest_structured(&id, &paper_parsed_dir(paper, cwd), !force) {
+                Ok(Some(structured)) => {
+                    println!("   Converted the {:?} source to Markdown", structured.format);
+                    update_spec_with_paper(&structured.content_list, cwd)?;
+                    manifest.papers.insert(key, PaperEntry {
+                        format: structured.format,
+                        pdf: String::new(),
+                        sha256: structured.sha256,
+                        parsed_at: Some(chrono::Utc::now()),
+                        mineru_version: None,
+                        resolved_url,
+                    });
+                    manifest.save(cwd)?;
+                    continue;
+                }
+                Ok(None) => println!("   No usable LaTeX or HTML source, falling back to the PDF"),
+                Err(e) => println!("⚠️  arXiv source ingestion failed ({:#}), falling back to the PDF", e),
+            }
+            discard_parse(paper, cwd)?;
+        }
+
+        let pdf_path = if !is_remote(paper) {
+            let pdf_abs_path = cwd.join(&paper.url);
+            if !pdf_abs_path.exists() {
+                println!("⚠️  Local PDF not found: {}", pdf_abs_path.display());
+                continue;
+            }
+            println!("📄 Processing local PDF: {}", pdf_abs_path.display());
+            pdf_abs_path
+        } else {
+            println!("📄 Processing remote paper: {}", paper.url);
+            let downloaded = paper_pdf_path(paper, cwd);
+            match &previous {
+                Some(entry) if downloaded.is_file() && sha256_file(&downloaded)? == entry.sha256 => downloaded,
+                _ => fetch_remote_paper(&paper.url, cwd, !force)?,
+            }
+        };
+
+        let sha256 = sha256_file(&pdf_path)?;
+        let unchanged = previous.is_some_and(|entry| {
+            entry.sha256 == sha256
+                && entry.parsed_at.is_some()
+                // Without a working mineru there is nothing newer to parse with
+                && (mineru.is_none() || entry.mineru_version == mineru)
+        }) && paper_parsed_dir(paper, cwd).is_dir();
+        if unchanged {
+            println!("   Unchanged since the last parse, skipping");
+            continue;
+        }
+
+        if force {
+            discard_parse(paper, cwd)?;
+        }
+        parse_pdf(&pdf_path, cwd, !force, mineru.as_deref())?;
+        manifest.papers.insert(key, PaperEntry {
+            format: PaperFormat::Pdf,
+            pdf: pdf_path.strip_prefix(cwd).unwrap_or(&pdf_path).to_string_lossy().to_string(),
+            sha256,
+            parsed_at: Some(chrono::Utc::now()),
+            mineru_version: mineru.clone(),
+            resolved_url,
+        });
+        manifest.save(cwd)?;
+    }
+    Ok(())
+}
+
+/// The project venv's mineru executable
+fn mineru_path(cwd: &Path) -> PathBuf {
+    if cfg!(windows) {
+        cwd.join(".qernel").join(".venv").join("Scripts").join("mineru.exe")
+    } else {
+        cwd.join(".qernel").join(".venv").join("bin").join("mineru")
+    }
+}
+
+/// `mineru --version` from the project venv, if it runs
+fn mineru_version(cwd: &Path) -> Option<String> {
+    let output = std::process::Command::new(mineru_path(cwd)).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().map(str::trim).rfind(|l| !l.is_empty()).map(str::to_string)
+}
+
+/// Whether a paper source is a URL to download rather than a local file
+pub fn is_remote(paper: &PaperConfig) -> bool {
+    paper.url.starts_with("http") || paper.url.starts_with("arxiv")
+}
+
+/// Where a paper's PDF lives: the file itself for local papers, or
+/// `.qernel/papers/<name>.pdf` once a remote one has been downloaded
+pub fn paper_pdf_path(paper: &PaperConfig, cwd: &Path) -> PathBuf {
+    if is_remote(paper) {
+        cwd.join(".qernel").join("papers").join(remote_pdf_filename(&normalize_arxiv_pdf_url(&paper.url)))
+    } else {
+        cwd.join(&paper.url)
+    }
+}
+
+/// mineru's output directory for a paper, `.qernel/parsed/<pdf stem>`
+pub fn paper_parsed_dir(paper: &PaperConfig, cwd: &Path) -> PathBuf {
+    let pdf = paper_pdf_path(paper, cwd);
+    let stem = pdf.file_stem().map(|s| s.to_os_string()).unwrap_or_default();
+    cwd.join(".qernel").join("parsed").join(stem)
+}
+
+/// Delete a paper's parsed output so stale files don't survive a fresh parse
+fn discard_parse(paper: &PaperConfig, cwd: &Path) -> Result<()> {
+    let parsed = paper_parsed_dir(paper, cwd);
+    if parsed.is_dir() {
+        fs::remove_dir_all(&parsed).with_context(|| format!("failed to remove {}", parsed.display()))?;
+    }
+    Ok(())
+}
+
+/// Process content files specified in the config
+pub fn process_content_files(content_files: &[String], cwd: &Path) -> Result<()> {
+    let _span = tracing::info_span!("process_content_files", count = content_files.len()).entered();
+    for content_file in content_files {
+        let content_path = cwd.join(content_file);
+        if content_path.exists() {
+            println!("Processing content file: {}", content_path.display());
+            update_spec_with_paper(&content_path, cwd)?;
+        } else {
+            println!("Content file not found: {}", content_path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Download a remote paper into `.qernel/papers` and return the PDF's path
+fn fetch_remote_paper(url: &str, cwd: &Path, use_cache: bool) -> Result<PathBuf> {
+    use indicatif::ProgressStyle;
+
+    let papers_dir = cwd.join(".qernel").join("papers");
+    fs::create_dir_all(&papers_dir)?;
+
+    let pb = crate::util::spinner();
+    pb.set_style(ProgressStyle::with_template("{spinner} Downloading remote paper...").unwrap());
+    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+
+    let downloaded_pdf = download_paper(url, &papers_dir, use_cache)?;
+    pb.finish_with_message("Paper downloaded");
+    Ok(downloaded_pdf)
+}
+
+fn download_paper(url: &str, papers_dir: &Path, use_cache: bool) -> Result<PathBuf> {
+    use reqwest::blocking::Client;
+    
+    // Normalize arXiv URLs to direct PDF endpoints
+    let effective_url = normalize_arxiv_pdf_url(url);
+
+    let pdf_path = papers_dir.join(remote_pdf_filename(&effective_url));
+
+    // Papers shared between projects are only downloaded once, then revalidated now and then
+    let cache_entry = format!("{}.pdf", cache_key(&[effective_url.as_bytes()]));
+    let cached = if use_cache { read_http_entry(CacheCategory::Papers, &cache_entry) } else { None };
+    if let Some(cached) = cached.as_ref().filter(|c| c.fresh) {
+        fs::write(&pdf_path, &cached.bytes).context("Failed to write PDF content")?;
+        return Ok(pdf_path);
+    }
+
+    // Download the PDF
+    let client = Client::new();
+    let request = client.get(&effective_url);
+    let request = match &cached {
+        Some(cached) => cached.conditional(request),
+        None => request,
+    };
+    let response = match (request.send(), cached) {
+        (Ok(response), Some(cached)) if response.status() == reqwest::StatusCode::NOT_MODIFIED => {
+            fs::write(&pdf_path, cached.revalidated()).context("Failed to write PDF content")?;
+            return Ok(pdf_path);
+        }
+        (Ok(response), _) => response,
+        // Offline: a stale copy beats no paper
+        (Err(e), Some(cached)) => {
+            tracing::debug!("revalidating {effective_url} failed, using the cached copy: {e}");
+            fs::write(&pdf_path, &cached.bytes).context("Failed to write PDF content")?;
+            return Ok(pdf_path);
+        }
+        (Err(e), None) => return Err(e).context("Failed to download paper"),
+    };
+    
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to download paper: HTTP {}", response.status());
+    }
+
+    // Validate that the response is a PDF
+    let content_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let headers = response.headers().clone();
+    let bytes = response.bytes().context("Failed to read response body")?;
+    let is_pdf_magic = bytes.len() >= 5 && &bytes[..5] == b"%PDF-";
+    let is_pdf_header = content_type.starts_with("application/pdf");
+    if !(is_pdf_magic || is_pdf_header) {
+        anyhow::bail!(
+            "Downloaded content is not a PDF. URL tried: {} (Content-Type: {}). If you passed an arXiv 'abs' link, use the PDF URL or let the CLI normalize it.",
+            effective_url,
+            content_type
+        );
+    }
+
+    write_http_entry(CacheCategory::Papers, &cache_entry, &bytes, &headers);
+
+    let mut file = std::fs::File::create(&pdf_path)
+        .context("Failed to create PDF file")?;
+    let mut content = std::io::Cursor::new(bytes);
+    std::io::copy(&mut content, &mut file)
+        .context("Failed to write PDF content")?;
+    
+    Ok(pdf_path)
+}
+
+/// File name for a downloaded paper, derived from its (normalized) URL
+fn remote_pdf_filename(effective_url: &str) -> String {
+    let last = effective_url.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    if effective_url.contains("arxiv.org") && !last.is_empty() {
+        format!("arxiv_{}.pdf", last.replace(".pdf", ""))
+    } else if last.to_lowercase().ends_with(".pdf") && last.len() > 4 {
+        last.to_string()
+    } else {
+        // Distinct URLs without a usable file name must not overwrite each other
+        format!("downloaded_paper_{}.pdf", &cache_key(&[effective_url.as_bytes()])[..8])
+    }
+}
+
+/// Parse a PDF with mineru (or restore a cached parse when `use_cache`) and add it to spec.md
+fn parse_pdf(pdf_path: &Path, cwd: &Path, use_cache: bool, mineru_version: Option<&str>) -> Result<()> {
+    use indicatif::ProgressStyle;
+    
+    // Create parsed directory inside .qernel
+    let parsed_dir = cwd.join(".qernel").join("parsed");
+    fs::create_dir_all(&parsed_dir)?;
+    
+    let pb = crate::util::spinner();
+    pb.set_style(ProgressStyle::with_template("{spinner} Processing PDF with mineru...").unwrap());
+    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+
+    let cache_entry = parse_cache_entry(pdf_path, mineru_version);
+    if use_cache && restore_cached_parse(pdf_path, cache_entry.as_deref(), &parsed_dir)? {
+        pb.finish_with_message("Using cached mineru output");
+        let content_json = find_content_json(&parsed_dir)?;
+        return update_spec_with_paper(&content_json, cwd);
+    }
+    
+    // Use the project's virtual environment mineru script directly
+    let output = std::process::Command::new(mineru_path(cwd))
+        .args([
+            "-p", pdf_path.to_str().unwrap(),
+            "-l", "en",
+            "-b", "pipeline", 
+            "-f", "true",
+            "-t", "true",
+            "-o", parsed_dir.to_str().unwrap(),
+        ])
+        .output()
+        .context("Failed to run mineru. Make sure it's installed in the project venv with: pip install mineru[core]")?;
+    
+    // Show mineru output to user
+    if !output.stdout.is_empty() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        println!("{}", stdout);
+    }
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.is_empty() {
+            println!("{}", stderr);
+        }
+        anyhow::bail!("mineru failed: {}", stderr);
+    }
+    
+    pb.finish_with_message("PDF processed");
+    println!("PDF processed with mineru");
+    store_parse_in_cache(pdf_path, cache_entry.as_deref(), &parsed_dir);
+    
+    // Find and process the content JSON
+    let content_json = find_content_json(&parsed_dir)?;
+    update_spec_with_paper(&content_json, cwd)?;
+    
+    Ok(())
+}
+
+/// Cache key for a PDF's mineru output: its content hash and the mineru version
+fn parse_cache_entry(pdf_path: &Path, mineru_version: Option<&str>) -> Option<String> {
+    let bytes = fs::read(pdf_path).ok()?;
+    Some(cache_key(&[b"mineru", mineru_version.unwrap_or("").as_bytes(), &bytes]))
+}
+
+/// Copy cached mineru output for this PDF into `parsed_dir/<pdf stem>`, if there is any
+fn restore_cached_parse(pdf_path: &Path, entry: Option<&str>, parsed_dir: &Path) -> Result<bool> {
+    let (Some(entry), Some(stem)) = (entry, pdf_path.file_stem()) else {
+        return Ok(false);
+    };
+    let cached = CacheCategory::Parsed.dir().join(entry);
+    if !cached.is_dir() {
+        return Ok(false);
+    }
+    copy_dir_all(&cached, &parsed_dir.join(stem))?;
+    touch(&cached);
+    Ok(true)
+}
+
+/// Save mineru's output directory for this PDF to the shared cache (best-effort)
+fn store_parse_in_cache(pdf_path: &Path, entry: Option<&str>, parsed_dir: &Path) {
+    let (Some(entry), Some(stem)) = (entry, pdf_path.file_stem()) else {
+        return;
+    };
+    let output = parsed_dir.join(stem);
+    if output.is_dir() {
+        write_dir_entry(CacheCategory::Parsed, entry, &output);
+    }
+}
+
+fn find_content_json(parsed_dir: &Path) -> Result<PathBuf> {
+    // Look strictly for content_list.json files recursively
+    let mut content_files = Vec::new();
+
+    fn find_json_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        if dir.is_dir() {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = entry.path();
+
+                if path.is_dir() {
+                    find_json_files(&path, files)?;
+                } else if path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.contains("content_list.json"))
+                    .unwrap_or(false) {
+                    files.push(path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    find_json_files(parsed_dir, &mut content_files)?;
+
+    if content_files.is_empty() {
+        anyhow::bail!("No content_list.json found in parsed directory");
+    }
+
+    // Use the most recent one
+    use std::time::SystemTime;
+    content_files.sort_by_key(|p| {
+        fs::metadata(p).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH)
+    });
+    Ok(content_files.last().cloned().unwrap())
+}
+
+fn normalize_arxiv_pdf_url(url: &str) -> String {
+    // Convert /abs/<id> -> /pdf/<id>.pdf and ensure .pdf suffix on /pdf
+    if let Ok(parsed) = url::Url::parse(url) {
+        if parsed.domain().map(|d| d.contains("arxiv.org")).unwrap_or(false) {
+            let mut p = parsed.clone();
+            let path = p.path();
+            if let Some(pos) = path.find("/abs/") {
+                let id = &path[pos + 5..];
+                p.set_path(&format!("/pdf/{}.pdf", id.trim_end_matches('.')));
+                return p.to_string();
+            }
+            if path.starts_with("/pdf/") && !path.ends_with(".pdf") {
+                let mut newp = path.to_string();
+                newp.push_str(".pdf");
+                p.set_path(&newp);
+                return p.to_string();
+            }
+            return p.to_string();
+        }
+    }
+    url.to_string()
+}
+
+fn update_spec_with_paper(content_json_path: &Path, cwd: &Path) -> Result<()> {
+    // Read the content JSON
+    let content = fs::read_to_string(content_json_path)
+        .context("Failed to read content JSON")?;
+    
+    let content_data: serde_json::Value = serde_json::from_str(&content)
+        .context("Failed to parse content JSON")?;
+
+    // Render mineru's content list as readable Markdown; fall back to the raw JSON
+    // for anything that doesn't look like a content list.
+    let paper_text = match render_content_list(&content_data) {
+        Some(markdown) => markdown,
+        None => serde_json::to_string_pretty(&content_data)
+            .context("Failed to serialize content JSON")?,
+    };
+    
+    // Note: Images are now handled directly in the agent request, not in spec.md
+    
+    // Read existing .qernel/spec.md
+    let spec_path = cwd.join(".qernel").join("spec.md");
+    let mut spec_content = if spec_path.exists() {
+        fs::read_to_string(&spec_path)?
+    } else {
+        String::new()
+    };
+    
+    // Add/replace the Paper Content section idempotently
+    let heading = "## Paper Content";
+    let new_section = format!("{heading}\n\n{}\n", paper_text);
+    if let Some(start) = spec_content.find(heading) {
+         let after = start + heading.len();
+         let end = spec_content[after..]
+             .find("\n## ")
+             .map(|i| after + i)
+             .unwrap_or(spec_content.len());
+         spec_content.replace_range(start..end, &new_section);
+    } else {
+         if !spec_content.ends_with('\n') { spec_content.push('\n'); }
+         spec_content.push('\n');
+         spec_content.push_str(&new_section);
+    }
+    
+    // Images are now handled directly in the agent request, not added to spec.md
+    
+    fs::write(&spec_path, spec_content)?;
+    
+    println!("Updated .qernel/spec.md with paper content");
+    
+    Ok(())
+}
+
+
+/// Convert mineru's `content_list.json` (an array of typed blocks) into Markdown.
+///
+/// Text becomes prose (with headings from `text_level`), tables become Markdown tables,
+/// equations are kept as display math, and figure captions stay next to their image
+/// reference. Layout noise (headers, footers, page numbers, discarded blocks) is dropped.
+/// Returns `None` when the JSON does not follow the content list schema.
+fn render_content_list(data: &serde_json::Value) -> Option<String> {
+    let blocks = data.as_array()?;
+    if blocks.is_empty() || !blocks.iter().all(|b| b.get("type").and_then(|t| t.as_str()).is_some()) {
+        return None;
+    }
+
+    let mut out = String::new();
+    for block in blocks {
+        let rendered = match block["type"].as_str().unwrap_or("") {
+            "text" => render_text_block(block),
+            "equation" | "interline_equation" => render_equation_block(block),
+            "table" => render_table_block(block),
+            "image" => render_image_block(block),
+            "code" | "algorithm" => render_code_block(block),
+            "list" => render_list_block(block),
+            // header, footer, page_number, aside_text, discarded, ...
+            _ => None,
+        };
+        if let Some(text) = rendered {
+            out.push_str(text.trim_end());
+            out.push_str("\n\n");
+        }
+    }
+    Some(out.trim_end().to_string())
+}
+
+/// `(img_path, caption)` for every image block in a content list, in document order.
+/// Images without a caption get an empty one.
+pub fn figure_captions(data: &serde_json::Value) -> Vec<(String, String)> {
+    data.as_array()
+        .into_iter()
+        .flatten()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("image"))
+        .filter_map(|b| {
+            let path = block_text(b, "img_path")?;
+            let caption = block_lines(b, "img_caption").or_else(|| block_lines(b, "image_caption")).unwrap_or_default();
+            Some((path, caption))
+        })
+        .collect()
+}
+
+fn block_text(block: &serde_json::Value, key: &str) -> Option<String> {
+    let text = block.get(key)?.as_str()?.trim();
+    if text.is_empty() { None } else { Some(text.to_string()) }
+}
+
+/// Captions and footnotes are stored as arrays of strings; join them into one line.
+fn block_lines(block: &serde_json::Value, key: &str) -> Option<String> {
+    let joined = block
+        .get(key)?
+        .as_array()?
+        .iter()
+        .filter_map(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if joined.is_empty() { None } else { Some(joined) }
+}
+
+fn render_text_block(block: &serde_json::Value) -> Option<String> {
+    let text = block_text(block, "text")?;
+    // A lone page number or running header that slipped through as plain text
+    if text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match block.get("text_level").and_then(|l| l.as_u64()) {
+        Some(level) if level > 0 => {
+            // Level 1 maps to "###" so paper headings nest under the spec's "## Paper Content"
+            let hashes = "#".repeat((level as usize + 2).min(6));
+            Some(format!("{} {}", hashes, text))
+        }
+        _ => Some(text),
+    }
+}
+
+fn render_equation_block(block: &serde_json::Value) -> Option<String> {
+    let text = block_text(block, "text")?;
+    if text.starts_with("$$") {
+        Some(text)
+    } else {
+        Some(format!("$$\n{}\n$$", text.trim_matches('$').trim()))
+    }
+}
+
+fn render_image_block(block: &serde_json::Value) -> Option<String> {
+    let caption = block_lines(block, "img_caption").or_else(|| block_lines(block, "image_caption"));
+    let footnote = block_lines(block, "img_footnote").or_else(|| block_lines(block, "image_footnote"));
+    let path = block_text(block, "img_path");
+    if caption.is_none() && path.is_none() {
+        return None;
+    }
+    let mut out = String::new();
+    if let Some(p) = &path {
+        out.push_str(&format!("![{}]({})\n", caption.as_deref().unwrap_or("figure"), p));
+    }
+    if let Some(c) = &caption {
+        out.push_str(&format!("*{}*\n", c));
+    }
+    if let Some(f) = &footnote {
+        out.push_str(&format!("{}\n", f));
+    }
+    Some(out)
+}
+
+fn render_table_block(block: &serde_json::Value) -> Option<String> {
+    let caption = block_lines(block, "table_caption");
+    let footnote = block_lines(block, "table_footnote");
+    let table = block_text(block, "table_body")
+        .and_then(|html| html_table_to_markdown(&html))
+        .or_else(|| block_text(block, "img_path").map(|p| format!("![table]({})", p)));
+    if caption.is_none() && table.is_none() {
+        return None;
+    }
+    let mut out = String::new();
+    if let Some(c) = &caption {
+        out.push_str(&format!("**{}**\n\n", c));
+    }
+    if let Some(t) = &table {
+        out.push_str(t);
+        out.push('\n');
+    }
+    if let Some(f) = &footnote {
+        out.push_str(&format!("\n{}\n", f));
+    }
+    Some(out)
+}
+
+fn render_code_block(block: &serde_json::Value) -> Option<String> {
+    let body = block_text(block, "code_body").or_else(|| block_text(block, "text"))?;
+    let caption = block_lines(block, "code_caption");
+    let mut out = String::new();
+    if let Some(c) = &caption {
+        out.push_str(&format!("**{}**\n\n", c));
+    }
+    out.push_str(&format!("```\n{}\n```", body));
+    Some(out)
+}
+
+fn render_list_block(block: &serde_json::Value) -> Option<String> {
+    let items = block.get("list_items").and_then(|v| v.as_array());
+    match items {
+        Some(items) => {
+            let lines = items
+                .iter()
+                .filter_map(|i| i.as_str())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| format!("- {}", s.trim_start_matches(['-', '•', '*']).trim()))
+                .collect::<Vec<_>>();
+            if lines.is_empty() { None } else { Some(lines.join("\n")) }
+        }
+        None => block_text(block, "text"),
+    }
+}
+
+/// Convert the simple HTML tables mineru emits (`<table><tr><td>..`) to a Markdown table.
+/// Cell spans are flattened; rows are padded to the widest row.
+fn html_table_to_markdown(html: &str) -> Option<String> {
+    let row_re = regex::Regex::new(r"(?is)<tr[^>]*>(.*?)</tr>").ok()?;
+    let cell_re = regex::Regex::new(r"(?is)<t[hd][^>]*>(.*?)</t[hd]>").ok()?;
+    let tag_re = regex::Regex::new(r"(?s)<[^>]+>").ok()?;
+    let entity_re = regex::Regex::new(r"&(amp|lt|gt|quot|nbsp);").ok()?;
+
+    let rows: Vec<Vec<String>> = row_re
+        .captures_iter(html)
+        .map(|row| {
+            cell_re
+                .captures_iter(&row[1])
+                .map(|cell| {
+                    let text = tag_re.replace_all(&cell[1], " ");
+                    // One pass, so an escaped entity such as `&amp;lt;` stays `&lt;`
+                    let text = entity_re.replace_all(&text, |entity: &regex::Captures| match &entity[1] {
+                        "amp" => "&",
+                        "lt" => "<",
+                        "gt" => ">",
+                        "quot" => "\"",
+                        _ => " ",
+                    });
+                    let text = text.replace('|', "\\|");
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                })
+                .collect()
+        })
+        .filter(|cells: &Vec<String>| !cells.is_empty())
+        .collect();
+
+    let width = rows.iter().map(|r| r.len()).max()?;
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let mut cells = row.clone();
+        cells.resize(width, String::new());
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if i == 0 {
+            out.push_str(&format!("|{}\n", " --- |".repeat(width)));
+        }
+    }
+    Some(out.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_tables_become_markdown_with_entities_decoded_once() {
+        let html = "<table><tr><th>Gate</th><th>Error</th></tr>\
+                    <tr><td>CX &amp; CZ</td><td>&lt;1e-3</td></tr>\
+                    <tr><td>&amp;lt;b&amp;gt;</td><td>a|b</td></tr></table>";
+        assert_eq!(
+            html_table_to_markdown(html).unwrap(),
+            "| Gate | Error |\n| --- | --- |\n| CX & CZ | <1e-3 |\n| &lt;b&gt; | a\\|b |"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+pub mod agent;
+pub mod approval;
+pub mod artifacts;
+pub mod arxiv_source;
+pub mod audit;
+pub mod budget;
+pub mod charts;
+pub mod checkpoint;
+pub mod command_policy;
+pub mod console;
+pub mod constraints;
+pub mod coverage;
+pub mod environment;
+pub mod events;
+pub mod exec_events;
+pub mod figures;
+pub mod interrupt;
+pub mod logging;
+pub mod manifest;
+pub mod metrics;
+pub mod mineru;
+pub mod network;
+pub mod patch_stats;
+pub mod plan;
+pub mod prompts;
+pub mod pull_request;
+pub mod report;
+pub mod sdk;
+pub mod slurm;
+pub mod snapshots;
+pub mod suites;
+pub mod summary;
+pub mod transaction;
+pub mod turns;
+pub mod validation;
+
+use anyhow::{Context, Result};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::config::{ConfigResolver, load_config};
+use crate::cmd::prototype::approval::{ApprovalMode, Approvals};
+use crate::cmd::prototype::events::ProgressSink;
+use crate::cmd::prototype::logging::{debug_log, end_debug_logging, init_debug_logging};
+use crate::cmd::prototype::summary::{RunSummary, last_run_path};
+
+/// What to run, as given by `qernel prototype`'s flags; `None` falls back to the settings
+#[derive(Debug, Clone, Default)]
+pub struct PrototypeOptions {
+    pub cwd: PathBuf,
+    pub model: Option<String>,
+    pub max_iters: Option<u32>,
+    pub debug: bool,
+    pub spec_only: bool,
+    pub spec_and_content_only: bool,
+    pub force_reparse: bool,
+    pub plan_first: bool,
+    pub report: bool,
+    pub resume: bool,
+    /// Never wait for input; always the case without console output or a terminal
+    pub yes: bool,
+    pub api_key_env: Option<String>,
+    /// Which actions wait for the user's go-ahead (`--approval`); `None` leaves it to `agent.command_policy`
+    pub approval: Option<ApprovalMode>,
+    /// Print test output as it arrives (`--stream-tests`); only with console output
+    pub stream_tests: bool,
+}
+
+/// Main prototype handler - orchestrates the entire prototype workflow
+#[allow(clippy::too_many_arguments)]
+pub fn handle_prototype(cwd: String, model: Option<String>, max_iters: Option<u32>, debug: bool, spec_only: bool, spec_and_content_only: bool, force_reparse: bool, plan_first: bool, report: bool, resume: bool, yes: bool, output_json: Option<PathBuf>, api_key_env: Option<String>, approval: Option<ApprovalMode>, stream_tests: bool, pr_remote: Option<String>) -> Result<()> {
+    // Resolved now: the agent loop changes into the project directory
+    let output_json = output_json.map(std::path::absolute).transpose().context("invalid --output-json path")?;
+    let project = std::path::absolute(&cwd).context("invalid --cwd path")?;
+    let options = PrototypeOptions { cwd: PathBuf::from(cwd), model, max_iters, debug, spec_only, spec_and_content_only, force_reparse, plan_first, report, resume, yes, api_key_env, approval, stream_tests };
+    let (summary, result) = run(options, true, &mut events::ConsoleSink::new(debug).streamed(stream_tests));
+    if let Some(path) = output_json {
+        match summary.write(&path) {
+            Ok(()) => println!("Run summary written to {}", path.display()),
+            // The run's own error matters more than a failed summary write
+            Err(e) if result.is_err() => eprintln!("Could not write the run summary: {:#}", e),
+            Err(e) => return Err(e),
+        }
+    }
+    result?;
+    if let Some(remote) = pr_remote {
+        pull_request::create_pull_request(&project, &summary, &remote)?;
+    }
+    Ok(())
+}
+
+/// Run the workflow, reporting progress to `sink` and also to stdout when `console` is set.
+/// The summary is kept as `.qernel/last_run.json` and returned with the run's result.
+pub fn run(options: PrototypeOptions, console: bool, sink: &mut dyn ProgressSink) -> (RunSummary, Result<()>) {
+    // A run changes the process's working directory and owns the audit log, the run log and
+    // the usage counters, so runs in one process take turns
+    static RUNS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _turn = RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    let caller_dir = std::env::current_dir().ok();
+    crate::cmd::common::usage::reset();
+    let cwd_abs = options.cwd.canonicalize().unwrap_or_else(|_| options.cwd.clone());
+    let started = Instant::now();
+    let mut summary = RunSummary::default();
+    let result = run_workflow(&cwd_abs, options, console, sink, &mut summary);
+    summary.finish(&result, &cwd_abs, started);
+    audit::record("run_finished", serde_json::json!({
+        "outcome": summary.outcome,
+        "iterations": summary.iterations,
+        "final_exit_code": summary.final_exit_code,
+        "error": summary.error,
+    }));
+    audit::close();
+    end_debug_logging();
+    if cwd_abs.join(".qernel").is_dir()
+        && let Err(e) = summary.write(&last_run_path(&cwd_abs))
+    {
+        eprintln!("Could not record the run in .qernel/last_run.json: {:#}", e);
+    }
+    if let Some(dir) = caller_dir {
+        let _ = std::env::set_current_dir(dir);
+    }
+    (summary, result)
+}
+
+fn run_workflow(cwd_abs: &Path, options: PrototypeOptions, console: bool, sink: &mut dyn ProgressSink, summary: &mut RunSummary) -> Result<()> {
+    let PrototypeOptions { model, max_iters, debug, spec_only, spec_and_content_only, force_reparse, plan_first, report, resume, yes, api_key_env, approval, stream_tests, .. } = options;
+    
+    // Load configuration from .qernel
+    let config_path = cwd_abs.join(".qernel").join("qernel.yaml");
+    let mut config = load_config(&config_path)?;
+    
+    // Model and iteration limit: flags > env > qernel.yaml > user config > defaults
+    let resolver = ConfigResolver::load(cwd_abs)?
+        .with_flag("agent.model", model)
+        .with_flag("agent.max_iterations", max_iters.map(|n| n.to_string()));
+    if let Some(model) = resolver.get("agent.model")? {
+        config.agent.model = model;
+    }
+    if let Some(max_iters) = resolver.get_u32("agent.max_iterations")? {
+        config.agent.max_iterations = max_iters;
+    }
+    if let Some(max_tool_calls) = resolver.get_u32("agent.max_tool_calls")? {
+        config.agent.max_tool_calls = max_tool_calls.max(1);
+    }
+    if let Some(max_patch_fuzz) = resolver.get_u32("agent.max_patch_fuzz")? {
+        config.agent.max_patch_fuzz = max_patch_fuzz;
+    }
+    // Shorthand for agent.constraints.max_patch_lines; the lower of the two applies
+    if let Some(max) = resolver.get_usize("agent.max_patch_lines")? {
+        let limit = &mut config.agent.constraints.max_patch_lines;
+        *limit = Some(limit.map_or(max, |l| l.min(max)));
+    }
+    config.agent.reviewer_model = resolver.get("agent.reviewer_model")?.filter(|m| !m.trim().is_empty());
+    config.agent.max_wall_clock_minutes = resolver.get_u32("agent.max_wall_clock_minutes")?;
+    config.agent.max_cost_usd = resolver.get_f64("agent.max_cost_usd")?;
+    config.agent.sampling.temperature = resolver.get_f64("agent.temperature")?;
+    config.agent.sampling.top_p = resolver.get_f64("agent.top_p")?;
+    config.agent.sampling.seed = resolver.get_u64("agent.seed")?;
+    config.agent.sampling.validate()?;
+    summary.sampling = config.agent.sampling;
+    if let Some(test_cmd) = resolver.get("benchmarks.test_command")? {
+        config.benchmarks.test_command = test_cmd;
+    }
+    let test_output_chars = resolver.get_usize("prototype.max_chars")?.unwrap_or(crate::config::DEFAULT_TEST_OUTPUT_CHARS).max(1);
+    let suite_workers = resolver.get_usize("prototype.workers")?.unwrap_or(1).max(1);
+    // env_files and env: are read again for every command; fail now rather than mid-run
+    environment::project_env(&config, cwd_abs)?;
+    
+    // Initialize debug logging
+    let debug_file = init_debug_logging(cwd_abs, debug)?;
+    
+    debug_log(&debug_file, "🔬 Starting prototype implementation...", debug);
+    if let Some(logger) = &debug_file {
+        if console {
+            println!("Run log: {} (view with `qernel logs show`)", logger.path().display());
+        }
+        summary.run_log = Some(logger.path().to_path_buf());
+    }
+    
+    // Conditional ingestion based on flags (see `qernel ingest`); a resumed run already has its inputs
+    let spec_only = spec_only || resume;
+    if !spec_only {
+        crate::cmd::ingest::ingest(&config, cwd_abs, !spec_and_content_only, true, force_reparse)?;
+    }
+    
+    // Read .qernel/spec.md for implementation goals
+    let goal = read_spec_goal(cwd_abs)?;
+    
+    // Read the test suites (or the single benchmark command) from config
+    let suites = suites::test_suites(&config.benchmarks)?;
+    artifacts::validate_artifact_globs(&config.benchmarks.artifacts)?;
+    
+    // Run agent loop
+    debug_log(&debug_file, "🤖 Starting agent optimization...", debug);
+    agent::run_agent_loop(
+        cwd_abs.to_string_lossy().to_string(),
+        goal,
+        suites,
+        config.benchmarks.metrics.clone(),
+        config.benchmarks.coverage.clone(),
+        config.benchmarks.artifacts.clone(),
+        config.benchmarks.limits.resource_limits(),
+        config.benchmarks.use_pty,
+        config.benchmarks.slurm.clone(),
+        config.agent.images.clone(),
+        config.agent.max_images as usize,
+        config.agent.model,
+        config.agent.max_iterations,
+        config.agent.max_tool_calls,
+        config.agent.max_patch_fuzz as usize,
+        budget::Budget::new(config.agent.max_wall_clock_minutes, config.agent.max_cost_usd),
+        // CI and pipes have no one to answer prompts
+        yes || !console || !std::io::stdin().is_terminal(),
+        debug,
+        config.agent.reviewer_model,
+        config.agent.post_patch_hooks,
+        config.agent.constraints,
+        Approvals { mode: approval, command_policy: config.agent.command_policy },
+        config.agent.sampling,
+        stream_tests && console,
+        test_output_chars,
+        suite_workers,
+        plan_first,
+        report || config.agent.report,
+        resume,
+        api_key_env,
+        !console,
+        sink,
+        summary,
+    )
+}
+
+/// Quickstart: scaffold a project for an arXiv URL then run prototype
+#[allow(clippy::too_many_arguments)]
+pub fn quickstart_arxiv(url: String, model: Option<String>, max_iters: Option<u32>, debug: bool, plan_first: bool, report: bool, yes: bool, output_json: Option<PathBuf>, api_key_env: Option<String>, approval: Option<ApprovalMode>, stream_tests: bool, pr_remote: Option<String>) -> Result<()> {
+    // 1) Derive folder name from arXiv id
+    let id = parse_arxiv_id(&url).unwrap_or_else(|| "paper".to_string());
+    let folder = format!("arxiv-{}", id);
+
+    // 2) Scaffold new project with template
+    //    (the paper_url variable adds the arXiv URL to .qernel/qernel.yaml)
+    crate::cmd::new::handle_new(
+        folder.clone(),
+        Some(crate::cmd::new::templates::DEFAULT_TEMPLATE.to_string()),
+        false,
+        vec![("paper_url".to_string(), url)],
+        false,
+        false,
+    )?;
+
+    // 3) Run prototype in that folder
+    handle_prototype(folder, model, max_iters, debug, false, false, false, plan_first, report, false, yes, output_json, api_key_env, approval, stream_tests, pr_remote)
+}
+
+fn parse_arxiv_id(url: &str) -> Option<String> {
+    // Handles /abs/<id>[vN], /pdf/<id>[vN].pdf, or raw ids
+    let url = url.trim();
+    if let Some(idx) = url.find("arxiv.org/") {
+        let rest = &url[idx..];
+        let parts: Vec<&str> = rest.split('/').collect();
+        if let Some(pos) = parts.iter().position(|p| *p == "abs" || *p == "pdf") {
+            if let Some(idpart) = parts.get(pos + 1) {
+                let mut id = idpart.to_string();
+                if let Some(dotpdf) = id.find(".pdf") { id.truncate(dotpdf); }
+                return Some(id);
+            }
+        }
+    }
+    // Fallback: if looks like an id
+    let clean = url.trim_end_matches(".pdf");
+    if clean.chars().all(|c| c.is_ascii_alphanumeric() || c == '/' || c == '.' || c == 'v') {
+        return Some(clean.to_string());
+    }
+    None
+}
+
+fn read_spec_goal(cwd: &Path) -> Result<String> {
+    let spec_path = cwd.join(".qernel").join("spec.md");
+    if !spec_path.exists() {
+        anyhow::bail!(".qernel/spec.md not found. Please create a project with 'qernel new --template' first.");
+    }
+
+    let spec_content = std::fs::read_to_string(&spec_path)
+        .context("Failed to read .qernel/spec.md")?;
+
+    Ok(spec_content)
+}
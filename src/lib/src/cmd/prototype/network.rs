@@ -0,0 +1,664 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::fs;
+use base64::{Engine as _, engine::general_purpose};
+
+use crate::cmd::common::endpoint::Backend;
+use crate::cmd::common::mock;
+use crate::cmd::common::models::capabilities;
+use crate::cmd::common::network::send_rate_limited;
+use crate::cmd::common::usage;
+use crate::cmd::prototype::audit;
+use crate::cmd::prototype::logging::{debug_log, RunLogger};
+use crate::config::{ImageConfig, ImageFormat, Sampling};
+use crate::error::QernelError;
+use codex_core::exec::truncate_utf8;
+use codex_core::plan_tool::UpdatePlanArgs;
+use codex_core::tool_add_dependency::AddDependencyArgs;
+use codex_core::tool_apply_patch::ApplyPatchArgs;
+use codex_core::tool_shell::ShellArgs;
+
+/// What the model asked for; deserialized from the `action` tag of a JSON step or built
+/// from a tool call's arguments
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AiAction {
+    ApplyPatch { patch: String },
+    /// An empty command runs the project's test command
+    Shell {
+        #[serde(default)]
+        command: String,
+    },
+    /// Pin a package in requirements.txt and install it into the project venv
+    AddDependency {
+        package: String,
+        #[serde(default)]
+        version: Option<String>,
+    },
+    /// Full plan with step statuses
+    Plan {
+        #[serde(alias = "plan")]
+        steps: Vec<codex_core::plan_tool::PlanItemArg>,
+    },
+    /// A final answer without a tool call; ends the turn
+    #[serde(alias = "message")]
+    Done {
+        #[serde(default)]
+        summary: String,
+    },
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug)]
+pub struct AiStep {
+    #[serde(flatten)]
+    pub action: AiAction,
+    pub rationale: Option<String>,
+    /// The tool call this step came from; its output goes back to the model and the
+    /// turn continues. Steps without one (JSON text, final messages) end the turn.
+    #[serde(default)]
+    #[schemars(skip)]
+    pub call: Option<ToolCall>,
+    /// Responses API id of the response, continued with `previous_response_id`
+    #[serde(default)]
+    #[schemars(skip)]
+    pub response_id: Option<String>,
+}
+
+/// Requests to fix a reply that does not match the step schema before giving up
+const MAX_REFORMAT_RETRIES: u32 = 2;
+
+/// JSON schema of [`AiStep`] as the model writes it, for structured output
+pub fn step_schema() -> serde_json::Value {
+    let mut schema = serde_json::to_value(codex_core::openai_tools::JsonSchema::for_type::<AiStep>()).unwrap_or_default();
+    // The action's own fields live in the `oneOf` variants, not in the root's properties
+    if let Some(root) = schema.as_object_mut() {
+        root.remove("additionalProperties");
+    }
+    schema
+}
+
+/// Offer the tools, or without any (models that cannot call tools) require each reply to be
+/// a JSON step matching [`step_schema`]
+fn with_tools_or_schema(body: &mut serde_json::Value, tools: serde_json::Value) {
+    if tools.as_array().is_some_and(|t| t.is_empty()) {
+        body["text"] = json!({
+            "format": {"type": "json_schema", "name": "ai_step", "schema": step_schema(), "strict": false}
+        });
+    } else {
+        body["tools"] = tools;
+        body["tool_choice"] = json!("auto");
+        body["parallel_tool_calls"] = json!(false);
+    }
+}
+
+impl AiStep {
+    fn new(action: AiAction, call: Option<ToolCall>) -> Self {
+        Self { action, rationale: None, call, response_id: None }
+    }
+}
+
+impl AiAction {
+    /// The `action` tag, for logs and messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            AiAction::ApplyPatch { .. } => "apply_patch",
+            AiAction::Shell { .. } => "shell",
+            AiAction::AddDependency { .. } => "add_dependency",
+            AiAction::Plan { .. } => "plan",
+            AiAction::Done { .. } => "done",
+        }
+    }
+}
+
+/// A tool call awaiting its output
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    /// A freeform (`custom_tool_call`) call rather than a function call
+    #[serde(default)]
+    pub custom: bool,
+}
+
+/// Make OpenAI API request and parse response
+pub fn make_openai_request(
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+    tools: serde_json::Value,
+    sampling: &Sampling,
+    debug_file: &Option<RunLogger>,
+) -> Result<AiStep> {
+    make_openai_request_with_images(api_key, model, system_prompt, user_prompt, tools, sampling, debug_file, None, &ImageConfig::default())
+}
+
+/// Make OpenAI API request with optional images, preprocessed per `image_config`
+#[allow(clippy::too_many_arguments)]
+pub fn make_openai_request_with_images(
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+    tools: serde_json::Value,
+    sampling: &Sampling,
+    debug_file: &Option<RunLogger>,
+    images: Option<Vec<String>>,
+    image_config: &ImageConfig,
+) -> Result<AiStep> {
+    if mock::enabled() {
+        debug_log(debug_file, &format!("[ai] mock provider: serving a canned step from {}", mock::fixtures_dir().display()), debug_file.is_some());
+        let step = mock::next_step()?;
+        audit::record_model_request("mock", None, &step);
+        return serde_json::from_value(step).context("mock steps.json: invalid step");
+    }
+
+    // Calculate total context size for warning
+    let total_context_size = system_prompt.len() + user_prompt.len();
+    debug_log(debug_file, &format!("[ai] system prompt length: {} chars", system_prompt.len()), debug_file.is_some());
+    debug_log(debug_file, &format!("[ai] user prompt length: {} chars", user_prompt.len()), debug_file.is_some());
+    debug_log(debug_file, &format!("[ai] total context size: {} chars", total_context_size), debug_file.is_some());
+
+    // Build the input array with optional images
+    let mut input_array = vec![
+        json!({"role": "system", "content": system_prompt}),
+    ];
+    
+    // Add user content with optional images
+    if let Some(image_paths) = &images {
+        if !image_paths.is_empty() {
+            debug_log(debug_file, &format!("[ai] attempting to encode {} images for request", image_paths.len()), debug_file.is_some());
+            
+            let mut user_content = vec![json!({"type": "input_text", "text": user_prompt})];
+            let mut successful_images = 0;
+            
+            // Add each image to the content as base64 data URLs
+            for image_path in image_paths {
+                match encode_image_to_base64(image_path, image_config, debug_file) {
+                    Ok(None) => {}
+                    Ok(Some(data_url)) => {
+                        user_content.push(json!({
+                            "type": "input_image",
+                            "image_url": data_url
+                        }));
+                        successful_images += 1;
+                        debug_log(debug_file, &format!("[ai] successfully encoded image: {}", image_path), debug_file.is_some());
+                    }
+                    Err(e) => {
+                        debug_log(debug_file, &format!("[ai] failed to encode image {}: {}", image_path, e), debug_file.is_some());
+                        // Continue with other images even if one fails
+                    }
+                }
+            }
+            
+            debug_log(debug_file, &format!("[ai] successfully encoded {} out of {} images for model request", successful_images, image_paths.len()), debug_file.is_some());
+            
+            input_array.push(json!({
+                "role": "user",
+                "content": user_content
+            }));
+        } else {
+            input_array.push(json!({"role": "user", "content": user_prompt}));
+        }
+    } else {
+        input_array.push(json!({"role": "user", "content": user_prompt}));
+    }
+    
+    let mut request_body = json!({
+        "model": model,
+        "input": input_array
+    });
+    with_tools_or_schema(&mut request_body, tools);
+    sampling.apply_to_responses(&mut request_body, &capabilities(model));
+    send_responses_request(api_key, &request_body, debug_file)
+}
+
+/// Return a tool call's output to the model, continuing the response that made the call
+#[allow(clippy::too_many_arguments)]
+pub fn send_tool_output(
+    api_key: &str,
+    model: &str,
+    tools: serde_json::Value,
+    previous_response_id: &str,
+    call: &ToolCall,
+    output: &str,
+    sampling: &Sampling,
+    debug_file: &Option<RunLogger>,
+) -> Result<AiStep> {
+    if mock::enabled() {
+        let step = mock::next_step()?;
+        audit::record_model_request("mock", None, &step);
+        return serde_json::from_value(step).context("mock steps.json: invalid step");
+    }
+    debug_log(debug_file, &format!("[ai] tool output for {} ({} chars)", call.id, output.len()), debug_file.is_some());
+    let item_type = if call.custom { "custom_tool_call_output" } else { "function_call_output" };
+    let mut request_body = json!({
+        "model": model,
+        "tools": tools,
+        "tool_choice": "auto",
+        "parallel_tool_calls": false,
+        "previous_response_id": previous_response_id,
+        "input": [{"type": item_type, "call_id": call.id, "output": output}]
+    });
+    sampling.apply_to_responses(&mut request_body, &capabilities(model));
+    send_responses_request(api_key, &request_body, debug_file)
+}
+
+/// POST to the Responses API and parse the next step out of the response. A structured
+/// (JSON schema) reply that does not parse is sent back to be reformatted.
+fn send_responses_request(api_key: &str, request_body: &serde_json::Value, debug_file: &Option<RunLogger>) -> Result<AiStep> {
+    let mut body = post_responses(api_key, request_body, debug_file)?;
+    let Some(format) = request_body.get("text") else {
+        let mut step = parse_ai_response(&body, debug_file)?;
+        step.response_id = body.get("id").and_then(|v| v.as_str()).map(str::to_string);
+        return Ok(step);
+    };
+    let mut retries = 0;
+    loop {
+        let text = message_text(&body);
+        let error = match serde_json::from_str::<AiStep>(text.trim()) {
+            Ok(mut step) => {
+                step.response_id = body.get("id").and_then(|v| v.as_str()).map(str::to_string);
+                return Ok(step);
+            }
+            Err(e) => e,
+        };
+        debug_log(debug_file, &format!("[ai] structured reply is not a valid step ({}): {}", error, truncate_utf8(&text, 500)), debug_file.is_some());
+        if retries == MAX_REFORMAT_RETRIES {
+            anyhow::bail!("the model's reply did not match the step schema after {} reformat requests: {}", MAX_REFORMAT_RETRIES, error);
+        }
+        retries += 1;
+        let mut retry = json!({
+            "model": request_body["model"],
+            "previous_response_id": body.get("id").cloned().unwrap_or_default(),
+            "input": [{
+                "role": "user",
+                "content": format!(
+                    "Your last reply is not a valid step: {}. Reply again with only one JSON object that matches the ai_step schema.",
+                    error
+                ),
+            }],
+            "text": format,
+        });
+        for key in ["temperature", "top_p"] {
+            if let Some(value) = request_body.get(key) {
+                retry[key] = value.clone();
+            }
+        }
+        body = post_responses(api_key, &retry, debug_file)?;
+    }
+}
+
+/// POST to the Responses API with retries; the response body once it succeeded
+fn post_responses(api_key: &str, request_body: &serde_json::Value, debug_file: &Option<RunLogger>) -> Result<serde_json::Value> {
+    use reqwest::blocking::Client;
+    
+    let backend = Backend::active()?;
+    // Validate API key
+    if api_key.is_empty() {
+        anyhow::bail!("{} API key is empty", backend.label());
+    }
+    // Azure keys are plain hex; only OpenAI's have a recognizable prefix
+    if backend == Backend::Openai && !api_key.starts_with("sk-") {
+        anyhow::bail!("OPENAI_API_KEY doesn't look like a valid OpenAI API key (should start with 'sk-')");
+    }
+    debug_log(debug_file, &format!("[ai] Using API key: {}...", &api_key[..api_key.len().min(10)]), debug_file.is_some());
+
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(600)) // 10 minute timeout
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    debug_log(debug_file, &format!("[ai] tools json: {}",
+        serde_json::to_string_pretty(&request_body["tools"]).unwrap_or_default()), debug_file.is_some());
+    
+    // Add retry logic for OpenAI API calls
+    let mut attempts = 0;
+    let max_attempts = 3;
+    let resp = loop {
+        attempts += 1;
+        debug_log(debug_file, &format!("[ai] {} API attempt {}/{}", backend.label(), attempts, max_attempts), debug_file.is_some());
+        
+        // Shares the per-provider rate limit with every other request in the process
+        match send_rate_limited(backend.name(), || backend.responses(&client, api_key, request_body)) {
+            Ok(response) => break response,
+            Err(e) => {
+                if attempts >= max_attempts {
+                    anyhow::bail!("{} API failed after {} attempts: {}", backend.label(), max_attempts, e);
+                }
+                debug_log(debug_file, &format!("[ai] {} API attempt {} failed: {}, retrying...", backend.label(), attempts, e), debug_file.is_some());
+                std::thread::sleep(std::time::Duration::from_secs(2 * attempts as u64));
+                continue;
+            }
+        }
+    };
+    
+    let status = resp.status();
+    debug_log(debug_file, &format!("[ai] openai status: {}", status), debug_file.is_some());
+    
+    // Check for API errors
+    if !status.is_success() {
+        let body = resp.text().unwrap_or_default();
+        return Err(QernelError::Provider { provider: backend.label().to_string(), status: status.as_u16(), body }.into());
+    }
+    
+    let raw = resp.text().context("openai response text")?;
+    debug_log(debug_file, &format!("[ai] openai body length: {} chars", raw.len()), debug_file.is_some());
+    
+    // Debug: Print the raw response for troubleshooting
+    debug_log(debug_file, &format!("[ai] openai raw response:\n{}", raw), false);
+    
+    // Parse response with better error handling
+    let body: serde_json::Value = match serde_json::from_str(&raw) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            debug_log(debug_file, &format!("[ai] Failed to parse JSON response: {}", e), debug_file.is_some());
+            debug_log(debug_file, &format!("[ai] Raw response (first 500 chars): {}", &raw[..raw.len().min(500)]), debug_file.is_some());
+            anyhow::bail!("Failed to parse OpenAI response JSON: {}", e);
+        }
+    };
+    
+    // Check for OpenAI API errors in the response body
+    if let Some(error) = body.get("error") {
+        if let Some(message) = error.get("message").and_then(|m| m.as_str()) {
+            anyhow::bail!("OpenAI API error: {}", message);
+        }
+    }
+    usage::record(request_body["model"].as_str().unwrap_or(""), &body);
+    audit::record_model_request(request_body["model"].as_str().unwrap_or(""), Some(request_body), &body);
+    Ok(body)
+}
+
+fn parse_ai_response(body: &serde_json::Value, debug_file: &Option<RunLogger>) -> Result<AiStep> {
+    // Prefer tool calls in the Responses API `output` array.
+    if let Some(output) = body.get("output").and_then(|v| v.as_array()) {
+        debug_log(debug_file, &format!("[ai] output array length: {}", output.len()), debug_file.is_some());
+        for (i, item) in output.iter().enumerate() {
+            debug_log(debug_file, &format!("[ai] output[{}]: {}", i, serde_json::to_string_pretty(item).unwrap_or_default()), debug_file.is_some());
+        }
+        
+        // 1) Grammar-based custom tools (GPT-5 "custom_tool_call")
+        if let Some(ctc) = output.iter().find(|item| {
+            item.get("type").and_then(|v| v.as_str()) == Some("custom_tool_call")
+                && item.get("name").and_then(|v| v.as_str()) == Some("apply_patch")
+        }) && let Some(input) = ctc.get("input").and_then(|v| v.as_str())
+        {
+            debug_log(debug_file, &format!("[ai] custom_tool_call input (len={}):", input.len()), debug_file.is_some());
+            let action = AiAction::ApplyPatch { patch: input.to_string() };
+            return Ok(AiStep::new(action, tool_call(ctc, true)));
+        }
+        
+        // 2) JSON/function tools (handle both function_call and tool_call)
+        if let Some(fc) = output.iter().find(|item| {
+            let t = item.get("type").and_then(|v| v.as_str());
+            t == Some("function_call") || t == Some("tool_call")
+        }) {
+            let name = fc.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            let args_str = fc.get("arguments").and_then(|v| v.as_str()).unwrap_or("{}");
+            debug_log(debug_file, &format!("[ai] function_call {} args:\\n{}", name, args_str), debug_file.is_some());
+
+            // Arguments are checked against the same types the tool schemas were generated from
+            let action = match name {
+                "apply_patch" => {
+                    let args: ApplyPatchArgs = serde_json::from_str(args_str).context("parse apply_patch arguments")?;
+                    AiAction::ApplyPatch { patch: args.input }
+                }
+                "shell" => {
+                    let args: ShellArgs = serde_json::from_str(args_str).context("parse shell arguments")?;
+                    AiAction::Shell { command: args.command }
+                }
+                "add_dependency" => {
+                    let args: AddDependencyArgs = serde_json::from_str(args_str).context("parse add_dependency arguments")?;
+                    AiAction::AddDependency { package: args.package, version: args.version }
+                }
+                "update_plan" => {
+                    let args: UpdatePlanArgs = serde_json::from_str(args_str).context("parse update_plan arguments")?;
+                    let mut step = AiStep::new(AiAction::Plan { steps: args.plan }, tool_call(fc, false));
+                    step.rationale = args.explanation;
+                    return Ok(step);
+                }
+                other => anyhow::bail!("Model called an unknown tool {:?}", other),
+            };
+            return Ok(AiStep::new(action, tool_call(fc, false)));
+        }
+    }
+
+    // Fallback: parse content as our JSON action schema
+    if let Some(output) = body["output"].as_array() {
+        debug_log(debug_file, "[ai] trying fallback JSON parsing...", debug_file.is_some());
+        if let Some(message) = output.iter().find(|item| item["type"].as_str() == Some("message")) {
+            debug_log(debug_file, "[ai] found message in output", debug_file.is_some());
+            if let Some(content_array) = message["content"].as_array() {
+                debug_log(debug_file, &format!("[ai] content array length: {}", content_array.len()), debug_file.is_some());
+                if let Some(text_content) = content_array.iter().find(|c| c["type"].as_str() == Some("output_text")) {
+                    if let Some(content) = text_content["text"].as_str() {
+                        debug_log(debug_file, &format!("[ai] openai content (to-parse):\n{}", content), debug_file.is_some());
+                        // Text that is not a valid step is a plain message, handled below
+                        match serde_json::from_str::<AiStep>(content) {
+                            Ok(step) => {
+                                debug_log(debug_file, &format!("[ai] parsed step: {:?}", step), debug_file.is_some());
+                                return Ok(step);
+                            }
+                            Err(e) => debug_log(debug_file, &format!("[ai] content is not a valid step: {}", e), debug_file.is_some()),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Final fallbacks: try output_text (SDK convenience), then message content.
+    debug_log(debug_file, "[ai] trying final fallbacks...", debug_file.is_some());
+    if let Some(s) = body.get("output_text").and_then(|v| v.as_str()) {
+        debug_log(debug_file, &format!("[ai] found output_text: {}", s), debug_file.is_some());
+        if let Ok(step) = serde_json::from_str::<AiStep>(s) {
+            debug_log(debug_file, &format!("[ai] parsed step from output_text: {:?}", step), debug_file.is_some());
+            return Ok(step);
+        } else {
+            debug_log(debug_file, "[ai] failed to parse output_text as AiStep", debug_file.is_some());
+        }
+    }
+    
+    if let Some(output) = body.get("output").and_then(|v| v.as_array()) {
+        for item in output {
+            if item.get("type").and_then(|v| v.as_str()) == Some("message") {
+                if let Some(s) = item.get("content").and_then(|v| v.as_str()) {
+                    if let Ok(step) = serde_json::from_str::<AiStep>(s) {
+                        debug_log(debug_file, "[ai] parsed step from message.content (string)", debug_file.is_some());
+                        return Ok(step);
+                    }
+                } else if let Some(parts) = item.get("content").and_then(|v| v.as_array()) {
+                    let text = parts
+                        .iter()
+                        .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                        .collect::<String>();
+                    if let Ok(step) = serde_json::from_str::<AiStep>(&text) {
+                        debug_log(debug_file, "[ai] parsed step from message.content parts", debug_file.is_some());
+                        return Ok(step);
+                    }
+                }
+            }
+        }
+    }
+    
+    // A plain message with no tool call ends the model's turn
+    let message = message_text(body);
+    if !message.trim().is_empty() {
+        return Ok(AiStep::new(AiAction::Done { summary: message }, None));
+    }
+
+    let kinds = body
+        .get("output")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .map(|i| i.get("type").and_then(|v| v.as_str()).unwrap_or("?").to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    
+    // Check if we have reasoning types but also function calls
+    let has_reasoning = kinds.contains(&"reasoning".to_string());
+    let has_function_call = kinds.contains(&"function_call".to_string()) || kinds.contains(&"tool_call".to_string());
+    
+    // If we have reasoning but no function calls, the model might be stuck
+    if has_reasoning && !has_function_call {
+        anyhow::bail!("Model is reasoning but not taking action. Output types = {:?}. This might indicate the model needs clearer instructions or the task is too complex, or an alternative error has occured.", kinds)
+    }
+    
+    anyhow::bail!("No actionable tool call or parseable text in response; output types = {:?}", kinds)
+}
+
+fn tool_call(item: &serde_json::Value, custom: bool) -> Option<ToolCall> {
+    let id = item.get("call_id").and_then(|v| v.as_str())?;
+    Some(ToolCall { id: id.to_string(), custom })
+}
+
+/// Text of every message item in a response's output
+fn message_text(body: &serde_json::Value) -> String {
+    let Some(output) = body.get("output").and_then(|v| v.as_array()) else {
+        return String::new();
+    };
+    output
+        .iter()
+        .filter(|item| item.get("type").and_then(|v| v.as_str()) == Some("message"))
+        .flat_map(|item| item.get("content").and_then(|v| v.as_array()).cloned().unwrap_or_default())
+        .filter_map(|part| part.get("text").and_then(|t| t.as_str()).map(str::to_string))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Encode an image file to a base64 data URL after downscaling and recompressing it.
+/// Returns `None` when the image is still above `config.max_kb` and should be skipped.
+fn encode_image_to_base64(image_path: &str, config: &ImageConfig, debug_file: &Option<RunLogger>) -> Result<Option<String>> {
+    use crate::cmd::common::cache::{CacheCategory, cache_key, read_entry, write_entry};
+
+    // Reuse a previous encoding while the file and settings are unchanged
+    let meta = fs::metadata(image_path).context("Failed to read image file")?;
+    let mtime = meta.modified().ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let settings = format!("{}:{:?}:{}", config.max_dimension, config.format, config.quality);
+    let entry = format!(
+        "{}.b64",
+        cache_key(&[image_path.as_bytes(), &meta.len().to_le_bytes(), &mtime.to_le_bytes(), settings.as_bytes()])
+    );
+    if let Some(data_url) = read_entry(CacheCategory::Images, &entry).and_then(|b| String::from_utf8(b).ok()) {
+        return Ok(within_size_cap(image_path, &data_url, config, debug_file).then_some(data_url));
+    }
+
+    let original = fs::read(image_path).context("Failed to read image file")?;
+    let (mime_type, image_data) = match preprocess_image(&original, config) {
+        Ok(Some((mime, processed))) => {
+            debug_log(debug_file, &format!(
+                "[ai] image {}: {} KB -> {} KB ({:.0}% smaller)",
+                image_path,
+                original.len() / 1024,
+                processed.len() / 1024,
+                100.0 * (1.0 - processed.len() as f64 / original.len().max(1) as f64)
+            ), debug_file.is_some());
+            (mime, processed)
+        }
+        Ok(None) => (get_image_mime_type(image_path), original),
+        Err(e) => {
+            // Formats the decoder does not know are still sent as-is
+            debug_log(debug_file, &format!("[ai] image {}: not preprocessed ({})", image_path, e), debug_file.is_some());
+            (get_image_mime_type(image_path), original)
+        }
+    };
+
+    let data_url = format!("data:{};base64,{}", mime_type, general_purpose::STANDARD.encode(&image_data));
+    write_entry(CacheCategory::Images, &entry, data_url.as_bytes());
+    Ok(within_size_cap(image_path, &data_url, config, debug_file).then_some(data_url))
+}
+
+/// Whether the decoded payload of `data_url` fits in `config.max_kb`
+fn within_size_cap(image_path: &str, data_url: &str, config: &ImageConfig, debug_file: &Option<RunLogger>) -> bool {
+    let payload = data_url.split_once(',').map_or(data_url.len(), |(_, b64)| b64.len() / 4 * 3);
+    let fits = payload as u64 <= config.max_kb.saturating_mul(1024);
+    if !fits {
+        debug_log(debug_file, &format!(
+            "[ai] skipping image {}: {} KB after preprocessing exceeds agent.images.max_kb ({} KB)",
+            image_path, payload / 1024, config.max_kb
+        ), debug_file.is_some());
+    }
+    fits
+}
+
+/// Resize to fit `max_dimension` and re-encode in the configured format.
+/// Returns `None` when that would not make the image any smaller.
+fn preprocess_image(original: &[u8], config: &ImageConfig) -> Result<Option<(&'static str, Vec<u8>)>> {
+    use image::{DynamicImage, ImageFormat as Codec, imageops::FilterType};
+    use std::io::Cursor;
+
+    let codec = image::guess_format(original).context("unrecognized image format")?;
+    let decoded = image::load_from_memory_with_format(original, codec).context("failed to decode image")?;
+    let max = config.max_dimension.max(1);
+    let resized = decoded.width() > max || decoded.height() > max;
+    let img = if resized { decoded.resize(max, max, FilterType::Lanczos3) } else { decoded };
+
+    let mut out = Cursor::new(Vec::new());
+    let mime = match config.format {
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha channel: flatten transparency onto white
+            let rgb = DynamicImage::ImageRgb8(flatten_on_white(&img));
+            let quality = config.quality.clamp(1, 100);
+            rgb.write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality))
+                .context("failed to encode JPEG")?;
+            "image/jpeg"
+        }
+        ImageFormat::Webp => {
+            let rgba = DynamicImage::ImageRgba8(img.to_rgba8());
+            rgba.write_with_encoder(image::codecs::webp::WebPEncoder::new_lossless(&mut out))
+                .context("failed to encode WebP")?;
+            "image/webp"
+        }
+        ImageFormat::Original => {
+            if !resized {
+                return Ok(None);
+            }
+            img.write_to(&mut out, codec).context("failed to re-encode image")?;
+            match codec {
+                Codec::Png => "image/png",
+                Codec::Jpeg => "image/jpeg",
+                Codec::Gif => "image/gif",
+                Codec::WebP => "image/webp",
+                Codec::Bmp => "image/bmp",
+                _ => "application/octet-stream",
+            }
+        }
+    };
+    let processed = out.into_inner();
+    if processed.len() >= original.len() && !resized {
+        return Ok(None);
+    }
+    Ok(Some((mime, processed)))
+}
+
+fn flatten_on_white(img: &image::DynamicImage) -> image::RgbImage {
+    let rgba = img.to_rgba8();
+    image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Get MIME type based on file extension
+fn get_image_mime_type(image_path: &str) -> &'static str {
+    if let Some(extension) = std::path::Path::new(image_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        match extension.to_lowercase().as_str() {
+            "jpg" | "jpeg" => "image/jpeg",
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "bmp" => "image/bmp",
+            "webp" => "image/webp",
+            _ => "image/jpeg", // Default fallback
+        }
+    } else {
+        "image/jpeg" // Default fallback
+    }
+}
@@ -0,0 +1,80 @@
+//! Size of an agent patch (files, hunks, lines added and removed), shown in the preview
+//! header, checked against `agent.max_patch_lines` and summed over the run.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::cmd::prototype::turns::is_binary;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchStats {
+    pub files: usize,
+    pub hunks: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl PatchStats {
+    /// Stats of a `*** Begin Patch` body. A deleted file counts all its lines as removed,
+    /// read from under `cwd`.
+    pub fn of(patch: &str, cwd: &Path) -> Self {
+        let mut stats = Self::default();
+        // Whether the current file's changes are already inside a hunk
+        let mut in_hunk = false;
+        for line in patch.lines() {
+            if line.starts_with("*** Add File: ") {
+                stats.files += 1;
+                stats.hunks += 1;
+                in_hunk = true;
+            } else if let Some(path) = line.strip_prefix("*** Delete File: ") {
+                stats.files += 1;
+                stats.hunks += 1;
+                stats.removed += std::fs::read(cwd.join(path.trim()))
+                    .ok()
+                    .filter(|bytes| !is_binary(bytes))
+                    .map_or(0, |bytes| String::from_utf8_lossy(&bytes).lines().count());
+                in_hunk = true;
+            } else if line.starts_with("*** Update File: ") {
+                stats.files += 1;
+                in_hunk = false;
+            } else if line.starts_with("@@") {
+                stats.hunks += 1;
+                in_hunk = true;
+            } else if line.starts_with('+') || line.starts_with('-') {
+                // The first hunk of an update may leave out its `@@` line
+                if !in_hunk {
+                    stats.hunks += 1;
+                    in_hunk = true;
+                }
+                if line.starts_with('+') {
+                    stats.added += 1;
+                } else {
+                    stats.removed += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Lines added plus lines removed
+    pub fn lines(&self) -> usize {
+        self.added + self.removed
+    }
+}
+
+impl std::fmt::Display for PatchStats {
+    /// e.g. `2 files, 3 hunks, +10 -4`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} file{}, {} hunk{}, +{} -{}",
+            self.files,
+            plural(self.files),
+            self.hunks,
+            plural(self.hunks),
+            self.added,
+            self.removed
+        )
+    }
+}
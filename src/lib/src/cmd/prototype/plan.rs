@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use codex_core::plan_tool::{PlanItemArg, StepStatus};
+
+/// Location of the persisted implementation plan
+pub fn plan_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("plan.md")
+}
+
+/// Load `.qernel/plan.md` if it exists and contains at least one step
+pub fn load_plan(cwd: &Path) -> Option<Vec<PlanItemArg>> {
+    let text = fs::read_to_string(plan_path(cwd)).ok()?;
+    let items = parse_plan(&text);
+    if items.is_empty() { None } else { Some(items) }
+}
+
+/// Write the plan to `.qernel/plan.md` as a numbered checklist
+pub fn save_plan(cwd: &Path, items: &[PlanItemArg]) -> Result<()> {
+    let path = plan_path(cwd);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, render_plan(items)).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Mark every step as completed (used once the tests pass)
+pub fn mark_all_completed(items: &mut [PlanItemArg]) {
+    for item in items.iter_mut() {
+        item.status = StepStatus::Completed;
+    }
+}
+
+/// Markdown form stored on disk:
+///
+/// ```text
+/// # Implementation Plan
+///
+/// - [x] 1. Parse the Hamiltonian
+/// - [~] 2. Build the ansatz
+/// - [ ] 3. Optimize parameters
+/// ```
+pub fn render_plan(items: &[PlanItemArg]) -> String {
+    let mut out = String::from("# Implementation Plan\n\n");
+    for (i, item) in items.iter().enumerate() {
+        out.push_str(&format!("- [{}] {}. {}\n", status_marker(item.status), i + 1, item.step.trim()));
+    }
+    out
+}
+
+/// Parse the checklist written by `render_plan`; unrecognized lines are ignored
+pub fn parse_plan(text: &str) -> Vec<PlanItemArg> {
+    text.lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("- [")?;
+            let (marker, step) = rest.split_once(']')?;
+            let status = match marker.trim() {
+                "x" | "X" => StepStatus::Completed,
+                "~" => StepStatus::InProgress,
+                _ => StepStatus::Pending,
+            };
+            let step = step.trim();
+            // Drop the "N. " numbering prefix
+            let step = match step.split_once(". ") {
+                Some((n, s)) if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => s,
+                _ => step,
+            };
+            if step.is_empty() {
+                None
+            } else {
+                Some(PlanItemArg { step: step.to_string(), status })
+            }
+        })
+        .collect()
+}
+
+/// Plan section appended to the user prompt on every iteration
+pub fn plan_prompt_section(items: &[PlanItemArg]) -> String {
+    let done = items.iter().filter(|i| i.status == StepStatus::Completed).count();
+    let mut out = format!("Implementation plan ({}/{} steps completed):\n", done, items.len());
+    for (i, item) in items.iter().enumerate() {
+        out.push_str(&format!("{}. [{}] {}\n", i + 1, status_label(item.status), item.step.trim()));
+    }
+    out.push_str(
+        "\nWork through the plan in order. When a step is finished or the plan needs to change, \
+        call update_plan with the full list of steps and their updated statuses.",
+    );
+    out
+}
+
+fn status_marker(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Completed => "x",
+        StepStatus::InProgress => "~",
+        StepStatus::Pending => " ",
+    }
+}
+
+pub fn status_label(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Completed => "completed",
+        StepStatus::InProgress => "in progress",
+        StepStatus::Pending => "pending",
+    }
+}
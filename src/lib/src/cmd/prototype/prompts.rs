@@ -0,0 +1,113 @@
+use std::path::Path;
+
+/// Build the system prompt for the AI agent
+pub fn build_system_prompt(_goal: &str, test_cmd: &str, cwd: &Path, project_directory_content: &str) -> String {
+    use codex_apply_patch::APPLY_PATCH_TOOL_INSTRUCTIONS;
+    
+    format!(
+        "You are a coding agent that implements code in src/main.py to achieve the given goal.\n\n\
+        Current working directory: {}\n\
+        Test command: {}\n\n\
+        Project context:\n\
+        {}\n\n\
+        CRITICAL REQUIREMENTS:\n\
+        - You MUST implement the required functionality in src/main.py. Empty patches or no-op operations are NOT allowed.\n\
+        - You can ONLY modify src/main.py. Do not modify test files, configuration files, or other project files.\n\
+        - Use action=apply_patch with a *** Begin Patch / *** End Patch body to modify src/main.py.\n\
+        - Use action=shell with a 'command' string to run commands.\n\
+        - Use the add_dependency tool (action=add_dependency) to add a missing Python package, pinned, to requirements.txt.\n\
+        - Use the update_plan tool (action=plan) to record or update the implementation plan and mark steps completed.\n\
+        - You may chain several tool calls in one turn: each call's result (patch outcome, command output) is returned to you. \
+        For example, run the tests with the shell tool, patch, and run them again. When you are done, reply with a short message \
+        summarizing your changes; the test command then runs.\n\
+        - Always aim to make the test command exit 0.\n\
+        - When patching, use the EXACT current content from the files above.\n\
+        - CRITICAL: Include 3+ lines of context when available. For new or empty files, it's OK to provide only + lines in a single *** Update File: src/main.py hunk (no context required). Never emit an empty patch.\n\
+        - Use @@ headers to identify functions/classes when needed.\n\
+        - Study the benchmark.md, qernel.yaml, and requirements.txt to understand the project requirements.\n\
+        - If src/main.py is empty, you must implement the complete functionality from scratch.\n\
+        - If tests are failing, implement the missing functions in src/main.py to make them pass.\n\
+        - Focus on implementing the goal by modifying only src/main.py.\n\
+        - NEVER generate empty patches like '*** Begin Patch\\n*** End Patch' - always include actual code changes.\n\
+        - When you see test failures, carefully read the error messages and stack traces to understand what's wrong.\n\
+        - Pay special attention to ImportError, NameError, AttributeError, and other Python errors in the test output.\n\
+        - Fix the specific errors mentioned in the test output by modifying the code accordingly.\n\n\
+        - PLEASE reason through your actions carefully, and share your reasoning for each decision that you make.
+        {}\
+        ",
+        cwd.display(),
+        test_cmd,
+        project_directory_content,
+        APPLY_PATCH_TOOL_INSTRUCTIONS
+    )
+}
+
+/// Build the user prompt for the AI agent
+pub fn build_user_prompt(goal: &str, failure_context: &str, plan_section: &str) -> String {
+    let mut prompt = format!("Goal: {}", goal);
+    if !plan_section.is_empty() {
+        prompt.push_str(&format!("\n\n{}", plan_section));
+    }
+    if !failure_context.is_empty() {
+        prompt.push_str(&format!("\n\nPrevious iteration failed. Here are the details:\n{}\n\nIMPORTANT: There are very likely failures and errors in the output above. The best way to complete the task is to read the errors, understand the errors, and adjust the code to fix these errors as shown in the response.", failure_context));
+    }
+    prompt
+}
+
+/// Build the user prompt for the planning phase that runs before any code is written
+pub fn build_planning_prompt(goal: &str) -> String {
+    format!(
+        "Goal: {}\n\n\
+        Before writing any code, call the update_plan tool with a numbered implementation plan for src/main.py. \
+        Use 3-8 concise, concrete steps in the order you will implement them, all with status \"pending\". \
+        Do not apply a patch in this response.",
+        goal
+    )
+}
+
+/// System prompt for the review-mode report written after a successful run
+pub fn build_report_system_prompt() -> String {
+    "You are reviewing a finished research-code implementation for a reader who knows the paper \
+    but has not seen the code. Write GitHub-flavored Markdown only, with no preamble and no code fences around the whole answer. \
+    Be specific and cite locations as `path:line`. Never invent results: the tests and metrics are appended separately."
+        .to_string()
+}
+
+/// Ask for `.qernel/report.md`: the algorithm as implemented, and which function implements
+/// which equation or section of the paper content in the spec
+pub fn build_report_prompt(spec: &str, code: &str, outline: &str, test_cmd: &str, test_tail: &str, artifacts: &str) -> String {
+    format!(
+        "Project spec, including the parsed paper content:\n{}\n\n\
+        Final implementation:\n{}\n\n\
+        Functions (path:lines kind name):\n{}\n\
+        Passing test command: {}\n\
+        End of the test output:\n{}\n\n\
+        {}\
+        Write a report with these sections:\n\
+        # Implementation report\n\
+        ## Summary: two or three sentences on what was implemented.\n\
+        ## Algorithm: the final algorithm step by step, with key equations in LaTeX ($...$ / $$...$$).\n\
+        ## Code to paper mapping: a table with columns Function | Location | Paper reference | Notes, \
+        one row per function above. Reference equation numbers and section titles as they appear in the paper content; \
+        write \"not in paper\" for helpers with no counterpart.\n\
+        ## Deviations and limitations: where the code simplifies or departs from the paper, and what the tests do not cover.",
+        spec, code, outline, test_cmd, test_tail, if artifacts.is_empty() { String::new() } else { format!("{}\n", artifacts) }
+    )
+}
+
+/// System prompt for the reviewer role, which vets each patch before it is applied
+pub fn build_review_system_prompt(goal: &str) -> String {
+    format!(
+        "You review patches proposed by a coding agent implementing this spec:\n{}\n\n\
+        Reject a patch only for concrete problems: it implements the spec or paper incorrectly (wrong equation, sign, \
+        normalization or algorithm step), breaks code that already works, edits files other than src/main.py, or cannot \
+        make progress toward passing the tests. Do not reject for style. \
+        Reply with only a JSON object: {{\"verdict\": \"approve\" or \"reject\", \"critique\": \"what is wrong and how to fix it\"}}.",
+        goal
+    )
+}
+
+/// The patch under review, with the project files it applies to
+pub fn build_review_prompt(patch: &str, project_context: &str) -> String {
+    format!("Current project files:\n{}\n\nProposed patch:\n{}", project_context, patch)
+}
@@ -0,0 +1,315 @@
+//! `qernel prototype --create-pr`: after a successful run, commit the files the run changed
+//! to a new `qernel/<project>-<time>` branch, push it, and open a pull request on GitHub or
+//! the Zoo describing the run. The checked-out branch, the index and any other uncommitted
+//! work are left as they were.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use crate::cmd::prototype::budget::format_elapsed;
+use crate::cmd::prototype::summary::{RunOutcome, RunSummary};
+use crate::cmd::prototype::turns::{list_turns, turn_diff_files, turn_diff_path};
+use crate::cmd::publish::strip_credentials;
+use crate::cmd::secrets::scan_outgoing;
+use crate::cmd::zoo;
+use crate::config::load_config;
+use crate::error::QernelError;
+
+#[derive(Debug, Default, Deserialize)]
+struct PullRequestResponse {
+    /// GitHub
+    html_url: Option<String>,
+    /// Zoo
+    url: Option<String>,
+}
+
+/// Where the pull request is opened, from the remote's URL
+enum Host {
+    GitHub { owner: String, repo: String },
+    Zoo,
+}
+
+/// Push the run's changes on a new branch and open a pull request into the branch the
+/// project was on. Only for runs that succeeded.
+pub fn create_pull_request(project: &Path, summary: &RunSummary, remote: &str) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    if summary.outcome != RunOutcome::Success {
+        println!("{} Not opening a pull request: the run {}", crate::util::sym_question(ce), summary.outcome.describe());
+        return Ok(());
+    }
+    let base = zoo::git_output(project, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .ok_or_else(|| QernelError::Config("--create-pr needs the project to be in a git repository with a commit".into()))?;
+    if base == "HEAD" {
+        return Err(QernelError::Config("--create-pr needs a checked-out branch to open the pull request into, not a detached HEAD".into()).into());
+    }
+    let remote_url = zoo::git_output(project, &["remote", "get-url", remote])
+        .ok_or_else(|| QernelError::Config(format!("--create-pr: no git remote named '{}'", remote)))?;
+    let files = run_files(project, summary);
+    if files.is_empty() {
+        anyhow::bail!("Not opening a pull request: the run left no changes to commit");
+    }
+    // Checked before anything is committed or pushed
+    let host = host(&remote_url);
+    let token = match &host {
+        Host::GitHub { .. } => ["GITHUB_TOKEN", "GH_TOKEN"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()))
+            .ok_or_else(|| QernelError::Config("--create-pr: set GITHUB_TOKEN or GH_TOKEN to open a pull request on GitHub".into()))?,
+        Host::Zoo => zoo::require_token()?,
+    };
+
+    let config = load_config(&project.join(".qernel").join("qernel.yaml"))?;
+    let branch = branch_name(&config.project.name);
+    let title = format!("qernel prototype: {}", config.project.name);
+    commit_to_branch(project, &files, &branch, &title)?;
+    println!("{} Committed {} changed file(s) to branch {}", crate::util::sym_check(ce), files.len(), branch);
+
+    let findings = scan_outgoing(project, remote, &branch, false)?;
+    if !findings.is_empty() {
+        println!("{} Possible secrets in the commits to push:", crate::util::sym_cross(ce));
+        for f in &findings {
+            println!("   {}:{}: {} ({})", f.path, f.line, f.kind, f.preview);
+        }
+        anyhow::bail!("Not opening a pull request: {} possible secret(s) found; branch {} was committed but not pushed", findings.len(), branch);
+    }
+    git(project, &["push", remote, &format!("refs/heads/{0}:refs/heads/{0}", branch)])?;
+    println!("{} Pushed {} to {}", crate::util::sym_check(ce), branch, remote);
+
+    let body = pull_request_body(summary, &config.project.name);
+
+    let payload = serde_json::json!({ "title": title, "head": branch, "base": base, "body": body });
+    let response = match &host {
+        Host::GitHub { owner, repo } => {
+            let request = zoo::client()?
+                .post(format!("https://api.github.com/repos/{}/{}/pulls", owner, repo))
+                .bearer_auth(token)
+                .header("Accept", "application/vnd.github+json")
+                .header("User-Agent", "qernel")
+                .json(&payload);
+            send(request, "the GitHub pulls API")?
+        }
+        Host::Zoo => {
+            let api = zoo::api_base(project, None)?;
+            let mut payload = payload;
+            payload["repository"] = serde_json::Value::String(strip_credentials(&remote_url));
+            let request = zoo::client()?.post(format!("{}/pulls", api)).bearer_auth(token).json(&payload);
+            zoo::send(request, "the pulls API")?
+        }
+    };
+    let opened: PullRequestResponse = response.json().unwrap_or_default();
+    println!("{} Opened a pull request from {} into {}", crate::util::sym_check(ce), branch, base);
+    if let Some(url) = opened.html_url.or(opened.url) {
+        println!("   {}", url);
+    }
+    Ok(())
+}
+
+/// Files the run's turn diffs record, relative to the project: the diffs written since the
+/// run started, without anything under `.qernel/`
+fn run_files(project: &Path, summary: &RunSummary) -> Vec<String> {
+    let elapsed = Duration::from_secs_f64(summary.elapsed_secs);
+    let finished = summary.finished_at.map_or_else(SystemTime::now, SystemTime::from);
+    let started = finished.checked_sub(elapsed).unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut files = BTreeSet::new();
+    for turn in list_turns(project) {
+        let path = turn_diff_path(project, turn);
+        let written = std::fs::metadata(&path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+        if written < started {
+            continue;
+        }
+        let Ok(diff) = std::fs::read_to_string(&path) else { continue };
+        files.extend(turn_diff_files(&diff).into_iter().filter(|f| !Path::new(f).starts_with(".qernel")));
+    }
+    files.into_iter().collect()
+}
+
+/// Commit `files` as they are on disk on top of `HEAD` to a new `branch`, through an index
+/// of its own so the user's index, working tree and checked-out branch stay as they are
+fn commit_to_branch(project: &Path, files: &[String], branch: &str, message: &str) -> Result<()> {
+    let index_path = zoo::git_output(project, &["rev-parse", "--git-path", "qernel-pr-index"])
+        .map(|path| project.join(path))
+        .ok_or_else(|| QernelError::Config("--create-pr needs the project to be in a git repository".into()))?;
+    let run = |args: &[&str]| -> Result<String> {
+        let out = Command::new("git")
+            .args(args)
+            .env("GIT_INDEX_FILE", &index_path)
+            .current_dir(project)
+            .output()
+            .context("failed to run git")?;
+        if !out.status.success() {
+            anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&out.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    let tree = (|| {
+        run(&["read-tree", "HEAD"])?;
+        let mut update = vec!["update-index", "--add", "--remove", "--"];
+        update.extend(files.iter().map(String::as_str));
+        run(&update)?;
+        run(&["write-tree"])
+    })();
+    std::fs::remove_file(&index_path).ok();
+    let tree = tree?;
+    if tree == run(&["rev-parse", "HEAD^{tree}"])? {
+        anyhow::bail!("Not opening a pull request: the files the run changed match the last commit");
+    }
+    let commit = run(&["commit-tree", &tree, "-p", "HEAD", "-m", message])?;
+    run(&["update-ref", &format!("refs/heads/{}", branch), &commit, ""])?;
+    Ok(())
+}
+
+/// e.g. `qernel/vqe-h2-20260301-142530`
+fn branch_name(project: &str) -> String {
+    let slug: String = project
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+    let slug = if slug.is_empty() { "prototype" } else { slug };
+    format!("qernel/{}-{}", slug, chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Markdown summary of the run: outcome, metrics, suites and coverage. The report and
+/// metrics files stay local with the rest of `.qernel/`, so they are only named.
+fn pull_request_body(summary: &RunSummary, project: &str) -> String {
+    let mut body = format!(
+        "`qernel prototype` {} on **{}** after {} of {} iterations, applying {} patch(es) ({}) in {}.\n",
+        summary.outcome.describe(),
+        project,
+        summary.iterations,
+        summary.max_iterations,
+        summary.patches_applied,
+        summary.churn,
+        format_elapsed(std::time::Duration::from_secs_f64(summary.elapsed_secs)),
+    );
+
+    if !summary.metrics.is_empty() {
+        body.push_str("\n## Metrics\n\n| Metric | Value | Target | Met |\n|---|---|---|---|\n");
+        for m in &summary.metrics {
+            let value = m.value.map_or("-".to_string(), |v| format!("{}", v));
+            let target = m.target.map_or("-".to_string(), |v| format!("{}", v));
+            body.push_str(&format!("| {} | {} | {} | {} |\n", m.name, value, target, if m.met { "yes" } else { "no" }));
+        }
+    }
+    if !summary.suites.is_empty() {
+        body.push_str("\n## Test suites\n\n");
+        for s in &summary.suites {
+            let status = if s.passed { "passed".to_string() } else { format!("failed (exit {})", s.exit_code) };
+            body.push_str(&format!("- `{}`: {}{}\n", s.name, status, if s.required { "" } else { ", optional" }));
+        }
+    }
+    if let Some(coverage) = summary.coverage {
+        body.push_str(&format!("\nCoverage of `src/`: {:.1}%\n", coverage));
+    }
+
+    let a = &summary.artifacts;
+    let mut files = Vec::new();
+    if a.report.is_some() {
+        files.push("`.qernel/report.md`");
+    }
+    if a.metrics.is_some() {
+        files.push("`.qernel/metrics.json`");
+    }
+    if a.metrics_chart.is_some() {
+        files.push("`.qernel/artifacts/metrics.svg`");
+    }
+    if !files.is_empty() {
+        body.push_str(&format!("\nThe run also wrote {} in the project (not part of this pull request).\n", files.join(", ")));
+    }
+
+    let u = &summary.usage;
+    body.push_str(&format!(
+        "\n{} model request(s), {} input and {} output tokens, about ${:.2}.\n",
+        u.requests, u.input_tokens, u.output_tokens, u.cost_usd
+    ));
+    body
+}
+
+/// `github.com` remotes in HTTPS, SSH or scp-like form; anything else is taken to be a Zoo
+fn host(remote_url: &str) -> Host {
+    let path = if let Some(rest) = remote_url.strip_prefix("git@github.com:") {
+        Some(rest.to_string())
+    } else {
+        url::Url::parse(remote_url)
+            .ok()
+            .filter(|u| u.host_str() == Some("github.com"))
+            .map(|u| u.path().trim_start_matches('/').to_string())
+    };
+    let parts: Option<(String, String)> = path.and_then(|p| {
+        let p = p.trim_end_matches('/').trim_end_matches(".git").to_string();
+        let (owner, repo) = p.split_once('/')?;
+        (!owner.is_empty() && !repo.is_empty() && !repo.contains('/')).then(|| (owner.to_string(), repo.to_string()))
+    });
+    match parts {
+        Some((owner, repo)) => Host::GitHub { owner, repo },
+        None => Host::Zoo,
+    }
+}
+
+fn git(project: &Path, args: &[&str]) -> Result<()> {
+    let out = Command::new("git").args(args).current_dir(project).output().context("failed to run git")?;
+    if !out.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Like [`zoo::send`], with GitHub's error messages
+fn send(request: reqwest::blocking::RequestBuilder, what: &str) -> Result<reqwest::blocking::Response> {
+    let response = request.send().with_context(|| format!("failed to reach {}", what))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        anyhow::bail!("GitHub rejected the token ({}). Check GITHUB_TOKEN or GH_TOKEN.", status);
+    }
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        anyhow::bail!("Request to {} failed ({}): {}", what, status, body.trim());
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git_in(dir: &Path, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@t", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn commits_only_the_run_files_and_leaves_the_checkout_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        git_in(dir, &["init", "-q", "-b", "main"]);
+        std::fs::write(dir.join("a.py"), "a\n").unwrap();
+        std::fs::write(dir.join("gone.py"), "g\n").unwrap();
+        git_in(dir, &["add", "."]);
+        git_in(dir, &["commit", "-qm", "init"]);
+        // The run's changes, and the user's own uncommitted work next to them
+        std::fs::write(dir.join("a.py"), "a2\n").unwrap();
+        std::fs::remove_file(dir.join("gone.py")).unwrap();
+        std::fs::write(dir.join("notes.txt"), "mine\n").unwrap();
+        git_in(dir, &["add", "notes.txt"]);
+
+        git_in(dir, &["config", "user.name", "t"]);
+        git_in(dir, &["config", "user.email", "t@t"]);
+        commit_to_branch(dir, &["a.py".to_string(), "gone.py".to_string()], "qernel/run", "run").unwrap();
+
+        assert_eq!(git_in(dir, &["ls-tree", "--name-only", "qernel/run"]), "a.py");
+        assert_eq!(git_in(dir, &["show", "qernel/run:a.py"]), "a2");
+        assert_eq!(git_in(dir, &["rev-parse", "--abbrev-ref", "HEAD"]), "main");
+        assert_eq!(git_in(dir, &["diff", "--cached", "--name-only"]), "notes.txt");
+        assert!(!dir.join(".git").join("qernel-pr-index").exists());
+    }
+}
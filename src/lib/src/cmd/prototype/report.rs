@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use codex_core::exec::truncate_utf8;
+
+use crate::cmd::common::mock;
+use crate::cmd::common::models;
+use crate::cmd::explain::chunk::{ChunkGranularity, chunk_python_or_fallback};
+use crate::cmd::explain::network::call_text_model;
+use crate::cmd::prototype::artifacts::artifacts_prompt_section;
+use crate::cmd::prototype::charts::metrics_chart_path;
+use crate::cmd::prototype::metrics::{MetricReading, metrics_path};
+use crate::cmd::prototype::prompts::{build_report_prompt, build_report_system_prompt};
+use crate::cmd::prototype::turns::list_turns;
+use crate::config::Sampling;
+
+/// Review-mode report explaining the final implementation, shown by `qernel see`
+pub fn report_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("report.md")
+}
+
+/// Full output of the passing test run, linked from the report
+pub fn test_output_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("report-test-output.txt")
+}
+
+/// The passing test run the report is written for
+pub struct TestRun<'a> {
+    pub command: &'a str,
+    pub iteration: u32,
+    pub stdout: &'a str,
+    pub stderr: &'a str,
+    pub readings: &'a [MetricReading],
+    /// Files collected by `benchmarks.artifacts`, relative to the project
+    pub artifacts: &'a [String],
+}
+
+/// Ask the model to explain the final code against the paper content in `spec`, then
+/// append the test results qernel observed (never model-written) and save `.qernel/report.md`.
+pub fn write_report(api_key: &str, model: &str, cwd: &Path, spec: &str, tests: &TestRun, sampling: &Sampling) -> Result<PathBuf> {
+    let _span = tracing::info_span!("report", model).entered();
+    let output_path = test_output_path(cwd);
+    let mut output = tests.stdout.to_string();
+    if !tests.stderr.is_empty() {
+        output.push_str("\n--- stderr ---\n");
+        output.push_str(tests.stderr);
+    }
+    fs::write(&output_path, &output).with_context(|| format!("failed to write {}", output_path.display()))?;
+
+    // Code first; the spec (with the parsed paper) gets whatever is left of the budget
+    let budget = models::capabilities(model).max_context_chars() / 2;
+    let code = source_listing(cwd);
+    let code = truncate_utf8(&code, budget * 2 / 3);
+    let spec = truncate_utf8(spec, budget.saturating_sub(code.len()));
+    let outline = function_outline(cwd);
+    let artifacts = artifacts_prompt_section(cwd, tests.iteration, tests.artifacts);
+    let user = build_report_prompt(spec, code, &outline, tests.command, &tail_lines(tests.stdout, 60), &artifacts);
+
+    let body = if mock::enabled() {
+        mock::report_response()?
+    } else {
+        call_text_model(api_key, model, &build_report_system_prompt(), &user, sampling)?
+    };
+
+    let mut report = body.trim().to_string();
+    report.push_str("\n\n");
+    report.push_str(&test_results_section(cwd, tests));
+    let path = report_path(cwd);
+    fs::write(&path, report).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Python sources under `src/` (what the agent may have written), each under its path
+fn source_listing(cwd: &Path) -> String {
+    let mut out = String::new();
+    for path in python_files(cwd) {
+        let Ok(text) = fs::read_to_string(&path) else { continue };
+        let rel = path.strip_prefix(cwd).unwrap_or(&path);
+        out.push_str(&format!("--- {} ---\n{}\n", rel.display(), text));
+    }
+    out
+}
+
+/// `path:start-end kind name` for every function, so the mapping cites real locations
+fn function_outline(cwd: &Path) -> String {
+    let mut out = String::new();
+    for path in python_files(cwd) {
+        let Ok(text) = fs::read_to_string(&path) else { continue };
+        let rel = path.strip_prefix(cwd).unwrap_or(&path);
+        for chunk in chunk_python_or_fallback(&text, &path, ChunkGranularity::Function).unwrap_or_default() {
+            out.push_str(&format!("{}:{}-{} {} {}\n", rel.display(), chunk.start_line, chunk.end_line, chunk.kind, chunk.name));
+        }
+    }
+    out
+}
+
+fn python_files(cwd: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![cwd.join("src")];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for path in entries.flatten().map(|e| e.path()) {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "__pycache__" {
+                    stack.push(path);
+                }
+            } else if name.ends_with(".py") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Deterministic `## Test results` section linking the saved output, metrics and turn diffs
+fn test_results_section(cwd: &Path, tests: &TestRun) -> String {
+    let mut out = String::from("## Test results\n\n");
+    out.push_str(&format!(
+        "`{}` passed on iteration {} ({}).\n\n",
+        tests.command,
+        tests.iteration,
+        chrono::Local::now().format("%Y-%m-%d %H:%M")
+    ));
+    if !tests.readings.is_empty() {
+        out.push_str("| Metric | Value | Target |\n| --- | --- | --- |\n");
+        for r in tests.readings {
+            let value = r.value.map_or_else(|| "-".to_string(), |v| format!("{}", v));
+            let target = r.target.map_or_else(|| "-".to_string(), |t| format!("{}", t));
+            out.push_str(&format!("| {} | {} | {} |\n", r.name, value, target));
+        }
+        out.push('\n');
+    }
+    // Links are relative to .qernel/, where the report lives
+    out.push_str("- [Full test output](report-test-output.txt)\n");
+    if metrics_chart_path(cwd).is_file() {
+        out.push_str("![Metrics per iteration](artifacts/metrics.svg)\n\n");
+    }
+    if metrics_path(cwd).is_file() {
+        out.push_str("- [Metrics per iteration](metrics.json)\n");
+    }
+    for rel in tests.artifacts {
+        out.push_str(&format!("- [{}](artifacts/iter-{}/{})\n", rel, tests.iteration, rel));
+    }
+    for turn in list_turns(cwd) {
+        out.push_str(&format!("- [Changes in iteration {}](turns/{}.diff)\n", turn, turn));
+    }
+    out
+}
+
+fn tail_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
@@ -0,0 +1,303 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use crate::cmd::env::venv_python;
+
+/// Quantum SDKs the analyzer recognizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantumSdk {
+    Qiskit,
+    Cirq,
+    PennyLane,
+    Qulacs,
+}
+
+/// Pins suggested when the model adds a package without a version; known to work together
+const PINNED: &[(&str, &str)] = &[
+    ("qiskit", "1.2.4"),
+    ("qiskit-aer", "0.15.1"),
+    ("cirq", "1.4.1"),
+    ("pennylane", "0.38.0"),
+    ("qulacs", "0.6.10"),
+    ("numpy", "1.26.4"),
+    ("scipy", "1.14.1"),
+];
+
+impl QuantumSdk {
+    pub const ALL: [QuantumSdk; 4] = [QuantumSdk::Qiskit, QuantumSdk::Cirq, QuantumSdk::PennyLane, QuantumSdk::Qulacs];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuantumSdk::Qiskit => "Qiskit",
+            QuantumSdk::Cirq => "Cirq",
+            QuantumSdk::PennyLane => "PennyLane",
+            QuantumSdk::Qulacs => "Qulacs",
+        }
+    }
+
+    /// Top-level module imported in code, also the pip package that provides it
+    fn module(self) -> &'static str {
+        match self {
+            QuantumSdk::Qiskit => "qiskit",
+            QuantumSdk::Cirq => "cirq",
+            QuantumSdk::PennyLane => "pennylane",
+            QuantumSdk::Qulacs => "qulacs",
+        }
+    }
+
+    /// SDK-specific advice for the system prompt: current APIs the model tends to get wrong
+    fn guidance(self) -> &'static str {
+        match self {
+            QuantumSdk::Qiskit => {
+                "Qiskit 1.x: `execute()` and `qiskit.Aer` were removed. Simulate with `qiskit_aer.AerSimulator` \
+                 and `transpile(circuit, backend)` then `backend.run(...)`, or use the primitives \
+                 (`qiskit.primitives.StatevectorSampler` / `StatevectorEstimator`). For exact states use \
+                 `qiskit.quantum_info.Statevector(circuit)`. Qubit 0 is the least significant bit in bitstrings."
+            }
+            QuantumSdk::Cirq => {
+                "Cirq: build circuits from `cirq.LineQubit.range(n)` or `cirq.GridQubit`, simulate with \
+                 `cirq.Simulator().simulate(circuit)` for states or `.run(circuit, repetitions=n)` for samples \
+                 (measurements need keys). `final_state_vector` uses big-endian qubit order."
+            }
+            QuantumSdk::PennyLane => {
+                "PennyLane: `import pennylane as qml`; create a device with `qml.device(\"default.qubit\", wires=n)` \
+                 and decorate circuit functions with `@qml.qnode(dev)`. Return measurements such as \
+                 `qml.expval(...)`, `qml.probs(...)` or `qml.state()`. Use `pennylane.numpy` for trainable arrays."
+            }
+            QuantumSdk::Qulacs => {
+                "Qulacs: states are `QuantumState(n)`, circuits `QuantumCircuit(n)` with `add_H_gate(i)` etc., \
+                 applied with `circuit.update_quantum_state(state)`. Observables come from `qulacs.Observable` \
+                 and `get_expectation_value(state)`. Qubit 0 is the least significant bit."
+            }
+        }
+    }
+}
+
+/// What the analyzer found in `requirements.txt` and the imports under `src/`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectAnalysis {
+    pub sdks: Vec<QuantumSdk>,
+    /// SDK packages imported in `src/` but not listed in `requirements.txt`
+    pub missing: Vec<&'static str>,
+}
+
+/// The requirements file the project venv installs: `.qernel/requirements.txt`, or a
+/// `requirements.txt` at the project root when only that one exists
+pub fn requirements_path(cwd: &Path) -> PathBuf {
+    let qernel = cwd.join(".qernel").join("requirements.txt");
+    let root = cwd.join("requirements.txt");
+    if !qernel.exists() && root.exists() { root } else { qernel }
+}
+
+/// Analyses by project, with the files they were made from, so the analyzer only re-reads
+/// sources after one of them changed
+static ANALYSES: Mutex<Vec<(PathBuf, Vec<SourceStamp>, ProjectAnalysis)>> = Mutex::new(Vec::new());
+
+/// A source file's path, modification time and size when it was analyzed
+type SourceStamp = (PathBuf, Option<SystemTime>, u64);
+
+static IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*(?:from\s+([A-Za-z_][A-Za-z0-9_]*)|import\s+([A-Za-z_][A-Za-z0-9_]*))").expect("import regex"));
+
+/// Detect which quantum SDKs the project uses, from its requirements and its imports
+pub fn analyze_project(cwd: &Path) -> ProjectAnalysis {
+    let requirements = requirements_path(cwd);
+    let sources = python_files(&cwd.join("src"));
+    let stamps: Vec<SourceStamp> = std::iter::once(&requirements)
+        .chain(&sources)
+        .map(|path| {
+            let meta = fs::metadata(path).ok();
+            (path.clone(), meta.as_ref().and_then(|m| m.modified().ok()), meta.map_or(0, |m| m.len()))
+        })
+        .collect();
+    let mut cache = ANALYSES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, _, analysis)) = cache.iter().find(|(root, seen, _)| root == cwd && *seen == stamps) {
+        return analysis.clone();
+    }
+
+    let required = fs::read_to_string(&requirements).map(|t| requirement_names(&t)).unwrap_or_default();
+    let imported = imported_modules(&sources);
+    let mut analysis = ProjectAnalysis::default();
+    for sdk in QuantumSdk::ALL {
+        let listed = required.iter().any(|r| r == sdk.module() || r.starts_with(&format!("{}-", sdk.module())));
+        let used = imported.iter().any(|m| m == sdk.module());
+        if listed || used {
+            analysis.sdks.push(sdk);
+        }
+        if used && !required.iter().any(|r| r == sdk.module()) {
+            analysis.missing.push(sdk.module());
+        }
+    }
+    cache.retain(|(root, _, _)| root != cwd);
+    cache.push((cwd.to_path_buf(), stamps, analysis.clone()));
+    analysis
+}
+
+/// System prompt section for the detected SDKs; empty when none is used
+pub fn sdk_prompt_section(analysis: &ProjectAnalysis) -> String {
+    if analysis.sdks.is_empty() {
+        return String::new();
+    }
+    let names: Vec<&str> = analysis.sdks.iter().map(|s| s.name()).collect();
+    let mut out = format!("Quantum SDKs used by this project: {}.\n", names.join(", "));
+    for sdk in &analysis.sdks {
+        out.push_str(&format!("- {}\n", sdk.guidance()));
+    }
+    if !analysis.missing.is_empty() {
+        out.push_str(&format!(
+            "These packages are imported but missing from requirements.txt: {}. Add them with the add_dependency tool.\n",
+            analysis.missing.join(", ")
+        ));
+    }
+    out
+}
+
+/// Normalized package names in a requirements file (`Qiskit_Aer>=0.15` -> `qiskit-aer`)
+fn requirement_names(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('-'))
+        .map(|l| {
+            let end = l.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))).unwrap_or(l.len());
+            normalize(&l[..end])
+        })
+        .collect()
+}
+
+fn normalize(package: &str) -> String {
+    package.trim().to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+/// The Python files under `dir`, in a stable order
+fn python_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.is_dir() {
+                stack.push(path);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("py") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Top-level modules imported by `files`
+fn imported_modules(files: &[PathBuf]) -> Vec<String> {
+    let mut modules = Vec::new();
+    for source in files.iter().filter_map(|f| fs::read_to_string(f).ok()) {
+        for caps in IMPORT.captures_iter(&source) {
+            if let Some(m) = caps.get(1).or_else(|| caps.get(2)) {
+                let m = m.as_str().to_string();
+                if !modules.contains(&m) {
+                    modules.push(m);
+                }
+            }
+        }
+    }
+    modules
+}
+
+/// A dependency the model asked for, validated and pinned but not yet recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    /// The pinned requirement, e.g. `qiskit==1.2.4`
+    pub line: String,
+}
+
+/// Validate the `add_dependency` tool's arguments and pin `package` (with a known-good version
+/// when the model gives none). `Err` carries the message for the model when the package was
+/// rejected or is already listed.
+pub fn pin_dependency(cwd: &Path, package: &str, version: Option<&str>) -> std::result::Result<Dependency, String> {
+    let name = normalize(package);
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Rejected: '{}' is not a valid package name.", package));
+    }
+    let version = match version.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => v.trim_start_matches("==").to_string(),
+        None => match PINNED.iter().find(|(p, _)| *p == name) {
+            Some((_, v)) => v.to_string(),
+            None => return Err(format!("Rejected: give a version for {}; requirements are pinned.", name)),
+        },
+    };
+    if !version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '*' | '+' | '!')) {
+        return Err(format!("Rejected: '{}' is not a valid version.", version));
+    }
+    let text = fs::read_to_string(requirements_path(cwd)).unwrap_or_default();
+    if requirement_names(&text).contains(&name) {
+        return Err(format!("{} is already in requirements.txt; nothing changed.", name));
+    }
+    let line = format!("{}=={}", name, version);
+    Ok(Dependency { name, line })
+}
+
+/// The command that installs `dependency` into the project venv; `None` when there is no
+/// venv yet. `python` resolves to the venv interpreter where commands are run.
+pub fn install_command(cwd: &Path, dependency: &Dependency) -> Option<Vec<String>> {
+    if !venv_python(&cwd.join(".qernel").join(".venv")).exists() {
+        return None;
+    }
+    Some(["python", "-m", "pip", "install", "-q", &dependency.line].map(String::from).to_vec())
+}
+
+/// Append `dependency` to the project's requirements file
+pub fn record_dependency(cwd: &Path, dependency: &Dependency) -> Result<()> {
+    let path = requirements_path(cwd);
+    let mut text = fs::read_to_string(&path).unwrap_or_default();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(&dependency.line);
+    text.push('\n');
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinning_does_not_touch_requirements() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("requirements.txt"), "numpy==1.26.4").unwrap();
+        let dependency = pin_dependency(dir.path(), "Qiskit_Aer", None).unwrap();
+        assert_eq!(dependency.line, "qiskit-aer==0.15.1");
+        assert_eq!(fs::read_to_string(dir.path().join("requirements.txt")).unwrap(), "numpy==1.26.4");
+        assert!(install_command(dir.path(), &dependency).is_none());
+
+        record_dependency(dir.path(), &dependency).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("requirements.txt")).unwrap(), "numpy==1.26.4\nqiskit-aer==0.15.1\n");
+        assert!(pin_dependency(dir.path(), "qiskit-aer", Some("0.16")).unwrap_err().contains("already"));
+        assert!(pin_dependency(dir.path(), "left-pad", None).unwrap_err().contains("give a version"));
+        assert!(pin_dependency(dir.path(), "x; rm -rf /", Some("1")).unwrap_err().contains("not a valid package"));
+    }
+
+    #[test]
+    fn analysis_follows_source_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("requirements.txt"), "cirq==1.4.1\n").unwrap();
+        fs::write(dir.path().join("src").join("main.py"), "import cirq\n").unwrap();
+        let first = analyze_project(dir.path());
+        assert_eq!(first.sdks, vec![QuantumSdk::Cirq]);
+        assert!(first.missing.is_empty());
+        assert_eq!(analyze_project(dir.path()), first);
+
+        fs::write(dir.path().join("src").join("main.py"), "import cirq\nfrom qiskit import QuantumCircuit\n").unwrap();
+        let second = analyze_project(dir.path());
+        assert_eq!(second.sdks, vec![QuantumSdk::Qiskit, QuantumSdk::Cirq]);
+        assert_eq!(second.missing, vec!["qiskit"]);
+    }
+}
@@ -0,0 +1,334 @@
+//! `benchmarks.slurm`: run the test command as a Slurm batch job. The job is submitted with
+//! `sbatch`, polled with `squeue` until it leaves the queue, and its output is read back from
+//! files under `.qernel/slurm/`, so the project has to sit on a filesystem the compute nodes
+//! share with the machine running qernel.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use codex_core::exec::{ExecToolCallOutput, ResourceLimits, SandboxType, StreamOutput};
+
+use crate::cmd::prototype::audit;
+use crate::cmd::prototype::environment::{prepare_command, PreparedCommand};
+use crate::cmd::prototype::interrupt::interrupted;
+use crate::config::SlurmConfig;
+use crate::error::QernelError;
+
+const DEFAULT_TEMPLATE: &str = "#!/bin/bash\n{{command}}\n";
+const DEFAULT_POLL_SECS: u64 = 10;
+/// How long a job may take, queueing included, without `max_wait_secs`
+const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(24 * 3600);
+/// How long to wait for the job's files to show up after it left the queue (NFS attribute
+/// caching can hide them for a while)
+const FILE_GRACE: Duration = Duration::from_secs(30);
+/// `squeue` failures in a row before giving up on the job
+const MAX_POLL_ERRORS: u32 = 5;
+
+/// Files of one job under `.qernel/slurm/`
+struct JobFiles {
+    script: PathBuf,
+    stdout: PathBuf,
+    stderr: PathBuf,
+    exit: PathBuf,
+}
+
+impl JobFiles {
+    fn new(cwd: &Path) -> Result<Self> {
+        let dir = cwd.join(".qernel").join("slurm");
+        std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let stem = format!("{}-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S%3f"), std::process::id());
+        Ok(Self {
+            script: dir.join(format!("{stem}.sh")),
+            stdout: dir.join(format!("{stem}.out")),
+            stderr: dir.join(format!("{stem}.err")),
+            exit: dir.join(format!("{stem}.exit")),
+        })
+    }
+
+    fn remove(&self) {
+        for path in [&self.script, &self.stdout, &self.stderr, &self.exit] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Run `argv` as a Slurm job in the project's Python environment and wait for it. `live`
+/// prints the job's output as it is written (`--stream-tests`). Ctrl-C cancels the job.
+pub fn run_job(config: &SlurmConfig, argv: &[String], cwd: &Path, limits: &ResourceLimits, live: bool) -> Result<ExecToolCallOutput> {
+    let PreparedCommand { argv: cmd, env, sandbox } = prepare_command(argv, cwd)?;
+    if !matches!(sandbox, SandboxType::None) {
+        return Err(QernelError::Config("benchmarks.slurm cannot be combined with exec.backend docker".to_string()).into());
+    }
+    let job_name = format!("qernel-{}", cwd.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default());
+    let files = JobFiles::new(cwd)?;
+    let script = render_script(&load_template(config, cwd)?, &cmd, &job_name, cwd, &files.exit)?;
+    std::fs::write(&files.script, script).with_context(|| format!("failed to write {}", files.script.display()))?;
+
+    let _span = tracing::info_span!("slurm", command = %cmd.join(" ")).entered();
+    let started = Instant::now();
+    let submitted = sbatch(config, &job_name, cwd, limits, &files, &env);
+    let job_id = match submitted {
+        Ok(id) => id,
+        Err(e) => {
+            files.remove();
+            return Err(e);
+        }
+    };
+    tracing::info!(job_id = %job_id, "submitted");
+    if live {
+        eprintln!("qernel: submitted Slurm job {job_id}");
+    }
+
+    let poll = Duration::from_secs(config.poll_secs.unwrap_or(DEFAULT_POLL_SECS).max(1));
+    let max_wait = config.max_wait_secs.map_or(DEFAULT_MAX_WAIT, Duration::from_secs);
+    let mut tail = Tail::default();
+    let mut last_state = String::new();
+    let mut errors = 0;
+    let out = loop {
+        if wait_interruptible(poll.min(max_wait.saturating_sub(started.elapsed()))) {
+            scancel(&job_id);
+            files.remove();
+            return Err(QernelError::Exec(format!("Slurm job {job_id} cancelled")).into());
+        }
+        if live {
+            tail.print(&files);
+        }
+        // Stuck in the queue or running past its welcome: a timed-out run, like a local one
+        if started.elapsed() >= max_wait {
+            scancel(&job_id);
+            tracing::warn!(job_id = %job_id, "cancelled after {}s", max_wait.as_secs());
+            break abandoned_output(&job_id, &last_state, &files, max_wait);
+        }
+        match job_state(&job_id) {
+            Ok(Some(state)) => {
+                errors = 0;
+                if state != last_state {
+                    tracing::info!(job_id = %job_id, state = %state, "job state");
+                    if live {
+                        eprintln!("qernel: Slurm job {job_id} is {}", state.to_lowercase());
+                    }
+                    last_state = state;
+                }
+            }
+            Ok(None) => break collect_output(&job_id, &files, started.elapsed()),
+            Err(e) => {
+                errors += 1;
+                if errors >= MAX_POLL_ERRORS {
+                    files.remove();
+                    return Err(QernelError::Exec(format!("could not poll Slurm job {job_id}: {e}")).into());
+                }
+                tracing::warn!(job_id = %job_id, "squeue failed: {e}");
+            }
+        }
+    };
+
+    if live {
+        tail.print(&files);
+    }
+    files.remove();
+    audit::record("exec", serde_json::json!({
+        "command": argv,
+        "slurm_job": job_id,
+        "exit_code": out.exit_code,
+        "duration_ms": out.duration.as_millis() as u64,
+        "stdout_sha256": audit::sha256_hex(out.stdout.text.as_bytes()),
+        "stderr_sha256": audit::sha256_hex(out.stderr.text.as_bytes()),
+    }));
+    Ok(out)
+}
+
+fn load_template(config: &SlurmConfig, cwd: &Path) -> Result<String> {
+    match (&config.template, &config.template_file) {
+        (Some(_), Some(_)) => Err(QernelError::Config("set only one of benchmarks.slurm.template and template_file".to_string()).into()),
+        (Some(template), None) => Ok(template.clone()),
+        (None, Some(file)) => {
+            let path = cwd.join(file);
+            std::fs::read_to_string(&path).with_context(|| format!("failed to read benchmarks.slurm.template_file {}", path.display()))
+        }
+        (None, None) => Ok(DEFAULT_TEMPLATE.to_string()),
+    }
+}
+
+/// Fill in the template; `{{command}}` becomes the command followed by a line that writes
+/// its exit code to `exit_file`, which also works under `set -e`
+fn render_script(template: &str, cmd: &[String], job_name: &str, cwd: &Path, exit_file: &Path) -> Result<String> {
+    if !template.contains("{{command}}") {
+        return Err(QernelError::Config("benchmarks.slurm template has no {{command}} placeholder".to_string()).into());
+    }
+    let quoted = shlex::try_join(cmd.iter().map(String::as_str))
+        .map_err(|e| QernelError::Config(format!("cannot quote `{}` for a batch script: {e}", cmd.join(" "))))?;
+    let exit_file = shlex::try_quote(&exit_file.to_string_lossy()).map_err(|e| QernelError::Config(e.to_string()))?.into_owned();
+    let command = format!("{quoted} && qernel_status=0 || qernel_status=$?\necho \"$qernel_status\" > {exit_file}");
+    Ok(template
+        .replace("{{job_name}}", job_name)
+        .replace("{{cwd}}", &cwd.to_string_lossy())
+        .replace("{{command}}", &command))
+}
+
+/// Submit the script and return the job id. Memory and wall-clock limits become the job's
+/// `--mem` and `--time`; `sbatch_args` come after them so they can override both.
+fn sbatch(config: &SlurmConfig, job_name: &str, cwd: &Path, limits: &ResourceLimits, files: &JobFiles, env: &std::collections::HashMap<String, String>) -> Result<String> {
+    let mut args: Vec<String> = Vec::new();
+    if let Some(bytes) = limits.memory_bytes {
+        args.push(format!("--mem={}M", bytes.div_ceil(1024 * 1024)));
+    }
+    if let Some(wall) = limits.wall_clock {
+        let secs = wall.as_secs().max(1);
+        args.push(format!("--time={}:{:02}", secs / 60, secs % 60));
+    }
+    args.extend(config.sbatch_args.iter().cloned());
+    args.extend([
+        "--parsable".to_string(),
+        format!("--job-name={job_name}"),
+        format!("--chdir={}", cwd.display()),
+        format!("--output={}", files.stdout.display()),
+        format!("--error={}", files.stderr.display()),
+    ]);
+    args.push(files.script.to_string_lossy().into_owned());
+
+    // The job inherits this environment (sbatch's default --export=ALL), venv PATH included
+    let out = Command::new("sbatch")
+        .args(&args)
+        .current_dir(cwd)
+        .env_clear()
+        .envs(env)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| QernelError::Exec(format!("could not run sbatch: {e}")))?;
+    if !out.status.success() {
+        return Err(QernelError::Exec(format!("sbatch failed: {}", String::from_utf8_lossy(&out.stderr).trim())).into());
+    }
+    // `--parsable` prints `<id>` or `<id>;<cluster>`
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let id = stdout.trim().split(';').next().unwrap_or_default().to_string();
+    if id.is_empty() {
+        return Err(QernelError::Exec("sbatch did not print a job id".to_string()).into());
+    }
+    Ok(id)
+}
+
+/// The job's state while it is queued or running, `None` once it has left the queue
+fn job_state(job_id: &str) -> Result<Option<String>, String> {
+    let out = Command::new("squeue")
+        .args(["--noheader", "--jobs", job_id, "--format=%T"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    if !out.status.success() {
+        // Finished jobs drop out of squeue's view after MinJobAge
+        if stderr.contains("Invalid job id") {
+            return Ok(None);
+        }
+        return Err(stderr.trim().to_string());
+    }
+    let state = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    Ok(match state.as_str() {
+        "" | "COMPLETED" | "FAILED" | "CANCELLED" | "TIMEOUT" | "OUT_OF_MEMORY" | "NODE_FAIL" | "PREEMPTED" | "BOOT_FAIL" | "DEADLINE" => None,
+        _ => Some(state),
+    })
+}
+
+/// The final state from accounting, e.g. `TIMEOUT` or `OUT_OF_MEMORY`, when it is enabled
+fn accounting_state(job_id: &str) -> Option<String> {
+    let out = Command::new("sacct")
+        .args(["--noheader", "--allocations", "--parsable2", "--format=State", "--jobs", job_id])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let state = String::from_utf8_lossy(&out.stdout).lines().next()?.trim().to_string();
+    (out.status.success() && !state.is_empty()).then_some(state)
+}
+
+fn collect_output(job_id: &str, files: &JobFiles, duration: Duration) -> ExecToolCallOutput {
+    let deadline = Instant::now() + FILE_GRACE;
+    let exit_code = loop {
+        if let Some(code) = std::fs::read_to_string(&files.exit).ok().and_then(|s| s.trim().parse::<i32>().ok()) {
+            break Some(code);
+        }
+        if Instant::now() >= deadline || wait_interruptible(Duration::from_secs(1)) {
+            break None;
+        }
+    };
+    let stdout = std::fs::read_to_string(&files.stdout).unwrap_or_default();
+    let mut stderr = std::fs::read_to_string(&files.stderr).unwrap_or_default();
+    let mut timed_out = false;
+    let exit_code = exit_code.unwrap_or_else(|| {
+        // The job ended before the command did: killed at its time limit, out of memory,
+        // cancelled, or a node failure
+        let state = accounting_state(job_id).unwrap_or_else(|| "unknown".to_string());
+        timed_out = state.starts_with("TIMEOUT");
+        stderr.push_str(&format!("\nqernel: Slurm job {job_id} ended ({state}) before the command finished\n"));
+        if timed_out { 124 } else { -1 }
+    });
+    let aggregated = format!("{stdout}{stderr}");
+    ExecToolCallOutput {
+        exit_code,
+        stdout: StreamOutput { text: stdout, truncated_after_lines: None },
+        stderr: StreamOutput { text: stderr, truncated_after_lines: None },
+        aggregated_output: StreamOutput { text: aggregated, truncated_after_lines: None },
+        duration,
+        timed_out,
+    }
+}
+
+/// The output of a job cancelled for taking longer than `max_wait`: whatever it wrote so far
+fn abandoned_output(job_id: &str, state: &str, files: &JobFiles, max_wait: Duration) -> ExecToolCallOutput {
+    let stdout = std::fs::read_to_string(&files.stdout).unwrap_or_default();
+    let mut stderr = std::fs::read_to_string(&files.stderr).unwrap_or_default();
+    let state = if state.is_empty() { "submitted".to_string() } else { state.to_lowercase() };
+    stderr.push_str(&format!(
+        "\nqernel: Slurm job {job_id} was still {state} after {}s (benchmarks.slurm.max_wait_secs); cancelled\n",
+        max_wait.as_secs()
+    ));
+    let aggregated = format!("{stdout}{stderr}");
+    ExecToolCallOutput {
+        exit_code: 124,
+        stdout: StreamOutput { text: stdout, truncated_after_lines: None },
+        stderr: StreamOutput { text: stderr, truncated_after_lines: None },
+        aggregated_output: StreamOutput { text: aggregated, truncated_after_lines: None },
+        duration: max_wait,
+        timed_out: true,
+    }
+}
+
+fn scancel(job_id: &str) {
+    let _ = Command::new("scancel").arg(job_id).stdout(Stdio::null()).stderr(Stdio::null()).status();
+}
+
+/// Sleep for `duration` in short steps; true as soon as Ctrl-C is pressed
+fn wait_interruptible(duration: Duration) -> bool {
+    let until = Instant::now() + duration;
+    while Instant::now() < until {
+        if interrupted() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100).min(until.saturating_duration_since(Instant::now())));
+    }
+    interrupted()
+}
+
+/// How much of the job's output files `--stream-tests` has printed
+#[derive(Default)]
+struct Tail {
+    stdout: u64,
+    stderr: u64,
+}
+
+impl Tail {
+    fn print(&mut self, files: &JobFiles) {
+        use std::io::{Read, Seek, SeekFrom, Write};
+        for (path, offset, is_stderr) in [(&files.stdout, &mut self.stdout, false), (&files.stderr, &mut self.stderr, true)] {
+            let Ok(mut file) = std::fs::File::open(path) else { continue };
+            let mut new = Vec::new();
+            if file.seek(SeekFrom::Start(*offset)).is_err() || file.read_to_end(&mut new).is_err() {
+                continue;
+            }
+            *offset += new.len() as u64;
+            let _ = if is_stderr { std::io::stderr().write_all(&new) } else { std::io::stdout().write_all(&new) };
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! `benchmarks.suites`: several named test commands run one after another each iteration.
+//! A project without suites has a single required suite, `benchmarks.test_command`.
+
+use anyhow::Result;
+use codex_core::exec::ExecToolCallOutput;
+use serde::{Deserialize, Serialize};
+
+use crate::config::BenchmarkConfig;
+
+/// Name of the suite made from `benchmarks.test_command`
+pub const DEFAULT_SUITE: &str = "tests";
+
+#[derive(Debug, Clone)]
+pub struct TestSuite {
+    pub name: String,
+    pub command: String,
+    pub argv: Vec<String>,
+    pub weight: f64,
+    pub required: bool,
+}
+
+/// How one suite did in one iteration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuiteResult {
+    pub name: String,
+    pub command: String,
+    pub required: bool,
+    pub weight: f64,
+    pub exit_code: i32,
+    pub passed: bool,
+}
+
+/// The suites to run, in the order of qernel.yaml; fails on empty commands and bad weights
+pub fn test_suites(benchmarks: &BenchmarkConfig) -> Result<Vec<TestSuite>> {
+    if benchmarks.suites.is_empty() {
+        let command = benchmarks.test_command.clone();
+        let argv = shlex::split(&command).unwrap_or_else(|| vec![command.clone()]);
+        if argv.is_empty() {
+            anyhow::bail!("empty test_cmd");
+        }
+        return Ok(vec![TestSuite { name: DEFAULT_SUITE.to_string(), command, argv, weight: 1.0, required: true }]);
+    }
+    let mut suites = Vec::new();
+    for (name, suite) in &benchmarks.suites {
+        let argv = shlex::split(&suite.command).filter(|a| !a.is_empty());
+        let Some(argv) = argv else {
+            anyhow::bail!("benchmarks.suites '{}': command is empty or not valid shell syntax", name);
+        };
+        if !suite.weight.is_finite() || suite.weight < 0.0 {
+            anyhow::bail!("benchmarks.suites '{}': weight must be a non-negative number", name);
+        }
+        suites.push(TestSuite {
+            name: name.clone(),
+            command: suite.command.clone(),
+            argv,
+            weight: suite.weight,
+            required: suite.required,
+        });
+    }
+    if !suites.iter().any(|s| s.required) {
+        anyhow::bail!("benchmarks.suites: at least one suite must be required");
+    }
+    Ok(suites)
+}
+
+/// The test command as the model sees it: each suite's command on its own line
+pub fn describe_suites(suites: &[TestSuite]) -> String {
+    match suites {
+        [only] => only.command.clone(),
+        _ => suites
+            .iter()
+            .map(|s| format!("\n  {} ({}): {}", s.name, if s.required { "required" } else { "optional" }, s.command))
+            .collect(),
+    }
+}
+
+/// Results of one iteration's suites, in the order they ran
+pub struct SuiteRuns(pub Vec<(SuiteResult, ExecToolCallOutput)>);
+
+impl SuiteRuns {
+    pub fn results(&self) -> Vec<SuiteResult> {
+        self.0.iter().map(|(result, _)| result.clone()).collect()
+    }
+
+    /// Every required suite passed; optional ones do not count
+    pub fn required_passed(&self) -> bool {
+        self.0.iter().all(|(r, _)| r.passed || !r.required)
+    }
+
+    /// Exit code of the first failing required suite, else of the first failing one, else 0
+    pub fn exit_code(&self) -> i32 {
+        let failing = |required: bool| self.0.iter().find(|(r, _)| !r.passed && (r.required || !required));
+        failing(true).or_else(|| failing(false)).map_or(0, |(r, _)| r.exit_code)
+    }
+
+    /// Names of the suites killed at the wall-clock limit
+    pub fn timed_out(&self) -> Vec<String> {
+        self.0.iter().filter(|(_, out)| out.timed_out).map(|(r, _)| r.name.clone()).collect()
+    }
+
+    /// All suites' stdout; with several suites each one's output is headed by its name
+    pub fn stdout(&self) -> String {
+        self.combined(|out| &out.stdout.text)
+    }
+
+    pub fn stderr(&self) -> String {
+        self.combined(|out| &out.stderr.text)
+    }
+
+    fn combined(&self, text: impl Fn(&ExecToolCallOutput) -> &String) -> String {
+        if let [(_, out)] = self.0.as_slice() {
+            return text(out).clone();
+        }
+        self.0
+            .iter()
+            .filter(|(_, out)| !text(out).is_empty())
+            .map(|(r, out)| format!("== {} ==\n{}", r.name, text(out)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Weighted share of suites that passed, from 0 to 1
+pub fn score(results: &[SuiteResult]) -> f64 {
+    let total: f64 = results.iter().map(|r| r.weight).sum();
+    if total <= 0.0 {
+        return if results.iter().all(|r| r.passed) { 1.0 } else { 0.0 };
+    }
+    results.iter().filter(|r| r.passed).map(|r| r.weight).sum::<f64>() / total
+}
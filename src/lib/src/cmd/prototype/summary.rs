@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::cmd::common::usage::{self, Usage};
+use crate::cmd::prototype::agent::RunFailure;
+use crate::cmd::prototype::checkpoint::checkpoint_path;
+use crate::cmd::prototype::metrics::{MetricReading, metrics_path};
+use crate::cmd::prototype::patch_stats::PatchStats;
+use crate::cmd::prototype::plan::plan_path;
+use crate::cmd::prototype::report::{report_path, test_output_path};
+use crate::cmd::prototype::suites::SuiteResult;
+use crate::cmd::prototype::turns::{list_turns, turn_diff_path};
+use crate::config::Sampling;
+use crate::error::QernelError;
+
+pub fn last_run_path(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("last_run.json")
+}
+
+/// Summary of the most recent prototype run in this project, if one was recorded
+pub fn load_last_run(cwd: &Path) -> Option<RunSummary> {
+    let text = std::fs::read_to_string(last_run_path(cwd)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// How a run ended, as reported in `--output-json`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Success,
+    /// Ctrl-C, `.qernel/STOP`, or the user declined to continue
+    #[default]
+    Stopped,
+    MaxIterations,
+    BudgetExceeded,
+    Error,
+}
+
+impl RunOutcome {
+    /// e.g. `reached max iterations`, for status lines
+    pub fn describe(self) -> &'static str {
+        match self {
+            RunOutcome::Success => "succeeded",
+            RunOutcome::Stopped => "stopped",
+            RunOutcome::MaxIterations => "reached max iterations",
+            RunOutcome::BudgetExceeded => "exceeded its budget",
+            RunOutcome::Error => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricResult {
+    pub name: String,
+    pub value: Option<f64>,
+    pub target: Option<f64>,
+    pub met: bool,
+}
+
+/// Files the run left behind, absolute; only those that exist are listed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunArtifacts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_output: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<PathBuf>,
+    /// `.qernel/artifacts/metrics.svg`, drawn after each test run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_chart: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_log: Option<PathBuf>,
+    pub turn_diffs: Vec<PathBuf>,
+    /// Copies of the last test run's `benchmarks.artifacts` files
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub test_artifacts: Vec<PathBuf>,
+}
+
+/// Structured result of `qernel prototype --output-json`, for orchestration tools
+/// and the Zoo backend. Written whether the run succeeds or not, and always kept
+/// as `.qernel/last_run.json` for `qernel status`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub outcome: RunOutcome,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// `config`, `provider`, `patch`, `exec` or `ingest` when the error has a known kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+    /// Iterations completed, including those of a resumed checkpoint
+    pub iterations: u32,
+    pub max_iterations: u32,
+    /// Exit code of the last test run, if one finished
+    pub final_exit_code: Option<i32>,
+    /// Readings from the last test run
+    pub metrics: Vec<MetricResult>,
+    /// Per-suite results of the last test run, when `benchmarks.suites` is configured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suites: Vec<SuiteResult>,
+    /// Coverage of src/ in percent after the last test run, with `benchmarks.coverage`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f64>,
+    pub patches_applied: u32,
+    /// Hunks and lines added and removed by the applied patches; `files` counts each file once
+    #[serde(default)]
+    pub churn: PatchStats,
+    /// `agent.temperature`, `agent.top_p` and `agent.seed` as the run used them
+    #[serde(default, skip_serializing_if = "Sampling::is_default")]
+    pub sampling: Sampling,
+    pub usage: Usage,
+    pub elapsed_secs: f64,
+    pub artifacts: RunArtifacts,
+    /// Set by the agent loop when the tests pass and every metric target is met
+    #[serde(skip)]
+    pub passed: bool,
+    #[serde(skip)]
+    pub run_log: Option<PathBuf>,
+    /// Files written by the applied patches, behind `churn.files`
+    #[serde(skip)]
+    pub changed_files: BTreeSet<PathBuf>,
+    /// The typed error behind `error`, for library callers
+    #[serde(skip)]
+    pub failure: Option<QernelError>,
+}
+
+impl RunSummary {
+    pub fn record_metrics(&mut self, readings: &[MetricReading]) {
+        self.metrics = readings
+            .iter()
+            .map(|r| MetricResult { name: r.name.clone(), value: r.value, target: r.target, met: r.met() })
+            .collect();
+    }
+
+    /// Count a patch that was applied, writing `files`
+    pub fn record_patch(&mut self, stats: &PatchStats, files: &[PathBuf]) {
+        self.patches_applied += 1;
+        self.changed_files.extend(files.iter().cloned());
+        self.churn.files = self.changed_files.len();
+        self.churn.hunks += stats.hunks;
+        self.churn.added += stats.added;
+        self.churn.removed += stats.removed;
+    }
+
+    /// Fill in the outcome, usage, elapsed time and artifacts once the run has returned
+    pub fn finish(&mut self, result: &Result<()>, cwd: &Path, started: Instant) {
+        self.outcome = match result {
+            Ok(()) if self.passed => RunOutcome::Success,
+            Ok(()) => RunOutcome::Stopped,
+            Err(e) => match e.downcast_ref::<RunFailure>() {
+                Some(RunFailure::MaxIterations(_)) => RunOutcome::MaxIterations,
+                Some(RunFailure::BudgetExceeded(_)) => RunOutcome::BudgetExceeded,
+                None => RunOutcome::Error,
+            },
+        };
+        self.error = result.as_ref().err().map(|e| format!("{:#}", e));
+        self.failure = result.as_ref().err().and_then(QernelError::find).cloned();
+        self.error_kind = self.failure.as_ref().map(|f| f.kind().to_string());
+        self.usage = usage::totals();
+        self.elapsed_secs = started.elapsed().as_secs_f64();
+        self.finished_at = Some(Utc::now());
+        let started_at = SystemTime::now() - started.elapsed();
+
+        let existing = |p: PathBuf| p.is_file().then_some(p);
+        // A report left by an earlier run does not describe this one
+        let succeeded = self.outcome == RunOutcome::Success;
+        self.artifacts = RunArtifacts {
+            plan: existing(plan_path(cwd)),
+            report: existing(report_path(cwd)).filter(|_| succeeded),
+            test_output: existing(test_output_path(cwd)).filter(|_| succeeded),
+            metrics: existing(metrics_path(cwd)),
+            // Recorded by the agent loop; a chart left by an earlier run is not listed
+            metrics_chart: self.artifacts.metrics_chart.take().and_then(existing),
+            checkpoint: existing(checkpoint_path(cwd)),
+            run_log: self.run_log.clone().and_then(existing),
+            // Diffs of earlier runs stay in .qernel/turns until overwritten
+            turn_diffs: list_turns(cwd)
+                .into_iter()
+                .map(|t| turn_diff_path(cwd, t))
+                .filter(|p| p.metadata().and_then(|m| m.modified()).is_ok_and(|m| m >= started_at))
+                .collect(),
+            // Recorded by the agent loop after each test run
+            test_artifacts: std::mem::take(&mut self.artifacts.test_artifacts).into_iter().filter(|p| p.is_file()).collect(),
+        };
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
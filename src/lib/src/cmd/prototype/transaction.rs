@@ -0,0 +1,287 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Node, Parser};
+use tree_sitter_python as tspy;
+
+use codex_apply_patch::{Hunk, parse_patch};
+
+use crate::error::QernelError;
+
+/// Result of applying a patch as one transaction
+pub enum PatchOutcome {
+    /// Every hunk applied and the files it wrote still parse
+    Applied { stdout: String, files: Vec<PathBuf> },
+    /// Nothing changed on disk; `reason` says which hunk or file failed, for the model
+    RolledBack { reason: String },
+}
+
+/// A syntax error found by tree-sitter, 1-based
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Contents of every file a patch may touch, taken before the first hunk runs
+struct Backup {
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl Backup {
+    fn capture(paths: &[PathBuf]) -> Self {
+        let files = paths.iter().map(|p| (p.clone(), fs::read(p).ok())).collect();
+        Backup { files }
+    }
+
+    fn before(&self, path: &Path) -> Option<&[u8]> {
+        self.files.iter().find(|(p, _)| p == path).and_then(|(_, before)| before.as_deref())
+    }
+
+    /// Put every file back as it was: rewrite changed ones, remove ones the patch added
+    fn restore(&self) -> Result<()> {
+        for (path, before) in &self.files {
+            match before {
+                Some(bytes) => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(path, bytes).with_context(|| format!("failed to restore {}", path.display()))?;
+                }
+                None if path.exists() => {
+                    fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Apply `patch` hunk by hunk, then check that every Python file it wrote still parses.
+/// Any failure restores the files from a backup taken up front, so a patch never lands
+/// half-applied. An error is returned only if the rollback itself fails. Chunks whose
+/// context has drifted may apply with up to `max_fuzz` context lines ignored at each end;
+/// those are listed in the returned stdout.
+pub fn apply_transactional(patch: &str, cwd: &Path, max_fuzz: usize) -> Result<PatchOutcome> {
+    let hunks = match parse_patch(patch) {
+        Ok(args) => args.hunks,
+        Err(e) => return Ok(PatchOutcome::RolledBack { reason: format!("the patch could not be parsed: {}", e) }),
+    };
+    if hunks.is_empty() {
+        return Ok(PatchOutcome::RolledBack { reason: "the patch has no hunks".to_string() });
+    }
+
+    let backup = Backup::capture(&touched_paths(&hunks, cwd));
+    let mut stdout = Vec::new();
+    for (i, hunk) in hunks.iter().enumerate() {
+        let mut stderr = Vec::new();
+        if let Err(e) = codex_apply_patch::apply_hunks_with_fuzz(std::slice::from_ref(hunk), max_fuzz, &mut stdout, &mut stderr) {
+            backup.restore().context(QernelError::Patch("failed to roll back a partially applied patch".to_string()))?;
+            let chunk = match &e {
+                codex_apply_patch::ApplyPatchError::ChunkNotFound { chunk, .. } => describe_chunk(hunk, *chunk),
+                _ => None,
+            };
+            let what = match chunk {
+                Some(chunk) => format!("hunk {} of {} ({}), {}", i + 1, hunks.len(), describe(hunk), chunk),
+                None => format!("hunk {} of {} ({})", i + 1, hunks.len(), describe(hunk)),
+            };
+            let reason = format!("{} failed: {}", what, e);
+            return Ok(PatchOutcome::RolledBack { reason });
+        }
+    }
+
+    let written = written_paths(&hunks, cwd);
+    for path in &written {
+        if path.extension().and_then(|e| e.to_str()) != Some("py") {
+            continue;
+        }
+        let Ok(source) = fs::read_to_string(path) else { continue };
+        // Only errors the patch introduced count; a file that was already broken can be fixed in steps
+        let was_broken = backup
+            .before(path)
+            .is_some_and(|before| python_syntax_error(&String::from_utf8_lossy(before)).is_some());
+        if !was_broken && let Some(err) = python_syntax_error(&source) {
+            backup.restore().context(QernelError::Patch("failed to roll back a patch that broke the syntax".to_string()))?;
+            let rel = path.strip_prefix(cwd).unwrap_or(path);
+            let reason = format!("{}:{}:{}: {} after applying the patch", rel.display(), err.line, err.column, err.message);
+            return Ok(PatchOutcome::RolledBack { reason });
+        }
+    }
+    Ok(PatchOutcome::Applied { stdout: String::from_utf8_lossy(&stdout).into_owned(), files: written })
+}
+
+/// First syntax error in a Python source, if tree-sitter finds one
+pub fn python_syntax_error(source: &str) -> Option<SyntaxError> {
+    let mut parser = Parser::new();
+    parser.set_language(&tspy::language()).expect("load python grammar");
+    let tree = parser.parse(source, None)?;
+    let node = first_error(tree.root_node())?;
+    let pos = node.start_position();
+    let message = if node.is_missing() {
+        format!("syntax error: missing `{}`", node.kind())
+    } else {
+        let text = node.utf8_text(source.as_bytes()).unwrap_or("");
+        let text = text.lines().next().unwrap_or("").trim();
+        let text: String = text.chars().take(40).collect();
+        format!("syntax error near `{}`", text)
+    };
+    Some(SyntaxError { line: pos.row + 1, column: pos.column + 1, message })
+}
+
+fn first_error(node: Node) -> Option<Node> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    if !node.has_error() {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    children.into_iter().find_map(first_error)
+}
+
+/// Every path a hunk may create, change or remove, including move destinations
+fn touched_paths(hunks: &[Hunk], cwd: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for hunk in hunks {
+        let mut add = |p: PathBuf| {
+            if !paths.contains(&p) {
+                paths.push(p);
+            }
+        };
+        add(hunk.resolve_path(cwd));
+        if let Hunk::UpdateFile { move_path: Some(dest), .. } = hunk {
+            add(cwd.join(dest));
+        }
+    }
+    paths
+}
+
+/// Files that exist after the patch, with the contents it wrote
+fn written_paths(hunks: &[Hunk], cwd: &Path) -> Vec<PathBuf> {
+    hunks
+        .iter()
+        .filter_map(|hunk| match hunk {
+            Hunk::AddFile { path, .. } => Some(cwd.join(path)),
+            Hunk::UpdateFile { move_path: Some(dest), .. } => Some(cwd.join(dest)),
+            Hunk::UpdateFile { path, .. } => Some(cwd.join(path)),
+            Hunk::DeleteFile { .. } => None,
+        })
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+/// The hunk's header as the model wrote it, e.g. `Update File: src/main.py`
+fn describe(hunk: &Hunk) -> String {
+    match hunk {
+        Hunk::AddFile { path, .. } => format!("Add File: {}", path.display()),
+        Hunk::DeleteFile { path } => format!("Delete File: {}", path.display()),
+        Hunk::UpdateFile { path, move_path: Some(dest), .. } => {
+            format!("Update File: {} -> {}", path.display(), dest.display())
+        }
+        Hunk::UpdateFile { path, .. } => format!("Update File: {}", path.display()),
+    }
+}
+
+/// Which `@@` chunk of an update hunk failed, e.g. ``chunk 2 of 3 (`@@ def build():`)``; a
+/// chunk without context is named by its first line
+fn describe_chunk(hunk: &Hunk, chunk: usize) -> Option<String> {
+    let Hunk::UpdateFile { chunks, .. } = hunk else { return None };
+    let failed = chunks.get(chunk.checked_sub(1)?)?;
+    let header = match &failed.change_context {
+        Some(context) => format!("`@@ {}`", context.trim()),
+        None => match failed.old_lines.iter().find(|l| !l.trim().is_empty()) {
+            Some(line) => format!("`@@` at `{}`", line.trim()),
+            None => "`@@`".to_string(),
+        },
+    };
+    Some(format!("chunk {} of {} ({})", chunk, chunks.len(), header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(body: &str) -> String {
+        format!("*** Begin Patch\n{}\n*** End Patch", body.trim_end())
+    }
+
+    fn rolled_back(outcome: PatchOutcome) -> String {
+        match outcome {
+            PatchOutcome::RolledBack { reason } => reason,
+            PatchOutcome::Applied { .. } => panic!("the patch applied"),
+        }
+    }
+
+    #[test]
+    fn a_failing_hunk_rolls_back_the_hunks_before_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, new) = (dir.path().join("a.py"), dir.path().join("b.py"), dir.path().join("new.py"));
+        fs::write(&a, "x = 1\n").unwrap();
+        fs::write(&b, "def f():\n    return 1\n\ndef g():\n    return 2\n").unwrap();
+        let body = format!(
+            "*** Update File: {a}\n@@\n-x = 1\n+x = 2\n*** Add File: {new}\n+y = 3\n*** Update File: {b}\n@@ def f():\n-    return 1\n+    return 10\n@@ def g():\n-    return 3\n+    return 30",
+            a = a.display(),
+            b = b.display(),
+            new = new.display()
+        );
+        let reason = rolled_back(apply_transactional(&patch(&body), dir.path(), 0).unwrap());
+        assert!(reason.starts_with("hunk 3 of 3 (Update File: "), "{reason}");
+        assert!(reason.contains("chunk 2 of 2 (`@@ def g():`) failed: Failed to find expected lines"), "{reason}");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "x = 1\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "def f():\n    return 1\n\ndef g():\n    return 2\n");
+        assert!(!new.exists());
+    }
+
+    #[test]
+    fn a_missing_context_line_names_its_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.py");
+        fs::write(&a, "x = 1\n").unwrap();
+        let body = format!("*** Update File: {}\n@@ class Missing:\n-x = 1\n+x = 2", a.display());
+        let reason = rolled_back(apply_transactional(&patch(&body), dir.path(), 0).unwrap());
+        assert!(reason.contains("chunk 1 of 1 (`@@ class Missing:`) failed: Failed to find context"), "{reason}");
+    }
+
+    #[test]
+    fn moves_apply_and_roll_back_as_a_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, moved, other) = (dir.path().join("old.py"), dir.path().join("moved.py"), dir.path().join("other.py"));
+        fs::write(&old, "x = 1\n").unwrap();
+        fs::write(&other, "y = 1\n").unwrap();
+        let rename = format!("*** Update File: {}\n*** Move to: {}\n@@\n-x = 1\n+x = 2", old.display(), moved.display());
+
+        let failing = format!("{}\n*** Update File: {}\n@@\n-y = 5\n+y = 6", rename, other.display());
+        let reason = rolled_back(apply_transactional(&patch(&failing), dir.path(), 0).unwrap());
+        assert!(reason.contains("chunk 1 of 1 (`@@` at `y = 5`)"), "{reason}");
+        assert_eq!(fs::read_to_string(&old).unwrap(), "x = 1\n");
+        assert!(!moved.exists());
+
+        match apply_transactional(&patch(&rename), dir.path(), 0).unwrap() {
+            PatchOutcome::Applied { files, .. } => assert_eq!(files, vec![moved.clone()]),
+            PatchOutcome::RolledBack { reason } => panic!("{reason}"),
+        }
+        assert!(!old.exists());
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "x = 2\n");
+    }
+
+    #[test]
+    fn only_syntax_errors_the_patch_introduces_roll_it_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let (clean, broken) = (dir.path().join("clean.py"), dir.path().join("broken.py"));
+        fs::write(&clean, "def f():\n    return 1\n").unwrap();
+        fs::write(&broken, "def g(:\n    return 1\nx = 1\n").unwrap();
+
+        let breaks = format!("*** Update File: {}\n@@\n-def f():\n+def f(:", clean.display());
+        let reason = rolled_back(apply_transactional(&patch(&breaks), dir.path(), 0).unwrap());
+        assert!(reason.starts_with("clean.py:1:"), "{reason}");
+        assert!(reason.ends_with("after applying the patch"), "{reason}");
+        assert_eq!(fs::read_to_string(&clean).unwrap(), "def f():\n    return 1\n");
+
+        // A file that already did not parse may be fixed over several patches
+        let partial = format!("*** Update File: {}\n@@\n-x = 1\n+x = 2", broken.display());
+        assert!(matches!(apply_transactional(&patch(&partial), dir.path(), 0).unwrap(), PatchOutcome::Applied { .. }));
+        assert_eq!(fs::read_to_string(&broken).unwrap(), "def g(:\n    return 1\nx = 2\n");
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::{Context, Result};
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory holding one unified diff per agent iteration that changed files
+pub fn turns_dir(cwd: &Path) -> PathBuf {
+    cwd.join(".qernel").join("turns")
+}
+
+/// Location of the stored diff for a given iteration
+pub fn turn_diff_path(cwd: &Path, turn: u32) -> PathBuf {
+    turns_dir(cwd).join(format!("{}.diff", turn))
+}
+
+/// Iterations with a stored diff, in ascending order
+pub fn list_turns(cwd: &Path) -> Vec<u32> {
+    let mut turns: Vec<u32> = fs::read_dir(turns_dir(cwd))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    name.strip_suffix(".diff")?.parse().ok()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    turns.sort_unstable();
+    turns
+}
+
+/// File contents captured before a patch is applied; `None` means the file did not exist
+#[derive(Default)]
+pub struct TurnSnapshot {
+    files: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl TurnSnapshot {
+    /// Also capture files a later patch of the same turn touches; files already
+    /// captured keep their contents from before the turn's first patch
+    pub fn add_patch_files(&mut self, patch: &str, cwd: &Path) {
+        for (path, before) in snapshot_patch_files(patch, cwd).files {
+            if !self.files.iter().any(|(p, _)| *p == path) {
+                self.files.push((path, before));
+            }
+        }
+    }
+
+    /// Files the turn's patches changed that still exist, relative to the project
+    pub fn changed_files(&self, cwd: &Path) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|(rel, before)| {
+                let after = fs::read(cwd.join(rel)).ok();
+                after.is_some() && *before != after
+            })
+            .map(|(rel, _)| rel.clone())
+            .collect()
+    }
+}
+
+/// Capture the current contents of every file the patch touches
+pub fn snapshot_patch_files(patch: &str, cwd: &Path) -> TurnSnapshot {
+    let files = patch_paths(patch)
+        .into_iter()
+        .map(|p| {
+            let before = fs::read(cwd.join(&p)).ok();
+            (p, before)
+        })
+        .collect();
+    TurnSnapshot { files }
+}
+
+/// Diff the snapshot against the files on disk and store it as `.qernel/turns/<turn>.diff`.
+/// Returns `None` when the patch left every file unchanged.
+pub fn record_turn_diff(cwd: &Path, turn: u32, snapshot: &TurnSnapshot) -> Result<Option<PathBuf>> {
+    let mut diff = String::new();
+    for (rel, before) in &snapshot.files {
+        let after = fs::read(cwd.join(rel)).ok();
+        if *before == after {
+            continue;
+        }
+        let old_header = if before.is_some() { format!("a/{}", rel) } else { "/dev/null".to_string() };
+        let new_header = if after.is_some() { format!("b/{}", rel) } else { "/dev/null".to_string() };
+        diff.push_str(&format!("diff --git a/{rel} b/{rel}\n", rel = rel));
+        let (Some(old), Some(new)) = (diff_text(before), diff_text(&after)) else {
+            diff.push_str(&format!("--- {}\n+++ {}\n", old_header, new_header));
+            diff.push_str(&binary_summary(before.as_ref().map(|b| b.len()), after.as_ref().map(|b| b.len())));
+            diff.push('\n');
+            continue;
+        };
+        diff.push_str(
+            &TextDiff::from_lines(old, new)
+                .unified_diff()
+                .context_radius(3)
+                .header(&old_header, &new_header)
+                .to_string(),
+        );
+    }
+    if diff.is_empty() {
+        return Ok(None);
+    }
+    let path = turn_diff_path(cwd, turn);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, diff).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(Some(path))
+}
+
+/// Contents to diff as text: empty for a missing file, `None` for a binary one
+fn diff_text(bytes: &Option<Vec<u8>>) -> Option<&str> {
+    match bytes {
+        None => Some(""),
+        Some(b) if is_binary(b) => None,
+        Some(b) => std::str::from_utf8(b).ok(),
+    }
+}
+
+/// Like git: content with a NUL byte near the start, or that is not UTF-8, is not diffed as text
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(8000)].contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// What a diff shows instead of hunks for a binary file, from its size before and after
+/// (`None` where it did not exist)
+pub fn binary_summary(before: Option<usize>, after: Option<usize>) -> String {
+    match (before, after) {
+        (None, Some(n)) => format!("Binary file added ({} bytes)", n),
+        (Some(n), None) => format!("Binary file deleted ({} bytes)", n),
+        (Some(a), Some(b)) if a != b => format!("Binary file changed ({} -> {} bytes)", a, b),
+        (_, n) => format!("Binary file changed ({} bytes)", n.unwrap_or(0)),
+    }
+}
+
+/// Paths named by `*** Add/Update/Delete File:` and `*** Move to:` headers, in patch order
+fn patch_paths(patch: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in patch.lines() {
+        let path_opt = line
+            .strip_prefix("*** Add File: ")
+            .or_else(|| line.strip_prefix("*** Update File: "))
+            .or_else(|| line.strip_prefix("*** Delete File: "))
+            .or_else(|| line.strip_prefix("*** Move to: "));
+        if let Some(raw) = path_opt {
+            let raw = raw.trim().to_string();
+            if !paths.contains(&raw) {
+                paths.push(raw);
+            }
+        }
+    }
+    paths
+}
+
+/// Apply a stored turn diff to in-memory file contents, or undo it with `reverse`. Every
+/// context and removed line must match exactly, so a diff only applies to the tree it was
+/// recorded against. Binary files are recorded without their contents and are skipped.
+pub fn apply_turn_diff(files: &mut BTreeMap<String, String>, diff: &str, reverse: bool) -> Result<()> {
+    for file in split_file_diffs(diff).into_iter().filter(|f| !f.binary) {
+        check_relative_path(&file.path)?;
+        let (existed, exists) = if reverse { (file.new_exists, file.old_exists) } else { (file.old_exists, file.new_exists) };
+        let before = match files.get(&file.path) {
+            Some(text) if existed => text.clone(),
+            Some(_) => anyhow::bail!("{} already exists", file.path),
+            None if existed => anyhow::bail!("{} does not exist", file.path),
+            None => String::new(),
+        };
+        let lines: Vec<&str> = before.split_inclusive('\n').collect();
+        let mut out = String::new();
+        let mut cursor = 0;
+        for hunk in &file.hunks {
+            let (start, count) = if reverse { hunk.new } else { hunk.old };
+            // An empty range starts after the given line rather than at it
+            let at = if count == 0 { start } else { start.saturating_sub(1) };
+            if at < cursor || at > lines.len() {
+                anyhow::bail!("{}: hunk at line {} is out of range", file.path, start);
+            }
+            out.extend(lines[cursor..at].iter().copied());
+            cursor = at;
+            for (tag, text) in &hunk.lines {
+                let (remove, add) = match (tag, reverse) {
+                    (' ', _) => (true, true),
+                    ('-', false) | ('+', true) => (true, false),
+                    _ => (false, true),
+                };
+                if remove {
+                    if lines.get(cursor) != Some(&text.as_str()) {
+                        anyhow::bail!("{}: line {} does not match the diff", file.path, cursor + 1);
+                    }
+                    cursor += 1;
+                }
+                if add {
+                    out.push_str(text);
+                }
+            }
+        }
+        out.extend(lines[cursor..].iter().copied());
+        if exists {
+            files.insert(file.path, out);
+        } else {
+            files.remove(&file.path);
+        }
+    }
+    Ok(())
+}
+
+/// Every file a stored turn diff records, text or binary, relative to the project
+pub fn turn_diff_files(diff: &str) -> Vec<String> {
+    split_file_diffs(diff).into_iter().map(|file| file.path).collect()
+}
+
+/// A path recorded in a diff or bundle must stay inside the project it is applied to: relative,
+/// and made of plain names only (no `..`, `.`, root or drive prefix)
+pub fn check_relative_path(path: &str) -> Result<()> {
+    let rel = Path::new(path);
+    let plain = !path.is_empty() && rel.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    if !plain {
+        anyhow::bail!("unsafe path {:?}: paths must be relative and stay inside the project", path);
+    }
+    Ok(())
+}
+
+struct FilePatch {
+    path: String,
+    old_exists: bool,
+    new_exists: bool,
+    /// Recorded as a "Binary file ..." line rather than hunks
+    binary: bool,
+    hunks: Vec<Hunk>,
+}
+
+struct Hunk {
+    /// `(start, count)` from the `@@ -a,b +c,d @@` header
+    old: (usize, usize),
+    new: (usize, usize),
+    /// Tag (` `, `-` or `+`) and the line including its newline, if it had one
+    lines: Vec<(char, String)>,
+}
+
+fn split_file_diffs(diff: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let path = rest.split_once(" b/").map(|(_, b)| b).unwrap_or(rest);
+            files.push(FilePatch { path: path.to_string(), old_exists: true, new_exists: true, binary: false, hunks: Vec::new() });
+            continue;
+        }
+        let Some(file) = files.last_mut() else { continue };
+        if file.hunks.is_empty() && line.starts_with("--- ") {
+            file.old_exists = line != "--- /dev/null";
+        } else if file.hunks.is_empty() && line.starts_with("+++ ") {
+            file.new_exists = line != "+++ /dev/null";
+        } else if file.hunks.is_empty() && line.starts_with("Binary file") {
+            file.binary = true;
+        } else if line.starts_with("@@") {
+            let mut parts = line.split_whitespace().skip(1);
+            let range = |p: Option<&str>, sign: char| {
+                let (start, count) = p.and_then(|s| s.strip_prefix(sign)).map(|s| s.split_once(',').unwrap_or((s, "1"))).unwrap_or(("0", "0"));
+                (start.parse().unwrap_or(0), count.parse().unwrap_or(0))
+            };
+            let old = range(parts.next(), '-');
+            let new = range(parts.next(), '+');
+            file.hunks.push(Hunk { old, new, lines: Vec::new() });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            if line.starts_with('\\') {
+                // "\ No newline at end of file" belongs to the line before it
+                if let Some((_, text)) = hunk.lines.last_mut() {
+                    text.pop();
+                }
+            } else if let Some(tag) = line.chars().next().filter(|c| matches!(c, ' ' | '-' | '+')) {
+                hunk.lines.push((tag, format!("{}\n", &line[1..])));
+            } else if line.is_empty() {
+                // Editors strip the trailing space of an empty context line
+                hunk.lines.push((' ', "\n".to_string()));
+            }
+        }
+    }
+    files
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+
+use crate::cmd::common::endpoint::{BACKENDS, Backend, ollama_host};
+use crate::cmd::common::models;
+use crate::config::{find_config_key, set_user_value};
+use crate::error::QernelError;
+use crate::util::{edit_config, sym_check, sym_cross};
+
+/// Choose where OpenAI models are served from and configure the Azure resource, then show
+/// the result (or with `models`, the models available). With no flags it only shows the
+/// current setup.
+pub fn handle_provider(set: Option<String>, base_url: Option<String>, api_version: Option<String>, deployments: Vec<(String, String)>, models: bool) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    if let Some(name) = &set {
+        let name = name.trim().to_lowercase();
+        if !BACKENDS.contains(&name.as_str()) {
+            anyhow::bail!("Unknown provider '{}'. Providers: {}", name, BACKENDS.join(", "));
+        }
+        set_user_value(&find_config_key("provider")?, &name)?;
+    }
+    if let Some(url) = &base_url {
+        set_user_value(&find_config_key("azure.base_url")?, url.trim_end_matches('/'))?;
+    }
+    if let Some(version) = &api_version {
+        set_user_value(&find_config_key("azure.api_version")?, version)?;
+    }
+    if !deployments.is_empty() {
+        edit_config(|cfg| {
+            let azure = cfg.azure.get_or_insert_with(Default::default);
+            for (model, deployment) in deployments {
+                // `MODEL=` drops the mapping again
+                if deployment.trim().is_empty() {
+                    azure.deployments.remove(&model);
+                } else {
+                    azure.deployments.insert(model, deployment.trim().to_string());
+                }
+            }
+            Ok(())
+        })?;
+    }
+    if models {
+        return list_models(ce);
+    }
+
+    let backend = match Backend::active() {
+        Ok(backend) => backend,
+        Err(e) => {
+            println!("{} {}", sym_cross(ce), e);
+            return Ok(());
+        }
+    };
+    println!("{} OpenAI models are served by {}", sym_check(ce), backend.label());
+    if let Backend::Azure { base_url, api_version, deployments } = &backend {
+        println!("  endpoint:    {}", base_url);
+        println!("  api-version: {}", api_version);
+        if deployments.is_empty() {
+            println!("  deployments: none mapped; each model is requested under its own name");
+        } else {
+            println!("  deployments:");
+            for (model, deployment) in deployments {
+                println!("    {} -> {}", model, deployment);
+            }
+        }
+    }
+    let name = backend.name();
+    if crate::util::get_api_key(name, None).is_none() {
+        println!(
+            "{} no {} key (set {} or run `qernel config set secrets.{}`)",
+            sym_cross(ce),
+            name,
+            crate::util::provider_env_var(name).unwrap_or("the provider's key variable"),
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Print the models the active backend and the local Ollama server offer, with context sizes
+fn list_models(ce: bool) -> Result<()> {
+    let client = Client::builder().timeout(std::time::Duration::from_secs(30)).build().context("create http client")?;
+    let backend = Backend::active()?;
+    let name = backend.name();
+    match crate::util::get_api_key(name, None) {
+        Some(key) => {
+            let resp = backend.models(&client, &key).send().with_context(|| format!("list {} models", backend.label()))?;
+            let status = resp.status();
+            let text = resp.text().unwrap_or_default();
+            if !status.is_success() {
+                return Err(QernelError::Provider { provider: backend.label().to_string(), status: status.as_u16(), body: text }.into());
+            }
+            let body: serde_json::Value = serde_json::from_str(&text).context("parse model list")?;
+            let mut ids: Vec<&str> = body["data"].as_array().into_iter().flatten().filter_map(|m| m["id"].as_str()).collect();
+            ids.sort_unstable();
+            println!("{} models:", backend.label());
+            print_models(&ids);
+        }
+        None => println!(
+            "{} no {} key, so its models cannot be listed (set {} or run `qernel config set secrets.{}`)",
+            sym_cross(ce),
+            name,
+            crate::util::provider_env_var(name).unwrap_or("the provider's key variable"),
+            name
+        ),
+    }
+
+    // A local Ollama server is optional; only list it when one answers
+    let host = ollama_host();
+    let tags = client
+        .get(format!("{}/api/tags", host))
+        .timeout(std::time::Duration::from_secs(3))
+        .send()
+        .ok()
+        .filter(|r| r.status().is_success())
+        .and_then(|r| r.json::<serde_json::Value>().ok());
+    match tags {
+        Some(body) => {
+            let mut names: Vec<&str> = body["models"].as_array().into_iter().flatten().filter_map(|m| m["name"].as_str()).collect();
+            names.sort_unstable();
+            println!("\nOllama models at {}:", host);
+            print_models(&names);
+        }
+        None => println!("\n{} no Ollama server at {} (set OLLAMA_HOST)", crate::util::sym_question(ce), host),
+    }
+    println!("\nPass a name to --model, or set agent.model / explain.model with `qernel config set`.");
+    Ok(())
+}
+
+/// One model per line with its context window, or `?` when qernel only has a guess for it
+fn print_models(names: &[&str]) {
+    if names.is_empty() {
+        println!("  (none)");
+        return;
+    }
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+    for name in names {
+        let context = if models::is_known(name) {
+            format!("{}k context", models::capabilities(name).max_context / 1000)
+        } else {
+            "?".to_string()
+        };
+        println!("  {:<width$}  {}", name, context, width = width);
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::cmd::prototype::metrics::metrics_path;
+use crate::cmd::prototype::report::{report_path, test_output_path};
+use crate::cmd::prototype::summary::{RunSummary, load_last_run};
+use crate::cmd::zoo;
+use crate::config::load_config;
+
+/// Version of [`RunManifest`]; bumped when a field changes meaning or is removed
+pub const MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+/// Larger files are left out of the upload with a warning
+const MAX_ARTIFACT_BYTES: usize = 5 * 1024 * 1024;
+
+/// Body of `POST <server>/_api/runs`: one prototype run and the files it produced
+#[derive(Debug, Serialize)]
+pub struct RunManifest {
+    pub schema_version: u32,
+    pub project: ProjectInfo,
+    pub git: GitInfo,
+    /// `.qernel/last_run.json`, absent if the project has never been prototyped
+    pub run: Option<RunSummary>,
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// Where the code the artifacts describe was pushed
+#[derive(Debug, Serialize)]
+pub struct GitInfo {
+    /// Remote URL with any credentials removed
+    pub remote_url: Option<String>,
+    pub branch: String,
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Artifact {
+    /// Path in the published run, e.g. `report.md` or `turns/3.diff`
+    pub name: String,
+    /// `report`, `usage`, `metrics`, `test_output` or `diff`
+    pub kind: &'static str,
+    pub bytes: usize,
+    pub sha256: String,
+    pub content_base64: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PublishResponse {
+    url: Option<String>,
+}
+
+/// Upload the last run's report, usage, metrics history and diffs to the Zoo,
+/// authenticated with the personal access token stored by `qernel auth`
+pub fn publish_run_artifacts(project: &Path, remote: &str, branch: &str, server: Option<String>) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let token = zoo::require_token()?;
+    let api = zoo::api_base(project, server)?;
+
+    let manifest = build_manifest(project, remote, branch)?;
+    if manifest.run.is_none() {
+        if manifest.artifacts.is_empty() {
+            anyhow::bail!("Nothing to publish: run `qernel prototype` first.");
+        }
+        println!("{} No prototype run recorded; publishing the files that exist", crate::util::sym_question(ce));
+    }
+
+    let request = zoo::client()?.post(format!("{}/runs", api)).bearer_auth(token).json(&manifest);
+    let response = zoo::send(request, "the runs API")?;
+    let published: PublishResponse = response.json().unwrap_or_default();
+    println!("{} Published {} run artifact(s)", crate::util::sym_check(ce), manifest.artifacts.len());
+    if let Some(url) = published.url {
+        println!("   {}", url);
+    }
+    Ok(())
+}
+
+fn build_manifest(project: &Path, remote: &str, branch: &str) -> Result<RunManifest> {
+    let config = load_config(&project.join(".qernel").join("qernel.yaml"))?;
+    let run = load_last_run(project);
+
+    // Prefer what the last run recorded: a report or diffs left by an earlier run describe other code
+    let files: Vec<(String, &'static str, Option<PathBuf>)> = match &run {
+        Some(run) => {
+            let a = &run.artifacts;
+            let mut files = vec![
+                ("report.md".to_string(), "report", a.report.clone()),
+                ("report-test-output.txt".to_string(), "test_output", a.test_output.clone()),
+                ("metrics.json".to_string(), "metrics", a.metrics.clone()),
+            ];
+            for diff in &a.turn_diffs {
+                if let Some(name) = diff.file_name().and_then(|n| n.to_str()) {
+                    files.push((format!("turns/{}", name), "diff", Some(diff.clone())));
+                }
+            }
+            files
+        }
+        None => vec![
+            ("report.md".to_string(), "report", Some(report_path(project))),
+            ("report-test-output.txt".to_string(), "test_output", Some(test_output_path(project))),
+            ("metrics.json".to_string(), "metrics", Some(metrics_path(project))),
+        ],
+    };
+
+    let mut artifacts = Vec::new();
+    if let Some(run) = &run {
+        artifacts.push(artifact("usage.json".to_string(), "usage", serde_json::to_vec_pretty(&run.usage)?));
+    }
+    let ce = crate::util::color_enabled_stdout();
+    for (name, kind, path) in files {
+        let Some(Ok(bytes)) = path.map(std::fs::read) else { continue };
+        if bytes.len() > MAX_ARTIFACT_BYTES {
+            println!("{} Skipping {} ({} bytes is over the upload limit)", crate::util::sym_question(ce), name, bytes.len());
+            continue;
+        }
+        artifacts.push(artifact(name, kind, bytes));
+    }
+
+    Ok(RunManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        project: ProjectInfo { name: config.project.name, description: config.project.description },
+        git: GitInfo {
+            remote_url: zoo::git_output(project, &["remote", "get-url", remote]).map(|u| strip_credentials(&u)),
+            branch: branch.to_string(),
+            commit: zoo::git_output(project, &["rev-parse", "HEAD"]),
+        },
+        run,
+        artifacts,
+    })
+}
+
+fn artifact(name: String, kind: &'static str, bytes: Vec<u8>) -> Artifact {
+    Artifact {
+        name,
+        kind,
+        bytes: bytes.len(),
+        sha256: format!("{:x}", Sha256::digest(&bytes)),
+        content_base64: base64::engine::general_purpose::STANDARD.encode(&bytes),
+    }
+}
+
+/// `qernel push --url` embeds the token in the remote URL; it must not be published
+pub(crate) fn strip_credentials(remote_url: &str) -> String {
+    match url::Url::parse(remote_url) {
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.to_string()
+        }
+        // scp-style `git@host:path` has no secret in it
+        Err(_) => remote_url.to_string(),
+    }
+}
@@ -2,7 +2,7 @@ use std::path::Path;
 use std::process::Command;
 
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
 
 fn is_full_url(s: &str) -> bool {
     s.starts_with("http://") || s.starts_with("https://") || s.starts_with("git@")
@@ -22,7 +22,7 @@ pub fn handle_pull(repo: String, dest: String, branch: Option<String>, server: S
         anyhow::bail!("destination already exists: {}", dest_path.display());
     }
 
-    let pb = ProgressBar::new_spinner();
+    let pb = crate::util::spinner();
     pb.set_style(ProgressStyle::with_template("{spinner} cloning repo...").unwrap());
     pb.enable_steady_tick(std::time::Duration::from_millis(80));
 
@@ -2,10 +2,10 @@ use std::process::{Command, Stdio};
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
 use crate::util::load_config;
 
-pub fn handle_push(remote: String, url: Option<String>, branch: Option<String>, no_commit: bool) -> Result<()> {
+pub fn handle_push(remote: String, url: Option<String>, branch: Option<String>, no_commit: bool, artifacts: bool, server: Option<String>, allow_secrets: bool) -> Result<()> {
     let ce = crate::util::color_enabled_stdout();
     
     // Step 1: Set up remote if URL provided
@@ -68,6 +68,7 @@ pub fn handle_push(remote: String, url: Option<String>, branch: Option<String>,
     println!("{} Branch: {}", crate::util::sym_check(ce), current_branch);
 
     // Step 3: Auto-commit changes if any exist (unless --no-commit flag is used)
+    let mut scanned = false;
     if !no_commit {
         let status_output = Command::new("git")
             .args(["status", "--porcelain"])
@@ -90,6 +91,12 @@ pub fn handle_push(remote: String, url: Option<String>, branch: Option<String>,
                     anyhow::bail!("Failed to stage changes: {}", error);
                 }
                 
+                // Scan what the commit will record, before it does
+                if !allow_secrets {
+                    check_secrets(&remote, true, ce)?;
+                    scanned = true;
+                }
+
                 // Commit changes
                 println!("{} Committing changes...", crate::util::sym_gear(ce));
                 let commit_output = Command::new("git")
@@ -111,8 +118,12 @@ pub fn handle_push(remote: String, url: Option<String>, branch: Option<String>,
         println!("{} Skipping auto-commit (--no-commit flag)", crate::util::sym_gear(ce));
     }
 
+    if !allow_secrets && !scanned {
+        check_secrets(&remote, false, ce)?;
+    }
+
     // Step 4: Push with progress and timeout handling
-    let pb = ProgressBar::new_spinner();
+    let pb = crate::util::spinner();
     pb.set_style(ProgressStyle::with_template("{spinner} Pushing...").unwrap());
     pb.enable_steady_tick(Duration::from_millis(100));
     
@@ -188,5 +199,29 @@ pub fn handle_push(remote: String, url: Option<String>, branch: Option<String>,
         }
     }
 
+    // Step 5: Publish run artifacts next to the code they describe
+    if artifacts {
+        crate::cmd::publish::publish_run_artifacts(std::path::Path::new("."), &remote, &current_branch, server)?;
+    }
+
     Ok(())
 }
+
+/// Block the push when the content to push looks like it holds API keys or tokens
+fn check_secrets(remote: &str, staged: bool, ce: bool) -> Result<()> {
+    println!("{} Scanning for secrets...", crate::util::sym_gear(ce));
+    let findings = crate::cmd::secrets::scan_outgoing(std::path::Path::new("."), remote, "HEAD", staged)?;
+    if findings.is_empty() {
+        return Ok(());
+    }
+    println!("{} Possible secrets in the content to push:", crate::util::sym_cross(ce));
+    for f in &findings {
+        println!("   {}:{}: {} ({})", f.path, f.line, f.kind, f.preview);
+    }
+    if staged {
+        println!("Nothing was committed. Remove them (projects from `qernel new` git-ignore .env) and push again.");
+    } else {
+        println!("Remove them from every commit to push, e.g. with `git rebase -i`, and push again.");
+    }
+    anyhow::bail!("Push blocked: {} possible secret(s) found. Rerun with --allow-secrets if they are not secrets.", findings.len());
+}
@@ -0,0 +1,19 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::cmd::explain::renderer::{RenderOptions, highlight_markdown, print_blocks};
+use crate::cmd::prototype::report::report_path;
+
+/// Show the review-mode report `.qernel/report.md`, paged when stdout is a terminal
+pub fn handle_see(cwd: String, no_pager: bool) -> Result<()> {
+    let path = report_path(Path::new(&cwd));
+    if !path.is_file() {
+        anyhow::bail!(
+            "No report at {}. Run `qernel prototype --report` (or set agent.report: true) to write one after a successful run.",
+            path.display()
+        );
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let rendered = if crate::util::color_enabled_stdout() { highlight_markdown(&text) } else { text };
+    print_blocks(rendered, &RenderOptions { pager: !no_pager })
+}
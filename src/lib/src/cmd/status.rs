@@ -0,0 +1,220 @@
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use std::path::Path;
+use std::process::Command;
+
+use crate::cmd::common::models::{self, ModelProvider};
+use crate::cmd::env::{interpreter_version, venv_python, version_satisfies};
+use crate::cmd::papers::paper_state;
+use crate::cmd::prototype::budget::format_elapsed;
+use crate::cmd::prototype::checkpoint::load_checkpoint;
+use crate::cmd::prototype::environment::PythonEnv;
+use crate::cmd::prototype::manifest::PaperManifest;
+use crate::cmd::prototype::summary::{RunOutcome, RunSummary, load_last_run};
+use crate::cmd::workspace::{Workspace, print_workspace_status};
+use crate::config::{ConfigResolver, ConfigSource, load_config};
+use crate::util::{sym_check, sym_cross, sym_question};
+
+/// Uncommitted files listed before the rest are summarized as a count
+const MAX_CHANGES_SHOWN: usize = 10;
+
+/// Where a project stands: config, last prototype run, uncommitted changes, papers,
+/// the project venv and whether the agent model's provider has a key. At a workspace root,
+/// the last run of every member instead
+pub fn handle_status(cwd: String) -> Result<()> {
+    let cwd_path = Path::new(&cwd);
+    let project = cwd_path.canonicalize().unwrap_or_else(|_| cwd_path.to_path_buf());
+    if let Some(workspace) = Workspace::load(&project)? {
+        return print_workspace_status(&workspace);
+    }
+    let config_path = project.join(".qernel").join("qernel.yaml");
+    if !config_path.is_file() {
+        anyhow::bail!("No .qernel/qernel.yaml in {}. Create a project with 'qernel new --template' first.", project.display());
+    }
+    let config = load_config(&config_path)?;
+    let resolver = ConfigResolver::load(&project)?;
+    let ce = crate::util::color_enabled_stdout();
+    let heading = |title: &str| if ce { format!("{}", title.bold()) } else { title.to_string() };
+
+    println!("{}  {}", heading(&config.project.name), config.project.description);
+    println!("  {}", project.display());
+    println!();
+
+    println!("{}", heading("Config"));
+    let model = resolver.resolve("agent.model")?;
+    let model_name = model.value.clone().unwrap_or(config.agent.model.clone());
+    for value in [&model, &resolver.resolve("agent.max_iterations")?, &resolver.resolve("benchmarks.test_command")?] {
+        let source = value.source.as_ref().map(|s| s.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("  {:<24} {}  ({})", value.key, value.display_value(), source);
+    }
+    if !config.benchmarks.metrics.is_empty() {
+        let names: Vec<&str> = config.benchmarks.metrics.iter().map(|m| m.name.as_str()).collect();
+        println!("  {:<24} {}", "benchmarks.metrics", names.join(", "));
+    }
+    println!();
+
+    println!("{}", heading("Last run"));
+    print_last_run(&project, ce)?;
+    println!();
+
+    println!("{}", heading("Working tree"));
+    print_git_status(&project, ce);
+    println!();
+
+    println!("{}", heading("Papers"));
+    if config.papers.is_empty() {
+        println!("  none (add one with `qernel papers add <url|path>`)");
+    } else {
+        let manifest = PaperManifest::load(&project);
+        for paper in &config.papers {
+            let state = paper_state(paper, &manifest, &project);
+            let sym = if state.ends_with(", parsed") { sym_check(ce) } else { sym_question(ce) };
+            println!("  {} {}  ({})", sym, paper.source(), state);
+        }
+    }
+    println!();
+
+    println!("{}", heading("Environment"));
+    print_venv_status(&project, config.python_version.as_deref(), ce);
+    println!();
+
+    println!("{}", heading("Provider"));
+    print_provider_status(&resolver, &model_name, ce)?;
+    Ok(())
+}
+
+fn print_last_run(project: &Path, ce: bool) -> Result<()> {
+    match load_last_run(project) {
+        Some(run) => {
+            println!("  {} {}", outcome_symbol(run.outcome, ce), run_line(&run));
+            let elapsed = format_elapsed(std::time::Duration::from_secs_f64(run.elapsed_secs));
+            println!("  {} patches applied ({}), {}, {}", run.patches_applied, run.churn, elapsed, run.usage.summary());
+            if let Some(error) = run.error.filter(|_| run.outcome == RunOutcome::Error) {
+                println!("  error: {}", error);
+            }
+        }
+        None => println!("  no prototype run recorded yet (run `qernel prototype`)"),
+    }
+    if let Some(checkpoint) = load_checkpoint(project)? {
+        println!(
+            "  {} checkpoint after iteration {} ({}); continue with `qernel prototype --resume`",
+            sym_question(ce),
+            checkpoint.iteration,
+            checkpoint.reason
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn outcome_symbol(outcome: RunOutcome, ce: bool) -> String {
+    match outcome {
+        RunOutcome::Success => sym_check(ce),
+        RunOutcome::Stopped => sym_question(ce),
+        RunOutcome::MaxIterations | RunOutcome::BudgetExceeded | RunOutcome::Error => sym_cross(ce),
+    }
+}
+
+/// e.g. `succeeded after 4 of 15 iterations on 2026-01-02 14:30`
+pub(crate) fn run_line(run: &RunSummary) -> String {
+    let when = run
+        .finished_at
+        .map(|t| format!(" on {}", t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")))
+        .unwrap_or_default();
+    format!("{} after {} of {} iterations{}", run.outcome.describe(), run.iterations, run.max_iterations, when)
+}
+
+fn print_git_status(project: &Path, ce: bool) {
+    let output = Command::new("git").args(["status", "--porcelain"]).current_dir(project).output();
+    let out = match output {
+        Ok(out) if out.status.success() => out,
+        Ok(_) => return println!("  not a git repository"),
+        Err(_) => return println!("  {} git is not installed", sym_question(ce)),
+    };
+    let text = String::from_utf8_lossy(&out.stdout);
+    let changes: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if changes.is_empty() {
+        return println!("  {} clean", sym_check(ce));
+    }
+    println!("  {} {} uncommitted change(s)", sym_question(ce), changes.len());
+    for line in changes.iter().take(MAX_CHANGES_SHOWN) {
+        println!("    {}", line);
+    }
+    if changes.len() > MAX_CHANGES_SHOWN {
+        println!("    ... and {} more", changes.len() - MAX_CHANGES_SHOWN);
+    }
+}
+
+fn print_venv_status(project: &Path, python_version: Option<&str>, ce: bool) {
+    let venv_dir = match PythonEnv::detect(project) {
+        Ok(PythonEnv::Venv(dir)) => dir,
+        Ok(other) => return print_managed_env_status(&other, python_version, ce),
+        Err(e) => return println!("  {} {:#}", sym_cross(ce), e),
+    };
+    let python = venv_python(&venv_dir);
+    if !python.exists() {
+        return println!("  {} no project venv (create it with `qernel env setup`)", sym_question(ce));
+    }
+    let Some(version) = interpreter_version(&python.to_string_lossy(), &[]) else {
+        return println!(
+            "  {} {} does not run (recreate it with `qernel env setup --recreate`)",
+            sym_cross(ce),
+            python.display()
+        );
+    };
+    match python_version {
+        Some(spec) if !version_satisfies(&version, spec) => println!(
+            "  {} Python {} does not satisfy python_version '{}' (run `qernel env setup --recreate`)",
+            sym_cross(ce),
+            version,
+            spec
+        ),
+        _ => println!("  {} Python {} at {}", sym_check(ce), version, venv_dir.display()),
+    }
+}
+
+/// Like the venv line for a conda or uv environment, asking its runner for the interpreter
+fn print_managed_env_status(python_env: &PythonEnv, python_version: Option<&str>, ce: bool) {
+    let runner = python_env.runner();
+    let mut pre: Vec<&str> = runner[1..].iter().map(String::as_str).collect();
+    pre.push("python");
+    match interpreter_version(&runner[0], &pre) {
+        None => println!("  {} no Python in the {} (create it with `qernel env setup`)", sym_question(ce), python_env.describe()),
+        Some(version) if python_version.is_some_and(|spec| !version_satisfies(&version, spec)) => println!(
+            "  {} Python {} in the {} does not satisfy python_version '{}' (run `qernel env setup --recreate`)",
+            sym_cross(ce),
+            version,
+            python_env.describe(),
+            python_version.unwrap_or_default()
+        ),
+        Some(version) => println!("  {} Python {} in the {}", sym_check(ce), version, python_env.describe()),
+    }
+}
+
+fn print_provider_status(resolver: &ConfigResolver, model: &str, ce: bool) -> Result<()> {
+    let provider = models::capabilities(model).provider;
+    let name = match provider {
+        ModelProvider::Openai => crate::cmd::common::endpoint::Backend::active()?.name(),
+        ModelProvider::Anthropic => "anthropic",
+        ModelProvider::Openrouter => "openrouter",
+        ModelProvider::Ollama => {
+            println!("  {} {} runs on Ollama; no API key needed", sym_check(ce), model);
+            return Ok(());
+        }
+    };
+    let key = resolver.resolve(&format!("secrets.{}", name))?;
+    match (&key.value, &key.source) {
+        (Some(_), Some(ConfigSource::Env(var))) => {
+            println!("  {} {} key for {} from {}", sym_check(ce), name, model, var)
+        }
+        (Some(_), _) => println!("  {} {} key for {} from the user config", sym_check(ce), name, model),
+        (None, _) => println!(
+            "  {} no {} key for {} (set {} or run `qernel config set secrets.{}`)",
+            sym_cross(ce),
+            name,
+            model,
+            crate::util::provider_env_var(name).unwrap_or("the provider's key variable"),
+            name
+        ),
+    }
+    Ok(())
+}
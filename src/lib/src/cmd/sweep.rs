@@ -0,0 +1,418 @@
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use indicatif::ProgressStyle;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use codex_core::exec::ResourceLimits;
+
+use crate::cmd::prototype::environment::{prepare_command, PreparedCommand};
+use crate::cmd::prototype::metrics::{extract_metrics, validate_metrics};
+use crate::config::{
+    MetricConfig, MetricDirection, SweepConfig, SweepMode, SweepParameter, SweepParameterKind, load_config,
+};
+
+const DEFAULT_SAMPLES: usize = 10;
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// One benchmark run in the sweep
+#[derive(Debug, Serialize)]
+struct SweepRun {
+    run: usize,
+    params: BTreeMap<String, String>,
+    exit_code: i32,
+    duration_secs: f64,
+    timed_out: bool,
+    metrics: BTreeMap<String, Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Run the benchmark command for every combination in the `sweep:` section and write a leaderboard
+pub fn handle_sweep(cwd: String, parallelism: Option<usize>, dry_run: bool) -> Result<()> {
+    let cwd_path = Path::new(&cwd);
+    let cwd_abs = cwd_path.canonicalize().unwrap_or_else(|_| cwd_path.to_path_buf());
+    let config = load_config(&cwd_abs.join(".qernel").join("qernel.yaml"))?;
+    let Some(sweep) = config.sweep.clone() else {
+        anyhow::bail!("no `sweep:` section in .qernel/qernel.yaml. See the README for an example.");
+    };
+    if sweep.parameters.is_empty() {
+        anyhow::bail!("sweep.parameters is empty");
+    }
+    let metrics = config.benchmarks.metrics.clone();
+    validate_metrics(&metrics)?;
+
+    let combos = build_combinations(&sweep)?;
+    let command = sweep.command.clone().unwrap_or_else(|| config.benchmarks.test_command.clone());
+    let base_argv = shlex::split(&command).filter(|a| !a.is_empty()).ok_or_else(|| anyhow::anyhow!("invalid sweep command: {}", command))?;
+    let ce = crate::util::color_enabled_stdout();
+
+    if dry_run {
+        println!("{} {} runs of `{}`:", crate::util::sym_gear(ce), combos.len(), command);
+        for (i, combo) in combos.iter().enumerate() {
+            let named: BTreeMap<String, String> =
+                sweep.parameters.iter().map(|p| p.name.clone()).zip(combo.iter().cloned()).collect();
+            println!("  {:>3}. {}", i + 1, format_params_map(&named));
+        }
+        return Ok(());
+    }
+
+    let parallelism = parallelism.or(sweep.parallelism).unwrap_or(1).max(1);
+    // sweep.timeout_secs bounds each run; benchmarks.limits adds the memory and CPU caps
+    let mut limits = config.benchmarks.limits.resource_limits();
+    limits.wall_clock = Some(std::time::Duration::from_secs(sweep.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS)));
+    let use_pty = config.benchmarks.use_pty;
+    // Resolved once, so a missing env file or variable stops the sweep before any run. Runs
+    // only append arguments, which the environment or container wrapping leaves in place.
+    let prepared = prepare_command(&base_argv, &cwd_abs)?;
+    println!(
+        "{} Sweeping {} combinations of `{}` ({} at a time)",
+        crate::util::sym_gear(ce),
+        combos.len(),
+        command,
+        parallelism
+    );
+
+    let pb = crate::util::progress_bar(combos.len() as u64);
+    pb.set_style(ProgressStyle::with_template("{spinner} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} runs").unwrap());
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to create tokio runtime")?;
+    let params = &sweep.parameters;
+    let metrics_ref = &metrics;
+    let prepared = &prepared;
+    let mut runs: Vec<SweepRun> = rt.block_on(async {
+        futures_util::stream::iter(combos.iter().enumerate())
+            .map(|(i, combo)| {
+                let pb = pb.clone();
+                let argv = build_argv(&prepared.argv, params, combo);
+                let cwd_abs = cwd_abs.clone();
+                async move {
+                    let run = run_one(i + 1, argv, &cwd_abs, prepared, params, combo, metrics_ref, limits, use_pty).await;
+                    pb.inc(1);
+                    run
+                }
+            })
+            .buffer_unordered(parallelism)
+            .collect()
+            .await
+    });
+    pb.finish_and_clear();
+
+    let rank_metric = rank_metric(&sweep, &metrics)?;
+    rank_runs(&mut runs, rank_metric);
+
+    let out_dir = cwd_abs.join(".qernel").join("sweeps");
+    std::fs::create_dir_all(&out_dir).with_context(|| format!("failed to create {}", out_dir.display()))?;
+    let stem = format!("sweep-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    let json_path = out_dir.join(format!("{}.json", stem));
+    let csv_path = out_dir.join(format!("{}.csv", stem));
+    std::fs::write(&json_path, serde_json::to_string_pretty(&runs)?).with_context(|| format!("failed to write {}", json_path.display()))?;
+    std::fs::write(&csv_path, render_csv(&runs, &sweep.parameters, &metrics)).with_context(|| format!("failed to write {}", csv_path.display()))?;
+
+    print_leaderboard(&runs, &metrics, rank_metric);
+    println!("\n{} Leaderboard written to {} and {}", crate::util::sym_check(ce), display_rel(&csv_path, &cwd_abs), display_rel(&json_path, &cwd_abs));
+    Ok(())
+}
+
+/// Parameter assignments for each run, in parameter order
+fn build_combinations(sweep: &SweepConfig) -> Result<Vec<Vec<String>>> {
+    match sweep.mode {
+        SweepMode::Grid => {
+            let mut combos: Vec<Vec<String>> = vec![Vec::new()];
+            for p in &sweep.parameters {
+                if p.values.is_empty() {
+                    anyhow::bail!("sweep parameter '{}' needs a list of values in grid mode", p.name);
+                }
+                let values: Vec<String> = p.values.iter().map(yaml_scalar).collect::<Result<_>>()?;
+                combos = combos
+                    .into_iter()
+                    .flat_map(|c| {
+                        values.iter().map(move |v| {
+                            let mut next = c.clone();
+                            next.push(v.clone());
+                            next
+                        })
+                    })
+                    .collect();
+            }
+            Ok(combos)
+        }
+        SweepMode::Random => {
+            let samples = sweep.samples.unwrap_or(DEFAULT_SAMPLES);
+            let seed = sweep.seed.unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+            let mut rng = SplitMix64(seed);
+            (0..samples)
+                .map(|_| sweep.parameters.iter().map(|p| sample_parameter(p, &mut rng)).collect())
+                .collect()
+        }
+    }
+}
+
+fn sample_parameter(p: &SweepParameter, rng: &mut SplitMix64) -> Result<String> {
+    if !p.values.is_empty() {
+        let idx = (rng.next_f64() * p.values.len() as f64) as usize;
+        return yaml_scalar(&p.values[idx.min(p.values.len() - 1)]);
+    }
+    let (Some(min), Some(max)) = (p.min, p.max) else {
+        anyhow::bail!("sweep parameter '{}' needs values or a min/max range", p.name);
+    };
+    if max < min {
+        anyhow::bail!("sweep parameter '{}': max is below min", p.name);
+    }
+    if p.integer {
+        let (lo, hi) = (min.ceil() as i64, max.floor() as i64);
+        let span = (hi - lo + 1).max(1) as f64;
+        Ok((lo + (rng.next_f64() * span) as i64).min(hi).to_string())
+    } else {
+        // Six decimals keeps the values readable as env vars and in the leaderboard
+        let v = format!("{:.6}", min + rng.next_f64() * (max - min));
+        Ok(v.trim_end_matches('0').trim_end_matches('.').to_string())
+    }
+}
+
+fn yaml_scalar(v: &serde_yaml::Value) -> Result<String> {
+    match v {
+        serde_yaml::Value::String(s) => Ok(s.clone()),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+        other => anyhow::bail!("sweep values must be scalars, got {:?}", other),
+    }
+}
+
+/// Small deterministic generator so seeded random sweeps are reproducible without extra deps
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn build_argv(base: &[String], params: &[SweepParameter], combo: &[String]) -> Vec<String> {
+    let mut argv = base.to_vec();
+    for (p, value) in params.iter().zip(combo) {
+        if p.kind == SweepParameterKind::Arg {
+            argv.push(format!("--{}", p.name));
+            argv.push(value.clone());
+        }
+    }
+    argv
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_one(
+    run: usize,
+    argv: Vec<String>,
+    cwd: &Path,
+    prepared: &PreparedCommand,
+    params: &[SweepParameter],
+    combo: &[String],
+    metrics: &[MetricConfig],
+    limits: ResourceLimits,
+    use_pty: bool,
+) -> SweepRun {
+    use codex_core::exec::{ExecParams, process_exec_tool_call};
+    use codex_core::protocol::SandboxPolicy;
+
+    let mut env = prepared.env.clone();
+    for (p, value) in params.iter().zip(combo) {
+        if p.kind == SweepParameterKind::Env {
+            env.insert(p.name.clone(), value.clone());
+        }
+    }
+    let exec_params = ExecParams {
+        command: argv,
+        cwd: cwd.to_path_buf(),
+        timeout_ms: limits.wall_clock.map(|d| d.as_millis() as u64),
+        env,
+        with_escalated_permissions: None,
+        justification: None,
+        limits,
+        use_pty,
+    };
+    let params_map: BTreeMap<String, String> = params.iter().map(|p| p.name.clone()).zip(combo.iter().cloned()).collect();
+    let started = std::time::Instant::now();
+    let result = process_exec_tool_call(
+        exec_params,
+        prepared.sandbox.clone(),
+        &SandboxPolicy::DANGER_FULL_ACCESS,
+        cwd,
+        &None,
+        None,
+    )
+    .await;
+    // A run killed at the wall-clock limit still counts, with whatever it printed by then
+    let result = match result {
+        Err(codex_core::exec::CodexErr::Sandbox(codex_core::exec::SandboxErr::Timeout { output })) => Ok(*output),
+        other => other,
+    };
+    match result {
+        Ok(out) => SweepRun {
+            run,
+            params: params_map,
+            exit_code: out.exit_code,
+            duration_secs: out.duration.as_secs_f64(),
+            timed_out: out.timed_out,
+            metrics: extract_metrics(metrics, &out.stdout.text).into_iter().map(|r| (r.name, r.value)).collect(),
+            error: None,
+        },
+        Err(e) => SweepRun {
+            run,
+            params: params_map,
+            exit_code: -1,
+            duration_secs: started.elapsed().as_secs_f64(),
+            timed_out: false,
+            metrics: metrics.iter().map(|m| (m.name.clone(), None)).collect(),
+            error: Some(format!("{:?}", e)),
+        },
+    }
+}
+
+/// The metric used for ranking: `sweep.rank_by`, else the first declared metric
+fn rank_metric<'a>(sweep: &SweepConfig, metrics: &'a [MetricConfig]) -> Result<Option<&'a MetricConfig>> {
+    match &sweep.rank_by {
+        Some(name) => metrics
+            .iter()
+            .find(|m| &m.name == name)
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("sweep.rank_by '{}' is not in benchmarks.metrics", name)),
+        None => Ok(metrics.first()),
+    }
+}
+
+/// Best first: by the rank metric when there is one (missing values last), then
+/// successful runs before failures, then faster runs
+fn rank_runs(runs: &mut [SweepRun], metric: Option<&MetricConfig>) {
+    runs.sort_by(|a, b| {
+        let by_metric = match metric {
+            Some(m) => {
+                let va = a.metrics.get(&m.name).copied().flatten();
+                let vb = b.metrics.get(&m.name).copied().flatten();
+                match (va, vb) {
+                    (Some(x), Some(y)) => match m.direction {
+                        MetricDirection::Maximize => y.total_cmp(&x),
+                        MetricDirection::Minimize => x.total_cmp(&y),
+                    },
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                }
+            }
+            None => std::cmp::Ordering::Equal,
+        };
+        by_metric
+            .then_with(|| (a.exit_code != 0).cmp(&(b.exit_code != 0)))
+            .then_with(|| a.duration_secs.total_cmp(&b.duration_secs))
+    });
+}
+
+fn render_csv(runs: &[SweepRun], params: &[SweepParameter], metrics: &[MetricConfig]) -> String {
+    let mut header = vec!["rank".to_string(), "run".to_string()];
+    header.extend(params.iter().map(|p| p.name.clone()));
+    header.extend(["exit_code".to_string(), "duration_s".to_string(), "timed_out".to_string()]);
+    header.extend(metrics.iter().map(|m| m.name.clone()));
+    let mut out = header.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(",");
+    out.push('\n');
+    for (rank, run) in runs.iter().enumerate() {
+        let mut row = vec![(rank + 1).to_string(), run.run.to_string()];
+        row.extend(params.iter().map(|p| run.params.get(&p.name).cloned().unwrap_or_default()));
+        row.extend([run.exit_code.to_string(), format!("{:.3}", run.duration_secs), run.timed_out.to_string()]);
+        row.extend(metrics.iter().map(|m| run.metrics.get(&m.name).copied().flatten().map(|v| v.to_string()).unwrap_or_default()));
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn print_leaderboard(runs: &[SweepRun], metrics: &[MetricConfig], rank_metric: Option<&MetricConfig>) {
+    const SHOWN: usize = 10;
+    match rank_metric {
+        Some(m) => {
+            let order = match m.direction {
+                MetricDirection::Maximize => "higher is better",
+                MetricDirection::Minimize => "lower is better",
+            };
+            println!("\nLeaderboard (ranked by {}, {}):", m.name, order)
+        }
+        None => println!("\nLeaderboard (no metrics declared; ranked by exit code and duration):"),
+    }
+    for (rank, run) in runs.iter().take(SHOWN).enumerate() {
+        let values: Vec<String> = metrics
+            .iter()
+            .map(|m| {
+                let v = run.metrics.get(&m.name).copied().flatten().map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+                format!("{}={}", m.name, v)
+            })
+            .collect();
+        let status = if run.timed_out {
+            "timeout".to_string()
+        } else {
+            format!("exit {}", run.exit_code)
+        };
+        println!(
+            "  {:>3}. {}  [{}, {:.1}s]  {}",
+            rank + 1,
+            format_params_map(&run.params),
+            status,
+            run.duration_secs,
+            values.join(" ")
+        );
+    }
+    if runs.len() > SHOWN {
+        println!("  ... {} more", runs.len() - SHOWN);
+    }
+}
+
+fn format_params_map(params: &BTreeMap<String, String>) -> String {
+    params.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(" ")
+}
+
+fn display_rel(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_core::exec::SandboxType;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn a_timed_out_run_keeps_its_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let argv: Vec<String> = ["sh", "-c", "echo 'loss: 0.25'; sleep 30"].map(String::from).to_vec();
+        let prepared = PreparedCommand { argv: argv.clone(), env: Default::default(), sandbox: SandboxType::None };
+        let metrics = vec![MetricConfig {
+            name: "loss".into(),
+            regex: Some(r"loss: ([\d.]+)".into()),
+            json_key: None,
+            target: None,
+            direction: Default::default(),
+        }];
+        let limits = ResourceLimits { wall_clock: Some(std::time::Duration::from_secs(1)), ..Default::default() };
+        let run = run_one(0, argv, dir.path(), &prepared, &[], &[], &metrics, limits, false).await;
+        assert!(run.timed_out);
+        assert_eq!(run.error, None);
+        assert_eq!(run.metrics["loss"], Some(0.25));
+        assert!(run.duration_secs < 10.0);
+    }
+}
@@ -0,0 +1,306 @@
+//! Workspaces: a `qernel-workspace.yaml` at the top of a repository lists member project
+//! directories, e.g. one prototype per algorithm of a benchmark suite. `qernel prototype --all`
+//! runs every member and `qernel status` reports on all of them.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::cmd::prototype::interrupt::{self, interrupted};
+use crate::cmd::prototype::summary::{RunOutcome, RunSummary, load_last_run};
+use crate::cmd::prototype::PrototypeOptions;
+use crate::cmd::status::{outcome_symbol, run_line};
+use crate::config::load_config;
+use crate::error::QernelError;
+use crate::util::{sym_cross, sym_question};
+
+pub const WORKSPACE_FILE: &str = "qernel-workspace.yaml";
+
+/// `qernel-workspace.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    /// Project directories relative to the workspace root; globs such as `algorithms/*`
+    /// match every directory with a `.qernel/qernel.yaml`
+    pub members: Vec<String>,
+    /// Members run at once by `qernel prototype --all` (default 1; `--jobs` overrides it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+}
+
+/// A loaded workspace with its members resolved to project directories
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub config: WorkspaceConfig,
+    pub members: Vec<PathBuf>,
+}
+
+impl Workspace {
+    /// The workspace whose root is `dir`, or `None` when it has no `qernel-workspace.yaml`
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let root = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let path = root.join(WORKSPACE_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let config: WorkspaceConfig =
+            serde_yaml::from_str(&text).map_err(|e| QernelError::Config(format!("{}: {}", path.display(), e)))?;
+        let members = resolve_members(&root, &config.members)?;
+        Ok(Some(Self { root, config, members }))
+    }
+
+    /// The nearest workspace at or above `dir`
+    pub fn find(dir: &Path) -> Result<Option<Self>> {
+        let start = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        for candidate in start.ancestors() {
+            if candidate.join(WORKSPACE_FILE).is_file() {
+                return Self::load(candidate);
+            }
+        }
+        Ok(None)
+    }
+
+    /// A member's path as listed, relative to the workspace root
+    pub fn member_name(&self, member: &Path) -> String {
+        member.strip_prefix(&self.root).unwrap_or(member).display().to_string()
+    }
+}
+
+fn resolve_members(root: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut members: Vec<PathBuf> = Vec::new();
+    for pattern in patterns {
+        let is_glob = pattern.contains(['*', '?', '[']);
+        let full = format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern);
+        let matched: Vec<PathBuf> = glob::glob(&full)
+            .map_err(|e| QernelError::Config(format!("{}: invalid member glob '{}': {}", WORKSPACE_FILE, pattern, e)))?
+            .flatten()
+            .filter(|p| p.join(".qernel").join("qernel.yaml").is_file())
+            .collect();
+        if matched.is_empty() && !is_glob {
+            return Err(QernelError::Config(format!(
+                "{}: member '{}' has no .qernel/qernel.yaml (create it with `qernel new`)",
+                WORKSPACE_FILE, pattern
+            ))
+            .into());
+        }
+        for member in matched {
+            let member = member.canonicalize().unwrap_or(member);
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+    }
+    if members.is_empty() {
+        return Err(QernelError::Config(format!("{} lists no member projects", WORKSPACE_FILE)).into());
+    }
+    Ok(members)
+}
+
+/// How one member's run ended
+struct MemberRun {
+    name: String,
+    exit_code: Option<i32>,
+    summary: Option<RunSummary>,
+}
+
+/// `qernel prototype --all`: run `qernel prototype` in every member of the workspace at or
+/// above `cwd`, `jobs` at a time. Each member runs as its own qernel process; with more than
+/// one job their output goes to `.qernel/logs/workspace-run.log` in each member and they
+/// never prompt.
+pub fn handle_prototype_all(cwd: String, jobs: Option<usize>, options: PrototypeOptions, output_json: Option<PathBuf>) -> Result<()> {
+    let output_json = output_json.map(std::path::absolute).transpose().context("invalid --output-json path")?;
+    let Some(workspace) = Workspace::find(Path::new(&cwd))? else {
+        return Err(QernelError::Config(format!("--all needs a {} in {} or a parent directory", WORKSPACE_FILE, cwd)).into());
+    };
+    let jobs = jobs.or(workspace.config.jobs).unwrap_or(1).clamp(1, workspace.members.len());
+    let exe = std::env::current_exe().context("cannot locate the qernel executable")?;
+    let args = prototype_args(&options, jobs > 1);
+    let ce = crate::util::color_enabled_stdout();
+    // Ctrl-C reaches the members directly; qernel itself only stops starting new ones
+    interrupt::install();
+
+    println!("Workspace {}: {} member(s), {} at a time", workspace.root.display(), workspace.members.len(), jobs);
+    let mut runs = Vec::new();
+    if jobs == 1 {
+        for (i, member) in workspace.members.iter().enumerate() {
+            if interrupted() {
+                break;
+            }
+            let name = workspace.member_name(member);
+            println!("\n── {} ({}/{}) ──", name, i + 1, workspace.members.len());
+            let started = chrono::Utc::now();
+            let status = Command::new(&exe).arg("prototype").arg("--cwd").arg(member).args(&args).status();
+            runs.push(finish_member(name, member, started, status));
+        }
+    } else {
+        let queue = std::sync::Mutex::new(workspace.members.iter());
+        let finished = std::sync::Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for _ in 0..jobs {
+                scope.spawn(|| loop {
+                    let Some(member) = queue.lock().unwrap_or_else(|e| e.into_inner()).next() else { break };
+                    if interrupted() {
+                        break;
+                    }
+                    let name = workspace.member_name(member);
+                    let run = run_member_logged(&exe, &args, name, member);
+                    println!("{} {}", member_symbol(&run, ce), member_line(&run));
+                    finished.lock().unwrap_or_else(|e| e.into_inner()).push(run);
+                });
+            }
+        });
+        runs = finished.into_inner().unwrap_or_else(|e| e.into_inner());
+        // Report in the order the workspace lists the members
+        runs.sort_by_key(|r| workspace.members.iter().position(|m| workspace.member_name(m) == r.name));
+    }
+
+    println!("\nWorkspace results");
+    for run in &runs {
+        println!("  {} {}", member_symbol(run, ce), member_line(run));
+    }
+    let skipped = workspace.members.len() - runs.len();
+    if skipped > 0 {
+        println!("  {} {} member(s) not run (interrupted)", sym_question(ce), skipped);
+    }
+
+    if let Some(path) = output_json {
+        let json: Vec<serde_json::Value> = runs
+            .iter()
+            .map(|r| serde_json::json!({ "member": r.name, "exit_code": r.exit_code, "summary": r.summary }))
+            .collect();
+        std::fs::write(&path, serde_json::to_string_pretty(&json)?).with_context(|| format!("failed to write {}", path.display()))?;
+        println!("Workspace summary written to {}", path.display());
+    }
+
+    let failed = runs.iter().filter(|r| r.exit_code != Some(0)).count();
+    if failed > 0 || skipped > 0 {
+        anyhow::bail!("{} of {} member(s) did not succeed", failed + skipped, workspace.members.len());
+    }
+    Ok(())
+}
+
+/// The flags `qernel prototype` was given, for each member's own process
+fn prototype_args(options: &PrototypeOptions, parallel: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(model) = &options.model {
+        args.extend(["--model".to_string(), model.clone()]);
+    }
+    if let Some(max_iters) = options.max_iters {
+        args.extend(["--max-iters".to_string(), max_iters.to_string()]);
+    }
+    if let Some(var) = &options.api_key_env {
+        args.extend(["--api-key-env".to_string(), var.clone()]);
+    }
+    if let Some(mode) = options.approval.and_then(|m| m.to_possible_value()) {
+        args.extend(["--approval".to_string(), mode.get_name().to_string()]);
+    }
+    let flags = [
+        (options.debug, "--debug"),
+        (options.spec_only, "--spec-only"),
+        (options.spec_and_content_only, "--spec-and-content-only"),
+        (options.force_reparse, "--force-reparse"),
+        (!options.plan_first, "--no-plan"),
+        (options.report, "--report"),
+        (options.resume, "--resume"),
+        // Nobody can answer prompts from members running side by side
+        (options.yes || parallel, "--yes"),
+        (options.stream_tests && !parallel, "--stream-tests"),
+    ];
+    args.extend(flags.iter().filter(|(set, _)| *set).map(|(_, flag)| flag.to_string()));
+    args
+}
+
+fn run_member_logged(exe: &Path, args: &[String], name: String, member: &Path) -> MemberRun {
+    let started = chrono::Utc::now();
+    let log_dir = member.join(".qernel").join("logs");
+    let log = std::fs::create_dir_all(&log_dir).and_then(|_| std::fs::File::create(log_dir.join("workspace-run.log")));
+    let status = log.and_then(|log| {
+        let stderr = log.try_clone()?;
+        Command::new(exe)
+            .arg("prototype")
+            .arg("--cwd")
+            .arg(member)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(log)
+            .stderr(stderr)
+            .status()
+    });
+    finish_member(name, member, started, status)
+}
+
+fn finish_member(name: String, member: &Path, started: chrono::DateTime<chrono::Utc>, status: std::io::Result<std::process::ExitStatus>) -> MemberRun {
+    let exit_code = match status {
+        Ok(status) => status.code(),
+        Err(e) => {
+            eprintln!("Could not run {}: {}", name, e);
+            None
+        }
+    };
+    // A member that failed before recording its run still has the previous run's summary
+    let summary = load_last_run(member).filter(|s| s.finished_at.is_some_and(|t| t >= started));
+    MemberRun { name, exit_code, summary }
+}
+
+fn member_symbol(run: &MemberRun, ce: bool) -> String {
+    match &run.summary {
+        Some(summary) if run.exit_code == Some(0) || summary.outcome != RunOutcome::Success => outcome_symbol(summary.outcome, ce),
+        _ => sym_cross(ce),
+    }
+}
+
+fn member_line(run: &MemberRun) -> String {
+    match &run.summary {
+        Some(summary) => format!("{}  {}", run.name, run_line(summary)),
+        None => format!("{}  exited with {}", run.name, run.exit_code.map_or("a signal".to_string(), |c| format!("code {}", c))),
+    }
+}
+
+/// `qernel status` at a workspace root: each member's last run, then totals
+pub fn print_workspace_status(workspace: &Workspace) -> Result<()> {
+    let ce = crate::util::color_enabled_stdout();
+    let heading = |title: &str| if ce { format!("{}", title.bold()) } else { title.to_string() };
+    println!("{}  {} member(s)", heading("Workspace"), workspace.members.len());
+    println!("  {}", workspace.root.display());
+    println!();
+
+    println!("{}", heading("Members"));
+    let mut outcomes: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut cost = 0.0;
+    for member in &workspace.members {
+        let name = workspace.member_name(member);
+        let project = load_config(&member.join(".qernel").join("qernel.yaml")).map(|c| c.project.name).unwrap_or_default();
+        let label = if project.is_empty() || project == name { name } else { format!("{} ({})", name, project) };
+        match load_last_run(member) {
+            Some(run) => {
+                println!("  {} {}  {}", outcome_symbol(run.outcome, ce), label, run_line(&run));
+                let metrics: Vec<String> = run
+                    .metrics
+                    .iter()
+                    .filter_map(|m| m.value.map(|v| format!("{}={}{}", m.name, v, if m.met { "" } else { " (target not met)" })))
+                    .collect();
+                if !metrics.is_empty() {
+                    println!("      {}", metrics.join(", "));
+                }
+                *outcomes.entry(run.outcome.describe()).or_default() += 1;
+                cost += run.usage.cost_usd;
+            }
+            None => {
+                println!("  {} {}  no run recorded yet", sym_question(ce), label);
+                *outcomes.entry("not run").or_default() += 1;
+            }
+        }
+    }
+    println!();
+
+    println!("{}", heading("Totals"));
+    let counts: Vec<String> = outcomes.iter().map(|(outcome, n)| format!("{} {}", n, outcome)).collect();
+    println!("  {}", counts.join(", "));
+    println!("  ~${:.2} spent on the last runs", cost);
+    Ok(())
+}
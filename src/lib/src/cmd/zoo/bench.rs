@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use crate::cmd::prototype::agent::run_cmd_with_events;
+use crate::cmd::prototype::metrics::{extract_metrics, validate_metrics};
+use crate::cmd::zoo;
+use crate::config::{MetricConfig, load_config, save_config};
+
+/// Replaced in a benchmark's `test_command` with its directory, relative to the project
+const DIR_PLACEHOLDER: &str = "{dir}";
+
+/// A standard benchmark from `GET <server>/_api/benchmarks/<id>`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BenchmarkDefinition {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    /// Run from the project root; `{dir}` is the benchmark directory
+    pub test_command: String,
+    #[serde(default)]
+    pub metrics: Vec<MetricConfig>,
+    /// Test files, written under `.qernel/benchmarks/<id>/`
+    #[serde(default, skip_serializing)]
+    pub files: Vec<BenchmarkFile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BenchmarkFile {
+    pub path: String,
+    pub content_base64: String,
+}
+
+/// Body of `POST <server>/_api/benchmarks/<id>/scores`
+#[derive(Debug, Serialize)]
+struct ScoreSubmission {
+    benchmark_version: Option<String>,
+    project: String,
+    commit: Option<String>,
+    exit_code: i32,
+    duration_secs: f64,
+    metrics: Vec<Score>,
+}
+
+#[derive(Debug, Serialize)]
+struct Score {
+    name: String,
+    value: Option<f64>,
+    target: Option<f64>,
+    met: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScoreResponse {
+    rank: Option<u32>,
+    entries: Option<u32>,
+    leaderboard_url: Option<String>,
+}
+
+/// `.qernel/benchmarks/<id>`
+pub fn benchmark_dir(cwd: &Path, id: &str) -> PathBuf {
+    cwd.join(".qernel").join("benchmarks").join(id)
+}
+
+/// Fetch a Zoo benchmark into `.qernel/benchmarks/<id>/` and make it the project's test
+/// command and metrics. With `submit`, run it once and report the scores to the leaderboard.
+pub fn handle_zoo_bench(cwd: String, id: String, server: Option<String>, submit: bool) -> Result<()> {
+    let cwd_path = Path::new(&cwd);
+    let project = cwd_path.canonicalize().unwrap_or_else(|_| cwd_path.to_path_buf());
+    let config_path = project.join(".qernel").join("qernel.yaml");
+    let mut config = load_config(&config_path)?;
+    zoo::validate_id(&id, "benchmark")?;
+    let ce = crate::util::color_enabled_stdout();
+    let api = zoo::api_base(&project, server)?;
+
+    let definition: BenchmarkDefinition = zoo::get_json(&format!("{}/benchmarks/{}", api, id), &format!("benchmark '{}'", id))?;
+    validate_metrics(&definition.metrics)?;
+    let dir = benchmark_dir(&project, &id);
+    write_files(&dir, &definition)?;
+    let version = definition.version.as_deref().map(|v| format!(" v{}", v)).unwrap_or_default();
+    println!(
+        "{} Downloaded {}{} ({} file(s)) to {}",
+        crate::util::sym_check(ce),
+        if definition.name.is_empty() { &id } else { &definition.name },
+        version,
+        definition.files.len(),
+        dir.strip_prefix(&project).unwrap_or(&dir).display()
+    );
+
+    let rel_dir = format!(".qernel/benchmarks/{}", id);
+    let test_command = definition.test_command.replace(DIR_PLACEHOLDER, &rel_dir);
+    if config.benchmarks.test_command != test_command {
+        println!("  test_command: {} (was: {})", test_command, config.benchmarks.test_command);
+    }
+    config.benchmarks.test_command = test_command.clone();
+    config.benchmarks.metrics = definition.metrics.clone();
+    save_config(&config, &config_path)?;
+    println!("{} benchmarks.test_command and benchmarks.metrics in qernel.yaml now use this benchmark", crate::util::sym_check(ce));
+
+    if !submit {
+        println!("Run `qernel prototype` against it, then `qernel zoo bench {} --submit` to report your scores.", id);
+        return Ok(());
+    }
+
+    let token = zoo::require_token()?;
+    let argv = shlex::split(&test_command).filter(|a| !a.is_empty()).ok_or_else(|| anyhow::anyhow!("invalid test_command: {}", test_command))?;
+    println!("{} Running `{}`...", crate::util::sym_gear(ce), test_command);
+    let out = run_cmd_with_events(&argv, &project, &config.benchmarks.limits.resource_limits())?;
+    let readings = extract_metrics(&definition.metrics, &out.stdout.text);
+    let symbol = if out.exit_code == 0 { crate::util::sym_check(ce) } else { crate::util::sym_cross(ce) };
+    println!("{} exit code {} in {:.1}s", symbol, out.exit_code, out.duration.as_secs_f64());
+    for r in &readings {
+        let value = r.value.map_or_else(|| "-".to_string(), |v| v.to_string());
+        let target = r.target.map(|t| format!(" (target {})", t)).unwrap_or_default();
+        println!("  {:<24} {}{}", r.name, value, target);
+    }
+
+    let submission = ScoreSubmission {
+        benchmark_version: definition.version.clone(),
+        project: config.project.name.clone(),
+        commit: zoo::git_output(&project, &["rev-parse", "HEAD"]),
+        exit_code: out.exit_code,
+        duration_secs: out.duration.as_secs_f64(),
+        metrics: readings
+            .iter()
+            .map(|r| Score { name: r.name.clone(), value: r.value, target: r.target, met: r.met() })
+            .collect(),
+    };
+    let request = zoo::client()?.post(format!("{}/benchmarks/{}/scores", api, id)).bearer_auth(token).json(&submission);
+    let response: ScoreResponse = zoo::send(request, &format!("benchmark '{}'", id))?.json().unwrap_or_default();
+    match (response.rank, response.entries) {
+        (Some(rank), Some(entries)) => println!("{} Scores submitted: rank {} of {}", crate::util::sym_check(ce), rank, entries),
+        _ => println!("{} Scores submitted", crate::util::sym_check(ce)),
+    }
+    if let Some(url) = response.leaderboard_url {
+        println!("   {}", url);
+    }
+    Ok(())
+}
+
+/// Replace the benchmark directory with the definition's files and `benchmark.json`
+fn write_files(dir: &Path, definition: &BenchmarkDefinition) -> Result<()> {
+    // Checked before anything is written so a bad definition leaves the old copy intact
+    let mut files = Vec::new();
+    for file in &definition.files {
+        let rel = Path::new(&file.path);
+        if rel.as_os_str().is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            anyhow::bail!("benchmark file path '{}' must be relative and stay inside the benchmark directory", file.path);
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(file.content_base64.trim())
+            .with_context(|| format!("benchmark file {} is not valid base64", file.path))?;
+        files.push((dir.join(rel), bytes));
+    }
+
+    if dir.exists() {
+        std::fs::remove_dir_all(dir).with_context(|| format!("failed to remove {}", dir.display()))?;
+    }
+    for (path, bytes) in files {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    std::fs::create_dir_all(dir)?;
+    let manifest = dir.join("benchmark.json");
+    std::fs::write(&manifest, serde_json::to_string_pretty(definition)?).with_context(|| format!("failed to write {}", manifest.display()))
+}
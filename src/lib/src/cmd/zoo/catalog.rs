@@ -0,0 +1,100 @@
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::cmd::papers::resolver::parse_doi;
+use crate::cmd::zoo;
+use crate::config::{PaperConfig, load_config, save_config};
+
+/// An algorithm in the Zoo catalog
+#[derive(Debug, Deserialize)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub summary: String,
+    /// Paper URLs (arXiv, DOI or PDF links), the primary paper first
+    #[serde(default)]
+    pub papers: Vec<String>,
+    /// Zoo benchmark id the entry is scored against, if it has one
+    #[serde(default)]
+    pub benchmark: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    entries: Vec<CatalogEntry>,
+}
+
+/// List catalog entries matching `query` with their ids and paper links
+pub fn handle_zoo_search(query: String, limit: usize, server: Option<String>) -> Result<()> {
+    let api = zoo::api_base(Path::new("."), server)?;
+    let url = url::Url::parse_with_params(&format!("{}/catalog", api), &[("q", query.as_str()), ("limit", &limit.to_string())])?;
+    let response: SearchResponse = zoo::get_json(url.as_str(), "the catalog")?;
+    if response.entries.is_empty() {
+        println!("No Zoo entries match '{}'.", query);
+        return Ok(());
+    }
+    let ce = crate::util::color_enabled_stdout();
+    for entry in response.entries.iter().take(limit) {
+        let id = if ce { format!("{}", entry.id.cyan().bold()) } else { entry.id.clone() };
+        println!("{}  {}", id, entry.name);
+        if !entry.summary.is_empty() {
+            println!("    {}", entry.summary);
+        }
+        for paper in &entry.papers {
+            println!("    paper: {}", paper);
+        }
+        if let Some(bench) = &entry.benchmark {
+            println!("    benchmark: {}", bench);
+        }
+        if !entry.tags.is_empty() {
+            println!("    tags: {}", entry.tags.join(", "));
+        }
+    }
+    println!("\nStart a project from an entry with `qernel zoo init <id>`.");
+    Ok(())
+}
+
+/// Scaffold a project for a catalog entry: its papers in qernel.yaml and, when the entry
+/// has one, its benchmark as the test command
+pub fn handle_zoo_init(id: String, path: Option<String>, server: Option<String>) -> Result<()> {
+    zoo::validate_id(&id, "entry")?;
+    let api = zoo::api_base(Path::new("."), server.clone())?;
+    let entry: CatalogEntry = zoo::get_json(&format!("{}/catalog/{}", api, id), &format!("entry '{}'", id))?;
+    let folder = match path {
+        Some(path) => path,
+        None => {
+            zoo::validate_id(&entry.id, "entry")?;
+            entry.id.clone()
+        }
+    };
+
+    crate::cmd::new::handle_new(folder.clone(), Some(crate::cmd::new::templates::DEFAULT_TEMPLATE.to_string()), false, vec![], false, false)?;
+
+    let config_path = Path::new(&folder).join(".qernel").join("qernel.yaml");
+    let mut config = load_config(&config_path)?;
+    config.project.description = if entry.summary.is_empty() { entry.name.clone() } else { format!("{}: {}", entry.name, entry.summary) };
+    for source in &entry.papers {
+        let paper = match parse_doi(source) {
+            Some(doi) => PaperConfig { doi: Some(doi), ..PaperConfig::default() },
+            None => PaperConfig::from_url(source.trim()),
+        };
+        if !config.papers.iter().any(|p| p.source() == paper.source()) {
+            config.papers.push(paper);
+        }
+    }
+    save_config(&config, &config_path)?;
+
+    if let Some(bench) = &entry.benchmark {
+        zoo::bench::handle_zoo_bench(folder.clone(), bench.clone(), server, false)?;
+    }
+    let ce = crate::util::color_enabled_stdout();
+    println!("{} Created {} for {} ({} paper(s))", crate::util::sym_check(ce), folder, entry.name, entry.papers.len());
+    println!("Next: cd {} && qernel prototype", folder);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+pub mod bench;
+pub mod catalog;
+
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::ConfigResolver;
+
+/// Zoo API used when `default_server` is not configured
+pub const DEFAULT_SERVER: &str = "https://dojoservice.onrender.com/";
+
+/// `<server>/_api` for the `--server` flag, else `default_server`, else the hosted Zoo
+pub fn api_base(project: &Path, server: Option<String>) -> Result<String> {
+    let server = ConfigResolver::load(project)?
+        .with_flag("default_server", server)
+        .get("default_server")?
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+    Ok(format!("{}/_api", server.trim_end_matches('/')))
+}
+
+/// Zoo ids end up in URLs and directory names, so only a safe subset is accepted
+pub fn validate_id(id: &str, what: &str) -> Result<()> {
+    if id.is_empty() || id.starts_with('.') || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        anyhow::bail!("invalid {} id '{}': use letters, digits, '-', '_' and '.'", what, id);
+    }
+    Ok(())
+}
+
+/// The personal access token stored by `qernel auth`
+pub fn require_token() -> Result<String> {
+    crate::util::load_config()
+        .ok()
+        .and_then(|c| c.token)
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("No personal access token stored. Run `qernel auth` first."))
+}
+
+pub fn client() -> Result<Client> {
+    Client::builder().timeout(Duration::from_secs(120)).build().context("failed to build HTTP client")
+}
+
+/// Send a request and turn HTTP errors into messages that say what to do
+pub fn send(request: RequestBuilder, what: &str) -> Result<Response> {
+    let response = request.send().with_context(|| format!("failed to reach the Zoo for {}", what))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        anyhow::bail!("The server rejected the token ({}). Run `qernel auth` to store a new one.", status);
+    }
+    if status == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("{} not found on the Zoo", what);
+    }
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        anyhow::bail!("Request for {} failed ({}): {}", what, status, body.trim());
+    }
+    Ok(response)
+}
+
+/// GET a JSON document, authenticated when a token is stored
+pub fn get_json<T: DeserializeOwned>(url: &str, what: &str) -> Result<T> {
+    let mut request = client()?.get(url);
+    if let Ok(token) = require_token() {
+        request = request.bearer_auth(token);
+    }
+    send(request, what)?.json().with_context(|| format!("unexpected response for {}", what))
+}
+
+/// Trimmed stdout of a git command, or `None` if it fails or prints nothing
+pub fn git_output(cwd: &Path, args: &[&str]) -> Option<String> {
+    let out = std::process::Command::new("git").args(args).current_dir(cwd).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    if text.is_empty() { None } else { Some(text) }
+}
@@ -0,0 +1,1364 @@
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use anyhow::Context;
+
+use crate::error::QernelError;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QernelConfig {
+    /// Project layout version (see [`crate::migrations`]); older projects are upgraded on load
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    pub project: ProjectConfig,
+    pub agent: AgentConfig,
+    pub papers: Vec<PaperConfig>,
+    pub content_files: Option<Vec<String>>,
+    pub benchmarks: BenchmarkConfig,
+    /// Required interpreter for the project venv, e.g. ">=3.10" or "3.11"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_version: Option<String>,
+    /// How the project's Python environment is created and activated
+    #[serde(default, skip_serializing_if = "PythonConfig::is_default")]
+    pub python: PythonConfig,
+    /// Where the project was scaffolded from (built-in name or remote repository)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateConfig>,
+    /// Parameter sweep run by `qernel sweep`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sweep: Option<SweepConfig>,
+    /// Variables set for the agent's shell commands and the test runs. Values may reference the
+    /// user's environment as `${VAR}`, so secrets stay out of the repository
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub env: IndexMap<String, String>,
+    /// Dotenv files (relative to the project root) loaded before `env`, later files winning
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_files: Vec<String>,
+    /// Where the agent's shell commands and the tests run
+    #[serde(default, skip_serializing_if = "ExecConfig::is_default")]
+    pub exec: ExecConfig,
+    /// Defaults for `qernel explain` run in this project
+    #[serde(default, skip_serializing_if = "ExplainConfig::is_default")]
+    pub explain: ExplainConfig,
+    /// Test output and suite scheduling for `qernel prototype`
+    #[serde(default, skip_serializing_if = "PrototypeConfig::is_default")]
+    pub prototype: PrototypeConfig,
+}
+
+/// `explain:` section; flags and environment variables take precedence
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ExplainConfig {
+    /// Model used when `--model` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Models compared side by side when `--models` is not given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// Most characters per explanation when `--max-chars` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chars: Option<usize>,
+    /// Concurrent model requests (default 4)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+}
+
+impl ExplainConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Concurrent explain requests unless `explain.workers` says otherwise
+pub const DEFAULT_EXPLAIN_WORKERS: usize = 4;
+
+/// `prototype:` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PrototypeConfig {
+    /// Most characters of each test output stream sent back to the model (default 16000);
+    /// longer output is summarized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chars: Option<usize>,
+    /// Test suites run at once (default 1). Suites always run one at a time when their
+    /// output is streamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+}
+
+impl PrototypeConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Test output budget unless `prototype.max_chars` says otherwise
+pub const DEFAULT_TEST_OUTPUT_CHARS: usize = 16_000;
+
+/// `exec:` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ExecConfig {
+    #[serde(default)]
+    pub backend: ExecBackend,
+    /// Container image for the docker backend (default: `python:3.12-slim`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl ExecConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Image the docker backend runs commands in unless `exec.image` names another
+pub const DEFAULT_EXEC_IMAGE: &str = "python:3.12-slim";
+
+/// `exec.backend`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecBackend {
+    /// On this machine, in the project's Python environment
+    #[default]
+    Local,
+    /// In a throwaway `exec.image` container with only the project mounted
+    Docker,
+}
+
+impl ExecBackend {
+    pub const NAMES: &'static [&'static str] = &["local", "docker"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "local" => Some(Self::Local),
+            "docker" => Some(Self::Docker),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectConfig {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AgentConfig {
+    pub model: String,
+    pub max_iterations: u32,
+    /// Tool calls (patches, shell commands, plan updates) the model may chain within one
+    /// iteration before the tests run
+    #[serde(default = "default_max_tool_calls", skip_serializing_if = "is_default_max_tool_calls")]
+    pub max_tool_calls: u32,
+    /// Context lines at each end of a patch chunk that may be ignored when the chunk does not
+    /// match the file as written; 0 requires every context line to match
+    #[serde(default = "default_max_patch_fuzz", skip_serializing_if = "is_default_max_patch_fuzz")]
+    pub max_patch_fuzz: u32,
+    /// Most lines one patch may add and remove in total, like `constraints.max_patch_lines`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_patch_lines: Option<usize>,
+    /// Most paper figures attached to each model request, picked by caption relevance
+    #[serde(default = "default_max_images", skip_serializing_if = "is_default_max_images")]
+    pub max_images: u32,
+    /// How paper figures are shrunk before they are attached to model requests
+    #[serde(default, skip_serializing_if = "ImageConfig::is_default")]
+    pub images: ImageConfig,
+    /// Model that reviews each patch against the spec before it is applied; unset means no review
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewer_model: Option<String>,
+    /// Write `.qernel/report.md` explaining the implementation after a successful run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub report: bool,
+    /// Commands run after every applied patch (e.g. `ruff check --fix`, `black`), with the
+    /// files the patch wrote appended; their changes join the turn diff
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_patch_hooks: Vec<String>,
+    /// Stop after the iteration in which the run has taken this long
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wall_clock_minutes: Option<u32>,
+    /// Stop after the iteration in which the estimated model cost reaches this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+    /// What the agent's actions may do; a violating action is not run and the model is told why
+    #[serde(default, skip_serializing_if = "ConstraintsConfig::is_default")]
+    pub constraints: ConstraintsConfig,
+    /// Whether the agent's shell commands run, wait for approval or are refused, by class
+    #[serde(default, skip_serializing_if = "CommandPolicy::is_default")]
+    pub command_policy: CommandPolicy,
+    /// `agent.temperature`, `agent.top_p` and `agent.seed`
+    #[serde(flatten)]
+    pub sampling: Sampling,
+}
+
+/// Sampling parameters sent with every model request of a run; unset ones are left to the
+/// provider. The Responses API has no seed, so `seed` only reaches Ollama models.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Sampling {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Sampling {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(t) = self.temperature
+            && !(0.0..=2.0).contains(&t)
+        {
+            return Err(QernelError::Config(format!("agent.temperature must be between 0 and 2, got {}", t)).into());
+        }
+        if let Some(p) = self.top_p
+            && !(0.0..=1.0).contains(&p)
+        {
+            return Err(QernelError::Config(format!("agent.top_p must be between 0 and 1, got {}", p)).into());
+        }
+        Ok(())
+    }
+
+    /// Add the parameters to a Responses API request body. Reasoning models reject
+    /// `temperature` and `top_p`, so for them the parameters are dropped with a warning
+    /// (once per process).
+    pub fn apply_to_responses(&self, body: &mut serde_json::Value, caps: &crate::cmd::common::models::ModelCapabilities) {
+        if caps.reasoning {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            if self.temperature.is_some() || self.top_p.is_some() {
+                let model = body["model"].as_str().unwrap_or("this model").to_string();
+                WARNED.call_once(|| {
+                    eprintln!("Warning: {} is a reasoning model and rejects agent.temperature and agent.top_p; not sending them", model)
+                });
+            }
+            return;
+        }
+        if let Some(t) = self.temperature {
+            body["temperature"] = t.into();
+        }
+        if let Some(p) = self.top_p {
+            body["top_p"] = p.into();
+        }
+    }
+
+    /// Ollama's `options` object for `/api/chat`
+    pub fn ollama_options(&self) -> serde_json::Value {
+        let mut options = serde_json::json!({});
+        if let Some(t) = self.temperature {
+            options["temperature"] = t.into();
+        }
+        if let Some(p) = self.top_p {
+            options["top_p"] = p.into();
+        }
+        if let Some(seed) = self.seed {
+            options["seed"] = seed.into();
+        }
+        options
+    }
+}
+
+/// `python:` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PythonConfig {
+    #[serde(default)]
+    pub env_manager: EnvManager,
+}
+
+impl PythonConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `python.env_manager`: what runs the project's commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvManager {
+    /// conda with an `environment.yml`, uv with a `uv.lock` or `[tool.uv]`, otherwise venv
+    #[default]
+    Auto,
+    /// `.qernel/.venv`, put first on PATH
+    Venv,
+    /// The environment named in `environment.yml`, through `conda run -n`
+    Conda,
+    /// The uv-managed project environment, through `uv run`
+    Uv,
+}
+
+impl EnvManager {
+    pub const NAMES: &'static [&'static str] = &["auto", "venv", "conda", "uv"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "auto" => Some(Self::Auto),
+            "venv" => Some(Self::Venv),
+            "conda" => Some(Self::Conda),
+            "uv" => Some(Self::Uv),
+            _ => None,
+        }
+    }
+}
+
+/// `agent.constraints`, checked before each patch, shell command and dependency is applied
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ConstraintsConfig {
+    /// Programs shell commands may start, e.g. `python`, `pytest`; empty allows any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_commands: Vec<String>,
+    /// Modules patches may not import (a package covers its submodules) or add as dependencies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forbidden_imports: Vec<String>,
+    /// Most lines one patch may add and remove in total
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_patch_lines: Option<usize>,
+    /// Run the agent's shell commands and the tests without a network (a network namespace
+    /// of their own on Linux, `--network none` with the docker backend) and refuse new
+    /// dependencies, which would be installed from the network
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_network: bool,
+}
+
+impl ConstraintsConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `agent.command_policy`: read-only commands (cat, ls, grep, ...) always run; the others
+/// are mutating or, when they name a URL, network commands. `network` also decides whether
+/// the other commands run with a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CommandPolicy {
+    #[serde(default = "default_mutating_policy")]
+    pub mutating: PolicyAction,
+    #[serde(default = "default_network_policy")]
+    pub network: PolicyAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    /// Ask the user; refused when nobody can answer (`--yes`, no terminal)
+    Ask,
+    Deny,
+}
+
+fn default_mutating_policy() -> PolicyAction {
+    PolicyAction::Allow
+}
+
+fn default_network_policy() -> PolicyAction {
+    PolicyAction::Ask
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        Self { mutating: default_mutating_policy(), network: default_network_policy() }
+    }
+}
+
+impl CommandPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `agent.images`: extracted PDF figures can be several MB each, so they are
+/// downscaled and recompressed before being base64-encoded into a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ImageConfig {
+    /// Longest side in pixels; larger images are resized to fit
+    #[serde(default = "default_image_max_dimension")]
+    pub max_dimension: u32,
+    /// Encoding sent to the model
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// JPEG quality (1-100)
+    #[serde(default = "default_image_quality")]
+    pub quality: u8,
+    /// Images still larger than this after processing are left out of the request
+    #[serde(default = "default_image_max_kb")]
+    pub max_kb: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Jpeg,
+    /// Lossless WebP; `quality` does not apply
+    Webp,
+    /// Keep the file's own encoding and only resize
+    Original,
+}
+
+fn default_max_tool_calls() -> u32 {
+    8
+}
+
+fn is_default_max_tool_calls(n: &u32) -> bool {
+    *n == default_max_tool_calls()
+}
+
+fn default_max_patch_fuzz() -> u32 {
+    2
+}
+
+fn is_default_max_patch_fuzz(n: &u32) -> bool {
+    *n == default_max_patch_fuzz()
+}
+
+fn default_max_images() -> u32 {
+    4
+}
+
+fn is_default_max_images(n: &u32) -> bool {
+    *n == default_max_images()
+}
+
+fn default_image_max_dimension() -> u32 {
+    1568
+}
+
+fn default_image_quality() -> u8 {
+    80
+}
+
+fn default_image_max_kb() -> u64 {
+    1024
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            max_dimension: default_image_max_dimension(),
+            format: ImageFormat::default(),
+            quality: default_image_quality(),
+            max_kb: default_image_max_kb(),
+        }
+    }
+}
+
+impl ImageConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A paper to ingest: a URL or project-relative PDF path, or a DOI or title that
+/// `qernel` resolves to an arXiv ID or open-access PDF before downloading
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PaperConfig {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl PaperConfig {
+    pub fn from_url(url: impl Into<String>) -> Self {
+        Self { url: url.into(), ..Self::default() }
+    }
+
+    /// How the paper was given, used to identify it in listings and the manifest:
+    /// the URL, else `doi:<doi>`, else `title:<title>`
+    pub fn source(&self) -> String {
+        if !self.url.is_empty() {
+            self.url.clone()
+        } else if let Some(doi) = &self.doi {
+            format!("doi:{}", doi)
+        } else if let Some(title) = &self.title {
+            format!("title:{}", title)
+        } else {
+            String::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BenchmarkConfig {
+    /// Ignored when `suites` are configured
+    #[serde(default)]
+    pub test_command: String,
+    /// Named test commands run in order instead of `test_command`; success needs every
+    /// required suite to pass
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub suites: IndexMap<String, SuiteConfig>,
+    /// Numeric objectives read from the test output; all targets must be met for success
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<MetricConfig>,
+    /// Caps on memory, CPU and wall-clock time for commands the agent runs
+    #[serde(default, skip_serializing_if = "LimitsConfig::is_empty")]
+    pub limits: LimitsConfig,
+    /// Measure coverage of src/ after each test run and show the agent what is untested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<CoverageConfig>,
+    /// Run the tests and sweep runs under a pseudo-terminal, for harnesses and progress
+    /// bars that behave differently without a TTY; stdout and stderr arrive merged
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub use_pty: bool,
+    /// Globs relative to the project root (e.g. `results/*.json`) of files the tests write;
+    /// copied to `.qernel/artifacts/iter-<n>/` after each test run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+    /// Submit the test runs as Slurm jobs instead of running them on this machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slurm: Option<SlurmConfig>,
+}
+
+/// `benchmarks.slurm`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SlurmConfig {
+    /// Batch script with a `{{command}}` line where the test command goes; `{{job_name}}`
+    /// and `{{cwd}}` are filled in too (default: a bash script running just the command)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Like `template`, read from a file relative to the project root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_file: Option<String>,
+    /// Extra `sbatch` options, e.g. `--partition=gpu`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sbatch_args: Vec<String>,
+    /// Seconds between `squeue` polls (default 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_secs: Option<u64>,
+    /// Seconds to wait for a job, queueing included, before cancelling it and failing the
+    /// run as timed out (default 24 hours)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wait_secs: Option<u64>,
+}
+
+/// `benchmarks.coverage`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CoverageConfig {
+    /// Command run after the tests that writes a coverage.py JSON report to
+    /// `.qernel/coverage.json` (default: the first pytest suite measures it, with
+    /// `--cov=src --cov-branch --cov-report=json`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Total coverage in percent that success also needs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_percent: Option<f64>,
+}
+
+/// One entry of `benchmarks.suites`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SuiteConfig {
+    pub command: String,
+    /// Share of the suite in the reported score (default 1)
+    #[serde(default = "default_suite_weight")]
+    pub weight: f64,
+    /// Optional suites are run and reported but do not decide success
+    #[serde(default = "default_suite_required")]
+    pub required: bool,
+}
+
+fn default_suite_weight() -> f64 {
+    1.0
+}
+
+fn default_suite_required() -> bool {
+    true
+}
+
+/// `benchmarks.limits`: keeps a runaway simulation from taking over the machine
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LimitsConfig {
+    /// Memory cap in MiB for the command and everything it spawns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// CPU time cap in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_secs: Option<u64>,
+    /// Wall-clock cap in seconds (default for agent test runs: 120)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_clock_secs: Option<u64>,
+    /// How many GPUs the command may use (CUDA/ROCm visible devices, or `--gpus` for
+    /// containers); 0 hides them all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpus: Option<u32>,
+}
+
+impl LimitsConfig {
+    pub fn is_empty(&self) -> bool {
+        self.memory_mb.is_none() && self.cpu_secs.is_none() && self.wall_clock_secs.is_none() && self.gpus.is_none()
+    }
+
+    pub fn resource_limits(&self) -> codex_core::exec::ResourceLimits {
+        codex_core::exec::ResourceLimits {
+            memory_bytes: self.memory_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+            cpu_seconds: self.cpu_secs,
+            wall_clock: self.wall_clock_secs.map(std::time::Duration::from_secs),
+            gpus: self.gpus,
+        }
+    }
+}
+
+/// A value extracted from test stdout, either by regex (first capture group) or by
+/// a dotted key into a JSON object printed on its own line
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MetricConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_key: Option<String>,
+    /// Threshold to reach; without one the metric is only reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<f64>,
+    #[serde(default)]
+    pub direction: MetricDirection,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricDirection {
+    /// Higher is better; the target is a lower bound (e.g. fidelity >= 0.95)
+    #[default]
+    Maximize,
+    /// Lower is better; the target is an upper bound (e.g. runtime <= 2s)
+    Minimize,
+}
+
+/// `sweep:` section: run the benchmark once per parameter combination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SweepConfig {
+    #[serde(default)]
+    pub mode: SweepMode,
+    /// Number of random samples (random mode only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples: Option<usize>,
+    /// Seed for random mode so a sweep can be repeated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// How many runs execute at once (default 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallelism: Option<usize>,
+    /// Command to run instead of benchmarks.test_command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Metric from benchmarks.metrics used to rank runs (default: the first one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank_by: Option<String>,
+    /// Per-run timeout in seconds (default 600)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    pub parameters: Vec<SweepParameter>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SweepMode {
+    /// Every combination of the listed values
+    #[default]
+    Grid,
+    /// `samples` draws from the listed values or the min/max range
+    Random,
+}
+
+/// A swept parameter, passed to the command as an environment variable or a `--name value` argument
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SweepParameter {
+    pub name: String,
+    #[serde(default)]
+    pub kind: SweepParameterKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<serde_json::Value>")]
+    pub values: Vec<serde_yaml::Value>,
+    /// Range for random mode when no values are listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Draw whole numbers from the range
+    #[serde(default)]
+    pub integer: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SweepParameterKind {
+    #[default]
+    Env,
+    Arg,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateConfig {
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+}
+
+fn unversioned() -> u32 {
+    1
+}
+
+impl Default for QernelConfig {
+    fn default() -> Self {
+        Self {
+            version: crate::migrations::CURRENT_VERSION,
+            project: ProjectConfig {
+                name: "qernel-project".to_string(),
+                description: "A qernel prototype project".to_string(),
+            },
+            agent: AgentConfig {
+                model: "gpt-5-codex".to_string(),
+                max_iterations: 15,
+                max_tool_calls: default_max_tool_calls(),
+                max_patch_fuzz: default_max_patch_fuzz(),
+                max_patch_lines: None,
+                max_images: default_max_images(),
+                images: ImageConfig::default(),
+                reviewer_model: None,
+                report: false,
+                post_patch_hooks: Vec::new(),
+                max_wall_clock_minutes: None,
+                max_cost_usd: None,
+                constraints: ConstraintsConfig::default(),
+                command_policy: CommandPolicy::default(),
+                sampling: Sampling::default(),
+            },
+            papers: Vec::new(),
+            content_files: None,
+            benchmarks: BenchmarkConfig {
+                test_command: "python -m pytest src/tests.py -v".to_string(),
+                suites: IndexMap::new(),
+                metrics: Vec::new(),
+                limits: LimitsConfig::default(),
+                coverage: None,
+                use_pty: false,
+                artifacts: Vec::new(),
+                slurm: None,
+            },
+            python_version: None,
+            python: PythonConfig::default(),
+            template: None,
+            sweep: None,
+            env: IndexMap::new(),
+            env_files: Vec::new(),
+            exec: ExecConfig::default(),
+            explain: ExplainConfig::default(),
+            prototype: PrototypeConfig::default(),
+        }
+    }
+}
+
+pub fn load_config(config_path: &PathBuf) -> anyhow::Result<QernelConfig> {
+    if !config_path.exists() {
+        return Ok(QernelConfig::default());
+    }
+    
+    crate::migrations::upgrade(config_path)?;
+    let content = std::fs::read_to_string(config_path)
+        .context("Failed to read qernel.yaml")?;
+    crate::schema::check_config(config_path, &content)?;
+    
+    let config: QernelConfig = serde_yaml::from_str(&content)
+        .with_context(|| QernelError::Config(format!("Failed to parse {}", config_path.display())))?;
+    
+    Ok(config)
+}
+
+pub fn save_config(config: &QernelConfig, config_path: &PathBuf) -> anyhow::Result<()> {
+    let content = serde_yaml::to_string(config)
+        .context("Failed to serialize config")?;
+    
+    std::fs::write(config_path, content)
+        .context("Failed to write qernel.yaml")?;
+    
+    Ok(())
+}
+
+/// Where an effective setting came from, highest precedence first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Command-line flag for this invocation
+    Flag,
+    Env(String),
+    /// `.qernel/qernel.yaml` in the project
+    Project,
+    /// The user config file managed by `qernel auth`
+    User,
+    Default,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Flag => write!(f, "flag"),
+            ConfigSource::Env(var) => write!(f, "env {}", var),
+            ConfigSource::Project => write!(f, "project"),
+            ConfigSource::User => write!(f, "user"),
+            ConfigSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A setting the resolver knows how to look up in each layer
+pub struct ConfigKey {
+    /// Dotted name, also the path in qernel.yaml for project-scoped keys
+    pub name: String,
+    /// Environment variables checked in order
+    pub env: Vec<String>,
+    /// Whether the key may be set in qernel.yaml
+    pub project: bool,
+    /// Paths in the user config checked in order
+    pub user: Vec<String>,
+    pub default: Option<String>,
+    /// Masked when displayed
+    pub secret: bool,
+    pub kind: ValueKind,
+}
+
+/// What `qernel config set` accepts for a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Text,
+    /// Non-negative integer
+    Integer,
+    /// Non-negative decimal number
+    Decimal,
+    /// http(s) URL
+    Url,
+    /// Version or specifier such as "3.11" or ">=3.10"
+    Version,
+    /// `dark`, `light`, a syntect theme name or a .tmTheme file
+    Theme,
+    /// Color name, #rrggbb or 256-color index
+    Color,
+    /// One of the listed words
+    OneOf(&'static [&'static str]),
+}
+
+impl ConfigKey {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            env: Vec::new(),
+            project: false,
+            user: Vec::new(),
+            default: None,
+            secret: false,
+            kind: ValueKind::Text,
+        }
+    }
+    fn env(mut self, var: &str) -> Self {
+        self.env.push(var.to_string());
+        self
+    }
+    fn project(mut self) -> Self {
+        self.project = true;
+        self
+    }
+    fn user(mut self, path: &str) -> Self {
+        self.user.push(path.to_string());
+        self
+    }
+    fn default(mut self, value: &str) -> Self {
+        self.default = Some(value.to_string());
+        self
+    }
+    fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+    fn kind(mut self, kind: ValueKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Check a value before it is written to either config file
+    pub fn validate(&self, value: &str) -> anyhow::Result<()> {
+        let v = value.trim();
+        if v.is_empty() {
+            anyhow::bail!("{} cannot be empty; use `qernel config unset {}` to remove it", self.name, self.name);
+        }
+        match self.kind {
+            ValueKind::Text => {}
+            ValueKind::Integer => {
+                v.parse::<u32>()
+                    .map_err(|_| anyhow::anyhow!("{} must be a non-negative integer, got '{}'", self.name, v))?;
+            }
+            ValueKind::Decimal => {
+                if !v.parse::<f64>().is_ok_and(|n| n.is_finite() && n >= 0.0) {
+                    anyhow::bail!("{} must be a non-negative number, got '{}'", self.name, v);
+                }
+            }
+            ValueKind::Url => {
+                let parsed = url::Url::parse(v).map_err(|e| anyhow::anyhow!("{} must be a URL: {}", self.name, e))?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    anyhow::bail!("{} must be an http(s) URL, got '{}'", self.name, v);
+                }
+            }
+            ValueKind::Version => {
+                if !v.chars().any(|c| c.is_ascii_digit())
+                    || !v.chars().all(|c| c.is_ascii_digit() || ".<>=!~*, ".contains(c))
+                {
+                    anyhow::bail!("{} must be a version or specifier like 3.11 or >=3.10, got '{}'", self.name, v);
+                }
+            }
+            ValueKind::Theme => crate::cmd::common::theme::validate_theme_name(v)?,
+            ValueKind::Color => {
+                if crate::cmd::common::theme::parse_color(v).is_none() {
+                    anyhow::bail!("{} must be a color name like cyan or bright-blue, #rrggbb or 0-255, got '{}'", self.name, v);
+                }
+            }
+            ValueKind::OneOf(names) => {
+                if !names.contains(&v) {
+                    anyhow::bail!("{} must be one of {}, got '{}'", self.name, names.join(", "), v);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Look up a key by name, listing the known keys when it isn't one
+pub fn find_config_key(name: &str) -> anyhow::Result<ConfigKey> {
+    let keys = config_keys();
+    let known: Vec<String> = keys.iter().map(|k| k.name.clone()).collect();
+    keys.into_iter().find(|k| k.name == name).ok_or_else(|| {
+        anyhow::anyhow!("Unknown config key '{}'. Known keys: {}", name, known.join(", "))
+    })
+}
+
+/// Every key the resolver knows about, in display order
+pub fn config_keys() -> Vec<ConfigKey> {
+    let defaults = QernelConfig::default();
+    let mut keys = vec![
+        ConfigKey::new("agent.model").env("QERNEL_MODEL").project().user("model").default(&defaults.agent.model),
+        ConfigKey::new("agent.max_iterations")
+            .env("QERNEL_MAX_ITERATIONS")
+            .project()
+            .user("max_iterations")
+            .default(&defaults.agent.max_iterations.to_string())
+            .kind(ValueKind::Integer),
+        ConfigKey::new("agent.max_tool_calls")
+            .project()
+            .default(&defaults.agent.max_tool_calls.to_string())
+            .kind(ValueKind::Integer),
+        ConfigKey::new("agent.max_patch_fuzz")
+            .env("QERNEL_MAX_PATCH_FUZZ")
+            .project()
+            .default(&defaults.agent.max_patch_fuzz.to_string())
+            .kind(ValueKind::Integer),
+        ConfigKey::new("agent.max_patch_lines").env("QERNEL_MAX_PATCH_LINES").project().kind(ValueKind::Integer),
+        ConfigKey::new("agent.reviewer_model").env("QERNEL_REVIEWER_MODEL").project(),
+        ConfigKey::new("agent.max_wall_clock_minutes")
+            .env("QERNEL_MAX_WALL_CLOCK_MINUTES")
+            .project()
+            .kind(ValueKind::Integer),
+        ConfigKey::new("agent.max_cost_usd").env("QERNEL_MAX_COST_USD").project().kind(ValueKind::Decimal),
+        ConfigKey::new("agent.temperature").env("QERNEL_TEMPERATURE").project().kind(ValueKind::Decimal),
+        ConfigKey::new("agent.top_p").project().kind(ValueKind::Decimal),
+        ConfigKey::new("agent.seed").env("QERNEL_SEED").project().kind(ValueKind::Integer),
+        ConfigKey::new("agent.max_images")
+            .project()
+            .default(&defaults.agent.max_images.to_string())
+            .kind(ValueKind::Integer),
+        ConfigKey::new("benchmarks.test_command")
+            .env("QERNEL_TEST_COMMAND")
+            .project()
+            .default(&defaults.benchmarks.test_command),
+        ConfigKey::new("python_version").project().kind(ValueKind::Version),
+        ConfigKey::new("python.env_manager")
+            .env("QERNEL_ENV_MANAGER")
+            .project()
+            .default("auto")
+            .kind(ValueKind::OneOf(EnvManager::NAMES)),
+        ConfigKey::new("exec.backend")
+            .env("QERNEL_EXEC_BACKEND")
+            .project()
+            .default("local")
+            .kind(ValueKind::OneOf(ExecBackend::NAMES)),
+        ConfigKey::new("exec.image").env("QERNEL_EXEC_IMAGE").project().default(DEFAULT_EXEC_IMAGE),
+        ConfigKey::new("explain.model")
+            .env("QERNEL_EXPLAIN_MODEL")
+            .project()
+            .user("explain_model")
+            .default("codex-mini-latest"),
+        ConfigKey::new("explain.max_chars").project().kind(ValueKind::Integer),
+        ConfigKey::new("explain.workers")
+            .env("QERNEL_EXPLAIN_WORKERS")
+            .project()
+            .default(&DEFAULT_EXPLAIN_WORKERS.to_string())
+            .kind(ValueKind::Integer),
+        ConfigKey::new("prototype.max_chars")
+            .project()
+            .default(&DEFAULT_TEST_OUTPUT_CHARS.to_string())
+            .kind(ValueKind::Integer),
+        ConfigKey::new("prototype.workers").env("QERNEL_PROTOTYPE_WORKERS").project().default("1").kind(ValueKind::Integer),
+        ConfigKey::new("default_remote").user("default_remote"),
+        ConfigKey::new("default_server").user("default_server").kind(ValueKind::Url),
+        ConfigKey::new("provider").env("QERNEL_PROVIDER").user("provider").default("openai"),
+        ConfigKey::new("azure.base_url").env("AZURE_OPENAI_ENDPOINT").user("azure.base_url").kind(ValueKind::Url),
+        ConfigKey::new("azure.api_version")
+            .env("AZURE_OPENAI_API_VERSION")
+            .user("azure.api_version")
+            .default(crate::cmd::common::endpoint::DEFAULT_AZURE_API_VERSION),
+    ];
+    for (provider, var) in crate::util::SECRET_PROVIDERS {
+        let mut key = ConfigKey::new(&format!("secrets.{}", provider)).env(var).user(&format!("secrets.{}", provider)).secret();
+        if *provider == "openai" {
+            // Keys saved before the secrets map existed
+            key = key.user("openai_api_key");
+        }
+        keys.push(key);
+    }
+    // Model providers that requests are rate limited for, including local Ollama
+    for provider in ["openai", "azure", "anthropic", "openrouter", "ollama"] {
+        let mut key = ConfigKey::new(&format!("rate_limits.{}", provider))
+            .env(&format!("QERNEL_RATE_LIMIT_{}", provider.to_uppercase()))
+            .env("QERNEL_RATE_LIMIT")
+            .user(&format!("rate_limits.{}", provider))
+            .kind(ValueKind::Integer);
+        if let Some(rpm) = crate::cmd::common::network::default_rate_limit(provider) {
+            key = key.default(&rpm.to_string());
+        }
+        keys.push(key);
+    }
+    keys.push(ConfigKey::new("theme.name").env("QERNEL_THEME").user("theme.name").kind(ValueKind::Theme));
+    for color in ["accent", "success", "error", "warning", "info"] {
+        keys.push(ConfigKey::new(&format!("theme.{}", color)).user(&format!("theme.{}", color)).kind(ValueKind::Color));
+    }
+    keys
+}
+
+/// A key's effective value and the layer it came from
+#[derive(Debug, Clone)]
+pub struct ResolvedValue {
+    pub key: String,
+    pub value: Option<String>,
+    pub source: Option<ConfigSource>,
+    pub secret: bool,
+}
+
+impl ResolvedValue {
+    /// Value for display, with secrets reduced to their last four characters
+    pub fn display_value(&self) -> String {
+        match &self.value {
+            None => "(unset)".to_string(),
+            Some(v) if self.secret => {
+                let tail: String = v.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+                format!("****{}", tail)
+            }
+            Some(v) => v.clone(),
+        }
+    }
+}
+
+/// Layered settings lookup: flags > env > project qernel.yaml > user config > defaults
+pub struct ConfigResolver {
+    flags: std::collections::BTreeMap<String, String>,
+    project: Option<serde_yaml::Value>,
+    project_path: PathBuf,
+    user: serde_json::Value,
+}
+
+impl ConfigResolver {
+    /// Load the project config under `project_dir/.qernel` (if any) and the user config
+    pub fn load(project_dir: &std::path::Path) -> anyhow::Result<Self> {
+        let project_path = project_dir.join(".qernel").join("qernel.yaml");
+        let project = if project_path.exists() {
+            crate::migrations::upgrade(&project_path)?;
+            let content = std::fs::read_to_string(&project_path).context("Failed to read qernel.yaml")?;
+            Some(serde_yaml::from_str(&content).context("Failed to parse qernel.yaml")?)
+        } else {
+            None
+        };
+        let user = crate::util::load_config().unwrap_or_default();
+        let user = serde_json::to_value(&user).context("Failed to read user config")?;
+        Ok(Self { flags: Default::default(), project, project_path, user })
+    }
+
+    /// Apply a command-line flag for `key` when one was given
+    pub fn with_flag(mut self, key: &str, value: Option<String>) -> Self {
+        if let Some(v) = value {
+            self.flags.insert(key.to_string(), v);
+        }
+        self
+    }
+
+    pub fn project_path(&self) -> &std::path::Path {
+        &self.project_path
+    }
+
+    /// Resolve one key; unknown keys are an error
+    pub fn resolve(&self, key: &str) -> anyhow::Result<ResolvedValue> {
+        Ok(self.resolve_key(&find_config_key(key)?))
+    }
+
+    /// Effective value of `key`, if any layer sets it
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.resolve(key)?.value)
+    }
+
+    /// Like `get`, parsed as an integer
+    pub fn get_u32(&self, key: &str) -> anyhow::Result<Option<u32>> {
+        self.get_integer(key)
+    }
+
+    /// Like `get`, parsed as a count or size
+    pub fn get_usize(&self, key: &str) -> anyhow::Result<Option<usize>> {
+        self.get_integer(key)
+    }
+
+    /// Like `get`, parsed as a 64-bit integer
+    pub fn get_u64(&self, key: &str) -> anyhow::Result<Option<u64>> {
+        self.get_integer(key)
+    }
+
+    fn get_integer<T>(&self, key: &str) -> anyhow::Result<Option<T>>
+    where
+        T: std::str::FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let resolved = self.resolve(key)?;
+        match resolved.value {
+            Some(v) => v
+                .trim()
+                .parse::<T>()
+                .map(Some)
+                .with_context(|| QernelError::Config(format!("{} must be a non-negative integer (from {}), got '{}'", key, resolved.source.unwrap_or(ConfigSource::Default), v))),
+            None => Ok(None),
+        }
+    }
+
+    /// Like `get`, parsed as a non-negative number
+    pub fn get_f64(&self, key: &str) -> anyhow::Result<Option<f64>> {
+        let resolved = self.resolve(key)?;
+        match resolved.value {
+            Some(v) => v
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .map(Some)
+                .with_context(|| QernelError::Config(format!("{} must be a non-negative number (from {}), got '{}'", key, resolved.source.unwrap_or(ConfigSource::Default), v))),
+            None => Ok(None),
+        }
+    }
+
+    /// Every known key with its effective value and provenance
+    pub fn effective(&self) -> Vec<ResolvedValue> {
+        config_keys().iter().map(|k| self.resolve_key(k)).collect()
+    }
+
+    fn resolve_key(&self, spec: &ConfigKey) -> ResolvedValue {
+        let found = self
+            .flags
+            .get(&spec.name)
+            .map(|v| (v.clone(), ConfigSource::Flag))
+            .or_else(|| {
+                spec.env.iter().find_map(|var| {
+                    let v = std::env::var(var).ok().filter(|v| !v.trim().is_empty())?;
+                    Some((v, ConfigSource::Env(var.clone())))
+                })
+            })
+            .or_else(|| {
+                let project = self.project.as_ref().filter(|_| spec.project)?;
+                Some((yaml_lookup(project, &spec.name)?, ConfigSource::Project))
+            })
+            .or_else(|| spec.user.iter().find_map(|path| Some((json_lookup(&self.user, path)?, ConfigSource::User))))
+            .or_else(|| spec.default.clone().map(|v| (v, ConfigSource::Default)));
+        let (value, source) = match found {
+            Some((v, s)) => (Some(v), Some(s)),
+            None => (None, None),
+        };
+        ResolvedValue { key: spec.name.clone(), value, source, secret: spec.secret }
+    }
+}
+
+/// Scalar at a dotted path in the project YAML
+fn yaml_lookup(root: &serde_yaml::Value, path: &str) -> Option<String> {
+    let mut node = root;
+    for part in path.split('.') {
+        node = node.get(part)?;
+    }
+    match node {
+        serde_yaml::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Scalar at a dotted path in the serialized user config
+fn json_lookup(root: &serde_json::Value, path: &str) -> Option<String> {
+    let mut node = root;
+    for part in path.split('.') {
+        node = node.get(part)?;
+    }
+    match node {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Write `key` into the project's `.qernel/qernel.yaml`
+pub fn set_project_value(project_dir: &std::path::Path, key: &ConfigKey, value: &str) -> anyhow::Result<()> {
+    if !key.project {
+        anyhow::bail!("{} can't be set per project; drop --project to set it in the user config", key.name);
+    }
+    key.validate(value)?;
+    let scalar = match key.kind {
+        ValueKind::Integer => serde_yaml::Value::Number(value.trim().parse::<u64>()?.into()),
+        ValueKind::Decimal => serde_yaml::Value::Number(value.trim().parse::<f64>()?.into()),
+        _ => serde_yaml::Value::String(value.trim().to_string()),
+    };
+    edit_project_yaml(project_dir, |root| {
+        let parts: Vec<&str> = key.name.split('.').collect();
+        let mut node = root;
+        for part in &parts[..parts.len() - 1] {
+            let map = node.as_mapping_mut().ok_or_else(|| anyhow::anyhow!("qernel.yaml: {} is not a mapping", part))?;
+            node = map
+                .entry(serde_yaml::Value::String(part.to_string()))
+                .or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+        }
+        let map = node.as_mapping_mut().ok_or_else(|| anyhow::anyhow!("qernel.yaml: parent of {} is not a mapping", key.name))?;
+        map.insert(serde_yaml::Value::String(parts[parts.len() - 1].to_string()), scalar);
+        Ok(())
+    })
+}
+
+/// Remove `key` from the project's `.qernel/qernel.yaml`
+pub fn unset_project_value(project_dir: &std::path::Path, key: &ConfigKey) -> anyhow::Result<()> {
+    if !key.project {
+        anyhow::bail!("{} isn't a project setting; drop --project to unset it in the user config", key.name);
+    }
+    edit_project_yaml(project_dir, |root| {
+        let parts: Vec<&str> = key.name.split('.').collect();
+        let mut node = root;
+        for part in &parts[..parts.len() - 1] {
+            match node.get_mut(*part) {
+                Some(child) => node = child,
+                None => return Ok(()),
+            }
+        }
+        if let Some(map) = node.as_mapping_mut() {
+            map.remove(parts[parts.len() - 1]);
+        }
+        Ok(())
+    })
+}
+
+/// Apply an edit to qernel.yaml as raw YAML (so unknown sections survive) and
+/// refuse to save a file that no longer parses as a project config
+fn edit_project_yaml(
+    project_dir: &std::path::Path,
+    edit: impl FnOnce(&mut serde_yaml::Value) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let path = project_dir.join(".qernel").join("qernel.yaml");
+    if !path.exists() {
+        anyhow::bail!("{} not found. Run inside a project created with 'qernel new' or pass --cwd.", path.display());
+    }
+    let content = std::fs::read_to_string(&path).context("Failed to read qernel.yaml")?;
+    let mut root: serde_yaml::Value = serde_yaml::from_str(&content).context("Failed to parse qernel.yaml")?;
+    edit(&mut root)?;
+    serde_yaml::from_value::<QernelConfig>(root.clone())
+        .map_err(|e| anyhow::anyhow!("qernel.yaml would no longer be valid: {}", e))?;
+    let out = serde_yaml::to_string(&root).context("Failed to serialize config")?;
+    std::fs::write(&path, out).context("Failed to write qernel.yaml")?;
+    Ok(())
+}
+
+/// Write `key` into the user config
+pub fn set_user_value(key: &ConfigKey, value: &str) -> anyhow::Result<()> {
+    let Some(path) = key.user.first() else {
+        anyhow::bail!("{} is a project setting; pass --project to set it in qernel.yaml", key.name);
+    };
+    key.validate(value)?;
+    let scalar = match key.kind {
+        ValueKind::Integer => serde_json::Value::from(value.trim().parse::<u64>()?),
+        ValueKind::Decimal => serde_json::Value::from(value.trim().parse::<f64>()?),
+        _ => serde_json::Value::String(value.trim().to_string()),
+    };
+    edit_user_config(|root| {
+        // A new value replaces any legacy location for the same key
+        for legacy in &key.user[1..] {
+            json_remove(root, legacy);
+        }
+        json_insert(root, path, scalar)
+    })
+}
+
+/// Remove `key` (and any legacy location for it) from the user config
+pub fn unset_user_value(key: &ConfigKey) -> anyhow::Result<()> {
+    if key.user.is_empty() {
+        anyhow::bail!("{} is a project setting; pass --project to unset it in qernel.yaml", key.name);
+    }
+    edit_user_config(|root| {
+        for path in &key.user {
+            json_remove(root, path);
+        }
+        Ok(())
+    })
+}
+
+/// Round-trip the user config through JSON so keys can be edited by path (in the active
+/// profile for the keys a profile holds)
+fn edit_user_config(edit: impl FnOnce(&mut serde_json::Value) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    crate::util::edit_config(|cfg| {
+        let mut root = serde_json::to_value(&*cfg).context("Failed to read user config")?;
+        edit(&mut root)?;
+        *cfg = serde_json::from_value(root).context("Invalid user config value")?;
+        Ok(())
+    })
+}
+
+fn json_insert(root: &mut serde_json::Value, path: &str, value: serde_json::Value) -> anyhow::Result<()> {
+    let parts: Vec<&str> = path.split('.').collect();
+    let mut node = root;
+    for part in &parts[..parts.len() - 1] {
+        let map = node.as_object_mut().ok_or_else(|| anyhow::anyhow!("user config: {} is not a table", part))?;
+        node = map.entry(part.to_string()).or_insert_with(|| serde_json::Value::Object(Default::default()));
+    }
+    let map = node.as_object_mut().ok_or_else(|| anyhow::anyhow!("user config: parent of {} is not a table", path))?;
+    map.insert(parts[parts.len() - 1].to_string(), value);
+    Ok(())
+}
+
+fn json_remove(root: &mut serde_json::Value, path: &str) {
+    let parts: Vec<&str> = path.split('.').collect();
+    let mut node = root;
+    for part in &parts[..parts.len() - 1] {
+        match node.get_mut(*part) {
+            Some(child) => node = child,
+            None => return,
+        }
+    }
+    if let Some(map) = node.as_object_mut() {
+        map.remove(parts[parts.len() - 1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::common::models::ModelRegistry;
+
+    #[test]
+    fn sampling_is_not_sent_to_reasoning_models() {
+        let sampling = Sampling { temperature: Some(0.2), top_p: Some(0.9), seed: None };
+        let registry = ModelRegistry::default();
+        for model in ["gpt-5-codex", "o3", "o4-mini"] {
+            let mut body = serde_json::json!({ "model": model });
+            sampling.apply_to_responses(&mut body, &registry.capabilities(model));
+            assert!(body.get("temperature").is_none() && body.get("top_p").is_none(), "{}", model);
+        }
+        for model in ["gpt-4.1", "gpt-5-chat-latest"] {
+            let mut body = serde_json::json!({ "model": model });
+            sampling.apply_to_responses(&mut body, &registry.capabilities(model));
+            assert_eq!(body["temperature"], 0.2, "{}", model);
+            assert_eq!(body["top_p"], 0.9, "{}", model);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Kinds of failure callers can branch on. They travel inside `anyhow::Error`, as the error
+//! itself or as context on the underlying cause; [`QernelError::find`] digs them out.
+
+/// A failure with a known remedy
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum QernelError {
+    /// `qernel.yaml` or a setting has a value qernel cannot use
+    #[error("{0}")]
+    Config(String),
+    /// A model provider answered with an HTTP error
+    #[error("{provider} API error ({status}): {body}")]
+    Provider { provider: String, status: u16, body: String },
+    /// A patch failed part-way and its files could not be restored
+    #[error("{0}")]
+    Patch(String),
+    /// A command could not be started or supervised; a command that fails is not an error
+    #[error("{0}")]
+    Exec(String),
+    /// Downloading or parsing the project's papers or content files failed
+    #[error("{0}")]
+    Ingest(String),
+}
+
+impl QernelError {
+    /// The typed error in `err`'s chain, if any
+    pub fn find(err: &anyhow::Error) -> Option<&QernelError> {
+        err.downcast_ref::<QernelError>()
+    }
+
+    /// `config`, `provider`, `patch`, `exec` or `ingest`, as recorded in run summaries
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QernelError::Config(_) => "config",
+            QernelError::Provider { .. } => "provider",
+            QernelError::Patch(_) => "patch",
+            QernelError::Exec(_) => "exec",
+            QernelError::Ingest(_) => "ingest",
+        }
+    }
+
+    /// What the user can do about it
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            QernelError::Config(_) => {
+                Some("check .qernel/qernel.yaml; `qernel config show --effective` lists every setting and where it comes from")
+            }
+            QernelError::Provider { status: 401 | 403, .. } => {
+                Some("the API key was rejected; set it with `qernel config set secrets.<provider>` or pass --api-key-env")
+            }
+            QernelError::Provider { status: 404, .. } => {
+                Some("the model may not exist or your account cannot use it; check `qernel config get agent.model`")
+            }
+            QernelError::Provider { status: 429, .. } => {
+                Some("rate limited or out of quota; check your provider's billing page, or wait and try again")
+            }
+            QernelError::Provider { status: 500..=599, .. } => Some("the provider is having problems; try again in a few minutes"),
+            QernelError::Provider { .. } => None,
+            QernelError::Patch(_) => {
+                Some("files touched by the patch may be half-written; check them with `git status` before resuming")
+            }
+            QernelError::Exec(_) => {
+                Some("check benchmarks.test_command in .qernel/qernel.yaml and that the project venv exists (`qernel env setup`)")
+            }
+            QernelError::Ingest(_) => {
+                Some("retry with `qernel ingest --force`, or skip ingestion with `qernel prototype --spec-only`")
+            }
+        }
+    }
+}
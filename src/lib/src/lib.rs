@@ -0,0 +1,42 @@
+//! The engine behind the `qernel` CLI. The binary only parses arguments and calls the
+//! `cmd::handle_*` functions; everything else lives here so other tools can use it.
+
+pub mod cmd;
+pub mod config;
+pub mod error;
+pub mod migrations;
+pub mod schema;
+pub mod util;
+
+/// Implementing a spec with the coding agent; start with [`run_prototype`]
+pub use cmd::prototype;
+/// Explaining Python code with a model
+pub use cmd::explain;
+/// Papers attached to a project, and their ingestion
+pub use cmd::papers;
+
+/// Model providers: capabilities and pricing, rate-limited requests, usage accounting
+/// and the offline mock
+pub mod providers {
+    pub use crate::cmd::common::{mock, models, network, usage};
+    pub use crate::cmd::explain::network::{call_text_model, is_local_model};
+}
+
+pub use error::QernelError;
+pub use cmd::prototype::PrototypeOptions;
+pub use cmd::prototype::events::{ProgressEvent, ProgressSink};
+pub use cmd::prototype::summary::{RunOutcome, RunSummary};
+
+/// Run the prototype workflow without printing: progress goes to `sink` as it happens and
+/// the summary says how the run ended (`outcome`, and `error` when it failed). Prompts are
+/// answered with their defaults. Paper and content ingestion still logs to stdout; set
+/// `spec_only` to skip it.
+///
+/// Runs in one process take turns: a run changes the working directory (restored when it
+/// ends) and owns the process-wide audit log, run log and usage counters, so a second call
+/// waits for the first to finish. Run projects in parallel from separate processes. Model
+/// overrides (`models.yaml`) are read once per process, from the first project that needs
+/// them.
+pub fn run_prototype(options: PrototypeOptions, mut sink: impl ProgressSink) -> RunSummary {
+    cmd::prototype::run(options, false, &mut sink).0
+}
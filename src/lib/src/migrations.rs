@@ -0,0 +1,217 @@
+//! Upgrades of older project layouts. `qernel.yaml` records the layout version it was
+//! written for in `version:` (absent means 1); when a newer CLI opens the project, the
+//! changes between that version and [`CURRENT_VERSION`] are applied in order.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::error::QernelError;
+
+/// Layout version this CLI reads and writes
+pub const CURRENT_VERSION: u32 = 2;
+
+/// One change to a project's layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// A dotted qernel.yaml key that moved, e.g. `agent.model` to `model.name`
+    RenameKey { from: &'static str, to: &'static str },
+    /// A file or directory, relative to the project root, that moved
+    MovePath { from: &'static str, to: &'static str },
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::RenameKey { from, to } => write!(f, "rename {} to {} in qernel.yaml", from, to),
+            Change::MovePath { from, to } => write!(f, "move {} to {}", from, to),
+        }
+    }
+}
+
+/// The changes that take a project from `from` to `from + 1`
+struct Migration {
+    from: u32,
+    changes: &'static [Change],
+}
+
+const MIGRATIONS: &[Migration] = &[
+    // 1 -> 2: debug output went from a `.logs` text file to JSONL run logs under .qernel/logs
+    Migration { from: 1, changes: &[Change::MovePath { from: ".logs", to: ".qernel/logs/legacy.log" }] },
+];
+
+/// What upgrading one project involves; only changes that apply to it are listed
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub config_path: PathBuf,
+    pub from_version: u32,
+    pub changes: Vec<Change>,
+}
+
+impl Plan {
+    /// Nothing to do, not even recording the version
+    pub fn is_empty(&self) -> bool {
+        self.from_version == CURRENT_VERSION
+    }
+}
+
+/// `version:` of a parsed qernel.yaml; an error when a newer CLI wrote it
+pub fn config_version(doc: &serde_yaml::Value) -> Result<u32> {
+    let version = match doc.get("version") {
+        None | Some(serde_yaml::Value::Null) => 1,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|&v| v >= 1)
+            .ok_or_else(|| QernelError::Config(format!("qernel.yaml: version must be a positive integer, got {:?}", v)))?,
+    };
+    if version > CURRENT_VERSION {
+        return Err(QernelError::Config(format!(
+            "qernel.yaml is version {}, newer than this qernel understands ({}). Upgrade qernel to open this project.",
+            version, CURRENT_VERSION
+        ))
+        .into());
+    }
+    Ok(version)
+}
+
+/// The upgrade for the project whose config is `config_path` (`<project>/.qernel/qernel.yaml`)
+pub fn plan(config_path: &Path) -> Result<Plan> {
+    let doc = read_config(config_path)?;
+    let from_version = config_version(&doc)?;
+    let root = project_root(config_path);
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from_version) {
+        for change in migration.changes {
+            let applies = match change {
+                Change::RenameKey { from, .. } => get_path(&doc, from).is_some(),
+                Change::MovePath { from, to } => root.join(from).exists() && !root.join(to).exists(),
+            };
+            if applies {
+                changes.push(*change);
+            }
+        }
+    }
+    Ok(Plan { config_path: config_path.to_path_buf(), from_version, changes })
+}
+
+/// Carry out `plan` and record [`CURRENT_VERSION`] in qernel.yaml
+pub fn apply(plan: &Plan) -> Result<()> {
+    if plan.is_empty() {
+        return Ok(());
+    }
+    let root = project_root(&plan.config_path);
+    for change in &plan.changes {
+        if let Change::MovePath { from, to } = change {
+            let (from, to) = (root.join(from), root.join(to));
+            if let Some(parent) = to.parent() {
+                std::fs::create_dir_all(parent).with_context(|| format!("failed to create {}", parent.display()))?;
+            }
+            std::fs::rename(&from, &to).with_context(|| format!("failed to move {} to {}", from.display(), to.display()))?;
+        }
+    }
+
+    let renames: Vec<(&str, &str)> = plan
+        .changes
+        .iter()
+        .filter_map(|c| match c {
+            Change::RenameKey { from, to } => Some((*from, *to)),
+            Change::MovePath { .. } => None,
+        })
+        .collect();
+    let text = std::fs::read_to_string(&plan.config_path).context("Failed to read qernel.yaml")?;
+    let text = if renames.is_empty() {
+        // Only the version changes, so comments and formatting are kept
+        set_version_line(&text, CURRENT_VERSION)
+    } else {
+        let mut doc: serde_yaml::Value = serde_yaml::from_str(&text).context("Failed to parse qernel.yaml")?;
+        for (from, to) in renames {
+            if let Some(value) = take_path(&mut doc, from) {
+                set_path(&mut doc, to, value);
+            }
+        }
+        if let serde_yaml::Value::Mapping(map) = &mut doc {
+            map.shift_remove("version");
+        }
+        let body = serde_yaml::to_string(&doc).context("Failed to serialize qernel.yaml")?;
+        format!("version: {}\n{}", CURRENT_VERSION, body)
+    };
+    std::fs::write(&plan.config_path, text).context("Failed to write qernel.yaml")?;
+    Ok(())
+}
+
+/// Upgrade the project behind `config_path` if it is older than this CLI. Called whenever a
+/// project config is loaded; moves and renames are reported on stderr.
+pub fn upgrade(config_path: &Path) -> Result<()> {
+    if !config_path.exists() {
+        return Ok(());
+    }
+    let plan = plan(config_path)?;
+    if plan.is_empty() {
+        return Ok(());
+    }
+    apply(&plan)?;
+    if !plan.changes.is_empty() {
+        eprintln!("Upgraded {} from version {} to {}:", config_path.display(), plan.from_version, CURRENT_VERSION);
+        for change in &plan.changes {
+            eprintln!("  {}", change);
+        }
+    }
+    Ok(())
+}
+
+fn read_config(config_path: &Path) -> Result<serde_yaml::Value> {
+    let content = std::fs::read_to_string(config_path).context("Failed to read qernel.yaml")?;
+    serde_yaml::from_str(&content).with_context(|| QernelError::Config(format!("Failed to parse {}", config_path.display())))
+}
+
+/// `<project>` for `<project>/.qernel/qernel.yaml`
+fn project_root(config_path: &Path) -> PathBuf {
+    config_path.parent().and_then(Path::parent).map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Replace a top-level `version:` line, or add one at the top
+fn set_version_line(text: &str, version: u32) -> String {
+    let line = format!("version: {}", version);
+    let mut found = false;
+    let mut out: Vec<String> = text
+        .lines()
+        .map(|l| {
+            if !found && l.starts_with("version:") {
+                found = true;
+                line.clone()
+            } else {
+                l.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        out.insert(0, line);
+    }
+    let mut joined = out.join("\n");
+    joined.push('\n');
+    joined
+}
+
+fn get_path<'a>(doc: &'a serde_yaml::Value, path: &str) -> Option<&'a serde_yaml::Value> {
+    path.split('.').try_fold(doc, |node, part| node.get(part))
+}
+
+fn take_path(doc: &mut serde_yaml::Value, path: &str) -> Option<serde_yaml::Value> {
+    let (parent, last) = match path.rsplit_once('.') {
+        Some((parent, last)) => (parent.split('.').try_fold(doc, |node, part| node.get_mut(part))?, last),
+        None => (doc, path),
+    };
+    parent.as_mapping_mut()?.shift_remove(last)
+}
+
+fn set_path(doc: &mut serde_yaml::Value, path: &str, value: serde_yaml::Value) {
+    let mut node = doc;
+    let parts: Vec<&str> = path.split('.').collect();
+    for part in &parts[..parts.len() - 1] {
+        if !node.get(*part).is_some_and(serde_yaml::Value::is_mapping) {
+            node[*part] = serde_yaml::Value::Mapping(Default::default());
+        }
+        node = &mut node[*part];
+    }
+    node[parts[parts.len() - 1]] = value;
+}
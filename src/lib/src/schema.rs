@@ -0,0 +1,427 @@
+//! JSON Schema of `qernel.yaml`, generated from [`QernelConfig`], and the check run on every
+//! load. serde stops at the first problem and ignores misspelled keys; the check reports all
+//! of them, each with its line and column and, for unknown keys and values, the closest
+//! valid one.
+
+use anyhow::Result;
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::config::QernelConfig;
+use crate::error::QernelError;
+
+/// Suggestions need at least this Jaro-Winkler similarity to the misspelled word
+const MIN_SIMILARITY: f64 = 0.8;
+
+pub fn config_schema() -> RootSchema {
+    schemars::schema_for!(QernelConfig)
+}
+
+/// One problem in a qernel.yaml
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// e.g. `benchmarks.metrics[0].direction`; empty for the document itself
+    pub path: String,
+    /// 1-based, where the key (or list item) is written
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Every place `text` departs from the schema; an error when it is not YAML at all
+pub fn validate_config_text(text: &str) -> Result<Vec<Issue>> {
+    let doc: serde_yaml::Value = serde_yaml::from_str(text).map_err(|e| QernelError::Config(e.to_string()))?;
+    let doc: Value = serde_json::to_value(doc).map_err(|e| QernelError::Config(format!("unsupported YAML: {}", e)))?;
+    let root = config_schema();
+    let mut validator = Validator { root: &root, problems: Vec::new() };
+    validator.check_object(&root.schema, &doc, &mut Vec::new());
+    let mut issues: Vec<Issue> = validator
+        .problems
+        .into_iter()
+        .map(|(path, message)| {
+            let position = locate(text, &path);
+            Issue { path: display_path(&path), line: position.map(|p| p.0), column: position.map(|p| p.1), message }
+        })
+        .collect();
+    issues.sort_by_key(|i| (i.line, i.column));
+    Ok(issues)
+}
+
+/// Fail with every issue in `text`, the content of `config_path`, listed one per line
+pub fn check_config(config_path: &Path, text: &str) -> Result<()> {
+    let issues = validate_config_text(text)
+        .map_err(|e| QernelError::Config(format!("{} is not valid YAML: {}", config_path.display(), e)))?;
+    if issues.is_empty() {
+        return Ok(());
+    }
+    // `file:line:column: ...` when the issue was located, `file: ...` when it was not
+    let listing: Vec<String> = issues
+        .iter()
+        .map(|i| format!("  {}{}{}", config_path.display(), if i.line.is_some() { ":" } else { ": " }, i))
+        .collect();
+    Err(QernelError::Config(format!("{} has {} problem(s):\n{}", config_path.display(), issues.len(), listing.join("\n"))).into())
+}
+
+struct Validator<'a> {
+    root: &'a RootSchema,
+    problems: Vec<(Vec<Segment>, String)>,
+}
+
+impl Validator<'_> {
+    fn check(&mut self, schema: &Schema, value: &Value, path: &mut Vec<Segment>) {
+        match schema {
+            Schema::Bool(true) => {}
+            Schema::Bool(false) => self.problems.push((path.clone(), "is not allowed here".to_string())),
+            Schema::Object(object) => self.check_object(object, value, path),
+        }
+    }
+
+    fn check_object(&mut self, schema: &SchemaObject, value: &Value, path: &mut Vec<Segment>) {
+        if let Some(reference) = &schema.reference {
+            let name = reference.trim_start_matches("#/definitions/");
+            if let Some(target) = self.root.definitions.get(name) {
+                self.check(target, value, path);
+            }
+            return;
+        }
+        if let Some(subschemas) = &schema.subschemas {
+            for sub in subschemas.all_of.iter().flatten() {
+                self.check(sub, value, path);
+            }
+            if let Some(branches) = subschemas.any_of.as_ref().or(subschemas.one_of.as_ref()) {
+                self.check_alternatives(branches, value, path);
+            }
+        }
+
+        if let Some(types) = &schema.instance_type
+            && !type_matches(types, value)
+        {
+            let expected: Vec<String> = single_or_vec(types).iter().map(|t| type_name(**t).to_string()).collect();
+            self.problems.push((path.clone(), format!("expected {}, found {}", expected.join(" or "), describe(value))));
+            return;
+        }
+        if let Some(allowed) = &schema.enum_values
+            && !allowed.contains(value)
+        {
+            self.problems.push((path.clone(), not_one_of(value, allowed)));
+            return;
+        }
+        if let Some(number) = &schema.number
+            && let Some(n) = value.as_f64()
+            && let Some(min) = number.minimum
+            && n < min
+        {
+            self.problems.push((path.clone(), format!("must be at least {}, found {}", min, n)));
+        }
+        if let (Some(max), Some(n)) = (format_max(schema.format.as_deref()), value.as_f64())
+            && n > max
+        {
+            self.problems.push((path.clone(), format!("must be at most {}, found {}", max, n)));
+        }
+
+        if let (Some(array), Some(items)) = (&schema.array, value.as_array())
+            && let Some(SingleOrVec::Single(item_schema)) = &array.items
+        {
+            for (i, item) in items.iter().enumerate() {
+                path.push(Segment::Index(i));
+                self.check(item_schema, item, path);
+                path.pop();
+            }
+        }
+
+        if let (Some(object), Some(map)) = (&schema.object, value.as_object()) {
+            for required in &object.required {
+                if !map.contains_key(required) {
+                    self.problems.push((path.clone(), format!("missing required key `{}`", required)));
+                }
+            }
+            for (key, item) in map {
+                path.push(Segment::Key(key.clone()));
+                if let Some(property) = object.properties.get(key) {
+                    self.check(property, item, path);
+                } else if let Some(additional) = &object.additional_properties {
+                    self.check(additional, item, path);
+                } else if !object.properties.is_empty() {
+                    // Structs accept any key to serde, which hides typos; treat them as closed
+                    let mut message = "unknown key".to_string();
+                    if let Some(close) = closest(key, object.properties.keys().map(String::as_str)) {
+                        message.push_str(&format!("; did you mean `{}`?", close));
+                    }
+                    self.problems.push((path.clone(), message));
+                }
+                path.pop();
+            }
+        }
+    }
+
+    /// `anyOf`/`oneOf`, as schemars writes them for `Option<Struct>` and documented enum
+    /// variants: fine if any branch matches, otherwise the branch of the same type explains why
+    fn check_alternatives(&mut self, branches: &[Schema], value: &Value, path: &[Segment]) {
+        let mut attempts = Vec::new();
+        for branch in branches {
+            let mut trial = Validator { root: self.root, problems: Vec::new() };
+            trial.check(branch, value, &mut path.to_vec());
+            if trial.problems.is_empty() {
+                return;
+            }
+            attempts.push(trial.problems);
+        }
+        let allowed: Vec<Value> = branches.iter().flat_map(|b| self.enum_values(b)).collect();
+        if !allowed.is_empty() && value.is_string() {
+            self.problems.push((path.to_vec(), not_one_of(value, &allowed)));
+            return;
+        }
+        // A branch that failed deeper than `path` had the right shape at this level
+        let depth = path.len();
+        match attempts.iter().find(|problems| problems.iter().any(|(p, _)| p.len() > depth)) {
+            Some(problems) => self.problems.extend(problems.iter().cloned()),
+            None => self.problems.push((path.to_vec(), format!("{} is not allowed here", describe(value)))),
+        }
+    }
+
+    fn enum_values(&self, schema: &Schema) -> Vec<Value> {
+        let Schema::Object(object) = schema else { return Vec::new() };
+        if let Some(reference) = &object.reference {
+            return self.root.definitions.get(reference.trim_start_matches("#/definitions/")).map(|s| self.enum_values(s)).unwrap_or_default();
+        }
+        let mut values = object.enum_values.clone().unwrap_or_default();
+        values.extend(object.const_value.clone());
+        if let Some(sub) = &object.subschemas {
+            for branch in sub.any_of.iter().chain(&sub.one_of).flatten() {
+                values.extend(self.enum_values(branch));
+            }
+        }
+        values
+    }
+}
+
+fn single_or_vec<T>(value: &SingleOrVec<T>) -> Vec<&T> {
+    match value {
+        SingleOrVec::Single(one) => vec![one.as_ref()],
+        SingleOrVec::Vec(many) => many.iter().collect(),
+    }
+}
+
+fn type_matches(types: &SingleOrVec<InstanceType>, value: &Value) -> bool {
+    single_or_vec(types).into_iter().any(|t| match t {
+        InstanceType::Null => value.is_null(),
+        InstanceType::Boolean => value.is_boolean(),
+        InstanceType::Object => value.is_object(),
+        InstanceType::Array => value.is_array(),
+        InstanceType::Number => value.is_number(),
+        InstanceType::String => value.is_string(),
+        InstanceType::Integer => value.is_i64() || value.is_u64(),
+    })
+}
+
+fn type_name(t: InstanceType) -> &'static str {
+    match t {
+        InstanceType::Null => "null",
+        InstanceType::Boolean => "true or false",
+        InstanceType::Object => "a mapping",
+        InstanceType::Array => "a list",
+        InstanceType::Number => "a number",
+        InstanceType::String => "a string",
+        InstanceType::Integer => "an integer",
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        Value::Array(_) => "a list".to_string(),
+        Value::Object(_) => "a mapping".to_string(),
+    }
+}
+
+/// Upper bound of schemars' unsigned integer formats
+fn format_max(format: Option<&str>) -> Option<f64> {
+    match format? {
+        "uint8" => Some(u8::MAX as f64),
+        "uint16" => Some(u16::MAX as f64),
+        "uint32" => Some(u32::MAX as f64),
+        _ => None,
+    }
+}
+
+fn not_one_of(value: &Value, allowed: &[Value]) -> String {
+    let names: Vec<&str> = allowed.iter().filter_map(Value::as_str).collect();
+    let mut message = format!("{} is not one of {}", describe(value), names.join(", "));
+    if let Some(close) = value.as_str().and_then(|v| closest(v, names.iter().copied())) {
+        message.push_str(&format!("; did you mean `{}`?", close));
+    }
+    message
+}
+
+fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    candidates
+        .map(|c| (strsim::jaro_winkler(&word, &c.to_lowercase()), c))
+        .filter(|(score, _)| *score >= MIN_SIMILARITY)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, c)| c)
+}
+
+fn display_path(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if out.is_empty() => out.push_str(key),
+            Segment::Key(key) => out.push_str(&format!(".{}", key)),
+            Segment::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+/// Line and column of the deepest part of `path` found in block-style YAML `text`.
+/// Flow-style values (`{...}`, `[...]`) are located at their key.
+fn locate(text: &str, path: &[Segment]) -> Option<(usize, usize)> {
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let significant = |line: &str| {
+        let t = line.trim_start();
+        !t.is_empty() && !t.starts_with('#') && t != "---"
+    };
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let (mut start, mut end) = (0, lines.len());
+    let mut found = None;
+    for segment in path {
+        let rows: Vec<usize> = (start..end).filter(|&i| significant(&lines[i])).collect();
+        let Some(base) = rows.iter().map(|&i| indent(&lines[i])).min() else { break };
+        let at_base: Vec<usize> = rows.iter().copied().filter(|&i| indent(&lines[i]) == base).collect();
+        match segment {
+            Segment::Key(key) => {
+                let Some(&row) = at_base.iter().find(|&&i| key_of(lines[i].trim_start()) == Some(key.as_str())) else { break };
+                found = Some((row + 1, base + 1));
+                // The value: deeper lines, and a list written at the key's own indentation
+                let next = rows
+                    .iter()
+                    .copied()
+                    .find(|&i| i > row && (indent(&lines[i]) < base || (indent(&lines[i]) == base && !lines[i].trim_start().starts_with('-'))));
+                start = row + 1;
+                end = next.unwrap_or(end);
+            }
+            Segment::Index(n) => {
+                let items: Vec<usize> = at_base.into_iter().filter(|&i| lines[i].trim_start().starts_with('-')).collect();
+                let Some(&row) = items.get(*n) else { break };
+                found = Some((row + 1, base + 1));
+                // The item's own mapping starts after the dash, one level deeper
+                lines[row].replace_range(base..base + 1, " ");
+                start = row;
+                end = items.get(n + 1).copied().unwrap_or(end);
+            }
+        }
+    }
+    found
+}
+
+/// `name` for a `name: ...` or `"name": ...` line
+fn key_of(line: &str) -> Option<&str> {
+    let (key, rest) = if let Some(quoted) = line.strip_prefix('"').or_else(|| line.strip_prefix('\'')) {
+        let close = quoted.find(['"', '\''])?;
+        (&quoted[..close], &quoted[close + 1..])
+    } else {
+        let colon = line.find(':')?;
+        (line[..colon].trim_end(), &line[colon..])
+    };
+    let rest = rest.trim_start();
+    (rest.starts_with(':') && (rest.len() == 1 || rest[1..].starts_with([' ', '\t']))).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "version: 2
+project:
+  name: demo
+  description: A demo
+agent:
+  model: gpt-5-codex
+  max_iterations: 15
+papers: []
+content_files: null
+benchmarks:
+  test_command: python -m pytest src/tests.py -v
+";
+
+    fn issues(text: &str) -> Vec<Issue> {
+        validate_config_text(text).unwrap()
+    }
+
+    #[test]
+    fn a_valid_config_and_the_defaults_have_no_issues() {
+        assert_eq!(issues(VALID), Vec::new());
+        let defaults = serde_yaml::to_string(&QernelConfig::default()).unwrap();
+        assert_eq!(issues(&defaults), Vec::new());
+    }
+
+    #[test]
+    fn unknown_keys_are_located_and_get_a_suggestion() {
+        let text = VALID.replace("  max_iterations: 15", "  max_iteration: 15");
+        let found = issues(&text);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_eq!(found[0].to_string(), "5:1: agent: missing required key `max_iterations`");
+        assert_eq!(found[1].path, "agent.max_iteration");
+        assert_eq!((found[1].line, found[1].column), (Some(7), Some(3)));
+        assert!(found[1].message.contains("did you mean `max_iterations`?"), "{}", found[1].message);
+    }
+
+    #[test]
+    fn every_problem_is_reported_in_document_order() {
+        let text = VALID.replace("  max_iterations: 15", "  max_iterations: many")
+            + "  metrics:\n    - name: fidelity\n      direction: maximise\n";
+        let found = issues(&text);
+        let paths: Vec<&str> = found.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["agent.max_iterations", "benchmarks.metrics[0].direction"]);
+        assert!(found[0].message.contains("found \"many\""), "{}", found[0].message);
+        assert_eq!(found[1].line, Some(14));
+        assert!(found[1].message.contains("did you mean `maximize`?"), "{}", found[1].message);
+    }
+
+    #[test]
+    fn a_missing_required_key_is_reported_at_its_parent() {
+        let text = VALID.replace("papers: []\n", "");
+        let found = issues(&text);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert_eq!(found[0].path, "");
+        assert_eq!(found[0].line, None);
+        assert_eq!(found[0].to_string(), "missing required key `papers`");
+    }
+
+    #[test]
+    fn the_listing_separates_the_file_from_each_issue() {
+        let text = VALID.replace("papers: []\n", "").replace("  max_iterations: 15", "  max_iteration: 15");
+        let error = check_config(Path::new("qernel.yaml"), &text).unwrap_err().to_string();
+        assert!(error.contains("\n  qernel.yaml: missing required key `papers`"), "{}", error);
+        assert!(error.contains("\n  qernel.yaml:7:3: agent.max_iteration: unknown key"), "{}", error);
+    }
+
+    #[test]
+    fn text_that_is_not_yaml_is_an_error() {
+        assert!(validate_config_text("project: [unclosed").is_err());
+    }
+}
@@ -0,0 +1,550 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Config {
+    pub token: Option<String>,
+    pub default_remote: Option<String>,
+    pub default_server: Option<String>,
+    /// Optional OpenAI API key for prototyping features
+    pub openai_api_key: Option<String>,
+    /// Default agent model when neither the project nor the environment sets one
+    pub model: Option<String>,
+    /// Default agent iteration limit
+    pub max_iterations: Option<u32>,
+    /// Default model for `qernel explain`
+    pub explain_model: Option<String>,
+    /// API keys for other providers, keyed by provider name (see `SECRET_PROVIDERS`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, String>,
+    /// Requests per minute allowed per provider (e.g. `openai = 60`); 0 disables limiting
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rate_limits: BTreeMap<String, u32>,
+    /// Terminal theme: `name` and message colors (see `cmd::common::theme`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub theme: BTreeMap<String, String>,
+    /// Where OpenAI models are served from: `openai` (default) or `azure`; see `qernel provider`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Azure OpenAI resource used when `provider` is `azure`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure: Option<AzureConfig>,
+    /// Named sets of account settings layered over the ones above; see [`active_profile`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Profile used when neither `--profile` nor `QERNEL_PROFILE` names one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// `profiles.<name>`: account settings that replace the top-level ones while the profile is
+/// active. Unset fields fall through to the top level; `secrets` and `azure.deployments`
+/// are merged entry by entry, so a secret the profile does not set is the top-level one.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_remote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain_model: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure: Option<AzureConfig>,
+}
+
+/// Top-level user config keys a profile can hold
+const PROFILE_KEYS: &[&str] =
+    &["token", "default_remote", "default_server", "model", "explain_model", "secrets", "provider", "azure"];
+
+/// An Azure OpenAI resource: requests go to `<base_url>/openai/responses` with the deployment as `model`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AzureConfig {
+    /// e.g. `https://my-resource.openai.azure.com`
+    pub base_url: Option<String>,
+    /// `api-version` query parameter (default: `cmd::common::endpoint::DEFAULT_AZURE_API_VERSION`)
+    pub api_version: Option<String>,
+    /// Deployment name per model; a model without an entry is assumed to be deployed under its own name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deployments: BTreeMap<String, String>,
+}
+
+/// Providers whose keys can be stored with `qernel config set secrets.<provider>`,
+/// and the environment variable that takes precedence over the stored key.
+pub const SECRET_PROVIDERS: &[(&str, &str)] = &[
+    ("openai", "OPENAI_API_KEY"),
+    ("anthropic", "ANTHROPIC_API_KEY"),
+    ("openrouter", "OPENROUTER_API_KEY"),
+    ("azure", "AZURE_OPENAI_API_KEY"),
+    ("ibm_quantum", "IBM_QUANTUM_TOKEN"),
+];
+
+/// Environment variable consulted for a provider's key
+pub fn provider_env_var(provider: &str) -> Option<&'static str> {
+    SECRET_PROVIDERS.iter().find(|(p, _)| *p == provider).map(|(_, v)| *v)
+}
+
+/// Error listing the known providers when `provider` isn't one of them
+pub fn ensure_known_provider(provider: &str) -> Result<()> {
+    if provider_env_var(provider).is_none() {
+        let known: Vec<&str> = SECRET_PROVIDERS.iter().map(|(p, _)| *p).collect();
+        anyhow::bail!("Unknown provider '{}'. Known providers: {}", provider, known.join(", "));
+    }
+    Ok(())
+}
+
+static CONFIG_HOME: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+
+/// Use `dir` for the user config and caches, from the global `--config-dir` flag
+pub fn set_config_home(dir: std::path::PathBuf) {
+    let _ = CONFIG_HOME.set(dir);
+}
+
+/// `--config-dir`, else `QERNEL_CONFIG_HOME`; `None` means the platform locations
+pub fn config_home() -> Option<std::path::PathBuf> {
+    config_home_origin().map(|(dir, _)| dir)
+}
+
+/// [`config_home`] and what set it (`--config-dir` or `QERNEL_CONFIG_HOME`)
+pub fn config_home_origin() -> Option<(std::path::PathBuf, &'static str)> {
+    if let Some(dir) = CONFIG_HOME.get() {
+        return Some((dir.clone(), "--config-dir"));
+    }
+    std::env::var("QERNEL_CONFIG_HOME")
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| (std::path::PathBuf::from(d), "QERNEL_CONFIG_HOME"))
+}
+
+/// File name of the user config inside the config home
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+static PROFILE: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+/// Use the named profile for this invocation, from the global `--profile` flag
+pub fn set_profile(name: String) {
+    let _ = PROFILE.set(name);
+}
+
+/// Profile in effect: `--profile`, else `QERNEL_PROFILE`, else the one chosen with
+/// `qernel config use-profile`. A name without an entry yet is an empty profile, so
+/// settings saved while it is active create it.
+pub fn active_profile(cfg: &Config) -> Option<String> {
+    PROFILE
+        .get()
+        .cloned()
+        .or_else(|| std::env::var("QERNEL_PROFILE").ok())
+        .or_else(|| cfg.profile.clone())
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+}
+
+/// The active profile when a change to the user config key at `path` (e.g. `secrets.openai`)
+/// would be saved to it rather than to the top level
+pub fn profile_for_key(path: &str) -> Option<String> {
+    let top = path.split('.').next().unwrap_or(path);
+    if !PROFILE_KEYS.contains(&top) {
+        return None;
+    }
+    active_profile(&load_stored_config().ok()?)
+}
+
+/// The user config as it applies to this invocation: the active profile layered over the
+/// top-level settings. Use [`edit_config`] to change it.
+pub fn load_config() -> Result<Config> {
+    let stored = load_stored_config()?;
+    match active_profile(&stored) {
+        Some(name) => layered(stored, &name),
+        None => Ok(stored),
+    }
+}
+
+/// `stored` with profile `name` layered over its top-level settings
+fn layered(stored: Config, name: &str) -> Result<Config> {
+    let Some(profile) = stored.profiles.get(name) else { return Ok(stored) };
+    let mut root = serde_json::to_value(&stored).context("failed to read config")?;
+    merge_json(&mut root, serde_json::to_value(profile).context("failed to read profile")?);
+    serde_json::from_value(root).with_context(|| format!("invalid settings in profile '{}'", name))
+}
+
+/// The user config file as written, without applying a profile
+pub fn load_stored_config() -> Result<Config> {
+    let cfg: Config = match config_home() {
+        Some(dir) => confy::load_path(dir.join(CONFIG_FILE_NAME)),
+        None => confy::load("qernel", None),
+    }
+    .context("failed to load config")?;
+    Ok(cfg)
+}
+
+/// Change the user config as [`load_config`] presents it. With a profile active, changed
+/// account settings (see [`Profile`]) are saved to that profile; everything else goes to
+/// the top level. Removing a setting the profile only inherits is refused, since the
+/// top-level value would still apply; removing one the profile sets brings the top-level
+/// value back.
+pub fn edit_config(edit: impl FnOnce(&mut Config) -> Result<()>) -> Result<()> {
+    let stored = load_stored_config().unwrap_or_default();
+    let Some(name) = active_profile(&stored) else {
+        let mut cfg = stored;
+        edit(&mut cfg)?;
+        return save_config(&cfg);
+    };
+    save_config(&edit_profile(stored, &name, edit)?)
+}
+
+/// `stored` after `edit` changed it as seen with profile `name` active
+fn edit_profile(stored: Config, name: &str, edit: impl FnOnce(&mut Config) -> Result<()>) -> Result<Config> {
+    let before = layered(stored.clone(), name)?;
+    let mut after = before.clone();
+    edit(&mut after)?;
+    let before = serde_json::to_value(&before).context("failed to read config")?;
+    let after = serde_json::to_value(&after).context("failed to read config")?;
+    let mut root = serde_json::to_value(&stored).context("failed to read config")?;
+    let empty = serde_json::Map::new();
+    let (before, after) = (before.as_object().unwrap_or(&empty), after.as_object().unwrap_or(&empty));
+    let mut changed = Vec::new();
+    for key in before.keys().chain(after.keys()).collect::<std::collections::BTreeSet<_>>() {
+        let (old, new) = (before.get(key), after.get(key));
+        if old == new {
+            continue;
+        }
+        changed.push(key);
+        let target = if PROFILE_KEYS.contains(&key.as_str()) {
+            let profiles = root.as_object_mut().context("config is not a table")?.entry("profiles").or_insert_with(|| serde_json::json!({}));
+            profiles.as_object_mut().context("profiles is not a table")?.entry(name.to_string()).or_insert_with(|| serde_json::json!({}))
+        } else {
+            &mut root
+        };
+        apply_change(target, key, old, new);
+    }
+    let cfg: Config = serde_json::from_value(root).context("invalid user config value")?;
+    let effective = serde_json::to_value(layered(cfg.clone(), name)?).context("failed to read config")?;
+    // Paths where the edit did not take effect. Removing the profile's own value and getting
+    // the top-level one back is expected; anything else was only ever inherited.
+    let null = serde_json::Value::Null;
+    let own = serde_json::to_value(stored.profiles.get(name)).context("failed to read profile")?;
+    let mut missed = Vec::new();
+    for key in changed {
+        differences(effective.get(key).unwrap_or(&null), after.get(key).unwrap_or(&null), key, &mut missed);
+    }
+    if let Some(key) = missed.into_iter().find(|path| path.split('.').try_fold(&own, |v, k| v.get(k)).is_none_or(|v| v.is_null())) {
+        return Err(crate::error::QernelError::Config(format!(
+            "{} is inherited from the top-level settings, not set in profile '{}'; \
+             run the command with `--profile ''` to change the top-level value",
+            key, name
+        ))
+        .into());
+    }
+    Ok(cfg)
+}
+
+/// Dotted paths of the entries under `path` where `a` and `b` differ; a missing entry,
+/// null and an empty table count as the same
+fn differences(a: &serde_json::Value, b: &serde_json::Value, path: &str, out: &mut Vec<String>) {
+    let empty = serde_json::Map::new();
+    let table = |v: &serde_json::Value| match v {
+        serde_json::Value::Null => Some(empty.clone()),
+        serde_json::Value::Object(map) => Some(map.clone()),
+        _ => None,
+    };
+    match (table(a), table(b)) {
+        (Some(a), Some(b)) => {
+            let null = serde_json::Value::Null;
+            for k in a.keys().chain(b.keys()).collect::<std::collections::BTreeSet<_>>() {
+                differences(a.get(k).unwrap_or(&null), b.get(k).unwrap_or(&null), &format!("{}.{}", path, k), out);
+            }
+        }
+        _ if a == b => {}
+        _ => out.push(path.to_string()),
+    }
+}
+
+/// Overlay `over` onto `base`: tables merge key by key, anything else replaces; nulls are skipped
+fn merge_json(base: &mut serde_json::Value, over: serde_json::Value) {
+    match (base, over) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(slot) if !slot.is_null() => merge_json(slot, value),
+                    _ if value.is_null() => {}
+                    _ => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, serde_json::Value::Null) => {}
+        (base, over) => *base = over,
+    }
+}
+
+/// Record in `target` that `key` went from `old` to `new`, descending into tables so only
+/// the entries that changed are written
+fn apply_change(target: &mut serde_json::Value, key: &str, old: Option<&serde_json::Value>, new: Option<&serde_json::Value>) {
+    let Some(map) = target.as_object_mut() else { return };
+    match (old, new) {
+        (Some(serde_json::Value::Object(old)), Some(serde_json::Value::Object(new))) => {
+            let slot = map.entry(key.to_string()).or_insert_with(|| serde_json::json!({}));
+            if !slot.is_object() {
+                *slot = serde_json::json!({});
+            }
+            for k in old.keys().chain(new.keys()).collect::<std::collections::BTreeSet<_>>() {
+                if old.get(k) != new.get(k) {
+                    apply_change(slot, k, old.get(k), new.get(k));
+                }
+            }
+        }
+        (_, None | Some(serde_json::Value::Null)) => {
+            map.remove(key);
+        }
+        (_, Some(value)) => {
+            map.insert(key.to_string(), value.clone());
+        }
+    }
+}
+
+/// Location of the user config file
+pub fn config_file_path() -> Result<std::path::PathBuf> {
+    match config_home() {
+        Some(dir) => Ok(dir.join(CONFIG_FILE_NAME)),
+        None => confy::get_configuration_file_path("qernel", None).context("failed to locate config file"),
+    }
+}
+
+pub fn save_config(cfg: &Config) -> Result<()> {
+    match config_home() {
+        Some(dir) => confy::store_path(dir.join(CONFIG_FILE_NAME), cfg),
+        None => confy::store("qernel", None, cfg),
+    }
+    .context("failed to save config")?;
+    Ok(())
+}
+
+use supports_color::Stream;
+use owo_colors::OwoColorize;
+
+/// When to color output, from the global `--color` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorPolicy {
+    /// Color terminals only, honoring NO_COLOR and FORCE_COLOR
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+static COLOR_POLICY: std::sync::OnceLock<ColorPolicy> = std::sync::OnceLock::new();
+
+/// Set the policy for this process; called once from main before any output
+pub fn set_color_policy(policy: ColorPolicy) {
+    let _ = COLOR_POLICY.set(policy);
+    // Progress bars and styled text from the `console` crate follow the same policy
+    if policy != ColorPolicy::Auto {
+        console::set_colors_enabled(policy == ColorPolicy::Always);
+        console::set_colors_enabled_stderr(policy == ColorPolicy::Always);
+    }
+}
+
+/// How much the CLI prints, from the global `-q/--quiet` and `-v/--verbose` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OutputLevel {
+    /// Warnings, errors and outcomes only; no progress bars, spinners or animations
+    Quiet,
+    #[default]
+    Normal,
+    /// Everything `--debug` shows on the console, without writing a run log
+    Verbose,
+}
+
+static OUTPUT_LEVEL: std::sync::OnceLock<OutputLevel> = std::sync::OnceLock::new();
+
+/// Set the level for this process; called once from main before any output
+pub fn set_output_level(level: OutputLevel) {
+    let _ = OUTPUT_LEVEL.set(level);
+}
+
+pub fn output_level() -> OutputLevel {
+    OUTPUT_LEVEL.get().copied().unwrap_or_default()
+}
+
+/// A spinner, drawn unless the output level is quiet
+pub fn spinner() -> indicatif::ProgressBar {
+    hide_when_quiet(indicatif::ProgressBar::new_spinner())
+}
+
+/// A progress bar of `len` steps, drawn unless the output level is quiet
+pub fn progress_bar(len: u64) -> indicatif::ProgressBar {
+    hide_when_quiet(indicatif::ProgressBar::new(len))
+}
+
+fn hide_when_quiet(bar: indicatif::ProgressBar) -> indicatif::ProgressBar {
+    if output_level() == OutputLevel::Quiet {
+        bar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+    }
+    bar
+}
+
+fn color_enabled(stream: Stream) -> bool {
+    match COLOR_POLICY.get().copied().unwrap_or_default() {
+        ColorPolicy::Always => true,
+        ColorPolicy::Never => false,
+        ColorPolicy::Auto => supports_color::on(stream).is_some(),
+    }
+}
+
+pub fn color_enabled_stdout() -> bool {
+    color_enabled(Stream::Stdout)
+}
+
+/// `text` as it should be written to stdout: ANSI escapes removed unless colors are enabled
+pub fn colorize_stdout(text: &str) -> std::borrow::Cow<'_, str> {
+    if color_enabled_stdout() { std::borrow::Cow::Borrowed(text) } else { console::strip_ansi_codes(text) }
+}
+
+pub fn sym_check(enabled: bool) -> String {
+    if enabled { format!("{}", "✔".green().bold()) } else { "✔".to_string() }
+}
+
+pub fn sym_cross(enabled: bool) -> String {
+    if enabled { format!("{}", "✖".red().bold()) } else { "x".to_string() }
+}
+
+pub fn sym_question(enabled: bool) -> String {
+    if enabled { format!("{}", "?".cyan().bold()) } else { "?".to_string() }
+}
+
+pub fn sym_gear(enabled: bool) -> String {
+    if enabled { format!("{}", "⚙".blue().bold()) } else { "⚙".to_string() }
+}
+
+/// Resolve an OpenAI API key from environment or stored config
+pub fn get_openai_api_key_from_env_or_config() -> Option<String> {
+    get_api_key("openai", None)
+}
+
+fn non_empty_env(var: &str) -> Option<String> {
+    std::env::var(var).ok().map(|k| k.trim().to_string()).filter(|k| !k.is_empty())
+}
+
+/// Resolve a provider's API key. With `api_key_env` (the `--api-key-env` flag) only that
+/// variable is read; otherwise the provider's env var wins over the stored secret.
+pub fn get_api_key(provider: &str, api_key_env: Option<&str>) -> Option<String> {
+    if let Some(var) = api_key_env {
+        return non_empty_env(var);
+    }
+    if let Some(k) = provider_env_var(provider).and_then(non_empty_env) {
+        return Some(k);
+    }
+    let cfg = load_config().ok()?;
+    let stored = cfg.secrets.get(provider).cloned().or_else(|| {
+        // Keys saved before the secrets map existed
+        if provider == "openai" { cfg.openai_api_key.clone() } else { None }
+    });
+    stored.map(|k| k.trim().to_string()).filter(|k| !k.is_empty())
+}
+
+/// Like `get_api_key`, but with an actionable error when no key is available
+pub fn require_api_key(provider: &str, api_key_env: Option<&str>) -> Result<String> {
+    // The mock provider answers from fixtures and needs no key
+    if crate::cmd::common::mock::enabled() {
+        return Ok("mock".to_string());
+    }
+    get_api_key(provider, api_key_env).ok_or_else(|| match api_key_env {
+        Some(var) => anyhow::anyhow!("{} (from --api-key-env) is not set or empty", var),
+        None => anyhow::anyhow!(
+            "No {} API key found. Set {} or run 'qernel config set secrets.{}'.",
+            provider,
+            provider_env_var(provider).unwrap_or("the provider's API key variable"),
+            provider
+        ),
+    })
+}
+
+/// Persist a provider API key into the local config (not committed to git)
+pub fn set_api_key_in_config(provider: &str, secret: &str) -> Result<()> {
+    ensure_known_provider(provider)?;
+    edit_config(|cfg| {
+        cfg.secrets.insert(provider.to_string(), secret.trim().to_string());
+        if provider == "openai" {
+            cfg.openai_api_key = None;
+        }
+        Ok(())
+    })
+}
+
+/// Remove a stored provider API key from the local config
+pub fn unset_api_key_in_config(provider: &str) -> Result<()> {
+    ensure_known_provider(provider)?;
+    edit_config(|cfg| {
+        cfg.secrets.remove(provider);
+        if provider == "openai" {
+            cfg.openai_api_key = None;
+        }
+        Ok(())
+    })
+}
+
+/// Providers with a key stored in the local config
+pub fn stored_api_key_providers() -> Vec<String> {
+    let Ok(cfg) = load_config() else { return Vec::new() };
+    let mut providers: Vec<String> = cfg.secrets.iter()
+        .filter(|(_, v)| !v.trim().is_empty())
+        .map(|(k, _)| k.clone())
+        .collect();
+    if cfg.openai_api_key.as_deref().is_some_and(|k| !k.trim().is_empty()) && !providers.iter().any(|p| p == "openai") {
+        providers.insert(0, "openai".to_string());
+    }
+    providers
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored() -> Config {
+        let mut cfg = Config::default();
+        cfg.secrets.insert("openai".into(), "top".into());
+        cfg.secrets.insert("anthropic".into(), "top-anthropic".into());
+        cfg.profiles.insert("work".into(), Profile { secrets: BTreeMap::from([("openai".into(), "work".into())]), ..Default::default() });
+        cfg
+    }
+
+    #[test]
+    fn profile_secrets_fall_back_to_top_level_ones() {
+        let cfg = layered(stored(), "work").unwrap();
+        assert_eq!(cfg.secrets["openai"], "work");
+        assert_eq!(cfg.secrets["anthropic"], "top-anthropic");
+    }
+
+    #[test]
+    fn changes_go_to_the_active_profile() {
+        let cfg = edit_profile(stored(), "work", |c| {
+            c.secrets.insert("anthropic".into(), "work-anthropic".into());
+            c.secrets.remove("openai");
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(cfg.secrets["openai"], "top");
+        assert_eq!(cfg.secrets["anthropic"], "top-anthropic");
+        assert_eq!(cfg.profiles["work"].secrets, BTreeMap::from([("anthropic".into(), "work-anthropic".into())]));
+    }
+
+    #[test]
+    fn unsetting_an_inherited_setting_is_refused() {
+        let err = edit_profile(stored(), "work", |c| {
+            c.secrets.remove("anthropic");
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("secrets.anthropic is inherited"), "{}", err);
+    }
+}
@@ -1,15 +1,31 @@
-mod cmd;
-mod config;
-mod util;
-
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use qernel_lib::{QernelError, cmd, util};
 
 #[derive(Parser)]
 #[command(name = "qernel", version, about = "Lightweight quantum CLI", long_about = None, disable_help_subcommand = true)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Write a Chrome trace (chrome://tracing, Perfetto) of where the run spends its time
+    #[arg(long, global = true, value_name = "PATH")]
+    trace_file: Option<std::path::PathBuf>,
+    /// When to color output; auto honors NO_COLOR and colors only terminals
+    #[arg(long, global = true, value_enum, default_value_t = util::ColorPolicy::Auto, value_name = "WHEN")]
+    color: util::ColorPolicy,
+    /// Keep the user config and caches in this directory instead of the platform locations
+    /// (also QERNEL_CONFIG_HOME)
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<std::path::PathBuf>,
+    /// Use this profile from the user config's `profiles` (also QERNEL_PROFILE)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    /// Print only warnings, errors and outcomes: no progress bars, spinners or command output
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print everything, as --debug does on the console, without writing a run log
+    #[arg(short, long, global = true)]
+    verbose: bool,
 }
 
 #[derive(Subcommand)]
@@ -17,19 +33,74 @@ enum Commands {
     /// Create a new qernel repo with scaffolded structure
     New {
         /// Project directory to create
-        path: String,
-        /// Initialize with prototype template
+        #[arg(required_unless_present = "list_templates")]
+        path: Option<String>,
+        /// Initialize from a project template (default: prototype)
+        #[arg(long, num_args = 0..=1, default_missing_value = "prototype", value_name = "NAME")]
+        template: Option<String>,
+        /// List the available project templates
+        #[arg(long)]
+        list_templates: bool,
+        /// Skip creating .qernel/.venv and installing requirements
+        #[arg(long)]
+        no_venv: bool,
+        /// Set a template variable declared in template.yaml (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+        /// Don't run the template's post_generate commands
         #[arg(long)]
-        template: bool,
+        no_hooks: bool,
+        /// Run a remote template's post_generate commands without asking
+        #[arg(long, conflicts_with = "no_hooks")]
+        trust: bool,
     },
-    /// Authenticate with the Zoo and manage local OpenAI API key
-    Auth {
-        /// Set and save an OpenAI API key (reads from stdin if empty prompt)
+    /// Manage the project Python environment
+    Env {
+        #[command(subcommand)]
+        command: EnvCommand,
+    },
+    /// Inspect and clean the shared download, parse and model response caches
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Inspect and edit layered configuration (flags > env > project > user > defaults)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Show or choose where OpenAI models are served from (openai or azure)
+    Provider {
+        /// Use this provider from now on: openai | azure
+        #[arg(long, value_name = "PROVIDER")]
+        set: Option<String>,
+        /// Azure resource endpoint, e.g. https://my-resource.openai.azure.com
+        #[arg(long, value_name = "URL")]
+        base_url: Option<String>,
+        /// Azure api-version query parameter
+        #[arg(long, value_name = "VERSION")]
+        api_version: Option<String>,
+        /// Serve a model from an Azure deployment (repeatable); MODEL= removes the mapping
+        #[arg(long = "deployment", value_name = "MODEL=DEPLOYMENT", value_parser = parse_var)]
+        deployments: Vec<(String, String)>,
+        /// List the models the provider and a local Ollama server offer, with context sizes
         #[arg(long)]
+        models: bool,
+    },
+    /// Authenticate with the Zoo
+    Auth {
+        /// Deprecated: use `qernel config set secrets.openai`
+        #[arg(long, hide = true)]
         set_openai_key: bool,
-        /// Remove any stored OpenAI API key from local config
-        #[arg(long)]
+        /// Deprecated: use `qernel config unset secrets.openai`
+        #[arg(long, hide = true)]
         unset_openai_key: bool,
+        /// Deprecated: use `qernel config set secrets.<provider>`
+        #[arg(long, value_name = "PROVIDER", hide = true)]
+        set_key: Option<String>,
+        /// Deprecated: use `qernel config unset secrets.<provider>`
+        #[arg(long, value_name = "PROVIDER", hide = true)]
+        unset_key: Option<String>,
     },
     /// Push current repo to remote server
     Push {
@@ -45,6 +116,15 @@ enum Commands {
         /// Skip auto-commit of changes
         #[arg(long)]
         no_commit: bool,
+        /// After pushing, upload the last run's report, usage, metrics history and diffs to the Zoo
+        #[arg(long)]
+        artifacts: bool,
+        /// Zoo API base URL for --artifacts (default: default_server, then the hosted Zoo)
+        #[arg(long, requires = "artifacts")]
+        server: Option<String>,
+        /// Push even if the scan for API keys and tokens finds something
+        #[arg(long)]
+        allow_secrets: bool,
     },
     /// Pull (clone) a repo from server or full URL
     Pull {
@@ -56,7 +136,7 @@ enum Commands {
         #[arg(long)]
         branch: Option<String>,
         /// Server base URL when repo is not a full URL
-        #[arg(long, default_value = "https://dojoservice.onrender.com/")]
+        #[arg(long, default_value = cmd::zoo::DEFAULT_SERVER)]
         server: String,
     },
     /// Run prototype implementation with AI agent
@@ -64,35 +144,169 @@ enum Commands {
         /// Working directory
         #[arg(long, default_value = ".")]
         cwd: String,
-        /// OpenAI model to use (e.g., gpt-4o-mini)
-        #[arg(long, default_value = "gpt-5-codex")]
-        model: String,
-        /// Max iterations for AI loop
-        #[arg(long, default_value_t = 15)]
-        max_iters: u32,
-        /// Enable debug logging to .logs file
+        /// OpenAI model to use (default: agent.model from `qernel config show --effective`)
+        #[arg(long)]
+        model: Option<String>,
+        /// Max iterations for AI loop (default: agent.max_iterations, 15)
+        #[arg(long)]
+        max_iters: Option<u32>,
+        /// Enable debug logging to .qernel/logs/run-<ts>.jsonl
         #[arg(long)]
         debug: bool,
-        /// Use existing .qernel/spec.md only (skip papers and content_files processing)
+        /// Use existing .qernel/spec.md only (skip papers and content_files processing; see `qernel ingest`)
         #[arg(long)]
         spec_only: bool,
         /// Use .qernel/spec.md and content_files only (skip papers processing)
         #[arg(long)]
         spec_and_content_only: bool,
+        /// Download and parse papers again even if unchanged since the last run
+        #[arg(long, conflicts_with_all = ["spec_only", "spec_and_content_only", "resume"])]
+        force_reparse: bool,
         /// One-shot prototype an arXiv paper URL (creates new project arxiv-<id>)
         #[arg(long)]
         arxiv: Option<String>,
+        /// Skip the planning phase (no .qernel/plan.md is drafted before coding)
+        #[arg(long)]
+        no_plan: bool,
+        /// After a successful run, write .qernel/report.md mapping the code to the paper (also agent.report)
+        #[arg(long)]
+        report: bool,
+        /// Continue from .qernel/checkpoint.json after Ctrl-C or .qernel/STOP
+        #[arg(long, conflicts_with = "arxiv")]
+        resume: bool,
+        /// Never prompt; continue every iteration (implied when stdin is not a terminal).
+        /// Exits 0 on success, 2 at max iterations, 3 when a budget is exceeded, 1 on other errors
+        #[arg(long, short = 'y', visible_alias = "non-interactive")]
+        yes: bool,
+        /// Write a JSON summary of the run (outcome, iterations, metrics, patches, usage, artifacts) here
+        #[arg(long, value_name = "PATH")]
+        output_json: Option<std::path::PathBuf>,
+        /// Read the API key from this environment variable for this run only
+        #[arg(long, value_name = "VAR")]
+        api_key_env: Option<String>,
+        /// Which agent actions wait for your go-ahead (default: as agent.command_policy says)
+        #[arg(long, value_enum, value_name = "MODE")]
+        approval: Option<cmd::prototype::approval::ApprovalMode>,
+        /// Print the test output line by line while the tests run instead of after they finish
+        #[arg(long)]
+        stream_tests: bool,
+        /// Run every member of the qernel-workspace.yaml at or above --cwd
+        #[arg(long, conflicts_with = "arxiv")]
+        all: bool,
+        /// With --all, members to run at once (overrides jobs: in qernel-workspace.yaml)
+        #[arg(long, short = 'j', requires = "all")]
+        jobs: Option<usize>,
+        /// After a successful run, commit to a new qernel/<project>-<time> branch, push it and open a
+        /// pull request on GitHub (GITHUB_TOKEN or GH_TOKEN) or the Zoo describing the run
+        #[arg(long, conflicts_with = "all")]
+        create_pr: bool,
+        /// Remote to push the branch to with --create-pr
+        #[arg(long, default_value = "origin", requires = "create_pr")]
+        remote: String,
+    },
+    /// Download and parse papers and content files into .qernel/spec.md without running the agent
+    Ingest {
+        /// Only ingest the papers listed in qernel.yaml
+        #[arg(long)]
+        papers: bool,
+        /// Only ingest content_files
+        #[arg(long)]
+        content: bool,
+        /// Download and parse papers again even if parsed output exists
+        #[arg(long)]
+        force: bool,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Add, list, remove and re-ingest the papers in qernel.yaml
+    Papers {
+        #[command(subcommand)]
+        command: PapersCommand,
+    },
+    /// Show the implementation report (.qernel/report.md) from `qernel prototype --report`
+    See {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+        /// Print directly instead of paging with less
+        #[arg(long)]
+        no_pager: bool,
+    },
+    /// Show where a project stands: config, last run, uncommitted changes, papers, venv and API key
+    /// (at a workspace root: the last run of every member)
+    Status {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Benchmarks and catalog entries from the Qernel Zoo
+    Zoo {
+        #[command(subcommand)]
+        command: ZooCommand,
+    },
+    /// Show or verify the hash-chained record of agent actions (.qernel/audit.jsonl)
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+    /// Pack a run into a .qernelrun tarball, or replay one onto a fresh scaffold
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
+    /// Inspect structured prototype run logs (.qernel/logs)
+    Logs {
+        #[command(subcommand)]
+        command: LogsCommand,
+    },
+    /// Upgrade an older project's .qernel layout and qernel.yaml (also done whenever qernel opens it)
+    Migrate {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+        /// List the planned changes without making them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Run the benchmark over the parameter grid in qernel.yaml's `sweep:` section
+    Sweep {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+        /// Runs to execute at once (overrides sweep.parallelism)
+        #[arg(long, short = 'j')]
+        parallelism: Option<usize>,
+        /// List the combinations without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show the file changes an agent iteration made, side by side
+    Diff {
+        /// Working directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+        /// Iteration to show (lists recorded iterations if omitted)
+        #[arg(long, value_name = "N")]
+        turn: Option<u32>,
+        /// Write a side-by-side HTML report next to the stored diff instead of printing
+        #[arg(long)]
+        html: bool,
     },
     /// Explain Python source files with snippet-level analysis
     Explain {
-        /// One or more files to explain
+        /// Files or directories to explain; a directory is explained module by module, imports first
         files: Vec<String>,
         /// Granularity: function | class | block (default: function)
         #[arg(long, default_value = "function")]
         per: String,
-        /// OpenAI model to use (default: codex-mini-latest)
-        #[arg(long, default_value = "codex-mini-latest")]
-        model: String,
+        /// OpenAI model to use (default: explain.model, codex-mini-latest)
+        #[arg(long)]
+        model: Option<String>,
+        /// Compare several models side by side, e.g. gpt-5-codex,llama3.1:8b (overrides --model;
+        /// default: explain.models). Ollama-style tags (name:tag) or ollama/<name> run against a local Ollama server.
+        #[arg(long, value_delimiter = ',', value_name = "MODELS")]
+        models: Vec<String>,
         /// Emit Markdown to .qernel/explain or to --output if provided
         #[arg(long)]
         markdown: bool,
@@ -102,24 +316,427 @@ enum Commands {
         /// Disable paging (default: pager on)
         #[arg(long)]
         no_pager: bool,
-        /// Max characters per explanation
+        /// Max characters per explanation (default: explain.max_chars)
         #[arg(long)]
         max_chars: Option<usize>,
+        /// Read the API key from this environment variable for this run only
+        #[arg(long, value_name = "VAR")]
+        api_key_env: Option<String>,
+        /// Only explain the functions and classes changed since this git revision (all changed
+        /// Python files when no paths are given), then summarize what changed
+        #[arg(long, value_name = "REF")]
+        since: Option<String>,
+    },
+    /// Show a guide (prototype-workflow, patch-format, providers, benchmarks) or a command's help
+    Help {
+        /// Guide or command name; lists the guides when omitted
+        topic: Option<String>,
+        /// Disable paging (default: pager on)
+        #[arg(long)]
+        no_pager: bool,
+        /// Write man pages for qernel and its commands to this directory (needs a build with `--features man`)
+        #[arg(long, value_name = "DIR", conflicts_with = "topic")]
+        man: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnvCommand {
+    /// Create the project environment (.qernel/.venv, conda or uv; see python.env_manager), install its dependencies and verify python_version
+    Setup {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+        /// Delete and recreate an existing environment
+        #[arg(long)]
+        recreate: bool,
+    },
+}
+
+/// Parse a `NAME=VALUE` template variable
+fn parse_var(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{}'", s)),
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Show where configuration is read from
+    Show {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+        /// Print every key's merged value and the layer it came from
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Print the effective value of a key, e.g. agent.model
+    Get {
+        key: String,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Set a key in the user config (or the project's qernel.yaml with --project)
+    Set {
+        key: String,
+        /// New value; secrets.* keys prompt for it when omitted
+        value: Option<String>,
+        /// Write to .qernel/qernel.yaml instead of the user config
+        #[arg(long)]
+        project: bool,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Remove a key from the user config (or the project's qernel.yaml with --project)
+    Unset {
+        key: String,
+        /// Remove from .qernel/qernel.yaml instead of the user config
+        #[arg(long)]
+        project: bool,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Check .qernel/qernel.yaml for unknown keys and invalid values, with their line and column
+    Validate {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Print the JSON Schema of qernel.yaml
+    Schema,
+    /// Print where the user config, caches and project config are read from
+    Path {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Use a profile from the user config's `profiles` by default, creating it if needed
+    UseProfile {
+        #[arg(required_unless_present = "none", conflicts_with = "none")]
+        name: Option<String>,
+        /// Stop using a profile and go back to the top-level settings
+        #[arg(long)]
+        none: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PapersCommand {
+    /// Add a paper URL (e.g. an arXiv link), DOI, title or local PDF and ingest it
+    Add {
+        /// URL, DOI, PDF path relative to the project, or title with --title
+        source: String,
+        /// Look the paper up by title on Semantic Scholar / Crossref
+        #[arg(long)]
+        title: bool,
+        /// Only add it to qernel.yaml; ingest later with `qernel papers reparse`
+        #[arg(long)]
+        no_parse: bool,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// List the papers in qernel.yaml and whether they are downloaded and parsed
+    List {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Remove a paper and its downloaded and parsed files
+    Remove {
+        /// Number from `qernel papers list`, or the paper's URL/path/DOI/title
+        paper: String,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Parse a paper (default: all papers) again, bypassing the parse cache
+    Reparse {
+        /// Number from `qernel papers list`, or the paper's URL/path
+        paper: Option<String>,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum LogsCommand {
+    /// List recorded runs
+    List {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Pretty-print a run log (default: the latest run)
+    Show {
+        /// Run name such as run-20250101-120000, or a path to a .jsonl file
+        run: Option<String>,
+        /// Only show events in this category (ai, exec, patch, ...)
+        #[arg(long)]
+        category: Option<String>,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Print the last events of a run and optionally follow new ones
+    Tail {
+        /// Run name such as run-20250101-120000, or a path to a .jsonl file
+        run: Option<String>,
+        /// Number of events to print
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+        /// Keep printing events as they are appended
+        #[arg(short, long)]
+        follow: bool,
+        /// Only show events in this category (ai, exec, patch, ...)
+        #[arg(long)]
+        category: Option<String>,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
     },
 }
 
+#[derive(Subcommand)]
+enum AuditCommand {
+    /// Print every entry: model requests, tool calls, patches, shell commands and exec results
+    Show {
+        /// Only show entries of this kind (model_request, tool_call, patch, shell, exec, ...)
+        #[arg(long)]
+        kind: Option<String>,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Check that no entry was modified, removed or reordered
+    Verify {
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BundleCommand {
+    /// Write spec, config, papers manifest, prompts, turn diffs, audit log and source to a .qernelrun file
+    Create {
+        /// Bundle to write (default: <project>-<timestamp>.qernelrun)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+    /// Recreate the project from a bundle by re-applying its turn diffs in order
+    Replay {
+        /// The .qernelrun file
+        bundle: String,
+        /// Directory to create (default: <project>-replay)
+        #[arg(long)]
+        into: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ZooCommand {
+    /// Search the Zoo catalog of quantum algorithms
+    Search {
+        /// Words to match against names, summaries and tags
+        query: String,
+        /// Maximum number of entries to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Zoo API base URL (default: default_server, then the hosted Zoo)
+        #[arg(long)]
+        server: Option<String>,
+    },
+    /// Scaffold a project for a catalog entry, with its papers and benchmark configured
+    Init {
+        /// Entry id from `qernel zoo search`
+        id: String,
+        /// Directory to create (default: the entry id)
+        path: Option<String>,
+        /// Zoo API base URL (default: default_server, then the hosted Zoo)
+        #[arg(long)]
+        server: Option<String>,
+    },
+    /// Download a Zoo benchmark into .qernel/benchmarks/<id> and use it as the test command and metrics
+    Bench {
+        /// Benchmark id from the Zoo
+        id: String,
+        /// Run the benchmark now and report the scores to the Zoo leaderboard
+        #[arg(long)]
+        submit: bool,
+        /// Zoo API base URL (default: default_server, then the hosted Zoo)
+        #[arg(long)]
+        server: Option<String>,
+        /// Project directory
+        #[arg(long, default_value = ".")]
+        cwd: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show the size of each cache category (explain, papers, parsed, images)
+    Stats,
+    /// Remove cache entries not used within a given age
+    Prune {
+        /// Age such as 30d, 12h or 2w
+        #[arg(long, value_name = "AGE")]
+        older_than: String,
+        /// Only prune one category: explain | papers | parsed | images
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// Delete all cached data (or a single category)
+    Clear {
+        /// Only clear one category: explain | papers | parsed | images
+        #[arg(long)]
+        category: Option<String>,
+    },
+}
+
+/// `qernel help`: a guide, the long help of the command with that name, or man pages
+fn help(topic: Option<String>, no_pager: bool, man: Option<std::path::PathBuf>) -> Result<()> {
+    if let Some(dir) = man {
+        return write_man_pages(&dir);
+    }
+    let mut cli = Cli::command();
+    // Built so subcommand usage reads `qernel <command>`
+    cli.build();
+    if let Some(name) = &topic
+        && let Some(command) = cli.find_subcommand_mut(name)
+    {
+        command.print_long_help()?;
+        return Ok(());
+    }
+    cmd::help::handle_help(topic, !no_pager)
+}
+
+#[cfg(feature = "man")]
+fn write_man_pages(dir: &std::path::Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    clap_mangen::generate_to(Cli::command(), dir)?;
+    println!("Man pages written to {}", dir.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "man"))]
+fn write_man_pages(_dir: &std::path::Path) -> Result<()> {
+    anyhow::bail!("this qernel was built without man pages; reinstall with `cargo install --path . --features man`")
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    match cli.command {
-        Commands::New { path, template } => cmd::new::handle_new(path, template),
-        Commands::Auth { set_openai_key, unset_openai_key } => cmd::login::handle_auth_with_flags(set_openai_key, unset_openai_key),
-        Commands::Push { remote, url, branch, no_commit } => cmd::push::handle_push(remote, url, branch, no_commit),
+    util::set_color_policy(cli.color);
+    util::set_output_level(if cli.quiet {
+        util::OutputLevel::Quiet
+    } else if cli.verbose {
+        util::OutputLevel::Verbose
+    } else {
+        util::OutputLevel::Normal
+    });
+    if let Some(dir) = cli.config_dir.clone() {
+        util::set_config_home(dir);
+    }
+    if let Some(profile) = cli.profile.clone() {
+        util::set_profile(profile);
+    }
+    // Held until main returns so the trace is flushed
+    let _trace_guard = match &cli.trace_file {
+        Some(path) => Some(cmd::common::trace::init_trace_file(path)?),
+        None => None,
+    };
+    let result = match cli.command {
+        Commands::New { path, template, list_templates, no_venv, vars, no_hooks, trust } => {
+            if list_templates { cmd::new::list_templates() } else { cmd::new::handle_new(path.unwrap_or_default(), template, no_venv, vars, no_hooks, trust) }
+        }
+        Commands::Env { command } => match command {
+            EnvCommand::Setup { cwd, recreate } => cmd::env::handle_env_setup(cwd, recreate),
+        },
+        Commands::Cache { command } => match command {
+            CacheCommand::Stats => cmd::cache::handle_cache_stats(),
+            CacheCommand::Prune { older_than, category } => cmd::cache::handle_cache_prune(older_than, category),
+            CacheCommand::Clear { category } => cmd::cache::handle_cache_clear(category),
+        },
+        Commands::Config { command } => match command {
+            ConfigCommand::Show { cwd, effective } => cmd::config::handle_config_show(cwd, effective),
+            ConfigCommand::Get { key, cwd } => cmd::config::handle_config_get(cwd, key),
+            ConfigCommand::Set { key, value, project, cwd } => cmd::config::handle_config_set(cwd, key, value, project),
+            ConfigCommand::Unset { key, project, cwd } => cmd::config::handle_config_unset(cwd, key, project),
+            ConfigCommand::Validate { cwd } => cmd::config::handle_config_validate(cwd),
+            ConfigCommand::Schema => cmd::config::handle_config_schema(),
+            ConfigCommand::Path { cwd } => cmd::config::handle_config_path(cwd),
+            ConfigCommand::UseProfile { name, .. } => cmd::config::handle_config_use_profile(name),
+        },
+        Commands::Provider { set, base_url, api_version, deployments, models } => {
+            cmd::provider::handle_provider(set, base_url, api_version, deployments, models)
+        }
+        Commands::Auth { set_openai_key, unset_openai_key, set_key, unset_key } => {
+            cmd::login::handle_auth_with_flags(set_openai_key, unset_openai_key, set_key, unset_key)
+        }
+        Commands::Push { remote, url, branch, no_commit, artifacts, server, allow_secrets } => cmd::push::handle_push(remote, url, branch, no_commit, artifacts, server, allow_secrets),
         Commands::Pull { repo, dest, branch, server } => cmd::pull::handle_pull(repo, dest, branch, server),
-        Commands::Prototype { cwd, model, max_iters, debug, spec_only, spec_and_content_only, arxiv } => {
-            if let Some(url) = arxiv { cmd::prototype::quickstart_arxiv(url, model, max_iters, debug) } else { cmd::prototype::handle_prototype(cwd, model, max_iters, debug, spec_only, spec_and_content_only) }
+        Commands::Prototype { cwd, model, max_iters, debug, spec_only, spec_and_content_only, force_reparse, arxiv, no_plan, report, resume, yes, output_json, api_key_env, approval, stream_tests, all, jobs, create_pr, remote } => {
+            if all {
+                let options = cmd::prototype::PrototypeOptions { cwd: std::path::PathBuf::from(&cwd), model, max_iters, debug, spec_only, spec_and_content_only, force_reparse, plan_first: !no_plan, report, resume, yes, api_key_env, approval, stream_tests };
+                cmd::workspace::handle_prototype_all(cwd, jobs, options, output_json)
+            } else {
+                let pr_remote = create_pr.then_some(remote);
+                if let Some(url) = arxiv { cmd::prototype::quickstart_arxiv(url, model, max_iters, debug, !no_plan, report, yes, output_json, api_key_env, approval, stream_tests, pr_remote) } else { cmd::prototype::handle_prototype(cwd, model, max_iters, debug, spec_only, spec_and_content_only, force_reparse, !no_plan, report, resume, yes, output_json, api_key_env, approval, stream_tests, pr_remote) }
+            }
+        }
+        Commands::Ingest { papers, content, force, cwd } => cmd::ingest::handle_ingest(cwd, papers, content, force),
+        Commands::Papers { command } => match command {
+            PapersCommand::Add { source, title, no_parse, cwd } => cmd::papers::handle_papers_add(cwd, source, title, no_parse),
+            PapersCommand::List { cwd } => cmd::papers::handle_papers_list(cwd),
+            PapersCommand::Remove { paper, cwd } => cmd::papers::handle_papers_remove(cwd, paper),
+            PapersCommand::Reparse { paper, cwd } => cmd::papers::handle_papers_reparse(cwd, paper),
+        },
+        Commands::See { cwd, no_pager } => cmd::see::handle_see(cwd, no_pager),
+        Commands::Status { cwd } => cmd::status::handle_status(cwd),
+        Commands::Zoo { command } => match command {
+            ZooCommand::Search { query, limit, server } => cmd::zoo::catalog::handle_zoo_search(query, limit, server),
+            ZooCommand::Init { id, path, server } => cmd::zoo::catalog::handle_zoo_init(id, path, server),
+            ZooCommand::Bench { id, submit, server, cwd } => cmd::zoo::bench::handle_zoo_bench(cwd, id, server, submit),
+        },
+        Commands::Logs { command } => match command {
+            LogsCommand::List { cwd } => cmd::logs::handle_logs_list(cwd),
+            LogsCommand::Show { run, category, cwd } => cmd::logs::handle_logs_show(cwd, run, category),
+            LogsCommand::Tail { run, lines, follow, category, cwd } => cmd::logs::handle_logs_tail(cwd, run, lines, follow, category),
+        },
+        Commands::Audit { command } => match command {
+            AuditCommand::Show { kind, cwd } => cmd::audit::handle_audit_show(cwd, kind),
+            AuditCommand::Verify { cwd } => cmd::audit::handle_audit_verify(cwd),
+        },
+        Commands::Bundle { command } => match command {
+            BundleCommand::Create { output, cwd } => cmd::bundle::handle_bundle_create(cwd, output),
+            BundleCommand::Replay { bundle, into } => cmd::bundle::handle_bundle_replay(bundle, into),
+        },
+        Commands::Migrate { cwd, dry_run } => cmd::migrate::handle_migrate(cwd, dry_run),
+        Commands::Sweep { cwd, parallelism, dry_run } => cmd::sweep::handle_sweep(cwd, parallelism, dry_run),
+        Commands::Diff { cwd, turn, html } => cmd::diff::handle_diff(cwd, turn, html),
+        Commands::Explain { files, per, model, models, markdown, output, no_pager, max_chars, api_key_env, since } => {
+            cmd::explain::handle_explain(files, per, model, models, markdown, output, !no_pager, max_chars, api_key_env, since)
         }
-        Commands::Explain { files, per, model, markdown, output, no_pager, max_chars } => {
-            cmd::explain::handle_explain(files, per, model, markdown, output, !no_pager, max_chars)
+        Commands::Help { topic, no_pager, man } => help(topic, no_pager, man),
+    };
+    if let Err(e) = &result {
+        eprintln!("Error: {:?}", e);
+        if let Some(hint) = QernelError::find(e).and_then(QernelError::hint) {
+            eprintln!("\nHint: {}", hint);
         }
+        // Runs that end at max iterations or over budget get their own exit codes, so CI can tell them apart
+        let code = e.downcast_ref::<cmd::prototype::agent::RunFailure>().map_or(1, |f| f.exit_code());
+        drop(_trace_guard);
+        std::process::exit(code);
     }
+    Ok(())
 }
\ No newline at end of file